@@ -0,0 +1,150 @@
+use std::{
+    error::Error,
+    net::IpAddr,
+    sync::atomic::{AtomicU32, AtomicU64, AtomicUsize, Ordering},
+};
+
+/// The `LocalAddressPool` struct holds a set of local source addresses that outbound connections
+/// rotate through, tracking failures per address so broken ones are skipped.
+///
+/// # Properties:
+///
+/// * `entries`: The addresses in the pool together with their failure/success counters.
+/// * `next`: The round-robin cursor used to pick the next address.
+/// * `max_failures`: The number of consecutive failures after which an address is skipped while
+///   healthier addresses are available.
+pub struct LocalAddressPool {
+    entries: Vec<PoolEntry>,
+    next: AtomicUsize,
+    max_failures: u32,
+}
+
+struct PoolEntry {
+    addr: IpAddr,
+    consecutive_failures: AtomicU32,
+    total_failures: AtomicU64,
+    successes: AtomicU64,
+}
+
+/// The `AddressStats` struct is a snapshot of the counters kept for one pool address.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AddressStats {
+    pub addr: IpAddr,
+    pub consecutive_failures: u32,
+    pub total_failures: u64,
+    pub successes: u64,
+}
+
+impl LocalAddressPool {
+    /// The function `new` creates a pool from a list of local IP addresses.
+    ///
+    /// # Arguments:
+    ///
+    /// * `addrs`: The local addresses to rotate through. Each must be assigned to an interface on
+    ///   this host, otherwise binding to it fails and is counted as a failure.
+    ///
+    /// # Returns:
+    ///
+    /// The `new` function returns a `Result` containing the pool, or an error if `addrs` is empty.
+    pub fn new(addrs: impl IntoIterator<Item = IpAddr>) -> Result<Self, Box<dyn Error>> {
+        let entries: Vec<PoolEntry> = addrs
+            .into_iter()
+            .map(|addr| PoolEntry {
+                addr,
+                consecutive_failures: AtomicU32::new(0),
+                total_failures: AtomicU64::new(0),
+                successes: AtomicU64::new(0),
+            })
+            .collect();
+        if entries.is_empty() {
+            return Err("local address pool must contain at least one address".into());
+        }
+        Ok(LocalAddressPool { entries, next: AtomicUsize::new(0), max_failures: 3 })
+    }
+
+    /// The function `max_consecutive_failures` sets after how many consecutive failures an address
+    /// is skipped. When every address is over the limit, the one with the fewest failures is used.
+    pub fn max_consecutive_failures(mut self, failures: u32) -> Self {
+        self.max_failures = failures.max(1);
+        self
+    }
+
+    /// The function `addrs` returns every address in the pool, in rotation order.
+    pub fn addrs(&self) -> Vec<IpAddr> {
+        self.entries.iter().map(|e| e.addr).collect()
+    }
+
+    /// The function `next` picks the next healthy address in round-robin order.
+    pub fn next(&self) -> IpAddr {
+        let len = self.entries.len();
+        let start = self.next.fetch_add(1, Ordering::Relaxed);
+        for offset in 0..len {
+            let entry = &self.entries[(start + offset) % len];
+            if entry.consecutive_failures.load(Ordering::Relaxed) < self.max_failures {
+                return entry.addr;
+            }
+        }
+        self.entries
+            .iter()
+            .min_by_key(|e| e.consecutive_failures.load(Ordering::Relaxed))
+            .map(|e| e.addr)
+            .unwrap_or(self.entries[start % len].addr)
+    }
+
+    /// The function `record_success` resets the consecutive failure count of `addr`.
+    pub fn record_success(&self, addr: IpAddr) {
+        if let Some(entry) = self.entry(addr) {
+            entry.consecutive_failures.store(0, Ordering::Relaxed);
+            entry.successes.fetch_add(1, Ordering::Relaxed);
+        }
+    }
+
+    /// The function `record_failure` counts a failed connection made from `addr`.
+    pub fn record_failure(&self, addr: IpAddr) {
+        if let Some(entry) = self.entry(addr) {
+            entry.consecutive_failures.fetch_add(1, Ordering::Relaxed);
+            entry.total_failures.fetch_add(1, Ordering::Relaxed);
+        }
+    }
+
+    /// The function `stats` returns a snapshot of the counters for every address.
+    pub fn stats(&self) -> Vec<AddressStats> {
+        self.entries
+            .iter()
+            .map(|e| AddressStats {
+                addr: e.addr,
+                consecutive_failures: e.consecutive_failures.load(Ordering::Relaxed),
+                total_failures: e.total_failures.load(Ordering::Relaxed),
+                successes: e.successes.load(Ordering::Relaxed),
+            })
+            .collect()
+    }
+
+    fn entry(&self, addr: IpAddr) -> Option<&PoolEntry> {
+        self.entries.iter().find(|e| e.addr == addr)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_rotation_skips_failing_addresses() {
+        let a: IpAddr = "127.0.0.1".parse().unwrap();
+        let b: IpAddr = "127.0.0.2".parse().unwrap();
+        let pool = LocalAddressPool::new([a, b]).unwrap().max_consecutive_failures(2);
+
+        assert_eq!(pool.next(), a);
+        assert_eq!(pool.next(), b);
+
+        pool.record_failure(a);
+        pool.record_failure(a);
+        assert_eq!(pool.next(), b);
+        assert_eq!(pool.next(), b);
+
+        pool.record_success(a);
+        assert_eq!(pool.stats()[0].consecutive_failures, 0);
+        assert_eq!(pool.stats()[0].total_failures, 2);
+    }
+}
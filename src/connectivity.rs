@@ -0,0 +1,247 @@
+use crate::http::ConnectivityMonitor;
+use reqwest::{header::LOCATION, redirect::Policy, Client, StatusCode};
+use std::{
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+use tokio::{
+    net::{lookup_host, TcpStream},
+    sync::watch,
+    task::JoinHandle,
+    time::timeout,
+};
+
+/// The `ConnectivityState` enum is the network reachability reported by a `NetworkMonitor`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ConnectivityState {
+    /// At least one probe reached the network.
+    Online,
+    /// No probe succeeded.
+    Offline,
+    /// The network is reachable but an HTTP probe got an unexpected redirect or content, which usually
+    /// means a captive portal intercepts the traffic. `NetworkMonitor::captive_portal` has the details.
+    Captive,
+}
+
+/// The `Probe` enum describes one reachability check run by a `NetworkMonitor`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Probe {
+    /// Resolve the given host name.
+    Dns(String),
+    /// Open a TCP connection to the given `host:port`.
+    Tcp(String),
+    /// Send a `GET` to the given URL and expect `204 No Content`, without following redirects.
+    Http204(String),
+    /// Send a `GET` to the given URL and expect a `200 OK` whose body contains the given text, like
+    /// the `Success` page served by well-known hotspot detection URLs.
+    HttpContent { url: String, expected: String },
+}
+
+/// The `CaptivePortal` struct describes how a known probe URL was intercepted, so applications can
+/// send the user to the portal's sign-in page instead of failing with an unexplained network error.
+///
+/// # Properties:
+///
+/// * `probe_url`: The probe URL that was intercepted.
+/// * `status`: The status code returned instead of the expected one.
+/// * `redirect`: The `Location` the portal redirected to, which is usually its sign-in page.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CaptivePortal {
+    pub probe_url: String,
+    pub status: StatusCode,
+    pub redirect: Option<String>,
+}
+
+/// The outcome of a single probe.
+enum ProbeResult {
+    Reachable,
+    Intercepted(CaptivePortal),
+    Failed,
+}
+
+/// The `NetworkMonitor` struct runs a set of probes periodically and publishes the resulting
+/// `ConnectivityState` on a `tokio::sync::watch` channel.
+///
+/// # Properties:
+///
+/// * `probes`: The checks run on every round.
+/// * `interval`: The delay between two rounds when running in the background.
+/// * `timeout`: The time each probe is given before it counts as failed.
+/// * `client`: The HTTP client used by `Http204` probes; it never follows redirects so captive
+///   portals are noticed.
+/// * `state`: The sending half of the state channel.
+/// * `portal`: The captive portal found by the latest check, if any.
+#[derive(Debug)]
+pub struct NetworkMonitor {
+    probes: Vec<Probe>,
+    interval: Duration,
+    timeout: Duration,
+    client: Client,
+    state: watch::Sender<ConnectivityState>,
+    portal: Mutex<Option<CaptivePortal>>,
+}
+
+impl NetworkMonitor {
+    /// The function `new` creates a monitor for the given probes. Until the first check completes the
+    /// state is `Online`, so callers are not held back while the monitor starts up.
+    ///
+    /// # Arguments:
+    ///
+    /// * `probes`: The reachability checks to run. The state is `Online` when any of them succeeds.
+    pub fn new(probes: Vec<Probe>) -> Self {
+        let client = Client::builder().redirect(Policy::none()).build().unwrap_or_default();
+        let (state, _) = watch::channel(ConnectivityState::Online);
+        NetworkMonitor {
+            probes,
+            interval: Duration::from_secs(30),
+            timeout: Duration::from_secs(5),
+            client,
+            state,
+            portal: Mutex::new(None),
+        }
+    }
+
+    /// The function `interval` sets the delay between two rounds of probes (30 seconds by default).
+    pub fn interval(mut self, interval: Duration) -> Self {
+        self.interval = interval;
+        self
+    }
+
+    /// The function `timeout` sets how long each probe may take (5 seconds by default).
+    pub fn timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    /// The function `state` returns the most recently published state.
+    pub fn state(&self) -> ConnectivityState {
+        *self.state.borrow()
+    }
+
+    /// The function `captive_portal` returns the portal found by the latest check while the state is
+    /// `Captive`, and `None` otherwise.
+    pub fn captive_portal(&self) -> Option<CaptivePortal> {
+        self.portal.lock().unwrap().clone()
+    }
+
+    /// The function `subscribe` returns a receiver that is notified every time the state changes.
+    pub fn subscribe(&self) -> watch::Receiver<ConnectivityState> {
+        self.state.subscribe()
+    }
+
+    /// The function `check` runs every probe once, publishes the resulting state and returns it.
+    ///
+    /// # Returns:
+    ///
+    /// The `check` function returns `Online` if any probe succeeded, otherwise `Captive` if an HTTP
+    /// probe was answered with an unexpected response, otherwise `Offline`.
+    pub async fn check(&self) -> ConnectivityState {
+        let results = futures_util::future::join_all(self.probes.iter().map(|probe| self.run(probe))).await;
+        let portal = results.into_iter().try_fold(None, |portal, result| match result {
+            ProbeResult::Reachable => Err(()),
+            ProbeResult::Intercepted(found) => Ok(portal.or(Some(found))),
+            ProbeResult::Failed => Ok(portal),
+        });
+        let state = match &portal {
+            Err(()) => ConnectivityState::Online,
+            Ok(Some(_)) => ConnectivityState::Captive,
+            Ok(None) => ConnectivityState::Offline,
+        };
+        *self.portal.lock().unwrap() = portal.ok().flatten();
+        self.state.send_if_modified(|current| std::mem::replace(current, state) != state);
+        state
+    }
+
+    /// The function `spawn` runs `check` every `interval` on a background task until the returned
+    /// handle is aborted.
+    pub fn spawn(self: Arc<Self>) -> JoinHandle<()> {
+        tokio::spawn(async move {
+            loop {
+                self.check().await;
+                tokio::time::sleep(self.interval).await;
+            }
+        })
+    }
+
+    async fn run(&self, probe: &Probe) -> ProbeResult {
+        let reachable = match probe {
+            Probe::Dns(host) => {
+                let lookup = lookup_host(format!("{}:0", host));
+                match timeout(self.timeout, lookup).await {
+                    Ok(Ok(mut addrs)) => addrs.next().is_some(),
+                    _ => false,
+                }
+            }
+            Probe::Tcp(addr) => matches!(timeout(self.timeout, TcpStream::connect(addr.as_str())).await, Ok(Ok(_))),
+            Probe::Http204(url) => return self.http_probe(url, StatusCode::NO_CONTENT, None).await,
+            Probe::HttpContent { url, expected } => return self.http_probe(url, StatusCode::OK, Some(expected)).await,
+        };
+        if reachable {
+            ProbeResult::Reachable
+        } else {
+            ProbeResult::Failed
+        }
+    }
+}
+
+impl NetworkMonitor {
+    /// The function `http_probe` fetches `url` and compares the answer with the expected status and
+    /// body. Anything else means the request was intercepted on its way, typically by a captive
+    /// portal.
+    async fn http_probe(&self, url: &str, expected_status: StatusCode, expected_body: Option<&str>) -> ProbeResult {
+        let response = match self.client.get(url).timeout(self.timeout).send().await {
+            Ok(response) => response,
+            Err(_) => return ProbeResult::Failed,
+        };
+        let status = response.status();
+        let redirect = response.headers().get(LOCATION).and_then(|v| v.to_str().ok()).map(str::to_string);
+        let matches = status == expected_status
+            && match expected_body {
+                Some(expected) => match timeout(self.timeout, response.text()).await {
+                    Ok(Ok(body)) => body.contains(expected),
+                    _ => return ProbeResult::Failed,
+                },
+                None => true,
+            };
+        if matches {
+            ProbeResult::Reachable
+        } else {
+            ProbeResult::Intercepted(CaptivePortal { probe_url: url.to_string(), status, redirect })
+        }
+    }
+}
+
+impl ConnectivityMonitor for NetworkMonitor {
+    fn is_online(&self) -> bool {
+        self.state() == ConnectivityState::Online
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::http::testing::serve;
+
+    #[tokio::test]
+    async fn test_connectivity_states() {
+        let (addr, server_task) = serve(vec![
+            "HTTP/1.1 204 No Content\r\nconnection: close\r\n\r\n",
+            "HTTP/1.1 302 Found\r\nlocation: http://portal.local/\r\ncontent-length: 0\r\nconnection: close\r\n\r\n",
+        ])
+        .await;
+        let monitor = NetworkMonitor::new(vec![Probe::Http204(format!("http://{}/generate_204", addr))]);
+        let mut changes = monitor.subscribe();
+
+        assert_eq!(monitor.check().await, ConnectivityState::Online);
+        assert_eq!(monitor.check().await, ConnectivityState::Captive);
+        assert!(changes.has_changed().unwrap());
+        assert_eq!(*changes.borrow_and_update(), ConnectivityState::Captive);
+        let portal = monitor.captive_portal().unwrap();
+        assert_eq!(portal.status, StatusCode::FOUND);
+        assert_eq!(portal.redirect.as_deref(), Some("http://portal.local/"));
+        assert_eq!(monitor.check().await, ConnectivityState::Offline);
+        assert!(!monitor.is_online());
+        assert!(monitor.captive_portal().is_none());
+        server_task.await.unwrap();
+    }
+}
@@ -0,0 +1,410 @@
+use crate::http::BoxError;
+#[cfg(not(target_arch = "wasm32"))]
+use crate::{
+    http::verbose,
+    timeline::{Timeline, CURRENT},
+};
+#[cfg(not(target_arch = "wasm32"))]
+use reqwest::dns::{Addrs, Name, Resolve, Resolving};
+use reqwest::Url;
+#[cfg(not(target_arch = "wasm32"))]
+use std::sync::Arc;
+use std::{
+    error::Error,
+    fmt,
+    future::Future,
+    net::{IpAddr, SocketAddr},
+};
+
+#[cfg(not(target_arch = "wasm32"))]
+tokio::task_local! {
+    /// The host of the request being sent and the checked addresses it was resolved to.
+    static PINNED: (String, Vec<SocketAddr>);
+}
+
+/// A CIDR block such as `10.0.0.0/8`; a bare address is a block of one.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct Network {
+    addr: IpAddr,
+    prefix: u8,
+}
+
+impl Network {
+    pub(crate) fn parse(cidr: &str) -> Option<Self> {
+        let (addr, prefix) = match cidr.split_once('/') {
+            Some((addr, prefix)) => (addr.parse::<IpAddr>().ok()?, Some(prefix.parse::<u8>().ok()?)),
+            None => (cidr.parse::<IpAddr>().ok()?, None),
+        };
+        let bits = if addr.is_ipv4() { 32 } else { 128 };
+        let prefix = prefix.unwrap_or(bits);
+        (prefix <= bits).then_some(Network { addr: addr.to_canonical(), prefix })
+    }
+
+    pub(crate) fn contains(&self, ip: IpAddr) -> bool {
+        let (network, ip, bits) = match (self.addr, ip.to_canonical()) {
+            (IpAddr::V4(network), IpAddr::V4(ip)) => (u32::from(network) as u128, u32::from(ip) as u128, 32),
+            (IpAddr::V6(network), IpAddr::V6(ip)) => (u128::from(network), u128::from(ip), 128),
+            _ => return false,
+        };
+        self.prefix == 0 || network >> (bits - self.prefix) == ip >> (bits - self.prefix)
+    }
+}
+
+/// The function `is_private` returns whether `ip` belongs to a range that is not reachable on the
+/// public internet: loopback, private, link-local (including the cloud metadata address
+/// `169.254.169.254`), carrier-grade NAT, unique local, unspecified, broadcast and multicast.
+fn is_private(ip: IpAddr) -> bool {
+    match ip.to_canonical() {
+        IpAddr::V4(ip) => {
+            let [first, second, ..] = ip.octets();
+            ip.is_private()
+                || ip.is_loopback()
+                || ip.is_link_local()
+                || ip.is_unspecified()
+                || ip.is_broadcast()
+                || ip.is_multicast()
+                || first == 0
+                || (first == 100 && second & 0xc0 == 64)
+        }
+        IpAddr::V6(ip) => {
+            let first = ip.segments()[0];
+            let (unique_local, link_local) = (first & 0xfe00 == 0xfc00, first & 0xffc0 == 0xfe80);
+            ip.is_loopback() || ip.is_unspecified() || ip.is_multicast() || unique_local || link_local
+        }
+    }
+}
+
+/// The `EgressPolicy` struct restricts the destinations a client may connect to, so that URLs or
+/// addresses supplied by users cannot reach internal services (server-side request forgery).
+///
+/// A new policy allows every destination. Once a host or a CIDR block is allowed, only destinations
+/// matching one of them are; once a port is allowed, only the allowed ports are. With
+/// `deny_private_ranges`, addresses in private ranges are refused unless an allowed CIDR block
+/// contains them. Host names are checked after resolution, and every resolved address must pass.
+///
+/// # Properties:
+///
+/// * `hosts`: The allowed host names; `*.example.com` allows every subdomain of `example.com`.
+/// * `networks`: The allowed CIDR blocks.
+/// * `ports`: The allowed ports.
+/// * `deny_private`: Whether addresses in private ranges are refused.
+#[derive(Debug, Clone, Default)]
+pub struct EgressPolicy {
+    hosts: Vec<String>,
+    networks: Vec<Network>,
+    ports: Vec<u16>,
+    deny_private: bool,
+}
+
+impl EgressPolicy {
+    /// The function `new` creates a policy allowing every destination.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The function `allow_host` allows connections to `pattern`, either an exact host name or
+    /// `*.example.com` for every subdomain of `example.com`.
+    pub fn allow_host(mut self, pattern: &str) -> Self {
+        self.hosts.push(normalize(pattern));
+        self
+    }
+
+    /// The function `allow_cidr` allows connections to the addresses of `cidr`, such as
+    /// `203.0.113.0/24` or a single address. Allowed blocks take precedence over
+    /// `deny_private_ranges`, so internal services can be allowed selectively.
+    ///
+    /// # Returns:
+    ///
+    /// The `allow_cidr` function returns a `Result` containing the policy, or an error if `cidr` is
+    /// not a valid address or CIDR block.
+    pub fn allow_cidr(mut self, cidr: &str) -> Result<Self, Box<dyn Error>> {
+        let network = Network::parse(cidr).ok_or_else(|| format!("invalid CIDR block: {}", cidr))?;
+        self.networks.push(network);
+        Ok(self)
+    }
+
+    /// The function `allow_port` allows connections to `port`. Without allowed ports every port is
+    /// allowed.
+    pub fn allow_port(mut self, port: u16) -> Self {
+        self.ports.push(port);
+        self
+    }
+
+    /// The function `deny_private_ranges` toggles refusing loopback, private, link-local and other
+    /// non-public addresses.
+    pub fn deny_private_ranges(mut self, deny: bool) -> Self {
+        self.deny_private = deny;
+        self
+    }
+
+    /// The function `check_host` checks what can be known about a destination before resolving it:
+    /// the port, the name, and the address itself when `host` is an IP address.
+    ///
+    /// # Returns:
+    ///
+    /// The `check_host` function returns a `PolicyViolation` if the destination is refused. A host
+    /// name that passes may still be refused once resolved, by `check_addr`.
+    pub fn check_host(&self, host: &str, port: u16) -> Result<(), PolicyViolation> {
+        if let Ok(ip) = host.trim_start_matches('[').trim_end_matches(']').parse::<IpAddr>() {
+            return self.check_addr(host, SocketAddr::new(ip, port));
+        }
+        if !self.ports.is_empty() && !self.ports.contains(&port) {
+            return Err(PolicyViolation::new(host, port, None, PolicyViolationKind::PortNotAllowed));
+        }
+        if self.networks.is_empty() && !self.hosts.is_empty() && !self.host_allowed(host) {
+            return Err(PolicyViolation::new(host, port, None, PolicyViolationKind::HostNotAllowed));
+        }
+        Ok(())
+    }
+
+    /// The function `check_addr` checks a resolved address `addr` of `host`.
+    ///
+    /// # Returns:
+    ///
+    /// The `check_addr` function returns a `PolicyViolation` if connecting to `addr` is refused.
+    pub fn check_addr(&self, host: &str, addr: SocketAddr) -> Result<(), PolicyViolation> {
+        let kind = match !self.ports.is_empty() && !self.ports.contains(&addr.port()) {
+            true => Some(PolicyViolationKind::PortNotAllowed),
+            false => self.refuse_ip(host, addr.ip()),
+        };
+        match kind {
+            Some(kind) => Err(PolicyViolation::new(host, addr.port(), Some(addr.ip()), kind)),
+            None => Ok(()),
+        }
+    }
+
+    /// The function `refuse_ip` returns why connecting to `ip` for `host` is refused, regardless of
+    /// the port, or `None` if it is allowed.
+    fn refuse_ip(&self, host: &str, ip: IpAddr) -> Option<PolicyViolationKind> {
+        if self.networks.iter().any(|network| network.contains(ip)) {
+            return None;
+        }
+        if self.deny_private && is_private(ip) {
+            return Some(PolicyViolationKind::PrivateAddress);
+        }
+        match (self.hosts.is_empty() && self.networks.is_empty()) || self.host_allowed(host) {
+            true => None,
+            false if self.networks.is_empty() => Some(PolicyViolationKind::HostNotAllowed),
+            false => Some(PolicyViolationKind::AddressNotAllowed),
+        }
+    }
+
+    fn host_allowed(&self, host: &str) -> bool {
+        let host = normalize(host);
+        self.hosts.iter().any(|pattern| match pattern.strip_prefix("*.") {
+            Some(domain) => host.strip_suffix(domain).is_some_and(|sub| sub.len() > 1 && sub.ends_with('.')),
+            None => *pattern == host,
+        })
+    }
+
+    /// The function `check_url` checks the host and port of `url` with `check_host`.
+    ///
+    /// # Returns:
+    ///
+    /// The `check_url` function returns the host and port to resolve, or a `PolicyViolation` if the
+    /// destination is refused or `url` has no host.
+    pub(crate) fn check_url(&self, url: &Url) -> Result<(String, u16), PolicyViolation> {
+        let port = url.port_or_known_default().unwrap_or(0);
+        let host = match url.host_str() {
+            Some(host) => host.trim_start_matches('[').trim_end_matches(']').to_string(),
+            None => return Err(PolicyViolation::new("", port, None, PolicyViolationKind::HostNotAllowed)),
+        };
+        self.check_host(&host, port)?;
+        Ok((host, port))
+    }
+
+    /// The function `resolve` checks `host`, resolves it and checks every resolved address.
+    ///
+    /// # Returns:
+    ///
+    /// The `resolve` function returns the addresses, or a `PolicyViolation` or lookup error.
+    #[cfg(not(target_arch = "wasm32"))]
+    pub(crate) async fn resolve(&self, host: &str, port: u16) -> Result<Vec<SocketAddr>, BoxError> {
+        self.check_host(host, port)?;
+        let addrs: Vec<SocketAddr> = tokio::net::lookup_host((host, port)).await?.collect();
+        for addr in &addrs {
+            self.check_addr(host, *addr)?;
+        }
+        Ok(addrs)
+    }
+
+    /// The function `scope` checks the destination of a request to `url` and runs `send` with the
+    /// checked addresses of its host pinned: the `PolicyResolver` of the client hands them out
+    /// instead of resolving the host again, so a DNS answer changing between the check and the
+    /// connection (DNS rebinding) cannot lead the request, its retries or same-host redirects to a
+    /// refused address. Browsers resolve host names themselves, so on `wasm32` only the name, the
+    /// port and literal addresses are checked.
+    pub(crate) async fn scope<T, F>(&self, url: &Url, send: F) -> Result<T, BoxError>
+    where
+        F: Future<Output = Result<T, BoxError>>,
+    {
+        let (host, port) = self.check_url(url)?;
+        #[cfg(not(target_arch = "wasm32"))]
+        if host.parse::<IpAddr>().is_err() {
+            let addrs = self.resolve(&host, port).await?;
+            return PINNED.scope((normalize(&host), addrs), send).await;
+        }
+        #[cfg(target_arch = "wasm32")]
+        let _ = (host, port);
+        send.await
+    }
+}
+
+/// The `PolicyResolver` struct is the resolver of an `HttpClient` with an `EgressPolicy`. It answers
+/// lookups of the host of the request being sent with the addresses pinned by `EgressPolicy::scope`,
+/// and checks the answer of every other lookup, such as the host of a redirect, before a connection
+/// is made. Lookups are recorded in the timeline of verbose requests.
+#[cfg(not(target_arch = "wasm32"))]
+pub(crate) struct PolicyResolver(pub(crate) Arc<EgressPolicy>);
+
+#[cfg(not(target_arch = "wasm32"))]
+impl Resolve for PolicyResolver {
+    fn resolve(&self, name: Name) -> Resolving {
+        let host = normalize(name.as_str());
+        let pinned = PINNED.try_with(|(pinned, addrs)| (*pinned == host).then(|| addrs.clone())).ok().flatten();
+        let (policy, timeline) = (self.0.clone(), CURRENT.try_with(Timeline::clone).ok());
+        Box::pin(async move {
+            let addrs = match pinned {
+                Some(addrs) => addrs,
+                None => {
+                    let addrs: Vec<SocketAddr> = tokio::net::lookup_host((host.as_str(), 0)).await?.collect();
+                    // The port is not known here; it was checked with the URL.
+                    let refused = addrs.iter().find_map(|a| Some((a.ip(), policy.refuse_ip(&host, a.ip())?)));
+                    if let Some((ip, kind)) = refused {
+                        return Err(Box::new(PolicyViolation::new(&host, 0, Some(ip), kind)) as BoxError);
+                    }
+                    addrs
+                }
+            };
+            verbose::record_lookup(timeline, &host, &addrs);
+            Ok(Box::new(addrs.into_iter()) as Addrs)
+        })
+    }
+}
+
+fn normalize(host: &str) -> String {
+    host.trim_end_matches('.').to_ascii_lowercase()
+}
+
+/// The `PolicyViolationKind` enum tells why an `EgressPolicy` refused a destination.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PolicyViolationKind {
+    HostNotAllowed,
+    AddressNotAllowed,
+    PortNotAllowed,
+    PrivateAddress,
+}
+
+/// The `PolicyViolation` is the cause of the `AarambhNetError` returned when a client configured
+/// with an `EgressPolicy` is asked to connect to a destination the policy refuses. Nothing is sent
+/// to such destinations.
+///
+/// # Properties:
+///
+/// * `host`: The host name or address that was refused.
+/// * `port`: The port of the destination, or 0 if a redirect target was refused when resolved.
+/// * `addr`: The resolved address that was refused, if the host passed the name checks.
+/// * `kind`: Why the destination was refused.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PolicyViolation {
+    pub host: String,
+    pub port: u16,
+    pub addr: Option<IpAddr>,
+    pub kind: PolicyViolationKind,
+}
+
+impl PolicyViolation {
+    fn new(host: &str, port: u16, addr: Option<IpAddr>, kind: PolicyViolationKind) -> Self {
+        PolicyViolation { host: host.to_string(), port, addr, kind }
+    }
+}
+
+impl fmt::Display for PolicyViolation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let reason = match self.kind {
+            PolicyViolationKind::HostNotAllowed => "host not allowed",
+            PolicyViolationKind::AddressNotAllowed => "address not allowed",
+            PolicyViolationKind::PortNotAllowed => "port not allowed",
+            PolicyViolationKind::PrivateAddress => "private address",
+        };
+        write!(f, "egress policy refused {}:{}", self.host, self.port)?;
+        if let Some(addr) = self.addr {
+            write!(f, " ({})", addr)?;
+        }
+        write!(f, ": {}", reason)
+    }
+}
+
+impl Error for PolicyViolation {}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{
+        error::AarambhNetError,
+        http::{testing::serve, HttpClient},
+        tcp::TcpClient,
+    };
+
+    #[tokio::test]
+    async fn test_policy_blocks_disallowed_destinations() {
+        let policy = EgressPolicy::new().allow_host("*.example.com").allow_port(443).deny_private_ranges(true);
+        let addr = |ip: &str| SocketAddr::new(ip.parse().unwrap(), 443);
+        assert!(policy.check_host("api.example.com", 443).is_ok());
+        assert_eq!(policy.check_host("example.com", 443).unwrap_err().kind, PolicyViolationKind::HostNotAllowed);
+        assert_eq!(policy.check_host("api.example.com", 80).unwrap_err().kind, PolicyViolationKind::PortNotAllowed);
+        assert!(policy.check_addr("api.example.com", addr("93.184.216.34")).is_ok());
+        let metadata = policy.check_addr("api.example.com", addr("169.254.169.254")).unwrap_err();
+        assert_eq!(metadata.kind, PolicyViolationKind::PrivateAddress);
+        assert_eq!(policy.check_host("::ffff:10.0.0.1", 443).unwrap_err().kind, PolicyViolationKind::PrivateAddress);
+        let internal = policy.clone().allow_cidr("10.1.0.0/16").unwrap();
+        assert!(internal.check_host("10.1.2.3", 443).is_ok());
+        assert_eq!(internal.check_host("10.2.0.1", 443).unwrap_err().kind, PolicyViolationKind::PrivateAddress);
+
+        // Refused requests fail before anything reaches the server.
+        let ok = "HTTP/1.1 200 OK\r\ncontent-length: 0\r\nconnection: close\r\n\r\n";
+        let redirect = "HTTP/1.1 302 Found\r\nlocation: http://10.0.0.1/\r\ncontent-length: 0\r\n\r\n";
+        let (server, server_task) = serve(vec![ok, redirect]).await;
+        let base = format!("http://{}", server);
+        let strict = EgressPolicy::new().deny_private_ranges(true);
+        let client = HttpClient::builder(&base).egress_policy(strict.clone()).build().unwrap();
+        let err = client.get("/", None).await.unwrap_err();
+        let err = err.downcast_ref::<AarambhNetError>().unwrap();
+        assert_eq!(err.cause::<PolicyViolation>().unwrap().addr, Some(server.ip()));
+        assert!(TcpClient::connect_with_policy(&server.to_string(), &strict).await.is_err());
+
+        let loopback = strict.allow_cidr("127.0.0.0/8").unwrap();
+        let client = HttpClient::builder(&base).egress_policy(loopback).build().unwrap();
+        assert_eq!(client.get("/", None).await.unwrap().status(), 200);
+        // Redirects are checked too.
+        let err = client.get("/", None).await.unwrap_err();
+        let violation = err.downcast_ref::<AarambhNetError>().unwrap().cause::<PolicyViolation>().cloned();
+        assert_eq!(violation.unwrap().kind, PolicyViolationKind::PrivateAddress);
+        server_task.await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_resolver_pins_checked_addresses() {
+        let resolver = PolicyResolver(Arc::new(EgressPolicy::new().deny_private_ranges(true)));
+        // Within a request the host resolves to the addresses checked when the request started, even
+        // if a new lookup would now answer differently.
+        let pinned: Vec<SocketAddr> = vec!["93.184.216.34:0".parse().unwrap()];
+        let lookup = async { resolver.resolve("rebind.example".parse().unwrap()).await };
+        let addrs = PINNED.scope(("rebind.example".to_string(), pinned.clone()), lookup).await.unwrap();
+        let addrs: Vec<_> = addrs.collect();
+        assert_eq!(addrs, pinned);
+        // Other lookups, such as those of redirect targets, are checked.
+        let err = resolver.resolve("localhost".parse().unwrap()).await.err().unwrap();
+        assert_eq!(err.downcast_ref::<PolicyViolation>().unwrap().kind, PolicyViolationKind::PrivateAddress);
+
+        let ok = "HTTP/1.1 200 OK\r\ncontent-length: 0\r\nconnection: close\r\n\r\n";
+        let (server, server_task) = serve(vec![ok]).await;
+        let loopback = EgressPolicy::new().deny_private_ranges(true).allow_cidr("127.0.0.0/8").unwrap();
+        let client = HttpClient::builder(&format!("http://localhost:{}", server.port()))
+            .egress_policy(loopback.allow_cidr("::1").unwrap())
+            .build()
+            .unwrap();
+        assert_eq!(client.get("/", None).await.unwrap().status(), 200);
+        server_task.await.unwrap();
+    }
+}
@@ -0,0 +1,263 @@
+use std::{error::Error, fmt, time::Duration};
+
+/// The `AarambhNetError` is returned by the clients and servers of this crate when an operation
+/// fails. Besides the underlying error it records what was being done and to whom, so logs and bug
+/// reports say more than a bare "Connection reset by peer".
+///
+/// # Properties:
+///
+/// * `operation`: What was being done, for example `"GET"` or `"connect"`.
+/// * `target`: The URL or address the operation was aimed at, if known.
+/// * `bytes_transferred`: The number of bytes sent and received before the failure, if known.
+/// * `elapsed`: How long the operation ran before it failed, if known.
+/// * `source`: The error that made the operation fail.
+#[derive(Debug)]
+pub struct AarambhNetError {
+    operation: String,
+    target: Option<String>,
+    bytes_transferred: Option<u64>,
+    elapsed: Option<Duration>,
+    source: Box<dyn Error + Send + Sync>,
+}
+
+impl AarambhNetError {
+    pub(crate) fn new(operation: impl Into<String>, source: impl Into<Box<dyn Error + Send + Sync>>) -> Self {
+        AarambhNetError {
+            operation: operation.into(),
+            target: None,
+            bytes_transferred: None,
+            elapsed: None,
+            source: source.into(),
+        }
+    }
+
+    pub(crate) fn with_target(mut self, target: impl fmt::Display) -> Self {
+        self.target = Some(target.to_string());
+        self
+    }
+
+    pub(crate) fn with_bytes_transferred(mut self, bytes: u64) -> Self {
+        self.bytes_transferred = Some(bytes);
+        self
+    }
+
+    pub(crate) fn with_elapsed(mut self, elapsed: Duration) -> Self {
+        self.elapsed = Some(elapsed);
+        self
+    }
+
+    /// The function `operation` returns what was being done when the error happened.
+    pub fn operation(&self) -> &str {
+        &self.operation
+    }
+
+    /// The function `target` returns the URL or address the operation was aimed at.
+    pub fn target(&self) -> Option<&str> {
+        self.target.as_deref()
+    }
+
+    /// The function `bytes_transferred` returns the number of bytes moved before the failure.
+    pub fn bytes_transferred(&self) -> Option<u64> {
+        self.bytes_transferred
+    }
+
+    /// The function `elapsed` returns how long the operation ran before it failed.
+    pub fn elapsed(&self) -> Option<Duration> {
+        self.elapsed
+    }
+
+    /// The function `inner` returns the error that made the operation fail.
+    pub fn inner(&self) -> &(dyn Error + Send + Sync + 'static) {
+        self.source.as_ref()
+    }
+
+    /// The function `cause` searches the chain of underlying errors for one of type `T`, for example
+    /// a `BodyTooLargeError` or an `std::io::Error`.
+    pub fn cause<T: Error + 'static>(&self) -> Option<&T> {
+        let mut current: Option<&(dyn Error + 'static)> = Some(self.source.as_ref());
+        while let Some(err) = current {
+            if let Some(found) = err.downcast_ref::<T>() {
+                return Some(found);
+            }
+            current = err.source();
+        }
+        None
+    }
+}
+
+impl fmt::Display for AarambhNetError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.operation)?;
+        if let Some(target) = &self.target {
+            write!(f, " {}", target)?;
+        }
+        write!(f, " failed")?;
+        if let Some(elapsed) = self.elapsed {
+            write!(f, " after {:?}", elapsed)?;
+        }
+        if let Some(bytes) = self.bytes_transferred {
+            write!(f, " ({} bytes transferred)", bytes)?;
+        }
+        write!(f, ": {}", self.source)
+    }
+}
+
+impl Error for AarambhNetError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        Some(self.source.as_ref())
+    }
+}
+
+/// The `AggregateError` is returned by operations fanned out to several targets, such as a request
+/// sent to every replica, when some of them fail. Unlike the first failure alone, it keeps the error
+/// of every failed sub-operation together with its target.
+///
+/// # Properties:
+///
+/// * `operation`: What was being done, for example `"GET /health"`.
+/// * `succeeded`: The number of sub-operations that succeeded.
+/// * `errors`: The errors of the failed sub-operations, in the order of their targets.
+#[derive(Debug)]
+pub struct AggregateError {
+    operation: String,
+    succeeded: usize,
+    errors: Vec<AarambhNetError>,
+}
+
+impl AggregateError {
+    /// The function `partition` splits the results of a fan-out into its successes and failures.
+    ///
+    /// # Arguments:
+    ///
+    /// * `operation`: What each sub-operation did, used to describe the failures.
+    /// * `results`: The target of each sub-operation with its result.
+    ///
+    /// # Returns:
+    ///
+    /// The `partition` function returns the successful values in order, and an `AggregateError` if
+    /// at least one sub-operation failed.
+    pub fn partition<T, D, E>(
+        operation: impl Into<String>,
+        results: impl IntoIterator<Item = (D, Result<T, E>)>,
+    ) -> (Vec<T>, Option<AggregateError>)
+    where
+        D: fmt::Display,
+        E: Into<Box<dyn Error + Send + Sync>>,
+    {
+        let operation = operation.into();
+        let (mut successes, mut errors) = (Vec::new(), Vec::new());
+        for (target, result) in results {
+            match result {
+                Ok(value) => successes.push(value),
+                Err(e) => errors.push(AarambhNetError::new(operation.clone(), e).with_target(target)),
+            }
+        }
+        if errors.is_empty() {
+            return (successes, None);
+        }
+        let succeeded = successes.len();
+        (successes, Some(AggregateError { operation, succeeded, errors }))
+    }
+
+    /// The function `collect` gathers the results of a fan-out that needs every sub-operation to
+    /// succeed.
+    ///
+    /// # Returns:
+    ///
+    /// The `collect` function returns the values in order, or an `AggregateError` holding every
+    /// failure if any sub-operation failed.
+    pub fn collect<T, D, E>(
+        operation: impl Into<String>,
+        results: impl IntoIterator<Item = (D, Result<T, E>)>,
+    ) -> Result<Vec<T>, AggregateError>
+    where
+        D: fmt::Display,
+        E: Into<Box<dyn Error + Send + Sync>>,
+    {
+        match Self::partition(operation, results) {
+            (successes, None) => Ok(successes),
+            (_, Some(err)) => Err(err),
+        }
+    }
+
+    /// The function `operation` returns what the sub-operations did.
+    pub fn operation(&self) -> &str {
+        &self.operation
+    }
+
+    /// The function `succeeded` returns the number of sub-operations that succeeded.
+    pub fn succeeded(&self) -> usize {
+        self.succeeded
+    }
+
+    /// The function `errors` returns the error of every failed sub-operation, with its target.
+    pub fn errors(&self) -> &[AarambhNetError] {
+        &self.errors
+    }
+
+    /// The function `failed_targets` returns the targets of the failed sub-operations.
+    pub fn failed_targets(&self) -> impl Iterator<Item = &str> {
+        self.errors.iter().filter_map(AarambhNetError::target)
+    }
+}
+
+impl fmt::Display for AggregateError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let total = self.succeeded + self.errors.len();
+        write!(f, "{} failed for {} of {} targets", self.operation, self.errors.len(), total)?;
+        for (i, err) in self.errors.iter().enumerate() {
+            let separator = if i == 0 { ": " } else { "; " };
+            write!(f, "{}{}: {}", separator, err.target().unwrap_or("?"), err.inner())?;
+        }
+        Ok(())
+    }
+}
+
+impl Error for AggregateError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        self.errors.first().map(|err| err as &(dyn Error + 'static))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::io;
+
+    #[test]
+    fn test_error_context() {
+        let err = AarambhNetError::new("send", io::Error::new(io::ErrorKind::ConnectionReset, "connection reset by peer"))
+            .with_target("10.0.0.7:9000")
+            .with_bytes_transferred(512)
+            .with_elapsed(Duration::from_millis(40));
+        assert_eq!(
+            err.to_string(),
+            "send 10.0.0.7:9000 failed after 40ms (512 bytes transferred): connection reset by peer"
+        );
+        assert_eq!(err.cause::<io::Error>().unwrap().kind(), io::ErrorKind::ConnectionReset);
+        assert_eq!(err.target(), Some("10.0.0.7:9000"));
+    }
+
+    #[test]
+    fn test_aggregate_error_keeps_every_failure() {
+        let results = vec![
+            ("10.0.0.1:80", Ok(1)),
+            ("10.0.0.2:80", Err(io::Error::new(io::ErrorKind::ConnectionRefused, "connection refused"))),
+            ("10.0.0.3:80", Ok(3)),
+            ("10.0.0.4:80", Err(io::Error::new(io::ErrorKind::TimedOut, "timed out"))),
+        ];
+        let (successes, err) = AggregateError::partition("connect", results);
+        assert_eq!(successes, vec![1, 3]);
+        let err = err.unwrap();
+        assert_eq!((err.succeeded(), err.errors().len()), (2, 2));
+        assert_eq!(err.failed_targets().collect::<Vec<_>>(), vec!["10.0.0.2:80", "10.0.0.4:80"]);
+        assert_eq!(err.errors()[1].cause::<io::Error>().unwrap().kind(), io::ErrorKind::TimedOut);
+        assert_eq!(
+            err.to_string(),
+            "connect failed for 2 of 4 targets: 10.0.0.2:80: connection refused; 10.0.0.4:80: timed out"
+        );
+
+        let all_ok = AggregateError::collect("connect", vec![("a", Ok::<_, io::Error>(1)), ("b", Ok(2))]);
+        assert_eq!(all_ok.unwrap(), vec![1, 2]);
+    }
+}
@@ -0,0 +1,123 @@
+use super::BoxError;
+use reqwest::{header::HeaderMap, Method, Request, Response, StatusCode, Url};
+use std::{
+    error::Error,
+    sync::{Arc, RwLock},
+    time::Duration,
+};
+
+type RequestHook = Arc<dyn Fn(&RequestEvent) + Send + Sync>;
+type ResponseHook = Arc<dyn Fn(&ResponseEvent) + Send + Sync>;
+
+/// The `RequestEvent` struct describes an outgoing request to the hooks registered with
+/// `HttpClient::on_request`. Retries are reported as separate requests.
+///
+/// # Properties:
+///
+/// * `method`: The HTTP method.
+/// * `url`: The full URL.
+/// * `headers`: The headers as sent, including the default and profile headers.
+#[derive(Debug)]
+pub struct RequestEvent<'a> {
+    pub method: &'a Method,
+    pub url: &'a Url,
+    pub headers: &'a HeaderMap,
+}
+
+/// The `ResponseEvent` struct describes the outcome of a request to the hooks registered with
+/// `HttpClient::on_response`.
+///
+/// # Properties:
+///
+/// * `method`: The HTTP method.
+/// * `url`: The full URL.
+/// * `status`: The response status, or `None` if no response was received.
+/// * `elapsed`: The time from sending the request to receiving the response headers or the error.
+/// * `error`: The transport error, if no response was received.
+#[derive(Debug)]
+pub struct ResponseEvent<'a> {
+    pub method: &'a Method,
+    pub url: &'a Url,
+    pub status: Option<StatusCode>,
+    pub elapsed: Duration,
+    pub error: Option<&'a (dyn Error + Send + Sync + 'static)>,
+}
+
+/// The `AuditHooks` struct holds the observers shared by an `HttpClient` and its clones. The lists
+/// are replaced rather than modified when a hook is added, so a request only clones an `Arc` and
+/// hooks may register further hooks.
+#[derive(Default)]
+pub(crate) struct AuditHooks {
+    requests: RwLock<Arc<[RequestHook]>>,
+    responses: RwLock<Arc<[ResponseHook]>>,
+}
+
+impl AuditHooks {
+    pub(crate) fn add_request_hook(&self, hook: RequestHook) {
+        let mut hooks = self.requests.write().unwrap();
+        *hooks = hooks.iter().cloned().chain([hook]).collect();
+    }
+
+    pub(crate) fn add_response_hook(&self, hook: ResponseHook) {
+        let mut hooks = self.responses.write().unwrap();
+        *hooks = hooks.iter().cloned().chain([hook]).collect();
+    }
+
+    /// The function `observe_request` reports `request` to the request hooks. It returns the method
+    /// and URL to report the response with, or `None` when no response hook is registered, so that
+    /// clients without hooks copy nothing.
+    pub(crate) fn observe_request(&self, request: &Request) -> Option<(Method, Url)> {
+        let hooks = self.requests.read().unwrap().clone();
+        if !hooks.is_empty() {
+            let event = RequestEvent { method: request.method(), url: request.url(), headers: request.headers() };
+            for hook in hooks.iter() {
+                hook(&event);
+            }
+        }
+        if self.responses.read().unwrap().is_empty() {
+            return None;
+        }
+        Some((request.method().clone(), request.url().clone()))
+    }
+
+    /// The function `observe_response` reports the outcome of the request to `url` to the response
+    /// hooks.
+    pub(crate) fn observe_response(&self, (method, url): (Method, Url), result: &Result<Response, BoxError>, elapsed: Duration) {
+        let hooks = self.responses.read().unwrap().clone();
+        let event = ResponseEvent {
+            method: &method,
+            url: &url,
+            status: result.as_ref().ok().map(Response::status),
+            elapsed,
+            error: result.as_ref().err().map(|e| &**e),
+        };
+        for hook in hooks.iter() {
+            hook(&event);
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::http::{testing::serve, HttpClient};
+    use std::sync::{Arc, Mutex};
+
+    #[tokio::test]
+    async fn test_audit_hooks_see_every_call() {
+        let ok = "HTTP/1.1 200 OK\r\ncontent-length: 0\r\nconnection: close\r\n\r\n";
+        let missing = "HTTP/1.1 404 Not Found\r\ncontent-length: 0\r\nconnection: close\r\n\r\n";
+        let (addr, server_task) = serve(vec![ok, missing]).await;
+        let client = HttpClient::new(&format!("http://{}", addr), None).unwrap();
+
+        let log = Arc::new(Mutex::new(Vec::new()));
+        let (requests, responses) = (log.clone(), log.clone());
+        client.on_request(move |event| requests.lock().unwrap().push(format!("{} {}", event.method, event.url.path())));
+        client.on_response(move |event| responses.lock().unwrap().push(format!("{:?}", event.status.map(|s| s.as_u16()))));
+
+        // Hooks are shared with clones, so they see calls made anywhere in the application.
+        client.get("/a", None).await.unwrap();
+        client.clone().delete("/b", None).await.unwrap();
+        server_task.await.unwrap();
+        assert_eq!(*log.lock().unwrap(), ["GET /a", "Some(200)", "DELETE /b", "Some(404)"]);
+    }
+}
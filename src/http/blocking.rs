@@ -0,0 +1,172 @@
+use super::{
+    client::HttpClient,
+    response::{AarambhResponse, HttpStatusError},
+};
+use reqwest::header::HeaderMap;
+use serde::de::DeserializeOwned;
+use std::{error::Error, ops::Deref, sync::Arc};
+use tokio::runtime::{Builder, Runtime};
+
+/// The `HttpClientBlocking` struct is a synchronous `HttpClient` for CLI tools and build scripts
+/// that do not run an async runtime. It drives an `HttpClient` on a private current-thread runtime, so
+/// every feature of the async client (endpoint profiles, cassettes, HAR recording, ...) is available
+/// through `client_mut` or `from_client`.
+///
+/// The runtime starts no threads: it runs on the calling thread, and only while a method blocks on
+/// it. Work the async client would do in the background, such as closing idle pooled connections,
+/// waits for the next call.
+///
+/// Its methods block the calling thread and must not be called from inside an async context. The
+/// runtime is dropped with the last of the client and its `BlockingResponse`s, and dropping it
+/// inside an async context panics, so neither may be dropped there either.
+///
+/// # Properties:
+///
+/// * `client`: The async client doing the work.
+/// * `runtime`: The runtime the requests run on, shared with the responses to read their bodies.
+pub struct HttpClientBlocking {
+    client: HttpClient,
+    runtime: Arc<Runtime>,
+}
+
+/// The `BlockingResponse` struct is the response returned by `HttpClientBlocking`. The accessors of
+/// `AarambhResponse` (`status`, `headers`, `is_success`, ...) are available through `Deref`; the
+/// body is read with the blocking methods below. It keeps the runtime of its client alive, and like
+/// the client must not be dropped inside an async context.
+pub struct BlockingResponse {
+    inner: AarambhResponse,
+    runtime: Arc<Runtime>,
+}
+
+impl HttpClientBlocking {
+    /// The function `new` creates a blocking client with a base URL and optional default headers,
+    /// like `HttpClient::new`.
+    ///
+    /// # Returns:
+    ///
+    /// The `new` function returns a `Result` containing the client, or an error if the URL cannot be
+    /// parsed or the runtime cannot be started.
+    pub fn new(base_url: &str, default_headers: Option<HeaderMap>) -> Result<Self, Box<dyn Error>> {
+        Self::from_client(HttpClient::new(base_url, default_headers)?)
+    }
+
+    /// The function `from_client` wraps an `HttpClient` configured with `HttpClient::builder`.
+    ///
+    /// # Returns:
+    ///
+    /// The `from_client` function returns a `Result` containing the client, or an error if the
+    /// runtime cannot be started.
+    pub fn from_client(client: HttpClient) -> Result<Self, Box<dyn Error>> {
+        let runtime = Builder::new_current_thread().enable_all().build()?;
+        Ok(HttpClientBlocking { client, runtime: Arc::new(runtime) })
+    }
+
+    /// The function `client` returns the wrapped async client.
+    pub fn client(&self) -> &HttpClient {
+        &self.client
+    }
+
+    /// The function `client_mut` returns the wrapped async client for reconfiguration.
+    pub fn client_mut(&mut self) -> &mut HttpClient {
+        &mut self.client
+    }
+
+    fn wrap(&self, response: AarambhResponse) -> BlockingResponse {
+        BlockingResponse { inner: response, runtime: self.runtime.clone() }
+    }
+
+    /// The function `get` sends a `GET` request to `endpoint`, like `HttpClient::get`.
+    pub fn get(&self, endpoint: &str, headers: Option<HeaderMap>) -> Result<BlockingResponse, Box<dyn Error>> {
+        Ok(self.wrap(self.runtime.block_on(self.client.get(endpoint, headers))?))
+    }
+
+    /// The function `post` sends a `POST` request to `endpoint`, like `HttpClient::post`.
+    pub fn post(&self, endpoint: &str, headers: Option<HeaderMap>, body: Option<&str>) -> Result<BlockingResponse, Box<dyn Error>> {
+        Ok(self.wrap(self.runtime.block_on(self.client.post(endpoint, headers, body))?))
+    }
+
+    /// The function `put` sends a `PUT` request to `endpoint`, like `HttpClient::put`.
+    pub fn put(&self, endpoint: &str, headers: Option<HeaderMap>, body: Option<&str>) -> Result<BlockingResponse, Box<dyn Error>> {
+        Ok(self.wrap(self.runtime.block_on(self.client.put(endpoint, headers, body))?))
+    }
+
+    /// The function `delete` sends a `DELETE` request to `endpoint`, like `HttpClient::delete`.
+    pub fn delete(&self, endpoint: &str, headers: Option<HeaderMap>) -> Result<BlockingResponse, Box<dyn Error>> {
+        Ok(self.wrap(self.runtime.block_on(self.client.delete(endpoint, headers))?))
+    }
+
+    /// The function `head` sends a `HEAD` request to `endpoint`, like `HttpClient::head`.
+    pub fn head(&self, endpoint: &str, headers: Option<HeaderMap>) -> Result<BlockingResponse, Box<dyn Error>> {
+        Ok(self.wrap(self.runtime.block_on(self.client.head(endpoint, headers))?))
+    }
+
+    /// The function `patch` sends a `PATCH` request to `endpoint`, like `HttpClient::patch`.
+    pub fn patch(&self, endpoint: &str, headers: Option<HeaderMap>, body: Option<&str>) -> Result<BlockingResponse, Box<dyn Error>> {
+        Ok(self.wrap(self.runtime.block_on(self.client.patch(endpoint, headers, body))?))
+    }
+}
+
+impl BlockingResponse {
+    /// The function `error_for_status` turns 4xx and 5xx responses into an `HttpStatusError`, like
+    /// `AarambhResponse::error_for_status`.
+    pub fn error_for_status(self) -> Result<Self, HttpStatusError> {
+        let runtime = self.runtime;
+        let inner = runtime.block_on(self.inner.error_for_status())?;
+        Ok(BlockingResponse { inner, runtime })
+    }
+
+    /// The function `text` reads the whole body as a string.
+    pub fn text(self) -> Result<String, Box<dyn Error>> {
+        self.runtime.block_on(self.inner.text())
+    }
+
+    /// The function `bytes` reads the whole body as raw bytes.
+    pub fn bytes(self) -> Result<Vec<u8>, Box<dyn Error>> {
+        self.runtime.block_on(self.inner.bytes())
+    }
+
+    /// The function `text_limited` reads the body as a string, refusing bodies larger than `max`.
+    pub fn text_limited(self, max: usize) -> Result<String, Box<dyn Error>> {
+        self.runtime.block_on(self.inner.text_limited(max))
+    }
+
+    /// The function `json` deserializes the body as JSON into `T`.
+    pub fn json<T: DeserializeOwned>(self) -> Result<T, Box<dyn Error>> {
+        self.runtime.block_on(self.inner.json())
+    }
+}
+
+impl Deref for BlockingResponse {
+    type Target = AarambhResponse;
+
+    fn deref(&self) -> &AarambhResponse {
+        &self.inner
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::{
+        io::{Read, Write},
+        net::TcpListener,
+        thread,
+    };
+
+    #[test]
+    fn test_blocking_get() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        let server = thread::spawn(move || {
+            let (mut socket, _) = listener.accept().unwrap();
+            let _ = socket.read(&mut [0; 4096]).unwrap();
+            socket.write_all(b"HTTP/1.1 200 OK\r\ncontent-length: 2\r\nconnection: close\r\n\r\nok").unwrap();
+        });
+
+        let client = HttpClientBlocking::new(&format!("http://{}", addr), None).unwrap();
+        let response = client.get("/", None).unwrap();
+        assert!(response.is_success());
+        assert_eq!(response.text().unwrap(), "ok");
+        server.join().unwrap();
+    }
+}
@@ -0,0 +1,1144 @@
+// client.rs
+use super::{
+    audit::{AuditHooks, RequestEvent, ResponseEvent},
+    curl::ToCurl,
+    encoding::{self, ContentEncoding},
+    limits::{self, BodyDirection, BodyTooLargeError},
+    metrics::ClientMetrics,
+    pool::{ConnectionTracker, PoolConfig, PoolStats},
+    profile::{self, EndpointProfile},
+    response::AarambhResponse,
+    throttle, BoxError,
+};
+#[cfg(not(target_arch = "wasm32"))]
+use super::{
+    har::HarRecorder,
+    vcr::Cassette,
+    verbose::{self, TimelineConnectLayer, TimelineResolver},
+};
+use crate::{
+    address_pool::LocalAddressPool,
+    egress::EgressPolicy,
+    error::AarambhNetError,
+    rate_limit::{RetryBudget, TokenBucket},
+    rt::{sleep, Instant},
+};
+#[cfg(not(target_arch = "wasm32"))]
+use crate::{egress::PolicyResolver, timeline::Timeline};
+use reqwest::{
+    header::{HeaderMap, HeaderName, HeaderValue, ACCEPT_ENCODING, USER_AGENT},
+    Client, ClientBuilder, Method, Request, RequestBuilder, Response, Url,
+};
+#[cfg(not(target_arch = "wasm32"))]
+use reqwest::redirect;
+use std::{
+    error::Error,
+    net::IpAddr,
+    sync::{Arc, RwLock},
+    time::Duration,
+};
+
+/// The `HttpClient` struct in Rust represents an HTTP client with a base URL, optional default headers,
+/// and a client instance.
+///
+/// `HttpClient` is `Send + Sync` and cheap to clone: clones share the connection pool, the default
+/// headers, the metrics and every attached recorder, so a clone (or the `Arc` returned by `shared`)
+/// can be handed to each tokio task. Settings changed later with the consuming `with_*` methods only
+/// affect the client they are called on.
+/// 
+/// # Properties:
+/// 
+/// * `base_url`: The `base_url` property in the `HttpClient` struct represents the base URL that will
+///   be used for making HTTP requests. This URL serves as the starting point for constructing full URLs
+///   for the requests sent by the HTTP client.
+/// * `default_headers`: The `default_headers` property in the `HttpClient` struct holds a snapshot of
+///   the default `HeaderMap` behind a shared `RwLock`. This field stores default headers that will be
+///   included in every request made by the `HttpClient`; `set_user_agent`, `add_default_header` and
+///   `remove_default_header` replace the snapshot at runtime, leaving requests being built with the old one.
+/// * `client`: The `client` property in the `HttpClient` struct is of type `Client`. This likely
+///   represents an HTTP client that can be used to make HTTP requests to a server. The `Client` type is
+///   commonly used in Rust libraries like `reqwest` for sending HTTP requests and handling responses.
+/// * `cassette`: The optional `Cassette` that records or replays every request sent by the client.
+/// * `har`: The optional `HarRecorder` that archives every request/response in HAR format.
+/// * `curl_debug`: When `true`, every outgoing request is logged as a `curl` command.
+/// * `address_pool`: The optional `LocalAddressPool` whose addresses are rotated per request.
+/// * `source_clients`: One underlying `Client` bound to each address of the `address_pool`.
+/// * `accept_encodings`: The content encodings advertised in `Accept-Encoding`.
+/// * `auto_decompress`: When `true`, bodies in one of the `accept_encodings` are decoded before they
+///   are handed to the caller.
+/// * `profiles`: The per-endpoint `EndpointProfile` overrides, keyed by path pattern and checked in
+///   registration order.
+/// * `connections`: The `ConnectionTracker` limiting the requests in flight and counting pool usage.
+/// * `max_request_body`: The largest request body that is sent, in bytes.
+/// * `max_response_body`: The largest response body that is received, in bytes.
+/// * `verbose`: When `true`, a `Timeline` of every exchange is recorded.
+/// * `follow_redirects`: When `true`, redirects are followed.
+/// * `metrics`: The request counters rendered by `metrics_prometheus`.
+/// * `retry_budget`: The optional `RetryBudget` every retry must draw from.
+/// * `download_limit`: The optional bandwidth limit shared by all response bodies.
+/// * `upload_limit`: The optional bandwidth limit shared by all request bodies.
+/// * `audit`: The request and response observers, shared by all clones.
+/// * `egress`: The optional `EgressPolicy` every destination, redirects included, is checked against.
+#[derive(Clone)]
+pub struct HttpClient {
+    base_url: Url,
+    default_headers: Arc<RwLock<Arc<HeaderMap>>>,
+    client: Client,
+    #[cfg(not(target_arch = "wasm32"))]
+    cassette: Option<Arc<Cassette>>,
+    #[cfg(not(target_arch = "wasm32"))]
+    har: Option<Arc<HarRecorder>>,
+    curl_debug: bool,
+    address_pool: Option<Arc<LocalAddressPool>>,
+    source_clients: Vec<(IpAddr, Client)>,
+    accept_encodings: Vec<ContentEncoding>,
+    #[cfg(not(target_arch = "wasm32"))]
+    auto_decompress: bool,
+    profiles: Vec<(String, EndpointProfile)>,
+    connections: Arc<ConnectionTracker>,
+    max_request_body: Option<u64>,
+    max_response_body: Option<u64>,
+    #[cfg(not(target_arch = "wasm32"))]
+    verbose: bool,
+    #[cfg(not(target_arch = "wasm32"))]
+    follow_redirects: bool,
+    metrics: Arc<ClientMetrics>,
+    retry_budget: Option<Arc<RetryBudget>>,
+    download_limit: Option<Arc<TokenBucket>>,
+    upload_limit: Option<Arc<TokenBucket>>,
+    audit: Arc<AuditHooks>,
+    egress: Option<Arc<EgressPolicy>>,
+}
+
+/// The `HttpClientBuilder` struct configures an `HttpClient` before it is created.
+///
+/// # Properties:
+///
+/// * `base_url`: The base URL requests are resolved against.
+/// * `default_headers`: The headers sent with every request.
+/// * `accept_encodings`: The content encodings the client advertises and decodes.
+/// * `auto_decompress`: Whether compressed bodies are decoded automatically.
+/// * `profiles`: The per-endpoint overrides registered with `endpoint_profile`.
+/// * `pool`: The connection pool settings.
+/// * `max_request_body`: The request body size limit.
+/// * `max_response_body`: The response body size limit.
+/// * `verbose`: Whether request timelines are recorded.
+/// * `follow_redirects`: Whether redirects are followed.
+/// * `retry_budget`: The `RetryBudget` shared with other clients, if any.
+/// * `download_rate`: The download bandwidth limit in bytes per second.
+/// * `upload_rate`: The upload bandwidth limit in bytes per second.
+/// * `egress`: The `EgressPolicy` restricting the destinations of requests, if any.
+pub struct HttpClientBuilder {
+    base_url: String,
+    default_headers: Option<HeaderMap>,
+    accept_encodings: Vec<ContentEncoding>,
+    #[cfg(not(target_arch = "wasm32"))]
+    auto_decompress: bool,
+    profiles: Vec<(String, EndpointProfile)>,
+    pool: PoolConfig,
+    max_request_body: Option<u64>,
+    max_response_body: Option<u64>,
+    #[cfg(not(target_arch = "wasm32"))]
+    verbose: bool,
+    #[cfg(not(target_arch = "wasm32"))]
+    follow_redirects: bool,
+    retry_budget: Option<Arc<RetryBudget>>,
+    download_rate: Option<u64>,
+    upload_rate: Option<u64>,
+    egress: Option<Arc<EgressPolicy>>,
+}
+
+/// The `impl HttpClient { ... }` block in the Rust code snippet is implementing methods for the
+/// `HttpClient` struct. Here's a breakdown of what each method is doing:
+impl HttpClient {
+    /// The function `new` creates a new instance of an `HttpClient` with a base URL, default headers,
+    /// and a new client.
+    /// 
+    /// # Arguments:
+    /// 
+    /// * `base_url`: The `base_url` parameter is a string reference (`&str`) that represents the base
+    ///   URL for the HTTP client. This is the URL that will be used as the starting point for making HTTP
+    ///   requests.
+    /// * `default_headers`: The `default_headers` parameter in the `new` function is an optional
+    ///   parameter of type `Option<HeaderMap>`. It allows you to provide a set of default headers to be
+    ///   included in each HTTP request made by the `HttpClient`. If no default headers are provided, you
+    ///   can pass `None
+    /// 
+    /// # Returns:
+    /// 
+    /// The `new` function is returning a `Result` containing an instance of `HttpClient` if the URL
+    /// parsing is successful and the `HttpClient` struct is properly initialized with the provided base
+    /// URL, default headers, and a new `Client` instance.
+    pub fn new(base_url: &str, default_headers: Option<HeaderMap>) -> Result<Self, Box<dyn Error>> {
+        let mut builder = Self::builder(base_url);
+        builder.default_headers = default_headers;
+        builder.build()
+    }
+
+    /// The function `builder` starts configuring an `HttpClient` for `base_url`.
+    ///
+    /// # Arguments:
+    ///
+    /// * `base_url`: The base URL requests are resolved against. It is parsed by
+    ///   `HttpClientBuilder::build`.
+    ///
+    /// # Returns:
+    ///
+    /// The `builder` function returns an `HttpClientBuilder` with the default configuration: no default
+    /// headers, and gzip, brotli, zstd and deflate accepted and decoded automatically.
+    pub fn builder(base_url: &str) -> HttpClientBuilder {
+        HttpClientBuilder {
+            base_url: base_url.to_string(),
+            default_headers: None,
+            accept_encodings: ContentEncoding::all(),
+            #[cfg(not(target_arch = "wasm32"))]
+            auto_decompress: true,
+            profiles: Vec::new(),
+            pool: PoolConfig::default(),
+            max_request_body: None,
+            max_response_body: None,
+            #[cfg(not(target_arch = "wasm32"))]
+            verbose: false,
+            #[cfg(not(target_arch = "wasm32"))]
+            follow_redirects: true,
+            retry_budget: None,
+            download_rate: None,
+            upload_rate: None,
+            egress: None,
+        }
+    }
+
+    /// The function `shared` moves the client into an `Arc`, for code that prefers passing
+    /// `Arc<HttpClient>` around over cloning the client.
+    pub fn shared(self) -> Arc<Self> {
+        Arc::new(self)
+    }
+
+    /// The function `with_cassette` attaches a `Cassette` to the client so that every request is
+    /// recorded to, or replayed from, the cassette file.
+    ///
+    /// # Arguments:
+    ///
+    /// * `cassette`: The `Cassette` to use. In replay mode no request reaches the network. In record
+    ///   mode the interactions are written when the last clone of the client is dropped, or by
+    ///   `Cassette::save`.
+    ///
+    /// # Returns:
+    ///
+    /// The `with_cassette` function returns the `HttpClient` with the cassette attached.
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn with_cassette(mut self, cassette: Cassette) -> Self {
+        self.cassette = Some(Arc::new(cassette));
+        self
+    }
+
+    /// The function `cassette` returns the `Cassette` attached to the client, if any, so recorded
+    /// interactions can be written with `Cassette::save` before every clone of the client is dropped.
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn cassette(&self) -> Option<&Cassette> {
+        self.cassette.as_deref()
+    }
+
+    /// The function `with_har_recorder` enables HAR recording of every request made by the client.
+    ///
+    /// # Arguments:
+    ///
+    /// * `recorder`: The shared `HarRecorder` that receives the entries. Keep a clone of the `Arc` to
+    ///   export the archive with `HarRecorder::save` or `HarRecorder::to_json`.
+    ///
+    /// # Returns:
+    ///
+    /// The `with_har_recorder` function returns the `HttpClient` with recording enabled. Responses are
+    /// buffered in memory while recording so their bodies can be archived.
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn with_har_recorder(mut self, recorder: Arc<HarRecorder>) -> Self {
+        self.har = Some(recorder);
+        self
+    }
+
+    /// The function `with_curl_debug` toggles logging every outgoing request as the equivalent `curl`
+    /// command, so failing requests can be reproduced from a shell. The commands are `tracing` events
+    /// at the `DEBUG` level with the target `aarambh_net::curl`; they may contain credentials, so
+    /// route that target with care.
+    ///
+    /// # Arguments:
+    ///
+    /// * `enabled`: Whether to log the `curl` command for each request.
+    pub fn with_curl_debug(mut self, enabled: bool) -> Self {
+        self.curl_debug = enabled;
+        self
+    }
+
+    /// The function `with_address_pool` makes the client rotate its source address across a pool of
+    /// local addresses, one address per request, recording transport failures against the address
+    /// that was used.
+    ///
+    /// # Arguments:
+    ///
+    /// * `pool`: The shared `LocalAddressPool`. It can also be handed to `TcpClient::connect_from_pool`
+    ///   so both clients share failure tracking.
+    ///
+    /// # Returns:
+    ///
+    /// The `with_address_pool` function returns a `Result` containing the `HttpClient`, or an error if
+    /// a client bound to one of the addresses cannot be built. Browsers pick the source address
+    /// themselves, so it is not available on `wasm32`.
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn with_address_pool(mut self, pool: Arc<LocalAddressPool>) -> Result<Self, Box<dyn Error>> {
+        self.source_clients = pool
+            .addrs()
+            .into_iter()
+            .map(|addr| {
+                let builder =
+                    client_builder(self.connections.config(), self.verbose, self.follow_redirects, self.egress.clone());
+                Ok((addr, builder.local_address(addr).build()?))
+            })
+            .collect::<Result<Vec<_>, Box<dyn Error>>>()?;
+        self.address_pool = Some(pool);
+        Ok(self)
+    }
+
+    /// The function `dispatch` sends a built request over the network and decodes the body when
+    /// automatic decompression is enabled. In verbose mode the exchange is traced into a `Timeline`.
+    async fn dispatch(&self, client: &Client, request: Request) -> Result<Response, BoxError> {
+        #[cfg(not(target_arch = "wasm32"))]
+        if self.verbose {
+            let detail = format!("{} {}", request.method(), request.url());
+            return verbose::trace(Timeline::new(), detail, self.exchange(client, request)).await;
+        }
+        self.exchange(client, request).await
+    }
+
+    async fn exchange(&self, client: &Client, request: Request) -> Result<Response, BoxError> {
+        let (upload, download) = self.bandwidth_limits(request.url());
+        let request = throttle::throttle_request(request, upload);
+        let response = self.connections.track(async { Ok(client.execute(request).await?) }).await?;
+        let response = throttle::throttle_response(response, download)?;
+        #[cfg(not(target_arch = "wasm32"))]
+        let response = match self.auto_decompress {
+            true => encoding::decode(response, &self.accept_encodings)?,
+            false => response,
+        };
+        match self.max_response_body {
+            Some(limit) => limits::limit_response(response, limit),
+            None => Ok(response),
+        }
+    }
+
+    /// The function `set_user_agent` changes the `User-Agent` sent with every following request.
+    ///
+    /// # Arguments:
+    ///
+    /// * `user_agent`: The new user agent, for example `"my-app/2.3.1"`.
+    ///
+    /// # Returns:
+    ///
+    /// The `set_user_agent` function returns an error if `user_agent` is not a valid header value.
+    pub fn set_user_agent(&self, user_agent: &str) -> Result<(), Box<dyn Error>> {
+        self.add_default_header(USER_AGENT.as_str(), user_agent)
+    }
+
+    /// The function `add_default_header` adds or replaces a header sent with every following
+    /// request. Per-request headers still take precedence.
+    ///
+    /// # Arguments:
+    ///
+    /// * `name`: The header name.
+    /// * `value`: The header value.
+    ///
+    /// # Returns:
+    ///
+    /// The `add_default_header` function returns an error if `name` or `value` is not valid in an HTTP
+    /// header.
+    pub fn add_default_header(&self, name: &str, value: &str) -> Result<(), Box<dyn Error>> {
+        let name = HeaderName::from_bytes(name.as_bytes())?;
+        let value = HeaderValue::from_str(value)?;
+        Arc::make_mut(&mut self.default_headers.write().unwrap()).insert(name, value);
+        Ok(())
+    }
+
+    /// The function `remove_default_header` stops sending a default header.
+    ///
+    /// # Returns:
+    ///
+    /// The `remove_default_header` function returns the removed value, or `None` if the header was not
+    /// set.
+    pub fn remove_default_header(&self, name: &str) -> Option<HeaderValue> {
+        let name = HeaderName::from_bytes(name.as_bytes()).ok()?;
+        Arc::make_mut(&mut self.default_headers.write().unwrap()).remove(name)
+    }
+
+    /// The function `default_headers` returns a copy of the headers currently sent with every request.
+    pub fn default_headers(&self) -> HeaderMap {
+        HeaderMap::clone(&self.default_headers.read().unwrap())
+    }
+
+    /// The function `pool_stats` returns a snapshot of the client's connection usage and pool
+    /// configuration.
+    ///
+    /// # Returns:
+    ///
+    /// The `pool_stats` function returns a `PoolStats` value. Replayed cassette responses never touch
+    /// the network and are not counted.
+    pub fn pool_stats(&self) -> PoolStats {
+        self.connections.stats()
+    }
+
+    /// The function `metrics_prometheus` renders the client metrics in the Prometheus text exposition
+    /// format, ready to be appended to an application's `/metrics` endpoint: responses per status
+    /// class, failed attempts, a request duration histogram and the connection pool gauges. Every
+    /// attempt is counted, including retries.
+    pub fn metrics_prometheus(&self) -> String {
+        self.metrics.render_prometheus(&self.pool_stats())
+    }
+
+    /// The function `on_request` registers `hook` to be called with every request this client and its
+    /// clones send, retries included, for example to audit all outbound calls of an application from
+    /// one place. Hooks observe the request as sent and cannot change it; they run on the request
+    /// path and should return quickly.
+    pub fn on_request<F>(&self, hook: F)
+    where
+        F: Fn(&RequestEvent) + Send + Sync + 'static,
+    {
+        self.audit.add_request_hook(Arc::new(hook));
+    }
+
+    /// The function `on_response` registers `hook` to be called with the status and duration, or the
+    /// error, of every request this client and its clones send, retries included. Like `on_request`
+    /// hooks, response hooks only observe.
+    pub fn on_response<F>(&self, hook: F)
+    where
+        F: Fn(&ResponseEvent) + Send + Sync + 'static,
+    {
+        self.audit.add_response_hook(Arc::new(hook));
+    }
+
+    /// The function `profile_for` returns the first `EndpointProfile` whose pattern matches `url`.
+    fn profile_for(&self, url: &Url) -> Option<&EndpointProfile> {
+        self.profiles
+            .iter()
+            .find(|(pattern, _)| profile::matches(pattern, url.path()))
+            .map(|(_, profile)| profile)
+    }
+
+    /// The function `bandwidth_limits` returns the upload and download limits that apply to `url`:
+    /// the client-wide ones followed by those of the matching endpoint profile.
+    fn bandwidth_limits(&self, url: &Url) -> (Vec<Arc<TokenBucket>>, Vec<Arc<TokenBucket>>) {
+        let profile = self.profile_for(url);
+        let upload = self.upload_limit.iter().chain(profile.and_then(|p| p.upload_limit.as_ref()));
+        let download = self.download_limit.iter().chain(profile.and_then(|p| p.download_limit.as_ref()));
+        (upload.cloned().collect(), download.cloned().collect())
+    }
+
+    /// The function `send` builds the request and sends it, wrapping any failure in an
+    /// `AarambhNetError` naming the method, the URL and the time spent.
+    async fn send(&self, request: RequestBuilder) -> Result<AarambhResponse, Box<dyn Error>> {
+        let request = request.build().map_err(|e| AarambhNetError::new("build request", e))?;
+        let (method, url, started) = (request.method().clone(), request.url().clone(), Instant::now());
+        self.send_with_retries(request).await.map_err(|e| -> Box<dyn Error> {
+            Box::new(AarambhNetError::new(method.as_str(), e).with_target(url).with_elapsed(started.elapsed()))
+        })
+    }
+
+    /// The function `send_with_retries` checks the destination against the egress policy, if any,
+    /// pinning the addresses of its host for all attempts, and sends the request.
+    async fn send_with_retries(&self, request: Request) -> Result<AarambhResponse, BoxError> {
+        match &self.egress {
+            Some(policy) => {
+                let url = request.url().clone();
+                policy.scope(&url, self.send_attempts(request)).await
+            }
+            None => self.send_attempts(request).await,
+        }
+    }
+
+    /// The function `send_attempts` applies the matching endpoint profile and sends the request,
+    /// retrying transport errors and 5xx responses as often as the profile allows.
+    async fn send_attempts(&self, mut request: Request) -> Result<AarambhResponse, BoxError> {
+        if let Some(limit) = self.max_request_body {
+            let size = request.body().and_then(|body| body.as_bytes()).map_or(0, |body| body.len() as u64);
+            if size > limit {
+                return Err(Box::new(BodyTooLargeError { direction: BodyDirection::Request, limit }));
+            }
+        }
+        if !request.headers().contains_key(ACCEPT_ENCODING) {
+            if let Some(value) = encoding::accept_encoding_header(&self.accept_encodings) {
+                request.headers_mut().insert(ACCEPT_ENCODING, value);
+            }
+        }
+
+        let profile = self.profile_for(request.url());
+        #[cfg(not(target_arch = "wasm32"))]
+        if let Some(timeout) = profile.and_then(|p| p.timeout) {
+            *request.timeout_mut() = Some(timeout);
+        }
+        let retries = profile.map_or(0, |p| p.retries);
+
+        let mut attempt = 0;
+        loop {
+            if let Some(limiter) = profile.and_then(|p| p.rate_limiter.as_ref()) {
+                limiter.acquire(1.0).await;
+            }
+
+            // The result is scoped to this block so it is dropped before sleeping.
+            let next = {
+                let next = if attempt < retries { request.try_clone() } else { None };
+                let result = self.send_once(request).await;
+                let retryable = match &result {
+                    Ok(response) => response.is_server_error(),
+                    Err(_) => true,
+                };
+                if let (Some(budget), false) = (&self.retry_budget, retryable) {
+                    budget.record_success();
+                }
+                let allowed = || self.retry_budget.as_ref().is_none_or(|budget| budget.try_withdraw());
+                match next {
+                    Some(next) if retryable && allowed() => next,
+                    _ => return result,
+                }
+            };
+
+            sleep(profile.map_or_else(Default::default, |p| p.retry_delay(attempt))).await;
+            attempt += 1;
+            request = next;
+        }
+    }
+
+    /// The function `send_once` performs a single attempt of a request, going through the cassette
+    /// when one is attached and archiving the exchange when HAR recording is enabled.
+    async fn send_once(&self, request: Request) -> Result<AarambhResponse, BoxError> {
+        if self.curl_debug {
+            tracing::debug!(target: "aarambh_net::curl", "{}", request.to_curl());
+        }
+        #[cfg(not(target_arch = "wasm32"))]
+        let capture = self.har.as_ref().map(|har| har.capture(&request));
+
+        let source = self.address_pool.as_ref().map(|pool| {
+            let addr = pool.next();
+            let client = self.source_clients.iter().find(|(a, _)| *a == addr).map(|(_, c)| c);
+            (pool, addr, client.unwrap_or(&self.client))
+        });
+        let client = source.map_or(&self.client, |(_, _, client)| client);
+
+        let audited = self.audit.observe_request(&request);
+        let started = Instant::now();
+        #[cfg(not(target_arch = "wasm32"))]
+        let result = match &self.cassette {
+            Some(cassette) => cassette.execute(request, |request| self.dispatch(client, request)).await,
+            None => self.dispatch(client, request).await,
+        };
+        #[cfg(target_arch = "wasm32")]
+        let result = self.dispatch(client, request).await;
+        self.metrics.observe(result.as_ref().ok().map(|r| r.status()), started.elapsed());
+        if let Some(audited) = audited {
+            self.audit.observe_response(audited, &result, started.elapsed());
+        }
+
+        if let Some((pool, addr, _)) = source {
+            match &result {
+                Ok(_) => pool.record_success(addr),
+                Err(_) => pool.record_failure(addr),
+            }
+        }
+
+        #[cfg(not(target_arch = "wasm32"))]
+        let result = match (&self.har, capture) {
+            (Some(har), Some(capture)) => har.finish(capture, result).await,
+            _ => result,
+        };
+        result.map(AarambhResponse::new)
+    }
+
+    /// The function `build_request` creates a request to `url` carrying the per-request `headers`,
+    /// then the headers of the endpoint profile matching `url`, then the default headers, each source
+    /// only adding the header names the earlier ones did not set.
+    ///
+    /// The default headers are read from a shared snapshot, without holding the lock while the request
+    /// is built. Without per-request or profile headers nothing is merged, but the request is not
+    /// allocation-free: `reqwest` takes ownership of the headers of a request, so it gets a clone of
+    /// the snapshot, whose names and values are shared by reference count. Otherwise the caller's map
+    /// is reused and only the missing headers are added to it. `benches/headers.rs` measures both
+    /// against a client without default headers.
+    ///
+    /// # Arguments:
+    ///
+    /// * `method`: The HTTP method of the request.
+    /// * `url`: The URL of the request, also used to find its endpoint profile.
+    /// * `headers`: The optional per-request headers, which take precedence over all others.
+    fn build_request(&self, method: Method, url: Url, headers: Option<HeaderMap>) -> RequestBuilder {
+        let profile = self.profile_for(&url).map(|profile| &profile.headers).filter(|headers| !headers.is_empty());
+        let defaults = Arc::clone(&self.default_headers.read().unwrap());
+        let merged = match (headers, profile) {
+            (None, None) => HeaderMap::clone(&defaults),
+            (headers, profile) => {
+                let mut merged = headers.unwrap_or_default();
+                merged.reserve(defaults.len() + profile.map_or(0, HeaderMap::len));
+                for layer in profile.into_iter().chain([&*defaults]) {
+                    for name in layer.keys() {
+                        if !merged.contains_key(name) {
+                            for value in layer.get_all(name) {
+                                merged.append(name.clone(), value.clone());
+                            }
+                        }
+                    }
+                }
+                merged
+            }
+        };
+
+        let mut request = Request::new(method, url);
+        *request.headers_mut() = merged;
+        RequestBuilder::from_parts(self.client.clone(), request)
+    }
+
+    /// This Rust function performs an asynchronous HTTP GET request with specified headers.
+    /// 
+    /// # Arguments:
+    /// 
+    /// * `endpoint`: The `endpoint` parameter in the `get` function represents the specific endpoint or
+    ///   path that you want to access on the base URL. It is a string that typically corresponds to a
+    ///   specific resource or action on the server.
+    /// * `headers`: The `headers` parameter in the `get` function is an optional `HeaderMap` type. It
+    ///   allows you to pass additional headers that will be merged with the default headers before making
+    ///   the HTTP request. If no additional headers are needed, you can pass `None` as the value for this
+    ///   parameter
+    /// 
+    /// # Returns:
+    /// 
+    /// The `get` function returns a `Result` containing an `AarambhResponse` if the request is successful, or a
+    /// `Box<dyn Error>` if an error occurs during the request.
+    pub async fn get(&self, endpoint: &str, headers: Option<HeaderMap>) -> Result<AarambhResponse, Box<dyn Error>> {
+        let url = self.base_url.join(endpoint)?;
+        self.send(self.build_request(Method::GET, url, headers)).await
+    }
+
+    /// The function `post` sends an asynchronous POST request with optional headers and body, returning
+    /// a Result containing the response.
+    /// 
+    /// # Arguments:
+    /// 
+    /// * `endpoint`: The `endpoint` parameter in the `post` function represents the specific endpoint
+    ///   or route that you want to send a POST request to. It is a string that typically comes after the
+    ///   base URL of the API you are interacting with.
+    /// * `headers`: The `headers` parameter in the `post` function is an optional `HeaderMap` type. It
+    ///   allows you to pass additional headers to be included in the HTTP request. If you don't need to
+    ///   include any extra headers, you can pass `None` as the value for this parameter. If
+    /// * `body`: The `body` parameter in the `post` function represents the payload or data that you
+    ///   want to send in the HTTP request body. It is an optional parameter, meaning you can choose to
+    ///   include a body or not when making a POST request. If you provide a body, it should be a string
+    /// 
+    /// # Returns:
+    /// 
+    /// The `post` function returns a `Result` containing an `AarambhResponse` if the operation is successful,
+    /// or a `Box` containing a dynamic error trait object if an error occurs.
+    pub async fn post(&self, endpoint: &str, headers: Option<HeaderMap>, body: Option<&str>) -> Result<AarambhResponse, Box<dyn Error>> {
+        let url = self.base_url.join(endpoint)?;
+        let mut request = self.build_request(Method::POST, url, headers);
+
+        // If a body is provided, add it to the request
+        if let Some(b) = body {
+            request = request.body(b.to_string());
+        }
+
+        self.send(request).await
+    }
+
+    /// The function `put` sends an HTTP PUT request with optional headers and body, and returns the
+    /// response asynchronously.
+    /// 
+    /// # Arguments:
+    /// 
+    /// * `endpoint`: The `endpoint` parameter is a string that represents the specific endpoint or
+    ///   route that you want to send a PUT request to. It is typically a part of the URL path after the
+    ///   base URL.
+    /// * `headers`: The `headers` parameter is an optional `HeaderMap` type, which represents a
+    ///   collection of HTTP headers. It allows you to pass additional headers along with the request. If
+    ///   no headers are needed, you can pass `None` as the value for this parameter.
+    /// * `body`: The `body` parameter in the `put` function is an optional reference to a string. It
+    ///   represents the body content that will be sent in the HTTP PUT request. If a value is provided
+    ///   for the `body` parameter, it will be included in the request; otherwise, the request will be
+    /// 
+    /// # Returns:
+    /// 
+    /// The `put` function returns a `Result` containing an `AarambhResponse` if the operation is successful, or
+    /// a `Box` containing a trait object that implements the `Error` trait if an error occurs.
+    pub async fn put(&self, endpoint: &str, headers: Option<HeaderMap>, body: Option<&str>) -> Result<AarambhResponse, Box<dyn Error>> {
+        let url = self.base_url.join(endpoint)?;
+        let mut request = self.build_request(Method::PUT, url, headers);
+
+        if let Some(b) = body {
+            request = request.body(b.to_string());
+        }
+
+        self.send(request).await
+    }
+
+    /// The function `delete` sends a DELETE request to a specified endpoint with optional headers and
+    /// returns the response asynchronously.
+    /// 
+    /// # Arguments:
+    /// 
+    /// * `endpoint`: The `endpoint` parameter in the `delete` function is a reference to a string that
+    ///   represents the specific endpoint or resource path that you want to delete on the server. It is
+    ///   used to construct the complete URL for the DELETE request.
+    /// * `headers`: The `headers` parameter in the `delete` function is an optional `HeaderMap` type.
+    ///   It allows you to pass additional headers to be included in the HTTP request. If no headers are
+    ///   needed, you can pass `None` as the value for this parameter. If you do need to include
+    /// 
+    /// # Returns:
+    /// 
+    /// The `delete` function returns a `Result` containing an `AarambhResponse` if the operation is successful,
+    /// or a `Box<dyn Error>` if an error occurs.
+    pub async fn delete(&self, endpoint: &str, headers: Option<HeaderMap>) -> Result<AarambhResponse, Box<dyn Error>> {
+        let url = self.base_url.join(endpoint)?;
+        self.send(self.build_request(Method::DELETE, url, headers)).await
+    }
+
+    /// This Rust function sends a HEAD request to a specified endpoint with optional headers and
+    /// returns the response asynchronously.
+    /// 
+    /// # Arguments:
+    /// 
+    /// * `endpoint`: The `endpoint` parameter in the `head` function represents the specific path or
+    ///   resource on the server that you want to send a HTTP HEAD request to. It is typically a string
+    ///   that specifies the endpoint URL relative to the base URL of the API.
+    /// * `headers`: The `headers` parameter in the `head` function is an optional `HeaderMap` type. It
+    ///   allows you to pass additional headers to be included in the HTTP request. If you don't need to
+    ///   include any extra headers, you can pass `None` as the value for this parameter. If
+    /// 
+    /// # Returns:
+    /// 
+    /// The `head` function returns a `Result` containing an `AarambhResponse` if the operation is successful,
+    /// or a `Box<dyn Error>` if an error occurs.
+    pub async fn head(&self, endpoint: &str, headers: Option<HeaderMap>) -> Result<AarambhResponse, Box<dyn Error>> {
+        let url = self.base_url.join(endpoint)?;
+        self.send(self.build_request(Method::HEAD, url, headers)).await
+    }
+
+    /// The function `patch` sends a PATCH request to a specified endpoint with optional headers and body,
+    /// and returns the response asynchronously.
+    /// 
+    /// # Arguments:
+    /// 
+    /// * `endpoint`: The `endpoint` parameter in the `patch` function is a string that represents the
+    ///   specific endpoint or route that you want to send a PATCH request to. It is typically a part of the
+    ///   URL after the base URL.
+    /// * `headers`: The `headers` parameter in the `patch` function is an optional `HeaderMap` type. It
+    ///   allows you to pass additional headers to be included in the HTTP request. If you don't need to
+    ///   include any extra headers, you can pass `None` as the value for this parameter. If
+    /// * `body`: The `body` parameter in the `patch` function is an optional reference to a string
+    ///   (`Option<&str>`). It represents the body content that will be sent in the HTTP request when making a
+    ///   PATCH request to the specified `endpoint`. If a value is provided for the `body`, it will
+    /// 
+    /// # Returns:
+    /// 
+    /// The `patch` function returns a `Result` containing either an `AarambhResponse` or a boxed trait object
+    /// implementing the `Error` trait.
+    pub async fn patch(&self, endpoint: &str, headers: Option<HeaderMap>, body: Option<&str>) -> Result<AarambhResponse, Box<dyn Error>> {
+        let url = self.base_url.join(endpoint)?;
+        let mut request = self.build_request(Method::PATCH, url, headers);
+
+        if let Some(b) = body {
+            request = request.body(b.to_string());
+        }
+
+        self.send(request).await
+    }
+
+    /// The function `forward` sends a request with a streamed body to `path_and_query` below the
+    /// base URL, keeping the path of the base URL, for the reverse proxy of `HttpServer`.
+    #[cfg(not(target_arch = "wasm32"))]
+    pub(crate) async fn forward(
+        &self,
+        method: Method,
+        path_and_query: &str,
+        headers: HeaderMap,
+        body: Option<reqwest::Body>,
+    ) -> Result<AarambhResponse, Box<dyn Error>> {
+        let (path, query) = match path_and_query.split_once('?') {
+            Some((path, query)) => (path, Some(query)),
+            None => (path_and_query, None),
+        };
+        let mut url = self.base_url.clone();
+        url.set_path(&format!("{}{}", self.base_url.path().trim_end_matches('/'), path));
+        url.set_query(query);
+        let mut request = self.build_request(method, url, Some(headers));
+        if let Some(body) = body {
+            request = request.body(body);
+        }
+        self.send(request).await
+    }
+
+    /// The function `request` sends a request with an arbitrary method, for callers that replay
+    /// stored requests.
+    pub(crate) async fn request(&self, method: Method, endpoint: &str, headers: Option<HeaderMap>, body: Option<&str>) -> Result<AarambhResponse, Box<dyn Error>> {
+        let url = self.base_url.join(endpoint)?;
+        let mut request = self.build_request(method, url, headers);
+
+        if let Some(b) = body {
+            request = request.body(b.to_string());
+        }
+
+        self.send(request).await
+    }
+}
+
+
+impl HttpClientBuilder {
+    /// The function `default_headers` sets the headers sent with every request.
+    pub fn default_headers(mut self, headers: HeaderMap) -> Self {
+        self.default_headers = Some(headers);
+        self
+    }
+
+    /// The function `accept_encodings` selects which content encodings are advertised in
+    /// `Accept-Encoding` and decoded. An empty list advertises nothing, so servers reply uncompressed.
+    ///
+    /// # Arguments:
+    ///
+    /// * `encodings`: The accepted encodings, in order of preference.
+    pub fn accept_encodings(mut self, encodings: &[ContentEncoding]) -> Self {
+        self.accept_encodings = encodings.to_vec();
+        self
+    }
+
+    /// The function `auto_decompress` toggles automatic decoding of compressed bodies. With it
+    /// disabled the accepted encodings are still advertised, but bodies are passed through exactly as
+    /// received together with their `Content-Encoding` header, which is what proxies need. Browsers
+    /// always decode bodies, so it is not available on `wasm32`.
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn auto_decompress(mut self, enabled: bool) -> Self {
+        self.auto_decompress = enabled;
+        self
+    }
+
+    /// The function `endpoint_profile` registers overrides for every request whose path matches
+    /// `pattern`. When several patterns match, the first one registered wins.
+    ///
+    /// # Arguments:
+    ///
+    /// * `pattern`: A path such as `/export`, matching that path and everything below it, or a glob
+    ///   such as `/api/*/search` where `*` matches any characters.
+    /// * `profile`: The `EndpointProfile` with the timeout, retries, headers and rate limit to apply.
+    pub fn endpoint_profile(mut self, pattern: &str, profile: EndpointProfile) -> Self {
+        self.profiles.push((pattern.to_string(), profile));
+        self
+    }
+
+    /// The function `max_idle_per_host` sets how many idle connections are kept open per host for
+    /// reuse.
+    pub fn max_idle_per_host(mut self, max: usize) -> Self {
+        self.pool.max_idle_per_host = Some(max);
+        self
+    }
+
+    /// The function `pool_idle_timeout` sets how long an idle connection is kept before it is
+    /// closed. `None` keeps idle connections open indefinitely.
+    pub fn pool_idle_timeout(mut self, timeout: Option<Duration>) -> Self {
+        self.pool.idle_timeout = Some(timeout);
+        self
+    }
+
+    /// The function `max_concurrent_requests` caps the number of requests in flight at once, across
+    /// all hosts. Further requests wait until an earlier one completes, which happens once its
+    /// response body has been read or dropped. It limits requests, not connections: requests
+    /// multiplexed over one HTTP/2 connection each count, and idle pooled connections do not.
+    pub fn max_concurrent_requests(mut self, max: usize) -> Self {
+        self.pool.max_concurrent_requests = Some(max.max(1));
+        self
+    }
+
+    /// The function `max_request_body_size` refuses to send request bodies larger than `max` bytes;
+    /// such requests fail with a `BodyTooLargeError` before anything is sent.
+    pub fn max_request_body_size(mut self, max: u64) -> Self {
+        self.max_request_body = Some(max);
+        self
+    }
+
+    /// The function `max_response_body_size` caps response bodies at `max` bytes, measured after
+    /// decompression. A response announcing a larger `Content-Length` fails right away; otherwise the
+    /// transfer is aborted with a `BodyTooLargeError` once the limit is crossed while reading the body.
+    pub fn max_response_body_size(mut self, max: u64) -> Self {
+        self.max_response_body = Some(max);
+        self
+    }
+
+    /// The function `verbose` turns on recording a `Timeline` for every request, the structured
+    /// equivalent of `curl -v`. It lists the DNS lookup, connect and TLS handshake (for new
+    /// connections), the time the request was written, the first response byte and the release of
+    /// the body. The timeline is available
+    /// from `AarambhResponse::timeline`, or from the `TimelineError` a failed request returns.
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn verbose(mut self, enabled: bool) -> Self {
+        self.verbose = enabled;
+        self
+    }
+
+    /// The function `retry_budget` makes the endpoint profile retries draw from `budget`, which is
+    /// usually shared by all clients of an application (see `RetryBudget::global`). Successful
+    /// requests refill it; when it is empty the failed response or error is returned without retrying.
+    pub fn retry_budget(mut self, budget: Arc<RetryBudget>) -> Self {
+        self.retry_budget = Some(budget);
+        self
+    }
+
+    /// The function `max_download_rate` caps the response bodies of all requests, together, to
+    /// `bytes_per_second`, so large background transfers leave room on the link. Bodies are read
+    /// more slowly rather than failing. Clones of the client share the limit. Bandwidth limits are not
+    /// applied on `wasm32`, where the browser handles the transfer.
+    pub fn max_download_rate(mut self, bytes_per_second: u64) -> Self {
+        self.download_rate = Some(bytes_per_second);
+        self
+    }
+
+    /// The function `max_upload_rate` caps the request bodies of all requests, together, to
+    /// `bytes_per_second`. The body is still announced with its full `Content-Length`.
+    pub fn max_upload_rate(mut self, bytes_per_second: u64) -> Self {
+        self.upload_rate = Some(bytes_per_second);
+        self
+    }
+
+    /// The function `follow_redirects` sets whether redirects are followed, up to ten in a row, or
+    /// returned to the caller as they are. Browsers always follow redirects, so it is not available
+    /// on `wasm32`.
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn follow_redirects(mut self, follow: bool) -> Self {
+        self.follow_redirects = follow;
+        self
+    }
+
+    /// The function `egress_policy` restricts the destinations the client may send requests to.
+    /// Requests to refused destinations, and redirects to them, fail with a `PolicyViolation` before
+    /// anything is sent, which makes it safe to fetch URLs supplied by users.
+    pub fn egress_policy(mut self, policy: EgressPolicy) -> Self {
+        self.egress = Some(Arc::new(policy));
+        self
+    }
+
+    /// The function `build` creates the configured `HttpClient`.
+    ///
+    /// # Returns:
+    ///
+    /// The `build` function returns a `Result` containing the `HttpClient`, or an error if the base URL
+    /// cannot be parsed or the underlying client cannot be created.
+    pub fn build(self) -> Result<HttpClient, Box<dyn Error>> {
+        #[cfg(not(target_arch = "wasm32"))]
+        let client = client_builder(&self.pool, self.verbose, self.follow_redirects, self.egress.clone()).build()?;
+        #[cfg(target_arch = "wasm32")]
+        let client = client_builder(&self.pool).build()?;
+        Ok(HttpClient {
+            base_url: Url::parse(&self.base_url)?,
+            default_headers: Arc::new(RwLock::new(Arc::new(self.default_headers.unwrap_or_default()))),
+            client,
+            #[cfg(not(target_arch = "wasm32"))]
+            cassette: None,
+            #[cfg(not(target_arch = "wasm32"))]
+            har: None,
+            curl_debug: false,
+            address_pool: None,
+            source_clients: Vec::new(),
+            accept_encodings: self.accept_encodings,
+            #[cfg(not(target_arch = "wasm32"))]
+            auto_decompress: self.auto_decompress,
+            profiles: self.profiles,
+            connections: Arc::new(ConnectionTracker::new(self.pool)),
+            max_request_body: self.max_request_body,
+            max_response_body: self.max_response_body,
+            #[cfg(not(target_arch = "wasm32"))]
+            verbose: self.verbose,
+            #[cfg(not(target_arch = "wasm32"))]
+            follow_redirects: self.follow_redirects,
+            metrics: Arc::default(),
+            retry_budget: self.retry_budget,
+            download_limit: self.download_rate.map(throttle::bandwidth_bucket),
+            upload_limit: self.upload_rate.map(throttle::bandwidth_bucket),
+            audit: Arc::default(),
+            egress: self.egress,
+        })
+    }
+}
+
+/// The function `client_builder` prepares a `reqwest` client builder with the pool settings, in
+/// verbose mode the resolver that records DNS lookups, and with an egress policy the resolver
+/// checking and pinning addresses and a redirect policy refusing to follow redirects to
+/// destinations the policy refuses. Without `follow_redirects`, redirects are returned as they are.
+#[cfg(not(target_arch = "wasm32"))]
+fn client_builder(
+    pool: &PoolConfig,
+    verbose: bool,
+    follow_redirects: bool,
+    egress: Option<Arc<EgressPolicy>>,
+) -> ClientBuilder {
+    let mut builder = pool.apply(Client::builder());
+    if let Some(policy) = &egress {
+        builder = builder.dns_resolver(Arc::new(PolicyResolver(policy.clone())));
+    } else if verbose {
+        builder = builder.dns_resolver(Arc::new(TimelineResolver));
+    }
+    if verbose {
+        builder = builder.connector_layer(TimelineConnectLayer).tls_info(true);
+    }
+    if !follow_redirects {
+        builder = builder.redirect(redirect::Policy::none());
+    } else if let Some(policy) = egress {
+        let default = redirect::Policy::default();
+        builder = builder.redirect(redirect::Policy::custom(move |attempt| match policy.check_url(attempt.url()) {
+            Ok(_) => default.redirect(attempt),
+            Err(violation) => attempt.error(violation),
+        }));
+    }
+    builder
+}
+
+/// On `wasm32` requests go through the browser's `fetch`, which resolves host names itself.
+#[cfg(target_arch = "wasm32")]
+fn client_builder(pool: &PoolConfig) -> ClientBuilder {
+    pool.apply(Client::builder())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{
+        http::testing::serve,
+        timeline::{TimelineError, TimelineEventKind},
+    };
+    use std::time::Duration;
+
+    fn setup_client() -> HttpClient {
+        let base_url = "https://httpbin.org";
+        HttpClient::new(base_url, None).unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_get_request() {
+        let client = setup_client();
+        let endpoint = "/get";
+
+        match client.get(endpoint, None).await {
+            Ok(response) => {
+                assert_eq!(response.status(), 200); // Assert that the status is 200 OK
+                let body = response.text().await.unwrap();
+                assert!(body.contains("\"url\": \"https://httpbin.org/get\"")); // Assert the response contains the expected URL
+                println!("GET Response: {}", body);
+            },
+            Err(e) => panic!("GET request failed: {}", e),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_endpoint_profile_retries_server_errors() {
+        let unavailable = "HTTP/1.1 503 Service Unavailable\r\ncontent-length: 0\r\nconnection: close\r\n\r\n";
+        let ok = "HTTP/1.1 200 OK\r\ncontent-length: 2\r\nconnection: close\r\n\r\nok";
+        let (addr, server_task) = serve(vec![unavailable, ok]).await;
+
+        let client = HttpClient::builder(&format!("http://{}", addr))
+            .endpoint_profile("/search", EndpointProfile::new().retries(1, Duration::from_millis(10)))
+            .build()
+            .unwrap();
+        let response = client.get("/search", None).await.unwrap();
+        assert_eq!(response.status(), 200);
+        assert_eq!(response.text().await.unwrap(), "ok");
+        server_task.await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_clones_share_pool_and_headers() {
+        fn assert_send_sync<T: Send + Sync + Clone>() {}
+        assert_send_sync::<HttpClient>();
+
+        let ok = "HTTP/1.1 200 OK\r\ncontent-length: 2\r\nconnection: close\r\n\r\nok";
+        let (addr, server_task) = serve(vec![ok, ok]).await;
+        let client = HttpClient::new(&format!("http://{}", addr), None).unwrap().shared();
+        let clone = (*client).clone();
+        clone.add_default_header("x-shared", "yes").unwrap();
+        assert_eq!(client.default_headers()["x-shared"], "yes");
+
+        let task = tokio::spawn(async move { clone.get("/", None).await.unwrap().status() });
+        assert_eq!(task.await.unwrap(), 200);
+        assert_eq!(client.get("/", None).await.unwrap().status(), 200);
+        server_task.await.unwrap();
+        assert_eq!(client.pool_stats().total_requests, 2);
+    }
+
+    #[test]
+    fn test_request_futures_are_send() {
+        fn assert_send<T: Send>(_: T) {}
+        let client = HttpClient::new("http://localhost", None).unwrap();
+        assert_send(client.get("/", None));
+        assert_send(client.post("/", None, Some("body")));
+        assert_send(client.put("/", None, None));
+        assert_send(client.patch("/", None, None));
+        assert_send(client.delete("/", None));
+        assert_send(client.head("/", None));
+    }
+
+    #[test]
+    fn test_runtime_default_headers() {
+        let client = HttpClient::new("http://localhost", None).unwrap();
+        client.set_user_agent("my-app/1.0").unwrap();
+        client.add_default_header("x-tenant", "blue").unwrap();
+        let url = Url::parse("http://localhost/").unwrap();
+        let request = client.build_request(Method::GET, url.clone(), None).build().unwrap();
+        assert_eq!(request.headers()[USER_AGENT], "my-app/1.0");
+        assert_eq!(request.headers()["x-tenant"], "blue");
+
+        let mut extra = HeaderMap::new();
+        extra.append("x-tenant", HeaderValue::from_static("red"));
+        extra.append("x-tenant", HeaderValue::from_static("green"));
+        let request = client.build_request(Method::GET, url, Some(extra)).build().unwrap();
+        let tenants: Vec<_> = request.headers().get_all("x-tenant").iter().collect();
+        assert_eq!(tenants, ["red", "green"]);
+        assert_eq!(request.headers()[USER_AGENT], "my-app/1.0");
+
+        assert!(client.remove_default_header("x-tenant").is_some());
+        assert!(client.add_default_header("bad header", "x").is_err());
+        assert!(!client.default_headers().contains_key("x-tenant"));
+    }
+
+    #[tokio::test]
+    async fn test_verbose_timeline() {
+        let ok = "HTTP/1.1 200 OK\r\ncontent-length: 2\r\nconnection: close\r\n\r\nok";
+        let (addr, server_task) = serve(vec![ok]).await;
+        let client = HttpClient::builder(&format!("http://localhost:{}", addr.port())).verbose(true).build().unwrap();
+
+        let response = client.get("/", None).await.unwrap();
+        let timeline = response.timeline().unwrap();
+        assert_eq!(response.text().await.unwrap(), "ok");
+        let kinds: Vec<_> = timeline.events().into_iter().map(|e| e.kind).collect();
+        assert_eq!(
+            kinds,
+            [
+                TimelineEventKind::Resolve,
+                TimelineEventKind::Connect,
+                TimelineEventKind::Write,
+                TimelineEventKind::FirstByte,
+                TimelineEventKind::Close
+            ]
+        );
+        server_task.await.unwrap();
+
+        let err = client.get("/", None).await.unwrap_err();
+        let err = err.downcast_ref::<AarambhNetError>().unwrap();
+        assert_eq!(err.operation(), "GET");
+        assert!(err.cause::<TimelineError>().is_some());
+    }
+
+    #[tokio::test]
+    async fn test_retry_budget_limits_retries() {
+        let unavailable = "HTTP/1.1 503 Service Unavailable\r\ncontent-length: 0\r\nconnection: close\r\n\r\n";
+        let (addr, server_task) = serve(vec![unavailable, unavailable, unavailable]).await;
+        let budget = Arc::new(RetryBudget::new(0.1, 0.0));
+        let client = HttpClient::builder(&format!("http://{}", addr))
+            .endpoint_profile("/", EndpointProfile::new().retries(5, Duration::from_millis(1)))
+            .retry_budget(budget.clone())
+            .build()
+            .unwrap();
+
+        // The budget holds a single retry, so the first call makes two attempts and the second one.
+        assert_eq!(client.get("/a", None).await.unwrap().status(), 503);
+        assert_eq!(client.get("/b", None).await.unwrap().status(), 503);
+        server_task.await.unwrap();
+        assert_eq!(client.pool_stats().total_requests, 3);
+    }
+}
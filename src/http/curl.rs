@@ -0,0 +1,71 @@
+use reqwest::{Method, Request};
+
+/// The `ToCurl` trait renders an outgoing request as an equivalent `curl` command line so a failing
+/// request can be reproduced from a shell.
+pub trait ToCurl {
+    /// The function `to_curl` returns a single-line `curl` invocation with the method, URL, headers
+    /// and body of the request, quoted for POSIX shells.
+    fn to_curl(&self) -> String;
+}
+
+impl ToCurl for Request {
+    fn to_curl(&self) -> String {
+        let mut parts = vec!["curl".to_string()];
+
+        match *self.method() {
+            Method::GET => {}
+            Method::HEAD => parts.push("--head".to_string()),
+            ref method => {
+                parts.push("-X".to_string());
+                parts.push(method.to_string());
+            }
+        }
+
+        parts.push(shell_quote(self.url().as_str()));
+
+        for (name, value) in self.headers() {
+            let header = format!("{}: {}", name, String::from_utf8_lossy(value.as_bytes()));
+            parts.push("-H".to_string());
+            parts.push(shell_quote(&header));
+        }
+
+        if let Some(body) = self.body() {
+            match body.as_bytes() {
+                Some(bytes) => {
+                    parts.push("--data-raw".to_string());
+                    parts.push(shell_quote(&String::from_utf8_lossy(bytes)));
+                }
+                // Streaming bodies cannot be inspected without consuming them.
+                None => parts.push("--data-binary @-".to_string()),
+            }
+        }
+
+        parts.join(" ")
+    }
+}
+
+/// The function `shell_quote` wraps `value` in single quotes, escaping embedded single quotes.
+fn shell_quote(value: &str) -> String {
+    format!("'{}'", value.replace('\'', r"'\''"))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use reqwest::Client;
+
+    #[test]
+    fn test_to_curl() {
+        let request = Client::new()
+            .post("https://example.com/items?q=1")
+            .header("x-token", "it's-secret")
+            .body(r#"{"name":"a"}"#)
+            .build()
+            .unwrap();
+
+        assert_eq!(
+            request.to_curl(),
+            r#"curl -X POST 'https://example.com/items?q=1' -H 'x-token: it'\''s-secret' --data-raw '{"name":"a"}'"#
+        );
+    }
+}
@@ -0,0 +1,144 @@
+#[cfg(not(target_arch = "wasm32"))]
+use async_compression::tokio::bufread::{BrotliDecoder, DeflateDecoder, GzipDecoder, ZstdDecoder};
+#[cfg(not(target_arch = "wasm32"))]
+use futures_util::TryStreamExt;
+use reqwest::header::HeaderValue;
+#[cfg(not(target_arch = "wasm32"))]
+use reqwest::{
+    header::{CONTENT_ENCODING, CONTENT_LENGTH},
+    Body, Response,
+};
+#[cfg(not(target_arch = "wasm32"))]
+use super::BoxError;
+#[cfg(not(target_arch = "wasm32"))]
+use std::io;
+#[cfg(not(target_arch = "wasm32"))]
+use tokio::io::AsyncRead;
+#[cfg(not(target_arch = "wasm32"))]
+use tokio_util::io::{ReaderStream, StreamReader};
+
+/// The `ContentEncoding` enum lists the response content encodings `HttpClient` can negotiate and
+/// decode.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ContentEncoding {
+    Gzip,
+    Brotli,
+    Zstd,
+    Deflate,
+}
+
+/// The `OriginalContentEncoding` response extension remembers the `Content-Encoding` a body was
+/// decoded from, because the header itself is removed once the body is decoded.
+#[cfg(not(target_arch = "wasm32"))]
+#[derive(Debug, Clone)]
+pub(crate) struct OriginalContentEncoding(pub(crate) String);
+
+impl ContentEncoding {
+    /// The function `all` returns every supported encoding in preference order.
+    pub fn all() -> Vec<ContentEncoding> {
+        vec![ContentEncoding::Gzip, ContentEncoding::Brotli, ContentEncoding::Zstd, ContentEncoding::Deflate]
+    }
+
+    /// The function `as_str` returns the token used for the encoding in HTTP headers.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ContentEncoding::Gzip => "gzip",
+            ContentEncoding::Brotli => "br",
+            ContentEncoding::Zstd => "zstd",
+            ContentEncoding::Deflate => "deflate",
+        }
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    pub(crate) fn from_token(token: &str) -> Option<ContentEncoding> {
+        match token.trim().to_ascii_lowercase().as_str() {
+            "gzip" | "x-gzip" => Some(ContentEncoding::Gzip),
+            "br" => Some(ContentEncoding::Brotli),
+            "zstd" => Some(ContentEncoding::Zstd),
+            "deflate" => Some(ContentEncoding::Deflate),
+            _ => None,
+        }
+    }
+}
+
+/// The function `accept_encoding_header` renders the `Accept-Encoding` value advertising
+/// `encodings`, or `None` when the list is empty.
+pub(crate) fn accept_encoding_header(encodings: &[ContentEncoding]) -> Option<HeaderValue> {
+    if encodings.is_empty() {
+        return None;
+    }
+    let value = encodings.iter().map(|e| e.as_str()).collect::<Vec<_>>().join(", ");
+    HeaderValue::from_str(&value).ok()
+}
+
+/// The function `decode` transparently decompresses `response` when its `Content-Encoding` is one of
+/// the `accepted` encodings. The body is decoded as it streams; the original encoding is kept in the
+/// `OriginalContentEncoding` extension. Responses in any other encoding are returned untouched.
+///
+/// It is not available on `wasm32`, where the browser decodes bodies before they reach the client.
+#[cfg(not(target_arch = "wasm32"))]
+pub(crate) fn decode(response: Response, accepted: &[ContentEncoding]) -> Result<Response, BoxError> {
+    let encoding = match response
+        .headers()
+        .get(CONTENT_ENCODING)
+        .and_then(|v| v.to_str().ok())
+        .and_then(ContentEncoding::from_token)
+    {
+        Some(encoding) if accepted.contains(&encoding) => encoding,
+        _ => return Ok(response),
+    };
+
+    let original = response.headers()[CONTENT_ENCODING].to_str()?.to_string();
+    let mut builder = http::Response::builder().status(response.status()).version(response.version());
+    for (name, value) in response.headers() {
+        if name != CONTENT_ENCODING && name != CONTENT_LENGTH {
+            builder = builder.header(name, value);
+        }
+    }
+    let mut extensions = response.extensions().clone();
+    extensions.insert(OriginalContentEncoding(original));
+
+    let reader = StreamReader::new(response.bytes_stream().map_err(io::Error::other));
+    let decoded: Box<dyn AsyncRead + Send + Sync + Unpin> = match encoding {
+        ContentEncoding::Gzip => Box::new(GzipDecoder::new(reader)),
+        ContentEncoding::Brotli => Box::new(BrotliDecoder::new(reader)),
+        ContentEncoding::Zstd => Box::new(ZstdDecoder::new(reader)),
+        ContentEncoding::Deflate => Box::new(DeflateDecoder::new(reader)),
+    };
+
+    let mut decoded = Response::from(builder.body(Body::wrap_stream(ReaderStream::new(decoded)))?);
+    *decoded.extensions_mut() = extensions;
+    Ok(decoded)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::http::AarambhResponse;
+    use async_compression::tokio::bufread::GzipEncoder;
+    use tokio::io::AsyncReadExt;
+
+    #[tokio::test]
+    async fn test_decode_keeps_original_encoding() {
+        let mut compressed = Vec::new();
+        GzipEncoder::new(&b"hello gzip"[..]).read_to_end(&mut compressed).await.unwrap();
+        let response = Response::from(
+            http::Response::builder()
+                .header(CONTENT_ENCODING, "gzip")
+                .body(compressed.clone())
+                .unwrap(),
+        );
+
+        let decoded = AarambhResponse::new(decode(response, &ContentEncoding::all()).unwrap());
+        assert_eq!(decoded.content_encoding(), Some("gzip"));
+        assert!(decoded.headers().get(CONTENT_ENCODING).is_none());
+        assert_eq!(decoded.text().await.unwrap(), "hello gzip");
+
+        let response = Response::from(
+            http::Response::builder().header(CONTENT_ENCODING, "gzip").body(compressed.clone()).unwrap(),
+        );
+        let raw = AarambhResponse::new(decode(response, &[ContentEncoding::Brotli]).unwrap());
+        assert_eq!(raw.content_encoding(), Some("gzip"));
+        assert_eq!(raw.bytes().await.unwrap(), compressed);
+    }
+}
@@ -0,0 +1,381 @@
+use super::BoxError;
+use crate::rt::{Instant, SystemTime, UNIX_EPOCH};
+use reqwest::{header::HeaderMap, Request, Response, Version};
+use serde::Serialize;
+use std::{
+    error::Error,
+    fs,
+    path::Path,
+    sync::Mutex,
+    time::Duration,
+};
+
+/// The default number of body bytes kept per request and response.
+const DEFAULT_MAX_BODY_SIZE: usize = 64 * 1024;
+
+/// The `HarRecorder` struct collects every request/response made by an `HttpClient` into an HTTP
+/// Archive (HAR 1.2) log that can be imported into browser devtools.
+///
+/// # Properties:
+///
+/// * `max_body_size`: The maximum number of bytes of each request and response body stored in the
+///   archive. Longer bodies are truncated and marked with a comment.
+/// * `entries`: The recorded HAR entries, in the order the requests completed.
+pub struct HarRecorder {
+    max_body_size: usize,
+    entries: Mutex<Vec<HarEntry>>,
+}
+
+/// The `HarCapture` struct holds what is known about a request before it is sent, so the entry
+/// can be completed once the response (or error) arrives.
+pub(crate) struct HarCapture {
+    started: SystemTime,
+    start: Instant,
+    request: HarRequest,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct HarLog {
+    version: &'static str,
+    creator: HarCreator,
+    entries: Vec<HarEntry>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+struct HarCreator {
+    name: &'static str,
+    version: &'static str,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct HarEntry {
+    started_date_time: String,
+    time: f64,
+    request: HarRequest,
+    response: HarResponse,
+    cache: serde_json::Value,
+    timings: HarTimings,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct HarRequest {
+    method: String,
+    url: String,
+    http_version: String,
+    cookies: Vec<HarNameValue>,
+    headers: Vec<HarNameValue>,
+    query_string: Vec<HarNameValue>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    post_data: Option<HarPostData>,
+    headers_size: i64,
+    body_size: i64,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct HarResponse {
+    status: u16,
+    status_text: String,
+    http_version: String,
+    cookies: Vec<HarNameValue>,
+    headers: Vec<HarNameValue>,
+    content: HarContent,
+    #[serde(rename = "redirectURL")]
+    redirect_url: String,
+    headers_size: i64,
+    body_size: i64,
+}
+
+#[derive(Debug, Clone, Serialize)]
+struct HarNameValue {
+    name: String,
+    value: String,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct HarPostData {
+    mime_type: String,
+    text: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    comment: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct HarContent {
+    size: i64,
+    mime_type: String,
+    text: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    comment: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+struct HarTimings {
+    blocked: f64,
+    dns: f64,
+    connect: f64,
+    ssl: f64,
+    send: f64,
+    wait: f64,
+    receive: f64,
+}
+
+impl Default for HarRecorder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl HarRecorder {
+    /// The function `new` creates an empty recorder that keeps up to 64 KiB of each body.
+    pub fn new() -> Self {
+        HarRecorder { max_body_size: DEFAULT_MAX_BODY_SIZE, entries: Mutex::new(Vec::new()) }
+    }
+
+    /// The function `max_body_size` sets how many bytes of each request and response body are stored.
+    ///
+    /// # Arguments:
+    ///
+    /// * `limit`: The maximum number of body bytes kept per message. `0` stores no body text at all.
+    pub fn max_body_size(mut self, limit: usize) -> Self {
+        self.max_body_size = limit;
+        self
+    }
+
+    /// The function `len` returns the number of recorded entries.
+    pub fn len(&self) -> usize {
+        self.entries.lock().unwrap().len()
+    }
+
+    /// The function `is_empty` returns `true` when nothing has been recorded yet.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// The function `clear` drops every recorded entry.
+    pub fn clear(&self) {
+        self.entries.lock().unwrap().clear();
+    }
+
+    /// The function `to_json` renders the recorded traffic as a HAR 1.2 JSON document.
+    pub fn to_json(&self) -> Result<String, Box<dyn Error>> {
+        let log = HarLog {
+            version: "1.2",
+            creator: HarCreator { name: env!("CARGO_PKG_NAME"), version: env!("CARGO_PKG_VERSION") },
+            entries: self.entries.lock().unwrap().clone(),
+        };
+        Ok(serde_json::to_string_pretty(&serde_json::json!({ "log": log }))?)
+    }
+
+    /// The function `save` writes the HAR document to `path`.
+    pub fn save(&self, path: impl AsRef<Path>) -> Result<(), Box<dyn Error>> {
+        fs::write(path, self.to_json()?)?;
+        Ok(())
+    }
+
+    /// The function `capture` snapshots an outgoing request right before it is sent.
+    pub(crate) fn capture(&self, request: &Request) -> HarCapture {
+        let url = request.url();
+        let body = request.body().and_then(|b| b.as_bytes());
+        let post_data = body.map(|bytes| {
+            let (text, comment) = self.truncate(bytes);
+            HarPostData { mime_type: Self::mime_type(request.headers()), text, comment }
+        });
+
+        HarCapture {
+            started: SystemTime::now(),
+            start: Instant::now(),
+            request: HarRequest {
+                method: request.method().to_string(),
+                url: url.to_string(),
+                http_version: Self::version(request.version()),
+                cookies: Vec::new(),
+                headers: Self::name_values(request.headers()),
+                query_string: url
+                    .query_pairs()
+                    .map(|(name, value)| HarNameValue { name: name.into_owned(), value: value.into_owned() })
+                    .collect(),
+                post_data,
+                headers_size: -1,
+                body_size: body.map_or(0, |b| b.len() as i64),
+            },
+        }
+    }
+
+    /// The function `finish` completes a captured request with its outcome. Successful responses are
+    /// buffered so their body can be archived, and an equivalent response is handed back to the caller.
+    pub(crate) async fn finish(
+        &self,
+        capture: HarCapture,
+        result: Result<Response, BoxError>,
+    ) -> Result<Response, BoxError> {
+        let wait = capture.start.elapsed();
+        let response = match result {
+            Ok(response) => response,
+            Err(e) => {
+                let har_response = HarResponse {
+                    status: 0,
+                    status_text: e.to_string(),
+                    http_version: String::new(),
+                    cookies: Vec::new(),
+                    headers: Vec::new(),
+                    content: HarContent { size: 0, mime_type: String::new(), text: String::new(), comment: None },
+                    redirect_url: String::new(),
+                    headers_size: -1,
+                    body_size: -1,
+                };
+                self.push(capture, har_response, wait, Duration::ZERO);
+                return Err(e);
+            }
+        };
+
+        let status = response.status();
+        let version = response.version();
+        let headers = response.headers().clone();
+        let extensions = response.extensions().clone();
+        let body = response.bytes().await?;
+        let receive = capture.start.elapsed() - wait;
+
+        let (text, comment) = self.truncate(&body);
+        let har_response = HarResponse {
+            status: status.as_u16(),
+            status_text: status.canonical_reason().unwrap_or_default().to_string(),
+            http_version: Self::version(version),
+            cookies: Vec::new(),
+            headers: Self::name_values(&headers),
+            content: HarContent { size: body.len() as i64, mime_type: Self::mime_type(&headers), text, comment },
+            redirect_url: headers
+                .get(reqwest::header::LOCATION)
+                .and_then(|v| v.to_str().ok())
+                .unwrap_or_default()
+                .to_string(),
+            headers_size: -1,
+            body_size: body.len() as i64,
+        };
+        self.push(capture, har_response, wait, receive);
+
+        let mut builder = http::Response::builder().status(status).version(version);
+        for (name, value) in headers.iter() {
+            if name == reqwest::header::CONTENT_ENCODING || name == reqwest::header::CONTENT_LENGTH {
+                continue;
+            }
+            builder = builder.header(name, value);
+        }
+        let mut response = Response::from(builder.body(body)?);
+        *response.extensions_mut() = extensions;
+        Ok(response)
+    }
+
+    fn push(&self, capture: HarCapture, response: HarResponse, wait: Duration, receive: Duration) {
+        let wait = wait.as_secs_f64() * 1000.0;
+        let receive = receive.as_secs_f64() * 1000.0;
+        self.entries.lock().unwrap().push(HarEntry {
+            started_date_time: format_rfc3339(capture.started),
+            time: wait + receive,
+            request: capture.request,
+            response,
+            cache: serde_json::json!({}),
+            timings: HarTimings { blocked: -1.0, dns: -1.0, connect: -1.0, ssl: -1.0, send: 0.0, wait, receive },
+        });
+    }
+
+    fn truncate(&self, bytes: &[u8]) -> (String, Option<String>) {
+        if bytes.len() <= self.max_body_size {
+            (String::from_utf8_lossy(bytes).into_owned(), None)
+        } else {
+            let text = String::from_utf8_lossy(&bytes[..self.max_body_size]).into_owned();
+            (text, Some(format!("truncated to {} of {} bytes", self.max_body_size, bytes.len())))
+        }
+    }
+
+    fn name_values(headers: &HeaderMap) -> Vec<HarNameValue> {
+        headers
+            .iter()
+            .map(|(name, value)| HarNameValue {
+                name: name.to_string(),
+                value: String::from_utf8_lossy(value.as_bytes()).into_owned(),
+            })
+            .collect()
+    }
+
+    fn mime_type(headers: &HeaderMap) -> String {
+        headers
+            .get(reqwest::header::CONTENT_TYPE)
+            .and_then(|v| v.to_str().ok())
+            .unwrap_or_default()
+            .to_string()
+    }
+
+    fn version(version: Version) -> String {
+        format!("{:?}", version)
+    }
+}
+
+/// The function `format_rfc3339` renders a `SystemTime` as a UTC ISO 8601 timestamp with
+/// millisecond precision, as required by `startedDateTime`.
+fn format_rfc3339(time: SystemTime) -> String {
+    let since_epoch = time.duration_since(UNIX_EPOCH).unwrap_or_default();
+    let secs = since_epoch.as_secs() as i64;
+    let (days, rem) = (secs.div_euclid(86_400), secs.rem_euclid(86_400));
+
+    // Civil-from-days conversion (Howard Hinnant's algorithm).
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z.rem_euclid(146_097);
+    let yoe = (doe - doe / 1460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + i64::from(month <= 2);
+
+    format!(
+        "{:04}-{:02}-{:02}T{:02}:{:02}:{:02}.{:03}Z",
+        year,
+        month,
+        day,
+        rem / 3600,
+        (rem % 3600) / 60,
+        rem % 60,
+        since_epoch.subsec_millis()
+    )
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::http::{testing::serve, HttpClient};
+    use std::sync::Arc;
+
+    #[tokio::test]
+    async fn test_records_entries() -> Result<(), Box<dyn Error>> {
+        let reply = "HTTP/1.1 201 Created\r\ncontent-type: text/plain\r\ncontent-length: 10\r\nconnection: close\r\n\r\n0123456789";
+        let (addr, server_task) = serve(vec![reply]).await;
+
+        let recorder = Arc::new(HarRecorder::new().max_body_size(4));
+        let client = HttpClient::new(&format!("http://{}", addr), None)?.with_har_recorder(recorder.clone());
+        let response = client.post("/items?id=7", None, Some("payload")).await?;
+        assert_eq!(response.text().await?, "0123456789");
+        server_task.await?;
+
+        let har: serde_json::Value = serde_json::from_str(&recorder.to_json()?)?;
+        let entry = &har["log"]["entries"][0];
+        assert_eq!(entry["request"]["method"], "POST");
+        assert_eq!(entry["request"]["queryString"][0]["value"], "7");
+        assert_eq!(entry["response"]["status"], 201);
+        assert_eq!(entry["response"]["content"]["text"], "0123");
+        assert_eq!(entry["response"]["content"]["size"], 10);
+        Ok(())
+    }
+
+    #[test]
+    fn test_format_rfc3339() {
+        let time = UNIX_EPOCH + Duration::from_millis(1_700_000_000_123);
+        assert_eq!(format_rfc3339(time), "2023-11-14T22:13:20.123Z");
+    }
+}
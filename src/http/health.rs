@@ -0,0 +1,120 @@
+use super::{client::HttpClient, response::AarambhResponse};
+use reqwest::StatusCode;
+use std::{
+    error::Error,
+    fmt,
+    time::{Duration, Instant},
+};
+use tokio::time::{sleep, timeout};
+
+/// The `HealthCheckTimeout` error is returned by `HttpClient::wait_until_healthy` when the endpoint
+/// did not answer with a 2xx response in time.
+///
+/// # Properties:
+///
+/// * `endpoint`: The endpoint that was polled.
+/// * `attempts`: The number of requests sent.
+/// * `last_status`: The status of the last response, if any response was received.
+/// * `last_error`: The error of the last attempt, if it failed without a response.
+#[derive(Debug, Clone)]
+pub struct HealthCheckTimeout {
+    pub endpoint: String,
+    pub attempts: u32,
+    pub last_status: Option<StatusCode>,
+    pub last_error: Option<String>,
+}
+
+impl fmt::Display for HealthCheckTimeout {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} did not become healthy after {} attempts", self.endpoint, self.attempts)?;
+        match (&self.last_status, &self.last_error) {
+            (Some(status), _) => write!(f, " (last status {})", status),
+            (None, Some(error)) => write!(f, " (last error: {})", error),
+            (None, None) => Ok(()),
+        }
+    }
+}
+
+impl Error for HealthCheckTimeout {}
+
+impl HttpClient {
+    /// The function `wait_until_healthy` polls `endpoint` with `GET` requests until it answers with a
+    /// 2xx status, for example to wait for a service started by an integration test. Every attempt is
+    /// reported as a `tracing` event.
+    ///
+    /// # Arguments:
+    ///
+    /// * `endpoint`: The health endpoint, relative to the client's base URL.
+    /// * `wait`: The total time to keep polling.
+    /// * `interval`: The delay between two attempts.
+    ///
+    /// # Returns:
+    ///
+    /// The `wait_until_healthy` function returns the first 2xx response, or a `HealthCheckTimeout`
+    /// once `wait` has elapsed.
+    pub async fn wait_until_healthy(
+        &self,
+        endpoint: &str,
+        wait: Duration,
+        interval: Duration,
+    ) -> Result<AarambhResponse, Box<dyn Error>> {
+        let deadline = Instant::now() + wait;
+        let mut failure = HealthCheckTimeout {
+            endpoint: endpoint.to_string(),
+            attempts: 0,
+            last_status: None,
+            last_error: None,
+        };
+
+        loop {
+            let remaining = deadline.saturating_duration_since(Instant::now());
+            failure.attempts += 1;
+            match timeout(remaining, self.get(endpoint, None)).await {
+                Ok(Ok(response)) if response.is_success() => {
+                    tracing::info!(endpoint, attempt = failure.attempts, status = %response.status(), "endpoint is healthy");
+                    return Ok(response);
+                }
+                Ok(Ok(response)) => {
+                    tracing::debug!(endpoint, attempt = failure.attempts, status = %response.status(), "endpoint not healthy yet");
+                    failure.last_status = Some(response.status());
+                    failure.last_error = None;
+                }
+                Ok(Err(e)) => {
+                    tracing::debug!(endpoint, attempt = failure.attempts, error = %e, "endpoint not reachable yet");
+                    failure.last_status = None;
+                    failure.last_error = Some(e.to_string());
+                }
+                Err(_) => failure.last_error = Some("health check request timed out".to_string()),
+            }
+
+            if Instant::now() + interval >= deadline {
+                tracing::warn!(endpoint, attempts = failure.attempts, "endpoint did not become healthy in time");
+                return Err(Box::new(failure));
+            }
+            sleep(interval).await;
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::http::testing::serve;
+
+    #[tokio::test]
+    async fn test_wait_until_healthy() {
+        let starting = "HTTP/1.1 503 Service Unavailable\r\ncontent-length: 0\r\nconnection: close\r\n\r\n";
+        let ready = "HTTP/1.1 200 OK\r\ncontent-length: 2\r\nconnection: close\r\n\r\nok";
+        let (addr, server_task) = serve(vec![starting, ready]).await;
+        let client = HttpClient::new(&format!("http://{}", addr), None).unwrap();
+
+        let response = client.wait_until_healthy("/health", Duration::from_secs(5), Duration::from_millis(10)).await;
+        assert_eq!(response.unwrap().status(), StatusCode::OK);
+        server_task.await.unwrap();
+
+        let err = client.wait_until_healthy("/health", Duration::from_millis(50), Duration::from_millis(10)).await;
+        let err = err.unwrap_err();
+        let err = err.downcast_ref::<HealthCheckTimeout>().unwrap();
+        assert!(err.attempts >= 1 && err.last_error.is_some());
+    }
+}
@@ -0,0 +1,115 @@
+#[cfg(not(target_arch = "wasm32"))]
+use super::response::ResponseUrl;
+use super::BoxError;
+#[cfg(not(target_arch = "wasm32"))]
+use futures_util::StreamExt;
+#[cfg(not(target_arch = "wasm32"))]
+use reqwest::Body;
+use reqwest::{header::CONTENT_LENGTH, Response};
+use std::{error::Error, fmt};
+
+/// The `BodyDirection` enum tells which side of an exchange exceeded its size limit.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BodyDirection {
+    Request,
+    Response,
+}
+
+/// The `BodyTooLargeError` is the cause of the `AarambhNetError` returned when a request body is
+/// larger than `HttpClientBuilder::max_request_body_size`, or when a response body grows past
+/// `HttpClientBuilder::max_response_body_size`. The response transfer is aborted at that point
+/// instead of being buffered.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BodyTooLargeError {
+    pub direction: BodyDirection,
+    pub limit: u64,
+}
+
+impl fmt::Display for BodyTooLargeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let direction = match self.direction {
+            BodyDirection::Request => "request",
+            BodyDirection::Response => "response",
+        };
+        write!(f, "{} body exceeds the limit of {} bytes", direction, self.limit)
+    }
+}
+
+impl Error for BodyTooLargeError {}
+
+/// The function `limit_response` fails immediately when `response` announces a body larger than
+/// `limit`, and otherwise wraps the body so the stream fails once more than `limit` bytes arrive.
+/// On `wasm32` only the announced length is checked; use `AarambhResponse::text_limited` to bound
+/// the body itself.
+pub(crate) fn limit_response(response: Response, limit: u64) -> Result<Response, BoxError> {
+    let too_large = BodyTooLargeError { direction: BodyDirection::Response, limit };
+    let announced = response.headers().get(CONTENT_LENGTH).and_then(|v| v.to_str().ok()?.parse::<u64>().ok());
+    if announced.is_some_and(|length| length > limit) {
+        return Err(Box::new(too_large));
+    }
+    limit_body(response, too_large)
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+fn limit_body(response: Response, too_large: BodyTooLargeError) -> Result<Response, BoxError> {
+    let limit = too_large.limit;
+    let mut builder = http::Response::builder().status(response.status()).version(response.version());
+    for (name, value) in response.headers() {
+        builder = builder.header(name, value);
+    }
+    let mut extensions = response.extensions().clone();
+    extensions.insert(ResponseUrl(response.url().clone()));
+
+    let mut received = 0u64;
+    let body = response.bytes_stream().map(move |chunk| -> Result<_, BoxError> {
+        let chunk = chunk?;
+        received += chunk.len() as u64;
+        if received > limit {
+            return Err(Box::new(too_large.clone()));
+        }
+        Ok(chunk)
+    });
+
+    let mut response = Response::from(builder.body(Body::wrap_stream(body))?);
+    *response.extensions_mut() = extensions;
+    Ok(response)
+}
+
+#[cfg(target_arch = "wasm32")]
+fn limit_body(response: Response, _too_large: BodyTooLargeError) -> Result<Response, BoxError> {
+    Ok(response)
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{
+        error::AarambhNetError,
+        http::{testing::serve, BodyDirection, BodyTooLargeError, HttpClient},
+    };
+
+    #[tokio::test]
+    async fn test_body_size_limits() {
+        let announced = "HTTP/1.1 200 OK\r\ncontent-length: 10\r\nconnection: close\r\n\r\n0123456789";
+        let streamed = "HTTP/1.1 200 OK\r\nconnection: close\r\n\r\n0123456789";
+        let (addr, server_task) = serve(vec![announced, streamed]).await;
+        let client = HttpClient::builder(&format!("http://{}", addr))
+            .max_request_body_size(4)
+            .max_response_body_size(4)
+            .build()
+            .unwrap();
+
+        let cause = |err: Box<dyn std::error::Error>| {
+            err.downcast_ref::<AarambhNetError>().unwrap().cause::<BodyTooLargeError>().unwrap().clone()
+        };
+        let err = client.get("/", None).await.unwrap_err();
+        assert_eq!(cause(err).direction, BodyDirection::Response);
+
+        let response = client.get("/", None).await.unwrap();
+        let err = response.text().await.unwrap_err();
+        assert_eq!(cause(err).limit, 4);
+
+        let err = client.post("/", None, Some("too long")).await.unwrap_err();
+        assert_eq!(cause(err).direction, BodyDirection::Request);
+        server_task.await.unwrap();
+    }
+}
@@ -0,0 +1,117 @@
+use super::pool::PoolStats;
+use reqwest::StatusCode;
+use std::{
+    fmt::Write,
+    sync::atomic::{AtomicU64, Ordering},
+    time::Duration,
+};
+
+/// The upper bounds, in seconds, of the request duration histogram buckets.
+const DURATION_BUCKETS: [f64; 11] = [0.005, 0.01, 0.025, 0.05, 0.1, 0.25, 0.5, 1.0, 2.5, 5.0, 10.0];
+
+/// The `ClientMetrics` struct counts the requests an `HttpClient` sends over the network.
+///
+/// # Properties:
+///
+/// * `responses`: The number of responses per status class, from 1xx to 5xx.
+/// * `errors`: The number of attempts that failed without a response.
+/// * `duration_buckets`: The cumulative request duration histogram, one counter per bucket in
+///   `DURATION_BUCKETS`.
+/// * `duration_count`: The number of observed durations.
+/// * `duration_sum_micros`: The sum of the observed durations in microseconds.
+#[derive(Debug, Default)]
+pub(crate) struct ClientMetrics {
+    responses: [AtomicU64; 5],
+    errors: AtomicU64,
+    duration_buckets: [AtomicU64; DURATION_BUCKETS.len()],
+    duration_count: AtomicU64,
+    duration_sum_micros: AtomicU64,
+}
+
+impl ClientMetrics {
+    /// The function `observe` records one attempt: its status, or `None` when it failed without a
+    /// response, and the time until the response headers arrived.
+    pub(crate) fn observe(&self, status: Option<StatusCode>, duration: Duration) {
+        match status {
+            Some(status) => {
+                let class = (status.as_u16() / 100).clamp(1, 5) as usize - 1;
+                self.responses[class].fetch_add(1, Ordering::Relaxed);
+            }
+            None => {
+                self.errors.fetch_add(1, Ordering::Relaxed);
+            }
+        }
+
+        let seconds = duration.as_secs_f64();
+        for (bucket, bound) in self.duration_buckets.iter().zip(DURATION_BUCKETS) {
+            if seconds <= bound {
+                bucket.fetch_add(1, Ordering::Relaxed);
+            }
+        }
+        self.duration_count.fetch_add(1, Ordering::Relaxed);
+        self.duration_sum_micros.fetch_add(duration.as_micros() as u64, Ordering::Relaxed);
+    }
+
+    /// The function `render_prometheus` renders the counters, together with the connection pool
+    /// gauges from `pool`, in the Prometheus text exposition format.
+    pub(crate) fn render_prometheus(&self, pool: &PoolStats) -> String {
+        let mut out = String::new();
+        let _ = self.write_prometheus(&mut out, pool);
+        out
+    }
+
+    fn write_prometheus(&self, out: &mut String, pool: &PoolStats) -> std::fmt::Result {
+        writeln!(out, "# HELP aarambh_http_client_responses_total Responses received, by status class.")?;
+        writeln!(out, "# TYPE aarambh_http_client_responses_total counter")?;
+        for (class, count) in self.responses.iter().enumerate() {
+            let count = count.load(Ordering::Relaxed);
+            writeln!(out, "aarambh_http_client_responses_total{{status_class=\"{}xx\"}} {}", class + 1, count)?;
+        }
+
+        writeln!(out, "# HELP aarambh_http_client_errors_total Requests that failed without a response.")?;
+        writeln!(out, "# TYPE aarambh_http_client_errors_total counter")?;
+        writeln!(out, "aarambh_http_client_errors_total {}", self.errors.load(Ordering::Relaxed))?;
+
+        writeln!(out, "# HELP aarambh_http_client_request_duration_seconds Time until the response headers arrived.")?;
+        writeln!(out, "# TYPE aarambh_http_client_request_duration_seconds histogram")?;
+        for (bucket, bound) in self.duration_buckets.iter().zip(DURATION_BUCKETS) {
+            let count = bucket.load(Ordering::Relaxed);
+            writeln!(out, "aarambh_http_client_request_duration_seconds_bucket{{le=\"{}\"}} {}", bound, count)?;
+        }
+        let count = self.duration_count.load(Ordering::Relaxed);
+        let sum = self.duration_sum_micros.load(Ordering::Relaxed) as f64 / 1_000_000.0;
+        writeln!(out, "aarambh_http_client_request_duration_seconds_bucket{{le=\"+Inf\"}} {}", count)?;
+        writeln!(out, "aarambh_http_client_request_duration_seconds_sum {}", sum)?;
+        writeln!(out, "aarambh_http_client_request_duration_seconds_count {}", count)?;
+
+        writeln!(out, "# HELP aarambh_http_client_in_flight Requests currently holding a connection.")?;
+        writeln!(out, "# TYPE aarambh_http_client_in_flight gauge")?;
+        writeln!(out, "aarambh_http_client_in_flight {}", pool.in_flight)?;
+        writeln!(out, "# HELP aarambh_http_client_waiting Requests waiting for a free connection.")?;
+        writeln!(out, "# TYPE aarambh_http_client_waiting gauge")?;
+        writeln!(out, "aarambh_http_client_waiting {}", pool.waiting)?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::http::{testing::serve, HttpClient};
+
+    #[tokio::test]
+    async fn test_metrics_prometheus() {
+        let ok = "HTTP/1.1 200 OK\r\ncontent-length: 2\r\nconnection: close\r\n\r\nok";
+        let missing = "HTTP/1.1 404 Not Found\r\ncontent-length: 0\r\nconnection: close\r\n\r\n";
+        let (addr, server_task) = serve(vec![ok, missing]).await;
+        let client = HttpClient::new(&format!("http://{}", addr), None).unwrap();
+        client.get("/a", None).await.unwrap();
+        client.get("/b", None).await.unwrap();
+        server_task.await.unwrap();
+
+        let text = client.metrics_prometheus();
+        assert!(text.contains("aarambh_http_client_responses_total{status_class=\"2xx\"} 1\n"));
+        assert!(text.contains("aarambh_http_client_responses_total{status_class=\"4xx\"} 1\n"));
+        assert!(text.contains("aarambh_http_client_request_duration_seconds_count 2\n"));
+        assert!(text.contains("# TYPE aarambh_http_client_request_duration_seconds histogram\n"));
+    }
+}
@@ -0,0 +1,85 @@
+mod audit;
+#[cfg(all(feature = "blocking", not(target_arch = "wasm32")))]
+mod blocking;
+mod client;
+mod curl;
+mod encoding;
+#[cfg(not(target_arch = "wasm32"))]
+mod har;
+#[cfg(not(target_arch = "wasm32"))]
+mod health;
+mod limits;
+mod metrics;
+mod offline;
+mod pool;
+mod profile;
+mod response;
+#[cfg(not(target_arch = "wasm32"))]
+mod server;
+mod throttle;
+#[cfg(not(target_arch = "wasm32"))]
+mod vcr;
+#[cfg(not(target_arch = "wasm32"))]
+pub(crate) mod verbose;
+mod webhook;
+
+/// The boxed error used inside the HTTP pipeline. Unlike `Box<dyn Error>` it is `Send`, which keeps
+/// the request futures `Send`; it converts into `Box<dyn Error>` at the public API boundary.
+pub(crate) type BoxError = Box<dyn std::error::Error + Send + Sync>;
+
+pub use audit::{RequestEvent, ResponseEvent};
+#[cfg(all(feature = "blocking", not(target_arch = "wasm32")))]
+pub use blocking::{BlockingResponse, HttpClientBlocking};
+pub use client::{HttpClient, HttpClientBuilder};
+pub use curl::ToCurl;
+pub use encoding::ContentEncoding;
+#[cfg(not(target_arch = "wasm32"))]
+pub use har::HarRecorder;
+#[cfg(not(target_arch = "wasm32"))]
+pub use health::HealthCheckTimeout;
+pub use limits::{BodyDirection, BodyTooLargeError};
+pub use offline::{
+    ConflictResolution, ConnectivityMonitor, FlushReport, ManualConnectivity, OfflineQueue, QueuedRequest, Submission,
+};
+pub use pool::PoolStats;
+pub use profile::EndpointProfile;
+pub use response::{AarambhResponse, HttpStatusError};
+#[cfg(not(target_arch = "wasm32"))]
+pub use server::{
+    AccessLog, AuthRejection, AuthenticatedUser, BasicAuth, Compression, Cors, Form, FormRejection, FromPathParams,
+    HealthChecks, Http2Settings, HttpServer, IntoResponse, Json, JsonRejection, JwtAuth, JwtClaims, Middleware,
+    Multipart, MultipartField, MultipartLimits, Next, PathParamError, PathParams, RateLimit, RequestId, Router,
+    ServerRequest, ServerResponse, ShutdownReport, SseEvent, SseResponse, WebSocket, WsMessage, proxy_to, proxy_with,
+};
+#[cfg(not(target_arch = "wasm32"))]
+pub use vcr::{
+    BodyEncoding, Cassette, CassetteMissError, Interaction, MatchRules, RecordedBody, RecordedRequest, RecordedResponse,
+    VcrMode,
+};
+pub use webhook::{DeliveryAttempt, WebhookDelivery, WebhookSender};
+
+#[cfg(test)]
+pub(crate) mod testing {
+    use std::net::SocketAddr;
+    use tokio::{
+        io::{AsyncReadExt, AsyncWriteExt},
+        net::TcpListener,
+        task::JoinHandle,
+    };
+
+    /// Starts a throwaway server on an ephemeral port that answers one connection per entry of
+    /// `replies` with that raw HTTP response and then exits.
+    pub(crate) async fn serve(replies: Vec<&'static str>) -> (SocketAddr, JoinHandle<()>) {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let task = tokio::spawn(async move {
+            for reply in replies {
+                let (mut socket, _) = listener.accept().await.unwrap();
+                let mut buf = vec![0; 8192];
+                let _ = socket.read(&mut buf).await.unwrap();
+                socket.write_all(reply.as_bytes()).await.unwrap();
+            }
+        });
+        (addr, task)
+    }
+}
\ No newline at end of file
@@ -0,0 +1,409 @@
+use super::{client::HttpClient, response::AarambhResponse};
+use reqwest::{
+    header::{HeaderMap, HeaderName, HeaderValue},
+    Method, StatusCode,
+};
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::VecDeque,
+    error::Error,
+    fs,
+    io::Write,
+    path::{Path, PathBuf},
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc, Mutex,
+    },
+};
+#[cfg(not(target_arch = "wasm32"))]
+use std::time::Duration;
+#[cfg(not(target_arch = "wasm32"))]
+use tokio::task::JoinHandle;
+
+/// The `ConnectivityMonitor` trait tells the offline queue whether the network is currently usable.
+/// Implement it to plug in platform reachability APIs or application-specific checks.
+pub trait ConnectivityMonitor: Send + Sync {
+    /// The function `is_online` returns `true` when requests are expected to reach the network.
+    fn is_online(&self) -> bool;
+}
+
+/// The `ManualConnectivity` struct is a `ConnectivityMonitor` whose state is set by the application,
+/// for example from OS network-change notifications.
+#[derive(Debug)]
+pub struct ManualConnectivity {
+    online: AtomicBool,
+}
+
+impl ManualConnectivity {
+    /// The function `new` creates a monitor with the given initial state.
+    pub fn new(online: bool) -> Self {
+        ManualConnectivity { online: AtomicBool::new(online) }
+    }
+
+    /// The function `set_online` updates the connectivity state.
+    pub fn set_online(&self, online: bool) {
+        self.online.store(online, Ordering::Relaxed);
+    }
+}
+
+impl ConnectivityMonitor for ManualConnectivity {
+    fn is_online(&self) -> bool {
+        self.online.load(Ordering::Relaxed)
+    }
+}
+
+/// The `QueuedRequest` struct is a request stored while offline, in the form persisted to disk.
+/// Header values are kept as raw bytes, since they need not be UTF-8.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct QueuedRequest {
+    pub id: u64,
+    pub method: String,
+    pub endpoint: String,
+    pub headers: Vec<(String, Vec<u8>)>,
+    pub body: Option<String>,
+}
+
+impl QueuedRequest {
+    /// The function `decode` returns the method and headers of the request, or an error if the
+    /// stored method or a stored header is invalid.
+    fn decode(&self) -> Result<(Method, HeaderMap), Box<dyn Error>> {
+        let method = Method::from_bytes(self.method.as_bytes())?;
+        let mut headers = HeaderMap::new();
+        for (name, value) in &self.headers {
+            headers.append(HeaderName::from_bytes(name.as_bytes())?, HeaderValue::from_bytes(value)?);
+        }
+        Ok((method, headers))
+    }
+}
+
+/// The `Submission` enum tells the caller of `OfflineQueue::send` what happened to a request.
+#[derive(Debug)]
+pub enum Submission {
+    /// The request was sent immediately.
+    Sent(AarambhResponse),
+    /// The request was queued under the given id and will be sent once connectivity returns.
+    Queued(u64),
+}
+
+/// The `ConflictResolution` enum is returned by the conflict hook to decide the fate of a queued
+/// request that the server rejected with `409 Conflict` or `412 Precondition Failed`.
+#[derive(Debug, Clone)]
+pub enum ConflictResolution {
+    /// Drop the request.
+    Discard,
+    /// Keep the request at the head of the queue and stop flushing for now.
+    Keep,
+    /// Replace the request with an updated one (for example rebased on the server state) and send
+    /// it next.
+    Replace(QueuedRequest),
+}
+
+/// The `FlushReport` struct summarizes one `OfflineQueue::flush` run.
+///
+/// # Properties:
+///
+/// * `sent`: The requests the server accepted.
+/// * `conflicts`: The requests the server rejected as conflicting.
+/// * `dropped`: The stored requests that could not be decoded into a request, and were removed
+///   without being sent.
+/// * `remaining`: The requests still queued.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct FlushReport {
+    pub sent: usize,
+    pub conflicts: usize,
+    pub dropped: usize,
+    pub remaining: usize,
+}
+
+type ConflictHook = Box<dyn Fn(&QueuedRequest, StatusCode, &str) -> ConflictResolution + Send + Sync>;
+
+/// The `OfflineQueue` struct wraps an `HttpClient` for apps that must keep working without a
+/// network: requests issued while offline are stored (optionally in a file, so they survive
+/// restarts) and sent in order once the `ConnectivityMonitor` reports the network is back.
+///
+/// # Properties:
+///
+/// * `client`: The `HttpClient` used to send requests.
+/// * `monitor`: The `ConnectivityMonitor` consulted before sending.
+/// * `path`: The optional file the queue is persisted to after every change.
+/// * `state`: The next request id and the queued requests, oldest first.
+/// * `conflict_hook`: The optional callback deciding what to do with conflicting requests.
+/// * `flushing`: Serializes flushes so queued requests are never sent twice.
+pub struct OfflineQueue {
+    client: HttpClient,
+    monitor: Arc<dyn ConnectivityMonitor>,
+    path: Option<PathBuf>,
+    state: Mutex<(u64, VecDeque<QueuedRequest>)>,
+    conflict_hook: Option<ConflictHook>,
+    flushing: tokio::sync::Mutex<()>,
+}
+
+impl OfflineQueue {
+    /// The function `new` creates an in-memory offline queue.
+    ///
+    /// # Arguments:
+    ///
+    /// * `client`: The `HttpClient` used to send requests.
+    /// * `monitor`: The `ConnectivityMonitor` deciding whether requests are sent or queued.
+    pub fn new(client: HttpClient, monitor: Arc<dyn ConnectivityMonitor>) -> Self {
+        OfflineQueue {
+            client,
+            monitor,
+            path: None,
+            state: Mutex::new((1, VecDeque::new())),
+            conflict_hook: None,
+            flushing: tokio::sync::Mutex::new(()),
+        }
+    }
+
+    /// The function `persist_to` makes the queue durable by storing it in `path`. Requests already
+    /// stored there by a previous run are loaded and will be flushed.
+    ///
+    /// # Returns:
+    ///
+    /// The `persist_to` function returns a `Result` containing the queue, or an error if an existing
+    /// file cannot be read or parsed.
+    pub fn persist_to(mut self, path: impl AsRef<Path>) -> Result<Self, Box<dyn Error>> {
+        let path = path.as_ref().to_path_buf();
+        if path.exists() {
+            let queued: VecDeque<QueuedRequest> = serde_json::from_str(&fs::read_to_string(&path)?)?;
+            let next_id = queued.iter().map(|r| r.id + 1).max().unwrap_or(1);
+            self.state = Mutex::new((next_id, queued));
+        }
+        self.path = Some(path);
+        Ok(self)
+    }
+
+    /// The function `on_conflict` installs the hook called when a flushed request is rejected with
+    /// `409 Conflict` or `412 Precondition Failed`. It receives the request, the status and the
+    /// response body. Without a hook, conflicting requests are discarded.
+    pub fn on_conflict<F>(mut self, hook: F) -> Self
+    where
+        F: Fn(&QueuedRequest, StatusCode, &str) -> ConflictResolution + Send + Sync + 'static,
+    {
+        self.conflict_hook = Some(Box::new(hook));
+        self
+    }
+
+    /// The function `client` returns the wrapped `HttpClient`.
+    pub fn client(&self) -> &HttpClient {
+        &self.client
+    }
+
+    /// The function `pending` returns a copy of the queued requests, oldest first.
+    pub fn pending(&self) -> Vec<QueuedRequest> {
+        self.state.lock().unwrap().1.iter().cloned().collect()
+    }
+
+    /// The function `send` sends a request now when online, or queues it otherwise. Requests that fail
+    /// with a transport error are queued too. While older requests are still queued, new ones are
+    /// queued behind them so the server sees them in order.
+    ///
+    /// # Arguments:
+    ///
+    /// * `method`: The HTTP method.
+    /// * `endpoint`: The endpoint, relative to the client's base URL.
+    /// * `headers`: Extra headers for this request.
+    /// * `body`: The optional request body.
+    ///
+    /// # Returns:
+    ///
+    /// The `send` function returns a `Submission` saying whether the request was sent or queued, or an
+    /// error if the queue cannot be persisted.
+    pub async fn send(
+        &self,
+        method: Method,
+        endpoint: &str,
+        headers: Option<HeaderMap>,
+        body: Option<&str>,
+    ) -> Result<Submission, Box<dyn Error>> {
+        if self.monitor.is_online() && !self.pending().is_empty() {
+            self.flush().await?;
+        }
+
+        if self.monitor.is_online() && self.pending().is_empty() {
+            let result = self.client.request(method.clone(), endpoint, headers.clone(), body).await;
+            if let Ok(response) = result {
+                return Ok(Submission::Sent(response));
+            }
+        }
+
+        let id = {
+            let mut state = self.state.lock().unwrap();
+            let id = state.0;
+            state.0 += 1;
+            state.1.push_back(QueuedRequest {
+                id,
+                method: method.to_string(),
+                endpoint: endpoint.to_string(),
+                headers: headers.iter().flatten().map(|(k, v)| (k.to_string(), v.as_bytes().to_vec())).collect(),
+                body: body.map(str::to_string),
+            });
+            id
+        };
+        self.persist()?;
+        Ok(Submission::Queued(id))
+    }
+
+    /// The function `flush` sends queued requests in order while the monitor reports the network as
+    /// online. It stops at the first transport error, leaving that request and the rest queued. A
+    /// stored request that is not a valid request, for example a file edited by hand, is dropped so
+    /// it does not hold up the requests behind it.
+    ///
+    /// # Returns:
+    ///
+    /// The `flush` function returns a `FlushReport`, or an error if the queue cannot be persisted.
+    pub async fn flush(&self) -> Result<FlushReport, Box<dyn Error>> {
+        let _flushing = self.flushing.lock().await;
+        let mut report = FlushReport::default();
+
+        while self.monitor.is_online() {
+            let next = self.state.lock().unwrap().1.front().cloned();
+            let Some(queued) = next else { break };
+
+            let (method, headers) = match queued.decode() {
+                Ok(decoded) => decoded,
+                Err(e) => {
+                    tracing::warn!(id = queued.id, error = %e, "dropped an invalid request from the offline queue");
+                    report.dropped += 1;
+                    self.state.lock().unwrap().1.pop_front();
+                    self.persist()?;
+                    continue;
+                }
+            };
+
+            let response = match self.client.request(method, &queued.endpoint, Some(headers), queued.body.as_deref()).await {
+                Ok(response) => response,
+                Err(_) => break,
+            };
+
+            let status = response.status();
+            if status == StatusCode::CONFLICT || status == StatusCode::PRECONDITION_FAILED {
+                report.conflicts += 1;
+                let body = response.text().await.unwrap_or_default();
+                let resolution = match &self.conflict_hook {
+                    Some(hook) => hook(&queued, status, &body),
+                    None => ConflictResolution::Discard,
+                };
+                match resolution {
+                    ConflictResolution::Discard => {
+                        self.state.lock().unwrap().1.pop_front();
+                    }
+                    ConflictResolution::Keep => break,
+                    ConflictResolution::Replace(replacement) => {
+                        if let Some(front) = self.state.lock().unwrap().1.front_mut() {
+                            *front = replacement;
+                        }
+                    }
+                }
+            } else {
+                report.sent += 1;
+                self.state.lock().unwrap().1.pop_front();
+            }
+            self.persist()?;
+        }
+
+        report.remaining = self.state.lock().unwrap().1.len();
+        Ok(report)
+    }
+
+    /// The function `spawn_flusher` starts a background task that flushes the queue every `interval`
+    /// whenever the monitor reports the network as online.
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn spawn_flusher(self: Arc<Self>, interval: Duration) -> JoinHandle<()> {
+        tokio::spawn(async move {
+            loop {
+                tokio::time::sleep(interval).await;
+                if self.monitor.is_online() && !self.pending().is_empty() {
+                    if let Err(e) = self.flush().await.map_err(|e| e.to_string()) {
+                        tracing::warn!(error = %e, "failed to flush the offline queue");
+                    }
+                }
+            }
+        })
+    }
+
+    /// The function `persist` writes the queue to a temporary file next to `path`, syncs it and
+    /// renames it over `path`, so a crash leaves either the old queue or the new one. The state lock
+    /// is held throughout, so concurrent calls cannot store an older queue last.
+    fn persist(&self) -> Result<(), Box<dyn Error>> {
+        let Some(path) = &self.path else { return Ok(()) };
+        let state = self.state.lock().unwrap();
+        let json = serde_json::to_string_pretty(&state.1)?;
+        let mut temporary = path.clone().into_os_string();
+        temporary.push(".tmp");
+        let mut file = fs::File::create(&temporary)?;
+        file.write_all(json.as_bytes())?;
+        file.sync_all()?;
+        fs::rename(&temporary, path)?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::http::testing::serve;
+
+    #[tokio::test]
+    async fn test_queues_offline_and_flushes_in_order() {
+        let ok = "HTTP/1.1 200 OK\r\ncontent-length: 0\r\nconnection: close\r\n\r\n";
+        let conflict = "HTTP/1.1 409 Conflict\r\ncontent-length: 5\r\nconnection: close\r\n\r\nstale";
+        let (addr, server_task) = serve(vec![ok, conflict]).await;
+
+        let path = std::env::temp_dir().join(format!("aarambh-offline-{}.json", addr.port()));
+        let _ = fs::remove_file(&path);
+        let monitor = Arc::new(ManualConnectivity::new(false));
+        let client = HttpClient::new(&format!("http://{}", addr), None).unwrap();
+        let queue = OfflineQueue::new(client, monitor.clone())
+            .persist_to(&path)
+            .unwrap()
+            .on_conflict(|_, status, body| {
+                assert_eq!((status, body), (StatusCode::CONFLICT, "stale"));
+                ConflictResolution::Discard
+            });
+
+        assert!(matches!(queue.send(Method::POST, "/notes", None, Some("a")).await.unwrap(), Submission::Queued(1)));
+        assert!(matches!(queue.send(Method::PUT, "/notes/1", None, Some("b")).await.unwrap(), Submission::Queued(2)));
+        let stored: Vec<QueuedRequest> = serde_json::from_str(&fs::read_to_string(&path).unwrap()).unwrap();
+        assert_eq!(stored.len(), 2);
+
+        monitor.set_online(true);
+        let report = queue.flush().await.unwrap();
+        assert_eq!(report, FlushReport { sent: 1, conflicts: 1, dropped: 0, remaining: 0 });
+        server_task.await.unwrap();
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_keeps_raw_header_bytes_and_drops_invalid_requests() {
+        let ok = "HTTP/1.1 200 OK\r\ncontent-length: 0\r\nconnection: close\r\n\r\n";
+        let (addr, server_task) = serve(vec![ok]).await;
+
+        let path = std::env::temp_dir().join(format!("aarambh-offline-{}.json", addr.port()));
+        let invalid = QueuedRequest {
+            id: 1,
+            method: "NOT A METHOD".into(),
+            endpoint: "/notes".into(),
+            headers: Vec::new(),
+            body: None,
+        };
+        fs::write(&path, serde_json::to_string(&[invalid]).unwrap()).unwrap();
+        let monitor = Arc::new(ManualConnectivity::new(false));
+        let client = HttpClient::new(&format!("http://{}", addr), None).unwrap();
+        let queue = OfflineQueue::new(client, monitor.clone()).persist_to(&path).unwrap();
+
+        let mut headers = HeaderMap::new();
+        headers.insert("x-name", HeaderValue::from_bytes(b"caf\xe9").unwrap());
+        let submission = queue.send(Method::POST, "/notes", Some(headers), None).await.unwrap();
+        assert!(matches!(submission, Submission::Queued(2)));
+        let stored: Vec<QueuedRequest> = serde_json::from_str(&fs::read_to_string(&path).unwrap()).unwrap();
+        assert_eq!(stored[1].headers, vec![("x-name".to_string(), b"caf\xe9".to_vec())]);
+
+        monitor.set_online(true);
+        let report = queue.flush().await.unwrap();
+        assert_eq!(report, FlushReport { sent: 1, conflicts: 0, dropped: 1, remaining: 0 });
+        server_task.await.unwrap();
+        fs::remove_file(&path).unwrap();
+    }
+}
@@ -0,0 +1,203 @@
+#[cfg(not(target_arch = "wasm32"))]
+use super::response::ResponseUrl;
+use super::BoxError;
+#[cfg(not(target_arch = "wasm32"))]
+use futures_util::StreamExt;
+#[cfg(not(target_arch = "wasm32"))]
+use reqwest::Body;
+use reqwest::{ClientBuilder, Response};
+use std::{
+    sync::{
+        atomic::{AtomicU64, AtomicUsize, Ordering},
+        Arc,
+    },
+    time::Duration,
+};
+use tokio::sync::{OwnedSemaphorePermit, Semaphore};
+
+/// The `PoolConfig` struct holds the connection pool settings of an `HttpClient`.
+///
+/// # Properties:
+///
+/// * `max_idle_per_host`: The maximum number of idle connections kept per host.
+/// * `idle_timeout`: How long an idle connection is kept; `Some(None)` keeps them forever.
+/// * `max_concurrent_requests`: The maximum number of requests in flight at once across all hosts.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct PoolConfig {
+    pub(crate) max_idle_per_host: Option<usize>,
+    pub(crate) idle_timeout: Option<Option<Duration>>,
+    pub(crate) max_concurrent_requests: Option<usize>,
+}
+
+/// The `PoolStats` struct is a snapshot of the connection usage of an `HttpClient`.
+///
+/// # Properties:
+///
+/// * `in_flight`: The number of requests currently holding a connection, counted until their
+///   response body has been read or dropped.
+/// * `peak_in_flight`: The highest `in_flight` value observed so far.
+/// * `waiting`: The number of requests waiting because `max_concurrent_requests` is reached.
+/// * `total_requests`: The number of requests sent over the network.
+/// * `max_concurrent_requests`: The configured limit on requests in flight, if any.
+/// * `max_idle_per_host`: The configured idle connection limit per host, if any.
+/// * `idle_timeout`: The configured idle timeout, if one was set.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PoolStats {
+    pub in_flight: usize,
+    pub peak_in_flight: usize,
+    pub waiting: usize,
+    pub total_requests: u64,
+    pub max_concurrent_requests: Option<usize>,
+    pub max_idle_per_host: Option<usize>,
+    pub idle_timeout: Option<Duration>,
+}
+
+/// The `ConnectionTracker` struct enforces the limit on requests in flight and keeps the counters
+/// behind `HttpClient::pool_stats`.
+#[derive(Debug)]
+pub(crate) struct ConnectionTracker {
+    config: PoolConfig,
+    limit: Option<Arc<Semaphore>>,
+    in_flight: AtomicUsize,
+    peak_in_flight: AtomicUsize,
+    waiting: AtomicUsize,
+    total_requests: AtomicU64,
+}
+
+/// The `InFlightGuard` struct marks one request as in flight until it is dropped, releasing its
+/// connection permit at the same time.
+struct InFlightGuard {
+    tracker: Arc<ConnectionTracker>,
+    _permit: Option<OwnedSemaphorePermit>,
+}
+
+impl Drop for InFlightGuard {
+    fn drop(&mut self) {
+        self.tracker.in_flight.fetch_sub(1, Ordering::Relaxed);
+    }
+}
+
+impl PoolConfig {
+    /// The function `apply` copies the settings reqwest understands onto `builder`.
+    /// The browser manages the connections of the `wasm32` build, so there it is a no-op.
+    #[cfg(not(target_arch = "wasm32"))]
+    pub(crate) fn apply(&self, mut builder: ClientBuilder) -> ClientBuilder {
+        if let Some(max) = self.max_idle_per_host {
+            builder = builder.pool_max_idle_per_host(max);
+        }
+        if let Some(timeout) = self.idle_timeout {
+            builder = builder.pool_idle_timeout(timeout);
+        }
+        builder
+    }
+
+    #[cfg(target_arch = "wasm32")]
+    pub(crate) fn apply(&self, builder: ClientBuilder) -> ClientBuilder {
+        builder
+    }
+}
+
+impl ConnectionTracker {
+    pub(crate) fn new(config: PoolConfig) -> Self {
+        ConnectionTracker {
+            limit: config.max_concurrent_requests.map(|max| Arc::new(Semaphore::new(max))),
+            config,
+            in_flight: AtomicUsize::new(0),
+            peak_in_flight: AtomicUsize::new(0),
+            waiting: AtomicUsize::new(0),
+            total_requests: AtomicU64::new(0),
+        }
+    }
+
+    /// The function `track` waits for a free request slot, runs `send` and ties the slot to the
+    /// lifetime of the response body.
+    pub(crate) async fn track<F>(self: &Arc<Self>, send: F) -> Result<Response, BoxError>
+    where
+        F: std::future::Future<Output = Result<Response, BoxError>>,
+    {
+        let permit = match &self.limit {
+            Some(limit) => {
+                self.waiting.fetch_add(1, Ordering::Relaxed);
+                let permit = limit.clone().acquire_owned().await;
+                self.waiting.fetch_sub(1, Ordering::Relaxed);
+                Some(permit?)
+            }
+            None => None,
+        };
+
+        let in_flight = self.in_flight.fetch_add(1, Ordering::Relaxed) + 1;
+        self.peak_in_flight.fetch_max(in_flight, Ordering::Relaxed);
+        self.total_requests.fetch_add(1, Ordering::Relaxed);
+        let guard = InFlightGuard { tracker: self.clone(), _permit: permit };
+        hold_until_released(send.await?, guard)
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    pub(crate) fn config(&self) -> &PoolConfig {
+        &self.config
+    }
+    pub(crate) fn stats(&self) -> PoolStats {
+        PoolStats {
+            in_flight: self.in_flight.load(Ordering::Relaxed),
+            peak_in_flight: self.peak_in_flight.load(Ordering::Relaxed),
+            waiting: self.waiting.load(Ordering::Relaxed),
+            total_requests: self.total_requests.load(Ordering::Relaxed),
+            max_concurrent_requests: self.config.max_concurrent_requests,
+            max_idle_per_host: self.config.max_idle_per_host,
+            idle_timeout: self.config.idle_timeout.flatten(),
+        }
+    }
+}
+
+/// The function `hold_until_released` rebuilds `response` around a body that keeps `guard` alive
+/// until the body has been read to the end or dropped.
+#[cfg(not(target_arch = "wasm32"))]
+fn hold_until_released(response: Response, guard: InFlightGuard) -> Result<Response, BoxError> {
+    let mut builder = http::Response::builder().status(response.status()).version(response.version());
+    for (name, value) in response.headers() {
+        builder = builder.header(name, value);
+    }
+    let mut extensions = response.extensions().clone();
+    extensions.insert(ResponseUrl(response.url().clone()));
+    let body = response.bytes_stream().map(move |chunk| {
+        let _ = &guard;
+        chunk
+    });
+
+    let mut response = Response::from(builder.body(Body::wrap_stream(body))?);
+    *response.extensions_mut() = extensions;
+    Ok(response)
+}
+
+/// Browser responses cannot be rebuilt around another body, so on `wasm32` the slot is released as
+/// soon as the response headers arrive.
+#[cfg(target_arch = "wasm32")]
+fn hold_until_released(response: Response, guard: InFlightGuard) -> Result<Response, BoxError> {
+    drop(guard);
+    Ok(response)
+}
+
+#[cfg(test)]
+mod test {
+    use crate::http::{testing::serve, HttpClient};
+
+    #[tokio::test]
+    async fn test_max_concurrent_requests_serializes_requests() {
+        let ok = "HTTP/1.1 200 OK\r\ncontent-length: 2\r\nconnection: close\r\n\r\nok";
+        let (addr, server_task) = serve(vec![ok, ok]).await;
+        let client = HttpClient::builder(&format!("http://{}", addr)).max_concurrent_requests(1).build().unwrap();
+
+        let (first, second) = tokio::join!(
+            async { client.get("/a", None).await.unwrap().text().await.unwrap() },
+            async { client.get("/b", None).await.unwrap().text().await.unwrap() },
+        );
+        assert_eq!((first.as_str(), second.as_str()), ("ok", "ok"));
+        server_task.await.unwrap();
+
+        let stats = client.pool_stats();
+        assert_eq!(stats.total_requests, 2);
+        assert_eq!(stats.peak_in_flight, 1);
+        assert_eq!(stats.in_flight, 0);
+        assert_eq!(stats.max_concurrent_requests, Some(1));
+    }
+}
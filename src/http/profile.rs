@@ -0,0 +1,188 @@
+use super::throttle;
+use crate::rate_limit::TokenBucket;
+use reqwest::header::{HeaderMap, HeaderName, HeaderValue};
+use std::{
+    collections::hash_map::RandomState,
+    error::Error,
+    hash::BuildHasher,
+    sync::Arc,
+    time::Duration,
+};
+
+/// The `EndpointProfile` struct holds per-endpoint overrides that `HttpClient` applies to requests
+/// whose path matches the pattern the profile was registered with.
+///
+/// # Properties:
+///
+/// * `timeout`: The total request timeout for matching requests.
+/// * `retries`: How many times a matching request is retried after a transport error or a 5xx
+///   response.
+/// * `retry_backoff`: The delay before the first retry; it doubles for each further retry.
+/// * `retry_max_backoff`: The longest delay between two retries.
+/// * `headers`: Headers added to matching requests. They override the client's default headers but
+///   not the headers passed to the individual request.
+/// * `rate_limiter`: The optional `TokenBucket` shared by all requests matching the profile.
+/// * `download_limit`: The optional bandwidth limit shared by the response bodies of matching
+///   requests.
+/// * `upload_limit`: The optional bandwidth limit shared by the request bodies of matching requests.
+#[derive(Debug, Clone)]
+pub struct EndpointProfile {
+    pub(crate) timeout: Option<Duration>,
+    pub(crate) retries: u32,
+    pub(crate) retry_backoff: Duration,
+    pub(crate) retry_max_backoff: Duration,
+    pub(crate) headers: HeaderMap,
+    pub(crate) rate_limiter: Option<Arc<TokenBucket>>,
+    pub(crate) download_limit: Option<Arc<TokenBucket>>,
+    pub(crate) upload_limit: Option<Arc<TokenBucket>>,
+}
+
+impl Default for EndpointProfile {
+    fn default() -> Self {
+        EndpointProfile {
+            timeout: None,
+            retries: 0,
+            retry_backoff: Duration::from_millis(100),
+            retry_max_backoff: Duration::from_secs(30),
+            headers: HeaderMap::new(),
+            rate_limiter: None,
+            download_limit: None,
+            upload_limit: None,
+        }
+    }
+}
+
+impl EndpointProfile {
+    /// The function `new` creates a profile without any overrides.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The function `timeout` sets the total timeout of matching requests. The browser's `fetch` has
+    /// no timeout, so it is ignored on `wasm32`.
+    pub fn timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = Some(timeout);
+        self
+    }
+
+    /// The function `retries` sets how many times a matching request is retried after a transport
+    /// error or a 5xx response. Requests with streaming bodies cannot be replayed and are never
+    /// retried.
+    ///
+    /// # Arguments:
+    ///
+    /// * `retries`: The number of retries after the first attempt.
+    /// * `backoff`: The delay before the first retry, doubled for each following retry up to
+    ///   `max_backoff`, 30 seconds by default. Each delay is randomly shortened by up to half, so
+    ///   clients failing together do not retry together.
+    pub fn retries(mut self, retries: u32, backoff: Duration) -> Self {
+        self.retries = retries;
+        self.retry_backoff = backoff;
+        self
+    }
+
+    /// The function `max_backoff` caps the delay between two retries of a matching request.
+    pub fn max_backoff(mut self, max: Duration) -> Self {
+        self.retry_max_backoff = max;
+        self
+    }
+
+    /// The function `retry_delay` returns how long to wait before retry number `attempt`, counted
+    /// from zero: the backoff doubled `attempt` times, capped at the maximum, of which a random part
+    /// of up to half is taken off.
+    pub(crate) fn retry_delay(&self, attempt: u32) -> Duration {
+        let delay = self.retry_backoff.saturating_mul(2u32.saturating_pow(attempt)).min(self.retry_max_backoff);
+        let jitter = RandomState::new().hash_one(attempt) as f64 / u64::MAX as f64;
+        delay - (delay / 2).mul_f64(jitter)
+    }
+
+    /// The function `header` adds a header sent with every matching request.
+    pub fn header(mut self, name: &str, value: &str) -> Result<Self, Box<dyn Error>> {
+        self.headers.insert(HeaderName::from_bytes(name.as_bytes())?, HeaderValue::from_str(value)?);
+        Ok(self)
+    }
+
+    /// The function `rate_limit` caps matching requests to `requests_per_second`; requests over the
+    /// limit wait for a free slot instead of failing.
+    pub fn rate_limit(mut self, requests_per_second: f64) -> Self {
+        self.rate_limiter = Some(Arc::new(TokenBucket::per_second(requests_per_second)));
+        self
+    }
+
+    /// The function `download_rate` caps the response bodies of matching requests, together, to
+    /// `bytes_per_second`. It applies on top of `HttpClientBuilder::max_download_rate`.
+    pub fn download_rate(mut self, bytes_per_second: u64) -> Self {
+        self.download_limit = Some(throttle::bandwidth_bucket(bytes_per_second));
+        self
+    }
+
+    /// The function `upload_rate` caps the request bodies of matching requests, together, to
+    /// `bytes_per_second`. It applies on top of `HttpClientBuilder::max_upload_rate`.
+    pub fn upload_rate(mut self, bytes_per_second: u64) -> Self {
+        self.upload_limit = Some(throttle::bandwidth_bucket(bytes_per_second));
+        self
+    }
+}
+
+/// The function `matches` checks a request path against a profile pattern. A pattern containing
+/// `*` is a glob where `*` matches any run of characters (including `/`); any other pattern matches
+/// the path itself and everything below it, so `/export` matches `/export` and `/export/csv` but not
+/// `/exports`.
+pub(crate) fn matches(pattern: &str, path: &str) -> bool {
+    if pattern.contains('*') {
+        return glob(pattern.as_bytes(), path.as_bytes());
+    }
+    let pattern = pattern.trim_end_matches('/');
+    match path.strip_prefix(pattern) {
+        Some(rest) => rest.is_empty() || rest.starts_with('/'),
+        None => false,
+    }
+}
+
+fn glob(pattern: &[u8], text: &[u8]) -> bool {
+    let (mut p, mut t) = (0, 0);
+    let mut backtrack: Option<(usize, usize)> = None;
+    while t < text.len() {
+        if p < pattern.len() && pattern[p] == b'*' {
+            backtrack = Some((p, t));
+            p += 1;
+        } else if p < pattern.len() && pattern[p] == text[t] {
+            p += 1;
+            t += 1;
+        } else if let Some((star, matched)) = backtrack {
+            p = star + 1;
+            t = matched + 1;
+            backtrack = Some((star, matched + 1));
+        } else {
+            return false;
+        }
+    }
+    pattern[p..].iter().all(|&c| c == b'*')
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_pattern_matching() {
+        assert!(matches("/export", "/export"));
+        assert!(matches("/export/", "/export/csv"));
+        assert!(!matches("/export", "/exports"));
+        assert!(matches("/api/*/search", "/api/v2/search"));
+        assert!(matches("*.json", "/data/items.json"));
+        assert!(!matches("/api/*/search", "/api/v2/find"));
+    }
+
+    #[test]
+    fn test_retry_delay() {
+        let profile = EndpointProfile::new().retries(100, Duration::from_secs(1)).max_backoff(Duration::from_secs(8));
+        for (attempt, full) in [(0, 1), (1, 2), (3, 8), (4, 8), (99, 8)] {
+            let delay = profile.retry_delay(attempt);
+            let full = Duration::from_secs(full);
+            assert!(delay <= full && delay >= full / 2, "attempt {}: {:?}", attempt, delay);
+        }
+        let unbounded = EndpointProfile::new().retries(100, Duration::MAX).max_backoff(Duration::MAX);
+        assert!(unbounded.retry_delay(99) >= Duration::MAX / 2);
+    }
+}
@@ -0,0 +1,239 @@
+#[cfg(not(target_arch = "wasm32"))]
+use super::encoding::OriginalContentEncoding;
+use super::limits::{BodyDirection, BodyTooLargeError};
+use futures_util::StreamExt;
+use reqwest::{
+    header::{HeaderMap, CONTENT_ENCODING},
+    Response, StatusCode, Url,
+};
+#[cfg(not(target_arch = "wasm32"))]
+use reqwest::Version;
+use crate::error::AarambhNetError;
+#[cfg(not(target_arch = "wasm32"))]
+use crate::timeline::Timeline;
+use serde::de::DeserializeOwned;
+use std::{error::Error, fmt};
+
+/// The `AarambhResponse` struct wraps the response returned by `HttpClient`, adding typed body
+/// helpers and `error_for_status` semantics on top of the underlying `reqwest::Response`.
+///
+/// # Properties:
+///
+/// * `inner`: The wrapped `reqwest::Response`.
+#[derive(Debug)]
+pub struct AarambhResponse {
+    inner: Response,
+}
+
+/// The `HttpStatusError` is produced by `AarambhResponse::error_for_status` for 4xx and 5xx
+/// responses and carries the response body, which usually explains what went wrong.
+#[derive(Debug, Clone)]
+pub struct HttpStatusError {
+    pub status: StatusCode,
+    pub url: Url,
+    pub body: String,
+}
+
+impl fmt::Display for HttpStatusError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "HTTP status {} for {}", self.status, self.url)?;
+        if !self.body.is_empty() {
+            write!(f, ": {}", self.body)?;
+        }
+        Ok(())
+    }
+}
+
+impl Error for HttpStatusError {}
+
+/// The `ResponseUrl` response extension keeps the final URL of a response across the points where
+/// the response is rebuilt (decoding, recording), which would otherwise lose it.
+#[cfg(not(target_arch = "wasm32"))]
+#[derive(Debug, Clone)]
+pub(crate) struct ResponseUrl(pub(crate) Url);
+
+impl AarambhResponse {
+    pub(crate) fn new(inner: Response) -> Self {
+        AarambhResponse { inner }
+    }
+
+    /// The function `status` returns the HTTP status code of the response.
+    pub fn status(&self) -> StatusCode {
+        self.inner.status()
+    }
+
+    /// The function `headers` returns the response headers.
+    pub fn headers(&self) -> &HeaderMap {
+        self.inner.headers()
+    }
+
+    /// The function `url` returns the final URL of the response.
+    pub fn url(&self) -> &Url {
+        #[cfg(not(target_arch = "wasm32"))]
+        if let Some(ResponseUrl(url)) = self.inner.extensions().get::<ResponseUrl>() {
+            return url;
+        }
+        self.inner.url()
+    }
+
+    /// The function `content_encoding` returns the `Content-Encoding` the server used for the body,
+    /// even when the client already decoded it.
+    pub fn content_encoding(&self) -> Option<&str> {
+        #[cfg(not(target_arch = "wasm32"))]
+        if let Some(OriginalContentEncoding(encoding)) = self.inner.extensions().get::<OriginalContentEncoding>() {
+            return Some(encoding);
+        }
+        self.headers().get(CONTENT_ENCODING).and_then(|v| v.to_str().ok())
+    }
+
+    /// The function `timeline` returns the `Timeline` recorded for the exchange when the client is in
+    /// verbose mode.
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn timeline(&self) -> Option<Timeline> {
+        self.inner.extensions().get::<Timeline>().cloned()
+    }
+
+    /// The function `version` returns the HTTP version of the response. Browsers do not report it, so
+    /// it is not available on `wasm32`.
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn version(&self) -> Version {
+        self.inner.version()
+    }
+
+    /// The function `content_length` returns the body length announced by the server, if any.
+    pub fn content_length(&self) -> Option<u64> {
+        self.inner.content_length()
+    }
+
+    /// The function `is_informational` returns `true` for 1xx responses.
+    pub fn is_informational(&self) -> bool {
+        self.status().is_informational()
+    }
+
+    /// The function `is_success` returns `true` for 2xx responses.
+    pub fn is_success(&self) -> bool {
+        self.status().is_success()
+    }
+
+    /// The function `is_redirection` returns `true` for 3xx responses.
+    pub fn is_redirection(&self) -> bool {
+        self.status().is_redirection()
+    }
+
+    /// The function `is_client_error` returns `true` for 4xx responses.
+    pub fn is_client_error(&self) -> bool {
+        self.status().is_client_error()
+    }
+
+    /// The function `is_server_error` returns `true` for 5xx responses.
+    pub fn is_server_error(&self) -> bool {
+        self.status().is_server_error()
+    }
+
+    /// The function `error_for_status` turns 4xx and 5xx responses into an `HttpStatusError`.
+    ///
+    /// # Returns:
+    ///
+    /// The `error_for_status` function returns the response unchanged when its status is not an
+    /// error, or an `HttpStatusError` holding the status, URL and body text otherwise.
+    pub async fn error_for_status(self) -> Result<Self, HttpStatusError> {
+        let status = self.status();
+        if !status.is_client_error() && !status.is_server_error() {
+            return Ok(self);
+        }
+        let url = self.url().clone();
+        let body = self.inner.text().await.unwrap_or_default();
+        Err(HttpStatusError { status, url, body })
+    }
+
+    /// The function `text` reads the whole body as a string.
+    pub async fn text(self) -> Result<String, Box<dyn Error>> {
+        let url = self.url().clone();
+        self.inner.text().await.map_err(|e| read_error(url, e, None))
+    }
+
+    /// The function `bytes` reads the whole body as raw bytes.
+    pub async fn bytes(self) -> Result<Vec<u8>, Box<dyn Error>> {
+        let url = self.url().clone();
+        Ok(self.inner.bytes().await.map_err(|e| read_error(url, e, None))?.to_vec())
+    }
+
+    /// The function `text_limited` reads the body as a string, refusing bodies larger than `max`.
+    ///
+    /// # Arguments:
+    ///
+    /// * `max`: The maximum number of body bytes to accept.
+    ///
+    /// # Returns:
+    ///
+    /// The `text_limited` function returns the body decoded as UTF-8 (lossily), or a
+    /// `AarambhNetError` caused by a `BodyTooLargeError` as soon as more than `max` bytes have been
+    /// received.
+    pub async fn text_limited(self, max: usize) -> Result<String, Box<dyn Error>> {
+        let url = self.url().clone();
+        let mut body = Vec::new();
+        let mut stream = self.inner.bytes_stream();
+        while let Some(chunk) = stream.next().await {
+            let chunk = match chunk {
+                Ok(chunk) => chunk,
+                Err(e) => return Err(read_error(url, e, Some(body.len()))),
+            };
+            if body.len() + chunk.len() > max {
+                let e = BodyTooLargeError { direction: BodyDirection::Response, limit: max as u64 };
+                return Err(read_error(url, e, Some(body.len())));
+            }
+            body.extend_from_slice(&chunk);
+        }
+        Ok(String::from_utf8_lossy(&body).into_owned())
+    }
+
+    /// The function `json` deserializes the body as JSON into `T`.
+    pub async fn json<T: DeserializeOwned>(self) -> Result<T, Box<dyn Error>> {
+        let url = self.url().clone();
+        let bytes = self.inner.bytes().await.map_err(|e| read_error(url, e, None))?;
+        Ok(serde_json::from_slice(&bytes)?)
+    }
+
+    /// The function `into_inner` returns the wrapped `reqwest::Response`.
+    pub fn into_inner(self) -> Response {
+        self.inner
+    }
+}
+
+/// The function `read_error` wraps a failure to read the body of the response from `url`.
+fn read_error(url: Url, e: impl Error + Send + Sync + 'static, received: Option<usize>) -> Box<dyn Error> {
+    let err = AarambhNetError::new("read body", e).with_target(url);
+    match received {
+        Some(received) => Box::new(err.with_bytes_transferred(received as u64)),
+        None => Box::new(err),
+    }
+}
+
+impl From<Response> for AarambhResponse {
+    fn from(inner: Response) -> Self {
+        AarambhResponse::new(inner)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_error_for_status_carries_body() {
+        let response = Response::from(http::Response::builder().status(404).body("missing widget").unwrap());
+        let response = AarambhResponse::new(response);
+        assert!(response.is_client_error());
+
+        let err = response.error_for_status().await.unwrap_err();
+        assert_eq!(err.status, StatusCode::NOT_FOUND);
+        assert_eq!(err.body, "missing widget");
+
+        let response = Response::from(http::Response::builder().body(r#"{"id":7}"#).unwrap());
+        let value: serde_json::Value = AarambhResponse::new(response).json().await.unwrap();
+        assert_eq!(value["id"], 7);
+
+        let response = Response::from(http::Response::builder().body("0123456789").unwrap());
+        assert!(AarambhResponse::new(response).text_limited(4).await.is_err());
+    }
+}
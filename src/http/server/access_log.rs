@@ -0,0 +1,164 @@
+use super::{
+    middleware::{Middleware, Next},
+    request::ServerRequest,
+    router::HandlerFuture,
+};
+use hyper::header::{HeaderName, HeaderValue};
+use std::{
+    collections::hash_map::RandomState,
+    fmt,
+    hash::BuildHasher,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        OnceLock,
+    },
+    time::{Instant, SystemTime},
+};
+use tracing::Instrument;
+
+/// The longest request ID taken over from a client.
+const MAX_ID_LEN: usize = 128;
+
+/// The `RequestId` struct is the ID `AccessLog` gives a request, stored in the request extensions
+/// for handlers to read with `ServerRequest::request_id`.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct RequestId(String);
+
+impl RequestId {
+    /// The function `generate` creates a new random ID of 32 hexadecimal digits.
+    fn generate() -> Self {
+        static SEED: OnceLock<RandomState> = OnceLock::new();
+        static COUNTER: AtomicU64 = AtomicU64::new(0);
+        let count = COUNTER.fetch_add(1, Ordering::Relaxed);
+        let nanos = SystemTime::now().duration_since(SystemTime::UNIX_EPOCH).unwrap_or_default().as_nanos();
+        let random = SEED.get_or_init(RandomState::new).hash_one((count, nanos));
+        RequestId(format!("{:016x}{:016x}", random, count))
+    }
+
+    /// The function `as_str` returns the ID.
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+}
+
+impl fmt::Display for RequestId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+/// The `AccessLog` struct is a middleware giving every request an ID and logging it once answered.
+/// The ID is taken from the request header when the client or a proxy in front of the server set
+/// one, and generated otherwise. It is returned in the same response header, stored in the request
+/// extensions as a `RequestId`, and attached to a `tracing` span around the handler, so the events
+/// the handler logs can be correlated with the request. Each request is then logged as an `INFO`
+/// event with its method, path, status, latency and client address.
+///
+/// # Properties:
+///
+/// * `header`: The header carrying the ID, `x-request-id` by default.
+/// * `trust_incoming`: Whether the ID sent by the client is kept.
+#[derive(Debug, Clone)]
+pub struct AccessLog {
+    header: HeaderName,
+    trust_incoming: bool,
+}
+
+impl AccessLog {
+    /// The function `new` creates the middleware with the `x-request-id` header, keeping the IDs
+    /// sent by clients.
+    pub fn new() -> Self {
+        AccessLog { header: HeaderName::from_static("x-request-id"), trust_incoming: true }
+    }
+
+    /// The function `header` changes the header carrying the ID, for example to `x-correlation-id`.
+    /// Names that are not valid header names are ignored.
+    pub fn header(mut self, name: &str) -> Self {
+        if let Ok(name) = name.parse() {
+            self.header = name;
+        }
+        self
+    }
+
+    /// The function `trust_incoming` sets whether the ID sent by the client is kept. Servers
+    /// reachable by untrusted clients may prefer to always generate their own. IDs longer than 128
+    /// characters or containing anything but visible ASCII are replaced either way.
+    pub fn trust_incoming(mut self, trust: bool) -> Self {
+        self.trust_incoming = trust;
+        self
+    }
+}
+
+impl Default for AccessLog {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Middleware for AccessLog {
+    fn handle(&self, mut request: ServerRequest, next: Next) -> HandlerFuture {
+        let incoming = request
+            .headers()
+            .get(&self.header)
+            .and_then(|value| value.to_str().ok())
+            .filter(|id| self.trust_incoming && !id.is_empty() && id.len() <= MAX_ID_LEN)
+            .filter(|id| id.bytes().all(|byte| byte.is_ascii_graphic()));
+        let id = incoming.map(|id| RequestId(id.to_string())).unwrap_or_else(RequestId::generate);
+        let value = HeaderValue::from_str(id.as_str()).unwrap_or(HeaderValue::from_static(""));
+        request.headers_mut().insert(self.header.clone(), value.clone());
+        request.extensions_mut().insert(id.clone());
+
+        let (header, started) = (self.header.clone(), Instant::now());
+        let (method, path, remote_addr) = (request.method().clone(), request.path().to_string(), request.remote_addr());
+        let span = tracing::info_span!("request", request_id = %id);
+        let response = next.run(request);
+        Box::pin(
+            async move {
+                let mut response = response.await;
+                response.headers_mut().insert(header, value);
+                let latency_ms = started.elapsed().as_secs_f64() * 1000.0;
+                tracing::info!(%method, path, status = response.status().as_u16(), latency_ms, %remote_addr, "request");
+                response
+            }
+            .instrument(span),
+        )
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::http::{AccessLog, HttpServer, ServerRequest, ServerResponse};
+    use reqwest::Client;
+
+    #[tokio::test]
+    async fn test_request_ids_are_propagated() {
+        let server = HttpServer::bind("127.0.0.1:0")
+            .await
+            .unwrap()
+            .get("/id", |request: ServerRequest| async move {
+                ServerResponse::text(request.request_id().unwrap_or_default().to_string())
+            })
+            .middleware(AccessLog::new());
+        let server = server.spawn().unwrap();
+        let url = format!("http://{}/id", server.addr());
+        let client = Client::new();
+
+        let response = client.get(&url).header("x-request-id", "edge-42").send().await.unwrap();
+        assert_eq!(response.headers()["x-request-id"], "edge-42");
+        assert_eq!(response.text().await.unwrap(), "edge-42");
+
+        // Missing or malformed IDs are replaced by generated ones, unique per request.
+        let mut generated = Vec::new();
+        for incoming in ["", "two words"] {
+            let response = client.get(&url).header("x-request-id", incoming).send().await.unwrap();
+            let id = response.headers()["x-request-id"].to_str().unwrap().to_string();
+            assert_eq!(response.text().await.unwrap(), id);
+            assert!(id.len() == 32 && id.bytes().all(|byte| byte.is_ascii_hexdigit()), "{}", id);
+            generated.push(id);
+        }
+        assert_ne!(generated[0], generated[1]);
+
+        server.shutdown().await;
+        server.await.unwrap();
+    }
+}
@@ -0,0 +1,455 @@
+use super::{
+    middleware::{Middleware, Next},
+    request::ServerRequest,
+    response::{IntoResponse, ServerResponse},
+    router::HandlerFuture,
+};
+use base64::{
+    engine::general_purpose::{STANDARD, URL_SAFE_NO_PAD},
+    Engine,
+};
+use hmac::{Hmac, Mac};
+use hyper::{
+    header::{HeaderValue, AUTHORIZATION, WWW_AUTHENTICATE},
+    StatusCode,
+};
+use ring::{constant_time, signature};
+use serde::de::DeserializeOwned;
+use serde_json::{Map, Value};
+use sha2::{Digest, Sha256};
+use std::{
+    collections::HashMap,
+    error::Error,
+    fmt,
+    sync::Arc,
+    time::{Duration, SystemTime},
+};
+
+/// The `AuthRejection` struct is the `401 Unauthorized` answer to a request whose credentials are
+/// missing or invalid.
+///
+/// # Properties:
+///
+/// * `status`: The status of the response, `401 Unauthorized`.
+/// * `reason`: Why the credentials were refused.
+/// * `challenge`: The `WWW-Authenticate` header telling the client how to authenticate.
+#[derive(Debug, Clone)]
+pub struct AuthRejection {
+    pub status: StatusCode,
+    pub reason: String,
+    challenge: String,
+}
+
+impl AuthRejection {
+    fn new(challenge: String, reason: impl Into<String>) -> Self {
+        AuthRejection { status: StatusCode::UNAUTHORIZED, reason: reason.into(), challenge }
+    }
+}
+
+impl fmt::Display for AuthRejection {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.reason)
+    }
+}
+
+impl Error for AuthRejection {}
+
+impl IntoResponse for AuthRejection {
+    fn into_response(self) -> ServerResponse {
+        let challenge = HeaderValue::from_str(&self.challenge).unwrap_or(HeaderValue::from_static("Bearer"));
+        ServerResponse::text(self.reason).with_status(self.status).with_header(WWW_AUTHENTICATE, challenge)
+    }
+}
+
+/// The `AuthenticatedUser` struct is the user name `BasicAuth` accepted, stored in the request
+/// extensions.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AuthenticatedUser(pub String);
+
+type PasswordCheck = Arc<dyn Fn(&str, &str) -> bool + Send + Sync>;
+
+/// The `BasicAuth` struct is a middleware requiring HTTP Basic credentials (RFC 7617). Accepted
+/// requests carry the user name as an `AuthenticatedUser` extension; others are refused with
+/// `401 Unauthorized` and a challenge for `realm`. Basic credentials travel in clear text, so they
+/// belong behind TLS.
+///
+/// # Properties:
+///
+/// * `realm`: The protection space named in the challenge.
+/// * `users`: The SHA-256 digest of the password of each user.
+/// * `check`: Verifies the credentials of users not listed in `users`.
+#[derive(Clone)]
+pub struct BasicAuth {
+    realm: String,
+    users: HashMap<String, [u8; 32]>,
+    check: Option<PasswordCheck>,
+}
+
+impl BasicAuth {
+    /// The function `new` creates the middleware for `realm`, accepting no user yet.
+    pub fn new(realm: &str) -> Self {
+        BasicAuth { realm: realm.replace(['"', '\\'], ""), users: HashMap::new(), check: None }
+    }
+
+    /// The function `user` accepts `username` with `password`.
+    pub fn user(mut self, username: &str, password: &str) -> Self {
+        self.users.insert(username.to_string(), Sha256::digest(password).into());
+        self
+    }
+
+    /// The function `verify_with` accepts the users `check` returns `true` for, such as users looked
+    /// up in a database. Users added with `user` are checked first.
+    pub fn verify_with<F>(mut self, check: F) -> Self
+    where
+        F: Fn(&str, &str) -> bool + Send + Sync + 'static,
+    {
+        self.check = Some(Arc::new(check));
+        self
+    }
+
+    /// The function `authenticate` checks the `Authorization` header of `request`.
+    ///
+    /// # Returns:
+    ///
+    /// The `authenticate` function returns the user name, or an `AuthRejection` if the credentials
+    /// are missing or wrong.
+    pub fn authenticate(&self, request: &ServerRequest) -> Result<AuthenticatedUser, AuthRejection> {
+        let challenge = format!("Basic realm=\"{}\", charset=\"UTF-8\"", self.realm);
+        let reject = |reason: &str| AuthRejection::new(challenge.clone(), reason);
+        let encoded = credentials(request, "Basic").ok_or_else(|| reject("missing Basic credentials"))?;
+        let decoded = STANDARD.decode(encoded).ok().and_then(|decoded| String::from_utf8(decoded).ok());
+        let (username, password) = decoded
+            .as_deref()
+            .and_then(|decoded| decoded.split_once(':'))
+            .ok_or_else(|| reject("malformed credentials"))?;
+        let accepted = match self.users.get(username) {
+            Some(digest) => constant_time::verify_slices_are_equal(digest, &Sha256::digest(password)).is_ok(),
+            None => self.check.as_ref().is_some_and(|check| check(username, password)),
+        };
+        match accepted {
+            true => Ok(AuthenticatedUser(username.to_string())),
+            false => Err(reject("invalid user name or password")),
+        }
+    }
+}
+
+impl fmt::Debug for BasicAuth {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("BasicAuth").field("realm", &self.realm).field("users", &self.users.len()).finish()
+    }
+}
+
+impl Middleware for BasicAuth {
+    fn handle(&self, mut request: ServerRequest, next: Next) -> HandlerFuture {
+        match self.authenticate(&request) {
+            Ok(user) => {
+                request.extensions_mut().insert(user);
+                next.run(request)
+            }
+            Err(rejection) => Box::pin(async { rejection.into_response() }),
+        }
+    }
+}
+
+/// The `JwtClaims` struct holds the claims of a JSON Web Token `JwtAuth` accepted, stored in the
+/// request extensions.
+#[derive(Debug, Clone, PartialEq)]
+pub struct JwtClaims(Map<String, Value>);
+
+impl JwtClaims {
+    /// The function `get` returns the claim `name`.
+    pub fn get(&self, name: &str) -> Option<&Value> {
+        self.0.get(name)
+    }
+
+    /// The function `subject` returns the `sub` claim, usually the ID of the user.
+    pub fn subject(&self) -> Option<&str> {
+        self.0.get("sub").and_then(Value::as_str)
+    }
+
+    /// The function `deserialize` converts the claims into `T`.
+    pub fn deserialize<T: DeserializeOwned>(&self) -> Result<T, serde_json::Error> {
+        serde_json::from_value(Value::Object(self.0.clone()))
+    }
+}
+
+/// The key verifying token signatures, which also fixes the algorithm tokens must use, so a token
+/// cannot pick a weaker one.
+#[derive(Clone)]
+enum JwtKey {
+    Hs256(Vec<u8>),
+    Rs256(Vec<u8>),
+}
+
+/// The `JwtAuth` struct is a middleware requiring a JSON Web Token (RFC 7519) as a bearer token,
+/// signed with HS256 or RS256. The signature, expiry (`exp`), not-before time (`nbf`) and, when
+/// configured, audience (`aud`) and issuer (`iss`) are checked. Accepted requests carry the claims
+/// as a `JwtClaims` extension; others are refused with `401 Unauthorized`.
+///
+/// # Properties:
+///
+/// * `key`: The key verifying signatures.
+/// * `audience`: The audience tokens must be issued for, if any.
+/// * `issuer`: The issuer tokens must come from, if any.
+/// * `leeway`: The clock skew tolerated when checking `exp` and `nbf`.
+#[derive(Clone)]
+pub struct JwtAuth {
+    key: JwtKey,
+    audience: Option<String>,
+    issuer: Option<String>,
+    leeway: Duration,
+}
+
+impl JwtAuth {
+    /// The function `hs256` creates the middleware for tokens signed with HMAC-SHA256 and `secret`.
+    pub fn hs256(secret: impl Into<Vec<u8>>) -> Self {
+        Self::with_key(JwtKey::Hs256(secret.into()))
+    }
+
+    /// The function `rs256_pem` creates the middleware for tokens signed with RSA-SHA256, verified
+    /// with the public key in `pem`.
+    ///
+    /// # Arguments:
+    ///
+    /// * `pem`: The PEM public key, as a `PUBLIC KEY` (SubjectPublicKeyInfo) block.
+    ///
+    /// # Returns:
+    ///
+    /// The `rs256_pem` function returns a `Result` containing the `JwtAuth`, or an error if `pem`
+    /// holds no RSA public key.
+    pub fn rs256_pem(pem: &[u8]) -> Result<Self, Box<dyn Error>> {
+        let spki = rustls_pemfile::public_keys(&mut &pem[..]).next().ok_or("no public key found in the PEM data")??;
+        let key = rsa_public_key(spki.as_ref()).ok_or("the PEM data holds no RSA public key")?;
+        Ok(Self::with_key(JwtKey::Rs256(key.to_vec())))
+    }
+
+    fn with_key(key: JwtKey) -> Self {
+        JwtAuth { key, audience: None, issuer: None, leeway: Duration::from_secs(60) }
+    }
+
+    /// The function `audience` requires tokens issued for `audience`.
+    pub fn audience(mut self, audience: &str) -> Self {
+        self.audience = Some(audience.to_string());
+        self
+    }
+
+    /// The function `issuer` requires tokens issued by `issuer`.
+    pub fn issuer(mut self, issuer: &str) -> Self {
+        self.issuer = Some(issuer.to_string());
+        self
+    }
+
+    /// The function `leeway` sets the clock skew tolerated when checking `exp` and `nbf`, one minute
+    /// by default.
+    pub fn leeway(mut self, leeway: Duration) -> Self {
+        self.leeway = leeway;
+        self
+    }
+
+    /// The function `verify` checks `token` and returns its claims.
+    ///
+    /// # Returns:
+    ///
+    /// The `verify` function returns the claims, or an `AuthRejection` if the token is malformed,
+    /// its signature is invalid, it expired or is not valid yet, or it was issued for another
+    /// audience or by another issuer.
+    pub fn verify(&self, token: &str) -> Result<JwtClaims, AuthRejection> {
+        let reject = |description: &str| {
+            let challenge = format!("Bearer error=\"invalid_token\", error_description=\"{}\"", description);
+            AuthRejection::new(challenge, format!("invalid token: {}", description))
+        };
+        let mut parts = token.splitn(3, '.');
+        let (Some(header), Some(payload), Some(signature)) = (parts.next(), parts.next(), parts.next()) else {
+            return Err(reject("malformed token"));
+        };
+        let decode = |part: &str| URL_SAFE_NO_PAD.decode(part).map_err(|_| reject("malformed token"));
+        let header: Value = serde_json::from_slice(&decode(header)?).map_err(|_| reject("malformed header"))?;
+        let expected = match self.key {
+            JwtKey::Hs256(_) => "HS256",
+            JwtKey::Rs256(_) => "RS256",
+        };
+        if header.get("alg").and_then(Value::as_str) != Some(expected) {
+            return Err(reject("unexpected signing algorithm"));
+        }
+
+        let signed = &token[..token.len() - signature.len() - 1];
+        let signature = decode(signature)?;
+        let valid = match &self.key {
+            JwtKey::Hs256(secret) => Hmac::<Sha256>::new_from_slice(secret)
+                .map(|mac| mac.chain_update(signed).verify_slice(&signature).is_ok())
+                .unwrap_or(false),
+            JwtKey::Rs256(key) => signature::UnparsedPublicKey::new(&signature::RSA_PKCS1_2048_8192_SHA256, key)
+                .verify(signed.as_bytes(), &signature)
+                .is_ok(),
+        };
+        if !valid {
+            return Err(reject("invalid signature"));
+        }
+
+        let claims = match serde_json::from_slice(&decode(payload)?) {
+            Ok(Value::Object(claims)) => claims,
+            _ => return Err(reject("malformed claims")),
+        };
+        let now = SystemTime::now().duration_since(SystemTime::UNIX_EPOCH).unwrap_or_default().as_secs_f64();
+        let leeway = self.leeway.as_secs_f64();
+        if claims.get("exp").and_then(Value::as_f64).is_some_and(|exp| now > exp + leeway) {
+            return Err(reject("token expired"));
+        }
+        if claims.get("nbf").and_then(Value::as_f64).is_some_and(|nbf| now + leeway < nbf) {
+            return Err(reject("token not valid yet"));
+        }
+        if let Some(audience) = &self.audience {
+            let matches = match claims.get("aud") {
+                Some(Value::String(aud)) => aud == audience,
+                Some(Value::Array(auds)) => auds.iter().any(|aud| aud.as_str() == Some(audience)),
+                _ => false,
+            };
+            if !matches {
+                return Err(reject("unexpected audience"));
+            }
+        }
+        if self.issuer.as_deref().is_some_and(|issuer| claims.get("iss").and_then(Value::as_str) != Some(issuer)) {
+            return Err(reject("unexpected issuer"));
+        }
+        Ok(JwtClaims(claims))
+    }
+}
+
+impl fmt::Debug for JwtAuth {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let algorithm = match self.key {
+            JwtKey::Hs256(_) => "HS256",
+            JwtKey::Rs256(_) => "RS256",
+        };
+        f.debug_struct("JwtAuth")
+            .field("algorithm", &algorithm)
+            .field("audience", &self.audience)
+            .field("issuer", &self.issuer)
+            .field("leeway", &self.leeway)
+            .finish()
+    }
+}
+
+impl Middleware for JwtAuth {
+    fn handle(&self, mut request: ServerRequest, next: Next) -> HandlerFuture {
+        let claims = match credentials(&request, "Bearer") {
+            Some(token) => self.verify(token),
+            None => Err(AuthRejection::new("Bearer".to_string(), "missing bearer token")),
+        };
+        match claims {
+            Ok(claims) => {
+                request.extensions_mut().insert(claims);
+                next.run(request)
+            }
+            Err(rejection) => Box::pin(async { rejection.into_response() }),
+        }
+    }
+}
+
+/// The function `credentials` returns the credentials of the `scheme` authorization scheme sent
+/// with `request`.
+fn credentials<'a>(request: &'a ServerRequest, scheme: &str) -> Option<&'a str> {
+    let authorization = request.headers().get(AUTHORIZATION)?.to_str().ok()?;
+    let (request_scheme, credentials) = authorization.trim().split_once(' ')?;
+    request_scheme.eq_ignore_ascii_case(scheme).then(|| credentials.trim())
+}
+
+/// The function `rsa_public_key` extracts the PKCS#1 `RSAPublicKey` ring verifies with from a DER
+/// SubjectPublicKeyInfo, or returns `None` if the key is not an RSA key.
+fn rsa_public_key(spki: &[u8]) -> Option<&[u8]> {
+    /// The DER encoding of the rsaEncryption object identifier, 1.2.840.113549.1.1.1.
+    const RSA_ENCRYPTION: &[u8] = &[0x06, 0x09, 0x2a, 0x86, 0x48, 0x86, 0xf7, 0x0d, 0x01, 0x01, 0x01];
+
+    // Splits a DER element with the tag `tag` off `input`, returning its contents and the rest.
+    fn element(input: &[u8], tag: u8) -> Option<(&[u8], &[u8])> {
+        let (&first, rest) = input.split_first()?;
+        let (&length, mut rest) = rest.split_first()?;
+        let length = match length {
+            0..=0x7f => usize::from(length),
+            0x81..=0x84 => {
+                let (bytes, tail) = rest.split_at_checked(usize::from(length & 0x7f))?;
+                rest = tail;
+                bytes.iter().fold(0, |length, &byte| (length << 8) | usize::from(byte))
+            }
+            _ => return None,
+        };
+        if first != tag {
+            return None;
+        }
+        rest.split_at_checked(length)
+    }
+
+    let (info, _) = element(spki, 0x30)?;
+    let (algorithm, rest) = element(info, 0x30)?;
+    if !algorithm.starts_with(RSA_ENCRYPTION) {
+        return None;
+    }
+    let (key, _) = element(rest, 0x03)?;
+    // The first byte of a BIT STRING counts the unused bits, always zero for a DER key.
+    key.strip_prefix(&[0])
+}
+
+#[cfg(test)]
+mod test {
+    use crate::http::{BasicAuth, HttpServer, JwtAuth, JwtClaims, Middleware, Next, ServerRequest, ServerResponse};
+    use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
+    use hmac::{Hmac, Mac};
+    use reqwest::{Client, StatusCode};
+    use serde_json::json;
+    use sha2::Sha256;
+
+    fn sign(claims: serde_json::Value, secret: &[u8]) -> String {
+        let header = URL_SAFE_NO_PAD.encode(r#"{"alg":"HS256","typ":"JWT"}"#);
+        let signed = format!("{}.{}", header, URL_SAFE_NO_PAD.encode(claims.to_string()));
+        let signature = Hmac::<Sha256>::new_from_slice(secret).unwrap().chain_update(&signed).finalize();
+        format!("{}.{}", signed, URL_SAFE_NO_PAD.encode(signature.into_bytes()))
+    }
+
+    #[tokio::test]
+    async fn test_basic_and_jwt_auth() {
+        let basic = BasicAuth::new("admin").user("root", "hunter2");
+        let jwt = JwtAuth::hs256("secret").audience("api").issuer("https://auth.example");
+        let server = HttpServer::bind("127.0.0.1:0")
+            .await
+            .unwrap()
+            .get("/api/me", |request: ServerRequest| async move {
+                let claims = request.extensions().get::<JwtClaims>().cloned();
+                ServerResponse::text(claims.and_then(|claims| claims.subject().map(str::to_string)).unwrap_or_default())
+            })
+            .get("/admin", |_| async { ServerResponse::text("admin") })
+            .middleware(move |request: ServerRequest, next: Next| match request.path().starts_with("/admin") {
+                true => basic.handle(request, next),
+                false => jwt.handle(request, next),
+            });
+        let server = server.spawn().unwrap();
+        let base = format!("http://{}", server.addr());
+        let client = Client::new();
+
+        let response = client.get(format!("{}/admin", base)).basic_auth("root", Some("hunter2")).send().await;
+        assert_eq!(response.unwrap().status(), StatusCode::OK);
+        let response = client.get(format!("{}/admin", base)).basic_auth("root", Some("guess")).send().await.unwrap();
+        assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+        assert_eq!(response.headers()["www-authenticate"], "Basic realm=\"admin\", charset=\"UTF-8\"");
+
+        let claims = json!({"sub": "user-7", "aud": ["api"], "iss": "https://auth.example", "exp": 4102444800u64});
+        let token = sign(claims.clone(), b"secret");
+        let response = client.get(format!("{}/api/me", base)).bearer_auth(&token).send().await.unwrap();
+        assert_eq!(response.text().await.unwrap(), "user-7");
+
+        // Forged, expired and foreign tokens are refused.
+        let mut rejected = vec![sign(claims.clone(), b"other secret")];
+        rejected.push(sign(json!({"sub": "user-7", "aud": "api", "iss": "https://auth.example", "exp": 1}), b"secret"));
+        rejected.push(sign(json!({"sub": "user-7", "aud": "web", "iss": "https://auth.example"}), b"secret"));
+        rejected.push(token.replacen("eyJhbGciOiJIUzI1NiIsInR5cCI6IkpXVCJ9", "eyJhbGciOiJub25lIn0", 1));
+        for token in rejected {
+            let response = client.get(format!("{}/api/me", base)).bearer_auth(&token).send().await.unwrap();
+            assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+            assert!(response.headers()["www-authenticate"].to_str().unwrap().contains("invalid_token"));
+        }
+        let response = client.get(format!("{}/api/me", base)).send().await.unwrap();
+        assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+
+        server.shutdown().await;
+        server.await.unwrap();
+    }
+}
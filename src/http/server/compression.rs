@@ -0,0 +1,247 @@
+use super::{
+    middleware::{Middleware, Next},
+    request::ServerRequest,
+    response::ServerResponse,
+    router::HandlerFuture,
+};
+use crate::http::{BoxError, ContentEncoding};
+use async_compression::tokio::bufread::{BrotliEncoder, DeflateEncoder, GzipEncoder, ZstdEncoder};
+use futures_util::TryStreamExt;
+use http_body_util::{BodyExt, StreamBody};
+use hyper::{
+    body::{Body, Frame},
+    header::{
+        HeaderMap, HeaderValue, ACCEPT_ENCODING, ACCEPT_RANGES, CACHE_CONTROL, CONTENT_ENCODING, CONTENT_LENGTH,
+        CONTENT_TYPE, ETAG, VARY,
+    },
+    Method, StatusCode,
+};
+use std::io;
+use tokio::io::AsyncRead;
+use tokio_util::io::{ReaderStream, StreamReader};
+
+/// The smallest body compressed by default, in bytes; smaller ones rarely shrink enough to pay off.
+const DEFAULT_MIN_SIZE: u64 = 1024;
+
+/// The content types compressed by default.
+const DEFAULT_CONTENT_TYPES: [&str; 6] =
+    ["text/*", "application/json", "application/javascript", "application/xml", "application/wasm", "image/svg+xml"];
+
+/// The `Compression` struct is a middleware compressing response bodies with the encoding the client
+/// prefers in its `Accept-Encoding` header. Bodies are compressed as they stream, so large files and
+/// generated responses are not buffered.
+///
+/// Responses are sent unchanged when they are smaller than the minimum size, their content type is
+/// not allowed, they are already encoded or marked `Cache-Control: no-transform`, or they have no
+/// body to compress. Server-Sent Events streams are never compressed, since compressors hold events
+/// back until enough data accumulates.
+///
+/// # Properties:
+///
+/// * `encodings`: The encodings offered, in order of preference when the client has none.
+/// * `min_size`: The smallest body compressed, in bytes.
+/// * `content_types`: The content types compressed; `type/*` matches every subtype.
+#[derive(Debug, Clone)]
+pub struct Compression {
+    encodings: Vec<ContentEncoding>,
+    min_size: u64,
+    content_types: Vec<String>,
+}
+
+impl Compression {
+    /// The function `new` creates the middleware offering Brotli and gzip for text, JSON,
+    /// JavaScript, XML, WebAssembly and SVG bodies of 1 KiB or more.
+    pub fn new() -> Self {
+        Compression {
+            encodings: vec![ContentEncoding::Brotli, ContentEncoding::Gzip],
+            min_size: DEFAULT_MIN_SIZE,
+            content_types: DEFAULT_CONTENT_TYPES.iter().map(|content_type| content_type.to_string()).collect(),
+        }
+    }
+
+    /// The function `encodings` replaces the encodings offered, in order of preference.
+    pub fn encodings(mut self, encodings: impl IntoIterator<Item = ContentEncoding>) -> Self {
+        self.encodings = encodings.into_iter().collect();
+        self
+    }
+
+    /// The function `min_size` sets the smallest body compressed, in bytes. Bodies of unknown size,
+    /// such as streamed ones, are always compressed.
+    pub fn min_size(mut self, min_size: u64) -> Self {
+        self.min_size = min_size;
+        self
+    }
+
+    /// The function `content_types` replaces the content types compressed, such as `text/*` or
+    /// `application/json`.
+    pub fn content_types<'a>(mut self, content_types: impl IntoIterator<Item = &'a str>) -> Self {
+        self.content_types = content_types.into_iter().map(str::to_ascii_lowercase).collect();
+        self
+    }
+
+    /// The function `compressible` checks whether `response` may be compressed, whatever the client
+    /// accepts.
+    fn compressible(&self, response: &ServerResponse) -> bool {
+        let status = response.status();
+        if status.is_informational() || status == StatusCode::NO_CONTENT || status == StatusCode::PARTIAL_CONTENT {
+            return false;
+        }
+        let headers = response.headers();
+        let no_transform = headers
+            .get_all(CACHE_CONTROL)
+            .iter()
+            .filter_map(|value| value.to_str().ok())
+            .any(|value| value.split(',').any(|directive| directive.trim().eq_ignore_ascii_case("no-transform")));
+        if headers.contains_key(CONTENT_ENCODING) || no_transform {
+            return false;
+        }
+        let Some(content_type) = headers.get(CONTENT_TYPE).and_then(|value| value.to_str().ok()) else {
+            return false;
+        };
+        let essence = content_type.split(';').next().unwrap_or_default().trim().to_ascii_lowercase();
+        essence != "text/event-stream"
+            && self.content_types.iter().any(|allowed| match allowed.strip_suffix('*') {
+                Some(prefix) => essence.starts_with(prefix),
+                None => essence == *allowed,
+            })
+    }
+}
+
+impl Default for Compression {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Middleware for Compression {
+    fn handle(&self, request: ServerRequest, next: Next) -> HandlerFuture {
+        let encoding = negotiate(request.headers(), &self.encodings);
+        let is_head = request.method() == Method::HEAD;
+        let policy = self.clone();
+        let response = next.run(request);
+        Box::pin(async move {
+            let response = response.await;
+            if !policy.compressible(&response) {
+                return response;
+            }
+            let (mut parts, body) = response.into_inner().into_parts();
+            if body.size_hint().exact().is_some_and(|size| size < policy.min_size.max(1)) {
+                return ServerResponse::from_inner(hyper::Response::from_parts(parts, body));
+            }
+            parts.headers.append(VARY, HeaderValue::from_static("accept-encoding"));
+            let Some(encoding) = encoding.filter(|_| !is_head && parts.status != StatusCode::NOT_MODIFIED) else {
+                return ServerResponse::from_inner(hyper::Response::from_parts(parts, body));
+            };
+
+            parts.headers.insert(CONTENT_ENCODING, HeaderValue::from_static(encoding.as_str()));
+            parts.headers.remove(CONTENT_LENGTH);
+            parts.headers.remove(ACCEPT_RANGES);
+            // The compressed bytes differ from the original ones, so a strong validator no longer holds.
+            if let Some(etag) = parts.headers.get(ETAG).filter(|etag| !etag.as_bytes().starts_with(b"W/")) {
+                if let Ok(weak) = HeaderValue::from_bytes(&[b"W/", etag.as_bytes()].concat()) {
+                    parts.headers.insert(ETAG, weak);
+                }
+            }
+
+            let reader = StreamReader::new(body.into_data_stream().map_err(io::Error::other));
+            let encoded: Box<dyn AsyncRead + Send + Unpin> = match encoding {
+                ContentEncoding::Gzip => Box::new(GzipEncoder::new(reader)),
+                ContentEncoding::Brotli => Box::new(BrotliEncoder::new(reader)),
+                ContentEncoding::Zstd => Box::new(ZstdEncoder::new(reader)),
+                ContentEncoding::Deflate => Box::new(DeflateEncoder::new(reader)),
+            };
+            let frames = ReaderStream::new(encoded).map_ok(Frame::data).map_err(BoxError::from);
+            ServerResponse::from_inner(hyper::Response::from_parts(parts, StreamBody::new(frames).boxed_unsync()))
+        })
+    }
+}
+
+/// The function `negotiate` picks the encoding among `offered` with the highest quality in the
+/// `Accept-Encoding` header of the request, preferring the earlier one on a tie.
+///
+/// # Returns:
+///
+/// The `negotiate` function returns `None` if the client accepts none of the encodings offered.
+fn negotiate(headers: &HeaderMap, offered: &[ContentEncoding]) -> Option<ContentEncoding> {
+    let mut accepted = Vec::new();
+    for value in headers.get_all(ACCEPT_ENCODING).iter().filter_map(|value| value.to_str().ok()) {
+        for item in value.split(',') {
+            let mut params = item.split(';');
+            let token = params.next().unwrap_or_default().trim().to_ascii_lowercase();
+            let quality = params
+                .filter_map(|param| param.trim().strip_prefix("q="))
+                .find_map(|quality| quality.trim().parse::<f32>().ok())
+                .unwrap_or(1.0);
+            accepted.push((token, quality));
+        }
+    }
+    let quality_of = |token: &str| accepted.iter().find(|(accepted, _)| accepted == token).map(|(_, quality)| *quality);
+
+    let mut best: Option<(ContentEncoding, f32)> = None;
+    for &encoding in offered {
+        let quality = quality_of(encoding.as_str()).or_else(|| quality_of("*")).unwrap_or(0.0);
+        if quality > 0.0 && best.is_none_or(|(_, best)| quality > best) {
+            best = Some((encoding, quality));
+        }
+    }
+    best.map(|(encoding, _)| encoding)
+}
+
+#[cfg(test)]
+mod test {
+    use crate::http::{Compression, ContentEncoding, HttpServer, ServerResponse};
+    use async_compression::tokio::bufread::{BrotliDecoder, GzipDecoder};
+    use reqwest::Client;
+    use tokio::io::AsyncReadExt;
+
+    #[tokio::test]
+    async fn test_compression_negotiation() {
+        let page = "<p>compress me</p>".repeat(200);
+        let server = HttpServer::bind("127.0.0.1:0")
+            .await
+            .unwrap()
+            .get("/page", {
+                let page = page.clone();
+                move |_| {
+                    let page = page.clone();
+                    async move { ServerResponse::text(page) }
+                }
+            })
+            .get("/small", |_| async { ServerResponse::text("tiny") })
+            .get("/image", |_| async { ServerResponse::bytes(vec![7; 4096]) })
+            .middleware(Compression::new().encodings([ContentEncoding::Gzip, ContentEncoding::Brotli]));
+        let server = server.spawn().unwrap();
+        let base = format!("http://{}", server.addr());
+        let client = Client::new();
+        let get = |path: &str, accept: &str| client.get(format!("{}{}", base, path)).header("accept-encoding", accept);
+
+        let response = get("/page", "gzip;q=0.5, br").send().await.unwrap();
+        assert_eq!(response.headers()["content-encoding"], "br");
+        assert_eq!(response.headers()["vary"], "accept-encoding");
+        let body = response.bytes().await.unwrap();
+        assert!(body.len() < page.len());
+        let mut decoded = String::new();
+        BrotliDecoder::new(&body[..]).read_to_string(&mut decoded).await.unwrap();
+        assert_eq!(decoded, page);
+
+        // On a tie the server order decides, and rejected encodings are never used.
+        let response = get("/page", "*").send().await.unwrap();
+        assert_eq!(response.headers()["content-encoding"], "gzip");
+        let body = response.bytes().await.unwrap();
+        let mut decoded = String::new();
+        GzipDecoder::new(&body[..]).read_to_string(&mut decoded).await.unwrap();
+        assert_eq!(decoded, page);
+        let response = get("/page", "gzip;q=0, identity").send().await.unwrap();
+        assert!(!response.headers().contains_key("content-encoding"));
+        assert_eq!(response.text().await.unwrap(), page);
+
+        // Small bodies and content types outside the allowlist are sent as they are.
+        for path in ["/small", "/image"] {
+            let response = get(path, "gzip, br").send().await.unwrap();
+            assert!(!response.headers().contains_key("content-encoding"), "{}", path);
+        }
+
+        server.shutdown().await;
+        server.await.unwrap();
+    }
+}
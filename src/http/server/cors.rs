@@ -0,0 +1,258 @@
+use super::{
+    middleware::{Middleware, Next},
+    request::ServerRequest,
+    response::ServerResponse,
+    router::HandlerFuture,
+};
+use hyper::{
+    header::{
+        HeaderName, HeaderValue, ACCESS_CONTROL_ALLOW_CREDENTIALS, ACCESS_CONTROL_ALLOW_HEADERS,
+        ACCESS_CONTROL_ALLOW_METHODS, ACCESS_CONTROL_ALLOW_ORIGIN, ACCESS_CONTROL_EXPOSE_HEADERS,
+        ACCESS_CONTROL_MAX_AGE, ACCESS_CONTROL_REQUEST_HEADERS, ACCESS_CONTROL_REQUEST_METHOD, ORIGIN, VARY,
+    },
+    HeaderMap, Method, StatusCode,
+};
+use std::time::Duration;
+
+/// The `Cors` struct is a middleware answering the Cross-Origin Resource Sharing checks of browsers:
+/// it replies to preflight requests and adds the `Access-Control-*` headers to the responses sent
+/// to allowed origins. Requests from other origins are passed on unchanged, so browsers refuse to
+/// hand their responses to the page, and their preflight requests are answered with
+/// `403 Forbidden`.
+///
+/// # Properties:
+///
+/// * `origins`: The allowed origins, or `None` to allow any origin.
+/// * `methods`: The methods allowed in cross-origin requests.
+/// * `headers`: The request headers allowed, or `None` to allow those the browser asks for.
+/// * `expose_headers`: The response headers scripts may read besides the safelisted ones.
+/// * `credentials`: Whether requests may carry cookies and HTTP authentication.
+/// * `max_age`: How long browsers may cache the answer to a preflight request.
+#[derive(Debug, Clone)]
+pub struct Cors {
+    origins: Option<Vec<HeaderValue>>,
+    methods: Vec<Method>,
+    headers: Option<Vec<HeaderName>>,
+    expose_headers: Vec<HeaderName>,
+    credentials: bool,
+    max_age: Option<Duration>,
+}
+
+impl Cors {
+    /// The function `new` creates a policy allowing no origin yet, with the `GET`, `HEAD` and `POST`
+    /// methods.
+    pub fn new() -> Self {
+        Cors {
+            origins: Some(Vec::new()),
+            methods: vec![Method::GET, Method::HEAD, Method::POST],
+            headers: Some(Vec::new()),
+            expose_headers: Vec::new(),
+            credentials: false,
+            max_age: None,
+        }
+    }
+
+    /// The function `allow_origin` allows requests from `origin`, such as `https://app.example.com`.
+    /// Origins that are not valid header values are ignored.
+    pub fn allow_origin(mut self, origin: &str) -> Self {
+        if let (Some(origins), Ok(origin)) = (&mut self.origins, HeaderValue::from_str(origin.trim_end_matches('/'))) {
+            origins.push(origin);
+        }
+        self
+    }
+
+    /// The function `allow_any_origin` allows requests from every origin. With credentials allowed,
+    /// the origin of each request is echoed back, since browsers refuse `*` for credentialed
+    /// requests.
+    pub fn allow_any_origin(mut self) -> Self {
+        self.origins = None;
+        self
+    }
+
+    /// The function `allow_methods` replaces the methods allowed in cross-origin requests.
+    pub fn allow_methods(mut self, methods: impl IntoIterator<Item = Method>) -> Self {
+        self.methods = methods.into_iter().collect();
+        self
+    }
+
+    /// The function `allow_headers` replaces the request headers allowed besides the safelisted
+    /// ones. Names that are not valid header names are ignored.
+    pub fn allow_headers<'a>(mut self, headers: impl IntoIterator<Item = &'a str>) -> Self {
+        self.headers = Some(headers.into_iter().filter_map(|name| name.parse().ok()).collect());
+        self
+    }
+
+    /// The function `allow_any_header` allows every request header the browser asks for.
+    pub fn allow_any_header(mut self) -> Self {
+        self.headers = None;
+        self
+    }
+
+    /// The function `expose_headers` lets scripts read the given response headers.
+    pub fn expose_headers<'a>(mut self, headers: impl IntoIterator<Item = &'a str>) -> Self {
+        self.expose_headers = headers.into_iter().filter_map(|name| name.parse().ok()).collect();
+        self
+    }
+
+    /// The function `allow_credentials` sets whether requests may carry cookies and HTTP
+    /// authentication.
+    pub fn allow_credentials(mut self, allow: bool) -> Self {
+        self.credentials = allow;
+        self
+    }
+
+    /// The function `max_age` lets browsers cache the answer to a preflight request for `max_age`.
+    pub fn max_age(mut self, max_age: Duration) -> Self {
+        self.max_age = Some(max_age);
+        self
+    }
+
+    /// The function `allowed_origin` returns the `Access-Control-Allow-Origin` value for a request
+    /// from `origin`, or `None` if the origin is not allowed.
+    fn allowed_origin(&self, origin: &HeaderValue) -> Option<HeaderValue> {
+        match &self.origins {
+            None if self.credentials => Some(origin.clone()),
+            None => Some(HeaderValue::from_static("*")),
+            Some(origins) => {
+                origins.iter().find(|allowed| allowed.as_bytes().eq_ignore_ascii_case(origin.as_bytes())).cloned()
+            }
+        }
+    }
+
+    /// The function `preflight` answers a preflight request from an allowed origin.
+    fn preflight(&self, request: &ServerRequest, allow_origin: HeaderValue) -> ServerResponse {
+        let mut response = ServerResponse::new(StatusCode::NO_CONTENT);
+        let headers = response.headers_mut();
+        add_headers(headers, allow_origin, self.credentials);
+        let methods: Vec<&str> = self.methods.iter().map(Method::as_str).collect();
+        headers.insert(ACCESS_CONTROL_ALLOW_METHODS, join(&methods));
+        let allow_headers = match &self.headers {
+            Some(names) => Some(join(&names.iter().map(HeaderName::as_str).collect::<Vec<_>>())),
+            None => request.headers().get(ACCESS_CONTROL_REQUEST_HEADERS).cloned(),
+        };
+        if let Some(allow_headers) = allow_headers.filter(|value| !value.is_empty()) {
+            headers.insert(ACCESS_CONTROL_ALLOW_HEADERS, allow_headers);
+        }
+        if let Some(max_age) = self.max_age {
+            headers.insert(ACCESS_CONTROL_MAX_AGE, HeaderValue::from(max_age.as_secs()));
+        }
+        headers.append(VARY, HeaderValue::from_static("access-control-request-method, access-control-request-headers"));
+        response
+    }
+}
+
+impl Default for Cors {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Middleware for Cors {
+    fn handle(&self, request: ServerRequest, next: Next) -> HandlerFuture {
+        let Some(origin) = request.headers().get(ORIGIN).cloned() else {
+            return next.run(request);
+        };
+        let allow_origin = self.allowed_origin(&origin);
+        let is_preflight =
+            request.method() == Method::OPTIONS && request.headers().contains_key(ACCESS_CONTROL_REQUEST_METHOD);
+        match (is_preflight, allow_origin) {
+            (true, Some(allow_origin)) => {
+                let response = self.preflight(&request, allow_origin);
+                Box::pin(async { response })
+            }
+            (true, None) => Box::pin(async { ServerResponse::new(StatusCode::FORBIDDEN) }),
+            (false, None) => next.run(request),
+            (false, Some(allow_origin)) => {
+                let credentials = self.credentials;
+                let names: Vec<&str> = self.expose_headers.iter().map(HeaderName::as_str).collect();
+                let expose_headers = (!names.is_empty()).then(|| join(&names));
+                let response = next.run(request);
+                Box::pin(async move {
+                    let mut response = response.await;
+                    add_headers(response.headers_mut(), allow_origin, credentials);
+                    if let Some(expose_headers) = expose_headers {
+                        response.headers_mut().insert(ACCESS_CONTROL_EXPOSE_HEADERS, expose_headers);
+                    }
+                    response
+                })
+            }
+        }
+    }
+}
+
+/// The function `add_headers` adds the headers common to preflight and actual responses.
+fn add_headers(headers: &mut HeaderMap, allow_origin: HeaderValue, credentials: bool) {
+    headers.insert(ACCESS_CONTROL_ALLOW_ORIGIN, allow_origin);
+    if credentials {
+        headers.insert(ACCESS_CONTROL_ALLOW_CREDENTIALS, HeaderValue::from_static("true"));
+    }
+    headers.append(VARY, HeaderValue::from_static("origin"));
+}
+
+/// The function `join` lists `items` in a header value.
+fn join(items: &[&str]) -> HeaderValue {
+    HeaderValue::from_str(&items.join(", ")).unwrap_or(HeaderValue::from_static(""))
+}
+
+#[cfg(test)]
+mod test {
+    use crate::http::{Cors, HttpServer, ServerResponse};
+    use hyper::Method;
+    use reqwest::{Client, StatusCode};
+    use std::time::Duration;
+
+    #[tokio::test]
+    async fn test_cors_preflight_and_responses() {
+        let cors = Cors::new()
+            .allow_origin("https://app.example.com")
+            .allow_methods([Method::GET, Method::PUT])
+            .allow_headers(["content-type", "authorization"])
+            .expose_headers(["x-request-id"])
+            .allow_credentials(true)
+            .max_age(Duration::from_secs(600));
+        let server = HttpServer::bind("127.0.0.1:0")
+            .await
+            .unwrap()
+            .get("/items", |_| async { ServerResponse::text("items") })
+            .middleware(cors);
+        let server = server.spawn().unwrap();
+        let url = format!("http://{}/items", server.addr());
+        let client = Client::new();
+
+        let response = client
+            .request(Method::OPTIONS, &url)
+            .header("origin", "https://app.example.com")
+            .header("access-control-request-method", "PUT")
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::NO_CONTENT);
+        let headers = response.headers();
+        assert_eq!(headers["access-control-allow-origin"], "https://app.example.com");
+        assert_eq!(headers["access-control-allow-methods"], "GET, PUT");
+        assert_eq!(headers["access-control-allow-headers"], "content-type, authorization");
+        assert_eq!(headers["access-control-allow-credentials"], "true");
+        assert_eq!(headers["access-control-max-age"], "600");
+
+        let response = client.get(&url).header("origin", "https://app.example.com").send().await.unwrap();
+        assert_eq!(response.headers()["access-control-allow-origin"], "https://app.example.com");
+        assert_eq!(response.headers()["access-control-expose-headers"], "x-request-id");
+        assert_eq!(response.headers()["vary"], "origin");
+        assert_eq!(response.text().await.unwrap(), "items");
+
+        // Other origins get no CORS headers, and their preflight requests are refused.
+        let response = client.get(&url).header("origin", "https://evil.example").send().await.unwrap();
+        assert!(!response.headers().contains_key("access-control-allow-origin"));
+        let response = client
+            .request(Method::OPTIONS, &url)
+            .header("origin", "https://evil.example")
+            .header("access-control-request-method", "GET")
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::FORBIDDEN);
+
+        server.shutdown().await;
+        server.await.unwrap();
+    }
+}
@@ -0,0 +1,126 @@
+use std::{
+    future::Future,
+    pin::Pin,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+    },
+    time::Duration,
+};
+use crate::server_handle::ShutdownReport;
+use tokio::{sync::watch, task::JoinSet, time::Instant};
+
+/// The `RequestCounter` struct counts the requests a server is handling and has handled.
+#[derive(Default)]
+pub(crate) struct RequestCounter {
+    in_flight: AtomicUsize,
+    completed: AtomicUsize,
+}
+
+impl RequestCounter {
+    /// The function `start` counts a request as in flight until the returned guard is dropped.
+    pub(crate) fn start(self: &Arc<Self>) -> InFlight {
+        self.in_flight.fetch_add(1, Ordering::Relaxed);
+        InFlight(self.clone())
+    }
+}
+
+/// A request being handled; dropping it before `complete` means the request was cancelled.
+pub(crate) struct InFlight(Arc<RequestCounter>);
+
+impl InFlight {
+    pub(crate) fn complete(self) {
+        self.0.completed.fetch_add(1, Ordering::Relaxed);
+    }
+}
+
+impl Drop for InFlight {
+    fn drop(&mut self) {
+        self.0.in_flight.fetch_sub(1, Ordering::Relaxed);
+    }
+}
+
+/// The `ShutdownSignal` struct is attached to the extensions of every request, so work outliving
+/// the request, such as a WebSocket session, can end when the server shuts down.
+#[derive(Clone)]
+pub(crate) struct ShutdownSignal(pub(crate) watch::Receiver<bool>);
+
+impl ShutdownSignal {
+    /// The function `wait` resolves once the server starts shutting down.
+    pub(crate) async fn wait(&mut self) {
+        let _ = self.0.wait_for(|draining| *draining).await;
+    }
+
+    /// The function `is_draining` returns whether the server is shutting down.
+    pub(crate) fn is_draining(&self) -> bool {
+        *self.0.borrow()
+    }
+}
+
+/// The function `drive` serves `connection` until it ends, asking it to close gracefully once
+/// `draining` turns true: the requests in flight are finished, then the connection is closed.
+pub(crate) async fn drive<C, E>(
+    connection: C,
+    mut draining: watch::Receiver<bool>,
+    graceful: fn(Pin<&mut C>),
+) -> Result<(), E>
+where
+    C: Future<Output = Result<(), E>>,
+{
+    tokio::pin!(connection);
+    tokio::select! {
+        result = connection.as_mut() => return result,
+        _ = draining.wait_for(|draining| *draining) => graceful(connection.as_mut()),
+    }
+    connection.await
+}
+
+/// The function `drain` tells every connection to finish its requests and close, waits up to
+/// `timeout` for them, and then aborts the connections still open.
+pub(crate) async fn drain(
+    mut connections: JoinSet<()>,
+    draining: watch::Sender<bool>,
+    requests: &RequestCounter,
+    timeout: Duration,
+) -> ShutdownReport {
+    let (started, completed) = (Instant::now(), requests.completed.load(Ordering::Relaxed));
+    draining.send_replace(true);
+    let _ = tokio::time::timeout(timeout, async { while connections.join_next().await.is_some() {} }).await;
+    let aborted = requests.in_flight.load(Ordering::Relaxed);
+    connections.shutdown().await;
+    ShutdownReport {
+        drained: requests.completed.load(Ordering::Relaxed) - completed,
+        aborted,
+        elapsed: started.elapsed(),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::http::{HttpClient, HttpServer, ServerResponse};
+    use std::time::Duration;
+
+    #[tokio::test]
+    async fn test_shutdown_drains_requests() {
+        for (drain_timeout, drained, aborted) in [(Duration::from_secs(5), 1, 0), (Duration::from_millis(50), 0, 1)] {
+            let server = HttpServer::bind("127.0.0.1:0")
+                .await
+                .unwrap()
+                .drain_timeout(drain_timeout)
+                .get("/slow", |_| async {
+                    tokio::time::sleep(Duration::from_millis(300)).await;
+                    ServerResponse::text("done")
+                });
+            let server = server.spawn().unwrap();
+            let client = HttpClient::new(&format!("http://{}", server.addr()), None).unwrap();
+
+            let request = tokio::spawn(async move { client.get("/slow", None).await.is_ok() });
+            tokio::time::sleep(Duration::from_millis(100)).await;
+            server.shutdown().await;
+            let report = server.await.unwrap();
+            assert_eq!((report.drained, report.aborted), (drained, aborted));
+            // A drained request gets its response; an aborted one loses its connection.
+            assert_eq!(request.await.unwrap(), drained == 1);
+        }
+    }
+}
@@ -0,0 +1,319 @@
+use super::{request::ServerRequest, response::ServerResponse};
+use crate::http::BoxError;
+use futures_util::TryStreamExt;
+use http_body_util::{BodyExt, StreamBody};
+use hyper::{
+    body::Frame,
+    header::{
+        HeaderMap, HeaderValue, ACCEPT_RANGES, CONTENT_LENGTH, CONTENT_RANGE, CONTENT_TYPE, ETAG, IF_MODIFIED_SINCE,
+        IF_NONE_MATCH, IF_RANGE, LAST_MODIFIED, LOCATION, RANGE,
+    },
+    StatusCode,
+};
+use std::{
+    fs::Metadata,
+    io,
+    path::{Component, Path, PathBuf},
+    time::{SystemTime, UNIX_EPOCH},
+};
+use tokio::{
+    fs::{self, File},
+    io::{AsyncReadExt, AsyncSeekExt, SeekFrom},
+};
+use tokio_util::io::ReaderStream;
+
+/// The `ServeDir` struct serves the files below `root` for the routes added with
+/// `Router::serve_dir` and `Router::serve_spa`.
+///
+/// # Properties:
+///
+/// * `root`: The directory the files are served from.
+/// * `spa`: Whether paths without a file extension that match no file get `index.html`, so a
+///   single-page application can handle its own routes.
+pub(crate) struct ServeDir {
+    root: PathBuf,
+    spa: bool,
+}
+
+impl ServeDir {
+    pub(crate) fn new(root: PathBuf, spa: bool) -> Self {
+        ServeDir { root, spa }
+    }
+
+    /// The function `serve` answers `request` with the file named by its `path` capture.
+    pub(crate) async fn serve(&self, request: ServerRequest) -> ServerResponse {
+        let relative = request.param("path").unwrap_or_default();
+        let Some(mut path) = resolve(&self.root, relative) else {
+            return ServerResponse::new(StatusCode::NOT_FOUND);
+        };
+        let mut metadata = fs::metadata(&path).await;
+        if metadata.as_ref().is_ok_and(Metadata::is_dir) {
+            if !request.path().ends_with('/') {
+                let location = match request.query() {
+                    Some(query) => format!("{}/?{}", request.path(), query),
+                    None => format!("{}/", request.path()),
+                };
+                return match HeaderValue::from_str(&location) {
+                    Ok(location) => ServerResponse::new(StatusCode::MOVED_PERMANENTLY).with_header(LOCATION, location),
+                    Err(_) => ServerResponse::new(StatusCode::NOT_FOUND),
+                };
+            }
+            path.push("index.html");
+            metadata = fs::metadata(&path).await;
+        }
+        let is_asset = Path::new(relative).extension().is_some();
+        if self.spa && !is_asset && metadata.as_ref().is_err_and(|e| e.kind() == io::ErrorKind::NotFound) {
+            path = self.root.join("index.html");
+            metadata = fs::metadata(&path).await;
+        }
+
+        let result = match metadata {
+            Ok(metadata) if metadata.is_file() && self.contains(&path).await => {
+                file_response(request.headers(), &path, &metadata).await
+            }
+            Ok(_) => Err(io::ErrorKind::NotFound.into()),
+            Err(e) => Err(e),
+        };
+        result.unwrap_or_else(|e| match e.kind() {
+            io::ErrorKind::NotFound => ServerResponse::new(StatusCode::NOT_FOUND),
+            io::ErrorKind::PermissionDenied => ServerResponse::new(StatusCode::FORBIDDEN),
+            _ => {
+                tracing::warn!("failed to serve {}: {}", path.display(), e);
+                ServerResponse::new(StatusCode::INTERNAL_SERVER_ERROR)
+            }
+        })
+    }
+
+    /// The function `contains` returns whether `path` is still below the root once symbolic links
+    /// are followed, so a link cannot expose files outside the served directory.
+    async fn contains(&self, path: &Path) -> bool {
+        match (fs::canonicalize(&self.root).await, fs::canonicalize(path).await) {
+            (Ok(root), Ok(path)) => path.starts_with(root),
+            _ => false,
+        }
+    }
+}
+
+/// The function `resolve` joins the decoded request path `relative` to `root`, refusing `..`
+/// segments and anything that is not a plain file name, such as drive prefixes or backslashes.
+fn resolve(root: &Path, relative: &str) -> Option<PathBuf> {
+    let mut path = root.to_path_buf();
+    for part in relative.split('/').filter(|part| !part.is_empty() && *part != ".") {
+        let mut components = Path::new(part).components();
+        match (components.next(), components.next()) {
+            (Some(Component::Normal(name)), None) if !part.contains(['\\', '\0']) => path.push(name),
+            _ => return None,
+        }
+    }
+    Some(path)
+}
+
+/// The function `file_response` answers with the file at `path`: `304 Not Modified` when the
+/// client's copy is current, `206 Partial Content` for a satisfiable range, and the whole file
+/// otherwise.
+async fn file_response(headers: &HeaderMap, path: &Path, metadata: &Metadata) -> io::Result<ServerResponse> {
+    let len = metadata.len();
+    let modified = metadata.modified().ok();
+    let etag = entity_tag(len, modified);
+    let last_modified = modified.map(httpdate::fmt_http_date);
+    let validators = |response: ServerResponse| {
+        let response = match HeaderValue::from_str(&etag) {
+            Ok(etag) => response.with_header(ETAG, etag),
+            Err(_) => response,
+        };
+        match last_modified.as_deref().and_then(|date| HeaderValue::from_str(date).ok()) {
+            Some(date) => response.with_header(LAST_MODIFIED, date),
+            None => response,
+        }
+    };
+    if not_modified(headers, &etag, modified) {
+        return Ok(validators(ServerResponse::new(StatusCode::NOT_MODIFIED)));
+    }
+
+    let range = match headers.get(RANGE).and_then(|range| range.to_str().ok()) {
+        Some(range) if if_range_matches(headers, &etag, last_modified.as_deref()) => parse_range(range, len),
+        _ => None,
+    };
+    let (status, start, end) = match range {
+        None => (StatusCode::OK, 0, len),
+        Some(Ok((start, last))) => (StatusCode::PARTIAL_CONTENT, start, last + 1),
+        Some(Err(())) => {
+            let response = ServerResponse::new(StatusCode::RANGE_NOT_SATISFIABLE)
+                .with_header(CONTENT_RANGE, HeaderValue::from_str(&format!("bytes */{}", len)).unwrap());
+            return Ok(validators(response));
+        }
+    };
+
+    let mut file = File::open(path).await?;
+    if start > 0 {
+        file.seek(SeekFrom::Start(start)).await?;
+    }
+    let frames = ReaderStream::new(file.take(end - start)).map_ok(Frame::data).map_err(BoxError::from);
+    let mut response = ServerResponse::from_body(StreamBody::new(frames).boxed_unsync())
+        .with_status(status)
+        .with_header(CONTENT_TYPE, HeaderValue::from_static(content_type(path)))
+        .with_header(CONTENT_LENGTH, HeaderValue::from(end - start))
+        .with_header(ACCEPT_RANGES, HeaderValue::from_static("bytes"));
+    if status == StatusCode::PARTIAL_CONTENT {
+        let content_range = format!("bytes {}-{}/{}", start, end - 1, len);
+        response = response.with_header(CONTENT_RANGE, HeaderValue::from_str(&content_range).unwrap());
+    }
+    Ok(validators(response))
+}
+
+/// The function `entity_tag` derives a strong `ETag` from the size and modification time, which
+/// change whenever the file is rewritten.
+fn entity_tag(len: u64, modified: Option<SystemTime>) -> String {
+    let nanos = modified.and_then(|time| time.duration_since(UNIX_EPOCH).ok()).map_or(0, |time| time.as_nanos());
+    format!("\"{:x}-{:x}\"", len, nanos)
+}
+
+/// The function `not_modified` evaluates `If-None-Match`, or `If-Modified-Since` when it is absent.
+fn not_modified(headers: &HeaderMap, etag: &str, modified: Option<SystemTime>) -> bool {
+    if let Some(tags) = headers.get(IF_NONE_MATCH).and_then(|tags| tags.to_str().ok()) {
+        return tags.split(',').map(str::trim).any(|tag| tag == "*" || tag.trim_start_matches("W/") == etag);
+    }
+    let since = headers.get(IF_MODIFIED_SINCE).and_then(|since| httpdate::parse_http_date(since.to_str().ok()?).ok());
+    match (since, modified) {
+        // HTTP dates have a resolution of one second.
+        (Some(since), Some(modified)) => modified.duration_since(since).map_or(true, |newer| newer.as_secs() == 0),
+        _ => false,
+    }
+}
+
+/// The function `if_range_matches` returns whether a `Range` header applies: without `If-Range`
+/// it always does, with it only while the file still has the given validator.
+fn if_range_matches(headers: &HeaderMap, etag: &str, last_modified: Option<&str>) -> bool {
+    match headers.get(IF_RANGE).and_then(|value| value.to_str().ok()) {
+        Some(value) => value == etag || Some(value) == last_modified,
+        None => true,
+    }
+}
+
+/// The function `parse_range` parses a single `bytes=` range against a file of `len` bytes.
+///
+/// # Returns:
+///
+/// The `parse_range` function returns the first and last byte of the range, `Some(Err(()))` if the
+/// range lies outside the file, or `None` if the header should be ignored because it is malformed
+/// or asks for several ranges.
+fn parse_range(range: &str, len: u64) -> Option<Result<(u64, u64), ()>> {
+    let range = range.trim().strip_prefix("bytes=")?;
+    if range.contains(',') {
+        return None;
+    }
+    let (start, end) = range.split_once('-')?;
+    let (start, end) = (start.trim(), end.trim());
+    Some(match (start.is_empty(), end.is_empty()) {
+        (true, false) => match end.parse::<u64>().ok()? {
+            0 => Err(()),
+            _ if len == 0 => Err(()),
+            suffix => Ok((len.saturating_sub(suffix), len - 1)),
+        },
+        (false, _) => {
+            let start = start.parse::<u64>().ok()?;
+            let end = if end.is_empty() { u64::MAX } else { end.parse::<u64>().ok()? };
+            if start > end {
+                return None;
+            }
+            if start >= len {
+                Err(())
+            } else {
+                Ok((start, end.min(len - 1)))
+            }
+        }
+        (true, true) => return None,
+    })
+}
+
+/// The function `content_type` guesses the media type of `path` from its extension.
+fn content_type(path: &Path) -> &'static str {
+    let extension = path.extension().and_then(|extension| extension.to_str()).unwrap_or_default();
+    match extension.to_ascii_lowercase().as_str() {
+        "html" | "htm" => "text/html; charset=utf-8",
+        "css" => "text/css; charset=utf-8",
+        "js" | "mjs" => "text/javascript; charset=utf-8",
+        "json" | "map" => "application/json",
+        "txt" => "text/plain; charset=utf-8",
+        "csv" => "text/csv; charset=utf-8",
+        "xml" => "application/xml",
+        "svg" => "image/svg+xml",
+        "png" => "image/png",
+        "jpg" | "jpeg" => "image/jpeg",
+        "gif" => "image/gif",
+        "webp" => "image/webp",
+        "avif" => "image/avif",
+        "ico" => "image/x-icon",
+        "woff" => "font/woff",
+        "woff2" => "font/woff2",
+        "ttf" => "font/ttf",
+        "otf" => "font/otf",
+        "wasm" => "application/wasm",
+        "pdf" => "application/pdf",
+        "zip" => "application/zip",
+        "mp3" => "audio/mpeg",
+        "mp4" => "video/mp4",
+        "webm" => "video/webm",
+        _ => "application/octet-stream",
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::http::{HttpClient, HttpServer};
+
+    #[tokio::test]
+    async fn test_serve_dir() {
+        let root = std::env::temp_dir().join(format!("aarambh-files-{}", std::process::id()));
+        std::fs::create_dir_all(root.join("docs")).unwrap();
+        std::fs::write(root.join("app.js"), "console.log('hello');").unwrap();
+        std::fs::write(root.join("index.html"), "<h1>app</h1>").unwrap();
+        std::fs::write(root.join("docs/index.html"), "<h1>docs</h1>").unwrap();
+        let secret = root.parent().unwrap().join("aarambh-secret.txt");
+        std::fs::write(&secret, "secret").unwrap();
+
+        let server = HttpServer::bind("127.0.0.1:0")
+            .await
+            .unwrap()
+            .get("/api/health", |_| async { ServerResponse::text("ok") })
+            .serve_dir("/static", &root)
+            .serve_spa("/", &root);
+        let server = server.spawn().unwrap();
+        let client = HttpClient::new(&format!("http://{}", server.addr()), None).unwrap();
+
+        let response = client.get("/static/app.js", None).await.unwrap();
+        assert_eq!(response.headers()[CONTENT_TYPE], "text/javascript; charset=utf-8");
+        let etag = response.headers()[ETAG].clone();
+        assert_eq!(response.text().await.unwrap(), "console.log('hello');");
+
+        let mut headers = HeaderMap::new();
+        headers.insert(IF_NONE_MATCH, etag);
+        assert_eq!(client.get("/static/app.js", Some(headers)).await.unwrap().status(), StatusCode::NOT_MODIFIED);
+        let mut headers = HeaderMap::new();
+        headers.insert(RANGE, HeaderValue::from_static("bytes=0-6"));
+        let response = client.get("/static/app.js", Some(headers)).await.unwrap();
+        assert_eq!(response.status(), StatusCode::PARTIAL_CONTENT);
+        assert_eq!(response.headers()[CONTENT_RANGE], "bytes 0-6/21");
+        assert_eq!(response.text().await.unwrap(), "console");
+        let mut headers = HeaderMap::new();
+        headers.insert(RANGE, HeaderValue::from_static("bytes=100-"));
+        let response = client.get("/static/app.js", Some(headers)).await.unwrap();
+        assert_eq!(response.status(), StatusCode::RANGE_NOT_SATISFIABLE);
+
+        let response = client.get("/static/docs", None).await.unwrap();
+        assert_eq!(response.text().await.unwrap(), "<h1>docs</h1>");
+        let escaped = client.get("/static/%2e%2e/aarambh-secret.txt", None).await.unwrap();
+        assert_eq!(escaped.status(), StatusCode::NOT_FOUND);
+        assert_eq!(client.get("/static/missing.js", None).await.unwrap().status(), StatusCode::NOT_FOUND);
+        // Routes added first win, and the single-page application gets its client-side routes.
+        assert_eq!(client.get("/api/health", None).await.unwrap().text().await.unwrap(), "ok");
+        assert_eq!(client.get("/settings/profile", None).await.unwrap().text().await.unwrap(), "<h1>app</h1>");
+        assert_eq!(client.get("/missing.png", None).await.unwrap().status(), StatusCode::NOT_FOUND);
+
+        server.shutdown().await;
+        server.await.unwrap();
+        std::fs::remove_dir_all(&root).unwrap();
+        std::fs::remove_file(&secret).unwrap();
+    }
+}
@@ -0,0 +1,317 @@
+use super::{
+    request::ServerRequest,
+    response::{IntoResponse, ServerResponse},
+};
+use http_body_util::BodyDataStream;
+use hyper::{body::Bytes, header::CONTENT_TYPE, Method, StatusCode};
+use serde::de::DeserializeOwned;
+use std::{error::Error, fmt, io, path::Path};
+use tokio::{fs::File, io::AsyncWriteExt};
+
+/// The `Form` struct is a form read from a request: from the query string of `GET` and `HEAD`
+/// requests, and from an `application/x-www-form-urlencoded` body otherwise.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Form<T>(pub T);
+
+impl<T: DeserializeOwned> Form<T> {
+    /// The function `from_request` reads the form of `request` and deserializes it into `T`.
+    ///
+    /// # Returns:
+    ///
+    /// The `from_request` function returns a `Result` containing the value, or a `FormRejection`
+    /// answering `415 Unsupported Media Type` if the body is not URL-encoded, and `400 Bad Request`
+    /// if it cannot be read or deserialized.
+    pub async fn from_request(request: ServerRequest) -> Result<Self, FormRejection> {
+        if request.method() == Method::GET || request.method() == Method::HEAD {
+            let query = request.query().unwrap_or_default();
+            return serde_urlencoded::from_str(query).map(Form).map_err(FormRejection::invalid);
+        }
+        if essence(&request).as_deref() != Some("application/x-www-form-urlencoded") {
+            let reason = "expected an application/x-www-form-urlencoded body";
+            return Err(FormRejection::new(StatusCode::UNSUPPORTED_MEDIA_TYPE, reason));
+        }
+        let body = request.bytes().await.map_err(|e| FormRejection::new(StatusCode::BAD_REQUEST, e.to_string()))?;
+        serde_urlencoded::from_bytes(&body).map(Form).map_err(FormRejection::invalid)
+    }
+}
+
+/// The `MultipartLimits` struct bounds what a `Multipart` body may hold, so uploads cannot exhaust
+/// the memory or disk of the server.
+///
+/// # Properties:
+///
+/// * `max_field_size`: The maximum size of a field, 10 MiB by default.
+/// * `max_body_size`: The maximum size of the whole body, 50 MiB by default.
+/// * `max_fields`: The maximum number of fields, 100 by default.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MultipartLimits {
+    pub max_field_size: u64,
+    pub max_body_size: u64,
+    pub max_fields: usize,
+}
+
+impl Default for MultipartLimits {
+    fn default() -> Self {
+        MultipartLimits { max_field_size: 10 << 20, max_body_size: 50 << 20, max_fields: 100 }
+    }
+}
+
+/// The `Multipart` struct reads the fields of a `multipart/form-data` body one at a time, as they
+/// arrive, so file uploads can be streamed to disk instead of being held in memory.
+///
+/// # Properties:
+///
+/// * `inner`: The parser of the body.
+/// * `fields`: The number of fields read so far.
+/// * `max_fields`: The maximum number of fields.
+pub struct Multipart {
+    inner: multer::Multipart<'static>,
+    fields: usize,
+    max_fields: usize,
+}
+
+impl Multipart {
+    /// The function `from_request` starts reading the `multipart/form-data` body of `request`
+    /// within `limits`.
+    ///
+    /// # Returns:
+    ///
+    /// The `from_request` function returns a `Result` containing the `Multipart`, or a
+    /// `FormRejection` answering `415 Unsupported Media Type` if the body is not multipart or has no
+    /// boundary.
+    pub fn from_request(request: ServerRequest, limits: MultipartLimits) -> Result<Self, FormRejection> {
+        let content_type = request.headers().get(CONTENT_TYPE).and_then(|value| value.to_str().ok());
+        let boundary = content_type
+            .filter(|_| essence(&request).as_deref() == Some("multipart/form-data"))
+            .and_then(|content_type| multer::parse_boundary(content_type).ok())
+            .ok_or_else(|| {
+                FormRejection::new(StatusCode::UNSUPPORTED_MEDIA_TYPE, "expected a multipart/form-data body")
+            })?;
+        let size_limit = multer::SizeLimit::new()
+            .whole_stream(limits.max_body_size)
+            .per_field(limits.max_field_size);
+        let constraints = multer::Constraints::new().size_limit(size_limit);
+        let body = BodyDataStream::new(request.into_body());
+        let inner = multer::Multipart::with_constraints(body, boundary, constraints);
+        Ok(Multipart { inner, fields: 0, max_fields: limits.max_fields })
+    }
+
+    /// The function `next_field` waits for the next field of the body. The previous field must be
+    /// dropped or read first.
+    ///
+    /// # Returns:
+    ///
+    /// The `next_field` function returns `Ok(None)` after the last field, or a `FormRejection`
+    /// answering `413 Payload Too Large` once a limit is exceeded, and `400 Bad Request` if the body
+    /// is malformed.
+    pub async fn next_field(&mut self) -> Result<Option<MultipartField>, FormRejection> {
+        let Some(field) = self.inner.next_field().await.map_err(FormRejection::from_multer)? else {
+            return Ok(None);
+        };
+        self.fields += 1;
+        if self.fields > self.max_fields {
+            let reason = format!("the body has more than {} fields", self.max_fields);
+            return Err(FormRejection::new(StatusCode::PAYLOAD_TOO_LARGE, reason));
+        }
+        Ok(Some(MultipartField { inner: field }))
+    }
+}
+
+/// The `MultipartField` struct is one field of a `Multipart` body, read in chunks, whole, or into a
+/// file.
+pub struct MultipartField {
+    inner: multer::Field<'static>,
+}
+
+impl MultipartField {
+    /// The function `name` returns the name of the field in the form.
+    pub fn name(&self) -> Option<&str> {
+        self.inner.name()
+    }
+
+    /// The function `file_name` returns the name of the uploaded file, if the field is a file. It is
+    /// chosen by the client, so it must not be used as a path without being sanitized.
+    pub fn file_name(&self) -> Option<&str> {
+        self.inner.file_name()
+    }
+
+    /// The function `content_type` returns the content type the client gave the field, if any.
+    pub fn content_type(&self) -> Option<&str> {
+        self.inner.content_type().map(|mime| mime.as_ref())
+    }
+
+    /// The function `chunk` returns the next chunk of the field, or `Ok(None)` at its end.
+    pub async fn chunk(&mut self) -> Result<Option<Bytes>, FormRejection> {
+        self.inner.chunk().await.map_err(FormRejection::from_multer)
+    }
+
+    /// The function `bytes` reads the whole field into memory.
+    pub async fn bytes(self) -> Result<Bytes, FormRejection> {
+        self.inner.bytes().await.map_err(FormRejection::from_multer)
+    }
+
+    /// The function `text` reads the whole field as a UTF-8 string.
+    pub async fn text(self) -> Result<String, FormRejection> {
+        let bytes = self.bytes().await?;
+        String::from_utf8(bytes.to_vec()).map_err(FormRejection::invalid)
+    }
+
+    /// The function `save` streams the field into a new file at `path`, which is removed again if
+    /// the field cannot be read completely.
+    ///
+    /// # Returns:
+    ///
+    /// The `save` function returns a `Result` containing the number of bytes written, or a
+    /// `FormRejection` like `next_field`, answering `500 Internal Server Error` if the file cannot be
+    /// written.
+    pub async fn save(mut self, path: impl AsRef<Path>) -> Result<u64, FormRejection> {
+        let path = path.as_ref();
+        let mut file = File::create(path).await.map_err(|e| FormRejection::io(path, e))?;
+        let mut written = 0;
+        let result = async {
+            while let Some(chunk) = self.chunk().await? {
+                file.write_all(&chunk).await.map_err(|e| FormRejection::io(path, e))?;
+                written += chunk.len() as u64;
+            }
+            file.flush().await.map_err(|e| FormRejection::io(path, e))
+        }
+        .await;
+        if result.is_err() {
+            drop(file);
+            let _ = tokio::fs::remove_file(path).await;
+        }
+        result.map(|_| written)
+    }
+}
+
+/// The function `essence` returns the lowercase media type of the request body, without parameters.
+fn essence(request: &ServerRequest) -> Option<String> {
+    let content_type = request.headers().get(CONTENT_TYPE)?.to_str().ok()?;
+    Some(content_type.split(';').next().unwrap_or_default().trim().to_ascii_lowercase())
+}
+
+/// The `FormRejection` struct is returned when a form or multipart body cannot be read. As the
+/// error of a handler returning a `Result`, it answers the request with its status and reason.
+///
+/// # Properties:
+///
+/// * `status`: The status to answer the request with.
+/// * `reason`: A description of the problem, sent as the response body.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FormRejection {
+    pub status: StatusCode,
+    pub reason: String,
+}
+
+impl FormRejection {
+    fn new(status: StatusCode, reason: impl Into<String>) -> Self {
+        FormRejection { status, reason: reason.into() }
+    }
+
+    fn invalid(e: impl fmt::Display) -> Self {
+        Self::new(StatusCode::BAD_REQUEST, format!("invalid form: {}", e))
+    }
+
+    fn io(path: &Path, e: io::Error) -> Self {
+        tracing::warn!("failed to save an upload to {}: {}", path.display(), e);
+        Self::new(StatusCode::INTERNAL_SERVER_ERROR, "failed to save the upload")
+    }
+
+    fn from_multer(e: multer::Error) -> Self {
+        match e {
+            multer::Error::FieldSizeExceeded { .. } | multer::Error::StreamSizeExceeded { .. } => {
+                Self::new(StatusCode::PAYLOAD_TOO_LARGE, e.to_string())
+            }
+            e => Self::new(StatusCode::BAD_REQUEST, format!("invalid multipart body: {}", e)),
+        }
+    }
+}
+
+impl fmt::Display for FormRejection {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: {}", self.status, self.reason)
+    }
+}
+
+impl Error for FormRejection {}
+
+impl IntoResponse for FormRejection {
+    fn into_response(self) -> ServerResponse {
+        ServerResponse::text(self.reason).with_status(self.status)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::http::{Form, FormRejection, HttpClient, HttpServer, MultipartLimits, ServerRequest};
+    use hyper::{header::HeaderValue, HeaderMap, StatusCode};
+    use serde::Deserialize;
+
+    #[derive(Deserialize)]
+    struct Signup {
+        name: String,
+        age: u8,
+    }
+
+    #[tokio::test]
+    async fn test_form_and_multipart_uploads() {
+        let dir = std::env::temp_dir().join(format!("aarambh-upload-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let uploads = dir.clone();
+        let limits = MultipartLimits { max_field_size: 16, ..Default::default() };
+        let server = HttpServer::bind("127.0.0.1:0")
+            .await
+            .unwrap()
+            .post("/signup", |request: ServerRequest| async move {
+                let Form(signup) = request.form::<Signup>().await?;
+                Ok::<_, FormRejection>(format!("{} is {}", signup.name, signup.age))
+            })
+            .post("/upload", move |request: ServerRequest| {
+                let uploads = uploads.clone();
+                async move {
+                    let mut multipart = request.multipart(limits)?;
+                    let mut saved = Vec::new();
+                    while let Some(field) = multipart.next_field().await? {
+                        let name = field.file_name().or(field.name()).unwrap_or_default().to_string();
+                        let value = match field.file_name() {
+                            Some(_) => field.save(uploads.join("a")).await?.to_string(),
+                            None => field.text().await?,
+                        };
+                        saved.push(format!("{}={}", name, value));
+                    }
+                    Ok::<_, FormRejection>(saved.join(","))
+                }
+            });
+        let server = server.spawn().unwrap();
+        let client = HttpClient::new(&format!("http://{}", server.addr()), None).unwrap();
+
+        let mut headers = HeaderMap::new();
+        headers.insert("content-type", HeaderValue::from_static("application/x-www-form-urlencoded"));
+        let response = client.post("/signup", Some(headers.clone()), Some("name=Asha+R&age=30")).await.unwrap();
+        assert_eq!(response.text().await.unwrap(), "Asha R is 30");
+        let response = client.post("/signup", Some(headers), Some("name=Asha")).await.unwrap();
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+
+        let mut headers = HeaderMap::new();
+        headers.insert("content-type", HeaderValue::from_static("multipart/form-data; boundary=XyZ"));
+        let part = |disposition: &str, body: &str| {
+            format!("--XyZ\r\nContent-Disposition: form-data; {}\r\n\r\n{}\r\n", disposition, body)
+        };
+        let file = part("name=\"file\"; filename=\"a.txt\"", "hello file");
+        let body = part("name=\"title\"", "notes") + &file + "--XyZ--\r\n";
+        let response = client.post("/upload", Some(headers.clone()), Some(&body)).await.unwrap();
+        assert_eq!(response.text().await.unwrap(), "title=notes,a.txt=10");
+        assert_eq!(std::fs::read_to_string(dir.join("a")).unwrap(), "hello file");
+
+        // A field over the limit is refused, and the partial upload removed.
+        std::fs::remove_file(dir.join("a")).unwrap();
+        let body = part("name=\"file\"; filename=\"a.txt\"", &"x".repeat(64)) + "--XyZ--\r\n";
+        let response = client.post("/upload", Some(headers), Some(&body)).await.unwrap();
+        assert_eq!(response.status(), StatusCode::PAYLOAD_TOO_LARGE);
+        assert!(!dir.join("a").exists());
+
+        server.shutdown().await;
+        server.await.unwrap();
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,142 @@
+use hyper_util::{rt::TokioTimer, server::conn::auto};
+use std::time::Duration;
+
+/// The `Http2Settings` struct tunes the HTTP/2 connections of an `HttpServer`, negotiated through
+/// ALPN over TLS or opened with prior knowledge (h2c) over plain TCP. Raising the stream limit and
+/// the flow-control windows lets gRPC-style and high fan-in clients multiplex more requests over a
+/// connection; fields left unset keep the defaults of hyper.
+///
+/// # Properties:
+///
+/// * `max_concurrent_streams`: How many streams a client may open at once.
+/// * `initial_stream_window_size`: The flow-control window of each stream, in bytes.
+/// * `initial_connection_window_size`: The flow-control window of the whole connection, in bytes.
+/// * `adaptive_window`: Whether the windows grow with the measured bandwidth-delay product,
+///   ignoring the fixed window sizes.
+/// * `max_frame_size`: The largest frame the server accepts, in bytes.
+/// * `keep_alive_interval`: How often idle connections are pinged, if at all.
+/// * `keep_alive_timeout`: How long a ping may go unanswered before the connection is closed.
+#[derive(Debug, Clone, Default)]
+pub struct Http2Settings {
+    max_concurrent_streams: Option<u32>,
+    initial_stream_window_size: Option<u32>,
+    initial_connection_window_size: Option<u32>,
+    adaptive_window: bool,
+    max_frame_size: Option<u32>,
+    keep_alive_interval: Option<Duration>,
+    keep_alive_timeout: Option<Duration>,
+}
+
+impl Http2Settings {
+    /// The function `new` creates the settings with the defaults of hyper.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The function `max_concurrent_streams` sets how many streams a client may open at once, 200
+    /// by default.
+    pub fn max_concurrent_streams(mut self, max: u32) -> Self {
+        self.max_concurrent_streams = Some(max);
+        self
+    }
+
+    /// The function `initial_stream_window_size` sets the flow-control window of each stream, 1 MiB
+    /// by default.
+    pub fn initial_stream_window_size(mut self, size: u32) -> Self {
+        self.initial_stream_window_size = Some(size);
+        self
+    }
+
+    /// The function `initial_connection_window_size` sets the flow-control window of the whole
+    /// connection, 1 MiB by default.
+    pub fn initial_connection_window_size(mut self, size: u32) -> Self {
+        self.initial_connection_window_size = Some(size);
+        self
+    }
+
+    /// The function `adaptive_window` makes the flow-control windows follow the measured
+    /// bandwidth-delay product instead of the fixed sizes.
+    pub fn adaptive_window(mut self, enabled: bool) -> Self {
+        self.adaptive_window = enabled;
+        self
+    }
+
+    /// The function `max_frame_size` sets the largest frame the server accepts, 16 KiB by default.
+    pub fn max_frame_size(mut self, size: u32) -> Self {
+        self.max_frame_size = Some(size);
+        self
+    }
+
+    /// The function `keep_alive` pings idle connections every `interval` and closes those not
+    /// answering within `timeout`, detecting clients that vanished without closing.
+    pub fn keep_alive(mut self, interval: Duration, timeout: Duration) -> Self {
+        self.keep_alive_interval = Some(interval);
+        self.keep_alive_timeout = Some(timeout);
+        self
+    }
+
+    /// The function `apply` configures the HTTP/2 side of `builder` with the settings.
+    pub(crate) fn apply<E>(&self, builder: &mut auto::Builder<E>) {
+        let mut http2 = builder.http2();
+        http2.timer(TokioTimer::new()).adaptive_window(self.adaptive_window);
+        if let Some(max) = self.max_concurrent_streams {
+            http2.max_concurrent_streams(max);
+        }
+        if let Some(size) = self.initial_stream_window_size {
+            http2.initial_stream_window_size(size);
+        }
+        if let Some(size) = self.initial_connection_window_size {
+            http2.initial_connection_window_size(size);
+        }
+        if let Some(size) = self.max_frame_size {
+            http2.max_frame_size(size);
+        }
+        if let Some(interval) = self.keep_alive_interval {
+            http2.keep_alive_interval(interval);
+        }
+        if let Some(timeout) = self.keep_alive_timeout {
+            http2.keep_alive_timeout(timeout);
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::http::{Http2Settings, HttpServer};
+    use futures_util::future::join_all;
+    use reqwest::{Client, Version};
+    use std::{sync::Arc, time::Duration};
+
+    #[tokio::test]
+    async fn test_h2c_prior_knowledge() {
+        let settings = Http2Settings::new().max_concurrent_streams(16).initial_stream_window_size(256 * 1024);
+        let server = HttpServer::bind("127.0.0.1:0")
+            .await
+            .unwrap()
+            .http2(settings)
+            .get("/slow", |_| async {
+                tokio::time::sleep(Duration::from_millis(20)).await;
+                "slow"
+            });
+        let server = Arc::new(server);
+        let base = format!("http://{}", server.local_addr().unwrap());
+        let task = server.spawn_shared().unwrap();
+
+        // Clients with prior knowledge multiplex their requests over one connection.
+        let h2c = Client::builder().http2_prior_knowledge().build().unwrap();
+        let responses = join_all((0..32).map(|_| h2c.get(format!("{}/slow", base)).send())).await;
+        for response in responses {
+            let response = response.unwrap();
+            assert_eq!(response.version(), Version::HTTP_2);
+            assert_eq!(response.text().await.unwrap(), "slow");
+        }
+        assert!(server.metrics_prometheus().contains("aarambh_http_server_connections_total 1\n"));
+
+        // The same port keeps serving HTTP/1.1.
+        let response = Client::new().get(format!("{}/slow", base)).send().await.unwrap();
+        assert_eq!(response.version(), Version::HTTP_11);
+
+        server.shutdown().await;
+        task.await.unwrap();
+    }
+}
@@ -0,0 +1,131 @@
+use super::{
+    request::ServerRequest,
+    response::{IntoResponse, ServerResponse},
+};
+use hyper::{header::CONTENT_TYPE, StatusCode};
+use serde::{de::DeserializeOwned, Serialize};
+use std::{error::Error, fmt};
+
+/// The `Json` struct is a JSON body. Returned from a handler it becomes a `200 OK` response with an
+/// `application/json` body; read from a request with `Json::from_request` or `ServerRequest::json`
+/// it is the deserialized body.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Json<T>(pub T);
+
+impl<T: DeserializeOwned> Json<T> {
+    /// The function `from_request` reads the body of `request` and deserializes it into `T`.
+    ///
+    /// # Returns:
+    ///
+    /// The `from_request` function returns a `Result` containing the value, or a `JsonRejection`
+    /// answering `415 Unsupported Media Type` if the request declares a content type other than
+    /// JSON, and `400 Bad Request` if the body cannot be read or deserialized.
+    pub async fn from_request(request: ServerRequest) -> Result<Self, JsonRejection> {
+        if let Some(content_type) = request.headers().get(CONTENT_TYPE) {
+            if !is_json(content_type.to_str().unwrap_or_default()) {
+                let reason = format!("expected an application/json body, got {:?}", content_type);
+                return Err(JsonRejection::new(StatusCode::UNSUPPORTED_MEDIA_TYPE, reason));
+            }
+        }
+        let body = request.bytes().await.map_err(|e| JsonRejection::new(StatusCode::BAD_REQUEST, e.to_string()))?;
+        serde_json::from_slice(&body)
+            .map(Json)
+            .map_err(|e| JsonRejection::new(StatusCode::BAD_REQUEST, format!("invalid JSON body: {}", e)))
+    }
+}
+
+impl<T: Serialize> IntoResponse for Json<T> {
+    fn into_response(self) -> ServerResponse {
+        ServerResponse::json(&self.0)
+    }
+}
+
+/// The function `is_json` returns whether `content_type` is `application/json` or a `+json` type,
+/// ignoring parameters such as the charset.
+fn is_json(content_type: &str) -> bool {
+    let essence = content_type.split(';').next().unwrap_or_default().trim().to_ascii_lowercase();
+    essence == "application/json" || (essence.starts_with("application/") && essence.ends_with("+json"))
+}
+
+/// The `JsonRejection` struct is returned when a request body cannot be read as JSON. As the error
+/// of a handler returning a `Result`, it answers the request with its status and reason.
+///
+/// # Properties:
+///
+/// * `status`: The status to answer the request with.
+/// * `reason`: A description of the problem, sent as the response body.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct JsonRejection {
+    pub status: StatusCode,
+    pub reason: String,
+}
+
+impl JsonRejection {
+    fn new(status: StatusCode, reason: impl Into<String>) -> Self {
+        JsonRejection { status, reason: reason.into() }
+    }
+}
+
+impl fmt::Display for JsonRejection {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: {}", self.status, self.reason)
+    }
+}
+
+impl Error for JsonRejection {}
+
+impl IntoResponse for JsonRejection {
+    fn into_response(self) -> ServerResponse {
+        ServerResponse::text(self.reason).with_status(self.status)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::http::{HttpClient, HttpServer, Json, ServerRequest};
+    use hyper::{header::HeaderValue, HeaderMap, StatusCode};
+    use serde::{Deserialize, Serialize};
+
+    #[derive(Serialize, Deserialize)]
+    struct User {
+        name: String,
+        age: u8,
+    }
+
+    #[tokio::test]
+    async fn test_json_extraction_and_responses() {
+        let server = HttpServer::bind("127.0.0.1:0")
+            .await
+            .unwrap()
+            .post("/users", |request: ServerRequest| async move {
+                let Json(user) = request.json::<User>().await?;
+                let mut headers = HeaderMap::new();
+                headers.insert("location", HeaderValue::from_str(&format!("/users/{}", user.name)).unwrap());
+                Ok::<_, super::JsonRejection>((StatusCode::CREATED, headers, Json(user)))
+            })
+            .get("/teapot", |_| async { (StatusCode::IM_A_TEAPOT, "short and stout") });
+        let server = server.spawn().unwrap();
+        let client = HttpClient::new(&format!("http://{}", server.addr()), None).unwrap();
+
+        let response = client.post("/users", None, Some(r#"{"name":"asha","age":30}"#)).await.unwrap();
+        assert_eq!(response.status(), StatusCode::CREATED);
+        assert_eq!(response.headers()["location"], "/users/asha");
+        assert_eq!(response.headers()["content-type"], "application/json");
+        assert_eq!(response.json::<serde_json::Value>().await.unwrap()["age"], 30);
+
+        let response = client.post("/users", None, Some(r#"{"name":"asha"}"#)).await.unwrap();
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+        assert!(response.text().await.unwrap().contains("missing field `age`"));
+        let mut headers = reqwest::header::HeaderMap::new();
+        headers.insert("content-type", HeaderValue::from_static("text/plain"));
+        let response = client.post("/users", Some(headers), Some("{}")).await.unwrap();
+        assert_eq!(response.status(), StatusCode::UNSUPPORTED_MEDIA_TYPE);
+
+        let response = client.get("/teapot", None).await.unwrap();
+        assert_eq!(response.status(), StatusCode::IM_A_TEAPOT);
+        assert_eq!(response.text().await.unwrap(), "short and stout");
+
+        server.shutdown().await;
+        server.await.unwrap();
+    }
+}
@@ -0,0 +1,170 @@
+use hyper::StatusCode;
+use std::{
+    fmt::Write,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc,
+    },
+};
+use tokio::time::Instant;
+
+/// The upper bounds, in seconds, of the request duration histogram buckets.
+const DURATION_BUCKETS: [f64; 11] = [0.005, 0.01, 0.025, 0.05, 0.1, 0.25, 0.5, 1.0, 2.5, 5.0, 10.0];
+
+/// The `ServerMetrics` struct counts the connections and requests an `HttpServer` serves. It is
+/// updated by the server itself and rendered by `HttpServer::metrics_prometheus`.
+///
+/// # Properties:
+///
+/// * `responses`: The number of responses per status class, from 1xx to 5xx.
+/// * `in_flight`: The number of requests being handled.
+/// * `duration_buckets`: The cumulative request duration histogram, one counter per bucket in
+///   `DURATION_BUCKETS`.
+/// * `duration_count`: The number of observed durations.
+/// * `duration_sum_micros`: The sum of the observed durations in microseconds.
+/// * `connections_accepted`: The number of connections accepted.
+/// * `connections_open`: The number of connections open.
+#[derive(Debug, Default)]
+pub(crate) struct ServerMetrics {
+    responses: [AtomicU64; 5],
+    in_flight: AtomicU64,
+    duration_buckets: [AtomicU64; DURATION_BUCKETS.len()],
+    duration_count: AtomicU64,
+    duration_sum_micros: AtomicU64,
+    connections_accepted: AtomicU64,
+    connections_open: AtomicU64,
+}
+
+impl ServerMetrics {
+    /// The function `connection` counts a connection as open until the returned guard is dropped.
+    pub(crate) fn connection(self: &Arc<Self>) -> OpenConnection {
+        self.connections_accepted.fetch_add(1, Ordering::Relaxed);
+        self.connections_open.fetch_add(1, Ordering::Relaxed);
+        OpenConnection(self.clone())
+    }
+
+    /// The function `request` counts a request as in flight until the returned guard is dropped.
+    pub(crate) fn request(self: &Arc<Self>) -> ObservedRequest {
+        self.in_flight.fetch_add(1, Ordering::Relaxed);
+        ObservedRequest { metrics: self.clone(), started: Instant::now() }
+    }
+
+    /// The function `render_prometheus` renders the counters in the Prometheus text exposition
+    /// format.
+    pub(crate) fn render_prometheus(&self) -> String {
+        let mut out = String::new();
+        let _ = self.write_prometheus(&mut out);
+        out
+    }
+
+    fn write_prometheus(&self, out: &mut String) -> std::fmt::Result {
+        writeln!(out, "# HELP aarambh_http_server_responses_total Responses sent, by status class.")?;
+        writeln!(out, "# TYPE aarambh_http_server_responses_total counter")?;
+        for (class, count) in self.responses.iter().enumerate() {
+            let count = count.load(Ordering::Relaxed);
+            writeln!(out, "aarambh_http_server_responses_total{{status_class=\"{}xx\"}} {}", class + 1, count)?;
+        }
+
+        writeln!(out, "# HELP aarambh_http_server_request_duration_seconds Time until the response was ready.")?;
+        writeln!(out, "# TYPE aarambh_http_server_request_duration_seconds histogram")?;
+        for (bucket, bound) in self.duration_buckets.iter().zip(DURATION_BUCKETS) {
+            let count = bucket.load(Ordering::Relaxed);
+            writeln!(out, "aarambh_http_server_request_duration_seconds_bucket{{le=\"{}\"}} {}", bound, count)?;
+        }
+        let count = self.duration_count.load(Ordering::Relaxed);
+        let sum = self.duration_sum_micros.load(Ordering::Relaxed) as f64 / 1_000_000.0;
+        writeln!(out, "aarambh_http_server_request_duration_seconds_bucket{{le=\"+Inf\"}} {}", count)?;
+        writeln!(out, "aarambh_http_server_request_duration_seconds_sum {}", sum)?;
+        writeln!(out, "aarambh_http_server_request_duration_seconds_count {}", count)?;
+
+        writeln!(out, "# HELP aarambh_http_server_in_flight Requests being handled.")?;
+        writeln!(out, "# TYPE aarambh_http_server_in_flight gauge")?;
+        writeln!(out, "aarambh_http_server_in_flight {}", self.in_flight.load(Ordering::Relaxed))?;
+        writeln!(out, "# HELP aarambh_http_server_connections_total Connections accepted.")?;
+        writeln!(out, "# TYPE aarambh_http_server_connections_total counter")?;
+        writeln!(out, "aarambh_http_server_connections_total {}", self.connections_accepted.load(Ordering::Relaxed))?;
+        writeln!(out, "# HELP aarambh_http_server_connections_open Connections currently open.")?;
+        writeln!(out, "# TYPE aarambh_http_server_connections_open gauge")?;
+        writeln!(out, "aarambh_http_server_connections_open {}", self.connections_open.load(Ordering::Relaxed))?;
+        Ok(())
+    }
+}
+
+/// An open connection, counted until it is dropped.
+pub(crate) struct OpenConnection(Arc<ServerMetrics>);
+
+impl Drop for OpenConnection {
+    fn drop(&mut self) {
+        self.0.connections_open.fetch_sub(1, Ordering::Relaxed);
+    }
+}
+
+/// A request being handled; dropping it before `finish` leaves the request out of the response
+/// counts, as it was cancelled.
+pub(crate) struct ObservedRequest {
+    metrics: Arc<ServerMetrics>,
+    started: Instant,
+}
+
+impl ObservedRequest {
+    /// The function `finish` records the status of the response and how long the request took.
+    pub(crate) fn finish(self, status: StatusCode) {
+        let metrics = &self.metrics;
+        let class = (status.as_u16() / 100).clamp(1, 5) as usize - 1;
+        metrics.responses[class].fetch_add(1, Ordering::Relaxed);
+
+        let duration = self.started.elapsed();
+        let seconds = duration.as_secs_f64();
+        for (bucket, bound) in metrics.duration_buckets.iter().zip(DURATION_BUCKETS) {
+            if seconds <= bound {
+                bucket.fetch_add(1, Ordering::Relaxed);
+            }
+        }
+        metrics.duration_count.fetch_add(1, Ordering::Relaxed);
+        metrics.duration_sum_micros.fetch_add(duration.as_micros() as u64, Ordering::Relaxed);
+    }
+}
+
+impl Drop for ObservedRequest {
+    fn drop(&mut self) {
+        self.metrics.in_flight.fetch_sub(1, Ordering::Relaxed);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::http::{HttpServer, ServerResponse};
+    use reqwest::{Client, StatusCode};
+    use std::sync::Arc;
+
+    #[tokio::test]
+    async fn test_metrics_endpoint() {
+        let server = HttpServer::bind("127.0.0.1:0")
+            .await
+            .unwrap()
+            .get("/ok", |_| async { ServerResponse::text("ok") })
+            .metrics_endpoint("/metrics");
+        let server = Arc::new(server);
+        let base = format!("http://{}", server.local_addr().unwrap());
+        let task = server.spawn_shared().unwrap();
+        let client = Client::new();
+        for path in ["/ok", "/ok", "/missing"] {
+            client.get(format!("{}{}", base, path)).send().await.unwrap().bytes().await.unwrap();
+        }
+
+        let response = client.get(format!("{}/metrics", base)).send().await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        assert!(response.headers()["content-type"].to_str().unwrap().starts_with("text/plain; version=0.0.4"));
+        let text = response.text().await.unwrap();
+        assert!(text.contains("aarambh_http_server_responses_total{status_class=\"2xx\"} 2\n"), "{}", text);
+        assert!(text.contains("aarambh_http_server_responses_total{status_class=\"4xx\"} 1\n"));
+        assert!(text.contains("aarambh_http_server_request_duration_seconds_count 3\n"));
+        // The scrape itself is in flight, over the connection the client keeps open.
+        assert!(text.contains("aarambh_http_server_in_flight 1\n"));
+        assert!(text.contains("aarambh_http_server_connections_open 1\n"));
+        assert!(server.metrics_prometheus().contains("aarambh_http_server_request_duration_seconds_count 4\n"));
+
+        server.shutdown().await;
+        task.await.unwrap();
+    }
+}
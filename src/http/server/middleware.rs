@@ -0,0 +1,136 @@
+use super::{
+    request::ServerRequest,
+    response::ServerResponse,
+    router::{Handler, HandlerFuture, Router},
+};
+use std::{future::Future, sync::Arc};
+
+pub(crate) type Layer = Arc<dyn Middleware>;
+
+/// The `Middleware` trait wraps the handling of a request. A middleware can inspect or change the
+/// request, answer it itself (for example with `401 Unauthorized`), or pass it on with `Next::run`
+/// and inspect or change the response.
+///
+/// It is implemented for async functions and closures taking the request and the `Next` step, such
+/// as `|request: ServerRequest, next: Next| async move { next.run(request).await }`.
+pub trait Middleware: Send + Sync + 'static {
+    /// The function `handle` processes `request`, calling `next.run` to continue down the stack.
+    fn handle(&self, request: ServerRequest, next: Next) -> HandlerFuture;
+}
+
+impl<F, Fut> Middleware for F
+where
+    F: Fn(ServerRequest, Next) -> Fut + Send + Sync + 'static,
+    Fut: Future<Output = ServerResponse> + Send + 'static,
+{
+    fn handle(&self, request: ServerRequest, next: Next) -> HandlerFuture {
+        Box::pin(self(request, next))
+    }
+}
+
+/// What a stack of middleware ends in: the router for the server-wide stack, the route handler for
+/// the stack of a route.
+#[derive(Clone)]
+enum Endpoint {
+    Router(Arc<Router>),
+    Handler(Handler),
+}
+
+/// The `Next` struct is the rest of the middleware stack below the running middleware, ending in
+/// the router or the route handler.
+///
+/// # Properties:
+///
+/// * `layers`: The middleware of the stack, outermost first.
+/// * `index`: The position of the next middleware to run in `layers`.
+/// * `endpoint`: What runs once every middleware has passed the request on.
+#[derive(Clone)]
+pub struct Next {
+    layers: Arc<[Layer]>,
+    index: usize,
+    endpoint: Endpoint,
+}
+
+impl Next {
+    pub(crate) fn router(layers: Arc<[Layer]>, router: Arc<Router>) -> Self {
+        Next { layers, index: 0, endpoint: Endpoint::Router(router) }
+    }
+
+    pub(crate) fn handler(layers: Arc<[Layer]>, handler: Handler) -> Self {
+        Next { layers, index: 0, endpoint: Endpoint::Handler(handler) }
+    }
+
+    /// The function `run` passes `request` to the next middleware, or to the router or handler once
+    /// the stack is exhausted, and returns its response.
+    pub fn run(mut self, request: ServerRequest) -> HandlerFuture {
+        match self.layers.get(self.index).cloned() {
+            Some(layer) => {
+                self.index += 1;
+                layer.handle(request, self)
+            }
+            None => match self.endpoint {
+                Endpoint::Router(router) => router.route_request(request),
+                Endpoint::Handler(handler) => handler(request),
+            },
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::http::{HttpClient, HttpServer, Next, ServerRequest, ServerResponse};
+    use hyper::{header::HeaderValue, StatusCode};
+    use std::sync::{Arc, Mutex};
+
+    #[tokio::test]
+    async fn test_middleware_order_and_scope() {
+        let order = Arc::new(Mutex::new(Vec::new()));
+        let (outer, inner) = (order.clone(), order.clone());
+        let server = HttpServer::bind("127.0.0.1:0")
+            .await
+            .unwrap()
+            .get("/admin", |request| async move {
+                ServerResponse::text(request.headers()["x-user"].to_str().unwrap().to_string())
+            })
+            .route_middleware(|mut request: ServerRequest, next: Next| async move {
+                match request.headers().get("x-token").filter(|token| *token == "secret") {
+                    Some(_) => {
+                        request.headers_mut().insert("x-user", HeaderValue::from_static("admin"));
+                        next.run(request).await
+                    }
+                    None => ServerResponse::new(StatusCode::UNAUTHORIZED),
+                }
+            })
+            .get("/public", |_| async { ServerResponse::text("public") })
+            .middleware(move |request: ServerRequest, next: Next| {
+                let order = outer.clone();
+                async move {
+                    order.lock().unwrap().push("outer");
+                    next.run(request).await.with_header("x-served-by".parse().unwrap(), HeaderValue::from_static("aarambh"))
+                }
+            })
+            .middleware(move |request: ServerRequest, next: Next| {
+                let order = inner.clone();
+                async move {
+                    order.lock().unwrap().push("inner");
+                    next.run(request).await
+                }
+            });
+        let server = server.spawn().unwrap();
+        let client = HttpClient::new(&format!("http://{}", server.addr()), None).unwrap();
+
+        let response = client.get("/public", None).await.unwrap();
+        assert_eq!(response.headers()["x-served-by"], "aarambh");
+        assert_eq!(response.text().await.unwrap(), "public");
+        assert_eq!(client.get("/admin", None).await.unwrap().status(), StatusCode::UNAUTHORIZED);
+        let mut headers = reqwest::header::HeaderMap::new();
+        headers.insert("x-token", HeaderValue::from_static("secret"));
+        assert_eq!(client.get("/admin", Some(headers)).await.unwrap().text().await.unwrap(), "admin");
+        // Server-wide middleware also sees requests no route matches.
+        assert_eq!(client.get("/missing", None).await.unwrap().headers()["x-served-by"], "aarambh");
+        assert_eq!(order.lock().unwrap()[..2], ["outer", "inner"]);
+
+        server.shutdown().await;
+        server.await.unwrap();
+    }
+}
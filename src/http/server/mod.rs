@@ -0,0 +1,502 @@
+mod access_log;
+mod auth;
+mod compression;
+mod cors;
+mod drain;
+mod files;
+mod form;
+mod http2;
+mod json;
+mod metrics;
+mod middleware;
+mod params;
+mod probes;
+mod proxy;
+mod rate_limit;
+mod request;
+mod response;
+mod router;
+mod sse;
+mod tls_reload;
+mod websocket;
+
+pub use access_log::{AccessLog, RequestId};
+pub use auth::{AuthRejection, AuthenticatedUser, BasicAuth, JwtAuth, JwtClaims};
+pub use compression::Compression;
+pub use cors::Cors;
+pub use crate::server_handle::ShutdownReport;
+pub use form::{Form, FormRejection, Multipart, MultipartField, MultipartLimits};
+pub use http2::Http2Settings;
+pub use json::{Json, JsonRejection};
+pub use middleware::{Middleware, Next};
+pub use params::{FromPathParams, PathParamError, PathParams};
+pub use probes::HealthChecks;
+pub use proxy::{proxy_to, proxy_with};
+pub use rate_limit::RateLimit;
+pub use request::ServerRequest;
+pub use response::{IntoResponse, ServerResponse};
+pub use router::Router;
+pub use sse::{SseEvent, SseResponse};
+pub use websocket::{WebSocket, WsMessage};
+
+use crate::{error::AarambhNetError, server_handle::ServerHandle, tls::TlsConfig};
+use drain::{RequestCounter, ShutdownSignal};
+use hyper::{
+    body::Incoming,
+    header::{HeaderValue, CONTENT_TYPE},
+    service::service_fn,
+    Method, Request,
+};
+use hyper_util::{
+    rt::{TokioExecutor, TokioIo},
+    server::conn::auto,
+};
+use metrics::ServerMetrics;
+use std::{
+    convert::Infallible, error::Error, future::Future, io, net::SocketAddr, path::PathBuf, sync::Arc, time::Duration,
+};
+use tokio::{
+    io::{AsyncRead, AsyncWrite},
+    net::{TcpListener, TcpStream},
+    sync::{watch, Notify},
+    task::JoinSet,
+    time::timeout,
+};
+use tls_reload::ServerTls;
+use tokio_rustls::TlsAcceptor;
+
+/// How long a client may take to complete the TLS handshake.
+const TLS_HANDSHAKE_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// How long `run` waits for the requests in flight to finish after `shutdown` by default.
+const DEFAULT_DRAIN_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// The `HttpServer` struct serves HTTP/1.1 requests with the handlers of a `Router`, and HTTP/2 to
+/// clients negotiating it over TLS or opening plain connections with the HTTP/2 preface (h2c with
+/// prior knowledge).
+///
+/// # Properties:
+///
+/// * `listener`: The listener accepting client connections.
+/// * `notify`: The notification stopping `run`, sent by `shutdown`.
+/// * `router`: The routes requests are dispatched to.
+/// * `tls`: The acceptor terminating TLS, if the server speaks HTTPS.
+/// * `drain_timeout`: How long shutting down waits for the requests in flight.
+/// * `metrics`: The connection and request counters, updated as the server runs.
+/// * `http2`: The settings of HTTP/2 connections.
+pub struct HttpServer {
+    listener: TcpListener,
+    notify: Arc<Notify>,
+    router: Router,
+    tls: Option<Arc<ServerTls>>,
+    drain_timeout: Duration,
+    metrics: Arc<ServerMetrics>,
+    http2: Arc<Http2Settings>,
+}
+
+impl HttpServer {
+    /// The function `bind` creates an HTTP server listening on `addr`, without routes.
+    ///
+    /// # Arguments:
+    ///
+    /// * `addr`: The address to listen on, for example `127.0.0.1:8080`. Port 0 picks a free port,
+    ///   which `local_addr` reports.
+    ///
+    /// # Returns:
+    ///
+    /// The `bind` function returns a `Result` containing the `HttpServer`, or an error if the address
+    /// cannot be bound.
+    pub async fn bind(addr: &str) -> Result<Self, Box<dyn Error>> {
+        let listener = TcpListener::bind(addr).await.map_err(|e| AarambhNetError::new("bind", e).with_target(addr))?;
+        Ok(HttpServer {
+            listener,
+            notify: Arc::new(Notify::new()),
+            router: Router::new(),
+            tls: None,
+            drain_timeout: DEFAULT_DRAIN_TIMEOUT,
+            metrics: Arc::default(),
+            http2: Arc::default(),
+        })
+    }
+
+    /// The function `with_tls` makes the server speak HTTPS with the certificate of `tls`. Clients
+    /// negotiating `h2` through ALPN are served HTTP/2, the others HTTP/1.1. Plain HTTP clients fail
+    /// the handshake; serve them a redirect from a second server with `Router::redirect_to_https`.
+    ///
+    /// # Returns:
+    ///
+    /// The `with_tls` function returns a `Result` containing the `HttpServer`, or an error if the
+    /// rustls configuration cannot be built.
+    pub fn with_tls(mut self, tls: &TlsConfig) -> Result<Self, Box<dyn Error>> {
+        self.tls = Some(Arc::new(ServerTls::new(tls).map_err(|e| AarambhNetError::new("configure TLS", e))?));
+        Ok(self)
+    }
+
+    /// The function `watch_tls_files` makes the server speak HTTPS like `with_tls`, with the
+    /// certificate read from PEM files that are checked for changes every `interval` while the
+    /// server runs. A changed certificate, such as one renewed by a Let's Encrypt client, is
+    /// presented to new connections without dropping the established ones; if it cannot be loaded,
+    /// the current one stays in use.
+    ///
+    /// # Returns:
+    ///
+    /// The `watch_tls_files` function returns a `Result` containing the `HttpServer`, or an error if
+    /// the files cannot be read or do not hold a usable certificate and key.
+    pub fn watch_tls_files(
+        mut self,
+        cert_path: impl Into<PathBuf>,
+        key_path: impl Into<PathBuf>,
+        interval: Duration,
+    ) -> Result<Self, Box<dyn Error>> {
+        self.tls = Some(Arc::new(ServerTls::watching(cert_path.into(), key_path.into(), interval)?));
+        Ok(self)
+    }
+
+    /// The function `reload_tls` replaces the certificate of a running HTTPS server with the one of
+    /// `tls`. New connections are handshaked with it, while established ones are left untouched.
+    ///
+    /// # Returns:
+    ///
+    /// The `reload_tls` function returns a `Result`, with an error if the server does not speak
+    /// HTTPS or the rustls configuration cannot be built.
+    pub fn reload_tls(&self, tls: &TlsConfig) -> Result<(), Box<dyn Error>> {
+        let server_tls = self.tls.as_ref().ok_or("the server does not speak HTTPS")?;
+        server_tls.reload(tls).map_err(|e| AarambhNetError::new("reload TLS", e))?;
+        Ok(())
+    }
+
+    /// The function `http2` sets the stream and flow-control limits of HTTP/2 connections.
+    pub fn http2(mut self, settings: Http2Settings) -> Self {
+        self.http2 = Arc::new(settings);
+        self
+    }
+
+    /// The function `drain_timeout` sets how long `run` lets the requests in flight finish after
+    /// `shutdown` before closing their connections. It defaults to 30 seconds.
+    pub fn drain_timeout(mut self, drain_timeout: Duration) -> Self {
+        self.drain_timeout = drain_timeout;
+        self
+    }
+
+    /// The function `local_addr` returns the address the server listens on.
+    pub fn local_addr(&self) -> io::Result<SocketAddr> {
+        self.listener.local_addr()
+    }
+
+    /// The function `metrics_prometheus` renders the request counts, in-flight requests, latency
+    /// histogram and connection counts of the server in the Prometheus text exposition format.
+    pub fn metrics_prometheus(&self) -> String {
+        self.metrics.render_prometheus()
+    }
+
+    /// The function `metrics_endpoint` adds a `GET` route at `path`, usually `/metrics`, answering
+    /// Prometheus scrapes with `metrics_prometheus`. Add it before routes it would otherwise be
+    /// shadowed by, and after middleware such as authentication that should not apply to it.
+    pub fn metrics_endpoint(mut self, path: &str) -> Self {
+        let metrics = self.metrics.clone();
+        self.router = self.router.get(path, move |_| {
+            let body = metrics.render_prometheus();
+            async move {
+                let content_type = HeaderValue::from_static("text/plain; version=0.0.4; charset=utf-8");
+                ServerResponse::text(body).with_header(CONTENT_TYPE, content_type)
+            }
+        });
+        self
+    }
+
+    /// The function `with_router` replaces the routes of the server with `router`.
+    pub fn with_router(mut self, router: Router) -> Self {
+        self.router = router;
+        self
+    }
+
+    /// The function `get` adds a route for `GET` requests to `path`, like `Router::get`.
+    pub fn get<H, F>(mut self, path: &str, handler: H) -> Self
+    where
+        H: Fn(ServerRequest) -> F + Send + Sync + 'static,
+        F: Future + Send + 'static,
+        F::Output: IntoResponse,
+    {
+        self.router = self.router.get(path, handler);
+        self
+    }
+
+    /// The function `post` adds a route for `POST` requests to `path`, like `Router::post`.
+    pub fn post<H, F>(mut self, path: &str, handler: H) -> Self
+    where
+        H: Fn(ServerRequest) -> F + Send + Sync + 'static,
+        F: Future + Send + 'static,
+        F::Output: IntoResponse,
+    {
+        self.router = self.router.post(path, handler);
+        self
+    }
+
+    /// The function `put` adds a route for `PUT` requests to `path`, like `Router::put`.
+    pub fn put<H, F>(mut self, path: &str, handler: H) -> Self
+    where
+        H: Fn(ServerRequest) -> F + Send + Sync + 'static,
+        F: Future + Send + 'static,
+        F::Output: IntoResponse,
+    {
+        self.router = self.router.put(path, handler);
+        self
+    }
+
+    /// The function `delete` adds a route for `DELETE` requests to `path`, like `Router::delete`.
+    pub fn delete<H, F>(mut self, path: &str, handler: H) -> Self
+    where
+        H: Fn(ServerRequest) -> F + Send + Sync + 'static,
+        F: Future + Send + 'static,
+        F::Output: IntoResponse,
+    {
+        self.router = self.router.delete(path, handler);
+        self
+    }
+
+    /// The function `any` adds routes for every common method to `path`, like `Router::any`.
+    pub fn any<H, F>(mut self, path: &str, handler: H) -> Self
+    where
+        H: Fn(ServerRequest) -> F + Send + Sync + 'static,
+        F: Future + Send + 'static,
+        F::Output: IntoResponse,
+    {
+        self.router = self.router.any(path, handler);
+        self
+    }
+
+    /// The function `route_with_params` adds a route whose handler receives the typed captures of
+    /// `path`, like `Router::route_with_params`.
+    pub fn route_with_params<P, H, F>(mut self, method: Method, path: &str, handler: H) -> Self
+    where
+        P: FromPathParams + 'static,
+        H: Fn(ServerRequest, P) -> F + Send + Sync + 'static,
+        F: Future + Send + 'static,
+        F::Output: IntoResponse,
+    {
+        self.router = self.router.route_with_params(method, path, handler);
+        self
+    }
+
+    /// The function `ws` adds a WebSocket route, like `Router::ws`.
+    pub fn ws<H, F>(mut self, path: &str, handler: H) -> Self
+    where
+        H: Fn(ServerRequest, WebSocket) -> F + Send + Sync + 'static,
+        F: Future<Output = ()> + Send + 'static,
+    {
+        self.router = self.router.ws(path, handler);
+        self
+    }
+
+    /// The function `health_checks` adds the `/healthz` and `/readyz` endpoints, like
+    /// `Router::health_checks`.
+    pub fn health_checks(mut self, checks: HealthChecks) -> Self {
+        self.router = self.router.health_checks(checks);
+        self
+    }
+
+    /// The function `serve_dir` serves the files below `path` under `route`, like
+    /// `Router::serve_dir`.
+    pub fn serve_dir(mut self, route: &str, path: impl Into<PathBuf>) -> Self {
+        self.router = self.router.serve_dir(route, path);
+        self
+    }
+
+    /// The function `serve_spa` serves a single-page application from `path` under `route`, like
+    /// `Router::serve_spa`.
+    pub fn serve_spa(mut self, route: &str, path: impl Into<PathBuf>) -> Self {
+        self.router = self.router.serve_spa(route, path);
+        self
+    }
+
+    /// The function `middleware` adds server-wide middleware, like `Router::middleware`.
+    pub fn middleware(mut self, middleware: impl Middleware) -> Self {
+        self.router = self.router.middleware(middleware);
+        self
+    }
+
+    /// The function `route_middleware` adds middleware to the routes added so far, like
+    /// `Router::route_middleware`.
+    pub fn route_middleware(mut self, middleware: impl Middleware) -> Self {
+        self.router = self.router.route_middleware(middleware);
+        self
+    }
+
+    /// The function `run` accepts connections and serves their requests until `shutdown` is called.
+    /// Each connection runs on a task of its own. Once shut down, the server stops accepting, lets
+    /// the requests in flight finish while closing idle connections, and after the drain timeout
+    /// closes the connections still open.
+    ///
+    /// # Returns:
+    ///
+    /// The `run` function returns a `ShutdownReport` counting the requests drained and aborted once
+    /// the server has shut down.
+    pub async fn run(&self) -> Result<ShutdownReport, Box<dyn Error>> {
+        let router = Arc::new(self.router.clone());
+        let requests = Arc::new(RequestCounter::default());
+        let (draining, drain_signal) = watch::channel(false);
+        let mut connections = JoinSet::new();
+        let watch_tls = async {
+            match &self.tls {
+                Some(tls) => tls.watch().await,
+                None => std::future::pending().await,
+            }
+        };
+        tokio::pin!(watch_tls);
+        loop {
+            tokio::select! {
+                Ok((socket, remote_addr)) = self.listener.accept() => {
+                    let connection = Connection {
+                        remote_addr,
+                        router: router.clone(),
+                        requests: requests.clone(),
+                        draining: drain_signal.clone(),
+                        secure: self.tls.is_some(),
+                        metrics: self.metrics.clone(),
+                        http2: self.http2.clone(),
+                    };
+                    match &self.tls {
+                        Some(tls) => connections.spawn(serve_tls(tls.acceptor(), socket, connection)),
+                        None => connections.spawn(serve_connection(socket, connection, false)),
+                    };
+                }
+                Some(_) = connections.join_next(), if !connections.is_empty() => {}
+                _ = &mut watch_tls => {}
+                _ = self.notify.notified() => break,
+            }
+        }
+        Ok(drain::drain(connections, draining, &requests, self.drain_timeout).await)
+    }
+
+    /// The function `spawn` runs the server with `run` on a task of its own.
+    ///
+    /// # Returns:
+    ///
+    /// The `spawn` function returns a `ServerHandle` to shut the server down and await its
+    /// `ShutdownReport`, or an error if the address of the listener cannot be read.
+    pub fn spawn(self) -> io::Result<ServerHandle<ShutdownReport>> {
+        Arc::new(self).spawn_shared()
+    }
+
+    /// The function `spawn_shared` runs the server like `spawn`, leaving it shared with the caller,
+    /// which can go on using it while it runs.
+    pub(crate) fn spawn_shared(self: &Arc<Self>) -> io::Result<ServerHandle<ShutdownReport>> {
+        let (addr, notify, server) = (self.local_addr()?, self.notify.clone(), self.clone());
+        let run = async move { server.run().await.map_err(|e| e.to_string()) };
+        Ok(ServerHandle::spawn(addr, move || notify.notify_one(), run))
+    }
+
+    /// The `shutdown` function notifies the server to stop accepting connections and return from
+    /// `run` once the requests in flight are drained.
+    pub async fn shutdown(&self) {
+        self.notify.notify_one();
+    }
+}
+
+/// The `Connection` struct holds what serving one client connection needs.
+///
+/// # Properties:
+///
+/// * `remote_addr`: The address of the client.
+/// * `router`: The routes requests are dispatched to.
+/// * `requests`: The counter of the requests the server handles.
+/// * `draining`: Turns true when the server shuts down.
+/// * `secure`: Whether the connection is encrypted with TLS.
+/// * `metrics`: The counters of the server.
+/// * `http2`: The settings of HTTP/2 connections.
+struct Connection {
+    remote_addr: SocketAddr,
+    router: Arc<Router>,
+    requests: Arc<RequestCounter>,
+    draining: watch::Receiver<bool>,
+    secure: bool,
+    metrics: Arc<ServerMetrics>,
+    http2: Arc<Http2Settings>,
+}
+
+/// The function `serve_tls` performs the TLS handshake with a client and serves its requests in
+/// the protocol negotiated through ALPN. Handshakes still running at shutdown are dropped.
+async fn serve_tls(tls: TlsAcceptor, socket: TcpStream, mut connection: Connection) {
+    let remote_addr = connection.remote_addr;
+    let stream = tokio::select! {
+        stream = timeout(TLS_HANDSHAKE_TIMEOUT, tls.accept(socket)) => match stream {
+            Ok(Ok(stream)) => stream,
+            Ok(Err(e)) => return tracing::debug!("TLS handshake with {} failed: {}", remote_addr, e),
+            Err(_) => return tracing::debug!("TLS handshake with {} timed out", remote_addr),
+        },
+        _ = connection.draining.wait_for(|draining| *draining) => return,
+    };
+    let http2_only = stream.get_ref().1.alpn_protocol() == Some(b"h2");
+    serve_connection(stream, connection, http2_only).await
+}
+
+/// The function `serve_connection` serves the requests of one client connection, speaking HTTP/2
+/// if `http2_only` is set and otherwise whichever of HTTP/1.1 and HTTP/2 the client opens the
+/// connection with, and closes it gracefully when the server drains.
+async fn serve_connection<S>(socket: S, connection: Connection, http2_only: bool)
+where
+    S: AsyncRead + AsyncWrite + Unpin + Send + 'static,
+{
+    let Connection { remote_addr, router, requests, draining, secure, metrics, http2 } = connection;
+    let (signal, _open) = (ShutdownSignal(draining.clone()), metrics.connection());
+    let service = service_fn(move |mut request: Request<Incoming>| {
+        let (router, request_guard, observed) = (router.clone(), requests.start(), metrics.request());
+        request.extensions_mut().insert(signal.clone());
+        async move {
+            let response = router.dispatch(ServerRequest::new(request, remote_addr, secure)).await;
+            observed.finish(response.status());
+            request_guard.complete();
+            Ok::<_, Infallible>(response.into_inner())
+        }
+    });
+    let mut builder = auto::Builder::new(TokioExecutor::new());
+    http2.apply(&mut builder);
+    if http2_only {
+        builder = builder.http2_only();
+    }
+    let connection = builder.serve_connection_with_upgrades(TokioIo::new(socket), service);
+    let result = drain::drive(connection, draining, |connection| connection.graceful_shutdown()).await;
+    if let Err(e) = result {
+        tracing::debug!("HTTP connection from {} failed: {}", remote_addr, e);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::http::HttpClient;
+    use hyper::{header::ALLOW, StatusCode};
+
+    #[tokio::test]
+    async fn test_routing_and_shutdown() {
+        let server = HttpServer::bind("127.0.0.1:0")
+            .await
+            .unwrap()
+            .get("/hello", |_| async { ServerResponse::text("hello") })
+            .post("/echo", |request| async move {
+                match request.text().await {
+                    Ok(body) => ServerResponse::text(body).with_status(StatusCode::CREATED),
+                    Err(_) => ServerResponse::new(StatusCode::BAD_REQUEST),
+                }
+            })
+            .route_with_params(Method::GET, "/users/{id}", |_, (id,): (u64,)| async move {
+                ServerResponse::text(format!("user {}", id))
+            });
+        let server = server.spawn().unwrap();
+        let client = HttpClient::new(&format!("http://{}", server.addr()), None).unwrap();
+
+        assert_eq!(client.get("/hello", None).await.unwrap().text().await.unwrap(), "hello");
+        let response = client.post("/echo", None, Some("ping")).await.unwrap();
+        assert_eq!(response.status(), StatusCode::CREATED);
+        assert_eq!(response.text().await.unwrap(), "ping");
+        assert_eq!(client.get("/users/7", None).await.unwrap().text().await.unwrap(), "user 7");
+        assert_eq!(client.get("/users/me", None).await.unwrap().status(), StatusCode::BAD_REQUEST);
+        assert_eq!(client.get("/missing", None).await.unwrap().status(), StatusCode::NOT_FOUND);
+        let response = client.delete("/echo", None).await.unwrap();
+        assert_eq!(response.status(), StatusCode::METHOD_NOT_ALLOWED);
+        assert_eq!(response.headers()[ALLOW], "POST");
+
+        server.shutdown().await;
+        server.await.unwrap();
+    }
+}
@@ -0,0 +1,216 @@
+use crate::http::BoxError;
+use std::{error::Error, fmt, str::FromStr};
+
+/// One segment of a route pattern.
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Segment {
+    Literal(String),
+    Param(String),
+    Wildcard(String),
+}
+
+/// The `Pattern` struct is a parsed route path. `{name}` captures one path segment and `*name`, as
+/// the last segment, captures the rest of the path.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct Pattern {
+    segments: Vec<Segment>,
+}
+
+impl Pattern {
+    /// The function `parse` splits `path` into literal, parameter and wildcard segments.
+    ///
+    /// # Panics:
+    ///
+    /// The `parse` function panics if a wildcard is not the last segment, since such a route could
+    /// never match.
+    pub(crate) fn parse(path: &str) -> Self {
+        let parts: Vec<&str> = path.trim_start_matches('/').split('/').collect();
+        let segments = parts
+            .iter()
+            .enumerate()
+            .map(|(index, part)| {
+                if let Some(name) = part.strip_prefix('*') {
+                    assert!(index == parts.len() - 1, "the wildcard `{}` must be the last segment of `{}`", part, path);
+                    Segment::Wildcard(name.to_string())
+                } else if let Some(name) = part.strip_prefix('{').and_then(|part| part.strip_suffix('}')) {
+                    Segment::Param(name.to_string())
+                } else {
+                    Segment::Literal(part.to_string())
+                }
+            })
+            .collect();
+        Pattern { segments }
+    }
+
+    /// The function `matches` returns the captured parameters if `path` matches the pattern.
+    pub(crate) fn matches(&self, path: &str) -> Option<PathParams> {
+        let mut rest = Some(path.trim_start_matches('/'));
+        let mut params = PathParams::default();
+        for segment in &self.segments {
+            let current = rest?;
+            if let Segment::Wildcard(name) = segment {
+                params.captures.push((name.clone(), percent_decode(current)));
+                return Some(params);
+            }
+            let (part, tail) = match current.split_once('/') {
+                Some((part, tail)) => (part, Some(tail)),
+                None => (current, None),
+            };
+            match segment {
+                Segment::Literal(literal) if literal == part => {}
+                Segment::Param(name) if !part.is_empty() => params.captures.push((name.clone(), percent_decode(part))),
+                _ => return None,
+            }
+            rest = tail;
+        }
+        rest.is_none().then_some(params)
+    }
+}
+
+/// The function `percent_decode` decodes `%XX` escapes, keeping `value` as it is if the result is not
+/// valid UTF-8.
+fn percent_decode(value: &str) -> String {
+    let bytes = value.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        let escape = (bytes[i] == b'%').then(|| value.get(i + 1..i + 3)).flatten();
+        match escape.and_then(|hex| u8::from_str_radix(hex, 16).ok()) {
+            Some(byte) => {
+                decoded.push(byte);
+                i += 3;
+            }
+            None => {
+                decoded.push(bytes[i]);
+                i += 1;
+            }
+        }
+    }
+    String::from_utf8(decoded).unwrap_or_else(|_| value.to_string())
+}
+
+/// The `PathParams` struct holds the segments captured by the `{name}` and `*name` parts of the
+/// route that matched a request, percent-decoded and in pattern order.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct PathParams {
+    captures: Vec<(String, String)>,
+}
+
+impl PathParams {
+    /// The function `get` returns the value captured for `name`.
+    pub fn get(&self, name: &str) -> Option<&str> {
+        self.captures.iter().find(|(key, _)| key == name).map(|(_, value)| value.as_str())
+    }
+
+    /// The function `parse` converts the value captured for `name` into `T`.
+    ///
+    /// # Returns:
+    ///
+    /// The `parse` function returns a `Result` containing the value, or a `PathParamError` if `name`
+    /// was not captured or its value does not parse.
+    pub fn parse<T>(&self, name: &str) -> Result<T, PathParamError>
+    where
+        T: FromStr,
+        T::Err: Into<BoxError>,
+    {
+        let value = self.get(name).ok_or_else(|| PathParamError::new(name, "", "not captured by the route"))?;
+        value.parse().map_err(|e: T::Err| PathParamError::new(name, value, e.into().to_string()))
+    }
+
+    /// The function `iter` returns the captured names and values in pattern order.
+    pub fn iter(&self) -> impl Iterator<Item = (&str, &str)> {
+        self.captures.iter().map(|(name, value)| (name.as_str(), value.as_str()))
+    }
+
+    /// The function `len` returns the number of captured values.
+    pub fn len(&self) -> usize {
+        self.captures.len()
+    }
+
+    /// The function `is_empty` returns whether the route captured nothing.
+    pub fn is_empty(&self) -> bool {
+        self.captures.is_empty()
+    }
+
+    fn parse_at<T>(&self, index: usize) -> Result<T, PathParamError>
+    where
+        T: FromStr,
+        T::Err: Into<BoxError>,
+    {
+        let (name, value) = self.captures.get(index).ok_or_else(|| {
+            PathParamError::new(&format!("#{}", index), "", "the route captures fewer parameters than the handler takes")
+        })?;
+        value.parse().map_err(|e: T::Err| PathParamError::new(name, value, e.into().to_string()))
+    }
+}
+
+/// The `PathParamError` struct is returned when a captured path segment cannot be converted to the
+/// type a handler asked for. `HttpServer` answers such requests with `400 Bad Request`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PathParamError {
+    pub name: String,
+    pub value: String,
+    pub reason: String,
+}
+
+impl PathParamError {
+    fn new(name: &str, value: &str, reason: impl Into<String>) -> Self {
+        PathParamError { name: name.to_string(), value: value.to_string(), reason: reason.into() }
+    }
+}
+
+impl fmt::Display for PathParamError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "invalid path parameter `{}` = {:?}: {}", self.name, self.value, self.reason)
+    }
+}
+
+impl Error for PathParamError {}
+
+/// The `FromPathParams` trait converts the captures of a route into the typed argument of a handler
+/// added with `Router::route_with_params`. It is implemented for tuples of up to four `FromStr`
+/// types, filled from the captures in pattern order.
+pub trait FromPathParams: Sized {
+    /// The function `from_params` builds the value from the captures of the matched route.
+    fn from_params(params: &PathParams) -> Result<Self, PathParamError>;
+}
+
+macro_rules! impl_from_path_params {
+    ($($ty:ident => $index:tt),+) => {
+        impl<$($ty),+> FromPathParams for ($($ty,)+)
+        where
+            $($ty: FromStr, $ty::Err: Into<BoxError>,)+
+        {
+            fn from_params(params: &PathParams) -> Result<Self, PathParamError> {
+                Ok(($(params.parse_at::<$ty>($index)?,)+))
+            }
+        }
+    };
+}
+
+impl_from_path_params!(A => 0);
+impl_from_path_params!(A => 0, B => 1);
+impl_from_path_params!(A => 0, B => 1, C => 2);
+impl_from_path_params!(A => 0, B => 1, C => 2, D => 3);
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_pattern_captures() {
+        let pattern = Pattern::parse("/users/{id}/posts/{post}");
+        let params = pattern.matches("/users/42/posts/hello%20world").unwrap();
+        assert_eq!(params.get("id"), Some("42"));
+        assert_eq!(params.get("post"), Some("hello world"));
+        assert_eq!(<(u64, String)>::from_params(&params).unwrap(), (42, "hello world".to_string()));
+        assert!(<(u64, u64)>::from_params(&params).is_err());
+        assert!(pattern.matches("/users/42/posts").is_none());
+        assert!(pattern.matches("/users//posts/1").is_none());
+
+        let wildcard = Pattern::parse("/static/*path");
+        assert_eq!(wildcard.matches("/static/css/site.css").unwrap().get("path"), Some("css/site.css"));
+        assert!(wildcard.matches("/static").is_none());
+        assert!(Pattern::parse("/").matches("/").unwrap().is_empty());
+    }
+}
@@ -0,0 +1,198 @@
+use super::{drain::ShutdownSignal, request::ServerRequest, response::ServerResponse};
+use futures_util::future::join_all;
+use hyper::StatusCode;
+use serde_json::{json, Map};
+use std::{fmt, future::Future, pin::Pin, sync::Arc, time::Duration};
+use tokio::time::{timeout, Instant};
+
+/// How long a check may run before it counts as failed by default.
+const DEFAULT_CHECK_TIMEOUT: Duration = Duration::from_secs(5);
+
+type CheckFuture = Pin<Box<dyn Future<Output = Result<(), String>> + Send>>;
+type Check = Arc<dyn Fn() -> CheckFuture + Send + Sync>;
+
+/// The `HealthChecks` struct holds the checks behind the `/healthz` and `/readyz` endpoints mounted
+/// with `HttpServer::health_checks`, as polled by load balancers and orchestrators such as
+/// Kubernetes.
+///
+/// `/healthz` runs the liveness checks, which should only fail when the process needs restarting;
+/// `/readyz` runs the readiness checks, such as a database ping or a probe of an upstream, which
+/// fail while the server cannot serve traffic. Checks run concurrently, each with a timeout, and the
+/// endpoint answers `200 OK` if all pass and `503 Service Unavailable` otherwise, with a JSON body
+/// giving the outcome and duration of every check. `/readyz` also fails once the server is shutting
+/// down, so load balancers stop sending it new requests while it drains.
+///
+/// # Properties:
+///
+/// * `liveness`: The named checks of `/healthz`.
+/// * `readiness`: The named checks of `/readyz`.
+/// * `timeout`: How long a check may run before it counts as failed.
+#[derive(Clone)]
+pub struct HealthChecks {
+    liveness: Vec<(String, Check)>,
+    readiness: Vec<(String, Check)>,
+    timeout: Duration,
+}
+
+impl HealthChecks {
+    /// The function `new` creates the endpoints without checks, so both report healthy.
+    pub fn new() -> Self {
+        HealthChecks { liveness: Vec::new(), readiness: Vec::new(), timeout: DEFAULT_CHECK_TIMEOUT }
+    }
+
+    /// The function `liveness` adds the check `name` to `/healthz`. The check fails by returning an
+    /// error, which is reported in the response body.
+    pub fn liveness<F, Fut, E>(mut self, name: &str, check: F) -> Self
+    where
+        F: Fn() -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = Result<(), E>> + Send + 'static,
+        E: fmt::Display,
+    {
+        self.liveness.push((name.to_string(), boxed(check)));
+        self
+    }
+
+    /// The function `readiness` adds the check `name` to `/readyz`, like `liveness`.
+    pub fn readiness<F, Fut, E>(mut self, name: &str, check: F) -> Self
+    where
+        F: Fn() -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = Result<(), E>> + Send + 'static,
+        E: fmt::Display,
+    {
+        self.readiness.push((name.to_string(), boxed(check)));
+        self
+    }
+
+    /// The function `timeout` sets how long a check may run before it counts as failed, 5 seconds by
+    /// default.
+    pub fn timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    /// The function `live` answers `/healthz`.
+    pub(crate) async fn live(&self, _request: ServerRequest) -> ServerResponse {
+        self.report(&self.liveness, None).await
+    }
+
+    /// The function `ready` answers `/readyz`.
+    pub(crate) async fn ready(&self, request: ServerRequest) -> ServerResponse {
+        let draining = request.extensions().get::<ShutdownSignal>().is_some_and(ShutdownSignal::is_draining);
+        self.report(&self.readiness, draining.then_some("shutting down")).await
+    }
+
+    /// The function `report` runs `checks` and renders their outcome, failing with `override_failure`
+    /// regardless of the checks if it is set.
+    async fn report(&self, checks: &[(String, Check)], override_failure: Option<&str>) -> ServerResponse {
+        let outcomes = join_all(checks.iter().map(|(name, check)| async move {
+            let started = Instant::now();
+            let result = match timeout(self.timeout, check()).await {
+                Ok(result) => result,
+                Err(_) => Err(format!("timed out after {:?}", self.timeout)),
+            };
+            (name, result, started.elapsed())
+        }))
+        .await;
+
+        let mut healthy = override_failure.is_none();
+        let mut report = Map::new();
+        for (name, result, elapsed) in outcomes {
+            let mut outcome = json!({ "status": "ok", "duration_ms": elapsed.as_millis() as u64 });
+            if let Err(error) = result {
+                healthy = false;
+                outcome["status"] = json!("fail");
+                outcome["error"] = json!(error);
+            }
+            report.insert(name.clone(), outcome);
+        }
+        let mut body = json!({ "status": if healthy { "ok" } else { "fail" }, "checks": report });
+        if let Some(reason) = override_failure {
+            body["reason"] = json!(reason);
+        }
+        let status = if healthy { StatusCode::OK } else { StatusCode::SERVICE_UNAVAILABLE };
+        ServerResponse::json(&body).with_status(status)
+    }
+}
+
+impl Default for HealthChecks {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl fmt::Debug for HealthChecks {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let names = |checks: &[(String, Check)]| checks.iter().map(|(name, _)| name.clone()).collect::<Vec<_>>();
+        f.debug_struct("HealthChecks")
+            .field("liveness", &names(&self.liveness))
+            .field("readiness", &names(&self.readiness))
+            .field("timeout", &self.timeout)
+            .finish()
+    }
+}
+
+/// The function `boxed` erases the type of `check`, turning its error into a message.
+fn boxed<F, Fut, E>(check: F) -> Check
+where
+    F: Fn() -> Fut + Send + Sync + 'static,
+    Fut: Future<Output = Result<(), E>> + Send + 'static,
+    E: fmt::Display,
+{
+    Arc::new(move || {
+        let check = check();
+        Box::pin(async move { check.await.map_err(|e| e.to_string()) })
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use crate::http::{HealthChecks, HttpServer};
+    use reqwest::StatusCode;
+    use serde_json::Value;
+    use std::{
+        sync::{
+            atomic::{AtomicBool, Ordering},
+            Arc,
+        },
+        time::Duration,
+    };
+
+    #[tokio::test]
+    async fn test_health_and_readiness() {
+        let database_up = Arc::new(AtomicBool::new(true));
+        let checks = HealthChecks::new()
+            .readiness("database", {
+                let database_up = database_up.clone();
+                move || {
+                    let up = database_up.load(Ordering::SeqCst);
+                    async move { if up { Ok(()) } else { Err("connection refused") } }
+                }
+            })
+            .readiness("cache", || async {
+                tokio::time::sleep(Duration::from_millis(5)).await;
+                Ok::<_, String>(())
+            })
+            .timeout(Duration::from_millis(200));
+        let server = HttpServer::bind("127.0.0.1:0").await.unwrap().health_checks(checks).spawn().unwrap();
+        let base = format!("http://{}", server.addr());
+
+        let response = reqwest::get(format!("{}/healthz", base)).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        let response = reqwest::get(format!("{}/readyz", base)).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        let body: Value = response.json().await.unwrap();
+        assert_eq!(body["status"], "ok");
+        assert_eq!(body["checks"]["cache"]["status"], "ok");
+
+        database_up.store(false, Ordering::SeqCst);
+        let response = reqwest::get(format!("{}/readyz", base)).await.unwrap();
+        assert_eq!(response.status(), StatusCode::SERVICE_UNAVAILABLE);
+        let body: Value = response.json().await.unwrap();
+        assert_eq!(body["status"], "fail");
+        assert_eq!(body["checks"]["database"]["error"], "connection refused");
+        assert_eq!(body["checks"]["cache"]["status"], "ok");
+
+        server.shutdown().await;
+        server.await.unwrap();
+    }
+}
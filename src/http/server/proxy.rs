@@ -0,0 +1,186 @@
+use super::{request::ServerRequest, response::ServerResponse, router::HandlerFuture};
+use crate::http::{BoxError, HttpClient};
+use futures_util::TryStreamExt;
+use http_body_util::{BodyExt, StreamBody};
+use hyper::{
+    body::{Body, Frame},
+    header::{
+        HeaderMap, HeaderName, HeaderValue, ACCEPT_ENCODING, CONNECTION, HOST, TE, TRAILER, TRANSFER_ENCODING, UPGRADE,
+    },
+    StatusCode,
+};
+use std::{error::Error, sync::Arc};
+
+/// The hop-by-hop headers (RFC 9110, section 7.6.1), which describe a single connection and are
+/// never forwarded.
+const HOP_BY_HOP: [&str; 4] = ["keep-alive", "proxy-connection", "proxy-authenticate", "proxy-authorization"];
+
+/// The function `proxy_to` creates a handler forwarding requests to `upstream_base_url`, turning
+/// the server into a lightweight gateway. The path and query of each request are appended to the
+/// base URL, so with `http://backend:8080/v1` a request to `/users?page=2` goes to
+/// `http://backend:8080/v1/users?page=2`. Mount it with `Router::any` on a pattern such as
+/// `/api/*path`.
+///
+/// Bodies stream in both directions, redirects and encoded bodies are passed back untouched, and
+/// hop-by-hop headers are dropped. The upstream receives its own `Host`, with the original one in
+/// `X-Forwarded-Host`, the client address appended to `X-Forwarded-For` and the scheme in
+/// `X-Forwarded-Proto`. `X-Forwarded-Host` and `X-Forwarded-Proto` sent by the client are replaced,
+/// so it cannot claim another host or a secure connection. Upstreams that cannot be reached are
+/// answered with `502 Bad Gateway`.
+///
+/// # Returns:
+///
+/// The `proxy_to` function returns a `Result` containing the handler, or an error if
+/// `upstream_base_url` is not a valid URL.
+pub fn proxy_to(
+    upstream_base_url: &str,
+) -> Result<impl Fn(ServerRequest) -> HandlerFuture + Clone + Send + Sync + 'static, Box<dyn Error>> {
+    let client = HttpClient::builder(upstream_base_url).auto_decompress(false).follow_redirects(false).build()?;
+    Ok(proxy_with(client))
+}
+
+/// The function `proxy_with` creates a handler forwarding requests through `client` like
+/// `proxy_to`, for upstreams needing timeouts, retries or other settings of an `HttpClient`. The
+/// client should neither decode bodies nor follow redirects, so the client of the proxy sees the
+/// responses of the upstream as they are.
+pub fn proxy_with(client: HttpClient) -> impl Fn(ServerRequest) -> HandlerFuture + Clone + Send + Sync + 'static {
+    let client = Arc::new(client);
+    move |request| {
+        let client = client.clone();
+        Box::pin(async move { forward(&client, request).await })
+    }
+}
+
+/// The function `forward` sends `request` to the upstream of `client` and streams the response
+/// back.
+async fn forward(client: &HttpClient, request: ServerRequest) -> ServerResponse {
+    let (method, remote_addr) = (request.method().clone(), request.remote_addr());
+    let path_and_query = request.uri().path_and_query().map_or("/", |path| path.as_str()).to_string();
+    let mut headers = request.headers().clone();
+    remove_hop_by_hop(&mut headers);
+
+    let authority = request.uri().authority().and_then(|authority| HeaderValue::from_str(authority.as_str()).ok());
+    match headers.remove(HOST).or(authority) {
+        Some(host) => headers.insert("x-forwarded-host", host),
+        None => headers.remove("x-forwarded-host"),
+    };
+    let forwarded_for = match headers.get("x-forwarded-for").and_then(|value| value.to_str().ok()) {
+        Some(chain) => format!("{}, {}", chain, remote_addr.ip()),
+        None => remote_addr.ip().to_string(),
+    };
+    if let Ok(forwarded_for) = HeaderValue::from_str(&forwarded_for) {
+        headers.insert("x-forwarded-for", forwarded_for);
+    }
+    let scheme = if request.is_secure() { "https" } else { "http" };
+    headers.insert("x-forwarded-proto", HeaderValue::from_static(scheme));
+    // Without this the client would ask for every encoding it knows on behalf of a client that may
+    // not decode them.
+    headers.entry(ACCEPT_ENCODING).or_insert(HeaderValue::from_static("identity"));
+
+    let body = request.into_body();
+    let body = (!body.is_end_stream()).then(|| reqwest::Body::wrap_stream(body.into_data_stream()));
+    let response = match client.forward(method, &path_and_query, headers, body).await {
+        Ok(response) => response.into_inner(),
+        Err(e) => {
+            let message = e.to_string();
+            tracing::debug!("proxying {} for {} failed: {}", path_and_query, remote_addr, message);
+            return ServerResponse::text(format!("bad gateway: {}", message)).with_status(StatusCode::BAD_GATEWAY);
+        }
+    };
+
+    let (status, mut headers) = (response.status(), response.headers().clone());
+    remove_hop_by_hop(&mut headers);
+    let frames = response.bytes_stream().map_ok(Frame::data).map_err(BoxError::from);
+    ServerResponse::from_body(StreamBody::new(frames).boxed_unsync()).with_status(status).with_headers(headers)
+}
+
+/// The function `remove_hop_by_hop` drops the hop-by-hop headers from `headers`, including those
+/// named in `Connection`.
+fn remove_hop_by_hop(headers: &mut HeaderMap) {
+    let named: Vec<HeaderName> = headers
+        .get_all(CONNECTION)
+        .iter()
+        .filter_map(|value| value.to_str().ok())
+        .flat_map(|value| value.split(','))
+        .filter_map(|name| name.trim().parse().ok())
+        .collect();
+    for name in named.into_iter().chain([CONNECTION, TE, TRAILER, TRANSFER_ENCODING, UPGRADE]) {
+        headers.remove(name);
+    }
+    for name in HOP_BY_HOP {
+        headers.remove(name);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::http::{proxy_to, HttpServer, ServerRequest, ServerResponse};
+    use reqwest::{redirect::Policy, Client, StatusCode};
+    use tokio::net::TcpListener;
+
+    #[tokio::test]
+    async fn test_reverse_proxy() {
+        let upstream = HttpServer::bind("127.0.0.1:0")
+            .await
+            .unwrap()
+            .post("/v1/echo", |request: ServerRequest| async move {
+                let headers = request.headers();
+                let seen = format!(
+                    "{} {} host={} fwd-host={} fwd-for={} proto={}",
+                    request.method(),
+                    request.uri(),
+                    headers["host"].to_str().unwrap(),
+                    headers["x-forwarded-host"].to_str().unwrap(),
+                    headers["x-forwarded-for"].to_str().unwrap(),
+                    headers["x-forwarded-proto"].to_str().unwrap(),
+                );
+                format!("{} body={}", seen, request.text().await.unwrap())
+            })
+            .get("/v1/old", |_| async {
+                ServerResponse::new(StatusCode::FOUND).with_header(hyper::header::LOCATION, "/v1/new".parse().unwrap())
+            });
+        let upstream = upstream.spawn().unwrap();
+        let upstream_addr = upstream.addr();
+
+        let closed = TcpListener::bind("127.0.0.1:0").await.unwrap().local_addr().unwrap();
+        let gateway = HttpServer::bind("127.0.0.1:0")
+            .await
+            .unwrap()
+            .any("/down/*path", proxy_to(&format!("http://{}", closed)).unwrap())
+            .any("/*path", proxy_to(&format!("http://{}/v1", upstream_addr)).unwrap());
+        let gateway = gateway.spawn().unwrap();
+        let gateway_addr = gateway.addr();
+        let client = Client::builder().redirect(Policy::none()).build().unwrap();
+
+        let response = client
+            .post(format!("http://{}/echo?x=1", gateway_addr))
+            .header("x-forwarded-for", "10.0.0.9")
+            .header("x-forwarded-host", "spoofed.example")
+            .header("x-forwarded-proto", "https")
+            .body("payload")
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        // The chain of addresses is extended, while the host and scheme claimed by the client are replaced.
+        let expected = format!(
+            "POST /v1/echo?x=1 host={} fwd-host={} fwd-for=10.0.0.9, 127.0.0.1 proto=http body=payload",
+            upstream_addr, gateway_addr
+        );
+        assert_eq!(response.text().await.unwrap(), expected);
+
+        // Redirects reach the client instead of being followed by the proxy.
+        let response = client.get(format!("http://{}/old", gateway_addr)).send().await.unwrap();
+        assert_eq!(response.status(), StatusCode::FOUND);
+        assert_eq!(response.headers()["location"], "/v1/new");
+
+        // An upstream that cannot be reached is a bad gateway.
+        let response = client.get(format!("http://{}/down/status", gateway_addr)).send().await.unwrap();
+        assert_eq!(response.status(), StatusCode::BAD_GATEWAY);
+
+        gateway.shutdown().await;
+        gateway.await.unwrap();
+        upstream.shutdown().await;
+        upstream.await.unwrap();
+    }
+}
@@ -0,0 +1,225 @@
+use super::{
+    middleware::{Middleware, Next},
+    request::ServerRequest,
+    response::ServerResponse,
+    router::HandlerFuture,
+};
+use crate::rate_limit::TokenBucket;
+use hyper::{
+    header::{HeaderName, HeaderValue, AUTHORIZATION, RETRY_AFTER},
+    StatusCode,
+};
+use std::{
+    collections::{BTreeMap, HashMap},
+    sync::{Arc, Mutex},
+};
+
+/// How many buckets the store holds, unless set with `max_keys`, before the least recently used
+/// one is dropped.
+const DEFAULT_MAX_KEYS: usize = 4096;
+
+type KeyExtractor = Arc<dyn Fn(&ServerRequest) -> Option<String> + Send + Sync>;
+
+/// The `RateLimit` struct is a middleware limiting how many requests each client may send, with a
+/// token bucket per client kept in memory. Requests over the limit are answered with
+/// `429 Too Many Requests` and a `Retry-After` header telling the client when to try again.
+///
+/// Clients are told apart by their IP address unless another key is chosen, such as a header or an
+/// API key; requests missing that key fall back to their IP address. A key the client chooses
+/// itself bypasses the limit per IP address: a client sending a new value with every request gets
+/// a new bucket each time, so such keys must be validated, for example by an authentication
+/// middleware running first, or set by a trusted proxy. Added with `HttpServer::route_middleware`,
+/// each instance limits the routes it is added to on its own.
+///
+/// The store holds at most `max_keys` buckets, dropping the least recently used one for a new key.
+///
+/// # Properties:
+///
+/// * `capacity`: The largest burst of requests a client may send.
+/// * `per_second`: The sustained number of requests per second a client may send.
+/// * `key`: Extracts the key telling clients apart.
+/// * `per_route`: Whether each method and path has buckets of its own.
+/// * `max_keys`: How many buckets the store holds at most.
+/// * `buckets`: The bucket of each key.
+pub struct RateLimit {
+    capacity: f64,
+    per_second: f64,
+    key: KeyExtractor,
+    per_route: bool,
+    max_keys: usize,
+    buckets: Mutex<Buckets>,
+}
+
+/// The `Buckets` struct is the store of a `RateLimit`, remembering when each bucket was last used.
+///
+/// # Properties:
+///
+/// * `buckets`: The bucket of each key, with the time it was last used.
+/// * `recent`: The key of each bucket, by the time it was last used.
+/// * `clock`: Counts the requests checked, telling when a bucket was used.
+#[derive(Default)]
+struct Buckets {
+    buckets: HashMap<String, (TokenBucket, u64)>,
+    recent: BTreeMap<u64, String>,
+    clock: u64,
+}
+
+impl RateLimit {
+    /// The function `new` creates a limit allowing each IP address bursts of `capacity` requests and
+    /// `per_second` requests per second after that.
+    pub fn new(capacity: u32, per_second: f64) -> Self {
+        RateLimit {
+            capacity: f64::from(capacity.max(1)),
+            per_second,
+            key: Arc::new(|_| None),
+            per_route: false,
+            max_keys: DEFAULT_MAX_KEYS,
+            buckets: Mutex::new(Buckets::default()),
+        }
+    }
+
+    /// The function `key_by_header` tells clients apart by the value of the `name` header, such as
+    /// a tenant or user ID set by a trusted proxy. Names that are not valid header names are ignored.
+    /// Clients setting the header themselves can pick a new value for every request.
+    pub fn key_by_header(self, name: &str) -> Self {
+        let Ok(name) = name.parse::<HeaderName>() else {
+            return self;
+        };
+        self.key_by(move |request| request.headers().get(&name)?.to_str().ok().map(str::to_string))
+    }
+
+    /// The function `key_by_api_key` tells clients apart by their API key, read from the `X-Api-Key`
+    /// header or a bearer token in `Authorization`. The keys are not checked, so a middleware
+    /// validating them should run first.
+    pub fn key_by_api_key(self) -> Self {
+        self.key_by(|request| {
+            let headers = request.headers();
+            if let Some(key) = headers.get("x-api-key").and_then(|value| value.to_str().ok()) {
+                return Some(key.to_string());
+            }
+            let authorization = headers.get(AUTHORIZATION)?.to_str().ok()?;
+            authorization.strip_prefix("Bearer ").map(|token| token.trim().to_string())
+        })
+    }
+
+    /// The function `key_by` tells clients apart by the key `key` extracts from each request.
+    pub fn key_by<F>(mut self, key: F) -> Self
+    where
+        F: Fn(&ServerRequest) -> Option<String> + Send + Sync + 'static,
+    {
+        self.key = Arc::new(key);
+        self
+    }
+
+    /// The function `per_route` sets whether each method and path is limited separately, rather
+    /// than every request of a client drawing from the same bucket.
+    pub fn per_route(mut self, per_route: bool) -> Self {
+        self.per_route = per_route;
+        self
+    }
+
+    /// The function `max_keys` sets how many buckets are kept at most, 4096 by default. Past it,
+    /// the bucket used least recently is dropped, its client starting over with a full one.
+    pub fn max_keys(mut self, keys: usize) -> Self {
+        self.max_keys = keys.max(1);
+        self
+    }
+
+    /// The function `check` takes a token from the bucket of `key`.
+    ///
+    /// # Returns:
+    ///
+    /// The `check` function returns the number of seconds to wait if the bucket is empty.
+    fn check(&self, key: String) -> Result<(), u64> {
+        let mut store = self.buckets.lock().unwrap();
+        let Buckets { buckets, recent, clock } = &mut *store;
+        *clock += 1;
+        match buckets.get(&key) {
+            Some((_, used)) => {
+                recent.remove(used);
+            }
+            None if buckets.len() >= self.max_keys => {
+                if let Some((_, oldest)) = recent.pop_first() {
+                    buckets.remove(&oldest);
+                }
+            }
+            None => {}
+        }
+        recent.insert(*clock, key.clone());
+        let (bucket, used) =
+            buckets.entry(key).or_insert_with(|| (TokenBucket::new(self.capacity, self.per_second), 0));
+        *used = *clock;
+        bucket.try_acquire(1.0).map_err(|wait| wait.as_secs_f64().ceil().min(u32::MAX as f64) as u64)
+    }
+}
+
+impl Middleware for RateLimit {
+    fn handle(&self, request: ServerRequest, next: Next) -> HandlerFuture {
+        let mut key = (self.key)(&request).unwrap_or_else(|| request.remote_addr().ip().to_string());
+        if self.per_route {
+            key = format!("{} {} {}", key, request.method(), request.path());
+        }
+        match self.check(key) {
+            Ok(()) => next.run(request),
+            Err(retry_after) => Box::pin(async move {
+                ServerResponse::text("too many requests")
+                    .with_status(StatusCode::TOO_MANY_REQUESTS)
+                    .with_header(RETRY_AFTER, HeaderValue::from(retry_after.max(1)))
+            }),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::http::{HttpServer, RateLimit, ServerResponse};
+    use reqwest::{Client, StatusCode};
+
+    #[tokio::test]
+    async fn test_rate_limit_per_key() {
+        let server = HttpServer::bind("127.0.0.1:0")
+            .await
+            .unwrap()
+            .get("/search", |_| async { ServerResponse::text("results") })
+            .route_middleware(RateLimit::new(2, 0.5).key_by_api_key())
+            .get("/open", |_| async { ServerResponse::text("open") });
+        let server = server.spawn().unwrap();
+        let base = format!("http://{}", server.addr());
+        let client = Client::new();
+        let search = |key: &str| client.get(format!("{}/search", base)).header("x-api-key", key).send();
+
+        for _ in 0..2 {
+            assert_eq!(search("alice").await.unwrap().status(), StatusCode::OK);
+        }
+        let response = search("alice").await.unwrap();
+        assert_eq!(response.status(), StatusCode::TOO_MANY_REQUESTS);
+        assert_eq!(response.headers()["retry-after"], "2");
+
+        // Other keys have buckets of their own, and routes added afterwards are not limited.
+        assert_eq!(search("bob").await.unwrap().status(), StatusCode::OK);
+        for _ in 0..3 {
+            assert_eq!(client.get(format!("{}/open", base)).send().await.unwrap().status(), StatusCode::OK);
+        }
+
+        server.shutdown().await;
+        server.await.unwrap();
+    }
+
+    #[test]
+    fn test_rate_limit_max_keys() {
+        let limit = RateLimit::new(1, 0.0).max_keys(2);
+        assert!(limit.check("alice".into()).is_ok());
+        assert!(limit.check("alice".into()).is_err());
+        assert!(limit.check("bob".into()).is_ok());
+
+        // Rotating keys only ever replaces the bucket used least recently.
+        assert!(limit.check("alice".into()).is_err());
+        for key in ["key-1", "key-2", "key-3"] {
+            assert!(limit.check(key.into()).is_ok());
+        }
+        assert_eq!(limit.buckets.lock().unwrap().buckets.len(), 2);
+        assert_eq!(limit.buckets.lock().unwrap().recent.len(), 2);
+        assert!(limit.check("key-3".into()).is_err());
+        assert!(limit.check("alice".into()).is_ok());
+    }
+}
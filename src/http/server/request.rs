@@ -0,0 +1,158 @@
+use super::{
+    access_log::RequestId,
+    form::{Form, FormRejection, Multipart, MultipartLimits},
+    json::{Json, JsonRejection},
+    params::PathParams,
+};
+use crate::error::AarambhNetError;
+use http_body_util::BodyExt;
+use hyper::{
+    body::{Bytes, Incoming},
+    header::HeaderMap,
+    Method, Request, Uri, Version,
+};
+use http::Extensions;
+use serde::de::DeserializeOwned;
+use std::{error::Error, net::SocketAddr};
+
+/// The `ServerRequest` struct is the request handed to an `HttpServer` handler.
+///
+/// # Properties:
+///
+/// * `parts`: The method, URI, version, headers and extensions of the request.
+/// * `body`: The body, read on demand with `bytes` or `text`.
+/// * `remote_addr`: The address of the client.
+/// * `secure`: Whether the request arrived over TLS.
+/// * `params`: The path segments captured by the matched route.
+pub struct ServerRequest {
+    parts: http::request::Parts,
+    body: Incoming,
+    remote_addr: SocketAddr,
+    secure: bool,
+    params: PathParams,
+}
+
+impl ServerRequest {
+    pub(crate) fn new(request: Request<Incoming>, remote_addr: SocketAddr, secure: bool) -> Self {
+        let (parts, body) = request.into_parts();
+        ServerRequest { parts, body, remote_addr, secure, params: PathParams::default() }
+    }
+
+    /// The function `method` returns the HTTP method of the request.
+    pub fn method(&self) -> &Method {
+        &self.parts.method
+    }
+
+    /// The function `uri` returns the request target as sent by the client.
+    pub fn uri(&self) -> &Uri {
+        &self.parts.uri
+    }
+
+    /// The function `path` returns the path of the request target, without the query string.
+    pub fn path(&self) -> &str {
+        self.parts.uri.path()
+    }
+
+    /// The function `query` returns the query string of the request target, if any.
+    pub fn query(&self) -> Option<&str> {
+        self.parts.uri.query()
+    }
+
+    /// The function `version` returns the HTTP version the request was sent with.
+    pub fn version(&self) -> Version {
+        self.parts.version
+    }
+
+    /// The function `headers` returns the request headers.
+    pub fn headers(&self) -> &HeaderMap {
+        &self.parts.headers
+    }
+
+    /// The function `headers_mut` returns the request headers for modification, for example by a
+    /// middleware passing information on to the handler.
+    pub fn headers_mut(&mut self) -> &mut HeaderMap {
+        &mut self.parts.headers
+    }
+
+    /// The function `extensions` returns the typed values attached to the request.
+    pub fn extensions(&self) -> &Extensions {
+        &self.parts.extensions
+    }
+
+    /// The function `extensions_mut` returns the typed values attached to the request for
+    /// modification. Middleware can store values there, such as the authenticated user, for the
+    /// handler to read.
+    pub fn extensions_mut(&mut self) -> &mut Extensions {
+        &mut self.parts.extensions
+    }
+
+    /// The function `remote_addr` returns the address of the client.
+    pub fn remote_addr(&self) -> SocketAddr {
+        self.remote_addr
+    }
+
+    /// The function `is_secure` returns whether the request arrived over TLS.
+    pub fn is_secure(&self) -> bool {
+        self.secure
+    }
+
+    /// The function `request_id` returns the ID the `AccessLog` middleware gave the request, if it
+    /// ran.
+    pub fn request_id(&self) -> Option<&str> {
+        self.parts.extensions.get::<RequestId>().map(RequestId::as_str)
+    }
+
+    /// The function `params` returns the path segments captured by the route that matched the request.
+    pub fn params(&self) -> &PathParams {
+        &self.params
+    }
+
+    /// The function `param` returns the path segment captured for `name`, like `PathParams::get`.
+    pub fn param(&self, name: &str) -> Option<&str> {
+        self.params.get(name)
+    }
+
+    pub(crate) fn set_params(&mut self, params: PathParams) {
+        self.params = params;
+    }
+
+    pub(crate) fn into_body(self) -> Incoming {
+        self.body
+    }
+
+    /// The function `bytes` reads the whole body.
+    ///
+    /// # Returns:
+    ///
+    /// The `bytes` function returns a `Result` containing the body, or an error if the client
+    /// disconnects or sends a malformed body.
+    pub async fn bytes(self) -> Result<Bytes, Box<dyn Error>> {
+        let body = self.body.collect().await.map_err(|e| AarambhNetError::new("read request body", e))?;
+        Ok(body.to_bytes())
+    }
+
+    /// The function `text` reads the whole body as a UTF-8 string.
+    pub async fn text(self) -> Result<String, Box<dyn Error>> {
+        let bytes = self.bytes().await?;
+        Ok(String::from_utf8(bytes.to_vec()).map_err(|e| AarambhNetError::new("read request body", e))?)
+    }
+
+    /// The function `json` reads the body as JSON and deserializes it into `T`, like
+    /// `Json::from_request`. A handler returning a `Result` can use `?` on it to answer malformed
+    /// bodies with `400 Bad Request`.
+    pub async fn json<T: DeserializeOwned>(self) -> Result<Json<T>, JsonRejection> {
+        Json::from_request(self).await
+    }
+
+    /// The function `form` reads the form of the request and deserializes it into `T`, like
+    /// `Form::from_request`.
+    pub async fn form<T: DeserializeOwned>(self) -> Result<Form<T>, FormRejection> {
+        Form::from_request(self).await
+    }
+
+    /// The function `multipart` starts reading a `multipart/form-data` body within `limits`, like
+    /// `Multipart::from_request`.
+    pub fn multipart(self, limits: MultipartLimits) -> Result<Multipart, FormRejection> {
+        Multipart::from_request(self, limits)
+    }
+}
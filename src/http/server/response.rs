@@ -0,0 +1,185 @@
+use crate::http::BoxError;
+use http_body_util::{combinators::UnsyncBoxBody, BodyExt, Empty, Full};
+use hyper::{
+    body::Bytes,
+    header::{HeaderMap, HeaderName, HeaderValue, CONTENT_TYPE},
+    Response, StatusCode,
+};
+use serde::Serialize;
+
+/// The body type of the responses written by `HttpServer`.
+pub(crate) type ServerBody = UnsyncBoxBody<Bytes, BoxError>;
+
+/// The `ServerResponse` struct is the response returned by an `HttpServer` handler.
+///
+/// # Properties:
+///
+/// * `inner`: The response written to the client.
+pub struct ServerResponse {
+    inner: Response<ServerBody>,
+}
+
+impl ServerResponse {
+    /// The function `new` creates an empty response with the given status.
+    pub fn new(status: StatusCode) -> Self {
+        let body = Empty::new().map_err(|never| match never {}).boxed_unsync();
+        let mut inner = Response::new(body);
+        *inner.status_mut() = status;
+        ServerResponse { inner }
+    }
+
+    /// The function `text` creates a `200 OK` response with a `text/plain` body.
+    pub fn text(body: impl Into<String>) -> Self {
+        Self::full(body.into(), "text/plain; charset=utf-8")
+    }
+
+    /// The function `bytes` creates a `200 OK` response with an `application/octet-stream` body.
+    pub fn bytes(body: impl Into<Bytes>) -> Self {
+        Self::full(body, "application/octet-stream")
+    }
+
+    fn full(body: impl Into<Bytes>, content_type: &'static str) -> Self {
+        let body = Full::new(body.into()).map_err(|never| match never {}).boxed_unsync();
+        let mut inner = Response::new(body);
+        inner.headers_mut().insert(CONTENT_TYPE, HeaderValue::from_static(content_type));
+        ServerResponse { inner }
+    }
+
+    pub(crate) fn from_body(body: ServerBody) -> Self {
+        ServerResponse { inner: Response::new(body) }
+    }
+
+    /// The function `json` creates a `200 OK` response with `value` serialized as JSON, or a
+    /// `500 Internal Server Error` if it cannot be serialized.
+    pub fn json<T: Serialize + ?Sized>(value: &T) -> Self {
+        match serde_json::to_vec(value) {
+            Ok(body) => Self::full(body, "application/json"),
+            Err(e) => {
+                let response = Self::text(format!("failed to serialize the response: {}", e));
+                response.with_status(StatusCode::INTERNAL_SERVER_ERROR)
+            }
+        }
+    }
+
+    /// The function `with_status` replaces the status of the response.
+    pub fn with_status(mut self, status: StatusCode) -> Self {
+        *self.inner.status_mut() = status;
+        self
+    }
+
+    /// The function `with_header` sets a header, replacing any earlier value.
+    pub fn with_header(mut self, name: HeaderName, value: HeaderValue) -> Self {
+        self.inner.headers_mut().insert(name, value);
+        self
+    }
+
+    /// The function `with_headers` sets every header of `headers`, replacing earlier values of the
+    /// same names.
+    pub fn with_headers(mut self, headers: HeaderMap) -> Self {
+        let mut name = None;
+        for (key, value) in headers {
+            if let Some(key) = key {
+                self.inner.headers_mut().remove(&key);
+                name = Some(key);
+            }
+            if let Some(name) = &name {
+                self.inner.headers_mut().append(name, value);
+            }
+        }
+        self
+    }
+
+    /// The function `status` returns the status of the response.
+    pub fn status(&self) -> StatusCode {
+        self.inner.status()
+    }
+
+    /// The function `headers` returns the response headers.
+    pub fn headers(&self) -> &HeaderMap {
+        self.inner.headers()
+    }
+
+    /// The function `headers_mut` returns the response headers for modification.
+    pub fn headers_mut(&mut self) -> &mut HeaderMap {
+        self.inner.headers_mut()
+    }
+
+    pub(crate) fn into_inner(self) -> Response<ServerBody> {
+        self.inner
+    }
+
+    pub(crate) fn from_inner(inner: Response<ServerBody>) -> Self {
+        ServerResponse { inner }
+    }
+}
+
+impl From<StatusCode> for ServerResponse {
+    fn from(status: StatusCode) -> Self {
+        Self::new(status)
+    }
+}
+
+/// The `IntoResponse` trait converts what a handler returns into a `ServerResponse`, so handlers can
+/// return a status code, a string, a `Json` value, a `(StatusCode, body)` or
+/// `(StatusCode, HeaderMap, body)` tuple, or a `Result` of any of these.
+pub trait IntoResponse {
+    /// The function `into_response` builds the response.
+    fn into_response(self) -> ServerResponse;
+}
+
+impl IntoResponse for ServerResponse {
+    fn into_response(self) -> ServerResponse {
+        self
+    }
+}
+
+impl IntoResponse for StatusCode {
+    fn into_response(self) -> ServerResponse {
+        ServerResponse::new(self)
+    }
+}
+
+impl IntoResponse for &'static str {
+    fn into_response(self) -> ServerResponse {
+        ServerResponse::text(self)
+    }
+}
+
+impl IntoResponse for String {
+    fn into_response(self) -> ServerResponse {
+        ServerResponse::text(self)
+    }
+}
+
+impl IntoResponse for Vec<u8> {
+    fn into_response(self) -> ServerResponse {
+        ServerResponse::bytes(self)
+    }
+}
+
+impl IntoResponse for Bytes {
+    fn into_response(self) -> ServerResponse {
+        ServerResponse::bytes(self)
+    }
+}
+
+impl<R: IntoResponse> IntoResponse for (StatusCode, R) {
+    fn into_response(self) -> ServerResponse {
+        self.1.into_response().with_status(self.0)
+    }
+}
+
+impl<R: IntoResponse> IntoResponse for (StatusCode, HeaderMap, R) {
+    fn into_response(self) -> ServerResponse {
+        self.2.into_response().with_status(self.0).with_headers(self.1)
+    }
+}
+
+impl<R: IntoResponse, E: IntoResponse> IntoResponse for Result<R, E> {
+    fn into_response(self) -> ServerResponse {
+        match self {
+            Ok(response) => response.into_response(),
+            Err(e) => e.into_response(),
+        }
+    }
+}
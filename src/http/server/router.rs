@@ -0,0 +1,325 @@
+use super::{
+    files::ServeDir,
+    middleware::{Layer, Middleware, Next},
+    params::{FromPathParams, Pattern},
+    probes::HealthChecks,
+    request::ServerRequest,
+    response::{IntoResponse, ServerResponse},
+    websocket::{self, WebSocket},
+};
+use http::uri::Authority;
+use hyper::{
+    header::{HeaderValue, ALLOW, HOST, LOCATION},
+    Method, StatusCode,
+};
+use std::{future::Future, path::PathBuf, pin::Pin, sync::Arc};
+
+pub(crate) type HandlerFuture = Pin<Box<dyn Future<Output = ServerResponse> + Send>>;
+pub(crate) type Handler = Arc<dyn Fn(ServerRequest) -> HandlerFuture + Send + Sync>;
+
+#[derive(Clone)]
+struct Route {
+    method: Method,
+    pattern: Pattern,
+    handler: Handler,
+    layers: Arc<[Layer]>,
+}
+
+/// The `Router` struct maps a method and a path to the handler of an `HttpServer`.
+///
+/// Paths may capture segments: `/users/{id}` matches `/users/42` and `/static/*path` matches
+/// everything below `/static/`. The captures are available from `ServerRequest::params`, or typed
+/// as a handler argument with `route_with_params`.
+///
+/// A request whose path has no route gets `404 Not Found`; one whose path has routes for other
+/// methods only gets `405 Method Not Allowed` with an `Allow` header. `HEAD` requests fall back to
+/// the `GET` route of their path.
+///
+/// # Properties:
+///
+/// Middleware added with `middleware` wraps every request, including those answered with `404` or
+/// `405`; middleware added with `route_middleware` only wraps the routes added before it. Within
+/// each stack the middleware added first runs first.
+///
+/// # Properties:
+///
+/// * `routes`: The routes, checked in the order they were added.
+/// * `layers`: The server-wide middleware, outermost first.
+#[derive(Clone, Default)]
+pub struct Router {
+    routes: Vec<Route>,
+    layers: Arc<[Layer]>,
+}
+
+impl Router {
+    /// The function `new` creates a router without routes.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The function `redirect_to_https` creates a router answering every request with
+    /// `308 Permanent Redirect` to the same host and path over HTTPS, for the plain HTTP server
+    /// running next to one configured with `HttpServer::with_tls`.
+    ///
+    /// # Arguments:
+    ///
+    /// * `https_port`: The port of the HTTPS server, left out of the redirect when it is 443.
+    pub fn redirect_to_https(https_port: u16) -> Self {
+        Self::new().middleware(move |request: ServerRequest, _: Next| async move {
+            match https_location(&request, https_port).and_then(|location| HeaderValue::from_str(&location).ok()) {
+                Some(location) => ServerResponse::new(StatusCode::PERMANENT_REDIRECT).with_header(LOCATION, location),
+                None => ServerResponse::text("missing Host header").with_status(StatusCode::BAD_REQUEST),
+            }
+        })
+    }
+
+    /// The function `route` adds a route calling `handler` for `method` requests to `path`.
+    ///
+    /// # Arguments:
+    ///
+    /// * `method`: The HTTP method the route answers.
+    /// * `path`: The path the route answers, for example `/users` or `/users/{id}`.
+    /// * `handler`: The async function turning the `ServerRequest` into a response: a
+    ///   `ServerResponse`, or anything else implementing `IntoResponse`, such as `Json`, a status
+    ///   code or a `Result`.
+    pub fn route<H, F>(mut self, method: Method, path: &str, handler: H) -> Self
+    where
+        H: Fn(ServerRequest) -> F + Send + Sync + 'static,
+        F: Future + Send + 'static,
+        F::Output: IntoResponse,
+    {
+        let handler: Handler = Arc::new(move |request| {
+            let response = handler(request);
+            Box::pin(async move { response.await.into_response() })
+        });
+        self.routes.push(Route { method, pattern: Pattern::parse(path), handler, layers: Arc::new([]) });
+        self
+    }
+
+    /// The function `ws` adds a WebSocket route: `GET` requests to `path` carrying an RFC 6455
+    /// handshake are upgraded, and `handler` runs with the request and the upgraded `WebSocket` on a
+    /// task of its own. Other requests are answered with `400 Bad Request`. WebSockets are served
+    /// over HTTP/1.1 only.
+    pub fn ws<H, F>(self, path: &str, handler: H) -> Self
+    where
+        H: Fn(ServerRequest, WebSocket) -> F + Send + Sync + 'static,
+        F: Future<Output = ()> + Send + 'static,
+    {
+        let handler = Arc::new(handler);
+        self.get(path, move |request| std::future::ready(websocket::upgrade(request, handler.clone())))
+    }
+
+    /// The function `middleware` adds `middleware` to the server-wide stack, inside the middleware
+    /// added before it. It runs for every request, before the route is chosen.
+    pub fn middleware(mut self, middleware: impl Middleware) -> Self {
+        self.layers = self.layers.iter().cloned().chain([Arc::new(middleware) as Layer]).collect();
+        self
+    }
+
+    /// The function `route_middleware` adds `middleware` to the stack of every route added so far,
+    /// inside the middleware added to them before. Routes added afterwards are not affected, so
+    /// protected routes can be registered first and public ones after.
+    pub fn route_middleware(mut self, middleware: impl Middleware) -> Self {
+        let layer: Layer = Arc::new(middleware);
+        for route in &mut self.routes {
+            route.layers = route.layers.iter().cloned().chain([layer.clone()]).collect();
+        }
+        self
+    }
+
+    /// The function `route_with_params` adds a route whose handler also receives the captures of
+    /// `path` converted to `P`, a tuple of `FromStr` types in pattern order. Requests whose captures do
+    /// not convert are answered with `400 Bad Request`.
+    ///
+    /// # Arguments:
+    ///
+    /// * `method`: The HTTP method the route answers.
+    /// * `path`: The path pattern, for example `/users/{id}/posts/{post}`.
+    /// * `handler`: The async function receiving the request and the typed captures, for example
+    ///   `|request, (id, post): (u64, u32)| async move { ... }`.
+    pub fn route_with_params<P, H, F>(self, method: Method, path: &str, handler: H) -> Self
+    where
+        P: FromPathParams + 'static,
+        H: Fn(ServerRequest, P) -> F + Send + Sync + 'static,
+        F: Future + Send + 'static,
+        F::Output: IntoResponse,
+    {
+        let handler = Arc::new(handler);
+        self.route(method, path, move |request| {
+            let params = P::from_params(request.params()).map(|params| handler(request, params));
+            async move {
+                match params {
+                    Ok(response) => response.await.into_response(),
+                    Err(e) => ServerResponse::text(e.to_string()).with_status(StatusCode::BAD_REQUEST),
+                }
+            }
+        })
+    }
+
+    /// The function `get` adds a route for `GET` requests to `path`.
+    pub fn get<H, F>(self, path: &str, handler: H) -> Self
+    where
+        H: Fn(ServerRequest) -> F + Send + Sync + 'static,
+        F: Future + Send + 'static,
+        F::Output: IntoResponse,
+    {
+        self.route(Method::GET, path, handler)
+    }
+
+    /// The function `post` adds a route for `POST` requests to `path`.
+    pub fn post<H, F>(self, path: &str, handler: H) -> Self
+    where
+        H: Fn(ServerRequest) -> F + Send + Sync + 'static,
+        F: Future + Send + 'static,
+        F::Output: IntoResponse,
+    {
+        self.route(Method::POST, path, handler)
+    }
+
+    /// The function `put` adds a route for `PUT` requests to `path`.
+    pub fn put<H, F>(self, path: &str, handler: H) -> Self
+    where
+        H: Fn(ServerRequest) -> F + Send + Sync + 'static,
+        F: Future + Send + 'static,
+        F::Output: IntoResponse,
+    {
+        self.route(Method::PUT, path, handler)
+    }
+
+    /// The function `delete` adds a route for `DELETE` requests to `path`.
+    pub fn delete<H, F>(self, path: &str, handler: H) -> Self
+    where
+        H: Fn(ServerRequest) -> F + Send + Sync + 'static,
+        F: Future + Send + 'static,
+        F::Output: IntoResponse,
+    {
+        self.route(Method::DELETE, path, handler)
+    }
+
+    /// The function `any` adds routes for the `GET`, `HEAD`, `POST`, `PUT`, `PATCH`, `DELETE` and
+    /// `OPTIONS` requests to `path`, all calling `handler`, for example a reverse proxy.
+    pub fn any<H, F>(mut self, path: &str, handler: H) -> Self
+    where
+        H: Fn(ServerRequest) -> F + Send + Sync + 'static,
+        F: Future + Send + 'static,
+        F::Output: IntoResponse,
+    {
+        let handler = Arc::new(handler);
+        let methods = [
+            Method::GET,
+            Method::HEAD,
+            Method::POST,
+            Method::PUT,
+            Method::PATCH,
+            Method::DELETE,
+            Method::OPTIONS,
+        ];
+        for method in methods {
+            let handler = handler.clone();
+            self = self.route(method, path, move |request| handler(request));
+        }
+        self
+    }
+
+    /// The function `health_checks` adds the `GET /healthz` and `GET /readyz` endpoints running the
+    /// liveness and readiness checks of `checks`.
+    pub fn health_checks(self, checks: HealthChecks) -> Self {
+        let checks = Arc::new(checks);
+        let live = checks.clone();
+        self.get("/healthz", move |request| {
+            let checks = live.clone();
+            async move { checks.live(request).await }
+        })
+        .get("/readyz", move |request| {
+            let checks = checks.clone();
+            async move { checks.ready(request).await }
+        })
+    }
+
+    /// The function `serve_dir` serves the files below `path` for `GET` and `HEAD` requests under
+    /// `route`, so `/static/css/site.css` with `route` `/static` reads `css/site.css`. Directories
+    /// serve their `index.html`.
+    ///
+    /// The content type follows the file extension. Responses carry `ETag` and `Last-Modified` and
+    /// answer conditional requests with `304 Not Modified`; single byte ranges are answered with
+    /// `206 Partial Content`. Paths containing `..`, or reaching outside `path` through a symbolic
+    /// link, get `404 Not Found`.
+    ///
+    /// # Arguments:
+    ///
+    /// * `route`: The path the files are served under, for example `/static` or `/`. Routes added
+    ///   before take precedence over the files.
+    /// * `path`: The directory to serve.
+    pub fn serve_dir(self, route: &str, path: impl Into<PathBuf>) -> Self {
+        self.serve_files(route, ServeDir::new(path.into(), false))
+    }
+
+    /// The function `serve_spa` serves the files below `path` like `serve_dir`, answering paths
+    /// without a file extension that match no file with the `index.html` of `path`, so the
+    /// client-side routes of a single-page application load the application. Add the API routes
+    /// first so they take precedence.
+    pub fn serve_spa(self, route: &str, path: impl Into<PathBuf>) -> Self {
+        self.serve_files(route, ServeDir::new(path.into(), true))
+    }
+
+    fn serve_files(self, route: &str, files: ServeDir) -> Self {
+        let route = route.trim_end_matches('/');
+        let files = Arc::new(files);
+        let handler = move |request| {
+            let files = files.clone();
+            async move { files.serve(request).await }
+        };
+        let router = self.get(&format!("{}/*path", route), handler.clone());
+        match route.is_empty() {
+            true => router,
+            false => router.get(route, handler),
+        }
+    }
+
+    /// The function `dispatch` runs `request` through the server-wide middleware and the router.
+    pub(crate) fn dispatch(self: &Arc<Self>, request: ServerRequest) -> HandlerFuture {
+        Next::router(self.layers.clone(), self.clone()).run(request)
+    }
+
+    /// The function `route_request` passes `request` through the middleware and handler of the first
+    /// matching route.
+    pub(crate) fn route_request(&self, mut request: ServerRequest) -> HandlerFuture {
+        let on_path: Vec<_> = self
+            .routes
+            .iter()
+            .filter_map(|route| route.pattern.matches(request.path()).map(|params| (route, params)))
+            .collect();
+        let method = request.method();
+        let matched = on_path
+            .iter()
+            .find(|(route, _)| route.method == method)
+            .or_else(|| on_path.iter().find(|(route, _)| method == Method::HEAD && route.method == Method::GET));
+        if let Some((route, params)) = matched {
+            request.set_params(params.clone());
+            return Next::handler(route.layers.clone(), route.handler.clone()).run(request);
+        }
+        if on_path.is_empty() {
+            return Box::pin(async { ServerResponse::new(StatusCode::NOT_FOUND) });
+        }
+
+        let mut allowed: Vec<&str> = on_path.iter().map(|(route, _)| route.method.as_str()).collect();
+        allowed.dedup();
+        let allow = HeaderValue::from_str(&allowed.join(", ")).unwrap_or(HeaderValue::from_static(""));
+        let response = ServerResponse::new(StatusCode::METHOD_NOT_ALLOWED).with_header(ALLOW, allow);
+        Box::pin(async { response })
+    }
+}
+
+/// The function `https_location` builds the HTTPS URL of `request` on `port`, taking the host from
+/// the request target or the `Host` header.
+fn https_location(request: &ServerRequest, port: u16) -> Option<String> {
+    let authority = match request.uri().authority() {
+        Some(authority) => authority.clone(),
+        None => request.headers().get(HOST)?.to_str().ok()?.parse::<Authority>().ok()?,
+    };
+    let path = request.uri().path_and_query().map_or("/", |path| path.as_str());
+    Some(match port {
+        443 => format!("https://{}{}", authority.host(), path),
+        port => format!("https://{}:{}{}", authority.host(), port, path),
+    })
+}
@@ -0,0 +1,190 @@
+use super::response::{IntoResponse, ServerResponse};
+use crate::http::BoxError;
+use futures_util::{stream, Stream, StreamExt};
+use http_body_util::{BodyExt, StreamBody};
+use hyper::{
+    body::{Bytes, Frame},
+    header::{HeaderValue, CACHE_CONTROL, CONTENT_TYPE},
+};
+use serde::Serialize;
+use std::{pin::Pin, time::Duration};
+use tokio::{
+    sync::mpsc,
+    time::{interval_at, Instant, MissedTickBehavior},
+};
+
+/// How long an event stream may stay silent before a keep-alive comment is sent by default.
+const DEFAULT_KEEP_ALIVE: Duration = Duration::from_secs(15);
+
+/// The `SseEvent` struct is one event of a Server-Sent Events stream.
+///
+/// # Properties:
+///
+/// * `data`: The payload of the event; every line becomes a `data:` field.
+/// * `event`: The event type, which browsers dispatch to `addEventListener` listeners.
+/// * `id`: The event ID, sent back by reconnecting clients in `Last-Event-ID`.
+/// * `retry`: How long clients wait before reconnecting after the stream breaks.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct SseEvent {
+    data: String,
+    event: Option<String>,
+    id: Option<String>,
+    retry: Option<Duration>,
+}
+
+impl SseEvent {
+    /// The function `data` creates an event carrying `data`.
+    pub fn data(data: impl Into<String>) -> Self {
+        SseEvent { data: data.into(), ..Default::default() }
+    }
+
+    /// The function `json` creates an event carrying `value` serialized as JSON.
+    pub fn json<T: Serialize + ?Sized>(value: &T) -> Result<Self, serde_json::Error> {
+        Ok(Self::data(serde_json::to_string(value)?))
+    }
+
+    /// The function `event` sets the event type.
+    pub fn event(mut self, event: impl Into<String>) -> Self {
+        self.event = Some(event.into());
+        self
+    }
+
+    /// The function `id` sets the event ID.
+    pub fn id(mut self, id: impl Into<String>) -> Self {
+        self.id = Some(id.into());
+        self
+    }
+
+    /// The function `retry` sets how long clients wait before reconnecting.
+    pub fn retry(mut self, retry: Duration) -> Self {
+        self.retry = Some(retry);
+        self
+    }
+
+    /// The function `encode` writes the event in the `text/event-stream` format. Line breaks in the
+    /// event type and ID, which would end the field, are dropped.
+    fn encode(&self) -> Bytes {
+        let mut out = String::new();
+        let single_line = |value: &str| value.replace(['\r', '\n'], "");
+        if let Some(event) = &self.event {
+            out.push_str(&format!("event: {}\n", single_line(event)));
+        }
+        if let Some(id) = &self.id {
+            out.push_str(&format!("id: {}\n", single_line(id)));
+        }
+        if let Some(retry) = self.retry {
+            out.push_str(&format!("retry: {}\n", retry.as_millis()));
+        }
+        for line in self.data.split('\n') {
+            out.push_str(&format!("data: {}\n", line.strip_suffix('\r').unwrap_or(line)));
+        }
+        out.push('\n');
+        Bytes::from(out)
+    }
+}
+
+/// The `SseResponse` struct is a Server-Sent Events response streaming events to the client as they
+/// are produced. Returned from a handler it becomes a `200 OK` `text/event-stream` response that
+/// ends when the events do; while no event is sent, a comment keeps the connection from being
+/// closed by idle proxies.
+///
+/// # Properties:
+///
+/// * `events`: The events to send.
+/// * `keep_alive`: How long the stream may stay silent before a comment is sent, if at all.
+pub struct SseResponse {
+    events: Pin<Box<dyn Stream<Item = SseEvent> + Send>>,
+    keep_alive: Option<Duration>,
+}
+
+impl SseResponse {
+    /// The function `new` creates a response sending the events of `events`.
+    pub fn new(events: impl Stream<Item = SseEvent> + Send + 'static) -> Self {
+        SseResponse { events: Box::pin(events), keep_alive: Some(DEFAULT_KEEP_ALIVE) }
+    }
+
+    /// The function `from_channel` creates a response sending the events received on `events`,
+    /// until every sender is dropped.
+    pub fn from_channel(events: mpsc::Receiver<SseEvent>) -> Self {
+        Self::new(stream::unfold(events, |mut events| async move { events.recv().await.map(|event| (event, events)) }))
+    }
+
+    /// The function `keep_alive` sets how long the stream may stay silent before a keep-alive
+    /// comment is sent, 15 seconds by default. `None` disables the comments.
+    pub fn keep_alive(mut self, interval: Option<Duration>) -> Self {
+        self.keep_alive = interval.filter(|interval| !interval.is_zero());
+        self
+    }
+}
+
+impl IntoResponse for SseResponse {
+    fn into_response(self) -> ServerResponse {
+        let keep_alive = self.keep_alive.map(|period| {
+            let mut interval = interval_at(Instant::now() + period, period);
+            interval.set_missed_tick_behavior(MissedTickBehavior::Delay);
+            interval
+        });
+        let frames = stream::unfold((self.events, keep_alive), |(mut events, mut keep_alive)| async move {
+            let bytes = match &mut keep_alive {
+                Some(interval) => tokio::select! {
+                    event = events.next() => {
+                        interval.reset();
+                        event?.encode()
+                    }
+                    _ = interval.tick() => Bytes::from_static(b":\n\n"),
+                },
+                None => events.next().await?.encode(),
+            };
+            Some((Ok::<_, BoxError>(Frame::data(bytes)), (events, keep_alive)))
+        });
+        ServerResponse::from_body(StreamBody::new(frames).boxed_unsync())
+            .with_header(CONTENT_TYPE, HeaderValue::from_static("text/event-stream"))
+            .with_header(CACHE_CONTROL, HeaderValue::from_static("no-cache"))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::http::{HttpClient, HttpServer, SseEvent, SseResponse};
+    use futures_util::stream;
+    use std::time::Duration;
+    use tokio::sync::mpsc;
+
+    #[tokio::test]
+    async fn test_event_stream_and_keep_alive() {
+        let server = HttpServer::bind("127.0.0.1:0")
+            .await
+            .unwrap()
+            .get("/events", |_| async {
+                let (sender, receiver) = mpsc::channel(4);
+                tokio::spawn(async move {
+                    let _ = sender.send(SseEvent::data("first\nsecond").event("greeting").id("1")).await;
+                    let _ = sender.send(SseEvent::json(&[1, 2]).unwrap().retry(Duration::from_secs(3))).await;
+                });
+                SseResponse::from_channel(receiver)
+            })
+            .get("/slow", |_| async {
+                let late = stream::once(async {
+                    tokio::time::sleep(Duration::from_millis(150)).await;
+                    SseEvent::data("late")
+                });
+                SseResponse::new(late).keep_alive(Some(Duration::from_millis(40)))
+            });
+        let server = server.spawn().unwrap();
+        let client = HttpClient::new(&format!("http://{}", server.addr()), None).unwrap();
+
+        let response = client.get("/events", None).await.unwrap();
+        assert_eq!(response.headers()["content-type"], "text/event-stream");
+        assert_eq!(response.headers()["cache-control"], "no-cache");
+        assert_eq!(
+            response.text().await.unwrap(),
+            "event: greeting\nid: 1\ndata: first\ndata: second\n\nretry: 3000\ndata: [1,2]\n\n"
+        );
+
+        let body = client.get("/slow", None).await.unwrap().text().await.unwrap();
+        assert!(body.starts_with(":\n\n") && body.ends_with("data: late\n\n"), "{:?}", body);
+
+        server.shutdown().await;
+        server.await.unwrap();
+    }
+}
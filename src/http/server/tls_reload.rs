@@ -0,0 +1,173 @@
+use crate::tls::TlsConfig;
+use std::{
+    error::Error,
+    path::PathBuf,
+    sync::RwLock,
+    time::{Duration, SystemTime},
+};
+use tokio::time::{interval, MissedTickBehavior};
+use tokio_rustls::TlsAcceptor;
+
+/// The `ServerTls` struct holds the TLS acceptor of an `HttpServer`, which can be swapped while the
+/// server runs. Handshakes started after a swap present the new certificate; connections already
+/// established keep the one they were opened with.
+///
+/// # Properties:
+///
+/// * `acceptor`: The acceptor handshakes are performed with.
+/// * `files`: The PEM files the certificate is reloaded from when they change, if watched.
+pub(crate) struct ServerTls {
+    acceptor: RwLock<TlsAcceptor>,
+    files: Option<TlsFiles>,
+}
+
+/// The `TlsFiles` struct locates the certificate files watched for renewals.
+///
+/// # Properties:
+///
+/// * `cert_path`: The PEM certificate chain.
+/// * `key_path`: The PEM private key.
+/// * `interval`: How often the files are checked for changes.
+struct TlsFiles {
+    cert_path: PathBuf,
+    key_path: PathBuf,
+    interval: Duration,
+}
+
+impl ServerTls {
+    /// The function `new` creates the acceptor from `tls`, advertising HTTP/2 and HTTP/1.1.
+    pub(crate) fn new(tls: &TlsConfig) -> Result<Self, rustls::Error> {
+        Ok(ServerTls { acceptor: RwLock::new(acceptor(tls)?), files: None })
+    }
+
+    /// The function `watching` creates the acceptor from the PEM files and checks them for changes
+    /// every `interval` once `watch` runs.
+    pub(crate) fn watching(cert_path: PathBuf, key_path: PathBuf, interval: Duration) -> Result<Self, Box<dyn Error>> {
+        let tls = TlsConfig::from_pem_files(&cert_path, &key_path)?;
+        let files = TlsFiles { cert_path, key_path, interval };
+        Ok(ServerTls { acceptor: RwLock::new(acceptor(&tls)?), files: Some(files) })
+    }
+
+    /// The function `acceptor` returns the acceptor for the next handshake.
+    pub(crate) fn acceptor(&self) -> TlsAcceptor {
+        self.acceptor.read().unwrap_or_else(|e| e.into_inner()).clone()
+    }
+
+    /// The function `reload` swaps in the certificate of `tls`.
+    pub(crate) fn reload(&self, tls: &TlsConfig) -> Result<(), rustls::Error> {
+        let acceptor = acceptor(tls)?;
+        *self.acceptor.write().unwrap_or_else(|e| e.into_inner()) = acceptor;
+        Ok(())
+    }
+
+    /// The function `watch` reloads the certificate whenever the watched files change, and never
+    /// returns. A reload failing, as when the files are caught halfway through a renewal, keeps
+    /// the current certificate and is retried on the next check.
+    pub(crate) async fn watch(&self) {
+        let Some(files) = &self.files else { return std::future::pending().await };
+        let mut seen = files.modified().await;
+        let mut ticks = interval(files.interval);
+        ticks.set_missed_tick_behavior(MissedTickBehavior::Delay);
+        ticks.tick().await;
+        loop {
+            ticks.tick().await;
+            let modified = files.modified().await;
+            if modified == seen {
+                continue;
+            }
+            let reloaded = TlsConfig::from_pem_files(&files.cert_path, &files.key_path)
+                .and_then(|tls| Ok(self.reload(&tls)?))
+                .map_err(|e| e.to_string());
+            match reloaded {
+                Ok(()) => {
+                    tracing::info!(cert = %files.cert_path.display(), "reloaded the TLS certificate");
+                    seen = modified;
+                }
+                Err(e) => tracing::warn!(
+                    cert = %files.cert_path.display(),
+                    "reloading the TLS certificate failed, keeping the current one: {}",
+                    e
+                ),
+            }
+        }
+    }
+}
+
+impl TlsFiles {
+    /// The function `modified` returns when the certificate and key files were last modified.
+    async fn modified(&self) -> (Option<SystemTime>, Option<SystemTime>) {
+        let modified = |path| async move { tokio::fs::metadata(path).await.and_then(|m| m.modified()).ok() };
+        (modified(&self.cert_path).await, modified(&self.key_path).await)
+    }
+}
+
+/// The function `acceptor` builds an acceptor for `tls`, preferring HTTP/2 over HTTP/1.1.
+fn acceptor(tls: &TlsConfig) -> Result<TlsAcceptor, rustls::Error> {
+    tls.acceptor(vec![b"h2".to_vec(), b"http/1.1".to_vec()])
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{http::HttpServer, tls::TlsConfig};
+    use rcgen::{BasicConstraints, Certificate, CertificateParams, DnType, IsCa, KeyPair};
+    use reqwest::{tls::TlsInfo, Client};
+    use std::{fs, sync::Arc, time::Duration};
+
+    #[tokio::test]
+    async fn test_tls_reload() {
+        let ca_key = KeyPair::generate().unwrap();
+        let mut ca = CertificateParams::new(Vec::new()).unwrap();
+        ca.is_ca = IsCa::Ca(BasicConstraints::Unconstrained);
+        ca.distinguished_name.push(DnType::CommonName, "AarambhNet test CA");
+        let ca = ca.self_signed(&ca_key).unwrap();
+        let issue = || {
+            let key = KeyPair::generate().unwrap();
+            let cert = CertificateParams::new(vec!["localhost".to_string()]).unwrap();
+            (cert.signed_by(&key, &ca, &ca_key).unwrap(), key)
+        };
+        let dir = std::env::temp_dir().join(format!("aarambh-tls-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let (cert_path, key_path) = (dir.join("fullchain.pem"), dir.join("privkey.pem"));
+        let write = |(cert, key): &(Certificate, KeyPair)| {
+            fs::write(&cert_path, cert.pem()).unwrap();
+            fs::write(&key_path, key.serialize_pem()).unwrap();
+        };
+        let (first, renewed, manual) = (issue(), issue(), issue());
+        write(&first);
+
+        let server = HttpServer::bind("127.0.0.1:0")
+            .await
+            .unwrap()
+            .get("/hello", |_| async { "hello" })
+            .watch_tls_files(&cert_path, &key_path, Duration::from_millis(20))
+            .unwrap();
+        let server = Arc::new(server);
+        let url = format!("https://localhost:{}/hello", server.local_addr().unwrap().port());
+        let task = server.spawn_shared().unwrap();
+        let ca = reqwest::Certificate::from_pem(ca.pem().as_bytes()).unwrap();
+        let client = || Client::builder().add_root_certificate(ca.clone()).tls_info(true).build().unwrap();
+        let served = |client: Client| {
+            let url = url.clone();
+            async move {
+                let response = client.get(url).send().await.unwrap();
+                response.extensions().get::<TlsInfo>().unwrap().peer_certificate().unwrap().to_vec()
+            }
+        };
+
+        let open = client();
+        assert_eq!(served(open.clone()).await, first.0.der().to_vec());
+        write(&renewed);
+        tokio::time::sleep(Duration::from_millis(200)).await;
+        assert_eq!(served(client()).await, renewed.0.der().to_vec());
+        // The connection opened before the renewal is kept, with its certificate.
+        assert_eq!(served(open).await, first.0.der().to_vec());
+
+        let tls = TlsConfig::from_pem(manual.0.pem().as_bytes(), manual.1.serialize_pem().as_bytes()).unwrap();
+        server.reload_tls(&tls).unwrap();
+        assert_eq!(served(client()).await, manual.0.der().to_vec());
+
+        server.shutdown().await;
+        task.await.unwrap();
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
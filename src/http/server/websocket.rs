@@ -0,0 +1,215 @@
+use super::{drain::ShutdownSignal, request::ServerRequest, response::ServerResponse};
+use crate::error::AarambhNetError;
+use futures_util::{SinkExt, StreamExt};
+use hyper::{
+    header::{HeaderValue, CONNECTION, SEC_WEBSOCKET_ACCEPT, SEC_WEBSOCKET_KEY, SEC_WEBSOCKET_VERSION, UPGRADE},
+    upgrade::{OnUpgrade, Upgraded},
+    StatusCode,
+};
+use hyper_util::rt::TokioIo;
+use std::{future::Future, net::SocketAddr, sync::Arc};
+use tokio_tungstenite::{
+    tungstenite::{
+        handshake::derive_accept_key,
+        protocol::{frame::coding::CloseCode, CloseFrame, Role},
+        Message,
+    },
+    WebSocketStream,
+};
+
+/// The `WsMessage` enum is a message received from or sent to a WebSocket client. Pings are
+/// answered automatically and are not reported.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum WsMessage {
+    Text(String),
+    Binary(Vec<u8>),
+}
+
+impl From<String> for WsMessage {
+    fn from(text: String) -> Self {
+        WsMessage::Text(text)
+    }
+}
+
+impl From<&str> for WsMessage {
+    fn from(text: &str) -> Self {
+        WsMessage::Text(text.to_string())
+    }
+}
+
+impl From<Vec<u8>> for WsMessage {
+    fn from(data: Vec<u8>) -> Self {
+        WsMessage::Binary(data)
+    }
+}
+
+/// The `WebSocket` struct is the server side of a WebSocket connection, handed to the handler of a
+/// route added with `Router::ws`.
+///
+/// # Properties:
+///
+/// * `stream`: The connection, upgraded from HTTP/1.1.
+/// * `remote_addr`: The address of the client.
+/// * `shutdown`: Resolves when the server shuts down, which ends the session.
+pub struct WebSocket {
+    stream: WebSocketStream<TokioIo<Upgraded>>,
+    remote_addr: SocketAddr,
+    shutdown: Option<ShutdownSignal>,
+}
+
+impl WebSocket {
+    /// The function `recv` waits for the next text or binary message of the client.
+    ///
+    /// # Returns:
+    ///
+    /// The `recv` function returns `Ok(None)` once the client closed the connection, or once the
+    /// server shuts down, in which case the client is sent a "going away" close frame. It returns an
+    /// error if the connection fails or the client breaks the protocol.
+    pub async fn recv(&mut self) -> Result<Option<WsMessage>, AarambhNetError> {
+        loop {
+            let message = match &mut self.shutdown {
+                Some(shutdown) => tokio::select! {
+                    message = self.stream.next() => message,
+                    _ = shutdown.wait() => {
+                        self.shutdown = None;
+                        let _ = self.send_close(CloseCode::Away, "server shutting down").await;
+                        return Ok(None);
+                    }
+                },
+                None => self.stream.next().await,
+            };
+            match message {
+                Some(Ok(Message::Text(text))) => return Ok(Some(WsMessage::Text(text))),
+                Some(Ok(Message::Binary(data))) => return Ok(Some(WsMessage::Binary(data))),
+                Some(Ok(Message::Close(_))) | None => return Ok(None),
+                Some(Ok(_)) => continue,
+                Some(Err(e)) => return Err(AarambhNetError::new("receive", e).with_target(self.remote_addr)),
+            }
+        }
+    }
+
+    /// The function `send` sends `message` to the client.
+    pub async fn send(&mut self, message: impl Into<WsMessage>) -> Result<(), AarambhNetError> {
+        let message = match message.into() {
+            WsMessage::Text(text) => Message::Text(text),
+            WsMessage::Binary(data) => Message::Binary(data),
+        };
+        self.stream.send(message).await.map_err(|e| AarambhNetError::new("send", e).with_target(self.remote_addr))
+    }
+
+    /// The function `close` ends the session with a normal closure, sending `reason` to the client.
+    pub async fn close(mut self, reason: &str) -> Result<(), AarambhNetError> {
+        self.send_close(CloseCode::Normal, reason).await
+    }
+
+    /// The function `remote_addr` returns the address of the client.
+    pub fn remote_addr(&self) -> SocketAddr {
+        self.remote_addr
+    }
+
+    async fn send_close(&mut self, code: CloseCode, reason: &str) -> Result<(), AarambhNetError> {
+        let frame = CloseFrame { code, reason: reason.into() };
+        self.stream.close(Some(frame)).await.map_err(|e| AarambhNetError::new("close", e).with_target(self.remote_addr))
+    }
+}
+
+/// The function `upgrade` answers a WebSocket handshake (RFC 6455) and runs `handler` with the
+/// upgraded connection on a task of its own.
+///
+/// # Returns:
+///
+/// The `upgrade` function returns `101 Switching Protocols`, `426 Upgrade Required` if the client
+/// asks for a WebSocket version other than 13, and `400 Bad Request` if the request is not a valid
+/// handshake, such as a request sent over HTTP/2.
+pub(crate) fn upgrade<H, F>(mut request: ServerRequest, handler: Arc<H>) -> ServerResponse
+where
+    H: Fn(ServerRequest, WebSocket) -> F + Send + Sync + 'static,
+    F: Future<Output = ()> + Send + 'static,
+{
+    let headers = request.headers();
+    let has_token = |name, token: &str| {
+        headers.get_all(name).iter().filter_map(|value| value.to_str().ok()).any(|value| {
+            value.split(',').any(|item| item.trim().eq_ignore_ascii_case(token))
+        })
+    };
+    if !has_token(CONNECTION, "upgrade") || !has_token(UPGRADE, "websocket") {
+        return ServerResponse::text("expected a WebSocket upgrade request").with_status(StatusCode::BAD_REQUEST);
+    }
+    if headers.get(SEC_WEBSOCKET_VERSION).is_none_or(|version| version != "13") {
+        return ServerResponse::new(StatusCode::UPGRADE_REQUIRED)
+            .with_header(SEC_WEBSOCKET_VERSION, HeaderValue::from_static("13"));
+    }
+    let Some(key) = headers.get(SEC_WEBSOCKET_KEY) else {
+        return ServerResponse::text("missing Sec-WebSocket-Key").with_status(StatusCode::BAD_REQUEST);
+    };
+    let accept = derive_accept_key(key.as_bytes());
+    let Some(on_upgrade) = request.extensions_mut().remove::<OnUpgrade>() else {
+        return ServerResponse::text("the connection cannot be upgraded").with_status(StatusCode::BAD_REQUEST);
+    };
+
+    let (remote_addr, shutdown) = (request.remote_addr(), request.extensions().get::<ShutdownSignal>().cloned());
+    tokio::spawn(async move {
+        match on_upgrade.await {
+            Ok(upgraded) => {
+                let stream = WebSocketStream::from_raw_socket(TokioIo::new(upgraded), Role::Server, None).await;
+                handler(request, WebSocket { stream, remote_addr, shutdown }).await;
+            }
+            Err(e) => tracing::debug!("WebSocket upgrade from {} failed: {}", remote_addr, e),
+        }
+    });
+
+    ServerResponse::new(StatusCode::SWITCHING_PROTOCOLS)
+        .with_header(CONNECTION, HeaderValue::from_static("upgrade"))
+        .with_header(UPGRADE, HeaderValue::from_static("websocket"))
+        .with_header(SEC_WEBSOCKET_ACCEPT, HeaderValue::from_str(&accept).unwrap_or(HeaderValue::from_static("")))
+}
+
+#[cfg(test)]
+mod test {
+    use crate::http::{HttpServer, ServerResponse, WsMessage};
+    use futures_util::{SinkExt, StreamExt};
+    use tokio::net::TcpStream;
+    use tokio_tungstenite::tungstenite::{protocol::frame::coding::CloseCode, Message};
+
+    #[tokio::test]
+    async fn test_websocket_echo_and_shutdown() {
+        let server = HttpServer::bind("127.0.0.1:0")
+            .await
+            .unwrap()
+            .get("/health", |_| async { ServerResponse::text("ok") })
+            .ws("/echo/{room}", |request, mut socket| async move {
+                let room = request.param("room").unwrap_or_default().to_string();
+                while let Ok(Some(message)) = socket.recv().await {
+                    let reply = match message {
+                        WsMessage::Text(text) => WsMessage::Text(format!("{}: {}", room, text)),
+                        binary => binary,
+                    };
+                    if socket.send(reply).await.is_err() {
+                        return;
+                    }
+                }
+            });
+        let server = server.spawn().unwrap();
+        let addr = server.addr();
+
+        let url = format!("ws://{}/echo/lobby", addr);
+        let socket = TcpStream::connect(addr).await.unwrap();
+        let (mut client, _) = tokio_tungstenite::client_async(url, socket).await.unwrap();
+        client.send(Message::text("hi")).await.unwrap();
+        assert_eq!(client.next().await.unwrap().unwrap(), Message::text("lobby: hi"));
+        client.send(Message::binary(vec![1, 2, 3])).await.unwrap();
+        assert_eq!(client.next().await.unwrap().unwrap(), Message::binary(vec![1, 2, 3]));
+
+        // A plain request to a WebSocket route is refused.
+        let response = reqwest::get(format!("http://{}/echo/lobby", addr)).await.unwrap();
+        assert_eq!(response.status(), reqwest::StatusCode::BAD_REQUEST);
+
+        // Shutting down closes the session with "going away".
+        server.shutdown().await;
+        match client.next().await.unwrap().unwrap() {
+            Message::Close(Some(frame)) => assert_eq!(frame.code, CloseCode::Away),
+            other => panic!("expected a close frame, got {:?}", other),
+        }
+        server.await.unwrap();
+    }
+}
@@ -0,0 +1,147 @@
+use super::BoxError;
+use crate::rate_limit::TokenBucket;
+use reqwest::{Request, Response};
+use std::sync::Arc;
+#[cfg(not(target_arch = "wasm32"))]
+use super::response::ResponseUrl;
+#[cfg(not(target_arch = "wasm32"))]
+use futures_util::{stream, StreamExt};
+#[cfg(not(target_arch = "wasm32"))]
+use reqwest::{
+    header::{HeaderValue, CONTENT_LENGTH},
+    Body,
+};
+
+/// The size of the pieces an upload body is cut into, so the pacing stays smooth.
+#[cfg(not(target_arch = "wasm32"))]
+const UPLOAD_CHUNK: usize = 16 * 1024;
+
+/// The function `bandwidth_bucket` creates the `TokenBucket` enforcing `bytes_per_second`, with one
+/// second worth of burst.
+pub(crate) fn bandwidth_bucket(bytes_per_second: u64) -> Arc<TokenBucket> {
+    Arc::new(TokenBucket::per_second(bytes_per_second as f64))
+}
+
+/// The function `pace` waits until every bucket in `limits` allows `bytes` more bytes. Amounts larger
+/// than a bucket's burst are taken in several steps.
+#[cfg(not(target_arch = "wasm32"))]
+async fn pace(limits: &[Arc<TokenBucket>], bytes: usize) {
+    for bucket in limits {
+        let step = bucket.capacity().max(1.0);
+        let mut remaining = bytes as f64;
+        while remaining > 0.0 {
+            let tokens = remaining.min(step);
+            bucket.acquire(tokens).await;
+            remaining -= tokens;
+        }
+    }
+}
+
+/// The function `throttle_request` replaces the body of `request` with a stream paced by `limits`.
+/// The `Content-Length` is kept so the server still sees the size up front.
+#[cfg(not(target_arch = "wasm32"))]
+pub(crate) fn throttle_request(mut request: Request, limits: Vec<Arc<TokenBucket>>) -> Request {
+    let body = match request.body().and_then(|body| body.as_bytes()) {
+        Some(body) if !limits.is_empty() && !body.is_empty() => body.to_vec(),
+        _ => return request,
+    };
+    let length = HeaderValue::from(body.len());
+    let chunks: Vec<Vec<u8>> = body.chunks(UPLOAD_CHUNK).map(<[u8]>::to_vec).collect();
+    let paced = stream::iter(chunks).then(move |chunk| {
+        let limits = limits.clone();
+        async move {
+            pace(&limits, chunk.len()).await;
+            Ok::<_, BoxError>(chunk)
+        }
+    });
+    request.headers_mut().insert(CONTENT_LENGTH, length);
+    *request.body_mut() = Some(Body::wrap_stream(paced));
+    request
+}
+
+/// The function `throttle_response` wraps the body of `response` so it is read no faster than
+/// `limits` allow.
+#[cfg(not(target_arch = "wasm32"))]
+pub(crate) fn throttle_response(response: Response, limits: Vec<Arc<TokenBucket>>) -> Result<Response, BoxError> {
+    if limits.is_empty() {
+        return Ok(response);
+    }
+    let mut builder = http::Response::builder().status(response.status()).version(response.version());
+    for (name, value) in response.headers() {
+        builder = builder.header(name, value);
+    }
+    let mut extensions = response.extensions().clone();
+    extensions.insert(ResponseUrl(response.url().clone()));
+
+    let body = response.bytes_stream().then(move |chunk| {
+        let limits = limits.clone();
+        async move {
+            if let Ok(chunk) = &chunk {
+                pace(&limits, chunk.len()).await;
+            }
+            chunk
+        }
+    });
+
+    let mut response = Response::from(builder.body(Body::wrap_stream(body))?);
+    *response.extensions_mut() = extensions;
+    Ok(response)
+}
+
+/// Browser requests cannot be given a paced body, so bandwidth limits are not applied on `wasm32`.
+#[cfg(target_arch = "wasm32")]
+pub(crate) fn throttle_request(request: Request, _limits: Vec<Arc<TokenBucket>>) -> Request {
+    request
+}
+
+#[cfg(target_arch = "wasm32")]
+pub(crate) fn throttle_response(response: Response, _limits: Vec<Arc<TokenBucket>>) -> Result<Response, BoxError> {
+    Ok(response)
+}
+
+#[cfg(test)]
+mod test {
+    use crate::http::{testing::serve, EndpointProfile, HttpClient};
+    use std::time::{Duration, Instant};
+    use tokio::{
+        io::{AsyncReadExt, AsyncWriteExt},
+        net::TcpListener,
+    };
+
+    #[tokio::test]
+    async fn test_bandwidth_limits() {
+        let body = "x".repeat(4000);
+        let reply: &'static str =
+            Box::leak(format!("HTTP/1.1 200 OK\r\ncontent-length: 4000\r\nconnection: close\r\n\r\n{}", body).into_boxed_str());
+        let (addr, server_task) = serve(vec![reply]).await;
+        let client = HttpClient::builder(&format!("http://{}", addr)).max_download_rate(2000).build().unwrap();
+
+        // The first second of each transfer is covered by the burst, the rest is paced.
+        let started = Instant::now();
+        assert_eq!(client.get("/download", None).await.unwrap().text().await.unwrap().len(), 4000);
+        assert!(started.elapsed() >= Duration::from_millis(900));
+        server_task.await.unwrap();
+
+        // The upload is timed by a server that answers only once the whole body has arrived.
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let server_task = tokio::spawn(async move {
+            let (mut socket, _) = listener.accept().await.unwrap();
+            let mut received = Vec::new();
+            let mut buf = vec![0; 8192];
+            while !received.ends_with(&[b'x'; 4000]) {
+                let n = socket.read(&mut buf).await.unwrap();
+                received.extend_from_slice(&buf[..n]);
+            }
+            socket.write_all(b"HTTP/1.1 200 OK\r\ncontent-length: 0\r\nconnection: close\r\n\r\n").await.unwrap();
+        });
+        let client = HttpClient::builder(&format!("http://{}", addr))
+            .endpoint_profile("/upload", EndpointProfile::new().upload_rate(2000))
+            .build()
+            .unwrap();
+        let started = Instant::now();
+        client.post("/upload", None, Some(&body)).await.unwrap();
+        assert!(started.elapsed() >= Duration::from_millis(900));
+        server_task.await.unwrap();
+    }
+}
@@ -0,0 +1,400 @@
+use super::{response::ResponseUrl, BoxError};
+use base64::{engine::general_purpose::STANDARD, Engine};
+use reqwest::{Request, Response, Url};
+use serde::{Deserialize, Serialize};
+use std::{
+    error::Error,
+    fmt, fs,
+    future::Future,
+    path::{Path, PathBuf},
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Mutex,
+    },
+};
+
+/// The `VcrMode` enum selects whether a `Cassette` talks to the network or serves stored
+/// interactions.
+///
+/// # Variants:
+///
+/// * `Record`: Every request goes to the network and the request/response pair is added to the
+///   cassette, which is written to its file by `Cassette::save` or when it is dropped.
+/// * `Replay`: Requests never reach the network; they are answered from the cassette and an error is
+///   returned when no recorded interaction matches.
+/// * `Auto`: Replays when the cassette file already exists and records otherwise, so the first local
+///   run captures traffic and CI replays it deterministically.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum VcrMode {
+    Record,
+    Replay,
+    Auto,
+}
+
+/// The `MatchRules` struct controls which parts of a request must be equal for a recorded
+/// interaction to be replayed.
+///
+/// # Properties:
+///
+/// * `method`: When `true`, the HTTP method must match.
+/// * `url`: When `true`, the full URL (including the query string) must match.
+/// * `body`: When `true`, the request body must match byte for byte.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MatchRules {
+    pub method: bool,
+    pub url: bool,
+    pub body: bool,
+}
+
+impl Default for MatchRules {
+    fn default() -> Self {
+        MatchRules { method: true, url: true, body: true }
+    }
+}
+
+/// The `BodyEncoding` enum tells how the `data` of a `RecordedBody` is stored.
+///
+/// # Variants:
+///
+/// * `Utf8`: The body is valid UTF-8 and stored as is, so the cassette stays readable.
+/// * `Base64`: The body is binary and stored in standard base64.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum BodyEncoding {
+    Utf8,
+    Base64,
+}
+
+/// The `RecordedBody` struct is the serialized form of a request or response body, kept byte for
+/// byte.
+///
+/// # Properties:
+///
+/// * `encoding`: How `data` encodes the bytes of the body.
+/// * `data`: The body, encoded with `encoding`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RecordedBody {
+    pub encoding: BodyEncoding,
+    pub data: String,
+}
+
+impl RecordedBody {
+    /// The function `new` stores `bytes` as text when they are valid UTF-8, and in base64 otherwise.
+    pub fn new(bytes: &[u8]) -> Self {
+        match std::str::from_utf8(bytes) {
+            Ok(text) => RecordedBody { encoding: BodyEncoding::Utf8, data: text.to_string() },
+            Err(_) => RecordedBody { encoding: BodyEncoding::Base64, data: STANDARD.encode(bytes) },
+        }
+    }
+
+    /// The function `bytes` returns the bytes of the body, or an error if `data` is not valid
+    /// base64.
+    pub fn bytes(&self) -> Result<Vec<u8>, base64::DecodeError> {
+        match self.encoding {
+            BodyEncoding::Utf8 => Ok(self.data.clone().into_bytes()),
+            BodyEncoding::Base64 => STANDARD.decode(&self.data),
+        }
+    }
+}
+
+/// The `RecordedRequest` struct is the serialized form of an outgoing request stored in a cassette.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RecordedRequest {
+    pub method: String,
+    pub url: String,
+    pub headers: Vec<(String, String)>,
+    pub body: Option<RecordedBody>,
+}
+
+/// The `RecordedResponse` struct is the serialized form of a response stored in a cassette.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RecordedResponse {
+    pub status: u16,
+    pub headers: Vec<(String, String)>,
+    pub body: RecordedBody,
+}
+
+/// The `Interaction` struct pairs a recorded request with the response it received.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Interaction {
+    pub request: RecordedRequest,
+    pub response: RecordedResponse,
+}
+
+/// The `CassetteMissError` is the cause of the `AarambhNetError` returned in replay mode when no
+/// unused recorded interaction matches the outgoing request.
+#[derive(Debug)]
+pub struct CassetteMissError {
+    pub method: String,
+    pub url: String,
+}
+
+impl fmt::Display for CassetteMissError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "no recorded interaction matches {} {}", self.method, self.url)
+    }
+}
+
+impl Error for CassetteMissError {}
+
+/// The `Cassette` struct stores HTTP interactions in a JSON file and either records new ones or
+/// replays existing ones, depending on its `VcrMode`.
+///
+/// # Properties:
+///
+/// * `path`: The file the interactions are loaded from and saved to.
+/// * `mode`: The resolved mode; `Auto` is turned into `Record` or `Replay` when the cassette is
+///   opened.
+/// * `rules`: The `MatchRules` used to pick a recorded interaction during replay.
+/// * `state`: The loaded interactions together with a flag per interaction marking whether it has
+///   already been replayed, so identical requests are answered in recording order.
+/// * `unsaved`: Whether interactions were recorded since the file was last written.
+pub struct Cassette {
+    path: PathBuf,
+    mode: VcrMode,
+    rules: MatchRules,
+    state: Mutex<Vec<(Interaction, bool)>>,
+    unsaved: AtomicBool,
+}
+
+impl Cassette {
+    /// The function `open` opens a cassette in `VcrMode::Auto` with the default `MatchRules`.
+    ///
+    /// # Arguments:
+    ///
+    /// * `path`: The path of the cassette file. It does not need to exist yet.
+    ///
+    /// # Returns:
+    ///
+    /// The `open` function returns a `Result` containing the `Cassette`, or an error if an existing
+    /// cassette file cannot be read or parsed.
+    pub fn open(path: impl AsRef<Path>) -> Result<Self, Box<dyn Error>> {
+        Self::with_mode(path, VcrMode::Auto)
+    }
+
+    /// The function `with_mode` opens a cassette in the given mode with the default `MatchRules`.
+    ///
+    /// # Arguments:
+    ///
+    /// * `path`: The path of the cassette file.
+    /// * `mode`: The `VcrMode` to use. In `Replay` mode the file must exist; in `Record` mode any
+    ///   existing interactions are kept and new ones are appended.
+    ///
+    /// # Returns:
+    ///
+    /// The `with_mode` function returns a `Result` containing the `Cassette`, or an error if the file
+    /// cannot be read or parsed.
+    pub fn with_mode(path: impl AsRef<Path>, mode: VcrMode) -> Result<Self, Box<dyn Error>> {
+        let path = path.as_ref().to_path_buf();
+        let mode = match mode {
+            VcrMode::Auto if path.exists() => VcrMode::Replay,
+            VcrMode::Auto => VcrMode::Record,
+            other => other,
+        };
+
+        let interactions: Vec<Interaction> = if path.exists() {
+            serde_json::from_str(&fs::read_to_string(&path)?)?
+        } else if mode == VcrMode::Replay {
+            return Err(format!("cassette {} does not exist", path.display()).into());
+        } else {
+            Vec::new()
+        };
+
+        Ok(Cassette {
+            path,
+            mode,
+            rules: MatchRules::default(),
+            state: Mutex::new(interactions.into_iter().map(|i| (i, false)).collect()),
+            unsaved: AtomicBool::new(false),
+        })
+    }
+
+    /// The function `match_rules` replaces the rules used to match requests during replay.
+    pub fn match_rules(mut self, rules: MatchRules) -> Self {
+        self.rules = rules;
+        self
+    }
+
+    /// The function `mode` returns the resolved mode of the cassette (never `VcrMode::Auto`).
+    pub fn mode(&self) -> VcrMode {
+        self.mode
+    }
+
+    /// The function `interactions` returns a copy of every interaction currently held by the
+    /// cassette.
+    pub fn interactions(&self) -> Vec<Interaction> {
+        self.state.lock().unwrap().iter().map(|(i, _)| i.clone()).collect()
+    }
+
+    /// The function `save` writes all interactions to the cassette file as pretty-printed JSON.
+    /// Recorded interactions are also saved when the cassette is dropped, but errors can only be
+    /// seen by calling `save`.
+    pub fn save(&self) -> Result<(), Box<dyn Error>> {
+        self.write().map_err(|e| -> Box<dyn Error> { e })
+    }
+
+    fn write(&self) -> Result<(), BoxError> {
+        self.unsaved.store(false, Ordering::Relaxed);
+        let json = serde_json::to_string_pretty(&self.interactions())?;
+        if let Some(parent) = self.path.parent() {
+            if !parent.as_os_str().is_empty() {
+                fs::create_dir_all(parent)?;
+            }
+        }
+        fs::write(&self.path, json)?;
+        Ok(())
+    }
+
+    /// The function `execute` answers a request from the cassette or sends it with `dispatch` and
+    /// records the result, depending on the cassette mode.
+    pub(crate) async fn execute<F, Fut>(&self, request: Request, dispatch: F) -> Result<Response, BoxError>
+    where
+        F: FnOnce(Request) -> Fut,
+        Fut: Future<Output = Result<Response, BoxError>>,
+    {
+        let recorded = Self::record_request(&request);
+        match self.mode {
+            VcrMode::Replay => {
+                let response = self.replay(&recorded)?;
+                let mut response = Self::into_response(&response)?;
+                response.extensions_mut().insert(ResponseUrl(Url::parse(&recorded.url)?));
+                Ok(response)
+            }
+            _ => {
+                let response = dispatch(request).await?;
+                let extensions = response.extensions().clone();
+                let status = response.status().as_u16();
+                let headers = Self::header_pairs(response.headers());
+                let body = RecordedBody::new(&response.bytes().await?);
+                let response = RecordedResponse { status, headers, body };
+
+                self.state.lock().unwrap().push((
+                    Interaction { request: recorded, response: response.clone() },
+                    true,
+                ));
+                self.unsaved.store(true, Ordering::Relaxed);
+                let mut response = Self::into_response(&response)?;
+                *response.extensions_mut() = extensions;
+                Ok(response)
+            }
+        }
+    }
+
+    /// The function `replay` finds the first unused interaction that matches `request` and marks it
+    /// as used.
+    fn replay(&self, request: &RecordedRequest) -> Result<RecordedResponse, BoxError> {
+        let mut state = self.state.lock().unwrap();
+        let found = state.iter_mut().find(|(interaction, used)| {
+            let recorded = &interaction.request;
+            !*used
+                && (!self.rules.method || recorded.method == request.method)
+                && (!self.rules.url || recorded.url == request.url)
+                && (!self.rules.body || recorded.body == request.body)
+        });
+
+        match found {
+            Some((interaction, used)) => {
+                *used = true;
+                Ok(interaction.response.clone())
+            }
+            None => Err(Box::new(CassetteMissError {
+                method: request.method.clone(),
+                url: request.url.clone(),
+            })),
+        }
+    }
+
+    fn record_request(request: &Request) -> RecordedRequest {
+        RecordedRequest {
+            method: request.method().to_string(),
+            url: request.url().to_string(),
+            headers: Self::header_pairs(request.headers()),
+            body: request.body().and_then(|b| b.as_bytes()).map(RecordedBody::new),
+        }
+    }
+
+    fn header_pairs(headers: &reqwest::header::HeaderMap) -> Vec<(String, String)> {
+        headers
+            .iter()
+            .map(|(k, v)| (k.to_string(), String::from_utf8_lossy(v.as_bytes()).into_owned()))
+            .collect()
+    }
+
+    fn into_response(recorded: &RecordedResponse) -> Result<Response, BoxError> {
+        let mut builder = http::Response::builder().status(recorded.status);
+        for (name, value) in &recorded.headers {
+            // The stored body is already decoded, so the original framing headers no longer apply.
+            if name.eq_ignore_ascii_case("content-encoding") || name.eq_ignore_ascii_case("content-length") {
+                continue;
+            }
+            builder = builder.header(name, value);
+        }
+        Ok(Response::from(builder.body(recorded.body.bytes()?)?))
+    }
+}
+
+impl Drop for Cassette {
+    fn drop(&mut self) {
+        if self.unsaved.load(Ordering::Relaxed) {
+            if let Err(e) = self.write() {
+                tracing::warn!(path = %self.path.display(), error = %e, "failed to save the cassette");
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::http::{testing::serve, HttpClient};
+
+    #[tokio::test]
+    async fn test_record_then_replay() -> Result<(), Box<dyn Error>> {
+        let reply = "HTTP/1.1 200 OK\r\ncontent-length: 5\r\nconnection: close\r\n\r\nhello";
+        let (addr, server_task) = serve(vec![reply]).await;
+
+        let path = std::env::temp_dir().join(format!("aarambh-vcr-{}.json", addr.port()));
+        let _ = fs::remove_file(&path);
+        let base_url = format!("http://{}", addr);
+
+        let recorder = HttpClient::new(&base_url, None)?.with_cassette(Cassette::open(&path)?);
+        let response = recorder.get("/greeting", None).await?;
+        assert_eq!(response.text().await?, "hello");
+        server_task.await?;
+        assert!(!path.exists());
+        recorder.cassette().unwrap().save()?;
+
+        // The server is gone, so this can only succeed by replaying the cassette.
+        let replayer = HttpClient::new(&base_url, None)?.with_cassette(Cassette::open(&path)?);
+        let response = replayer.get("/greeting", None).await?;
+        assert_eq!(response.status(), 200);
+        assert_eq!(response.text().await?, "hello");
+        assert!(replayer.get("/other", None).await.is_err());
+
+        fs::remove_file(&path)?;
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_replay_binary_body() -> Result<(), Box<dyn Error>> {
+        let path = std::env::temp_dir().join(format!("aarambh-vcr-binary-{}.json", std::process::id()));
+        let body = vec![0x89, b'P', b'N', b'G', 0xff, 0];
+        let interaction = Interaction {
+            request: RecordedRequest {
+                method: "GET".into(),
+                url: "http://127.0.0.1:9/logo.png".into(),
+                headers: Vec::new(),
+                body: None,
+            },
+            response: RecordedResponse { status: 200, headers: Vec::new(), body: RecordedBody::new(&body) },
+        };
+        assert_eq!(interaction.response.body.encoding, BodyEncoding::Base64);
+        fs::write(&path, serde_json::to_string(&[interaction])?)?;
+
+        let replayer = HttpClient::new("http://127.0.0.1:9", None)?.with_cassette(Cassette::open(&path)?);
+        assert_eq!(replayer.get("/logo.png", None).await?.bytes().await?, body);
+
+        fs::remove_file(&path)?;
+        Ok(())
+    }
+}
@@ -0,0 +1,178 @@
+use super::{response::ResponseUrl, BoxError};
+use crate::timeline::{Timeline, TimelineError, TimelineEventKind, CURRENT};
+use futures_util::StreamExt;
+#[cfg(not(target_arch = "wasm32"))]
+use hyper_util::client::legacy::connect::{Connected, Connection, HttpInfo};
+#[cfg(not(target_arch = "wasm32"))]
+use reqwest::{
+    dns::{Addrs, Name, Resolve, Resolving},
+    tls::TlsInfo,
+};
+use reqwest::{Body, Response};
+#[cfg(not(target_arch = "wasm32"))]
+use std::{
+    future::Future,
+    net::SocketAddr,
+    pin::Pin,
+    task::{Context, Poll},
+};
+#[cfg(not(target_arch = "wasm32"))]
+use tokio::net::lookup_host;
+#[cfg(not(target_arch = "wasm32"))]
+use tower_layer::Layer;
+#[cfg(not(target_arch = "wasm32"))]
+use tower_service::Service;
+
+/// The `TimelineResolver` struct resolves host names like the default resolver and records the
+/// lookup in the timeline of the request that triggered it. Browsers do not expose DNS lookups, so
+/// it is not available on `wasm32`.
+#[cfg(not(target_arch = "wasm32"))]
+pub(crate) struct TimelineResolver;
+
+#[cfg(not(target_arch = "wasm32"))]
+impl Resolve for TimelineResolver {
+    fn resolve(&self, name: Name) -> Resolving {
+        let timeline = CURRENT.try_with(Timeline::clone).ok();
+        let host = name.as_str().to_string();
+        Box::pin(async move {
+            let addrs: Vec<SocketAddr> = lookup_host((host.as_str(), 0)).await?.collect();
+            record_lookup(timeline, &host, &addrs);
+            Ok(Box::new(addrs.into_iter()) as Addrs)
+        })
+    }
+}
+
+/// The function `record_lookup` records the addresses `host` resolved to in `timeline`, the
+/// timeline that was current when the lookup started.
+#[cfg(not(target_arch = "wasm32"))]
+pub(crate) fn record_lookup(timeline: Option<Timeline>, host: &str, addrs: &[SocketAddr]) {
+    if let Some(timeline) = timeline {
+        let list = addrs.iter().map(|a| a.ip().to_string()).collect::<Vec<_>>().join(", ");
+        timeline.record(TimelineEventKind::Resolve, format!("{} -> {}", host, list));
+    }
+}
+
+/// The `TimelineConnectLayer` struct wraps the connector of the client so the connections it opens
+/// are recorded in the timeline of the request that triggered them. The connector hands a
+/// connection over once it is ready, so for HTTPS `Connect` and `Tls` are both recorded when the
+/// handshake completes.
+#[cfg(not(target_arch = "wasm32"))]
+#[derive(Clone)]
+pub(crate) struct TimelineConnectLayer;
+
+#[cfg(not(target_arch = "wasm32"))]
+impl<S> Layer<S> for TimelineConnectLayer {
+    type Service = TimelineConnector<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        TimelineConnector(inner)
+    }
+}
+
+/// The `TimelineConnector` struct is the connector wrapped by `TimelineConnectLayer`.
+#[cfg(not(target_arch = "wasm32"))]
+#[derive(Clone)]
+pub(crate) struct TimelineConnector<S>(S);
+
+#[cfg(not(target_arch = "wasm32"))]
+impl<S, R> Service<R> for TimelineConnector<S>
+where
+    S: Service<R>,
+    S::Response: Connection + Send,
+    S::Error: Send,
+    S::Future: Send + 'static,
+{
+    type Response = S::Response;
+    type Error = S::Error;
+    type Future = Pin<Box<dyn Future<Output = Result<S::Response, S::Error>> + Send>>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.0.poll_ready(cx)
+    }
+
+    fn call(&mut self, request: R) -> Self::Future {
+        let timeline = CURRENT.try_with(Timeline::clone).ok();
+        let connecting = self.0.call(request);
+        Box::pin(async move {
+            let connection = connecting.await?;
+            if let Some(timeline) = timeline {
+                record_connection(&timeline, &connection.connected());
+            }
+            Ok(connection)
+        })
+    }
+}
+
+/// The function `record_connection` records the connection described by `connected` in `timeline`,
+/// followed by its TLS handshake if it is encrypted.
+#[cfg(not(target_arch = "wasm32"))]
+fn record_connection(timeline: &Timeline, connected: &Connected) {
+    let mut extras = http::Extensions::new();
+    connected.get_extras(&mut extras);
+    let peer = match extras.get::<HttpInfo>() {
+        Some(info) => info.remote_addr().to_string(),
+        None => "the proxy".to_string(),
+    };
+    timeline.record(TimelineEventKind::Connect, format!("connected to {}", peer));
+    if extras.get::<TlsInfo>().is_some() {
+        timeline.record(TimelineEventKind::Tls, format!("TLS handshake with {} complete", peer));
+    }
+}
+
+/// The struct `CloseOnDrop` records the `Close` event once the response body has been read to the
+/// end or dropped.
+struct CloseOnDrop(Timeline);
+
+impl Drop for CloseOnDrop {
+    fn drop(&mut self) {
+        self.0.record(TimelineEventKind::Close, "response body released");
+    }
+}
+
+/// The function `trace` runs `exchange` with `timeline` as the current timeline, records the
+/// request and response events, and attaches the timeline to the response (or to the error).
+///
+/// The request is written as soon as it has a connection: once the connection opened for it is
+/// ready, or right away on a connection reused from the pool. `Write` is recorded at that time.
+pub(crate) async fn trace<F>(timeline: Timeline, detail: String, exchange: F) -> Result<Response, BoxError>
+where
+    F: std::future::Future<Output = Result<Response, BoxError>>,
+{
+    let started = timeline.elapsed();
+    let result = CURRENT.scope(timeline.clone(), exchange).await;
+    let connected = timeline
+        .events()
+        .into_iter()
+        .rev()
+        .find(|e| e.elapsed >= started && matches!(e.kind, TimelineEventKind::Connect | TimelineEventKind::Tls))
+        .map(|e| e.elapsed);
+    let response = match result {
+        Ok(response) => response,
+        Err(e) => {
+            if let Some(connected) = connected {
+                timeline.record_at(TimelineEventKind::Write, connected, detail);
+            }
+            return Err(Box::new(TimelineError::new(timeline, e)));
+        }
+    };
+    timeline.record_at(TimelineEventKind::Write, connected.unwrap_or(started), detail);
+    timeline.record(TimelineEventKind::FirstByte, format!("{:?} {}", response.version(), response.status()));
+
+    let mut builder = http::Response::builder().status(response.status()).version(response.version());
+    for (name, value) in response.headers() {
+        builder = builder.header(name, value);
+    }
+    let mut extensions = response.extensions().clone();
+    extensions.insert(ResponseUrl(response.url().clone()));
+    extensions.insert(timeline.clone());
+
+    let guard = CloseOnDrop(timeline);
+    let body = response.bytes_stream().map(move |chunk| {
+        let _ = &guard;
+        chunk
+    });
+
+    let mut response = Response::from(builder.body(Body::wrap_stream(body))?);
+    *response.extensions_mut() = extensions;
+    Ok(response)
+}
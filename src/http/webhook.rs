@@ -0,0 +1,273 @@
+use super::client::HttpClient;
+use crate::{
+    rate_limit::RetryBudget,
+    rt::{sleep, Instant, SystemTime},
+};
+use hmac::{Hmac, Mac};
+use reqwest::{
+    header::{HeaderMap, HeaderName, HeaderValue, CONTENT_TYPE},
+    StatusCode,
+};
+use sha2::Sha256;
+use std::{
+    error::Error,
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+type DeadLetterHook = Box<dyn Fn(&WebhookDelivery) + Send + Sync>;
+
+/// The `DeliveryAttempt` struct records one attempt to deliver a webhook.
+///
+/// # Properties:
+///
+/// * `attempt`: The attempt number, starting at 1.
+/// * `sent_at`: When the attempt was started.
+/// * `duration`: How long the attempt took.
+/// * `status`: The response status, if a response was received.
+/// * `error`: The transport error, if no response was received.
+#[derive(Debug, Clone)]
+pub struct DeliveryAttempt {
+    pub attempt: u32,
+    pub sent_at: SystemTime,
+    pub duration: Duration,
+    pub status: Option<StatusCode>,
+    pub error: Option<String>,
+}
+
+/// The `WebhookDelivery` struct is the record of delivering one payload to one endpoint.
+///
+/// # Properties:
+///
+/// * `endpoint`: The endpoint the payload was sent to.
+/// * `payload`: The payload that was sent.
+/// * `signature`: The signature header value sent with every attempt.
+/// * `attempts`: Every attempt made, in order.
+/// * `delivered`: Whether the receiver accepted the payload with a 2xx response.
+#[derive(Debug, Clone)]
+pub struct WebhookDelivery {
+    pub endpoint: String,
+    pub payload: String,
+    pub signature: String,
+    pub attempts: Vec<DeliveryAttempt>,
+    pub delivered: bool,
+}
+
+/// The `WebhookSender` struct delivers signed webhook payloads through an `HttpClient`.
+///
+/// Each payload is posted as JSON with an HMAC-SHA256 signature of the body in the signature header
+/// (`X-Webhook-Signature: sha256=<hex>` by default). Transport errors, `429 Too Many Requests` and
+/// 5xx responses are retried with exponential backoff; other responses end the delivery. Payloads
+/// that could not be delivered are handed to the dead-letter hook.
+///
+/// # Properties:
+///
+/// * `client`: The `HttpClient` used to send the payloads.
+/// * `secret`: The key used to sign payloads.
+/// * `signature_header`: The header carrying the signature.
+/// * `max_attempts`: The total number of attempts per payload.
+/// * `backoff`: The delay before the first retry, doubled for each further retry.
+/// * `dead_letter`: The optional hook called with deliveries that failed for good.
+/// * `history`: The most recent deliveries, oldest first.
+/// * `history_limit`: How many deliveries `history` keeps.
+/// * `retry_budget`: The optional `RetryBudget` every retry must draw from.
+pub struct WebhookSender {
+    client: HttpClient,
+    secret: Vec<u8>,
+    signature_header: HeaderName,
+    max_attempts: u32,
+    backoff: Duration,
+    dead_letter: Option<DeadLetterHook>,
+    history: Mutex<Vec<WebhookDelivery>>,
+    history_limit: usize,
+    retry_budget: Option<Arc<RetryBudget>>,
+}
+
+impl WebhookSender {
+    /// The function `new` creates a sender that makes up to 5 attempts per payload, starting with a
+    /// one second backoff.
+    ///
+    /// # Arguments:
+    ///
+    /// * `client`: The `HttpClient` used to send the payloads; endpoints are relative to its base URL.
+    /// * `secret`: The shared secret receivers use to verify the signature.
+    pub fn new(client: HttpClient, secret: impl AsRef<[u8]>) -> Self {
+        WebhookSender {
+            client,
+            secret: secret.as_ref().to_vec(),
+            signature_header: HeaderName::from_static("x-webhook-signature"),
+            max_attempts: 5,
+            backoff: Duration::from_secs(1),
+            dead_letter: None,
+            history: Mutex::new(Vec::new()),
+            history_limit: 100,
+            retry_budget: None,
+        }
+    }
+
+    /// The function `signature_header` changes the header the signature is sent in.
+    pub fn signature_header(mut self, name: &str) -> Result<Self, Box<dyn Error>> {
+        self.signature_header = HeaderName::from_bytes(name.as_bytes())?;
+        Ok(self)
+    }
+
+    /// The function `retries` sets the total number of attempts per payload and the delay before the
+    /// first retry, which doubles for each further retry.
+    pub fn retries(mut self, max_attempts: u32, backoff: Duration) -> Self {
+        self.max_attempts = max_attempts.max(1);
+        self.backoff = backoff;
+        self
+    }
+
+    /// The function `retry_budget` makes retries draw from `budget`, usually shared with the rest of
+    /// the application. When it is empty the delivery ends after the current attempt.
+    pub fn retry_budget(mut self, budget: Arc<RetryBudget>) -> Self {
+        self.retry_budget = Some(budget);
+        self
+    }
+
+    /// The function `history_limit` sets how many deliveries are kept for `history`.
+    pub fn history_limit(mut self, limit: usize) -> Self {
+        self.history_limit = limit;
+        self
+    }
+
+    /// The function `on_dead_letter` installs the hook called with every delivery that failed after
+    /// its last attempt, for example to store the payload for a manual replay.
+    pub fn on_dead_letter<F>(mut self, hook: F) -> Self
+    where
+        F: Fn(&WebhookDelivery) + Send + Sync + 'static,
+    {
+        self.dead_letter = Some(Box::new(hook));
+        self
+    }
+
+    /// The function `sign` returns the signature header value for `payload`, as the receiver should
+    /// compute it: `sha256=` followed by the hex encoded HMAC-SHA256 of the payload.
+    pub fn sign(&self, payload: &str) -> String {
+        let mut mac = Hmac::<Sha256>::new_from_slice(&self.secret).expect("HMAC accepts keys of any size");
+        mac.update(payload.as_bytes());
+        let digest = mac.finalize().into_bytes();
+        let hex: String = digest.iter().map(|b| format!("{:02x}", b)).collect();
+        format!("sha256={}", hex)
+    }
+
+    /// The function `send` delivers `payload` to `endpoint`, retrying as configured.
+    ///
+    /// # Arguments:
+    ///
+    /// * `endpoint`: The receiver endpoint, relative to the client's base URL.
+    /// * `payload`: The JSON payload to deliver.
+    ///
+    /// # Returns:
+    ///
+    /// The `send` function returns the `WebhookDelivery` record, whose `delivered` field says whether
+    /// the receiver accepted the payload, or an error if the signature header cannot be built.
+    pub async fn send(&self, endpoint: &str, payload: &str) -> Result<WebhookDelivery, Box<dyn Error>> {
+        let signature = self.sign(payload);
+        let mut headers = HeaderMap::new();
+        headers.insert(CONTENT_TYPE, HeaderValue::from_static("application/json"));
+        headers.insert(self.signature_header.clone(), HeaderValue::from_str(&signature)?);
+
+        let mut delivery = WebhookDelivery {
+            endpoint: endpoint.to_string(),
+            payload: payload.to_string(),
+            signature,
+            attempts: Vec::new(),
+            delivered: false,
+        };
+
+        for attempt in 1..=self.max_attempts {
+            let (sent_at, started) = (SystemTime::now(), Instant::now());
+            let retryable = match self.client.post(endpoint, Some(headers.clone()), Some(payload)).await {
+                Ok(response) => {
+                    let status = response.status();
+                    delivery.attempts.push(DeliveryAttempt {
+                        attempt,
+                        sent_at,
+                        duration: started.elapsed(),
+                        status: Some(status),
+                        error: None,
+                    });
+                    delivery.delivered = status.is_success();
+                    status.is_server_error() || status == StatusCode::TOO_MANY_REQUESTS
+                }
+                Err(e) => {
+                    delivery.attempts.push(DeliveryAttempt {
+                        attempt,
+                        sent_at,
+                        duration: started.elapsed(),
+                        status: None,
+                        error: Some(e.to_string()),
+                    });
+                    true
+                }
+            };
+            if !retryable {
+                if let Some(budget) = &self.retry_budget {
+                    budget.record_success();
+                }
+                break;
+            }
+            if attempt == self.max_attempts || !self.retry_budget.as_ref().is_none_or(|b| b.try_withdraw()) {
+                break;
+            }
+            sleep(self.backoff * 2u32.saturating_pow(attempt - 1)).await;
+        }
+
+        if !delivery.delivered {
+            if let Some(hook) = &self.dead_letter {
+                hook(&delivery);
+            }
+        }
+        let mut history = self.history.lock().unwrap();
+        history.push(delivery.clone());
+        let excess = history.len().saturating_sub(self.history_limit);
+        history.drain(..excess);
+        Ok(delivery)
+    }
+
+    /// The function `history` returns a copy of the most recent deliveries, oldest first.
+    pub fn history(&self) -> Vec<WebhookDelivery> {
+        self.history.lock().unwrap().clone()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::http::testing::serve;
+    use std::sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+    };
+
+    #[tokio::test]
+    async fn test_webhook_retries_and_dead_letters() {
+        let unavailable = "HTTP/1.1 503 Service Unavailable\r\ncontent-length: 0\r\nconnection: close\r\n\r\n";
+        let accepted = "HTTP/1.1 204 No Content\r\nconnection: close\r\n\r\n";
+        let (addr, server_task) = serve(vec![unavailable, accepted, unavailable, unavailable]).await;
+
+        let dead = Arc::new(AtomicUsize::new(0));
+        let counter = dead.clone();
+        let sender = WebhookSender::new(HttpClient::new(&format!("http://{}", addr), None).unwrap(), "secret")
+            .retries(2, Duration::from_millis(10))
+            .on_dead_letter(move |_| {
+                counter.fetch_add(1, Ordering::SeqCst);
+            });
+
+        // HMAC-SHA256 of "{}" keyed with "secret".
+        assert_eq!(sender.sign("{}"), "sha256=77325902caca812dc259733aacd046b73817372c777b8d95b402647474516e13");
+
+        let delivery = sender.send("/hooks", "{}").await.unwrap();
+        assert!(delivery.delivered);
+        assert_eq!(delivery.attempts.len(), 2);
+        assert_eq!(delivery.attempts[0].status, Some(StatusCode::SERVICE_UNAVAILABLE));
+
+        let delivery = sender.send("/hooks", "{}").await.unwrap();
+        assert!(!delivery.delivered);
+        assert_eq!(dead.load(Ordering::SeqCst), 1);
+        assert_eq!(sender.history().len(), 2);
+        server_task.await.unwrap();
+    }
+}
@@ -1,9 +1,71 @@
+mod address_pool;
+#[cfg(not(target_arch = "wasm32"))]
+mod connectivity;
+mod egress;
+mod error;
 mod http;
+#[cfg(not(target_arch = "wasm32"))]
+mod power;
+mod rate_limit;
+mod rt;
+mod sequence;
+#[cfg(not(target_arch = "wasm32"))]
+mod server_handle;
+#[cfg(not(target_arch = "wasm32"))]
+mod socks;
+#[cfg(not(target_arch = "wasm32"))]
 mod tcp;
+#[cfg(not(target_arch = "wasm32"))]
+mod timeline;
+#[cfg(not(target_arch = "wasm32"))]
+mod tls;
+#[cfg(not(target_arch = "wasm32"))]
 mod udp;
 
-pub use http::HttpClient;
-pub use tcp::TcpClient;
-pub use tcp::TcpServer;
-pub use udp::UdpServer;
+pub use address_pool::{AddressStats, LocalAddressPool};
+#[cfg(not(target_arch = "wasm32"))]
+pub use connectivity::{CaptivePortal, ConnectivityState, NetworkMonitor, Probe};
+pub use egress::{EgressPolicy, PolicyViolation, PolicyViolationKind};
+pub use error::{AarambhNetError, AggregateError};
+pub use http::{
+    AarambhResponse, BodyDirection, BodyTooLargeError, ConflictResolution, ConnectivityMonitor, ContentEncoding,
+    DeliveryAttempt, EndpointProfile, FlushReport, HttpClient, HttpClientBuilder, HttpStatusError, ManualConnectivity,
+    OfflineQueue, PoolStats, QueuedRequest, RequestEvent, ResponseEvent, Submission, ToCurl, WebhookDelivery,
+    WebhookSender,
+};
+#[cfg(not(target_arch = "wasm32"))]
+pub use http::{
+    AccessLog, AuthRejection, AuthenticatedUser, BasicAuth, BodyEncoding, Cassette, CassetteMissError, Compression,
+    Cors, Form, FormRejection, FromPathParams, HarRecorder, HealthCheckTimeout, HealthChecks, Http2Settings, HttpServer,
+    Interaction, IntoResponse, Json, JsonRejection, JwtAuth, JwtClaims, MatchRules, Middleware, Multipart,
+    MultipartField, MultipartLimits, Next, PathParamError, PathParams, RateLimit, RecordedBody, RecordedRequest,
+    RecordedResponse, RequestId, Router, ServerRequest, ServerResponse, ShutdownReport, SseEvent, SseResponse, VcrMode,
+    WebSocket, WsMessage, proxy_to, proxy_with,
+};
+#[cfg(all(feature = "blocking", not(target_arch = "wasm32")))]
+pub use http::{BlockingResponse, HttpClientBlocking};
+#[cfg(not(target_arch = "wasm32"))]
+pub use power::{MessageBatcher, PowerManager, PowerProfile};
+pub use rate_limit::{RetryBudget, TokenBucket};
+pub use sequence::{SequenceCheck, SequenceStamper, SequenceTracker};
+#[cfg(not(target_arch = "wasm32"))]
+pub use server_handle::ServerHandle;
+#[cfg(not(target_arch = "wasm32"))]
+pub use socks::Socks5Server;
+#[cfg(not(target_arch = "wasm32"))]
+pub use tcp::{
+    AccessList, BandwidthLimits, ConnectionInfo, ConnectionStats, Endianness, FramedClient, Heartbeat, JsonConnection,
+    LengthPrefixedCodec, LimitAction, LineCodec, MessageRouter, MultiplexedClient, PeerCertificate, PeerLimits,
+    ProxyProtocol, QueuePolicy, ReconnectEvent, ReconnectingTcpClient, Rooms, ServerCodec, ServerStream, Session,
+    SocketOptions, TcpClient, TcpProxy, TcpServer, TcpServerBuilder, TcpServerError, TcpServerStats, TimeoutError,
+    TimeoutOperation,
+};
+#[cfg(not(target_arch = "wasm32"))]
+pub use timeline::{Timeline, TimelineError, TimelineEvent, TimelineEventKind};
+#[cfg(not(target_arch = "wasm32"))]
+pub use tls::{TlsClientConfig, TlsConfig};
+#[cfg(not(target_arch = "wasm32"))]
+pub use udp::{DropPolicy, SchedulerConfig, SchedulerStats, UdpServer};
+#[cfg(any(target_os = "linux", target_os = "android"))]
+pub use udp::{UdpRecvInfo, UdpSendInfo};
 pub use reqwest::header;
\ No newline at end of file
@@ -0,0 +1,241 @@
+use std::{
+    error::Error,
+    future::Future,
+    mem,
+    sync::{Arc, Mutex, Weak},
+    time::Duration,
+};
+use tokio::{
+    sync::{mpsc, watch},
+    task::JoinHandle,
+    time::{sleep, timeout_at, Instant},
+};
+
+type HeartbeatHook = Box<dyn Fn() + Send + Sync>;
+
+/// The `PowerProfile` struct describes how often connections may wake the radio of the device.
+///
+/// # Properties:
+///
+/// * `keepalive_interval`: The idle time before TCP keepalive probes are sent.
+/// * `heartbeat_interval`: The time between two rounds of application heartbeats.
+/// * `batch_window`: How long outbound messages are held to be sent together; zero sends each
+///   message immediately.
+/// * `max_batch`: The number of held messages that triggers a send before the window ends.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PowerProfile {
+    pub keepalive_interval: Duration,
+    pub heartbeat_interval: Duration,
+    pub batch_window: Duration,
+    pub max_batch: usize,
+}
+
+impl PowerProfile {
+    /// The function `standard` returns the profile for devices on mains power: 15 second keepalives,
+    /// 30 second heartbeats and no batching.
+    pub fn standard() -> Self {
+        PowerProfile {
+            keepalive_interval: Duration::from_secs(15),
+            heartbeat_interval: Duration::from_secs(30),
+            batch_window: Duration::ZERO,
+            max_batch: 1,
+        }
+    }
+
+    /// The function `low_power` returns the profile for battery-powered devices: 2 minute keepalives,
+    /// 5 minute heartbeats and outbound messages batched for up to 2 seconds.
+    pub fn low_power() -> Self {
+        PowerProfile {
+            keepalive_interval: Duration::from_secs(120),
+            heartbeat_interval: Duration::from_secs(300),
+            batch_window: Duration::from_secs(2),
+            max_batch: 64,
+        }
+    }
+}
+
+impl Default for PowerProfile {
+    fn default() -> Self {
+        Self::standard()
+    }
+}
+
+/// The `PowerManager` struct holds the `PowerProfile` shared by the connections of an application
+/// and lets it be switched at runtime, for example when the device is unplugged.
+///
+/// Heartbeats registered with `add_heartbeat` are consolidated: they all run in the same wakeup,
+/// once per `heartbeat_interval`, instead of each connection waking the device on its own timer.
+///
+/// # Properties:
+///
+/// * `profile`: The current profile; subscribers are told when it changes.
+/// * `heartbeats`: The heartbeat hooks run on every heartbeat round.
+pub struct PowerManager {
+    profile: watch::Sender<PowerProfile>,
+    heartbeats: Mutex<Vec<HeartbeatHook>>,
+}
+
+impl PowerManager {
+    /// The function `new` creates a manager starting with `profile`.
+    pub fn new(profile: PowerProfile) -> Arc<Self> {
+        Arc::new(PowerManager { profile: watch::Sender::new(profile), heartbeats: Mutex::new(Vec::new()) })
+    }
+
+    /// The function `profile` returns the current profile.
+    pub fn profile(&self) -> PowerProfile {
+        self.profile.borrow().clone()
+    }
+
+    /// The function `set_profile` switches to `profile`. Running heartbeat loops and batchers pick up
+    /// the change at once.
+    pub fn set_profile(&self, profile: PowerProfile) {
+        self.profile.send_replace(profile);
+    }
+
+    /// The function `subscribe` returns a receiver notified whenever the profile changes.
+    pub fn subscribe(&self) -> watch::Receiver<PowerProfile> {
+        self.profile.subscribe()
+    }
+
+    /// The function `add_heartbeat` registers a hook run on every heartbeat round. Hooks must not
+    /// block; a hook sending an async ping should spawn it or hand it to a channel.
+    pub fn add_heartbeat<F>(&self, hook: F)
+    where
+        F: Fn() + Send + Sync + 'static,
+    {
+        self.heartbeats.lock().unwrap().push(Box::new(hook));
+    }
+
+    /// The function `spawn_heartbeats` starts the task running every registered heartbeat once per
+    /// `heartbeat_interval`. A profile change restarts the wait with the new interval. The task ends
+    /// when the manager is dropped.
+    pub fn spawn_heartbeats(self: &Arc<Self>) -> JoinHandle<()> {
+        let manager: Weak<Self> = Arc::downgrade(self);
+        let mut changes = self.subscribe();
+        tokio::spawn(async move {
+            loop {
+                let interval = changes.borrow_and_update().heartbeat_interval;
+                tokio::select! {
+                    _ = sleep(interval) => {
+                        let Some(manager) = manager.upgrade() else { return };
+                        for hook in manager.heartbeats.lock().unwrap().iter() {
+                            hook();
+                        }
+                    }
+                    changed = changes.changed() => {
+                        if changed.is_err() {
+                            return;
+                        }
+                    }
+                }
+            }
+        })
+    }
+}
+
+/// The `MessageBatcher` struct holds outbound messages for the `batch_window` of the current
+/// profile and hands them to a flush function together, so the radio wakes once per batch rather
+/// than once per message.
+///
+/// # Properties:
+///
+/// * `sender`: The channel feeding the batching task.
+/// * `task`: The batching task, which flushes what is left when the batcher is closed.
+pub struct MessageBatcher {
+    sender: mpsc::UnboundedSender<Vec<u8>>,
+    task: JoinHandle<()>,
+}
+
+impl MessageBatcher {
+    /// The function `spawn` starts a batcher.
+    ///
+    /// # Arguments:
+    ///
+    /// * `manager`: The `PowerManager` whose profile sets the batch window and size, read again for
+    ///   every batch.
+    /// * `flush`: The function sending one batch of messages, oldest first.
+    pub fn spawn<F, Fut>(manager: Arc<PowerManager>, mut flush: F) -> Self
+    where
+        F: FnMut(Vec<Vec<u8>>) -> Fut + Send + 'static,
+        Fut: Future<Output = ()> + Send,
+    {
+        let (sender, mut receiver) = mpsc::unbounded_channel::<Vec<u8>>();
+        let task = tokio::spawn(async move {
+            let mut batch = Vec::new();
+            while let Some(message) = receiver.recv().await {
+                batch.push(message);
+                let profile = manager.profile();
+                let deadline = Instant::now() + profile.batch_window;
+                let mut open = true;
+                while open && batch.len() < profile.max_batch {
+                    match timeout_at(deadline, receiver.recv()).await {
+                        Ok(Some(message)) => batch.push(message),
+                        Ok(None) => open = false,
+                        Err(_) => break,
+                    }
+                }
+                flush(mem::take(&mut batch)).await;
+                if !open {
+                    return;
+                }
+            }
+        });
+        MessageBatcher { sender, task }
+    }
+
+    /// The function `send` queues `message` for the next batch.
+    ///
+    /// # Returns:
+    ///
+    /// The `send` function returns an error if the batching task has stopped.
+    pub fn send(&self, message: impl Into<Vec<u8>>) -> Result<(), Box<dyn Error>> {
+        self.sender.send(message.into()).map_err(|_| "the message batcher has stopped")?;
+        Ok(())
+    }
+
+    /// The function `close` flushes the messages still held and waits for the batching task to end.
+    pub async fn close(self) {
+        drop(self.sender);
+        let _ = self.task.await;
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    #[tokio::test(start_paused = true)]
+    async fn test_low_power_batches_and_heartbeats() {
+        let manager = PowerManager::new(PowerProfile::standard());
+        let batches = Arc::new(Mutex::new(Vec::new()));
+        let seen = batches.clone();
+        let batcher = MessageBatcher::spawn(manager.clone(), move |batch| {
+            seen.lock().unwrap().push(batch.len());
+            async {}
+        });
+
+        batcher.send("a").unwrap();
+        sleep(Duration::from_millis(10)).await;
+        manager.set_profile(PowerProfile::low_power());
+        for message in ["b", "c", "d"] {
+            batcher.send(message).unwrap();
+        }
+        batcher.close().await;
+        assert_eq!(*batches.lock().unwrap(), [1, 3]);
+
+        let beats = Arc::new(AtomicUsize::new(0));
+        for _ in 0..2 {
+            let beats = beats.clone();
+            manager.add_heartbeat(move || {
+                beats.fetch_add(1, Ordering::SeqCst);
+            });
+        }
+        let task = manager.spawn_heartbeats();
+        sleep(Duration::from_secs(301)).await;
+        assert_eq!(beats.load(Ordering::SeqCst), 2);
+        drop(manager);
+        sleep(Duration::from_secs(301)).await;
+        assert!(task.is_finished());
+    }
+}
@@ -0,0 +1,192 @@
+use crate::rt::{self, Instant};
+use std::{
+    sync::{Arc, Mutex, OnceLock},
+    time::Duration,
+};
+
+/// The `TokenBucket` struct is a thread-safe token-bucket rate limiter: it holds up to `capacity`
+/// tokens and refills at `refill_per_sec` tokens per second.
+///
+/// # Properties:
+///
+/// * `capacity`: The maximum number of tokens, i.e. the largest burst that is allowed.
+/// * `refill_per_sec`: The number of tokens added per second.
+/// * `state`: The current number of tokens and the instant they were last refilled.
+#[derive(Debug)]
+pub struct TokenBucket {
+    capacity: f64,
+    refill_per_sec: f64,
+    state: Mutex<(f64, Instant)>,
+}
+
+impl TokenBucket {
+    /// The function `new` creates a full bucket.
+    ///
+    /// # Arguments:
+    ///
+    /// * `capacity`: The maximum burst size in tokens.
+    /// * `refill_per_sec`: The sustained rate in tokens per second.
+    pub fn new(capacity: f64, refill_per_sec: f64) -> Self {
+        TokenBucket { capacity, refill_per_sec, state: Mutex::new((capacity, Instant::now())) }
+    }
+
+    /// The function `per_second` creates a bucket allowing `rate` operations per second with a burst
+    /// of the same size.
+    pub fn per_second(rate: f64) -> Self {
+        Self::new(rate.max(1.0), rate)
+    }
+
+    /// The function `try_acquire` takes `tokens` from the bucket if enough are available.
+    ///
+    /// # Returns:
+    ///
+    /// The `try_acquire` function returns `Ok(())` when the tokens were taken, or `Err(wait)` with the
+    /// time until enough tokens will be available.
+    pub fn try_acquire(&self, tokens: f64) -> Result<(), Duration> {
+        let mut state = self.state.lock().unwrap();
+        let now = Instant::now();
+        let elapsed = now.duration_since(state.1).as_secs_f64();
+        state.0 = (state.0 + elapsed * self.refill_per_sec).min(self.capacity);
+        state.1 = now;
+
+        if state.0 >= tokens {
+            state.0 -= tokens;
+            Ok(())
+        } else if self.refill_per_sec <= 0.0 {
+            Err(Duration::MAX)
+        } else {
+            Err(Duration::from_secs_f64((tokens - state.0) / self.refill_per_sec))
+        }
+    }
+
+    /// The function `acquire` waits until `tokens` are available and takes them.
+    pub async fn acquire(&self, tokens: f64) {
+        while let Err(wait) = self.try_acquire(tokens) {
+            rt::sleep(wait.min(Duration::from_secs(1))).await;
+        }
+    }
+
+    /// The function `consume` takes `tokens` from the bucket even if fewer are available, leaving it
+    /// in debt.
+    ///
+    /// # Returns:
+    ///
+    /// The `consume` function returns the time until the debt is repaid, zero if there is none.
+    #[cfg(not(target_arch = "wasm32"))]
+    pub(crate) fn consume(&self, tokens: f64) -> Duration {
+        let mut state = self.state.lock().unwrap();
+        let now = Instant::now();
+        let elapsed = now.duration_since(state.1).as_secs_f64();
+        state.0 = (state.0 + elapsed * self.refill_per_sec).min(self.capacity) - tokens;
+        state.1 = now;
+        if state.0 >= 0.0 {
+            Duration::ZERO
+        } else if self.refill_per_sec <= 0.0 {
+            Duration::MAX
+        } else {
+            Duration::from_secs_f64(-state.0 / self.refill_per_sec)
+        }
+    }
+
+    /// The function `add` returns up to `tokens` to the bucket, never exceeding its capacity.
+    pub fn add(&self, tokens: f64) {
+        let mut state = self.state.lock().unwrap();
+        state.0 = (state.0 + tokens).min(self.capacity);
+    }
+
+    /// The function `capacity` returns the maximum number of tokens the bucket holds.
+    pub fn capacity(&self) -> f64 {
+        self.capacity
+    }
+
+    /// The function `available` returns the number of tokens currently in the bucket.
+    pub fn available(&self) -> f64 {
+        let state = self.state.lock().unwrap();
+        let elapsed = state.1.elapsed().as_secs_f64();
+        (state.0 + elapsed * self.refill_per_sec).min(self.capacity)
+    }
+}
+
+/// The `RetryBudget` struct limits retries across every client sharing it, so an upstream outage is
+/// not amplified by each caller retrying on its own. Every successful request deposits a fraction
+/// of a token, every retry withdraws a whole one, and a small trickle of tokens per second keeps
+/// retries possible when traffic is low. Retries that find the budget empty are skipped and the
+/// last result is returned as is.
+///
+/// # Properties:
+///
+/// * `bucket`: The tokens available for retries.
+/// * `deposit`: The fraction of a token added for each successful request.
+#[derive(Debug)]
+pub struct RetryBudget {
+    bucket: TokenBucket,
+    deposit: f64,
+}
+
+impl RetryBudget {
+    /// The function `new` creates a budget that starts full.
+    ///
+    /// # Arguments:
+    ///
+    /// * `retry_ratio`: The share of requests that may be retried, for example `0.2` for one retry per
+    ///   five successful requests.
+    /// * `min_per_second`: The number of retries allowed per second regardless of traffic.
+    pub fn new(retry_ratio: f64, min_per_second: f64) -> Self {
+        let capacity = (min_per_second * 10.0).max(1.0);
+        RetryBudget { bucket: TokenBucket::new(capacity, min_per_second), deposit: retry_ratio.max(0.0) }
+    }
+
+    /// The function `global` returns the budget shared by the whole process, created on first use
+    /// with a 20% retry ratio and 10 retries per second.
+    pub fn global() -> Arc<RetryBudget> {
+        static GLOBAL: OnceLock<Arc<RetryBudget>> = OnceLock::new();
+        GLOBAL.get_or_init(|| Arc::new(RetryBudget::new(0.2, 10.0))).clone()
+    }
+
+    /// The function `record_success` deposits the share of a token earned by a successful request.
+    pub fn record_success(&self) {
+        self.bucket.add(self.deposit);
+    }
+
+    /// The function `try_withdraw` takes the token for one retry.
+    ///
+    /// # Returns:
+    ///
+    /// The `try_withdraw` function returns `true` when the retry may go ahead.
+    pub fn try_withdraw(&self) -> bool {
+        self.bucket.try_acquire(1.0).is_ok()
+    }
+
+    /// The function `available` returns the number of retries currently left in the budget.
+    pub fn available(&self) -> f64 {
+        self.bucket.available()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_token_bucket() {
+        let bucket = TokenBucket::new(2.0, 1.0);
+        assert!(bucket.try_acquire(1.0).is_ok());
+        assert!(bucket.try_acquire(1.0).is_ok());
+        let wait = bucket.try_acquire(1.0).unwrap_err();
+        assert!(wait > Duration::from_millis(900) && wait <= Duration::from_secs(1));
+
+        bucket.add(5.0);
+        assert!(bucket.available() <= 2.0);
+    }
+
+    #[test]
+    fn test_retry_budget() {
+        let budget = RetryBudget::new(0.5, 0.0);
+        assert!(budget.try_withdraw());
+        assert!(!budget.try_withdraw());
+        budget.record_success();
+        assert!(!budget.try_withdraw());
+        budget.record_success();
+        assert!(budget.try_withdraw());
+    }
+}
@@ -0,0 +1,15 @@
+//! Clock and timer shims for the code shared with the `wasm32` build, where `std::time::Instant` and
+//! the tokio timer are not available and the browser clock and `setTimeout` are used instead.
+
+#[cfg(not(target_arch = "wasm32"))]
+pub(crate) use std::time::{Instant, SystemTime, UNIX_EPOCH};
+#[cfg(not(target_arch = "wasm32"))]
+pub(crate) use tokio::time::sleep;
+#[cfg(target_arch = "wasm32")]
+pub(crate) use web_time::{Instant, SystemTime};
+
+/// The function `sleep` waits for `duration` using a browser timer.
+#[cfg(target_arch = "wasm32")]
+pub(crate) async fn sleep(duration: std::time::Duration) {
+    gloo_timers::future::sleep(duration).await
+}
@@ -0,0 +1,217 @@
+use crate::error::AarambhNetError;
+use std::{
+    collections::BTreeSet,
+    error::Error,
+    io,
+    ops::Range,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Mutex,
+    },
+};
+
+/// The length of the big-endian sequence number prefixed to every frame.
+const HEADER_LEN: usize = 8;
+
+type GapHook = Box<dyn Fn(Range<u64>) + Send + Sync>;
+type DuplicateHook = Box<dyn Fn(u64) + Send + Sync>;
+
+/// The `SequenceStamper` struct numbers outgoing messages by prefixing each payload with an 8-byte
+/// big-endian sequence number, starting at 0. A `SequenceTracker` on the receiving side strips the
+/// number again and reports lost and duplicated messages, which makes silent loss visible across
+/// proxies and reliable-UDP paths.
+#[derive(Debug, Default)]
+pub struct SequenceStamper {
+    next: AtomicU64,
+}
+
+impl SequenceStamper {
+    /// The function `new` creates a stamper whose first message gets sequence number 0.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The function `stamp` returns `payload` prefixed with the next sequence number.
+    pub fn stamp(&self, payload: &[u8]) -> Vec<u8> {
+        let sequence = self.next.fetch_add(1, Ordering::Relaxed);
+        let mut frame = Vec::with_capacity(HEADER_LEN + payload.len());
+        frame.extend_from_slice(&sequence.to_be_bytes());
+        frame.extend_from_slice(payload);
+        frame
+    }
+
+    /// The function `next_sequence` returns the number the next stamped message will get.
+    pub fn next_sequence(&self) -> u64 {
+        self.next.load(Ordering::Relaxed)
+    }
+}
+
+/// The `SequenceCheck` enum is the verdict of a `SequenceTracker` on one received frame.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SequenceCheck {
+    /// The frame is the one that was expected next.
+    InOrder,
+    /// The frame skipped ahead; the numbers in the range have not been received.
+    Gap(Range<u64>),
+    /// The frame fills an earlier gap, it was reordered rather than lost.
+    Late,
+    /// The frame was received before and should be discarded.
+    Duplicate,
+}
+
+#[derive(Default)]
+struct TrackerState {
+    expected: u64,
+    missing: BTreeSet<u64>,
+}
+
+/// The `SequenceTracker` struct checks the sequence numbers of frames built by a `SequenceStamper`.
+/// Use one tracker per sender, for example one per peer address of a `UdpServer`.
+///
+/// # Properties:
+///
+/// * `state`: The next expected number and the numbers of the gaps that are still open.
+/// * `window`: How many missing numbers are remembered to tell late frames from duplicates.
+/// * `on_gap`: The optional hook called with the missing range when a gap is detected.
+/// * `on_duplicate`: The optional hook called with the number of every duplicate frame.
+pub struct SequenceTracker {
+    state: Mutex<TrackerState>,
+    window: usize,
+    on_gap: Option<GapHook>,
+    on_duplicate: Option<DuplicateHook>,
+}
+
+impl Default for SequenceTracker {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl SequenceTracker {
+    /// The function `new` creates a tracker expecting sequence number 0 that remembers up to 1024
+    /// missing numbers.
+    pub fn new() -> Self {
+        SequenceTracker { state: Mutex::new(TrackerState::default()), window: 1024, on_gap: None, on_duplicate: None }
+    }
+
+    /// The function `window` sets how many missing numbers are remembered. A frame older than the
+    /// window is reported as a duplicate even if it was never received.
+    pub fn window(mut self, window: usize) -> Self {
+        self.window = window;
+        self
+    }
+
+    /// The function `on_gap` installs the hook called with the range of missing sequence numbers
+    /// whenever a frame skips ahead.
+    pub fn on_gap<F>(mut self, hook: F) -> Self
+    where
+        F: Fn(Range<u64>) + Send + Sync + 'static,
+    {
+        self.on_gap = Some(Box::new(hook));
+        self
+    }
+
+    /// The function `on_duplicate` installs the hook called with the sequence number of every
+    /// duplicate frame.
+    pub fn on_duplicate<F>(mut self, hook: F) -> Self
+    where
+        F: Fn(u64) + Send + Sync + 'static,
+    {
+        self.on_duplicate = Some(Box::new(hook));
+        self
+    }
+
+    /// The function `receive` checks a received frame and strips its sequence number.
+    ///
+    /// # Arguments:
+    ///
+    /// * `frame`: The frame as produced by `SequenceStamper::stamp`.
+    ///
+    /// # Returns:
+    ///
+    /// The `receive` function returns a `Result` containing the sequence number, the `SequenceCheck`
+    /// verdict and the payload, or an error if the frame is shorter than the sequence header.
+    pub fn receive<'a>(&self, frame: &'a [u8]) -> Result<(u64, SequenceCheck, &'a [u8]), Box<dyn Error>> {
+        if frame.len() < HEADER_LEN {
+            let e = io::Error::new(io::ErrorKind::InvalidData, "frame shorter than the sequence header");
+            return Err(Box::new(AarambhNetError::new("check sequence", e).with_bytes_transferred(frame.len() as u64)));
+        }
+        let (header, payload) = frame.split_at(HEADER_LEN);
+        let sequence = u64::from_be_bytes(header.try_into().unwrap());
+        Ok((sequence, self.check(sequence), payload))
+    }
+
+    /// The function `check` records `sequence` as received and returns the verdict, calling the gap
+    /// and duplicate hooks as needed. It is useful when the sequence number travels in a header of
+    /// its own rather than in a `SequenceStamper` frame.
+    pub fn check(&self, sequence: u64) -> SequenceCheck {
+        let mut state = self.state.lock().unwrap();
+        let verdict = if sequence == state.expected {
+            state.expected += 1;
+            SequenceCheck::InOrder
+        } else if sequence > state.expected {
+            let gap = state.expected..sequence;
+            state.missing.extend(gap.clone().rev().take(self.window));
+            while state.missing.len() > self.window {
+                state.missing.pop_first();
+            }
+            state.expected = sequence + 1;
+            SequenceCheck::Gap(gap)
+        } else if state.missing.remove(&sequence) {
+            SequenceCheck::Late
+        } else {
+            SequenceCheck::Duplicate
+        };
+        drop(state);
+
+        match &verdict {
+            SequenceCheck::Gap(gap) => {
+                if let Some(hook) = &self.on_gap {
+                    hook(gap.clone());
+                }
+            }
+            SequenceCheck::Duplicate => {
+                if let Some(hook) = &self.on_duplicate {
+                    hook(sequence);
+                }
+            }
+            _ => {}
+        }
+        verdict
+    }
+
+    /// The function `missing` returns the sequence numbers of open gaps, oldest first.
+    pub fn missing(&self) -> Vec<u64> {
+        self.state.lock().unwrap().missing.iter().copied().collect()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::sync::Arc;
+
+    #[test]
+    fn test_gap_and_duplicate_detection() {
+        let stamper = SequenceStamper::new();
+        let frames: Vec<_> = ["a", "b", "c", "d"].iter().map(|p| stamper.stamp(p.as_bytes())).collect();
+
+        let gaps = Arc::new(Mutex::new(Vec::new()));
+        let duplicates = Arc::new(Mutex::new(Vec::new()));
+        let (g, d) = (gaps.clone(), duplicates.clone());
+        let tracker = SequenceTracker::new()
+            .on_gap(move |range| g.lock().unwrap().push(range))
+            .on_duplicate(move |sequence| d.lock().unwrap().push(sequence));
+
+        assert_eq!(tracker.receive(&frames[0]).unwrap(), (0, SequenceCheck::InOrder, &b"a"[..]));
+        assert_eq!(tracker.receive(&frames[3]).unwrap().1, SequenceCheck::Gap(1..3));
+        assert_eq!(tracker.missing(), [1, 2]);
+        assert_eq!(tracker.receive(&frames[2]).unwrap().1, SequenceCheck::Late);
+        assert_eq!(tracker.receive(&frames[2]).unwrap().1, SequenceCheck::Duplicate);
+        assert_eq!(tracker.missing(), [1]);
+        assert!(tracker.receive(b"short").is_err());
+
+        assert_eq!(gaps.lock().unwrap().pop(), Some(1..3));
+        assert_eq!(*duplicates.lock().unwrap(), [2]);
+    }
+}
@@ -0,0 +1,149 @@
+use std::{
+    error::Error,
+    fmt,
+    future::Future,
+    net::SocketAddr,
+    panic,
+    pin::Pin,
+    task::{ready, Context, Poll},
+    time::Duration,
+};
+use tokio::task::JoinHandle;
+
+type ServerResult<T> = Result<T, Box<dyn Error + Send + Sync>>;
+
+/// The `ServerHandle` struct controls a server running on a task of its own, as returned by
+/// `TcpServer::spawn`, `UdpServer::spawn` and `HttpServer::spawn`. It tells the address the server
+/// listens on, shuts it down, and can be awaited for what its `run` returned once it stopped. A
+/// server whose `run` panicked resumes the panic in the task awaiting the handle. Dropping the
+/// handle leaves the server running in the background.
+///
+/// # Properties:
+///
+/// * `addr`: The address the server listens on.
+/// * `stop`: Tells the server to shut down.
+/// * `task`: The task running the server.
+pub struct ServerHandle<T> {
+    addr: SocketAddr,
+    stop: Box<dyn Fn() + Send + Sync>,
+    task: JoinHandle<ServerResult<T>>,
+}
+
+impl<T: Send + 'static> ServerHandle<T> {
+    /// The function `spawn` runs `server` on a new task, which `stop` shuts down.
+    pub(crate) fn spawn<F, E>(addr: SocketAddr, stop: impl Fn() + Send + Sync + 'static, server: F) -> Self
+    where
+        F: Future<Output = Result<T, E>> + Send + 'static,
+        E: Into<Box<dyn Error + Send + Sync>>,
+    {
+        let task = tokio::spawn(async move { server.await.map_err(Into::into) });
+        ServerHandle { addr, stop: Box::new(stop), task }
+    }
+}
+
+impl<T> ServerHandle<T> {
+    /// The function `addr` returns the address the server listens on, with the port the system
+    /// assigned when bound to port 0.
+    pub fn addr(&self) -> SocketAddr {
+        self.addr
+    }
+
+    /// The function `shutdown` tells the server to shut down like the `shutdown` of the server
+    /// itself. Await the handle to wait until it has stopped.
+    pub async fn shutdown(&self) {
+        (self.stop)();
+    }
+
+    /// The function `is_finished` returns whether the server has stopped.
+    pub fn is_finished(&self) -> bool {
+        self.task.is_finished()
+    }
+}
+
+impl<T> Future for ServerHandle<T> {
+    type Output = ServerResult<T>;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        match ready!(Pin::new(&mut self.task).poll(cx)) {
+            Ok(result) => Poll::Ready(result),
+            Err(e) if e.is_panic() => panic::resume_unwind(e.into_panic()),
+            Err(e) => Poll::Ready(Err(e.into())),
+        }
+    }
+}
+
+impl<T> fmt::Debug for ServerHandle<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ServerHandle").field("addr", &self.addr).field("finished", &self.is_finished()).finish()
+    }
+}
+
+/// The `ShutdownReport` struct tells how a server shut down: what it was still working on when
+/// `shutdown` was called, and whether that finished in time. It is returned by `HttpServer::run`,
+/// which counts requests, and by `TcpServer::shutdown_with_timeout`, which counts connections.
+///
+/// # Properties:
+///
+/// * `drained`: The number of requests or connections that completed after `shutdown` was called.
+/// * `aborted`: The number still running when the drain timeout expired, which were cancelled and
+///   their connections closed.
+/// * `elapsed`: How long draining took.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ShutdownReport {
+    pub drained: usize,
+    pub aborted: usize,
+    pub elapsed: Duration,
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{
+        http::{HttpClient, HttpServer, ServerResponse},
+        tcp::{LineCodec, TcpClient, TcpServer},
+        udp::UdpServer,
+    };
+    use futures_util::{SinkExt, StreamExt};
+    use tokio::net::UdpSocket;
+
+    #[tokio::test]
+    async fn test_server_handles() {
+        let tcp = TcpServer::bind("127.0.0.1:0").await.unwrap().spawn().unwrap();
+        let mut client = TcpClient::connect(&tcp.addr().to_string()).await.unwrap();
+        client.send_message("hello").await.unwrap();
+        assert_eq!(client.receive_response().await.unwrap(), "hello");
+        tcp.shutdown().await;
+        tcp.await.unwrap();
+
+        let framed = TcpServer::bind("127.0.0.1:0").await.unwrap().spawn_with(|server| async move {
+            let served = server.run_framed(LineCodec::new(), |mut lines, _| async move {
+                while let Some(Ok(line)) = lines.next().await {
+                    let _ = lines.send(line.to_uppercase()).await;
+                }
+            });
+            served.await
+        });
+        let framed = framed.unwrap();
+        let mut lines = TcpClient::connect(&framed.addr().to_string()).await.unwrap().into_framed(LineCodec::new());
+        lines.send("shout").await.unwrap();
+        assert_eq!(lines.next().await.unwrap().unwrap(), "SHOUT");
+        framed.shutdown().await;
+        framed.await.unwrap();
+
+        let udp = UdpServer::bind("127.0.0.1:0").await.unwrap().spawn().unwrap();
+        let socket = UdpSocket::bind("127.0.0.1:0").await.unwrap();
+        socket.send_to(b"datagram", udp.addr()).await.unwrap();
+        let mut buf = [0; 16];
+        let (len, _) = socket.recv_from(&mut buf).await.unwrap();
+        assert_eq!(&buf[..len], b"datagram");
+        udp.shutdown().await;
+        udp.await.unwrap();
+
+        let http = HttpServer::bind("127.0.0.1:0").await.unwrap().get("/", |_| async { ServerResponse::text("up") });
+        let http = http.spawn().unwrap();
+        let client = HttpClient::new(&format!("http://{}", http.addr()), None).unwrap();
+        assert_eq!(client.get("/", None).await.unwrap().text().await.unwrap(), "up");
+        assert!(!http.is_finished());
+        http.shutdown().await;
+        assert_eq!(http.await.unwrap().aborted, 0);
+    }
+}
@@ -0,0 +1,56 @@
+use super::protocol::{self, TargetAddr};
+use crate::http::BoxError;
+use tokio::{
+    io::{AsyncReadExt, AsyncWriteExt},
+    net::TcpStream,
+};
+
+/// The function `connect_through` asks the SOCKS5 proxy at the other end of `socket` to connect to
+/// `target`, authenticating with `credentials` if given (RFC 1929). Once it returns, `socket` is
+/// relayed to the target.
+pub(crate) async fn connect_through(
+    socket: &mut TcpStream,
+    target: &TargetAddr,
+    credentials: Option<(&str, &str)>,
+) -> Result<(), BoxError> {
+    let method = match credentials {
+        Some(_) => protocol::USERNAME_PASSWORD,
+        None => protocol::NO_AUTHENTICATION,
+    };
+    socket.write_all(&[protocol::VERSION, 1, method]).await?;
+    let mut chosen = [0; 2];
+    socket.read_exact(&mut chosen).await?;
+    if chosen[0] != protocol::VERSION {
+        return Err(format!("unsupported SOCKS version {}", chosen[0]).into());
+    }
+    if chosen[1] != method {
+        return Err("the proxy accepted none of the authentication methods offered".into());
+    }
+    if let Some((username, password)) = credentials {
+        if username.len() > 255 || password.len() > 255 {
+            return Err("the username and password must be at most 255 bytes".into());
+        }
+        let mut auth = vec![protocol::AUTH_VERSION, username.len() as u8];
+        auth.extend_from_slice(username.as_bytes());
+        auth.push(password.len() as u8);
+        auth.extend_from_slice(password.as_bytes());
+        socket.write_all(&auth).await?;
+        let mut status = [0; 2];
+        socket.read_exact(&mut status).await?;
+        if status[1] != 0 {
+            return Err(format!("the proxy refused the credentials of user {:?}", username).into());
+        }
+    }
+
+    let mut request = vec![protocol::VERSION, protocol::CONNECT, 0];
+    target.encode(&mut request);
+    socket.write_all(&request).await?;
+    let mut reply = [0; 3];
+    socket.read_exact(&mut reply).await?;
+    // The bound address follows the reply code, even when the request failed.
+    TargetAddr::read(socket).await?;
+    match reply[1] {
+        protocol::SUCCEEDED => Ok(()),
+        code => Err(protocol::reply_error(code).into()),
+    }
+}
@@ -0,0 +1,7 @@
+mod client;
+mod protocol;
+mod server;
+
+pub(crate) use client::connect_through;
+pub(crate) use protocol::TargetAddr;
+pub use server::Socks5Server;
@@ -0,0 +1,185 @@
+use std::{
+    fmt, io,
+    net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr},
+};
+use tokio::io::{AsyncRead, AsyncReadExt};
+
+/// The SOCKS protocol version spoken, from RFC 1928.
+pub(crate) const VERSION: u8 = 5;
+
+/// The authentication methods offered in the greeting.
+pub(crate) const NO_AUTHENTICATION: u8 = 0x00;
+pub(crate) const USERNAME_PASSWORD: u8 = 0x02;
+pub(crate) const NO_ACCEPTABLE_METHOD: u8 = 0xff;
+
+/// The version of the username/password subnegotiation, from RFC 1929.
+pub(crate) const AUTH_VERSION: u8 = 1;
+
+/// The commands of a request.
+pub(crate) const CONNECT: u8 = 1;
+pub(crate) const UDP_ASSOCIATE: u8 = 3;
+
+/// The address types of a request or a UDP header.
+const ATYP_IPV4: u8 = 1;
+const ATYP_DOMAIN: u8 = 3;
+const ATYP_IPV6: u8 = 4;
+
+/// The reply codes of a request.
+pub(crate) const SUCCEEDED: u8 = 0;
+pub(crate) const GENERAL_FAILURE: u8 = 1;
+pub(crate) const NOT_ALLOWED: u8 = 2;
+pub(crate) const NETWORK_UNREACHABLE: u8 = 3;
+pub(crate) const HOST_UNREACHABLE: u8 = 4;
+pub(crate) const CONNECTION_REFUSED: u8 = 5;
+pub(crate) const TTL_EXPIRED: u8 = 6;
+pub(crate) const COMMAND_NOT_SUPPORTED: u8 = 7;
+pub(crate) const ADDRESS_TYPE_NOT_SUPPORTED: u8 = 8;
+
+/// The `TargetAddr` enum is the destination of a request or a UDP datagram, which clients may give
+/// as a host name for the proxy to resolve.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) enum TargetAddr {
+    Ip(SocketAddr),
+    Domain(String, u16),
+}
+
+impl TargetAddr {
+    /// The function `from_address` reads a `host:port` or `[ipv6]:port` address, keeping host names
+    /// for the proxy to resolve.
+    pub(crate) fn from_address(addr: &str) -> io::Result<Self> {
+        let invalid = || io::Error::new(io::ErrorKind::InvalidInput, format!("{} is not a host:port address", addr));
+        let (host, port) = addr.rsplit_once(':').ok_or_else(invalid)?;
+        let port = port.parse::<u16>().map_err(|_| invalid())?;
+        let host = host.trim_start_matches('[').trim_end_matches(']');
+        match host.parse::<IpAddr>() {
+            Ok(ip) => Ok(TargetAddr::Ip(SocketAddr::new(ip, port))),
+            Err(_) if !host.is_empty() && host.len() <= 255 => Ok(TargetAddr::Domain(host.to_string(), port)),
+            Err(_) => Err(invalid()),
+        }
+    }
+
+    /// The function `read` reads an address type, address and port from `reader`. An unknown
+    /// address type is reported as an `io::ErrorKind::Unsupported` error.
+    pub(crate) async fn read<R: AsyncRead + Unpin>(reader: &mut R) -> io::Result<Self> {
+        let addr = match reader.read_u8().await? {
+            ATYP_IPV4 => {
+                let mut ip = [0; 4];
+                reader.read_exact(&mut ip).await?;
+                TargetAddr::Ip(SocketAddr::new(Ipv4Addr::from(ip).into(), 0))
+            }
+            ATYP_IPV6 => {
+                let mut ip = [0; 16];
+                reader.read_exact(&mut ip).await?;
+                TargetAddr::Ip(SocketAddr::new(Ipv6Addr::from(ip).into(), 0))
+            }
+            ATYP_DOMAIN => {
+                let mut domain = vec![0; reader.read_u8().await? as usize];
+                reader.read_exact(&mut domain).await?;
+                let domain = String::from_utf8(domain).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+                TargetAddr::Domain(domain, 0)
+            }
+            atyp => return Err(io::Error::new(io::ErrorKind::Unsupported, format!("address type {}", atyp))),
+        };
+        Ok(addr.with_port(reader.read_u16().await?))
+    }
+
+    /// The function `parse` reads an address from the start of `data`, as in the header of a UDP
+    /// datagram, and returns it with the number of bytes it took.
+    pub(crate) fn parse(data: &[u8]) -> Option<(Self, usize)> {
+        let (addr, len) = match *data.first()? {
+            ATYP_IPV4 => {
+                let ip: [u8; 4] = data.get(1..5)?.try_into().ok()?;
+                (TargetAddr::Ip(SocketAddr::new(Ipv4Addr::from(ip).into(), 0)), 5)
+            }
+            ATYP_IPV6 => {
+                let ip: [u8; 16] = data.get(1..17)?.try_into().ok()?;
+                (TargetAddr::Ip(SocketAddr::new(Ipv6Addr::from(ip).into(), 0)), 17)
+            }
+            ATYP_DOMAIN => {
+                let end = 2 + *data.get(1)? as usize;
+                let domain = std::str::from_utf8(data.get(2..end)?).ok()?;
+                (TargetAddr::Domain(domain.to_string(), 0), end)
+            }
+            _ => return None,
+        };
+        let port = u16::from_be_bytes(data.get(len..len + 2)?.try_into().ok()?);
+        Some((addr.with_port(port), len + 2))
+    }
+
+    /// The function `encode` appends the address type, address and port to `out`.
+    pub(crate) fn encode(&self, out: &mut Vec<u8>) {
+        match self {
+            TargetAddr::Ip(SocketAddr::V4(addr)) => {
+                out.push(ATYP_IPV4);
+                out.extend_from_slice(&addr.ip().octets());
+            }
+            TargetAddr::Ip(SocketAddr::V6(addr)) => {
+                out.push(ATYP_IPV6);
+                out.extend_from_slice(&addr.ip().octets());
+            }
+            TargetAddr::Domain(domain, _) => {
+                out.push(ATYP_DOMAIN);
+                out.push(domain.len().min(255) as u8);
+                out.extend_from_slice(&domain.as_bytes()[..domain.len().min(255)]);
+            }
+        }
+        out.extend_from_slice(&self.port().to_be_bytes());
+    }
+
+    /// The function `host` returns the host name or IP address, without the port.
+    pub(crate) fn host(&self) -> String {
+        match self {
+            TargetAddr::Ip(addr) => addr.ip().to_string(),
+            TargetAddr::Domain(domain, _) => domain.clone(),
+        }
+    }
+
+    pub(crate) fn port(&self) -> u16 {
+        match self {
+            TargetAddr::Ip(addr) => addr.port(),
+            TargetAddr::Domain(_, port) => *port,
+        }
+    }
+
+    fn with_port(self, port: u16) -> Self {
+        match self {
+            TargetAddr::Ip(addr) => TargetAddr::Ip(SocketAddr::new(addr.ip(), port)),
+            TargetAddr::Domain(domain, _) => TargetAddr::Domain(domain, port),
+        }
+    }
+}
+
+impl fmt::Display for TargetAddr {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TargetAddr::Ip(addr) => write!(f, "{}", addr),
+            TargetAddr::Domain(domain, port) => write!(f, "{}:{}", domain, port),
+        }
+    }
+}
+
+/// The function `reply_error` returns the error a reply code other than `SUCCEEDED` stands for.
+pub(crate) fn reply_error(code: u8) -> io::Error {
+    let (kind, message) = match code {
+        NOT_ALLOWED => (io::ErrorKind::PermissionDenied, "connection not allowed by ruleset"),
+        NETWORK_UNREACHABLE => (io::ErrorKind::NetworkUnreachable, "network unreachable"),
+        HOST_UNREACHABLE => (io::ErrorKind::HostUnreachable, "host unreachable"),
+        CONNECTION_REFUSED => (io::ErrorKind::ConnectionRefused, "connection refused"),
+        TTL_EXPIRED => (io::ErrorKind::TimedOut, "TTL expired"),
+        COMMAND_NOT_SUPPORTED => (io::ErrorKind::Unsupported, "command not supported"),
+        ADDRESS_TYPE_NOT_SUPPORTED => (io::ErrorKind::Unsupported, "address type not supported"),
+        _ => (io::ErrorKind::Other, "general SOCKS server failure"),
+    };
+    io::Error::new(kind, format!("the proxy replied: {}", message))
+}
+
+/// The function `reply_code` returns the reply code describing why a connection failed.
+pub(crate) fn reply_code(err: &io::Error) -> u8 {
+    match err.kind() {
+        io::ErrorKind::ConnectionRefused => CONNECTION_REFUSED,
+        io::ErrorKind::NetworkUnreachable => NETWORK_UNREACHABLE,
+        io::ErrorKind::HostUnreachable => HOST_UNREACHABLE,
+        io::ErrorKind::TimedOut => TTL_EXPIRED,
+        _ => GENERAL_FAILURE,
+    }
+}
@@ -0,0 +1,395 @@
+use super::protocol::{self, TargetAddr};
+use crate::{
+    egress::{EgressPolicy, PolicyViolation},
+    error::AarambhNetError,
+    http::BoxError,
+    tcp::{Accepted, TcpServer},
+};
+use std::{collections::HashMap, error::Error, io, net::SocketAddr, sync::Arc, time::Duration};
+use tokio::{
+    io::{AsyncRead, AsyncReadExt, AsyncWriteExt},
+    net::{lookup_host, TcpStream, UdpSocket},
+    time::timeout,
+};
+
+/// How long a client may take to authenticate and send its request.
+const HANDSHAKE_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// How long the server tries to reach the destination of a `CONNECT` request.
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// The `Socks5Server` struct is a SOCKS5 proxy (RFC 1928) relaying `CONNECT` streams and
+/// `UDP ASSOCIATE` datagrams, meant to stand up a lightweight egress proxy for tests and labs.
+///
+/// # Properties:
+///
+/// * `tcp`: The server accepting the control connections of the clients.
+/// * `users`: The usernames and passwords clients must authenticate with (RFC 1929). When empty,
+///   clients connect without authentication.
+/// * `egress`: The policy the destinations of the clients must satisfy, if any.
+pub struct Socks5Server {
+    tcp: TcpServer,
+    users: HashMap<String, String>,
+    egress: Option<Arc<EgressPolicy>>,
+}
+
+/// What the connection tasks of a running `Socks5Server` share.
+struct Settings {
+    users: HashMap<String, String>,
+    egress: Option<Arc<EgressPolicy>>,
+}
+
+impl Socks5Server {
+    /// The function `bind` creates a SOCKS5 server listening on `addr`, accepting clients without
+    /// authentication until `add_user` is called.
+    ///
+    /// # Returns:
+    ///
+    /// The `bind` function returns a `Result` containing the `Socks5Server`, or an error if the address
+    /// cannot be bound.
+    pub async fn bind(addr: &str) -> Result<Self, Box<dyn Error>> {
+        Ok(Socks5Server { tcp: TcpServer::bind(addr).await?, users: HashMap::new(), egress: None })
+    }
+
+    /// The function `add_user` allows a client to authenticate as `username` with `password`. Once a
+    /// user is added, clients must authenticate with a username and password.
+    pub fn add_user(mut self, username: &str, password: &str) -> Self {
+        self.users.insert(username.to_string(), password.to_string());
+        self
+    }
+
+    /// The function `egress_policy` refuses the destinations `policy` does not allow: `CONNECT`
+    /// requests are answered with "connection not allowed by ruleset" and datagrams are dropped.
+    pub fn egress_policy(mut self, policy: EgressPolicy) -> Self {
+        self.egress = Some(Arc::new(policy));
+        self
+    }
+
+    /// The function `local_addr` returns the address the server listens on.
+    pub fn local_addr(&self) -> io::Result<SocketAddr> {
+        self.tcp.local_addr()
+    }
+
+    /// The function `run` accepts clients and relays their traffic until `shutdown` is called, which
+    /// closes the connections and associations still open.
+    ///
+    /// # Returns:
+    ///
+    /// The `run` function returns `Ok(())` once the server has shut down.
+    pub async fn run(&self) -> Result<(), Box<dyn Error>> {
+        let settings = Arc::new(Settings { users: self.users.clone(), egress: self.egress.clone() });
+        self.tcp
+            .serve(move |socket, peer| {
+                let settings = settings.clone();
+                async move {
+                    if let Err(e) = serve_client(socket, &settings).await {
+                        tracing::debug!("SOCKS5 client {} failed: {}", peer, e);
+                    }
+                }
+            })
+            .await?;
+        Ok(())
+    }
+
+    /// The `shutdown` function notifies the server to stop accepting clients and return from `run`.
+    pub async fn shutdown(&self) {
+        self.tcp.shutdown().await;
+    }
+}
+
+/// The function `serve_client` negotiates with a client and carries out its request.
+async fn serve_client(mut socket: Accepted, settings: &Settings) -> Result<(), BoxError> {
+    let (command, target) = timeout(HANDSHAKE_TIMEOUT, negotiate(&mut socket, &settings.users))
+        .await
+        .map_err(|_| "the client did not complete the handshake in time")??;
+    match command {
+        protocol::CONNECT => connect(socket, target, settings.egress.as_deref()).await,
+        protocol::UDP_ASSOCIATE => associate(socket, target, settings.egress.as_deref()).await,
+        _ => {
+            reply(&mut socket, protocol::COMMAND_NOT_SUPPORTED, None).await?;
+            Err(format!("unsupported command {}", command).into())
+        }
+    }
+}
+
+/// The function `negotiate` agrees on an authentication method with the client, authenticates it
+/// and reads its request.
+///
+/// # Returns:
+///
+/// The `negotiate` function returns the command and destination of the request.
+async fn negotiate(socket: &mut Accepted, users: &HashMap<String, String>) -> Result<(u8, TargetAddr), BoxError> {
+    let [version, count] = read_array(socket).await?;
+    if version != protocol::VERSION {
+        return Err(format!("unsupported SOCKS version {}", version).into());
+    }
+    let mut methods = vec![0; count as usize];
+    socket.read_exact(&mut methods).await?;
+    let method = match users.is_empty() {
+        true => protocol::NO_AUTHENTICATION,
+        false => protocol::USERNAME_PASSWORD,
+    };
+    if !methods.contains(&method) {
+        socket.write_all(&[protocol::VERSION, protocol::NO_ACCEPTABLE_METHOD]).await?;
+        return Err("the client offered no acceptable authentication method".into());
+    }
+    socket.write_all(&[protocol::VERSION, method]).await?;
+    if method == protocol::USERNAME_PASSWORD {
+        authenticate(socket, users).await?;
+    }
+
+    let [version, command, _] = read_array(socket).await?;
+    if version != protocol::VERSION {
+        return Err(format!("unsupported SOCKS version {}", version).into());
+    }
+    match TargetAddr::read(socket).await {
+        Ok(target) => Ok((command, target)),
+        Err(e) if e.kind() == io::ErrorKind::Unsupported => {
+            reply(socket, protocol::ADDRESS_TYPE_NOT_SUPPORTED, None).await?;
+            Err(e.into())
+        }
+        Err(e) => Err(e.into()),
+    }
+}
+
+/// The function `authenticate` checks the username and password sent by the client (RFC 1929).
+async fn authenticate(socket: &mut Accepted, users: &HashMap<String, String>) -> Result<(), BoxError> {
+    let [version, len] = read_array(socket).await?;
+    if version != protocol::AUTH_VERSION {
+        return Err(format!("unsupported authentication version {}", version).into());
+    }
+    let mut username = vec![0; len as usize];
+    socket.read_exact(&mut username).await?;
+    let mut password = vec![0; socket.read_u8().await? as usize];
+    socket.read_exact(&mut password).await?;
+
+    let username = String::from_utf8_lossy(&username);
+    let accepted = users.get(username.as_ref()).is_some_and(|expected| expected.as_bytes() == password);
+    socket.write_all(&[protocol::AUTH_VERSION, if accepted { 0 } else { 1 }]).await?;
+    match accepted {
+        true => Ok(()),
+        false => Err(format!("authentication failed for user {:?}", username).into()),
+    }
+}
+
+/// The function `connect` opens a connection to `target` and relays it with the client.
+async fn connect(mut socket: Accepted, target: TargetAddr, egress: Option<&EgressPolicy>) -> Result<(), BoxError> {
+    let mut stream = match open(&target, egress).await {
+        Ok(stream) => stream,
+        Err((code, e)) => {
+            reply(&mut socket, code, None).await?;
+            return Err(AarambhNetError::new("connect", e).with_target(&target).into());
+        }
+    };
+    reply(&mut socket, protocol::SUCCEEDED, stream.local_addr().ok()).await?;
+    tokio::io::copy_bidirectional(&mut socket, &mut stream).await?;
+    Ok(())
+}
+
+/// The function `open` connects to `target`, returning the reply code to send the client if it
+/// cannot.
+async fn open(target: &TargetAddr, egress: Option<&EgressPolicy>) -> Result<TcpStream, (u8, BoxError)> {
+    let addrs = match resolve(target, egress).await {
+        Ok(addrs) => addrs,
+        Err(e) if e.is::<PolicyViolation>() => return Err((protocol::NOT_ALLOWED, e)),
+        Err(e) => return Err((protocol::HOST_UNREACHABLE, e)),
+    };
+    match timeout(CONNECT_TIMEOUT, TcpStream::connect(&addrs[..])).await {
+        Ok(Ok(stream)) => Ok(stream),
+        Ok(Err(e)) => Err((protocol::reply_code(&e), e.into())),
+        Err(_) => Err((protocol::TTL_EXPIRED, "the connection timed out".into())),
+    }
+}
+
+/// The function `associate` relays datagrams between the client and their destinations until the
+/// client closes its control connection. A client on a Unix domain socket has no address to relay
+/// datagrams for, so it is refused.
+///
+/// # Arguments:
+///
+/// * `socket`: The control connection of the client.
+/// * `requested`: The address the client says it will send from; zeros when it does not know.
+/// * `egress`: The policy the destinations must satisfy, if any.
+async fn associate(
+    mut socket: Accepted,
+    requested: TargetAddr,
+    egress: Option<&EgressPolicy>,
+) -> Result<(), BoxError> {
+    let (peer, local) = match &socket {
+        Accepted::Tcp(tcp) => (tcp.peer_addr()?, tcp.local_addr()?),
+        #[cfg(unix)]
+        Accepted::Unix(_) => {
+            reply(&mut socket, protocol::COMMAND_NOT_SUPPORTED, None).await?;
+            return Err("UDP ASSOCIATE needs a TCP control connection".into());
+        }
+    };
+    let relay = UdpSocket::bind(SocketAddr::new(local.ip(), 0)).await?;
+    let relay_addr = relay.local_addr()?;
+    reply(&mut socket, protocol::SUCCEEDED, Some(relay_addr)).await?;
+
+    // Datagrams from the client come from its own address; anything else is a reply to relay back.
+    let mut client = match requested {
+        TargetAddr::Ip(addr) if !addr.ip().is_unspecified() && addr.port() != 0 => Some(addr),
+        _ => None,
+    };
+    let (mut buf, mut control) = (vec![0; 65535], [0; 1]);
+    loop {
+        tokio::select! {
+            read = socket.read(&mut control) => match read {
+                Ok(0) | Err(_) => return Ok(()),
+                Ok(_) => {}
+            },
+            received = relay.recv_from(&mut buf) => {
+                let (len, from) = received?;
+                if client.is_none() && from.ip() == peer.ip() {
+                    client = Some(from);
+                }
+                if Some(from) == client {
+                    forward(&relay, &buf[..len], egress).await;
+                } else if let Some(client) = client {
+                    let mut datagram = vec![0, 0, 0];
+                    TargetAddr::Ip(from).encode(&mut datagram);
+                    datagram.extend_from_slice(&buf[..len]);
+                    if let Err(e) = relay.send_to(&datagram, client).await {
+                        tracing::debug!("failed to relay a datagram from {} to {}: {}", from, client, e);
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// The function `forward` sends the payload of a client datagram to the destination in its header.
+/// Fragmented datagrams and refused destinations are dropped.
+async fn forward(relay: &UdpSocket, datagram: &[u8], egress: Option<&EgressPolicy>) {
+    // The header starts with two reserved bytes and the fragment number.
+    if datagram.len() < 4 || datagram[2] != 0 {
+        return;
+    }
+    let Some((target, header)) = TargetAddr::parse(&datagram[3..]) else {
+        return;
+    };
+    let payload = &datagram[3 + header..];
+    let family = relay.local_addr().map(|addr| addr.is_ipv4()).unwrap_or(true);
+    match resolve(&target, egress).await {
+        Ok(addrs) => {
+            if let Some(addr) = addrs.iter().find(|addr| addr.is_ipv4() == family) {
+                if let Err(e) = relay.send_to(payload, addr).await {
+                    tracing::debug!("failed to relay a datagram to {}: {}", target, e);
+                }
+            }
+        }
+        Err(e) => tracing::debug!("dropped a datagram to {}: {}", target, e),
+    }
+}
+
+/// The function `resolve` returns the addresses of `target`, checked against `egress` if set.
+async fn resolve(target: &TargetAddr, egress: Option<&EgressPolicy>) -> Result<Vec<SocketAddr>, BoxError> {
+    match (egress, target) {
+        (Some(policy), _) => policy.resolve(&target.host(), target.port()).await,
+        (None, TargetAddr::Ip(addr)) => Ok(vec![*addr]),
+        (None, TargetAddr::Domain(domain, port)) => Ok(lookup_host((domain.as_str(), *port)).await?.collect()),
+    }
+}
+
+/// The function `reply` answers the request of the client with `code` and the address the server
+/// bound for it.
+async fn reply(socket: &mut Accepted, code: u8, bound: Option<SocketAddr>) -> io::Result<()> {
+    let mut message = vec![protocol::VERSION, code, 0];
+    TargetAddr::Ip(bound.unwrap_or_else(|| SocketAddr::from(([0, 0, 0, 0], 0)))).encode(&mut message);
+    socket.write_all(&message).await
+}
+
+async fn read_array<const N: usize>(socket: &mut (impl AsyncRead + Unpin)) -> io::Result<[u8; N]> {
+    let mut bytes = [0; N];
+    socket.read_exact(&mut bytes).await?;
+    Ok(bytes)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    /// Opens a control connection to `proxy` and authenticates with `password`, returning the
+    /// connection and the status of the authentication.
+    async fn login(proxy: SocketAddr, password: &str) -> (TcpStream, u8) {
+        let mut socket = TcpStream::connect(proxy).await.unwrap();
+        socket.write_all(&[5, 1, protocol::USERNAME_PASSWORD]).await.unwrap();
+        assert_eq!(read_array(&mut socket).await.unwrap(), [5, protocol::USERNAME_PASSWORD]);
+        let mut auth = vec![1, 3];
+        auth.extend_from_slice(b"lab");
+        auth.push(password.len() as u8);
+        auth.extend_from_slice(password.as_bytes());
+        socket.write_all(&auth).await.unwrap();
+        let [_, status] = read_array(&mut socket).await.unwrap();
+        (socket, status)
+    }
+
+    /// Sends a request for `command` to `target` and returns the reply code and bound address.
+    async fn request(socket: &mut TcpStream, command: u8, target: SocketAddr) -> (u8, SocketAddr) {
+        let mut message = vec![5, command, 0];
+        TargetAddr::Ip(target).encode(&mut message);
+        socket.write_all(&message).await.unwrap();
+        let [_, code, _] = read_array(socket).await.unwrap();
+        match TargetAddr::read(socket).await.unwrap() {
+            TargetAddr::Ip(bound) => (code, bound),
+            other => panic!("unexpected bound address {}", other),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_connect_and_udp_associate() {
+        let echo = Arc::new(TcpServer::bind("127.0.0.1:0").await.unwrap());
+        let echo_addr = echo.local_addr().unwrap();
+        let echo_task = tokio::spawn({
+            let echo = echo.clone();
+            async move { echo.run_scoped().await.map_err(|e| e.to_string()) }
+        });
+        let udp_echo = UdpSocket::bind("127.0.0.1:0").await.unwrap();
+        let udp_echo_addr = udp_echo.local_addr().unwrap();
+        let udp_task = tokio::spawn(async move {
+            let mut buf = [0; 64];
+            let (len, from) = udp_echo.recv_from(&mut buf).await.unwrap();
+            udp_echo.send_to(&buf[..len], from).await.unwrap();
+        });
+
+        let proxy = Socks5Server::bind("127.0.0.1:0").await.unwrap().add_user("lab", "secret");
+        let proxy = Arc::new(proxy);
+        let proxy_addr = proxy.local_addr().unwrap();
+        let task = tokio::spawn({
+            let proxy = proxy.clone();
+            async move { proxy.run().await.map_err(|e| e.to_string()) }
+        });
+
+        // Clients must authenticate, with the right password.
+        let mut socket = TcpStream::connect(proxy_addr).await.unwrap();
+        socket.write_all(&[5, 1, protocol::NO_AUTHENTICATION]).await.unwrap();
+        assert_eq!(read_array(&mut socket).await.unwrap(), [5, protocol::NO_ACCEPTABLE_METHOD]);
+        assert_eq!(login(proxy_addr, "wrong").await.1, 1);
+
+        let (mut socket, status) = login(proxy_addr, "secret").await;
+        assert_eq!(status, 0);
+        assert_eq!(request(&mut socket, protocol::CONNECT, echo_addr).await.0, protocol::SUCCEEDED);
+        socket.write_all(b"hello").await.unwrap();
+        assert_eq!(&read_array::<5>(&mut socket).await.unwrap(), b"hello");
+
+        let (mut control, _) = login(proxy_addr, "secret").await;
+        let (code, relay) = request(&mut control, protocol::UDP_ASSOCIATE, SocketAddr::from(([0, 0, 0, 0], 0))).await;
+        assert_eq!(code, protocol::SUCCEEDED);
+        let client = UdpSocket::bind("127.0.0.1:0").await.unwrap();
+        let mut datagram = vec![0, 0, 0];
+        TargetAddr::Ip(udp_echo_addr).encode(&mut datagram);
+        datagram.extend_from_slice(b"ping");
+        client.send_to(&datagram, relay).await.unwrap();
+        let mut buf = [0; 64];
+        let len = client.recv(&mut buf).await.unwrap();
+        let (from, header) = TargetAddr::parse(&buf[3..len]).unwrap();
+        assert_eq!((from, &buf[3 + header..len]), (TargetAddr::Ip(udp_echo_addr), &b"ping"[..]));
+        udp_task.await.unwrap();
+
+        proxy.shutdown().await;
+        task.await.unwrap().unwrap();
+        echo.shutdown().await;
+        echo_task.await.unwrap().unwrap();
+    }
+}
@@ -0,0 +1,129 @@
+use crate::egress::Network;
+use std::{
+    error::Error,
+    net::IpAddr,
+    sync::{Arc, RwLock},
+};
+
+/// The `AccessList` struct decides which peer addresses a `TcpServer` accepts, with CIDR blocks
+/// checked as each connection is accepted. Addresses in a denied block are refused; if any block is
+/// allowed, addresses outside every allowed block are refused as well. Refused connections are
+/// closed at once and logged through `tracing`.
+///
+/// Clones share their rules, so a clone kept after handing the list to `TcpServer::access_list`
+/// changes the rules of the running server.
+///
+/// # Properties:
+///
+/// * `rules`: The allowed and denied blocks.
+#[derive(Debug, Clone, Default)]
+pub struct AccessList {
+    rules: Arc<RwLock<Rules>>,
+}
+
+#[derive(Debug, Default)]
+struct Rules {
+    allowed: Vec<Network>,
+    denied: Vec<Network>,
+}
+
+impl AccessList {
+    /// The function `new` creates a list accepting every address.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The function `allow` accepts the addresses of `cidr`, such as `"10.0.0.0/8"` or a single
+    /// address, and refuses those outside every allowed block.
+    ///
+    /// # Returns:
+    ///
+    /// The `allow` function returns a `Result`, with an error if `cidr` is not a valid address or
+    /// CIDR block.
+    pub fn allow(&self, cidr: &str) -> Result<(), Box<dyn Error>> {
+        let network = parse(cidr)?;
+        self.rules.write().unwrap().allowed.push(network);
+        Ok(())
+    }
+
+    /// The function `deny` refuses the addresses of `cidr`, even if an allowed block contains them.
+    ///
+    /// # Returns:
+    ///
+    /// The `deny` function returns a `Result`, with an error if `cidr` is not a valid address or
+    /// CIDR block.
+    pub fn deny(&self, cidr: &str) -> Result<(), Box<dyn Error>> {
+        let network = parse(cidr)?;
+        self.rules.write().unwrap().denied.push(network);
+        Ok(())
+    }
+
+    /// The function `remove` drops the allowed and denied blocks equal to `cidr`.
+    ///
+    /// # Returns:
+    ///
+    /// The `remove` function returns a `Result` containing whether a block was dropped, or an error
+    /// if `cidr` is not a valid address or CIDR block.
+    pub fn remove(&self, cidr: &str) -> Result<bool, Box<dyn Error>> {
+        let network = parse(cidr)?;
+        let mut rules = self.rules.write().unwrap();
+        let count = rules.allowed.len() + rules.denied.len();
+        rules.allowed.retain(|allowed| *allowed != network);
+        rules.denied.retain(|denied| *denied != network);
+        Ok(rules.allowed.len() + rules.denied.len() < count)
+    }
+
+    /// The function `clear` drops every block, accepting every address again.
+    pub fn clear(&self) {
+        *self.rules.write().unwrap() = Rules::default();
+    }
+
+    /// The function `is_allowed` returns whether a connection from `ip` is accepted.
+    pub fn is_allowed(&self, ip: IpAddr) -> bool {
+        let rules = self.rules.read().unwrap();
+        if rules.denied.iter().any(|denied| denied.contains(ip)) {
+            return false;
+        }
+        rules.allowed.is_empty() || rules.allowed.iter().any(|allowed| allowed.contains(ip))
+    }
+}
+
+fn parse(cidr: &str) -> Result<Network, Box<dyn Error>> {
+    Ok(Network::parse(cidr).ok_or_else(|| format!("invalid CIDR block: {}", cidr))?)
+}
+
+#[cfg(test)]
+mod test {
+    use crate::tcp::{AccessList, TcpClient, TcpServer};
+    use std::sync::Arc;
+
+    #[tokio::test]
+    async fn test_access_list() {
+        let access = AccessList::new();
+        access.allow("10.0.0.0/8").unwrap();
+        access.deny("10.1.0.0/16").unwrap();
+        assert!(access.is_allowed("10.2.3.4".parse().unwrap()));
+        assert!(!access.is_allowed("10.1.3.4".parse().unwrap()));
+        assert!(!access.is_allowed("192.168.1.1".parse().unwrap()));
+        assert!(access.allow("10.0.0.0/33").is_err());
+
+        let server = Arc::new(TcpServer::bind("127.0.0.1:0").await.unwrap().access_list(access.clone()));
+        let addr = server.local_addr().unwrap().to_string();
+        let task = tokio::spawn({
+            let server = server.clone();
+            async move { server.run_scoped().await.map_err(|e| e.to_string()) }
+        });
+
+        // Loopback is outside the allowed block until the rules change at runtime.
+        let mut client = TcpClient::connect(&addr).await.unwrap();
+        client.send_message("hello").await.unwrap();
+        assert_eq!(client.receive_response().await.unwrap_or_default(), "");
+        access.allow("127.0.0.0/8").unwrap();
+        let mut client = TcpClient::connect(&addr).await.unwrap();
+        client.send_message("hello").await.unwrap();
+        assert_eq!(client.receive_response().await.unwrap(), "hello");
+
+        server.shutdown().await;
+        task.await.unwrap().unwrap();
+    }
+}
@@ -0,0 +1,160 @@
+use crate::rate_limit::TokenBucket;
+use std::{
+    future::Future,
+    pin::Pin,
+    sync::Arc,
+    task::{ready, Context, Poll},
+    time::Duration,
+};
+use tokio::time::{sleep, Sleep};
+
+/// The `BandwidthLimits` struct caps the bytes per second the connections of a `TcpServer` read and
+/// write, each on its own and all together, so a bulk transfer cannot starve interactive clients.
+/// Reads over the budget stop reading until it is repaid, leaving the client to block on TCP flow
+/// control; writes over the budget, including broadcasts, wait the same way. Each limit allows a
+/// burst of one second of traffic.
+///
+/// # Properties:
+///
+/// * `read_per_connection`: How many bytes per second each connection reads, if limited.
+/// * `write_per_connection`: How many bytes per second each connection writes, if limited.
+/// * `read_total`: The bytes every connection reads together, if limited.
+/// * `write_total`: The bytes every connection writes together, if limited.
+#[derive(Debug, Clone, Default)]
+pub struct BandwidthLimits {
+    read_per_connection: Option<f64>,
+    write_per_connection: Option<f64>,
+    read_total: Option<Arc<TokenBucket>>,
+    write_total: Option<Arc<TokenBucket>>,
+}
+
+impl BandwidthLimits {
+    /// The function `new` creates limits allowing any bandwidth.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The function `read_per_connection` limits how many bytes per second each connection reads.
+    pub fn read_per_connection(mut self, bytes_per_second: u64) -> Self {
+        self.read_per_connection = Some(bytes_per_second as f64);
+        self
+    }
+
+    /// The function `write_per_connection` limits how many bytes per second each connection writes.
+    pub fn write_per_connection(mut self, bytes_per_second: u64) -> Self {
+        self.write_per_connection = Some(bytes_per_second as f64);
+        self
+    }
+
+    /// The function `read_total` limits how many bytes per second the connections read together.
+    pub fn read_total(mut self, bytes_per_second: u64) -> Self {
+        self.read_total = Some(Arc::new(TokenBucket::per_second(bytes_per_second as f64)));
+        self
+    }
+
+    /// The function `write_total` limits how many bytes per second the connections write together.
+    pub fn write_total(mut self, bytes_per_second: u64) -> Self {
+        self.write_total = Some(Arc::new(TokenBucket::per_second(bytes_per_second as f64)));
+        self
+    }
+
+    /// The function `throttle` returns the throttle of a new connection, if anything is limited.
+    pub(crate) fn throttle(&self) -> Option<Throttle> {
+        let buckets = |per_connection: Option<f64>, total: &Option<Arc<TokenBucket>>| {
+            let per_connection = per_connection.map(|rate| Arc::new(TokenBucket::per_second(rate)));
+            per_connection.into_iter().chain(total.clone()).collect::<Vec<_>>()
+        };
+        let read = buckets(self.read_per_connection, &self.read_total);
+        let write = buckets(self.write_per_connection, &self.write_total);
+        (!read.is_empty() || !write.is_empty()).then_some(Throttle { read, write, write_paused: None })
+    }
+}
+
+/// The `Throttle` struct applies the `BandwidthLimits` of a server to one connection.
+///
+/// # Properties:
+///
+/// * `read`: The buckets the bytes read are taken from.
+/// * `write`: The buckets the bytes written are taken from.
+/// * `write_paused`: Fires when the writes may go on, while they are over budget.
+pub(crate) struct Throttle {
+    read: Vec<Arc<TokenBucket>>,
+    write: Vec<Arc<TokenBucket>>,
+    write_paused: Option<Pin<Box<Sleep>>>,
+}
+
+impl Throttle {
+    /// The function `read` counts `bytes` read.
+    ///
+    /// # Returns:
+    ///
+    /// The `read` function returns how long reading must stop, zero if it may go on.
+    pub(crate) fn read(&self, bytes: usize) -> Duration {
+        consume(&self.read, bytes)
+    }
+
+    /// The function `wrote` counts `bytes` written, pausing the writes if they went over budget.
+    pub(crate) fn wrote(&mut self, bytes: usize) {
+        let wait = consume(&self.write, bytes);
+        if !wait.is_zero() {
+            self.write_paused = Some(Box::pin(sleep(wait.min(Duration::from_secs(60)))));
+        }
+    }
+
+    /// The function `poll_write_ready` waits until the writes are back within budget.
+    pub(crate) fn poll_write_ready(&mut self, cx: &mut Context<'_>) -> Poll<()> {
+        if let Some(paused) = &mut self.write_paused {
+            ready!(paused.as_mut().poll(cx));
+            self.write_paused = None;
+        }
+        Poll::Ready(())
+    }
+}
+
+/// The function `consume` takes `bytes` from every bucket, returning the longest wait.
+fn consume(buckets: &[Arc<TokenBucket>], bytes: usize) -> Duration {
+    if bytes == 0 {
+        return Duration::ZERO;
+    }
+    buckets.iter().map(|bucket| bucket.consume(bytes as f64)).max().unwrap_or_default()
+}
+
+#[cfg(test)]
+mod test {
+    use crate::tcp::{BandwidthLimits, TcpServer};
+    use std::{sync::Arc, time::Duration};
+    use tokio::{
+        io::{AsyncReadExt, AsyncWriteExt},
+        net::TcpStream,
+        time::Instant,
+    };
+
+    /// Echoes 30000 bytes through a server limited to 20000 bytes per second, returning how long
+    /// the round trip took.
+    async fn echo_through(limits: BandwidthLimits) -> Duration {
+        let server = Arc::new(TcpServer::bind("127.0.0.1:0").await.unwrap().bandwidth(limits));
+        let addr = server.local_addr().unwrap();
+        let running = server.clone();
+        let task = tokio::spawn(async move { running.run_scoped().await.map_err(|e| e.to_string()) });
+
+        let started = Instant::now();
+        let (mut reader, mut writer) = TcpStream::connect(addr).await.unwrap().into_split();
+        let sent = tokio::spawn(async move { writer.write_all(&[7; 30_000]).await.unwrap() });
+        let mut echoed = vec![0; 30_000];
+        reader.read_exact(&mut echoed).await.unwrap();
+        sent.await.unwrap();
+        let elapsed = started.elapsed();
+
+        server.shutdown().await;
+        task.await.unwrap().unwrap();
+        elapsed
+    }
+
+    #[tokio::test]
+    async fn test_bandwidth_limits() {
+        // A burst of 20000 bytes goes through at once, the next 10000 take half a second.
+        assert!(echo_through(BandwidthLimits::new()).await < Duration::from_millis(300));
+        assert!(echo_through(BandwidthLimits::new().read_per_connection(20_000)).await >= Duration::from_millis(400));
+        assert!(echo_through(BandwidthLimits::new().write_total(20_000)).await >= Duration::from_millis(400));
+    }
+}
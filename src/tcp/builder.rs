@@ -0,0 +1,359 @@
+use super::{
+    access::AccessList,
+    bandwidth::BandwidthLimits,
+    error::TcpServerError,
+    heartbeat::Heartbeat,
+    hooks::Hooks,
+    limits::PeerLimits,
+    options::SocketOptions,
+    outbox::QueuePolicy,
+    server::{StreamHandler, TcpServer},
+    session::Session,
+    stream::ServerStream,
+};
+use crate::tls::TlsConfig;
+#[cfg(unix)]
+use std::path::PathBuf;
+use std::{fmt, future::Future, net::SocketAddr, sync::Arc, time::Duration};
+use tokio_rustls::TlsAcceptor;
+
+/// The `TcpServerBuilder` struct gathers the addresses and settings of a `TcpServer`, returned by
+/// `TcpServer::builder`, and checks they fit together before binding the server with `build`. Each
+/// setting does what the `TcpServer` method of the same name does.
+///
+/// # Properties:
+///
+/// * `addrs`: The TCP addresses to listen on.
+/// * `unix`: The path of the Unix domain socket to listen on, if any.
+/// * `reuseport`: How many `SO_REUSEPORT` listeners to bind on the address, if set.
+/// * `tls`: The acceptor terminating TLS, or the error building it, if set.
+/// * `limits`: The connection and message limits of each peer address, if any.
+/// * `access`: The peer addresses accepted, if restricted.
+/// * `idle_timeout`: How long a connection may go without sending data, if limited.
+/// * `heartbeat`: The heartbeats detecting dead peers, if enabled.
+/// * `bandwidth`: The bytes per second the connections may read and write, if limited.
+/// * `options`: The options set on every accepted socket, if any.
+/// * `buffer_size`: How many bytes a connection reads at once, if set.
+/// * `write_queue`: The capacity and policy of the write queue of each connection, if set.
+/// * `stats_interval`: How often the statistics of the server are logged, if they are.
+/// * `max_connections`: How many connections may be open at once, if limited.
+/// * `handler`: The handler of each connection, if set.
+/// * `hooks`: The callbacks run as connections open, close and fail.
+#[derive(Default)]
+pub struct TcpServerBuilder {
+    addrs: Vec<String>,
+    #[cfg(unix)]
+    unix: Option<PathBuf>,
+    #[cfg(any(target_os = "linux", target_os = "android"))]
+    reuseport: Option<usize>,
+    tls: Option<Result<TlsAcceptor, String>>,
+    limits: Option<PeerLimits>,
+    access: Option<AccessList>,
+    idle_timeout: Option<Duration>,
+    heartbeat: Option<Heartbeat>,
+    bandwidth: Option<BandwidthLimits>,
+    options: Option<SocketOptions>,
+    buffer_size: Option<usize>,
+    write_queue: Option<(usize, QueuePolicy)>,
+    stats_interval: Option<Duration>,
+    max_connections: Option<usize>,
+    handler: Option<StreamHandler>,
+    hooks: Hooks,
+}
+
+impl TcpServerBuilder {
+    /// The function `bind` adds `addr` to the addresses the server listens on. Several addresses
+    /// are bound like `TcpServer::bind_all`.
+    pub fn bind(mut self, addr: &str) -> Self {
+        self.addrs.push(addr.to_string());
+        self
+    }
+
+    /// The function `bind_unix` makes the server listen on the Unix domain socket at `path`, like
+    /// `TcpServer::bind_unix`, instead of TCP addresses.
+    #[cfg(unix)]
+    pub fn bind_unix(mut self, path: impl Into<PathBuf>) -> Self {
+        self.unix = Some(path.into());
+        self
+    }
+
+    /// The function `reuseport` binds `acceptors` listeners with `SO_REUSEPORT` on the address, like
+    /// `TcpServer::bind_reuseport`.
+    #[cfg(any(target_os = "linux", target_os = "android"))]
+    pub fn reuseport(mut self, acceptors: usize) -> Self {
+        self.reuseport = Some(acceptors);
+        self
+    }
+
+    /// The function `tls` encrypts the connections with the certificate of `tls`, like
+    /// `TcpServer::with_tls`. A configuration rustls refuses fails `build`.
+    pub fn tls(mut self, tls: &TlsConfig) -> Self {
+        self.tls = Some(tls.acceptor(Vec::new()).map_err(|e| e.to_string()));
+        self
+    }
+
+    /// The function `max_connections` refuses new connections while `max` are open.
+    pub fn max_connections(mut self, max: usize) -> Self {
+        self.max_connections = Some(max);
+        self
+    }
+
+    /// The function `peer_limits` limits the connections and messages of each peer address.
+    pub fn peer_limits(mut self, limits: PeerLimits) -> Self {
+        self.limits = Some(limits);
+        self
+    }
+
+    /// The function `access_list` restricts the peer addresses the server accepts.
+    pub fn access_list(mut self, access: AccessList) -> Self {
+        self.access = Some(access);
+        self
+    }
+
+    /// The function `idle_timeout` closes connections whose reads waited `timeout` for data.
+    pub fn idle_timeout(mut self, timeout: Duration) -> Self {
+        self.idle_timeout = Some(timeout);
+        self
+    }
+
+    /// The function `heartbeat` pings silent clients and closes those that stop answering.
+    pub fn heartbeat(mut self, heartbeat: Heartbeat) -> Self {
+        self.heartbeat = Some(heartbeat);
+        self
+    }
+
+    /// The function `bandwidth` keeps the bytes read and written by the connections within `limits`.
+    pub fn bandwidth(mut self, limits: BandwidthLimits) -> Self {
+        self.bandwidth = Some(limits);
+        self
+    }
+
+    /// The function `socket_options` sets `options` on every accepted socket.
+    pub fn socket_options(mut self, options: SocketOptions) -> Self {
+        self.options = Some(options);
+        self
+    }
+
+    /// The function `buffer_size` sets how many bytes a connection reads at once.
+    pub fn buffer_size(mut self, size: usize) -> Self {
+        self.buffer_size = Some(size);
+        self
+    }
+
+    /// The function `write_queue` sets the capacity of the write queue of each connection and what
+    /// happens to the messages pushed to a full queue.
+    pub fn write_queue(mut self, capacity: usize, policy: QueuePolicy) -> Self {
+        self.write_queue = Some((capacity, policy));
+        self
+    }
+
+    /// The function `log_stats` logs the statistics of the server every `interval`.
+    pub fn log_stats(mut self, interval: Duration) -> Self {
+        self.stats_interval = Some(interval);
+        self
+    }
+
+    /// The function `handler` makes `run` call `handler` with each connection instead of echoing.
+    pub fn handler<H, F>(mut self, handler: H) -> Self
+    where
+        H: Fn(ServerStream, SocketAddr) -> F + Send + Sync + 'static,
+        F: Future<Output = ()> + Send + 'static,
+    {
+        self.handler = Some(Arc::new(move |stream, peer| Box::pin(handler(stream, peer))));
+        self
+    }
+
+    /// The function `on_connect` calls `hook` with the session of every new connection.
+    pub fn on_connect<F: Fn(&Session) + Send + Sync + 'static>(mut self, hook: F) -> Self {
+        self.hooks.connect = Some(Arc::new(hook));
+        self
+    }
+
+    /// The function `on_disconnect` calls `hook` with the session of every closed connection.
+    pub fn on_disconnect<F: Fn(&Session) + Send + Sync + 'static>(mut self, hook: F) -> Self {
+        self.hooks.disconnect = Some(Arc::new(hook));
+        self
+    }
+
+    /// The function `on_error` calls `hook` with the error whenever a connection or an accept fails.
+    pub fn on_error<F: Fn(&TcpServerError) + Send + Sync + 'static>(mut self, hook: F) -> Self {
+        self.hooks.error = Some(Arc::new(hook));
+        self
+    }
+
+    /// The function `build` checks the settings and binds the server.
+    ///
+    /// # Returns:
+    ///
+    /// The `build` function returns a `Result` containing the `TcpServer`, a `TcpServerError::Config`
+    /// if no address was given, `max_connections` is 0, the TLS configuration was refused, or
+    /// settings contradict each other, or a `TcpServerError::Bind` if an address cannot be bound.
+    pub async fn build(self) -> Result<TcpServer, TcpServerError> {
+        self.validate()?;
+        let mut server = self.listen().await?;
+        if let Some(tls) = self.tls {
+            server = server.with_tls_acceptor(tls.map_err(|e| config_error(format!("TLS: {}", e)))?);
+        }
+        if let Some((capacity, policy)) = self.write_queue {
+            server = server.write_queue(capacity, policy);
+        }
+        if let Some(limits) = self.limits {
+            server = server.peer_limits(limits);
+        }
+        if let Some(access) = self.access {
+            server = server.access_list(access);
+        }
+        if let Some(timeout) = self.idle_timeout {
+            server = server.idle_timeout(timeout);
+        }
+        if let Some(heartbeat) = self.heartbeat {
+            server = server.heartbeat(heartbeat);
+        }
+        if let Some(limits) = self.bandwidth {
+            server = server.bandwidth(limits);
+        }
+        if let Some(options) = self.options {
+            server = server.socket_options(options);
+        }
+        if let Some(size) = self.buffer_size {
+            server = server.buffer_size(size);
+        }
+        if let Some(interval) = self.stats_interval {
+            server = server.log_stats(interval);
+        }
+        if let Some(max) = self.max_connections {
+            server = server.max_connections(max);
+        }
+        if let Some(handler) = self.handler {
+            server = server.handler(move |stream, peer| handler(stream, peer));
+        }
+        Ok(server.with_hooks(self.hooks))
+    }
+
+    /// The function `validate` checks the settings fit together.
+    fn validate(&self) -> Result<(), TcpServerError> {
+        if self.max_connections == Some(0) {
+            return Err(config_error("`max_connections` must be at least 1"));
+        }
+        #[cfg(any(target_os = "linux", target_os = "android"))]
+        if self.reuseport.is_some() && self.addrs.len() != 1 {
+            return Err(config_error("`reuseport` needs exactly one address"));
+        }
+        #[cfg(unix)]
+        if self.unix.is_some() {
+            if !self.addrs.is_empty() {
+                return Err(config_error("a server listens on TCP addresses or on a Unix domain socket, not both"));
+            }
+            let tcp_only = [
+                ("tls", self.tls.is_some()),
+                ("peer_limits", self.limits.is_some()),
+                ("access_list", self.access.is_some()),
+                ("socket_options", self.options.is_some()),
+            ];
+            if let Some((setting, _)) = tcp_only.iter().find(|(_, set)| *set) {
+                return Err(config_error(format!("`{}` does not apply to a Unix domain socket", setting)));
+            }
+            return Ok(());
+        }
+        if self.addrs.is_empty() {
+            return Err(config_error("no address to bind"));
+        }
+        Ok(())
+    }
+
+    /// The function `listen` binds the listeners of the server.
+    async fn listen(&self) -> Result<TcpServer, TcpServerError> {
+        #[cfg(unix)]
+        if let Some(path) = &self.unix {
+            return TcpServer::bind_unix(path);
+        }
+        #[cfg(any(target_os = "linux", target_os = "android"))]
+        if let Some(acceptors) = self.reuseport {
+            return TcpServer::bind_reuseport(&self.addrs[0], acceptors).await;
+        }
+        match self.addrs.as_slice() {
+            [addr] => TcpServer::bind(addr).await,
+            addrs => TcpServer::bind_all(&addrs.iter().map(String::as_str).collect::<Vec<_>>()).await,
+        }
+    }
+}
+
+/// The function `config_error` returns the error of invalid settings.
+fn config_error(message: impl Into<String>) -> TcpServerError {
+    TcpServerError::Config(message.into())
+}
+
+impl fmt::Debug for TcpServerBuilder {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("TcpServerBuilder")
+            .field("addrs", &self.addrs)
+            .field("tls", &self.tls.is_some())
+            .field("max_connections", &self.max_connections)
+            .field("handler", &self.handler.is_some())
+            .finish_non_exhaustive()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::tcp::{AccessList, TcpClient, TcpServer, TcpServerError};
+    use std::{sync::Arc, time::Duration};
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+    #[tokio::test]
+    async fn test_builder() {
+        let server = TcpServer::builder()
+            .bind("127.0.0.1:0")
+            .max_connections(1)
+            .handler(|mut stream, _| async move {
+                let _ = stream.write_all(b"welcome\n").await;
+                let mut buf = [0; 16];
+                while matches!(stream.read(&mut buf).await, Ok(n) if n > 0) {}
+            })
+            .build()
+            .await
+            .unwrap();
+        let server = Arc::new(server);
+        let addr = server.local_addr().unwrap().to_string();
+        let task = tokio::spawn({
+            let server = server.clone();
+            async move { server.run().await.map_err(|e| e.to_string()) }
+        });
+
+        // The second client is refused while the first is connected.
+        let mut first = TcpClient::connect(&addr).await.unwrap();
+        assert_eq!(first.receive_response().await.unwrap(), "welcome\n");
+        let mut second = TcpClient::connect(&addr).await.unwrap();
+        assert_eq!(second.receive_response().await.unwrap_or_default(), "");
+        assert_eq!(server.stats().connections_refused, 1);
+        drop(first);
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        let mut third = TcpClient::connect(&addr).await.unwrap();
+        assert_eq!(third.receive_response().await.unwrap(), "welcome\n");
+
+        server.shutdown().await;
+        task.await.unwrap().unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_builder_validation() {
+        let invalid = [
+            TcpServer::builder(),
+            TcpServer::builder().bind("127.0.0.1:0").max_connections(0),
+            #[cfg(any(target_os = "linux", target_os = "android"))]
+            TcpServer::builder().bind("127.0.0.1:0").bind("127.0.0.1:0").reuseport(2),
+            #[cfg(unix)]
+            TcpServer::builder().bind("127.0.0.1:0").bind_unix("/tmp/aarambh-builder.sock"),
+            #[cfg(unix)]
+            TcpServer::builder().bind_unix("/tmp/aarambh-builder.sock").access_list(AccessList::new()),
+        ];
+        for builder in invalid {
+            let description = format!("{:?}", builder);
+            match builder.build().await {
+                Err(TcpServerError::Config(_)) => {}
+                other => panic!("{} built: {:?}", description, other.map(|_| ())),
+            }
+        }
+        assert!(TcpServer::builder().bind("127.0.0.1:0").bind("127.0.0.1:0").build().await.is_ok());
+    }
+}
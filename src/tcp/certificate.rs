@@ -0,0 +1,257 @@
+use sha2::{Digest, Sha256};
+use std::net::IpAddr;
+
+/// The `PeerCertificate` struct is a certificate a client presented to a `TcpServer` configured with
+/// `TlsConfig::with_client_auth`, verified during the TLS handshake. Handlers find the chain of the
+/// client, its own certificate first, in `Session::peer_certificates`, to authorize it by subject,
+/// alternative name or pinned fingerprint.
+///
+/// # Properties:
+///
+/// * `der`: The certificate, DER encoded.
+/// * `subject`: The distinguished name of the subject, such as `CN=client, O=Acme`.
+/// * `common_name`: The common name of the subject, if any.
+/// * `subject_alt_names`: The DNS names, IP addresses, email addresses and URIs the certificate is
+///   issued for.
+/// * `fingerprint`: The SHA-256 digest of `der`, in lowercase hexadecimal.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PeerCertificate {
+    der: Vec<u8>,
+    subject: String,
+    common_name: Option<String>,
+    subject_alt_names: Vec<String>,
+    fingerprint: String,
+}
+
+impl PeerCertificate {
+    /// The function `from_der` reads the details of the DER encoded certificate `der`, leaving out
+    /// those it cannot decode.
+    pub(crate) fn from_der(der: &[u8]) -> Self {
+        let fingerprint = Sha256::digest(der).iter().map(|b| format!("{:02x}", b)).collect();
+        let mut certificate = PeerCertificate {
+            der: der.to_vec(),
+            subject: String::new(),
+            common_name: None,
+            subject_alt_names: Vec::new(),
+            fingerprint,
+        };
+        let _ = certificate.read_tbs_certificate(der);
+        certificate
+    }
+
+    /// The function `der` returns the certificate, DER encoded, for the checks this struct does not
+    /// cover.
+    pub fn der(&self) -> &[u8] {
+        &self.der
+    }
+
+    /// The function `subject` returns the distinguished name of the subject, such as
+    /// `CN=client, O=Acme`, with its attributes in the order of the certificate.
+    pub fn subject(&self) -> &str {
+        &self.subject
+    }
+
+    /// The function `common_name` returns the common name (CN) of the subject, if any.
+    pub fn common_name(&self) -> Option<&str> {
+        self.common_name.as_deref()
+    }
+
+    /// The function `subject_alt_names` returns the DNS names, IP addresses, email addresses and URIs
+    /// of the subject alternative name extension, in the order of the certificate.
+    pub fn subject_alt_names(&self) -> &[String] {
+        &self.subject_alt_names
+    }
+
+    /// The function `fingerprint` returns the SHA-256 digest of the certificate in lowercase
+    /// hexadecimal, as printed by `openssl x509 -fingerprint -sha256` without the colons.
+    pub fn fingerprint(&self) -> &str {
+        &self.fingerprint
+    }
+
+    /// The function `read_tbs_certificate` reads the subject and the extensions of the certificate,
+    /// returning `None` once it meets something it cannot decode.
+    fn read_tbs_certificate(&mut self, der: &[u8]) -> Option<()> {
+        let certificate = Der(der).expect(SEQUENCE)?;
+        let mut tbs = Der(Der(certificate).expect(SEQUENCE)?);
+        if tbs.peek() == Some(0xa0) {
+            tbs.next()?; // version
+        }
+        for _ in ["serial number", "signature", "issuer", "validity"] {
+            tbs.next()?;
+        }
+        self.read_subject(tbs.expect(SEQUENCE)?)?;
+        tbs.next()?; // subject public key info
+        while let Some((tag, value)) = tbs.next() {
+            if tag == 0xa3 {
+                self.read_extensions(Der(value).expect(SEQUENCE)?)?;
+            }
+        }
+        Some(())
+    }
+
+    /// The function `read_subject` renders the attributes of the subject name.
+    fn read_subject(&mut self, name: &[u8]) -> Option<()> {
+        let mut attributes = Vec::new();
+        let mut names = Der(name);
+        while let Some(set) = names.expect(SET) {
+            let mut set = Der(set);
+            while let Some(attribute) = set.expect(SEQUENCE) {
+                let mut attribute = Der(attribute);
+                let oid = attribute.expect(OID)?;
+                let (tag, value) = attribute.next()?;
+                let value = decode_string(tag, value)?;
+                let label = match oid {
+                    [0x55, 0x04, 0x03] => "CN",
+                    [0x55, 0x04, 0x06] => "C",
+                    [0x55, 0x04, 0x07] => "L",
+                    [0x55, 0x04, 0x08] => "ST",
+                    [0x55, 0x04, 0x0a] => "O",
+                    [0x55, 0x04, 0x0b] => "OU",
+                    [0x2a, 0x86, 0x48, 0x86, 0xf7, 0x0d, 0x01, 0x09, 0x01] => "emailAddress",
+                    _ => {
+                        attributes.push(format!("{}={}", decode_oid(oid), value));
+                        continue;
+                    }
+                };
+                if label == "CN" && self.common_name.is_none() {
+                    self.common_name = Some(value.clone());
+                }
+                attributes.push(format!("{}={}", label, value));
+            }
+        }
+        self.subject = attributes.join(", ");
+        Some(())
+    }
+
+    /// The function `read_extensions` reads the subject alternative names among the extensions.
+    fn read_extensions(&mut self, extensions: &[u8]) -> Option<()> {
+        let mut extensions = Der(extensions);
+        while let Some(extension) = extensions.expect(SEQUENCE) {
+            let mut extension = Der(extension);
+            if extension.expect(OID)? != [0x55, 0x1d, 0x11] {
+                continue;
+            }
+            if extension.peek() == Some(BOOLEAN) {
+                extension.next()?; // critical
+            }
+            let mut names = Der(Der(extension.expect(OCTET_STRING)?).expect(SEQUENCE)?);
+            while let Some((tag, value)) = names.next() {
+                let name = match tag {
+                    0x81 | 0x82 | 0x86 => String::from_utf8_lossy(value).into_owned(),
+                    0x87 => match value.len() {
+                        4 => IpAddr::from(<[u8; 4]>::try_from(value).ok()?).to_string(),
+                        16 => IpAddr::from(<[u8; 16]>::try_from(value).ok()?).to_string(),
+                        _ => continue,
+                    },
+                    _ => continue,
+                };
+                self.subject_alt_names.push(name);
+            }
+        }
+        Some(())
+    }
+}
+
+const BOOLEAN: u8 = 0x01;
+const OCTET_STRING: u8 = 0x04;
+const OID: u8 = 0x06;
+const SEQUENCE: u8 = 0x30;
+const SET: u8 = 0x31;
+
+/// The `Der` struct reads the elements of DER encoded data one after the other.
+struct Der<'a>(&'a [u8]);
+
+impl<'a> Der<'a> {
+    /// The function `peek` returns the tag of the next element, if any.
+    fn peek(&self) -> Option<u8> {
+        self.0.first().copied()
+    }
+
+    /// The function `next` reads the next element, returning its tag and its contents.
+    fn next(&mut self) -> Option<(u8, &'a [u8])> {
+        let (&tag, rest) = self.0.split_first()?;
+        let (&first, rest) = rest.split_first()?;
+        let (length, rest) = match first {
+            0..=0x7f => (first as usize, rest),
+            0x81..=0x84 => {
+                let (length, rest) = rest.split_at_checked((first & 0x7f) as usize)?;
+                (length.iter().fold(0, |length, &b| length << 8 | b as usize), rest)
+            }
+            _ => return None,
+        };
+        let (value, rest) = rest.split_at_checked(length)?;
+        self.0 = rest;
+        Some((tag, value))
+    }
+
+    /// The function `expect` reads the next element if it has `tag`, returning its contents.
+    fn expect(&mut self, tag: u8) -> Option<&'a [u8]> {
+        match self.next()? {
+            (found, value) if found == tag => Some(value),
+            _ => None,
+        }
+    }
+}
+
+/// The function `decode_string` decodes an attribute value of the string type `tag`.
+fn decode_string(tag: u8, value: &[u8]) -> Option<String> {
+    match tag {
+        // UTF8String, PrintableString, TeletexString, IA5String
+        0x0c | 0x13 | 0x14 | 0x16 => Some(String::from_utf8_lossy(value).into_owned()),
+        // BMPString
+        0x1e => {
+            let units: Vec<u16> = value.chunks_exact(2).map(|pair| u16::from_be_bytes([pair[0], pair[1]])).collect();
+            Some(String::from_utf16_lossy(&units))
+        }
+        _ => None,
+    }
+}
+
+/// The function `decode_oid` renders an object identifier in dotted form, such as `2.5.4.3`.
+fn decode_oid(oid: &[u8]) -> String {
+    let mut arcs = Vec::new();
+    let mut arc: u64 = 0;
+    for &b in oid {
+        arc = arc << 7 | (b & 0x7f) as u64;
+        if b & 0x80 == 0 {
+            if arcs.is_empty() {
+                let first = (arc / 40).min(2);
+                arcs.extend([first, arc - first * 40]);
+            } else {
+                arcs.push(arc);
+            }
+            arc = 0;
+        }
+    }
+    arcs.iter().map(u64::to_string).collect::<Vec<_>>().join(".")
+}
+
+#[cfg(test)]
+mod test {
+    use super::PeerCertificate;
+    use rcgen::{CertificateParams, DnType, KeyPair, SanType};
+
+    #[test]
+    fn test_peer_certificate() {
+        let key = KeyPair::generate().unwrap();
+        let mut params = CertificateParams::new(vec!["client.example".to_string()]).unwrap();
+        params.distinguished_name.push(DnType::CommonName, "client");
+        params.distinguished_name.push(DnType::OrganizationName, "Acme");
+        params.distinguished_name.push(DnType::CustomDnType(vec![0, 9, 2342, 19200300, 100, 1, 1]), "uid-7");
+        params.subject_alt_names.push(SanType::IpAddress("10.0.0.1".parse().unwrap()));
+        params.subject_alt_names.push(SanType::Rfc822Name("ops@example.com".try_into().unwrap()));
+        let certificate = params.self_signed(&key).unwrap();
+
+        let peer = PeerCertificate::from_der(certificate.der());
+        assert_eq!(peer.subject(), "CN=client, O=Acme, 0.9.2342.19200300.100.1.1=uid-7");
+        assert_eq!(peer.common_name(), Some("client"));
+        assert_eq!(peer.subject_alt_names(), ["client.example", "10.0.0.1", "ops@example.com"]);
+        assert_eq!(peer.fingerprint().len(), 64);
+        assert_eq!(peer.der(), certificate.der().as_ref());
+
+        // Undecodable certificates keep their fingerprint only.
+        let garbage = PeerCertificate::from_der(b"not a certificate");
+        assert_eq!((garbage.subject(), garbage.common_name()), ("", None));
+        assert!(garbage.subject_alt_names().is_empty());
+    }
+}
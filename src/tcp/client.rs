@@ -1,18 +1,78 @@
-use tokio::net::TcpStream;
-use tokio::io::{AsyncReadExt, AsyncWriteExt};
-use std::error::Error;
+#[cfg(any(target_os = "linux", target_os = "android"))]
+use super::sockopt::set_tcp_option;
+use super::codec::LengthPrefixedCodec;
+use super::framed::FramedClient;
+use super::multiplex::MultiplexedClient;
+use super::heartbeat::{Heartbeat, HeartbeatTimer, KeepaliveTimer};
+use super::json::JsonConnection;
+use super::options::SocketOptions;
+use super::proxy::TcpProxy;
+use super::timeout::{within, TimeoutOperation};
+use crate::address_pool::LocalAddressPool;
+use crate::egress::EgressPolicy;
+use crate::error::AarambhNetError;
+use crate::power::{MessageBatcher, PowerManager, PowerProfile};
+use crate::timeline::{Timeline, TimelineError, TimelineEventKind};
+use crate::tls::TlsClientConfig;
+use rustls::pki_types::ServerName;
+use tokio::net::{lookup_host, TcpSocket, TcpStream};
+#[cfg(unix)]
+use tokio::net::UnixStream;
+use tokio_rustls::client::TlsStream;
+use tokio::sync::Mutex;
+use tokio::io::{AsyncRead, AsyncWrite, AsyncWriteExt, ReadBuf};
+use tokio_util::codec::{Decoder, Encoder, Framed};
+use socket2::{SockRef, TcpKeepalive};
+use futures_util::{stream, Stream};
+use bytes::{Buf, BufMut, Bytes, BytesMut};
+use std::{
+    error::Error,
+    future::poll_fn,
+    io,
+    net::SocketAddr,
+    pin::Pin,
+    sync::Arc,
+    task::{ready, Context, Poll},
+    time::{Duration, Instant},
+};
+#[cfg(unix)]
+use std::path::Path;
 
 /// The `TcpClient` struct represents a TCP client with a `stream` field of type `TcpStream`.
 /// 
 /// # Properties:
 /// 
-/// * `stream`: The `stream` property in the `TcpClient` struct represents the TCP stream that is used
-/// for communication with the server. It allows data to be sent and received over the network
-/// connection.
+/// * `stream`: The `stream` property in the `TcpClient` struct represents the TCP stream, or the Unix
+///   domain socket of `connect_unix`, that is used for communication with the server. It allows data
+///   to be sent and received over the connection.
+/// * `target`: The address the client connected to, reported in errors.
+/// * `transferred`: The number of bytes sent and received so far, reported in errors.
+/// * `timeline`: The `Timeline` recorded for the connection when it was opened with
+///   `connect_verbose`.
+/// * `buffer_size`: How many bytes a single read takes at most.
+/// * `pending`: The bytes read but not consumed yet by `receive_frame`.
+/// * `heartbeat`: Pings the server while a read waits for data, if set with `set_heartbeat`.
+/// * `keepalive`: Sends a payload once the connection is idle, if set with `set_keepalive_payload`.
+/// * `ping`: The rest of the ping or keepalive payload being written.
+/// * `read_timeout`: How long a read waits for data, if limited with `set_read_timeout`.
+/// * `write_timeout`: How long a write may take, if limited with `set_write_timeout`.
 pub struct TcpClient {
-    stream: TcpStream,
+    stream: ClientStream,
+    target: String,
+    transferred: u64,
+    timeline: Option<Timeline>,
+    buffer_size: usize,
+    pending: BytesMut,
+    heartbeat: Option<HeartbeatTimer>,
+    keepalive: Option<KeepaliveTimer>,
+    ping: Option<Bytes>,
+    read_timeout: Option<Duration>,
+    write_timeout: Option<Duration>,
 }
 
+/// How many bytes a read of a `TcpClient` takes at most, unless set with `set_buffer_size`.
+const DEFAULT_BUFFER_SIZE: usize = 1024;
+
 impl TcpClient {
     /// The function `connect` establishes a TCP connection to the specified address asynchronously in
     /// Rust.
@@ -20,8 +80,8 @@ impl TcpClient {
     /// # Arguments:
     /// 
     /// * `addr`: The `addr` parameter in the `connect` function is a reference to a string (`&str`)
-    /// which represents the address to which the TCP client will connect. This address typically
-    /// includes the IP address and port number of the server to establish the connection with.
+    ///   which represents the address to which the TCP client will connect. This address typically
+    ///   includes the IP address and port number of the server to establish the connection with.
     /// 
     /// # Returns:
     /// 
@@ -29,8 +89,387 @@ impl TcpClient {
     /// the connection is successful, or a boxed `Error` trait object if an error occurs during the
     /// connection process.
     pub async fn connect(addr: &str) -> Result<Self, Box<dyn Error>> {
-        let stream = TcpStream::connect(addr).await?;
-        Ok(TcpClient { stream })
+        let started = Instant::now();
+        match TcpStream::connect(addr).await {
+            Ok(stream) => Ok(TcpClient::new(ClientStream::Tcp(stream), addr, None)),
+            Err(e) => Err(Box::new(AarambhNetError::new("connect", e).with_target(addr).with_elapsed(started.elapsed()))),
+        }
+    }
+
+    /// The function `connect_timeout` establishes a TCP connection like `connect`, giving up once it
+    /// took longer than `timeout`, as against a host that drops the connection attempts.
+    ///
+    /// # Returns:
+    ///
+    /// The `connect_timeout` function returns a `Result` containing the connected `TcpClient`, or an
+    /// `AarambhNetError` caused by a `TimeoutError` once `timeout` ran out, or by the connection error.
+    pub async fn connect_timeout(addr: &str, timeout: Duration) -> Result<Self, Box<dyn Error>> {
+        let started = Instant::now();
+        match within(Some(timeout), TimeoutOperation::Connect, TcpStream::connect(addr)).await {
+            Ok(stream) => Ok(TcpClient::new(ClientStream::Tcp(stream), addr, None)),
+            Err(e) => {
+                let e = AarambhNetError::new("connect", e).with_target(addr);
+                Err(Box::new(e.with_elapsed(started.elapsed())))
+            }
+        }
+    }
+
+    /// The function `connect_tls` establishes a TCP connection like `connect` and secures it with
+    /// TLS, for services such as databases or MQTT brokers that only speak TLS. The client then works
+    /// like one connected in the clear.
+    ///
+    /// # Arguments:
+    ///
+    /// * `addr`: The address of the server, for example `"db.example.com:5432"`.
+    /// * `domain`: The name the certificate of the server must be issued for, usually the host of
+    ///   `addr`. An IP address is checked against the IP addresses of the certificate.
+    /// * `tls`: The `TlsClientConfig` with the trusted certificate authorities, and the client
+    ///   certificate for servers requiring one.
+    ///
+    /// # Returns:
+    ///
+    /// The `connect_tls` function returns a `Result` containing the connected `TcpClient`, or an
+    /// error if `domain` is not a valid name, or if the connection or the handshake fails.
+    pub async fn connect_tls(addr: &str, domain: &str, tls: &TlsClientConfig) -> Result<Self, Box<dyn Error>> {
+        let started = Instant::now();
+        let result = async {
+            let domain = ServerName::try_from(domain.to_string())?;
+            let stream = TcpStream::connect(addr).await?;
+            Ok::<_, Box<dyn Error + Send + Sync>>(tls.connector().connect(domain, stream).await?)
+        }
+        .await;
+
+        match result {
+            Ok(stream) => Ok(TcpClient::new(ClientStream::Tls(Box::new(stream)), addr, None)),
+            Err(e) => {
+                let e = AarambhNetError::new("connect", e).with_target(addr);
+                Err(Box::new(e.with_elapsed(started.elapsed())))
+            }
+        }
+    }
+
+    /// The function `connect_unix` connects to the Unix domain socket at `path`, served by a
+    /// `TcpServer` bound with `bind_unix`. The client works like one connected over TCP, except for
+    /// TCP socket options, which fail.
+    ///
+    /// # Returns:
+    ///
+    /// The `connect_unix` function returns a `Result` containing the connected `TcpClient`, or an
+    /// error if nothing listens at `path`.
+    #[cfg(unix)]
+    pub async fn connect_unix(path: impl AsRef<Path>) -> Result<Self, Box<dyn Error>> {
+        let (target, started) = (path.as_ref().display().to_string(), Instant::now());
+        match UnixStream::connect(path).await {
+            Ok(stream) => Ok(TcpClient::new(ClientStream::Unix(stream), &target, None)),
+            Err(e) => {
+                let e = AarambhNetError::new("connect", e).with_target(target);
+                Err(Box::new(e.with_elapsed(started.elapsed())))
+            }
+        }
+    }
+
+    fn new(stream: ClientStream, target: &str, timeline: Option<Timeline>) -> Self {
+        TcpClient {
+            stream,
+            target: target.to_string(),
+            transferred: 0,
+            timeline,
+            buffer_size: DEFAULT_BUFFER_SIZE,
+            pending: BytesMut::new(),
+            heartbeat: None,
+            keepalive: None,
+            ping: None,
+            read_timeout: None,
+            write_timeout: None,
+        }
+    }
+
+    /// The function `connect_verbose` establishes a TCP connection like `connect`, recording a
+    /// `Timeline` of the DNS lookup, the connect, every write, the first byte received and the close.
+    ///
+    /// # Returns:
+    ///
+    /// The `connect_verbose` function returns a `Result` containing the connected `TcpClient`, or an
+    /// `AarambhNetError` caused by a `TimelineError` holding the events recorded up to the failure.
+    pub async fn connect_verbose(addr: &str) -> Result<Self, Box<dyn Error>> {
+        let (timeline, started) = (Timeline::new(), Instant::now());
+        let result = async {
+            let targets: Vec<SocketAddr> = lookup_host(addr).await?.collect();
+            let list = targets.iter().map(|a| a.ip().to_string()).collect::<Vec<_>>().join(", ");
+            timeline.record(TimelineEventKind::Resolve, format!("{} -> {}", addr, list));
+            let stream = TcpStream::connect(&targets[..]).await?;
+            timeline.record(TimelineEventKind::Connect, format!("connected to {}", stream.peer_addr()?));
+            Ok::<_, io::Error>(stream)
+        }
+        .await;
+
+        match result {
+            Ok(stream) => Ok(TcpClient::new(ClientStream::Tcp(stream), addr, Some(timeline))),
+            Err(e) => {
+                let e = TimelineError::new(timeline, Box::new(e));
+                Err(Box::new(AarambhNetError::new("connect", e).with_target(addr).with_elapsed(started.elapsed())))
+            }
+        }
+    }
+
+    /// The function `timeline` returns the `Timeline` of a connection opened with `connect_verbose`.
+    /// The returned clone keeps receiving events, including `Close` when the client is dropped.
+    pub fn timeline(&self) -> Option<Timeline> {
+        self.timeline.clone()
+    }
+
+    /// The function `fail` wraps an I/O error of `operation` in an `AarambhNetError`, attaching the
+    /// timeline in verbose mode.
+    fn fail(&self, operation: &str, e: impl Into<Box<dyn Error + Send + Sync>>, started: Instant) -> Box<dyn Error> {
+        let err = match &self.timeline {
+            Some(timeline) => AarambhNetError::new(operation, TimelineError::new(timeline.clone(), e.into())),
+            None => AarambhNetError::new(operation, e),
+        };
+        Box::new(err.with_target(&self.target).with_bytes_transferred(self.transferred).with_elapsed(started.elapsed()))
+    }
+
+    /// The function `connect_from_pool` establishes a TCP connection whose source address is the
+    /// next address taken from a `LocalAddressPool`.
+    ///
+    /// # Arguments:
+    ///
+    /// * `addr`: The address of the server to connect to, for example `"example.com:443"`.
+    /// * `pool`: The `LocalAddressPool` to take the source address from. The outcome of the
+    ///   connection attempt is recorded against the chosen address.
+    ///
+    /// # Returns:
+    ///
+    /// The `connect_from_pool` function returns a `Result` containing the connected `TcpClient`, or an
+    /// error if the target cannot be resolved to an address of the same family as the source address,
+    /// or if binding or connecting fails.
+    pub async fn connect_from_pool(addr: &str, pool: &LocalAddressPool) -> Result<Self, Box<dyn Error>> {
+        let (local_ip, started) = (pool.next(), Instant::now());
+        let fail = |e: Box<dyn Error + Send + Sync>| -> Box<dyn Error> {
+            Box::new(AarambhNetError::new("connect", e).with_target(addr).with_elapsed(started.elapsed()))
+        };
+        let target = lookup_host(addr)
+            .await
+            .map_err(|e| fail(e.into()))?
+            .find(|a| a.is_ipv4() == local_ip.is_ipv4())
+            .ok_or_else(|| fail(format!("{} has no address matching source {}", addr, local_ip).into()))?;
+
+        let result = async {
+            let socket = if target.is_ipv4() { TcpSocket::new_v4()? } else { TcpSocket::new_v6()? };
+            socket.bind(SocketAddr::new(local_ip, 0))?;
+            socket.connect(target).await
+        }
+        .await;
+
+        match result {
+            Ok(stream) => {
+                pool.record_success(local_ip);
+                Ok(TcpClient::new(ClientStream::Tcp(stream), addr, None))
+            }
+            Err(e) => {
+                pool.record_failure(local_ip);
+                Err(fail(e.into()))
+            }
+        }
+    }
+
+    /// The function `connect_via_proxy` establishes a TCP connection to `target` through `proxy`, a
+    /// SOCKS5 or HTTP `CONNECT` proxy, for networks that only reach the outside through one. The
+    /// client then works like one connected directly.
+    ///
+    /// # Arguments:
+    ///
+    /// * `proxy`: The `TcpProxy` to tunnel through, with its credentials if it requires some.
+    /// * `target`: The address to reach, for example `"example.com:443"`. Host names are resolved by
+    ///   the proxy.
+    ///
+    /// # Returns:
+    ///
+    /// The `connect_via_proxy` function returns a `Result` containing the connected `TcpClient`, or
+    /// an error if the proxy cannot be reached, refuses the credentials, or fails to reach `target`.
+    pub async fn connect_via_proxy(proxy: &TcpProxy, target: &str) -> Result<Self, Box<dyn Error>> {
+        let started = Instant::now();
+        match proxy.open(target).await {
+            Ok(stream) => Ok(TcpClient::new(ClientStream::Tcp(stream), target, None)),
+            Err(e) => {
+                let e = AarambhNetError::new(format!("connect via {}", proxy.addr()), e).with_target(target);
+                Err(Box::new(e.with_elapsed(started.elapsed())))
+            }
+        }
+    }
+
+    /// The function `connect_from` establishes a TCP connection from the local address `local`, for
+    /// multi-homed hosts whose traffic must leave through the network interface holding it.
+    ///
+    /// # Arguments:
+    ///
+    /// * `addr`: The address of the server to connect to, for example `"example.com:443"`.
+    /// * `local`: The source address to bind, with port `0` to let the system pick the port.
+    ///
+    /// # Returns:
+    ///
+    /// The `connect_from` function returns a `Result` containing the connected `TcpClient`, or an
+    /// error if the target has no address of the family of `local`, or if binding or connecting
+    /// fails.
+    pub async fn connect_from(addr: &str, local: SocketAddr) -> Result<Self, Box<dyn Error>> {
+        let started = Instant::now();
+        let result = async {
+            let target = lookup_host(addr)
+                .await?
+                .find(|a| a.is_ipv4() == local.is_ipv4())
+                .ok_or_else(|| format!("{} has no address matching source {}", addr, local.ip()))?;
+            let socket = if target.is_ipv4() { TcpSocket::new_v4()? } else { TcpSocket::new_v6()? };
+            socket.bind(local)?;
+            Ok::<_, Box<dyn Error + Send + Sync>>(socket.connect(target).await?)
+        }
+        .await;
+
+        match result {
+            Ok(stream) => Ok(TcpClient::new(ClientStream::Tcp(stream), addr, None)),
+            Err(e) => {
+                let e = AarambhNetError::new("connect", e).with_target(addr);
+                Err(Box::new(e.with_elapsed(started.elapsed())))
+            }
+        }
+    }
+
+    /// The function `connect_via_device` establishes a TCP connection through the network interface
+    /// named `device`, such as `eth1`, whatever the routing table says (`SO_BINDTODEVICE`). Binding
+    /// to a device usually requires the `CAP_NET_RAW` capability.
+    ///
+    /// # Returns:
+    ///
+    /// The `connect_via_device` function returns a `Result` containing the connected `TcpClient`, or
+    /// an error if the address cannot be resolved, the device does not exist or may not be used, or
+    /// the connection fails.
+    #[cfg(any(target_os = "linux", target_os = "android"))]
+    pub async fn connect_via_device(addr: &str, device: &str) -> Result<Self, Box<dyn Error>> {
+        let started = Instant::now();
+        let result = async {
+            let target = lookup_host(addr)
+                .await?
+                .next()
+                .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "no address found"))?;
+            let socket = if target.is_ipv4() { TcpSocket::new_v4()? } else { TcpSocket::new_v6()? };
+            SockRef::from(&socket).bind_device(Some(device.as_bytes()))?;
+            socket.connect(target).await
+        }
+        .await;
+
+        match result {
+            Ok(stream) => Ok(TcpClient::new(ClientStream::Tcp(stream), addr, None)),
+            Err(e) => {
+                let e = AarambhNetError::new(format!("connect via {}", device), e).with_target(addr);
+                Err(Box::new(e.with_elapsed(started.elapsed())))
+            }
+        }
+    }
+
+    /// The function `connect_fast_open` establishes a TCP connection like `connect` with TCP Fast Open
+    /// enabled: once the server has issued a cookie, the first `send_message` after a reconnect
+    /// travels in the SYN, saving a round trip on short request/response exchanges. Without a cookie,
+    /// or against a server without Fast Open, the connection behaves like a normal one.
+    ///
+    /// # Returns:
+    ///
+    /// The `connect_fast_open` function returns a `Result` containing the connected `TcpClient`, or an
+    /// error if the address cannot be resolved or the connection fails.
+    #[cfg(any(target_os = "linux", target_os = "android"))]
+    pub async fn connect_fast_open(addr: &str) -> Result<Self, Box<dyn Error>> {
+        let started = Instant::now();
+        let result = async {
+            let target = lookup_host(addr)
+                .await?
+                .next()
+                .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "no address found"))?;
+            let socket = if target.is_ipv4() { TcpSocket::new_v4()? } else { TcpSocket::new_v6()? };
+            set_tcp_option(&socket, libc::TCP_FASTOPEN_CONNECT, 1)?;
+            socket.connect(target).await
+        }
+        .await;
+
+        match result {
+            Ok(stream) => Ok(TcpClient::new(ClientStream::Tcp(stream), addr, None)),
+            Err(e) => Err(Box::new(AarambhNetError::new("connect", e).with_target(addr).with_elapsed(started.elapsed()))),
+        }
+    }
+
+    /// The function `connect_with_policy` establishes a TCP connection like `connect` after checking
+    /// the destination against `policy`. The host is resolved once and the connection is made to the
+    /// checked addresses, so a second lookup cannot return a different answer.
+    ///
+    /// # Arguments:
+    ///
+    /// * `addr`: The address of the server, for example `"example.com:443"` or `"[::1]:8080"`.
+    /// * `policy`: The `EgressPolicy` the destination must satisfy.
+    ///
+    /// # Returns:
+    ///
+    /// The `connect_with_policy` function returns a `Result` containing the connected `TcpClient`, or
+    /// an `AarambhNetError` caused by a `PolicyViolation` if the destination is refused, or by the
+    /// lookup or connection error.
+    pub async fn connect_with_policy(addr: &str, policy: &EgressPolicy) -> Result<Self, Box<dyn Error>> {
+        let started = Instant::now();
+        let result = async {
+            let (host, port) = addr.rsplit_once(':').ok_or("the address has no port")?;
+            let port = port.parse::<u16>()?;
+            let targets = policy.resolve(host.trim_start_matches('[').trim_end_matches(']'), port).await?;
+            Ok::<_, Box<dyn Error + Send + Sync>>(TcpStream::connect(&targets[..]).await?)
+        }
+        .await;
+
+        match result {
+            Ok(stream) => Ok(TcpClient::new(ClientStream::Tcp(stream), addr, None)),
+            Err(e) => Err(Box::new(AarambhNetError::new("connect", e).with_target(addr).with_elapsed(started.elapsed()))),
+        }
+    }
+
+    /// The function `apply_power_profile` sets the TCP keepalive of the connection to the
+    /// `keepalive_interval` of `profile`.
+    pub fn apply_power_profile(&self, profile: &PowerProfile) -> Result<(), Box<dyn Error>> {
+        let keepalive = TcpKeepalive::new().with_time(profile.keepalive_interval);
+        self.stream
+            .tcp()
+            .and_then(|stream| SockRef::from(stream).set_tcp_keepalive(&keepalive))
+            .map_err(|e| AarambhNetError::new("set keepalive", e).with_target(&self.target))?;
+        Ok(())
+    }
+
+    /// The function `apply_socket_options` sets `options`, such as keepalive, `TCP_NODELAY` and
+    /// `SO_LINGER`, on the connection.
+    pub fn apply_socket_options(&self, options: &SocketOptions) -> Result<(), Box<dyn Error>> {
+        self.stream
+            .tcp()
+            .and_then(|stream| options.apply(stream))
+            .map_err(|e| AarambhNetError::new("set socket options", e).with_target(&self.target))?;
+        Ok(())
+    }
+
+    /// The function `into_batcher` hands the connection to a `MessageBatcher` that writes the
+    /// messages of each batch with a single write, following the profile of `manager`. The keepalive
+    /// is applied at once and again before a batch whenever the profile changed.
+    ///
+    /// Write errors are reported as `tracing` warnings; the messages of a failed batch are lost.
+    pub fn into_batcher(self, manager: Arc<PowerManager>) -> Result<MessageBatcher, Box<dyn Error>> {
+        let applied = manager.profile();
+        self.apply_power_profile(&applied)?;
+        let state = Arc::new(Mutex::new((self, applied)));
+        let watcher = manager.clone();
+        Ok(MessageBatcher::spawn(manager, move |batch| {
+            let (state, profile) = (state.clone(), watcher.profile());
+            async move {
+                let mut state = state.lock().await;
+                let (client, applied) = &mut *state;
+                if profile != *applied {
+                    if let Err(e) = client.apply_power_profile(&profile) {
+                        tracing::warn!(target = %client.target, error = %e, "failed to update keepalive");
+                    }
+                    *applied = profile;
+                }
+                if let Err(e) = client.write(&batch.concat()).await {
+                    tracing::warn!(target = %client.target, messages = batch.len(), error = %e, "failed to send batch");
+                }
+            }
+        }))
     }
 
     /// The function `send_message` sends a message over a stream in Rust asynchronously.
@@ -38,7 +477,7 @@ impl TcpClient {
     /// # Arguments:
     /// 
     /// * `message`: The `message` parameter in the `send_message` function is a reference to a string
-    /// (`&str`) that represents the message to be sent.
+    ///   (`&str`) that represents the message to be sent.
     /// 
     /// # Returns:
     /// 
@@ -46,19 +485,610 @@ impl TcpClient {
     /// the message is successfully sent, or an error wrapped in a `Box<dyn Error>` if an error occurs
     /// during the process.
     pub async fn send_message(&mut self, message: &str) -> Result<(), Box<dyn Error>> {
-        self.stream.write_all(message.as_bytes()).await?;
+        self.write(message.as_bytes()).await
+    }
+
+    /// The function `send_bytes` sends `data` as is, for binary protocols that `send_message` cannot
+    /// carry without requiring UTF-8.
+    ///
+    /// # Returns:
+    ///
+    /// The `send_bytes` function returns a `Result` that is an error if writing fails.
+    pub async fn send_bytes(&mut self, data: &[u8]) -> Result<(), Box<dyn Error>> {
+        self.write(data).await
+    }
+
+    async fn write(&mut self, data: &[u8]) -> Result<(), Box<dyn Error>> {
+        let (started, write_timeout) = (Instant::now(), self.write_timeout);
+        let written = async {
+            poll_fn(|cx| self.poll_write_ping(cx)).await?;
+            self.stream.write_all(data).await
+        };
+        within(write_timeout, TimeoutOperation::Write, written).await.map_err(|e| self.fail("send", e, started))?;
+        self.transferred += data.len() as u64;
+        self.active();
+        if let Some(timeline) = &self.timeline {
+            timeline.record(TimelineEventKind::Write, format!("{} bytes", data.len()));
+        }
         Ok(())
     }
 
+    /// The function `into_framed` hands the connection to `codec`, returning a `Stream` of decoded
+    /// messages and a `Sink` of messages to encode, for protocols whose messages must keep their
+    /// boundaries across reads. The client keeps counting the bytes transferred.
+    pub fn into_framed<C: Decoder>(self, codec: C) -> Framed<TcpClient, C> {
+        Framed::new(self, codec)
+    }
+
+    /// The function `framed` hands the connection to `codec`, a `LineCodec` or a `LengthPrefixedCodec`
+    /// matching the server, returning a `FramedClient` that sends and receives whole messages with
+    /// `send_frame` and `next_frame`.
+    pub fn framed<C: Decoder>(self, codec: C) -> FramedClient<C>
+    where
+        C::Error: Into<Box<dyn Error + Send + Sync>>,
+    {
+        let target = self.target.clone();
+        FramedClient::new(self, codec, target)
+    }
+
+    /// The function `into_multiplexed` hands the connection to a `MultiplexedClient`, which tags the
+    /// frames of `codec` with request identifiers so many calls can wait for their responses at once.
+    /// The connection is driven by a task of its own.
+    pub fn into_multiplexed(self, codec: LengthPrefixedCodec) -> MultiplexedClient {
+        let target = self.target.clone();
+        MultiplexedClient::new(self, codec, target)
+    }
+
+    /// The function `into_json` hands the connection to a `JsonConnection` exchanging JSON messages
+    /// framed by `framing`, a `LineCodec` or a `LengthPrefixedCodec` matching the server.
+    pub fn into_json<F>(self, framing: F) -> JsonConnection<TcpClient, F>
+    where
+        F: Decoder<Error = io::Error> + for<'a> Encoder<&'a [u8], Error = io::Error>,
+        F::Item: AsRef<[u8]>,
+    {
+        let target = self.target.clone();
+        JsonConnection::new(Framed::new(self, framing), target)
+    }
+
     /// The function `receive_response` reads data from a stream and returns it as a string.
+    /// A single read returns at most the buffer size set with `set_buffer_size`, 1024 bytes by
+    /// default, so longer responses arrive over several calls; `receive_frame` reads a complete
+    /// message instead.
     /// 
     /// # Returns:
     /// 
     /// The `receive_response` function returns a `Result` containing a `String` or a `Box<dyn Error>`.
     pub async fn receive_response(&mut self) -> Result<String, Box<dyn Error>> {
-        let mut buffer = vec![0; 1024];
-        let n = self.stream.read(&mut buffer).await?;
-        let response = String::from_utf8_lossy(&buffer[..n]).to_string();
+        if self.pending.is_empty() {
+            self.fill_pending().await?;
+        }
+        let response = String::from_utf8_lossy(&self.pending.split()).to_string();
         Ok(response)
     }
-}
\ No newline at end of file
+
+    /// The function `receive_bytes` reads data from the server like `receive_response`, returning
+    /// the bytes received without converting them to a string.
+    ///
+    /// # Returns:
+    ///
+    /// The `receive_bytes` function returns a `Result` containing the bytes, empty once the server
+    /// closed the connection.
+    pub async fn receive_bytes(&mut self) -> Result<Bytes, Box<dyn Error>> {
+        if self.pending.is_empty() {
+            self.fill_pending().await?;
+        }
+        Ok(self.pending.split().freeze())
+    }
+
+    /// The function `receive_into` reads data from the server into `buf`, keeping what does not fit
+    /// for the next call, so a binary protocol can read into a buffer of its own.
+    ///
+    /// # Returns:
+    ///
+    /// The `receive_into` function returns a `Result` containing the number of bytes written to
+    /// `buf`, `0` once the server closed the connection or if `buf` is empty.
+    pub async fn receive_into(&mut self, buf: &mut [u8]) -> Result<usize, Box<dyn Error>> {
+        if buf.is_empty() {
+            return Ok(0);
+        }
+        if self.pending.is_empty() {
+            self.fill_pending().await?;
+        }
+        let n = self.pending.len().min(buf.len());
+        buf[..n].copy_from_slice(&self.pending[..n]);
+        self.pending.advance(n);
+        Ok(n)
+    }
+
+    /// The function `set_buffer_size` sets how many bytes a single read of `receive_response` or
+    /// `receive_frame` takes at most. Frames larger than the buffer are still received whole by
+    /// `receive_frame`, over several reads.
+    pub fn set_buffer_size(&mut self, size: usize) {
+        self.buffer_size = size.max(1);
+    }
+
+    /// The function `set_heartbeat` makes the client ping the server once a read has been waiting
+    /// for data for the interval of `heartbeat`, failing the read with a `TimedOut` error once the
+    /// server stopped answering. Pings are only sent while a read waits, between the writes of the
+    /// client; the pongs of the server are returned by reads like any other data.
+    pub fn set_heartbeat(&mut self, heartbeat: Heartbeat) {
+        self.heartbeat = Some(HeartbeatTimer::new(heartbeat));
+    }
+
+    /// The function `set_keepalive_payload` makes the client send `payload` once the connection has
+    /// gone `idle` without data either way, and again after every further `idle`, keeping the
+    /// mappings of NATs and firewalls alive on long-lived connections. Unlike a `Heartbeat`, no
+    /// answer is expected. The payload is sent as is while a read waits, so it must be framed for
+    /// the protocol and be something the server ignores. An empty payload turns it off.
+    pub fn set_keepalive_payload(&mut self, payload: impl Into<Bytes>, idle: Duration) {
+        let payload = payload.into();
+        self.keepalive = (!payload.is_empty()).then(|| KeepaliveTimer::new(payload, idle));
+    }
+
+    /// The function `set_read_timeout` limits how long a read of `receive_response` or
+    /// `receive_frame` waits for data, failing it with an `AarambhNetError` caused by a
+    /// `TimeoutError` once `timeout` ran out. `None` waits forever, the default.
+    pub fn set_read_timeout(&mut self, timeout: Option<Duration>) {
+        self.read_timeout = timeout;
+    }
+
+    /// The function `set_write_timeout` limits how long `send_message` may take, which stalls once
+    /// the server stops reading and the socket buffers are full. A message cut off by the timeout
+    /// may have been sent in part. `None` waits forever, the default.
+    pub fn set_write_timeout(&mut self, timeout: Option<Duration>) {
+        self.write_timeout = timeout;
+    }
+
+    /// The function `receive_frame` reads until `codec` decodes a complete message, so a message is
+    /// received whole however it was split on the wire. Bytes read past the message are kept for the
+    /// next call.
+    ///
+    /// # Returns:
+    ///
+    /// The `receive_frame` function returns a `Result` containing the message, `None` once the server
+    /// closed the connection, or an error if reading or decoding fails.
+    pub async fn receive_frame<C: Decoder>(&mut self, codec: &mut C) -> Result<Option<C::Item>, Box<dyn Error>>
+    where
+        C::Error: Into<Box<dyn Error + Send + Sync>>,
+    {
+        let started = Instant::now();
+        loop {
+            let decoded = match codec.decode(&mut self.pending) {
+                Ok(Some(item)) => return Ok(Some(item)),
+                Ok(None) => None,
+                Err(e) => Some(e),
+            };
+            if let Some(e) = decoded {
+                let e = io::Error::new(io::ErrorKind::InvalidData, e);
+                return Err(self.fail("decode", e, started));
+            }
+            if self.fill_pending().await? == 0 {
+                return match codec.decode_eof(&mut self.pending) {
+                    Ok(item) => Ok(item),
+                    Err(e) => Err(self.fail("decode", io::Error::new(io::ErrorKind::InvalidData, e), started)),
+                };
+            }
+        }
+    }
+
+    /// The function `messages` returns the messages decoded by `codec` as a `Stream`, for
+    /// `while let Some(message) = messages.next().await` loops and `select!` arms. The stream ends
+    /// once the server closed the connection, or after yielding a read or decode error. The client
+    /// can be used again once the stream is dropped, with the bytes read past the last message kept.
+    pub fn messages<'a, C>(&'a mut self, codec: C) -> impl Stream<Item = Result<C::Item, Box<dyn Error>>> + 'a
+    where
+        C: Decoder + 'a,
+        C::Error: Into<Box<dyn Error + Send + Sync>>,
+    {
+        stream::unfold(Some((self, codec)), |state| async move {
+            let (client, mut codec) = state?;
+            match client.receive_frame(&mut codec).await {
+                Ok(Some(message)) => Some((Ok(message), Some((client, codec)))),
+                Ok(None) => None,
+                Err(e) => Some((Err(e), None)),
+            }
+        })
+    }
+
+    /// The function `poll_write_ping` writes the rest of the ping due, if any.
+    fn poll_write_ping(&mut self, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        while let Some(ping) = &mut self.ping {
+            let written = ready!(Pin::new(&mut self.stream).poll_write(cx, ping))?;
+            if written == 0 {
+                return Poll::Ready(Err(io::ErrorKind::WriteZero.into()));
+            }
+            ping.advance(written);
+            self.transferred += written as u64;
+            if ping.is_empty() {
+                self.ping = None;
+            }
+            self.active();
+        }
+        Poll::Ready(Ok(()))
+    }
+
+    /// The function `active` records data sent or received, restarting the idle time of the
+    /// keepalive.
+    fn active(&mut self) {
+        if let Some(keepalive) = &mut self.keepalive {
+            keepalive.active();
+        }
+    }
+
+    /// The function `fill_pending` reads up to the buffer size into `pending`.
+    ///
+    /// # Returns:
+    ///
+    /// The `fill_pending` function returns the number of bytes read, `0` once the server closed the
+    /// connection.
+    async fn fill_pending(&mut self) -> Result<usize, Box<dyn Error>> {
+        let (started, read_timeout) = (Instant::now(), self.read_timeout);
+        self.pending.reserve(self.buffer_size);
+        let read = poll_fn(|cx| {
+            // The pending bytes are set aside so the read goes through `poll_read` to the socket.
+            let mut pending = std::mem::take(&mut self.pending);
+            let limit = self.buffer_size;
+            let read = tokio_util::io::poll_read_buf(Pin::new(&mut *self), cx, &mut (&mut pending).limit(limit));
+            self.pending = pending;
+            read
+        });
+        let n = within(read_timeout, TimeoutOperation::Read, read).await.map_err(|e| self.fail("receive", e, started))?;
+        if let Some(timeline) = &self.timeline {
+            if n > 0 && timeline.elapsed_at(TimelineEventKind::FirstByte).is_none() {
+                timeline.record(TimelineEventKind::FirstByte, format!("{} bytes", n));
+            }
+        }
+        Ok(n)
+    }
+}
+
+impl AsyncRead for TcpClient {
+    fn poll_read(mut self: Pin<&mut Self>, cx: &mut Context<'_>, buf: &mut ReadBuf<'_>) -> Poll<io::Result<()>> {
+        if !self.pending.is_empty() {
+            let n = self.pending.len().min(buf.remaining());
+            buf.put_slice(&self.pending[..n]);
+            self.pending.advance(n);
+            return Poll::Ready(Ok(()));
+        }
+        let this = self.get_mut();
+        if let Poll::Ready(Err(e)) = this.poll_write_ping(cx) {
+            return Poll::Ready(Err(e));
+        }
+        let filled = buf.filled().len();
+        let read = Pin::new(&mut this.stream).poll_read(cx, buf);
+        this.transferred += (buf.filled().len() - filled) as u64;
+        if read.is_ready() {
+            this.active();
+        } else if let (Some(keepalive), None) = (&mut this.keepalive, &this.ping) {
+            if let Poll::Ready(payload) = keepalive.poll_due(cx) {
+                this.ping = Some(payload);
+                cx.waker().wake_by_ref();
+                return Poll::Pending;
+            }
+        }
+        let Some(heartbeat) = &mut this.heartbeat else { return read };
+        if read.is_ready() {
+            heartbeat.received();
+            return read;
+        }
+        match ready!(heartbeat.poll_ping(cx)) {
+            Ok(ping) => {
+                this.ping = Some(ping);
+                cx.waker().wake_by_ref();
+                Poll::Pending
+            }
+            Err(e) => Poll::Ready(Err(e)),
+        }
+    }
+}
+
+impl AsyncWrite for TcpClient {
+    fn poll_write(mut self: Pin<&mut Self>, cx: &mut Context<'_>, buf: &[u8]) -> Poll<io::Result<usize>> {
+        let poll = Pin::new(&mut self.stream).poll_write(cx, buf);
+        if let Poll::Ready(Ok(n)) = poll {
+            self.transferred += n as u64;
+            self.active();
+        }
+        poll
+    }
+
+    fn poll_flush(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Pin::new(&mut self.stream).poll_flush(cx)
+    }
+
+    fn poll_shutdown(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Pin::new(&mut self.stream).poll_shutdown(cx)
+    }
+}
+
+/// The `ClientStream` enum is the socket a `TcpClient` is connected with.
+enum ClientStream {
+    Tcp(TcpStream),
+    Tls(Box<TlsStream<TcpStream>>),
+    #[cfg(unix)]
+    Unix(UnixStream),
+}
+
+impl ClientStream {
+    /// The function `tcp` returns the TCP socket, or an `Unsupported` error for a Unix domain socket.
+    fn tcp(&self) -> io::Result<&TcpStream> {
+        match self {
+            ClientStream::Tcp(stream) => Ok(stream),
+            ClientStream::Tls(stream) => Ok(stream.get_ref().0),
+            #[cfg(unix)]
+            ClientStream::Unix(_) => Err(io::Error::new(io::ErrorKind::Unsupported, "not a TCP connection")),
+        }
+    }
+}
+
+impl AsyncRead for ClientStream {
+    fn poll_read(self: Pin<&mut Self>, cx: &mut Context<'_>, buf: &mut ReadBuf<'_>) -> Poll<io::Result<()>> {
+        match self.get_mut() {
+            ClientStream::Tcp(stream) => Pin::new(stream).poll_read(cx, buf),
+            ClientStream::Tls(stream) => Pin::new(stream).poll_read(cx, buf),
+            #[cfg(unix)]
+            ClientStream::Unix(stream) => Pin::new(stream).poll_read(cx, buf),
+        }
+    }
+}
+
+impl AsyncWrite for ClientStream {
+    fn poll_write(self: Pin<&mut Self>, cx: &mut Context<'_>, buf: &[u8]) -> Poll<io::Result<usize>> {
+        match self.get_mut() {
+            ClientStream::Tcp(stream) => Pin::new(stream).poll_write(cx, buf),
+            ClientStream::Tls(stream) => Pin::new(stream).poll_write(cx, buf),
+            #[cfg(unix)]
+            ClientStream::Unix(stream) => Pin::new(stream).poll_write(cx, buf),
+        }
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        match self.get_mut() {
+            ClientStream::Tcp(stream) => Pin::new(stream).poll_flush(cx),
+            ClientStream::Tls(stream) => Pin::new(stream).poll_flush(cx),
+            #[cfg(unix)]
+            ClientStream::Unix(stream) => Pin::new(stream).poll_flush(cx),
+        }
+    }
+
+    fn poll_shutdown(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        match self.get_mut() {
+            ClientStream::Tcp(stream) => Pin::new(stream).poll_shutdown(cx),
+            ClientStream::Tls(stream) => Pin::new(stream).poll_shutdown(cx),
+            #[cfg(unix)]
+            ClientStream::Unix(stream) => Pin::new(stream).poll_shutdown(cx),
+        }
+    }
+}
+
+impl Drop for TcpClient {
+    fn drop(&mut self) {
+        if let Some(timeline) = &self.timeline {
+            timeline.record(TimelineEventKind::Close, "connection closed");
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::tcp::{LengthPrefixedCodec, LineCodec, TcpServer};
+    use crate::tls::TlsConfig;
+    use futures_util::{SinkExt, StreamExt};
+    use rcgen::{BasicConstraints, CertificateParams, IsCa, KeyPair};
+    use tokio::{io::AsyncReadExt, net::TcpListener};
+
+    #[tokio::test]
+    async fn test_receive_frame_accumulates_reads() {
+        let server = TcpServer::bind("127.0.0.1:0").await.unwrap().buffer_size(64);
+        let server = Arc::new(server);
+        let addr = server.local_addr().unwrap().to_string();
+        let task = tokio::spawn({
+            let server = server.clone();
+            async move {
+                let served = server.run_framed(LengthPrefixedCodec::new(), |mut frames, _| async move {
+                    while let Some(Ok(frame)) = frames.next().await {
+                        let _ = frames.send(frame.freeze()).await;
+                    }
+                });
+                served.await.map_err(|e| e.to_string())
+            }
+        });
+
+        // Two large frames in one write come back whole, however the reads split them.
+        let (big, small) = (vec![7; 5000], b"tail".to_vec());
+        let mut wire = bytes::BytesMut::new();
+        let mut codec = LengthPrefixedCodec::new();
+        codec.encode(big.as_slice(), &mut wire).unwrap();
+        codec.encode(small.as_slice(), &mut wire).unwrap();
+        let mut client = TcpClient::connect(&addr).await.unwrap();
+        client.set_buffer_size(100);
+        client.write_all(&wire).await.unwrap();
+        assert_eq!(client.receive_frame(&mut codec).await.unwrap().unwrap(), big.as_slice());
+        assert_eq!(client.receive_frame(&mut codec).await.unwrap().unwrap(), small.as_slice());
+        drop(client);
+
+        server.shutdown().await;
+        task.await.unwrap().unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_raw_bytes() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let server = tokio::spawn(async move {
+            let (mut socket, _) = listener.accept().await.unwrap();
+            let mut buf = [0; 6];
+            socket.read_exact(&mut buf).await.unwrap();
+            socket.write_all(&buf).await.unwrap();
+            socket.read_exact(&mut buf).await.unwrap();
+            socket.write_all(&buf).await.unwrap();
+        });
+
+        // Bytes that are not UTF-8 come back unchanged.
+        let binary = [0xff, 0xfe, 0x00, 0x80, 0xc3, 0x28];
+        let mut client = TcpClient::connect(&addr.to_string()).await.unwrap();
+        client.send_bytes(&binary).await.unwrap();
+        let mut received = Vec::new();
+        while received.len() < binary.len() {
+            received.extend_from_slice(&client.receive_bytes().await.unwrap());
+        }
+        assert_eq!(received, binary);
+
+        client.send_bytes(&binary).await.unwrap();
+        let mut buf = [0; 4];
+        let mut received = Vec::new();
+        while received.len() < binary.len() {
+            let n = client.receive_into(&mut buf).await.unwrap();
+            received.extend_from_slice(&buf[..n]);
+        }
+        assert_eq!(received, binary);
+        server.await.unwrap();
+        assert_eq!(client.receive_into(&mut buf).await.unwrap(), 0);
+        assert!(client.receive_bytes().await.unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_connect_tls() {
+        let ca_key = KeyPair::generate().unwrap();
+        let mut ca = CertificateParams::new(Vec::new()).unwrap();
+        ca.is_ca = IsCa::Ca(BasicConstraints::Unconstrained);
+        let ca = ca.self_signed(&ca_key).unwrap();
+        let issue = |name: &str| {
+            let key = KeyPair::generate().unwrap();
+            let cert = CertificateParams::new(vec![name.to_string()]).unwrap().signed_by(&key, &ca, &ca_key).unwrap();
+            (cert.pem(), key.serialize_pem())
+        };
+        let ((server_cert, server_key), (client_cert, client_key)) = (issue("localhost"), issue("client"));
+
+        let tls = TlsConfig::from_pem(server_cert.as_bytes(), server_key.as_bytes()).unwrap();
+        let tls = tls.with_client_auth(ca.pem().as_bytes()).unwrap();
+        let server = Arc::new(TcpServer::bind("127.0.0.1:0").await.unwrap().with_tls(&tls).unwrap());
+        let addr = server.local_addr().unwrap().to_string();
+        let task = tokio::spawn({
+            let server = server.clone();
+            async move {
+                let served = server.run_framed(LineCodec::new(), |mut lines, _| async move {
+                    let client = lines.get_ref().session().peer_certificates()[0].subject_alt_names().join(",");
+                    while let Some(Ok(line)) = lines.next().await {
+                        let _ = lines.send(format!("{} from {}", line, client)).await;
+                    }
+                });
+                served.await.map_err(|e| e.to_string())
+            }
+        });
+
+        let roots = TlsClientConfig::from_roots_pem(ca.pem().as_bytes()).unwrap();
+        let config = roots.clone().with_client_cert(client_cert.as_bytes(), client_key.as_bytes()).unwrap();
+        let mut client = TcpClient::connect_tls(&addr, "localhost", &config).await.unwrap().framed(LineCodec::new());
+        client.send_frame("hello").await.unwrap();
+        assert_eq!(client.next_frame().await.unwrap().unwrap(), "hello from client");
+        assert!(client.get_ref().apply_socket_options(&SocketOptions::new().nodelay(true)).is_ok());
+
+        // The certificate of the server is not issued for another name.
+        assert!(TcpClient::connect_tls(&addr, "example.com", &config).await.is_err());
+        assert!(TlsClientConfig::from_roots_pem(b"").is_err());
+
+        server.shutdown().await;
+        task.await.unwrap().unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_messages() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let server = tokio::spawn(async move {
+            let (mut socket, _) = listener.accept().await.unwrap();
+            socket.write_all(b"one\ntwo\nthree\nfour\n").await.unwrap();
+            let mut buf = [0; 5];
+            socket.read_exact(&mut buf).await.unwrap();
+            socket.write_all(b"five\n").await.unwrap();
+        });
+
+        let mut client = TcpClient::connect(&addr.to_string()).await.unwrap();
+        let mut received = Vec::new();
+        {
+            let mut messages = std::pin::pin!(client.messages(LineCodec::new()));
+            while let Some(message) = messages.next().await {
+                received.push(message.unwrap());
+                if received.len() == 2 {
+                    break;
+                }
+            }
+        }
+        // The lines read past the stream are kept for the client.
+        client.send_message("more\n").await.unwrap();
+        let mut messages = std::pin::pin!(client.messages(LineCodec::new()));
+        loop {
+            tokio::select! {
+                message = messages.next() => match message {
+                    Some(message) => received.push(message.unwrap()),
+                    None => break,
+                },
+                _ = tokio::time::sleep(std::time::Duration::from_secs(5)) => panic!("no message"),
+            }
+        }
+        assert_eq!(received, ["one", "two", "three", "four", "five"]);
+        server.await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_connect_from_local_address() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap().to_string();
+        let reserved = std::net::TcpListener::bind("127.0.0.2:0").unwrap().local_addr().unwrap();
+        let accepted = tokio::spawn(async move { listener.accept().await.unwrap().1 });
+        let _client = TcpClient::connect_from(&addr, reserved).await.unwrap();
+        assert_eq!(accepted.await.unwrap(), reserved);
+
+        let v6: SocketAddr = "[::1]:0".parse().unwrap();
+        assert!(TcpClient::connect_from(&addr, v6).await.is_err());
+
+        #[cfg(any(target_os = "linux", target_os = "android"))]
+        {
+            let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+            let addr = listener.local_addr().unwrap().to_string();
+            let accepted = tokio::spawn(async move { listener.accept().await.map(|_| ()) });
+            match TcpClient::connect_via_device(&addr, "lo").await {
+                Ok(_) => accepted.await.unwrap().unwrap(),
+                // Without CAP_NET_RAW the device cannot be bound.
+                Err(e) => {
+                    let e = e.downcast_ref::<AarambhNetError>().unwrap().cause::<io::Error>().unwrap().kind();
+                    assert_eq!(e, io::ErrorKind::PermissionDenied);
+                }
+            }
+            assert!(TcpClient::connect_via_device(&addr, "no-such-device0").await.is_err());
+        }
+    }
+
+    #[tokio::test]
+    async fn test_connect_verbose_records_timeline() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let server = tokio::spawn(async move {
+            let (mut socket, _) = listener.accept().await.unwrap();
+            let mut buf = [0; 4];
+            socket.read_exact(&mut buf).await.unwrap();
+            socket.write_all(&buf).await.unwrap();
+        });
+
+        let mut client = TcpClient::connect_verbose(&addr.to_string()).await.unwrap();
+        client.send_message("ping").await.unwrap();
+        assert_eq!(client.receive_response().await.unwrap(), "ping");
+        let timeline = client.timeline().unwrap();
+        drop(client);
+        server.await.unwrap();
+
+        let kinds: Vec<_> = timeline.events().into_iter().map(|e| e.kind).collect();
+        assert_eq!(
+            kinds,
+            [
+                TimelineEventKind::Resolve,
+                TimelineEventKind::Connect,
+                TimelineEventKind::Write,
+                TimelineEventKind::FirstByte,
+                TimelineEventKind::Close
+            ]
+        );
+    }
+}
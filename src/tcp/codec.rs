@@ -0,0 +1,438 @@
+use super::{heartbeat::HeartbeatFrames, limits::MessageLimiter, stats::Traffic};
+use bytes::{Buf, BufMut, Bytes, BytesMut};
+use std::{io, sync::Arc};
+use tokio_util::codec::{Decoder, Encoder};
+
+/// The longest line a `LineCodec` accepts by default.
+const DEFAULT_MAX_LINE_LENGTH: usize = 64 * 1024;
+
+/// The largest frame a `LengthPrefixedCodec` accepts by default.
+const DEFAULT_MAX_FRAME_SIZE: usize = 8 * 1024 * 1024;
+
+/// The size of the length prefix of a `LengthPrefixedCodec` frame.
+const LENGTH_PREFIX_SIZE: usize = 4;
+
+/// The `LineCodec` struct frames a TCP stream into newline-delimited UTF-8 lines, so messages keep
+/// their boundaries however the bytes are split across reads. Decoded lines have their `\n` or
+/// `\r\n` terminator removed; encoded lines get a `\n` appended. Use it with
+/// `TcpServer::run_framed` and `TcpClient::into_framed`.
+///
+/// # Properties:
+///
+/// * `max_length`: The longest line accepted, without its terminator. Longer lines fail decoding
+///   with `InvalidData`, so a peer never sending a newline cannot grow the buffer without bound.
+/// * `next_index`: Where the search for the next newline resumes, so buffered bytes are only
+///   scanned once.
+#[derive(Debug, Clone)]
+pub struct LineCodec {
+    max_length: usize,
+    next_index: usize,
+}
+
+impl LineCodec {
+    /// The function `new` creates a codec accepting lines of up to 64 KiB.
+    pub fn new() -> Self {
+        Self::with_max_length(DEFAULT_MAX_LINE_LENGTH)
+    }
+
+    /// The function `with_max_length` creates a codec accepting lines of up to `max_length` bytes.
+    pub fn with_max_length(max_length: usize) -> Self {
+        LineCodec { max_length, next_index: 0 }
+    }
+
+    /// The function `max_length` returns the longest line the codec accepts.
+    pub fn max_length(&self) -> usize {
+        self.max_length
+    }
+
+    /// The function `line` turns the bytes of a line into a `String` without its terminator.
+    fn line(mut bytes: BytesMut) -> io::Result<String> {
+        if bytes.last() == Some(&b'\n') {
+            bytes.truncate(bytes.len() - 1);
+        }
+        if bytes.last() == Some(&b'\r') {
+            bytes.truncate(bytes.len() - 1);
+        }
+        String::from_utf8(bytes.to_vec()).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
+    }
+}
+
+impl Default for LineCodec {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Decoder for LineCodec {
+    type Item = String;
+    type Error = io::Error;
+
+    fn decode(&mut self, src: &mut BytesMut) -> io::Result<Option<String>> {
+        match src[self.next_index..].iter().position(|byte| *byte == b'\n') {
+            Some(offset) => {
+                let end = self.next_index + offset + 1;
+                self.next_index = 0;
+                // The terminator does not count towards the limit.
+                let mut length = end - 1;
+                if length > 0 && src[length - 1] == b'\r' {
+                    length -= 1;
+                }
+                if length > self.max_length {
+                    return Err(too_long(self.max_length));
+                }
+                Self::line(src.split_to(end)).map(Some)
+            }
+            // One byte more than the limit may be the `\r` of a `\r\n`.
+            None if src.len() > self.max_length + 1 => Err(too_long(self.max_length)),
+            None => {
+                self.next_index = src.len();
+                Ok(None)
+            }
+        }
+    }
+
+    fn decode_eof(&mut self, src: &mut BytesMut) -> io::Result<Option<String>> {
+        match self.decode(src)? {
+            Some(line) => Ok(Some(line)),
+            // A last line without a terminator is still a line.
+            None if !src.is_empty() => {
+                self.next_index = 0;
+                Self::line(src.split()).map(Some)
+            }
+            None => Ok(None),
+        }
+    }
+}
+
+impl Encoder<&str> for LineCodec {
+    type Error = io::Error;
+
+    fn encode(&mut self, line: &str, dst: &mut BytesMut) -> io::Result<()> {
+        if line.contains('\n') {
+            return Err(io::Error::new(io::ErrorKind::InvalidInput, "a line cannot contain a newline"));
+        }
+        if line.len() > self.max_length {
+            return Err(too_long(self.max_length));
+        }
+        dst.reserve(line.len() + 1);
+        dst.put_slice(line.as_bytes());
+        dst.put_u8(b'\n');
+        Ok(())
+    }
+}
+
+impl Encoder<String> for LineCodec {
+    type Error = io::Error;
+
+    fn encode(&mut self, line: String, dst: &mut BytesMut) -> io::Result<()> {
+        self.encode(line.as_str(), dst)
+    }
+}
+
+impl Encoder<&[u8]> for LineCodec {
+    type Error = io::Error;
+
+    fn encode(&mut self, line: &[u8], dst: &mut BytesMut) -> io::Result<()> {
+        let line = std::str::from_utf8(line).map_err(|e| io::Error::new(io::ErrorKind::InvalidInput, e))?;
+        self.encode(line, dst)
+    }
+}
+
+fn too_long(max_length: usize) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, format!("line longer than {} bytes", max_length))
+}
+
+/// The `Endianness` enum selects the byte order of the length prefix of a `LengthPrefixedCodec`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Endianness {
+    /// Most significant byte first, the network byte order.
+    Big,
+    /// Least significant byte first.
+    Little,
+}
+
+/// The `LengthPrefixedCodec` struct frames a TCP stream into binary messages, each preceded by its
+/// length as a 4-byte unsigned integer, so binary protocols exchange discrete messages however the
+/// bytes are split across reads. Decoded frames are the payloads without their prefix.
+///
+/// # Properties:
+///
+/// * `max_frame_size`: The largest payload accepted or sent. Larger frames fail with
+///   `InvalidData` before their payload is buffered.
+/// * `endianness`: The byte order of the length prefix, big-endian by default.
+#[derive(Debug, Clone, Copy)]
+pub struct LengthPrefixedCodec {
+    max_frame_size: usize,
+    endianness: Endianness,
+}
+
+impl LengthPrefixedCodec {
+    /// The function `new` creates a codec with big-endian prefixes accepting frames of up to 8 MiB.
+    pub fn new() -> Self {
+        LengthPrefixedCodec { max_frame_size: DEFAULT_MAX_FRAME_SIZE, endianness: Endianness::Big }
+    }
+
+    /// The function `max_frame_size` sets the largest payload accepted or sent.
+    pub fn max_frame_size(mut self, max_frame_size: usize) -> Self {
+        self.max_frame_size = max_frame_size.min(u32::MAX as usize);
+        self
+    }
+
+    /// The function `endianness` sets the byte order of the length prefix.
+    pub fn endianness(mut self, endianness: Endianness) -> Self {
+        self.endianness = endianness;
+        self
+    }
+
+    fn too_large(&self, size: usize) -> io::Error {
+        let message = format!("frame of {} bytes exceeds the limit of {} bytes", size, self.max_frame_size);
+        io::Error::new(io::ErrorKind::InvalidData, message)
+    }
+}
+
+impl Default for LengthPrefixedCodec {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Decoder for LengthPrefixedCodec {
+    type Item = BytesMut;
+    type Error = io::Error;
+
+    fn decode(&mut self, src: &mut BytesMut) -> io::Result<Option<BytesMut>> {
+        let Some(prefix) = src.get(..LENGTH_PREFIX_SIZE) else { return Ok(None) };
+        let prefix = [prefix[0], prefix[1], prefix[2], prefix[3]];
+        let size = match self.endianness {
+            Endianness::Big => u32::from_be_bytes(prefix),
+            Endianness::Little => u32::from_le_bytes(prefix),
+        } as usize;
+        if size > self.max_frame_size {
+            return Err(self.too_large(size));
+        }
+        if src.len() < LENGTH_PREFIX_SIZE + size {
+            src.reserve(LENGTH_PREFIX_SIZE + size - src.len());
+            return Ok(None);
+        }
+        src.advance(LENGTH_PREFIX_SIZE);
+        Ok(Some(src.split_to(size)))
+    }
+}
+
+impl Encoder<&[u8]> for LengthPrefixedCodec {
+    type Error = io::Error;
+
+    fn encode(&mut self, frame: &[u8], dst: &mut BytesMut) -> io::Result<()> {
+        if frame.len() > self.max_frame_size {
+            return Err(self.too_large(frame.len()));
+        }
+        dst.reserve(LENGTH_PREFIX_SIZE + frame.len());
+        match self.endianness {
+            Endianness::Big => dst.put_u32(frame.len() as u32),
+            Endianness::Little => dst.put_u32_le(frame.len() as u32),
+        }
+        dst.put_slice(frame);
+        Ok(())
+    }
+}
+
+impl Encoder<Vec<u8>> for LengthPrefixedCodec {
+    type Error = io::Error;
+
+    fn encode(&mut self, frame: Vec<u8>, dst: &mut BytesMut) -> io::Result<()> {
+        self.encode(frame.as_slice(), dst)
+    }
+}
+
+impl Encoder<Bytes> for LengthPrefixedCodec {
+    type Error = io::Error;
+
+    fn encode(&mut self, frame: Bytes, dst: &mut BytesMut) -> io::Result<()> {
+        self.encode(frame.as_ref(), dst)
+    }
+}
+
+/// The `ServerCodec` struct wraps the codec of the connections of `TcpServer::run_framed` and
+/// `TcpServer::run_json`, applying the message limits of the server to the decoded messages and
+/// counting them. It encodes and decodes like the codec it wraps.
+///
+/// # Properties:
+///
+/// * `inner`: The codec of the protocol.
+/// * `limiter`: The message limit of the peer, if the server has one.
+/// * `traffic`: The counters of the connection.
+/// * `heartbeat`: Recognizes the heartbeat frames of the peer, kept from the handler, if the server
+///   has heartbeats.
+#[derive(Debug)]
+pub struct ServerCodec<C> {
+    inner: C,
+    limiter: Option<MessageLimiter>,
+    traffic: Arc<Traffic>,
+    heartbeat: Option<HeartbeatFrames>,
+}
+
+impl<C> ServerCodec<C> {
+    pub(crate) fn new(inner: C, limiter: Option<MessageLimiter>, traffic: Arc<Traffic>) -> Self {
+        ServerCodec { inner, limiter, traffic, heartbeat: None }
+    }
+
+    /// The function `with_heartbeat` keeps the heartbeat frames recognized by `heartbeat` from the
+    /// handler.
+    pub(crate) fn with_heartbeat(mut self, heartbeat: Option<HeartbeatFrames>) -> Self {
+        self.heartbeat = heartbeat;
+        self
+    }
+
+    /// The function `get_ref` returns the codec of the protocol.
+    pub fn get_ref(&self) -> &C {
+        &self.inner
+    }
+
+    /// The function `get_mut` returns the codec of the protocol.
+    pub fn get_mut(&mut self) -> &mut C {
+        &mut self.inner
+    }
+
+    /// The function `admit` applies the message limit to a decoded message, counting it if it is
+    /// delivered.
+    fn admit(&self) -> io::Result<bool> {
+        let admitted = self.limiter.as_ref().map_or(Ok(true), MessageLimiter::admit)?;
+        if admitted {
+            self.traffic.message_received();
+        }
+        Ok(admitted)
+    }
+}
+
+impl<C: Decoder> ServerCodec<C> {
+    /// The function `decode_with` decodes messages with `decode` until one is neither a heartbeat
+    /// frame nor discarded by the message limit.
+    fn decode_with<D>(&mut self, src: &mut BytesMut, mut decode: D) -> Result<Option<C::Item>, C::Error>
+    where
+        D: FnMut(&mut C, &mut BytesMut) -> Result<Option<C::Item>, C::Error>,
+    {
+        loop {
+            let (available, frame) = (src.len(), self.heartbeat.as_ref().and_then(|frames| frames.frame_at(src)));
+            let Some(item) = decode(&mut self.inner, src)? else { return Ok(None) };
+            let consumed = available - src.len();
+            if let (Some(frames), Some(frame)) = (&self.heartbeat, frame.filter(|frame| frame.len() == consumed)) {
+                frames.consume(&frame);
+                continue;
+            }
+            if self.admit()? {
+                return Ok(Some(item));
+            }
+        }
+    }
+}
+
+impl<C: Decoder> Decoder for ServerCodec<C> {
+    type Item = C::Item;
+    type Error = C::Error;
+
+    fn decode(&mut self, src: &mut BytesMut) -> Result<Option<C::Item>, C::Error> {
+        self.decode_with(src, C::decode)
+    }
+
+    fn decode_eof(&mut self, src: &mut BytesMut) -> Result<Option<C::Item>, C::Error> {
+        self.decode_with(src, C::decode_eof)
+    }
+}
+
+impl<I, C: Encoder<I>> Encoder<I> for ServerCodec<C> {
+    type Error = C::Error;
+
+    fn encode(&mut self, item: I, dst: &mut BytesMut) -> Result<(), C::Error> {
+        self.inner.encode(item, dst)?;
+        self.traffic.message_sent();
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::tcp::{TcpClient, TcpServer};
+    use futures_util::{SinkExt, StreamExt};
+    use std::sync::Arc;
+    use tokio::io::AsyncWriteExt;
+
+    #[tokio::test]
+    async fn test_line_framing() {
+        let server = Arc::new(TcpServer::bind("127.0.0.1:0").await.unwrap());
+        let addr = server.local_addr().unwrap().to_string();
+        let task = tokio::spawn({
+            let server = server.clone();
+            async move {
+                let served = server.run_framed(LineCodec::new(), |mut lines, _| async move {
+                    while let Some(Ok(line)) = lines.next().await {
+                        if lines.send(line.to_uppercase()).await.is_err() {
+                            return;
+                        }
+                    }
+                });
+                served.await.map_err(|e| e.to_string())
+            }
+        });
+
+        // Lines split across writes, or sharing one, arrive whole and one at a time.
+        let mut client = TcpClient::connect(&addr).await.unwrap();
+        client.send_message("hel").await.unwrap();
+        tokio::time::sleep(std::time::Duration::from_millis(20)).await;
+        client.send_message("lo\r\nwor").await.unwrap();
+        client.send_message("ld\nbye\n").await.unwrap();
+        let mut lines = client.into_framed(LineCodec::with_max_length(8));
+        assert_eq!(lines.next().await.unwrap().unwrap(), "HELLO");
+        assert_eq!(lines.next().await.unwrap().unwrap(), "WORLD");
+        assert_eq!(lines.next().await.unwrap().unwrap(), "BYE");
+        assert!(lines.send("far too long").await.is_err());
+
+        let mut buffer = BytesMut::from("no newline in sight");
+        assert_eq!(lines.codec_mut().decode(&mut buffer).unwrap_err().kind(), io::ErrorKind::InvalidData);
+        let mut buffer = BytesMut::from("last");
+        assert_eq!(LineCodec::new().decode_eof(&mut buffer).unwrap().as_deref(), Some("last"));
+
+        server.shutdown().await;
+        task.await.unwrap().unwrap();
+    }
+    #[tokio::test]
+    async fn test_length_prefixed_framing() {
+        let server = Arc::new(TcpServer::bind("127.0.0.1:0").await.unwrap());
+        let addr = server.local_addr().unwrap().to_string();
+        let codec = LengthPrefixedCodec::new().endianness(Endianness::Little).max_frame_size(16);
+        let task = tokio::spawn({
+            let server = server.clone();
+            async move {
+                let served = server.run_framed(codec, |mut frames, _| async move {
+                    while let Some(Ok(frame)) = frames.next().await {
+                        let reversed: Vec<u8> = frame.iter().rev().copied().collect();
+                        if frames.send(reversed).await.is_err() {
+                            return;
+                        }
+                    }
+                });
+                served.await.map_err(|e| e.to_string())
+            }
+        });
+
+        // A frame split across writes, followed by an empty one in the same write.
+        let mut client = TcpClient::connect(&addr).await.unwrap();
+        client.write_all(&[3, 0, 0, 0, 0xff, 0x00]).await.unwrap();
+        tokio::time::sleep(std::time::Duration::from_millis(20)).await;
+        client.write_all(&[0x7f, 0, 0, 0, 0]).await.unwrap();
+        let mut frames = client.into_framed(codec);
+        assert_eq!(&frames.next().await.unwrap().unwrap()[..], [0x7f, 0x00, 0xff]);
+        assert!(frames.next().await.unwrap().unwrap().is_empty());
+        frames.send(&b"binary\0data"[..]).await.unwrap();
+        assert_eq!(&frames.next().await.unwrap().unwrap()[..], b"atad\0yranib");
+        assert!(frames.send(vec![0; 17]).await.is_err());
+
+        // Oversized frames are refused from their prefix, before the payload arrives.
+        let mut buffer = BytesMut::from(&[0, 0, 0, 17][..]);
+        let mut big_endian = LengthPrefixedCodec::new().max_frame_size(16);
+        assert_eq!(big_endian.decode(&mut buffer).unwrap_err().kind(), io::ErrorKind::InvalidData);
+
+        server.shutdown().await;
+        task.await.unwrap().unwrap();
+    }
+}
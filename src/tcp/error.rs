@@ -0,0 +1,82 @@
+use std::{any::Any, error::Error, fmt, io, net::SocketAddr};
+
+/// The `TcpServerError` enum is the error of a `TcpServer`. Failing to bind and an invalid
+/// configuration are fatal and returned by the `bind` functions and `TcpServerBuilder::build`; the
+/// other errors concern one connection or one accept, leave the server
+/// running, and are passed to the `on_error` hook.
+#[derive(Debug)]
+pub enum TcpServerError {
+    /// The server could not listen on `address`.
+    Bind { address: String, source: io::Error },
+    /// The settings of a `TcpServerBuilder` are missing, invalid or contradict each other.
+    Config(String),
+    /// Accepting a connection failed, for example because the process ran out of file descriptors.
+    Accept(io::Error),
+    /// The TLS handshake with `peer` failed or timed out.
+    TlsHandshake { peer: SocketAddr, source: io::Error },
+    /// The connection with `peer` failed: an idle timeout, missed heartbeats, or an error reading,
+    /// writing or reaching the upstream server of `forward_to`.
+    Connection { peer: SocketAddr, source: io::Error },
+    /// The handler of the connection with `peer` panicked with `message`.
+    HandlerPanic { peer: SocketAddr, message: String },
+}
+
+impl TcpServerError {
+    /// The function `handler_panic` describes the panic `payload` of the handler serving `peer`.
+    pub(crate) fn handler_panic(peer: SocketAddr, payload: Box<dyn Any + Send>) -> Self {
+        let message = match payload.downcast::<String>() {
+            Ok(message) => *message,
+            Err(payload) => payload.downcast_ref::<&str>().map_or("Box<dyn Any>", |message| message).to_string(),
+        };
+        TcpServerError::HandlerPanic { peer, message }
+    }
+
+    /// The function `is_fatal` returns whether the error stops the server, as opposed to failing a
+    /// single connection or accept.
+    pub fn is_fatal(&self) -> bool {
+        matches!(self, TcpServerError::Bind { .. } | TcpServerError::Config(_))
+    }
+
+    /// The function `peer` returns the address of the client the error concerns, if any.
+    pub fn peer(&self) -> Option<SocketAddr> {
+        match self {
+            TcpServerError::TlsHandshake { peer, .. }
+            | TcpServerError::Connection { peer, .. }
+            | TcpServerError::HandlerPanic { peer, .. } => Some(*peer),
+            TcpServerError::Bind { .. } | TcpServerError::Config(_) | TcpServerError::Accept(_) => None,
+        }
+    }
+
+    /// The function `io_error` returns the I/O error behind the failure, which a panic or an invalid
+    /// configuration has not.
+    pub fn io_error(&self) -> Option<&io::Error> {
+        match self {
+            TcpServerError::Bind { source, .. }
+            | TcpServerError::Accept(source)
+            | TcpServerError::TlsHandshake { source, .. }
+            | TcpServerError::Connection { source, .. } => Some(source),
+            TcpServerError::Config(_) | TcpServerError::HandlerPanic { .. } => None,
+        }
+    }
+}
+
+impl fmt::Display for TcpServerError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TcpServerError::Bind { address, source } => write!(f, "bind {} failed: {}", address, source),
+            TcpServerError::Config(message) => write!(f, "invalid server configuration: {}", message),
+            TcpServerError::Accept(source) => write!(f, "accept failed: {}", source),
+            TcpServerError::TlsHandshake { peer, source } => {
+                write!(f, "TLS handshake with {} failed: {}", peer, source)
+            }
+            TcpServerError::Connection { peer, source } => write!(f, "connection with {} failed: {}", peer, source),
+            TcpServerError::HandlerPanic { peer, message } => write!(f, "handler of {} panicked: {}", peer, message),
+        }
+    }
+}
+
+impl Error for TcpServerError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        self.io_error().map(|source| source as &(dyn Error + 'static))
+    }
+}
@@ -0,0 +1,153 @@
+use super::{client::TcpClient, json::JsonConnection};
+use crate::error::AarambhNetError;
+use futures_util::{SinkExt, Stream, StreamExt};
+use std::{error::Error, io};
+use tokio_util::codec::{Decoder, Encoder, Framed};
+
+/// The `FramedClient` struct is a `TcpClient` exchanging whole messages framed by `codec`, a
+/// `LineCodec` or a `LengthPrefixedCodec` matching the one the server runs with `run_framed`, so
+/// message boundaries are kept end to end however the bytes are split on the wire. It is returned
+/// by `TcpClient::framed`.
+///
+/// # Properties:
+///
+/// * `framed`: The connection, framed by the codec.
+/// * `target`: The address of the server, reported in errors.
+pub struct FramedClient<C> {
+    framed: Framed<TcpClient, C>,
+    target: String,
+}
+
+impl<C: Decoder> FramedClient<C>
+where
+    C::Error: Into<Box<dyn Error + Send + Sync>>,
+{
+    pub(crate) fn new(client: TcpClient, codec: C, target: impl Into<String>) -> Self {
+        FramedClient { framed: Framed::new(client, codec), target: target.into() }
+    }
+
+    /// The function `send_frame` encodes `frame` with the codec and sends it, such as a `&str` line
+    /// for a `LineCodec` or a `&[u8]` payload for a `LengthPrefixedCodec`.
+    ///
+    /// # Returns:
+    ///
+    /// The `send_frame` function returns a `Result` that is an error if encoding or writing fails.
+    pub async fn send_frame<I>(&mut self, frame: I) -> Result<(), AarambhNetError>
+    where
+        C: Encoder<I>,
+        <C as Encoder<I>>::Error: Into<Box<dyn Error + Send + Sync>>,
+    {
+        self.framed.send(frame).await.map_err(|e| AarambhNetError::new("send", e).with_target(&self.target))
+    }
+
+    /// The function `next_frame` waits for the next message decoded by the codec.
+    ///
+    /// # Returns:
+    ///
+    /// The `next_frame` function returns `Ok(None)` once the server closed the connection, or an
+    /// error if reading or decoding fails.
+    pub async fn next_frame(&mut self) -> Result<Option<C::Item>, AarambhNetError> {
+        match self.framed.next().await {
+            Some(Ok(frame)) => Ok(Some(frame)),
+            Some(Err(e)) => Err(AarambhNetError::new("receive", e).with_target(&self.target)),
+            None => Ok(None),
+        }
+    }
+
+    /// The function `messages` returns the messages of the server as a `Stream`, like
+    /// `TcpClient::messages`, ending once the server closed the connection.
+    pub fn messages(&mut self) -> impl Stream<Item = Result<C::Item, AarambhNetError>> + '_ {
+        let target = &self.target;
+        let failed = move |e: C::Error| AarambhNetError::new("receive", e).with_target(target);
+        (&mut self.framed).map(move |message| message.map_err(failed))
+    }
+
+    /// The function `get_ref` returns the underlying client, for its timeline or socket options.
+    pub fn get_ref(&self) -> &TcpClient {
+        self.framed.get_ref()
+    }
+
+    /// The function `get_mut` returns the underlying client, to set its timeouts or heartbeat.
+    pub fn get_mut(&mut self) -> &mut TcpClient {
+        self.framed.get_mut()
+    }
+
+    /// The function `into_framed` returns the underlying framed connection, a `Stream` and `Sink` of
+    /// messages.
+    pub fn into_framed(self) -> Framed<TcpClient, C> {
+        self.framed
+    }
+}
+
+impl<C> FramedClient<C>
+where
+    C: Decoder<Error = io::Error> + for<'a> Encoder<&'a [u8], Error = io::Error>,
+    C::Item: AsRef<[u8]>,
+{
+    /// The function `into_json` exchanges typed messages as JSON over the frames, like the
+    /// connections of `TcpServer::run_json`. Bytes already read are kept.
+    pub fn into_json(self) -> JsonConnection<TcpClient, C> {
+        JsonConnection::new(self.framed, self.target)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::tcp::{LengthPrefixedCodec, LineCodec, TcpClient, TcpServer};
+    use futures_util::{SinkExt, StreamExt};
+    use std::sync::Arc;
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+    #[tokio::test]
+    async fn test_framed_client() {
+        let server = Arc::new(TcpServer::bind("127.0.0.1:0").await.unwrap());
+        let addr = server.local_addr().unwrap().to_string();
+        let task = tokio::spawn({
+            let server = server.clone();
+            async move {
+                let served = server.run_framed(LengthPrefixedCodec::new(), |mut frames, _| async move {
+                    while let Some(Ok(frame)) = frames.next().await {
+                        let _ = frames.send(frame.freeze()).await;
+                    }
+                });
+                served.await.map_err(|e| e.to_string())
+            }
+        });
+
+        // Frames keep their boundaries, empty and binary ones included.
+        let mut client = TcpClient::connect(&addr).await.unwrap().framed(LengthPrefixedCodec::new());
+        for frame in [&b"first"[..], b"", &[0xff; 3000]] {
+            client.send_frame(frame).await.unwrap();
+        }
+        for frame in [&b"first"[..], b"", &[0xff; 3000]] {
+            assert_eq!(client.next_frame().await.unwrap().unwrap(), frame);
+        }
+        assert!(client.get_ref().timeline().is_none());
+        client.send_frame(&b"again"[..]).await.unwrap();
+        assert_eq!(client.messages().next().await.unwrap().unwrap(), &b"again"[..]);
+
+        // The same codec carries JSON once the client switches over.
+        let mut json = client.into_json();
+        json.send(&serde_json::json!({ "n": 1 })).await.unwrap();
+        assert_eq!(json.recv::<serde_json::Value>().await.unwrap().unwrap()["n"], 1);
+        drop(json);
+        server.shutdown().await;
+        task.await.unwrap().unwrap();
+
+        // A line client reads the last line, left unterminated, then sees the server close the connection.
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap().to_string();
+        let closing = tokio::spawn(async move {
+            let (mut socket, _) = listener.accept().await.unwrap();
+            let mut line = [0; 4];
+            socket.read_exact(&mut line).await.unwrap();
+            socket.write_all(b"one\r\ntwo").await.unwrap();
+        });
+        let mut client = TcpClient::connect(&addr).await.unwrap().framed(LineCodec::new());
+        client.send_frame("one").await.unwrap();
+        assert_eq!(client.next_frame().await.unwrap().as_deref(), Some("one"));
+        assert_eq!(client.next_frame().await.unwrap().as_deref(), Some("two"));
+        closing.await.unwrap();
+        assert_eq!(client.next_frame().await.unwrap(), None);
+    }
+}
@@ -0,0 +1,269 @@
+use super::outbox::Outbox;
+use bytes::Bytes;
+use std::{
+    future::Future,
+    io,
+    pin::Pin,
+    sync::Arc,
+    task::{ready, Context, Poll},
+    time::Duration,
+};
+use tokio::time::{sleep, Instant, Sleep};
+
+/// The `Heartbeat` struct configures the application-level heartbeats detecting dead peers on a
+/// `TcpServer` or a `TcpClient`. Once a connection has been waiting `interval` for data, `ping` is
+/// sent; anything the peer sends within `timeout` answers it. Otherwise another ping is sent, and the
+/// connection is closed with a `TimedOut` error once `max_missed` pings in a row went unanswered.
+/// The payloads are sent as is, so they must be framed for the protocol, for example end with a
+/// newline for a `LineCodec`, and differ from each other.
+///
+/// The framed connections of a `TcpServer` recognize both payloads: they answer a `ping` from the
+/// client with `pong` and hide both from the handler. A `TcpClient` hands the `pong` of the server
+/// to the application like any message.
+///
+/// # Properties:
+///
+/// * `ping`: The payload sent to a silent peer.
+/// * `pong`: The payload answering a ping of the peer.
+/// * `interval`: How long a connection waits for data before pinging.
+/// * `timeout`: How long the peer has to answer a ping.
+/// * `max_missed`: How many pings in a row may go unanswered.
+#[derive(Debug, Clone)]
+pub struct Heartbeat {
+    ping: Bytes,
+    pong: Bytes,
+    interval: Duration,
+    timeout: Duration,
+    max_missed: u32,
+}
+
+impl Heartbeat {
+    /// The function `new` creates heartbeats exchanging `ping` and `pong`, sent after 30 seconds
+    /// without data, answered within 10 seconds, and closing the connection after 3 missed pings.
+    pub fn new(ping: impl Into<Bytes>, pong: impl Into<Bytes>) -> Self {
+        Heartbeat {
+            ping: ping.into(),
+            pong: pong.into(),
+            interval: Duration::from_secs(30),
+            timeout: Duration::from_secs(10),
+            max_missed: 3,
+        }
+    }
+
+    /// The function `interval` sets how long a connection waits for data before pinging.
+    pub fn interval(mut self, interval: Duration) -> Self {
+        self.interval = interval;
+        self
+    }
+
+    /// The function `timeout` sets how long the peer has to answer a ping.
+    pub fn timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    /// The function `max_missed` sets how many pings in a row may go unanswered before the
+    /// connection is closed.
+    pub fn max_missed(mut self, pings: u32) -> Self {
+        self.max_missed = pings.max(1);
+        self
+    }
+}
+
+/// The `HeartbeatTimer` struct tracks when a connection waiting for data must ping its peer.
+///
+/// # Properties:
+///
+/// * `heartbeat`: The configuration of the heartbeats.
+/// * `timer`: Fires when the next ping is due.
+/// * `waiting`: Whether a read is waiting, with `timer` running.
+/// * `unanswered`: The pings sent since the peer last sent data.
+pub(crate) struct HeartbeatTimer {
+    heartbeat: Heartbeat,
+    timer: Pin<Box<Sleep>>,
+    waiting: bool,
+    unanswered: u32,
+}
+
+impl HeartbeatTimer {
+    pub(crate) fn new(heartbeat: Heartbeat) -> Self {
+        HeartbeatTimer { timer: Box::pin(sleep(heartbeat.interval)), heartbeat, waiting: false, unanswered: 0 }
+    }
+
+    /// The function `received` records data from the peer, answering the pings sent.
+    pub(crate) fn received(&mut self) {
+        self.waiting = false;
+        self.unanswered = 0;
+    }
+
+    /// The function `poll_ping` runs the timer while a read waits for data.
+    ///
+    /// # Returns:
+    ///
+    /// The `poll_ping` function returns the ping to send once one is due, or a `TimedOut` error once
+    /// the peer missed too many.
+    pub(crate) fn poll_ping(&mut self, cx: &mut Context<'_>) -> Poll<io::Result<Bytes>> {
+        if !self.waiting {
+            self.waiting = true;
+            self.timer.as_mut().reset(Instant::now() + self.heartbeat.interval);
+        }
+        ready!(self.timer.as_mut().poll(cx));
+        if self.unanswered >= self.heartbeat.max_missed {
+            return Poll::Ready(Err(io::Error::new(io::ErrorKind::TimedOut, "peer missed its heartbeats")));
+        }
+        self.unanswered += 1;
+        self.timer.as_mut().reset(Instant::now() + self.heartbeat.timeout);
+        Poll::Ready(Ok(self.heartbeat.ping.clone()))
+    }
+
+    /// The function `frames` returns the recognizer of the heartbeat frames sent by the peer,
+    /// answering its pings through `outbox`.
+    pub(crate) fn frames(&self, outbox: Arc<Outbox>) -> HeartbeatFrames {
+        HeartbeatFrames { heartbeat: self.heartbeat.clone(), outbox }
+    }
+}
+
+/// The `KeepaliveTimer` struct tracks when an idle connection must send its keepalive payload.
+///
+/// # Properties:
+///
+/// * `payload`: The payload sent once the connection is idle.
+/// * `idle`: How long the connection may go without data before the payload is sent.
+/// * `timer`: Fires when the payload is due, reset by any data sent or received.
+pub(crate) struct KeepaliveTimer {
+    payload: Bytes,
+    idle: Duration,
+    timer: Pin<Box<Sleep>>,
+}
+
+impl KeepaliveTimer {
+    pub(crate) fn new(payload: Bytes, idle: Duration) -> Self {
+        KeepaliveTimer { payload, idle, timer: Box::pin(sleep(idle)) }
+    }
+
+    /// The function `active` records data sent or received, restarting the idle time.
+    pub(crate) fn active(&mut self) {
+        self.timer.as_mut().reset(Instant::now() + self.idle);
+    }
+
+    /// The function `poll_due` runs the timer, returning the payload once the connection has been
+    /// idle long enough, and starting the next idle time.
+    pub(crate) fn poll_due(&mut self, cx: &mut Context<'_>) -> Poll<Bytes> {
+        ready!(self.timer.as_mut().poll(cx));
+        self.active();
+        Poll::Ready(self.payload.clone())
+    }
+}
+
+/// The `HeartbeatFrames` struct recognizes the heartbeat frames a client sends to a framed
+/// connection of a `TcpServer`, so they are not handed to the handler.
+///
+/// # Properties:
+///
+/// * `heartbeat`: The payloads recognized.
+/// * `outbox`: The queue the pongs answering pings are written from.
+#[derive(Debug)]
+pub(crate) struct HeartbeatFrames {
+    heartbeat: Heartbeat,
+    outbox: Arc<Outbox>,
+}
+
+impl HeartbeatFrames {
+    /// The function `frame_at` returns the heartbeat payload `src` starts with, if any.
+    pub(crate) fn frame_at(&self, src: &[u8]) -> Option<Bytes> {
+        let payloads = [&self.heartbeat.ping, &self.heartbeat.pong];
+        payloads.into_iter().find(|payload| !payload.is_empty() && src.starts_with(payload)).cloned()
+    }
+
+    /// The function `consume` handles a heartbeat frame received, answering a ping with a pong.
+    pub(crate) fn consume(&self, frame: &Bytes) {
+        if *frame == self.heartbeat.ping {
+            self.outbox.push(self.heartbeat.pong.clone());
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::tcp::{Heartbeat, LineCodec, TcpClient, TcpServer};
+    use futures_util::{SinkExt, StreamExt};
+    use std::{
+        sync::{Arc, Mutex},
+        time::Duration,
+    };
+    use tokio::net::{TcpListener, TcpStream};
+    use tokio_util::codec::Framed;
+
+    #[tokio::test]
+    async fn test_heartbeats() {
+        let heartbeat = Heartbeat::new("ping\n", "pong\n")
+            .interval(Duration::from_millis(100))
+            .timeout(Duration::from_millis(100))
+            .max_missed(2);
+        let errors = Arc::new(Mutex::new(Vec::new()));
+        let server = TcpServer::bind("127.0.0.1:0").await.unwrap().heartbeat(heartbeat.clone()).on_error({
+            let errors = errors.clone();
+            move |e| errors.lock().unwrap().extend(e.io_error().map(|e| e.kind()))
+        });
+        let server = Arc::new(server);
+        let addr = server.local_addr().unwrap();
+        let task = tokio::spawn({
+            let server = server.clone();
+            async move {
+                let served = server.run_framed(LineCodec::new(), |mut lines, _| async move {
+                    while let Some(Ok(line)) = lines.next().await {
+                        let _ = lines.send(line).await;
+                    }
+                });
+                served.await.map_err(|e| e.to_string())
+            }
+        });
+
+        // A client answering the pings stays connected, and the handler never sees the pongs.
+        let mut lines = Framed::new(TcpStream::connect(addr).await.unwrap(), LineCodec::new());
+        for _ in 0..3 {
+            assert_eq!(lines.next().await.unwrap().unwrap(), "ping");
+            lines.send("pong").await.unwrap();
+        }
+        lines.send("hello").await.unwrap();
+        assert_eq!(lines.next().await.unwrap().unwrap(), "hello");
+
+        // A client pinging the server is answered without involving the handler.
+        let mut client = TcpClient::connect(&addr.to_string()).await.unwrap();
+        client.set_heartbeat(heartbeat.interval(Duration::from_millis(50)));
+        assert_eq!(client.receive_response().await.unwrap(), "pong\n");
+
+        // A silent client is closed after two unanswered pings.
+        let mut silent = Framed::new(TcpStream::connect(addr).await.unwrap(), LineCodec::new());
+        assert_eq!(silent.next().await.unwrap().unwrap(), "ping");
+        assert_eq!(silent.next().await.unwrap().unwrap(), "ping");
+        assert!(matches!(silent.next().await, None | Some(Err(_))));
+        assert!(errors.lock().unwrap().contains(&std::io::ErrorKind::TimedOut));
+
+        server.shutdown().await;
+        task.await.unwrap().unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_keepalive_payload() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap().to_string();
+        let server = tokio::spawn(async move {
+            let (socket, _) = listener.accept().await.unwrap();
+            let mut lines = Framed::new(socket, LineCodec::new());
+            let mut received = Vec::new();
+            for _ in 0..3 {
+                received.push(lines.next().await.unwrap().unwrap());
+            }
+            lines.send("bye").await.unwrap();
+            received
+        });
+
+        // The idle client sends the payload while it waits for the server, which never answers it.
+        let mut client = TcpClient::connect(&addr).await.unwrap();
+        client.set_keepalive_payload("noop\n", Duration::from_millis(50));
+        client.send_message("hello\n").await.unwrap();
+        assert_eq!(client.receive_response().await.unwrap(), "bye\n");
+        assert_eq!(server.await.unwrap(), ["hello", "noop", "noop"]);
+    }
+}
@@ -0,0 +1,100 @@
+use super::{error::TcpServerError, session::Session};
+use std::{fmt, io, net::SocketAddr, sync::Arc};
+
+type SessionHook = Arc<dyn Fn(&Session) + Send + Sync>;
+type ErrorHook = Arc<dyn Fn(&TcpServerError) + Send + Sync>;
+
+/// The `Hooks` struct holds the lifecycle callbacks of a `TcpServer`, set with
+/// `TcpServer::on_connect`, `on_disconnect` and `on_error`.
+///
+/// # Properties:
+///
+/// * `connect`: Called with the session of each new connection.
+/// * `disconnect`: Called with the session of each closed connection.
+/// * `error`: Called with the error of each failed connection or accept.
+#[derive(Clone, Default)]
+pub(crate) struct Hooks {
+    pub(crate) connect: Option<SessionHook>,
+    pub(crate) disconnect: Option<SessionHook>,
+    pub(crate) error: Option<ErrorHook>,
+}
+
+impl Hooks {
+    pub(crate) fn connected(&self, session: &Session) {
+        if let Some(hook) = &self.connect {
+            hook(session);
+        }
+    }
+
+    pub(crate) fn disconnected(&self, session: &Session) {
+        if let Some(hook) = &self.disconnect {
+            hook(session);
+        }
+    }
+
+    pub(crate) fn failed(&self, error: &TcpServerError) {
+        if let Some(hook) = &self.error {
+            hook(error);
+        }
+    }
+
+    /// The function `connection_failed` reports `error` of the connection with `peer`, which the
+    /// caller keeps, as a `TcpServerError::Connection` holding a copy.
+    pub(crate) fn connection_failed(&self, peer: SocketAddr, error: &io::Error) {
+        if let Some(hook) = &self.error {
+            hook(&TcpServerError::Connection { peer, source: io::Error::new(error.kind(), error.to_string()) });
+        }
+    }
+}
+
+impl fmt::Debug for Hooks {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Hooks")
+            .field("connect", &self.connect.is_some())
+            .field("disconnect", &self.disconnect.is_some())
+            .field("error", &self.error.is_some())
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::tcp::{TcpClient, TcpServer};
+    use std::{
+        sync::{Arc, Mutex},
+        time::Duration,
+    };
+
+    #[tokio::test]
+    async fn test_lifecycle_hooks() {
+        let events = Arc::new(Mutex::new(Vec::new()));
+        let record = |events: &Arc<Mutex<Vec<String>>>| {
+            let events = events.clone();
+            move |event: String| events.lock().unwrap().push(event)
+        };
+        let (connect, disconnect, error) = (record(&events), record(&events), record(&events));
+        let server = TcpServer::bind("127.0.0.1:0")
+            .await
+            .unwrap()
+            .idle_timeout(Duration::from_millis(100))
+            .on_connect(move |session| connect(format!("connect {}", session.id())))
+            .on_disconnect(move |session| disconnect(format!("disconnect {}", session.id())))
+            .on_error(move |e| error(format!("error {:?}", e.io_error().map(|e| e.kind()))));
+        let server = Arc::new(server);
+        let addr = server.local_addr().unwrap().to_string();
+        let task = tokio::spawn({
+            let server = server.clone();
+            async move { server.run_scoped().await.map_err(|e| e.to_string()) }
+        });
+
+        let mut client = TcpClient::connect(&addr).await.unwrap();
+        client.send_message("hello").await.unwrap();
+        assert_eq!(client.receive_response().await.unwrap(), "hello");
+        assert_eq!(client.receive_response().await.unwrap_or_default(), "");
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        assert_eq!(*events.lock().unwrap(), ["connect 0", "error Some(TimedOut)", "disconnect 0"]);
+
+        server.shutdown().await;
+        task.await.unwrap().unwrap();
+    }
+}
@@ -0,0 +1,119 @@
+use super::codec::LineCodec;
+use crate::error::AarambhNetError;
+use futures_util::{SinkExt, StreamExt};
+use serde::{de::DeserializeOwned, Serialize};
+use std::io;
+use tokio::io::{AsyncRead, AsyncWrite};
+use tokio_util::codec::{Decoder, Encoder, Framed};
+
+/// The `JsonConnection` struct exchanges typed messages as JSON over a framed TCP connection, one
+/// message per frame. It is handed to the handler of `TcpServer::run_json` and returned by
+/// `TcpClient::into_json`, so both ends speak the same protocol.
+///
+/// # Properties:
+///
+/// * `framed`: The connection, framed by a `LineCodec` or `LengthPrefixedCodec`.
+/// * `target`: The address of the peer, reported in errors.
+pub struct JsonConnection<S, F = LineCodec> {
+    framed: Framed<S, F>,
+    target: String,
+}
+
+impl<S, F> JsonConnection<S, F>
+where
+    S: AsyncRead + AsyncWrite + Unpin,
+    F: Decoder<Error = io::Error> + for<'a> Encoder<&'a [u8], Error = io::Error>,
+    F::Item: AsRef<[u8]>,
+{
+    pub(crate) fn new(framed: Framed<S, F>, target: impl Into<String>) -> Self {
+        JsonConnection { framed, target: target.into() }
+    }
+
+    /// The function `send` serializes `message` to JSON and sends it as one frame.
+    pub async fn send<T: Serialize + ?Sized>(&mut self, message: &T) -> Result<(), AarambhNetError> {
+        let json = serde_json::to_vec(message).map_err(|e| AarambhNetError::new("encode", e))?;
+        self.framed.send(json.as_slice()).await.map_err(|e| AarambhNetError::new("send", e).with_target(&self.target))
+    }
+
+    /// The function `recv` waits for the next frame and deserializes it as a `T`.
+    ///
+    /// # Returns:
+    ///
+    /// The `recv` function returns `Ok(None)` once the peer closed the connection. It returns an
+    /// error if the connection fails, or if the frame is not a valid `T`, in which case the frame is
+    /// consumed and the connection stays usable.
+    pub async fn recv<T: DeserializeOwned>(&mut self) -> Result<Option<T>, AarambhNetError> {
+        match self.framed.next().await {
+            Some(Ok(frame)) => serde_json::from_slice(frame.as_ref())
+                .map(Some)
+                .map_err(|e| AarambhNetError::new("decode", e).with_target(&self.target)),
+            Some(Err(e)) => Err(AarambhNetError::new("receive", e).with_target(&self.target)),
+            None => Ok(None),
+        }
+    }
+
+    /// The function `get_ref` returns the underlying connection, such as the `ServerStream` holding
+    /// the session of a server connection.
+    pub fn get_ref(&self) -> &S {
+        self.framed.get_ref()
+    }
+
+    /// The function `into_framed` returns the underlying framed connection.
+    pub fn into_framed(self) -> Framed<S, F> {
+        self.framed
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::tcp::{LengthPrefixedCodec, TcpClient, TcpServer};
+    use serde::{Deserialize, Serialize};
+    use std::sync::Arc;
+
+    #[derive(Debug, Serialize, Deserialize, PartialEq)]
+    struct Add {
+        a: i64,
+        b: i64,
+    }
+
+    #[derive(Debug, Serialize, Deserialize, PartialEq)]
+    struct Sum {
+        sum: i64,
+    }
+
+    #[tokio::test]
+    async fn test_json_messages() {
+        let server = Arc::new(TcpServer::bind("127.0.0.1:0").await.unwrap());
+        let addr = server.local_addr().unwrap().to_string();
+        let task = tokio::spawn({
+            let server = server.clone();
+            async move {
+                let served = server.run_json(LengthPrefixedCodec::new(), |mut connection, _| async move {
+                    loop {
+                        match connection.recv::<Add>().await {
+                            Ok(Some(Add { a, b })) => connection.send(&Sum { sum: a + b }).await.unwrap(),
+                            Ok(None) => return,
+                            Err(e) => connection.send(&serde_json::json!({ "error": e.to_string() })).await.unwrap(),
+                        }
+                    }
+                });
+                served.await.map_err(|e| e.to_string())
+            }
+        });
+
+        let client = TcpClient::connect(&addr).await.unwrap();
+        let mut connection = client.into_json(LengthPrefixedCodec::new());
+        connection.send(&Add { a: 2, b: 40 }).await.unwrap();
+        assert_eq!(connection.recv::<Sum>().await.unwrap(), Some(Sum { sum: 42 }));
+
+        // A message of the wrong shape is reported without closing the connection.
+        connection.send("not an addition").await.unwrap();
+        let reply: serde_json::Value = connection.recv().await.unwrap().unwrap();
+        assert!(reply["error"].as_str().unwrap().starts_with("decode"), "{}", reply);
+        connection.send(&Add { a: -1, b: 1 }).await.unwrap();
+        assert_eq!(connection.recv::<Sum>().await.unwrap(), Some(Sum { sum: 0 }));
+
+        server.shutdown().await;
+        task.await.unwrap().unwrap();
+    }
+}
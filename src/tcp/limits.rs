@@ -0,0 +1,195 @@
+use super::stream::ReadPause;
+use crate::rate_limit::TokenBucket;
+use std::{
+    collections::HashMap,
+    io,
+    net::{IpAddr, SocketAddr},
+    sync::{Arc, Mutex},
+};
+
+/// How many buckets a map holds before the full ones, whose peers went quiet, are dropped.
+const PRUNE_THRESHOLD: usize = 4096;
+
+/// The `LimitAction` enum is what a `TcpServer` does with a message over the budget of its peer.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LimitAction {
+    /// Deliver the message, then stop reading from the connection until the peer is back within
+    /// its budget, slowing it down through TCP flow control. Messages already received are still
+    /// delivered.
+    Delay,
+    /// Discard the message.
+    Drop,
+    /// Close the connection.
+    Disconnect,
+}
+
+/// The `PeerLimits` struct limits what each IP address may do on a `TcpServer`, with token buckets
+/// kept in memory, so one misbehaving client cannot exhaust the server. Connections over the
+/// connection budget are closed as soon as they are accepted; messages over the message budget,
+/// counted across every connection of the address, are handled according to the `LimitAction`.
+/// Message limits apply to the connections of `run_framed` and `run_json`.
+///
+/// # Properties:
+///
+/// * `connections_per_minute`: How many connections an address may open per minute, in bursts of
+///   the same size.
+/// * `messages_per_second`: How many messages an address may send per second, in bursts of the
+///   same size.
+/// * `action`: What happens to messages over the limit.
+/// * `connection_buckets`: The connection bucket of each address.
+/// * `message_buckets`: The message bucket of each address, shared by its connections.
+#[derive(Debug)]
+pub struct PeerLimits {
+    connections_per_minute: Option<u32>,
+    messages_per_second: Option<f64>,
+    action: LimitAction,
+    connection_buckets: Mutex<HashMap<IpAddr, TokenBucket>>,
+    message_buckets: Mutex<HashMap<IpAddr, Arc<TokenBucket>>>,
+}
+
+impl PeerLimits {
+    /// The function `new` creates limits allowing everything, delaying messages over the limit once
+    /// one is set.
+    pub fn new() -> Self {
+        PeerLimits {
+            connections_per_minute: None,
+            messages_per_second: None,
+            action: LimitAction::Delay,
+            connection_buckets: Mutex::new(HashMap::new()),
+            message_buckets: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// The function `connections_per_minute` limits how many connections an address may open per
+    /// minute.
+    pub fn connections_per_minute(mut self, connections: u32) -> Self {
+        self.connections_per_minute = Some(connections.max(1));
+        self
+    }
+
+    /// The function `messages_per_second` limits how many messages an address may send per second.
+    pub fn messages_per_second(mut self, messages: f64) -> Self {
+        self.messages_per_second = Some(messages);
+        self
+    }
+
+    /// The function `action` sets what happens to messages over the limit, `LimitAction::Delay` by
+    /// default.
+    pub fn action(mut self, action: LimitAction) -> Self {
+        self.action = action;
+        self
+    }
+
+    /// The function `allow_connection` takes a token from the connection bucket of `ip`.
+    pub(crate) fn allow_connection(&self, ip: IpAddr) -> bool {
+        let Some(per_minute) = self.connections_per_minute else { return true };
+        let mut buckets = self.connection_buckets.lock().unwrap();
+        if buckets.len() >= PRUNE_THRESHOLD && !buckets.contains_key(&ip) {
+            buckets.retain(|_, bucket| bucket.available() < bucket.capacity());
+        }
+        let per_minute = f64::from(per_minute);
+        let bucket = buckets.entry(ip).or_insert_with(|| TokenBucket::new(per_minute, per_minute / 60.0));
+        bucket.try_acquire(1.0).is_ok()
+    }
+
+    /// The function `message_limiter` returns the limiter of the messages `peer` sends over a
+    /// connection whose reads `pause` stops, if messages are limited.
+    pub(crate) fn message_limiter(&self, peer: SocketAddr, pause: ReadPause) -> Option<MessageLimiter> {
+        let per_second = self.messages_per_second?;
+        let mut buckets = self.message_buckets.lock().unwrap();
+        if buckets.len() >= PRUNE_THRESHOLD && !buckets.contains_key(&peer.ip()) {
+            buckets.retain(|_, bucket| Arc::strong_count(bucket) > 1 || bucket.available() < bucket.capacity());
+        }
+        let bucket = buckets.entry(peer.ip()).or_insert_with(|| Arc::new(TokenBucket::per_second(per_second)));
+        Some(MessageLimiter { bucket: bucket.clone(), action: self.action, peer, pause })
+    }
+}
+
+impl Default for PeerLimits {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// The `MessageLimiter` struct applies the message limit of a peer to one of its connections.
+///
+/// # Properties:
+///
+/// * `bucket`: The message bucket of the address of the peer.
+/// * `action`: What happens to messages over the limit.
+/// * `peer`: The address of the peer, reported in logs.
+/// * `pause`: Stops the reads of the connection.
+#[derive(Debug)]
+pub(crate) struct MessageLimiter {
+    bucket: Arc<TokenBucket>,
+    action: LimitAction,
+    peer: SocketAddr,
+    pause: ReadPause,
+}
+
+impl MessageLimiter {
+    /// The function `admit` takes a token for a decoded message.
+    ///
+    /// # Returns:
+    ///
+    /// The `admit` function returns whether the message is delivered, or an error closing the
+    /// connection.
+    pub(crate) fn admit(&self) -> io::Result<bool> {
+        let Err(wait) = self.bucket.try_acquire(1.0) else { return Ok(true) };
+        tracing::debug!(peer = %self.peer, action = ?self.action, "message rate exceeded");
+        match self.action {
+            LimitAction::Delay => {
+                self.pause.pause_for(wait);
+                Ok(true)
+            }
+            LimitAction::Drop => Ok(false),
+            LimitAction::Disconnect => Err(io::Error::new(io::ErrorKind::ConnectionAborted, "message rate exceeded")),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::tcp::{LimitAction, LineCodec, PeerLimits, TcpServer};
+    use futures_util::{SinkExt, StreamExt};
+    use std::{sync::Arc, time::Duration};
+    use tokio::{io::AsyncReadExt, net::TcpStream};
+    use tokio_util::codec::Framed;
+
+    #[tokio::test]
+    async fn test_peer_limits() {
+        let limits = PeerLimits::new().connections_per_minute(2).messages_per_second(2.0).action(LimitAction::Drop);
+        let server = Arc::new(TcpServer::bind("127.0.0.1:0").await.unwrap().peer_limits(limits));
+        let addr = server.local_addr().unwrap();
+        let task = tokio::spawn({
+            let server = server.clone();
+            async move {
+                let served = server.run_framed(LineCodec::new(), |mut lines, _| async move {
+                    while let Some(Ok(line)) = lines.next().await {
+                        let _ = lines.send(line).await;
+                    }
+                });
+                served.await.map_err(|e| e.to_string())
+            }
+        });
+
+        // A burst of five messages loses the three over the budget.
+        let mut lines = Framed::new(TcpStream::connect(addr).await.unwrap(), LineCodec::new());
+        for line in ["1", "2", "3", "4", "5"] {
+            lines.send(line).await.unwrap();
+        }
+        assert_eq!(lines.next().await.unwrap().unwrap(), "1");
+        assert_eq!(lines.next().await.unwrap().unwrap(), "2");
+        tokio::time::sleep(Duration::from_millis(600)).await;
+        lines.send("6").await.unwrap();
+        assert_eq!(lines.next().await.unwrap().unwrap(), "6");
+
+        // The third connection within a minute is closed at once.
+        let _second = TcpStream::connect(addr).await.unwrap();
+        let mut third = TcpStream::connect(addr).await.unwrap();
+        assert!(matches!(third.read(&mut [0; 1]).await, Ok(0) | Err(_)));
+
+        server.shutdown().await;
+        task.await.unwrap().unwrap();
+    }
+}
@@ -0,0 +1,221 @@
+#[cfg(unix)]
+use std::path::Path;
+use socket2::{Domain, Protocol, Socket, Type};
+use std::{
+    io,
+    net::{Ipv4Addr, SocketAddr},
+    pin::Pin,
+    task::{Context, Poll},
+};
+use tokio::io::{AsyncRead, AsyncWrite, ReadBuf};
+#[cfg(unix)]
+use tokio::net::{UnixListener, UnixStream};
+use tokio::net::{TcpListener, TcpStream};
+
+/// The address handlers and sessions see for clients of a Unix domain socket, which have none.
+pub(crate) const UNIX_PEER: SocketAddr = SocketAddr::new(std::net::IpAddr::V4(Ipv4Addr::UNSPECIFIED), 0);
+
+/// The `Listener` enum is the socket a `TcpServer` accepts connections on.
+pub(crate) enum Listener {
+    Tcp(TcpListener),
+    #[cfg(unix)]
+    Unix(UnixListener),
+}
+
+/// The `Accepted` enum is a connection accepted by a `Listener`, read and written alike whichever
+/// socket it came from.
+pub(crate) enum Accepted {
+    Tcp(TcpStream),
+    #[cfg(unix)]
+    Unix(UnixStream),
+}
+
+impl AsyncRead for Accepted {
+    fn poll_read(self: Pin<&mut Self>, cx: &mut Context<'_>, buf: &mut ReadBuf<'_>) -> Poll<io::Result<()>> {
+        match self.get_mut() {
+            Accepted::Tcp(socket) => Pin::new(socket).poll_read(cx, buf),
+            #[cfg(unix)]
+            Accepted::Unix(socket) => Pin::new(socket).poll_read(cx, buf),
+        }
+    }
+}
+
+impl AsyncWrite for Accepted {
+    fn poll_write(self: Pin<&mut Self>, cx: &mut Context<'_>, buf: &[u8]) -> Poll<io::Result<usize>> {
+        match self.get_mut() {
+            Accepted::Tcp(socket) => Pin::new(socket).poll_write(cx, buf),
+            #[cfg(unix)]
+            Accepted::Unix(socket) => Pin::new(socket).poll_write(cx, buf),
+        }
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        match self.get_mut() {
+            Accepted::Tcp(socket) => Pin::new(socket).poll_flush(cx),
+            #[cfg(unix)]
+            Accepted::Unix(socket) => Pin::new(socket).poll_flush(cx),
+        }
+    }
+
+    fn poll_shutdown(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        match self.get_mut() {
+            Accepted::Tcp(socket) => Pin::new(socket).poll_shutdown(cx),
+            #[cfg(unix)]
+            Accepted::Unix(socket) => Pin::new(socket).poll_shutdown(cx),
+        }
+    }
+}
+
+/// How many connections the listeners of `bind_tcp` queue before they are accepted.
+const BACKLOG: i32 = 1024;
+
+impl Listener {
+    /// The function `bind_tcp` listens on `addr`, restricting IPv6 sockets to IPv6 so an IPv4
+    /// listener can share their port.
+    pub(crate) fn bind_tcp(addr: SocketAddr) -> io::Result<Self> {
+        Self::listen(Self::tcp_socket(addr)?, addr)
+    }
+
+    /// The function `bind_reuse_port` listens on `addr` like `bind_tcp` with `SO_REUSEPORT` set, so
+    /// several listeners can share the address, the kernel spreading connections between them.
+    #[cfg(any(target_os = "linux", target_os = "android"))]
+    pub(crate) fn bind_reuse_port(addr: SocketAddr) -> io::Result<Self> {
+        let socket = Self::tcp_socket(addr)?;
+        socket.set_reuse_port(true)?;
+        Self::listen(socket, addr)
+    }
+
+    fn tcp_socket(addr: SocketAddr) -> io::Result<Socket> {
+        let socket = Socket::new(Domain::for_address(addr), Type::STREAM, Some(Protocol::TCP))?;
+        if addr.is_ipv6() {
+            socket.set_only_v6(true)?;
+        }
+        #[cfg(unix)]
+        socket.set_reuse_address(true)?;
+        socket.set_nonblocking(true)?;
+        Ok(socket)
+    }
+
+    fn listen(socket: Socket, addr: SocketAddr) -> io::Result<Self> {
+        socket.bind(&addr.into())?;
+        socket.listen(BACKLOG)?;
+        Ok(Listener::Tcp(TcpListener::from_std(socket.into())?))
+    }
+
+    /// The function `bind_unix` listens on the Unix domain socket at `path`, replacing a stale socket
+    /// file left by a previous server. A socket file that still accepts connections belongs to a
+    /// running server and fails the bind with `AddrInUse` instead.
+    #[cfg(unix)]
+    pub(crate) fn bind_unix(path: &Path) -> io::Result<Self> {
+        use std::os::unix::{fs::FileTypeExt, net::UnixStream};
+        if std::fs::symlink_metadata(path).is_ok_and(|metadata| metadata.file_type().is_socket()) {
+            match UnixStream::connect(path) {
+                Err(e) if e.kind() == io::ErrorKind::ConnectionRefused => std::fs::remove_file(path)?,
+                _ => {
+                    let message = format!("a server is listening on {}", path.display());
+                    return Err(io::Error::new(io::ErrorKind::AddrInUse, message));
+                }
+            }
+        }
+        Ok(Listener::Unix(UnixListener::bind(path)?))
+    }
+
+    /// The function `accept` waits for the next connection, returning it with the address of its
+    /// peer, `UNIX_PEER` for Unix domain sockets.
+    pub(crate) async fn accept(&self) -> io::Result<(Accepted, SocketAddr)> {
+        match self {
+            Listener::Tcp(listener) => listener.accept().await.map(|(socket, peer)| (Accepted::Tcp(socket), peer)),
+            #[cfg(unix)]
+            Listener::Unix(listener) => listener.accept().await.map(|(socket, _)| (Accepted::Unix(socket), UNIX_PEER)),
+        }
+    }
+
+    /// The function `tcp` returns the TCP listener, or an `Unsupported` error for a Unix domain socket.
+    pub(crate) fn tcp(&self) -> io::Result<&TcpListener> {
+        match self {
+            Listener::Tcp(listener) => Ok(listener),
+            #[cfg(unix)]
+            Listener::Unix(_) => Err(io::Error::new(io::ErrorKind::Unsupported, "the server listens on a Unix socket")),
+        }
+    }
+}
+
+#[cfg(all(test, unix))]
+mod test {
+    use crate::tcp::{LineCodec, TcpClient, TcpServer, TcpServerError};
+    use futures_util::{SinkExt, StreamExt};
+    use std::sync::Arc;
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+    #[tokio::test]
+    async fn test_unix_socket() {
+        let path = std::env::temp_dir().join(format!("aarambh-net-{}.sock", std::process::id()));
+        let server = Arc::new(TcpServer::bind_unix(&path).unwrap());
+        assert!(server.local_addr().is_err());
+        let task = tokio::spawn({
+            let server = server.clone();
+            async move {
+                let served = server.run_framed(LineCodec::new(), |mut lines, _| async move {
+                    let peer = lines.get_ref().session().peer_addr();
+                    while let Some(Ok(line)) = lines.next().await {
+                        let _ = lines.send(format!("{} from {}", line, peer)).await;
+                    }
+                });
+                served.await.map_err(|e| e.to_string())
+            }
+        });
+
+        let mut lines = TcpClient::connect_unix(&path).await.unwrap().into_framed(LineCodec::new());
+        lines.send("hello").await.unwrap();
+        assert_eq!(lines.next().await.unwrap().unwrap(), "hello from 0.0.0.0:0");
+        assert!(lines.get_ref().apply_socket_options(&Default::default()).is_err());
+
+        server.shutdown().await;
+        task.await.unwrap().unwrap();
+        drop(server);
+        let _ = std::fs::remove_file(&path);
+    }
+
+    #[tokio::test]
+    async fn test_bind_unix_in_use() {
+        let path = std::env::temp_dir().join(format!("aarambh-net-in-use-{}.sock", std::process::id()));
+        let server = TcpServer::bind_unix(&path).unwrap();
+        let Err(TcpServerError::Bind { source, .. }) = TcpServer::bind_unix(&path) else {
+            panic!("took over the path");
+        };
+        assert_eq!(source.kind(), std::io::ErrorKind::AddrInUse);
+
+        // Once the server is gone, its socket file is stale and replaced.
+        drop(server);
+        assert!(path.exists());
+        drop(TcpServer::bind_unix(&path).unwrap());
+        let _ = std::fs::remove_file(&path);
+    }
+
+    #[tokio::test]
+    async fn test_serve_unix_socket() {
+        let path = std::env::temp_dir().join(format!("aarambh-net-serve-{}.sock", std::process::id()));
+        let server = Arc::new(TcpServer::bind_unix(&path).unwrap());
+        let task = tokio::spawn({
+            let server = server.clone();
+            async move {
+                let served = server.serve(|mut socket, peer| async move {
+                    let mut line = [0; 5];
+                    socket.read_exact(&mut line).await.unwrap();
+                    let reply = format!("{} from {}", String::from_utf8_lossy(&line), peer);
+                    socket.write_all(reply.as_bytes()).await.unwrap();
+                });
+                served.await.map_err(|e| e.to_string())
+            }
+        });
+
+        let mut client = TcpClient::connect_unix(&path).await.unwrap();
+        client.send_message("hello").await.unwrap();
+        assert_eq!(client.receive_response().await.unwrap(), "hello from 0.0.0.0:0");
+
+        server.shutdown().await;
+        task.await.unwrap().unwrap();
+        drop(server);
+        let _ = std::fs::remove_file(&path);
+    }
+}
@@ -1,5 +1,53 @@
+mod access;
+mod bandwidth;
+mod builder;
+mod certificate;
 mod client;
+mod codec;
+mod error;
+mod framed;
+mod heartbeat;
+mod hooks;
+mod json;
+mod limits;
+mod listener;
+mod multiplex;
+mod options;
+mod outbox;
+mod proxy;
+mod reconnect;
+mod registry;
+mod rooms;
+mod router;
+mod session;
+mod stats;
 mod server;
+#[cfg(any(target_os = "linux", target_os = "android"))]
+mod sockopt;
+mod stream;
+mod timeout;
 
+pub(crate) use listener::Accepted;
+pub use access::AccessList;
+pub use bandwidth::BandwidthLimits;
+pub use builder::TcpServerBuilder;
+pub use certificate::PeerCertificate;
 pub use client::TcpClient;
-pub use server::TcpServer;
\ No newline at end of file
+pub use codec::{Endianness, LengthPrefixedCodec, LineCodec, ServerCodec};
+pub use error::TcpServerError;
+pub use framed::FramedClient;
+pub use heartbeat::Heartbeat;
+pub use json::JsonConnection;
+pub use limits::{LimitAction, PeerLimits};
+pub use multiplex::MultiplexedClient;
+pub use options::SocketOptions;
+pub use outbox::QueuePolicy;
+pub use proxy::{ProxyProtocol, TcpProxy};
+pub use reconnect::{ReconnectEvent, ReconnectingTcpClient};
+pub use rooms::Rooms;
+pub use router::MessageRouter;
+pub use server::TcpServer;
+pub use session::Session;
+pub use stats::{ConnectionInfo, ConnectionStats, TcpServerStats};
+pub use stream::ServerStream;
+pub use timeout::{TimeoutError, TimeoutOperation};
\ No newline at end of file
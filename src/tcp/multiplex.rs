@@ -0,0 +1,169 @@
+use super::{client::TcpClient, codec::LengthPrefixedCodec};
+use crate::error::AarambhNetError;
+use bytes::{Buf, BufMut, Bytes, BytesMut};
+use futures_util::{SinkExt, StreamExt};
+use std::{collections::HashMap, io};
+use tokio::sync::{mpsc, oneshot};
+use tokio_util::codec::Framed;
+
+/// How many calls may wait to be written before `call` waits too.
+const CALL_QUEUE: usize = 64;
+
+/// The size of the request identifier heading every frame.
+const ID_SIZE: usize = 8;
+
+type Reply = oneshot::Sender<io::Result<Bytes>>;
+
+/// The `MultiplexedClient` struct sends many requests over one connection at once and matches each
+/// response to its request, however the server orders them. Every frame of a `LengthPrefixedCodec`
+/// starts with the 8-byte big-endian identifier of the request, followed by the payload; the server
+/// answers with a frame starting with the same identifier. It is returned by
+/// `TcpClient::into_multiplexed`, and clones share the connection.
+///
+/// A task owns the connection: once it fails or the server closes it, the calls waiting for a
+/// response and the later calls fail. Responses to calls that were dropped are discarded.
+///
+/// # Properties:
+///
+/// * `calls`: Hands the requests to the task owning the connection.
+/// * `target`: The address of the server, reported in errors.
+#[derive(Clone)]
+pub struct MultiplexedClient {
+    calls: mpsc::Sender<(Bytes, Reply)>,
+    target: String,
+}
+
+impl MultiplexedClient {
+    pub(crate) fn new(client: TcpClient, codec: LengthPrefixedCodec, target: impl Into<String>) -> Self {
+        let (calls, queued) = mpsc::channel(CALL_QUEUE);
+        tokio::spawn(drive(Framed::new(client, codec), queued));
+        MultiplexedClient { calls, target: target.into() }
+    }
+
+    /// The function `call` sends `request` and waits for the response the server tagged with its
+    /// identifier, while other calls go on over the same connection.
+    ///
+    /// # Returns:
+    ///
+    /// The `call` function returns a `Result` containing the payload of the response, or an error if
+    /// the connection failed or was closed before the response arrived.
+    pub async fn call(&self, request: impl Into<Bytes>) -> Result<Bytes, AarambhNetError> {
+        let fail = |e: io::Error| AarambhNetError::new("call", e).with_target(&self.target);
+        let (reply, response) = oneshot::channel();
+        self.calls.send((request.into(), reply)).await.map_err(|_| fail(closed()))?;
+        response.await.map_err(|_| fail(closed()))?.map_err(fail)
+    }
+
+    /// The function `is_closed` returns whether the connection is gone, failing every further call.
+    pub fn is_closed(&self) -> bool {
+        self.calls.is_closed()
+    }
+}
+
+/// The function `drive` writes the queued calls with a new identifier each and hands the responses
+/// to the calls waiting for them, until the connection ends or every `MultiplexedClient` is dropped.
+async fn drive(mut framed: Framed<TcpClient, LengthPrefixedCodec>, mut queued: mpsc::Receiver<(Bytes, Reply)>) {
+    let mut pending: HashMap<u64, Reply> = HashMap::new();
+    let mut next_id = 0u64;
+    let error = loop {
+        tokio::select! {
+            call = queued.recv() => {
+                let Some((request, reply)) = call else { return };
+                next_id += 1;
+                let mut frame = BytesMut::with_capacity(ID_SIZE + request.len());
+                frame.put_u64(next_id);
+                frame.put_slice(&request);
+                if let Err(e) = framed.send(&frame[..]).await {
+                    let _ = reply.send(Err(io::Error::new(e.kind(), e.to_string())));
+                    break e;
+                }
+                pending.retain(|_, reply| !reply.is_closed());
+                pending.insert(next_id, reply);
+            }
+            frame = framed.next() => match frame {
+                Some(Ok(frame)) if frame.len() < ID_SIZE => {
+                    tracing::warn!(len = frame.len(), "dropped a response too short for a request identifier");
+                }
+                Some(Ok(mut frame)) => {
+                    let id = frame.get_u64();
+                    match pending.remove(&id) {
+                        Some(reply) => {
+                            let _ = reply.send(Ok(frame.freeze()));
+                        }
+                        None => tracing::debug!(id, "dropped the response to an unknown or cancelled call"),
+                    }
+                }
+                Some(Err(e)) => break e,
+                None => break closed(),
+            },
+        }
+    };
+    queued.close();
+    let mut replies: Vec<Reply> = pending.into_values().collect();
+    while let Ok((_, reply)) = queued.try_recv() {
+        replies.push(reply);
+    }
+    for reply in replies {
+        let _ = reply.send(Err(io::Error::new(error.kind(), error.to_string())));
+    }
+}
+
+/// The function `closed` returns the error of a call over a closed connection.
+fn closed() -> io::Error {
+    io::Error::new(io::ErrorKind::UnexpectedEof, "the connection is closed")
+}
+
+#[cfg(test)]
+mod test {
+    use crate::tcp::{LengthPrefixedCodec, TcpClient, TcpServer};
+    use bytes::{BufMut, BytesMut};
+    use futures_util::{future::join_all, SinkExt, StreamExt};
+    use std::sync::Arc;
+
+    #[tokio::test]
+    async fn test_multiplexed_calls() {
+        let server = Arc::new(TcpServer::bind("127.0.0.1:0").await.unwrap());
+        let addr = server.local_addr().unwrap().to_string();
+        let task = tokio::spawn({
+            let server = server.clone();
+            async move {
+                // Answers every three requests in reverse order, with the payload in upper case.
+                let served = server.run_framed(LengthPrefixedCodec::new(), |mut frames, _| async move {
+                    let mut batch = Vec::new();
+                    while let Some(Ok(frame)) = frames.next().await {
+                        batch.push(frame);
+                        if batch.len() < 3 {
+                            continue;
+                        }
+                        for request in batch.drain(..).rev() {
+                            let mut response = BytesMut::new();
+                            response.put_slice(&request[..8]);
+                            response.put_slice(request[8..].to_ascii_uppercase().as_slice());
+                            let _ = frames.send(response.freeze()).await;
+                        }
+                    }
+                });
+                served.await.map_err(|e| e.to_string())
+            }
+        });
+
+        let client = TcpClient::connect(&addr).await.unwrap().into_multiplexed(LengthPrefixedCodec::new());
+        let calls = ["alpha", "beta", "gamma"].map(|request| {
+            let client = client.clone();
+            async move { client.call(request).await.unwrap() }
+        });
+        assert_eq!(join_all(calls).await, ["ALPHA", "BETA", "GAMMA"]);
+
+        // Calls waiting when the connection goes away fail instead of hanging.
+        let waiting = tokio::spawn({
+            let client = client.clone();
+            async move { client.call("delta").await }
+        });
+        tokio::task::yield_now().await;
+        server.shutdown().await;
+        task.await.unwrap().unwrap();
+        assert!(waiting.await.unwrap().is_err());
+        assert!(client.call("epsilon").await.is_err());
+        assert!(client.is_closed());
+    }
+}
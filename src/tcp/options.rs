@@ -0,0 +1,139 @@
+use socket2::{SockRef, TcpKeepalive};
+use std::{io, time::Duration};
+use tokio::net::TcpStream;
+
+/// The `SocketOptions` struct gathers the options set on TCP sockets, by `TcpServer::socket_options`
+/// on every accepted connection and by `TcpClient::apply_socket_options` on a client. Options left
+/// unset keep the defaults of the system.
+///
+/// # Properties:
+///
+/// * `nodelay`: Whether `TCP_NODELAY` disables Nagle's algorithm, sending small writes at once.
+/// * `keepalive`: The idle time before `SO_KEEPALIVE` probes are sent, if enabled.
+/// * `keepalive_interval`: The time between unanswered probes.
+/// * `keepalive_retries`: How many unanswered probes close the connection.
+/// * `linger`: The `SO_LINGER` timeout, `Some(None)` turning lingering off.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct SocketOptions {
+    nodelay: Option<bool>,
+    keepalive: Option<Duration>,
+    keepalive_interval: Option<Duration>,
+    keepalive_retries: Option<u32>,
+    linger: Option<Option<Duration>>,
+}
+
+impl SocketOptions {
+    /// The function `new` creates options keeping every default of the system.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The function `nodelay` sets `TCP_NODELAY`, sending small writes at once instead of batching
+    /// them, at the cost of more packets.
+    pub fn nodelay(mut self, nodelay: bool) -> Self {
+        self.nodelay = Some(nodelay);
+        self
+    }
+
+    /// The function `keepalive` enables `SO_KEEPALIVE`, probing the peer once the connection has been
+    /// idle for `time`, so connections to peers that vanished are eventually closed.
+    pub fn keepalive(mut self, time: Duration) -> Self {
+        self.keepalive = Some(time);
+        self
+    }
+
+    /// The function `keepalive_interval` sets the time between unanswered keepalive probes, on the
+    /// platforms supporting it.
+    pub fn keepalive_interval(mut self, interval: Duration) -> Self {
+        self.keepalive_interval = Some(interval);
+        self
+    }
+
+    /// The function `keepalive_retries` sets how many unanswered keepalive probes close the
+    /// connection, on the platforms supporting it.
+    pub fn keepalive_retries(mut self, retries: u32) -> Self {
+        self.keepalive_retries = Some(retries);
+        self
+    }
+
+    /// The function `linger` sets `SO_LINGER`: with `Some(timeout)`, closing the socket waits up to
+    /// `timeout` for unsent data, and `Some(Duration::ZERO)` resets the connection instead; with
+    /// `None`, closing returns at once and the system sends the data in the background.
+    pub fn linger(mut self, linger: Option<Duration>) -> Self {
+        self.linger = Some(linger);
+        self
+    }
+
+    /// The function `apply` sets the options on `stream`.
+    pub(crate) fn apply(&self, stream: &TcpStream) -> io::Result<()> {
+        let socket = SockRef::from(stream);
+        if let Some(nodelay) = self.nodelay {
+            socket.set_nodelay(nodelay)?;
+        }
+        if let Some(time) = self.keepalive {
+            socket.set_tcp_keepalive(&self.tcp_keepalive(time))?;
+        }
+        if let Some(linger) = self.linger {
+            socket.set_linger(linger)?;
+        }
+        Ok(())
+    }
+
+    #[cfg(any(target_os = "linux", target_os = "android", target_os = "macos", target_os = "freebsd"))]
+    fn tcp_keepalive(&self, time: Duration) -> TcpKeepalive {
+        let mut keepalive = TcpKeepalive::new().with_time(time);
+        if let Some(interval) = self.keepalive_interval {
+            keepalive = keepalive.with_interval(interval);
+        }
+        if let Some(retries) = self.keepalive_retries {
+            keepalive = keepalive.with_retries(retries);
+        }
+        keepalive
+    }
+
+    #[cfg(not(any(target_os = "linux", target_os = "android", target_os = "macos", target_os = "freebsd")))]
+    fn tcp_keepalive(&self, time: Duration) -> TcpKeepalive {
+        TcpKeepalive::new().with_time(time)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::tcp::{LineCodec, SocketOptions, TcpClient, TcpServer};
+    use futures_util::{SinkExt, StreamExt};
+    use socket2::SockRef;
+    use std::{sync::Arc, time::Duration};
+
+    #[tokio::test]
+    async fn test_socket_options() {
+        let options = SocketOptions::new()
+            .nodelay(true)
+            .keepalive(Duration::from_secs(30))
+            .keepalive_interval(Duration::from_secs(5))
+            .keepalive_retries(3)
+            .linger(Some(Duration::from_secs(1)));
+        let server = TcpServer::bind("127.0.0.1:0").await.unwrap().socket_options(options.clone());
+        let server = Arc::new(server);
+        let addr = server.local_addr().unwrap().to_string();
+        let task = tokio::spawn({
+            let server = server.clone();
+            async move {
+                let served = server.run_framed(LineCodec::new(), |mut lines, _| async move {
+                    let socket = SockRef::from(lines.get_ref().tcp_stream().unwrap());
+                    let applied = (socket.nodelay().unwrap(), socket.keepalive().unwrap(), socket.linger().unwrap());
+                    let _ = lines.send(format!("{:?}", applied)).await;
+                });
+                served.await.map_err(|e| e.to_string())
+            }
+        });
+
+        let client = TcpClient::connect(&addr).await.unwrap();
+        client.apply_socket_options(&options).unwrap();
+        let mut lines = client.into_framed(LineCodec::new());
+        let expected = format!("{:?}", (true, true, Some(Duration::from_secs(1))));
+        assert_eq!(lines.next().await.unwrap().unwrap(), expected);
+
+        server.shutdown().await;
+        task.await.unwrap().unwrap();
+    }
+}
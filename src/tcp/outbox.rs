@@ -0,0 +1,218 @@
+use bytes::Bytes;
+use futures_util::task::AtomicWaker;
+use std::{
+    collections::VecDeque,
+    io,
+    sync::{Mutex, OnceLock},
+};
+use tokio::sync::Notify;
+
+/// How many messages the write queue of a connection holds by default.
+pub(crate) const DEFAULT_QUEUE_CAPACITY: usize = 1024;
+
+/// The `QueuePolicy` enum is what a `TcpServer` does with a message pushed to a connection whose
+/// write queue is full, because its client reads slower than the server broadcasts.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum QueuePolicy {
+    /// Keep the message out of the queue until the client catches up: `broadcast` skips the
+    /// connection, while `broadcast_with_backpressure` waits for room.
+    Block,
+    /// Discard the oldest message of the queue to make room.
+    DropOldest,
+    /// Close the connection.
+    Disconnect,
+}
+
+/// The `Outbox` struct holds the data queued for a connection, written by its `ServerStream` while
+/// the handler is not in the middle of a write.
+///
+/// # Properties:
+///
+/// * `queue`: The data waiting to be written, oldest first.
+/// * `capacity`: How many messages `queue` holds at most.
+/// * `policy`: What happens to messages pushed to a full queue.
+/// * `closed`: Why the outbox refuses data, once the connection is gone or must be closed.
+/// * `space`: Notified when data leaves the queue or the outbox closes.
+/// * `waker`: Wakes the connection task when data is queued.
+#[derive(Debug)]
+pub(crate) struct Outbox {
+    queue: Mutex<VecDeque<Bytes>>,
+    capacity: usize,
+    policy: QueuePolicy,
+    closed: OnceLock<&'static str>,
+    space: Notify,
+    pub(crate) waker: AtomicWaker,
+}
+
+impl Outbox {
+    pub(crate) fn new(capacity: usize, policy: QueuePolicy) -> Self {
+        Outbox {
+            queue: Mutex::new(VecDeque::new()),
+            capacity: capacity.max(1),
+            policy,
+            closed: OnceLock::new(),
+            space: Notify::new(),
+            waker: AtomicWaker::new(),
+        }
+    }
+
+    /// The function `push` queues `data` according to the policy of the outbox, without waiting.
+    ///
+    /// # Returns:
+    ///
+    /// The `push` function returns whether `data` was queued.
+    pub(crate) fn push(&self, data: Bytes) -> bool {
+        self.try_push(&data).unwrap_or(false)
+    }
+
+    /// The function `push_with_backpressure` queues `data` like `push`, but waits for room in a full
+    /// queue with the `QueuePolicy::Block` policy, until the connection closes.
+    pub(crate) async fn push_with_backpressure(&self, data: Bytes) -> bool {
+        loop {
+            let space = self.space.notified();
+            if let Some(queued) = self.try_push(&data) {
+                return queued;
+            }
+            space.await;
+        }
+    }
+
+    /// The function `try_push` queues `data`, or returns `None` if the queue is full and blocks.
+    fn try_push(&self, data: &Bytes) -> Option<bool> {
+        if self.closed.get().is_some() {
+            return Some(false);
+        }
+        let mut queue = self.queue.lock().unwrap();
+        if queue.len() >= self.capacity {
+            match self.policy {
+                QueuePolicy::Block => return None,
+                QueuePolicy::DropOldest => {
+                    queue.pop_front();
+                }
+                QueuePolicy::Disconnect => {
+                    drop(queue);
+                    self.close("write queue full");
+                    return Some(false);
+                }
+            }
+        }
+        queue.push_back(data.clone());
+        drop(queue);
+        self.waker.wake();
+        Some(true)
+    }
+
+    /// The function `pop` takes the oldest data queued, if any.
+    pub(crate) fn pop(&self) -> Option<Bytes> {
+        let data = self.queue.lock().unwrap().pop_front();
+        if data.is_some() {
+            self.space.notify_waiters();
+        }
+        data
+    }
+
+    /// The function `close` refuses further data for `reason`, unless already closed, and wakes the
+    /// connection and the pushes waiting.
+    pub(crate) fn close(&self, reason: &'static str) {
+        let _ = self.closed.set(reason);
+        self.waker.wake();
+        self.space.notify_waiters();
+    }
+
+    /// The function `check_open` returns a `ConnectionAborted` error with the reason the outbox was
+    /// closed, which ends the connection.
+    pub(crate) fn check_open(&self) -> io::Result<()> {
+        match self.closed.get() {
+            Some(reason) => Err(io::Error::new(io::ErrorKind::ConnectionAborted, *reason)),
+            None => Ok(()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{
+        server_handle::ServerHandle,
+        tcp::{LineCodec, QueuePolicy, TcpServer},
+    };
+    use futures_util::{SinkExt, StreamExt};
+    use std::{sync::Arc, time::Duration};
+    use tokio::{net::TcpStream, sync::Notify};
+    use tokio_util::codec::Framed;
+
+    type Lines = Framed<TcpStream, LineCodec>;
+
+    /// Serves a client whose handler stops reading after its first line until `release` is notified,
+    /// so broadcasts pile up in its write queue of two messages.
+    async fn hold(policy: QueuePolicy) -> (Arc<TcpServer>, Arc<Notify>, Lines, ServerHandle<()>) {
+        let server = Arc::new(TcpServer::bind("127.0.0.1:0").await.unwrap().write_queue(2, policy));
+        let release = Arc::new(Notify::new());
+        let handle = server.spawn_shared({
+            let release = release.clone();
+            |server| async move {
+                let served = server.run_framed(LineCodec::new(), move |mut lines, _| {
+                    let release = release.clone();
+                    async move {
+                        while let Some(Ok(line)) = lines.next().await {
+                            let _ = lines.send(line).await;
+                            release.notified().await;
+                        }
+                    }
+                });
+                served.await
+            }
+        });
+        let task = handle.unwrap();
+        let mut lines = Framed::new(TcpStream::connect(task.addr()).await.unwrap(), LineCodec::new());
+        lines.send("hold").await.unwrap();
+        assert_eq!(lines.next().await.unwrap().unwrap(), "hold");
+        (server, release, lines, task)
+    }
+
+    #[tokio::test]
+    async fn test_write_queue_policies() {
+        // The oldest message makes room for the newest.
+        let (server, release, mut lines, task) = hold(QueuePolicy::DropOldest).await;
+        for message in ["1\n", "2\n", "3\n"] {
+            assert_eq!(server.broadcast_message(message), 1);
+        }
+        release.notify_one();
+        lines.send("ping").await.unwrap();
+        for expected in ["2", "3", "ping"] {
+            assert_eq!(lines.next().await.unwrap().unwrap(), expected);
+        }
+        server.shutdown().await;
+        release.notify_one();
+        task.await.unwrap();
+
+        // The overflowing connection is closed.
+        let (server, release, mut lines, task) = hold(QueuePolicy::Disconnect).await;
+        assert_eq!(server.broadcast_message("1\n"), 1);
+        assert_eq!(server.broadcast_message("2\n"), 1);
+        assert_eq!(server.broadcast_message("3\n"), 0);
+        release.notify_one();
+        assert!(matches!(lines.next().await, None | Some(Err(_))));
+        server.shutdown().await;
+        task.await.unwrap();
+
+        // A full queue is skipped by `broadcast` and waited for by `broadcast_with_backpressure`.
+        let (server, release, mut lines, task) = hold(QueuePolicy::Block).await;
+        assert_eq!(server.broadcast_message("1\n"), 1);
+        assert_eq!(server.broadcast_message("2\n"), 1);
+        assert_eq!(server.broadcast_message("skipped\n"), 0);
+        let waiting = tokio::spawn({
+            let server = server.clone();
+            async move { server.broadcast_with_backpressure(b"3\n").await }
+        });
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        assert!(!waiting.is_finished());
+        release.notify_one();
+        assert_eq!(waiting.await.unwrap(), 1);
+        for expected in ["1", "2", "3"] {
+            assert_eq!(lines.next().await.unwrap().unwrap(), expected);
+        }
+        server.shutdown().await;
+        release.notify_one();
+        task.await.unwrap();
+    }
+}
@@ -0,0 +1,204 @@
+use crate::{
+    http::BoxError,
+    socks::{self, TargetAddr},
+};
+use base64::{engine::general_purpose::STANDARD, Engine};
+use std::io;
+use tokio::{
+    io::{AsyncReadExt, AsyncWriteExt},
+    net::TcpStream,
+};
+
+/// The longest response header an HTTP proxy may send to a `CONNECT` request.
+const MAX_CONNECT_RESPONSE: usize = 8 * 1024;
+
+/// The `ProxyProtocol` enum tells how a `TcpProxy` is asked for a tunnel.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ProxyProtocol {
+    /// A SOCKS5 proxy (RFC 1928), which resolves host names itself.
+    Socks5,
+    /// An HTTP proxy tunneling with the `CONNECT` method.
+    HttpConnect,
+}
+
+/// The `TcpProxy` struct is a proxy `TcpClient::connect_via_proxy` tunnels raw TCP connections
+/// through, such as the SOCKS5 or HTTP proxy of a corporate network. Host names of the target are
+/// passed to the proxy unresolved, so they resolve on its side of the network.
+///
+/// # Properties:
+///
+/// * `protocol`: Whether the proxy speaks SOCKS5 or HTTP `CONNECT`.
+/// * `addr`: The address of the proxy, for example `"proxy.corp:1080"`.
+/// * `credentials`: The username and password to authenticate with, if any.
+#[derive(Debug, Clone)]
+pub struct TcpProxy {
+    protocol: ProxyProtocol,
+    addr: String,
+    credentials: Option<(String, String)>,
+}
+
+impl TcpProxy {
+    /// The function `socks5` creates a proxy speaking SOCKS5 at `addr`.
+    pub fn socks5(addr: &str) -> Self {
+        TcpProxy { protocol: ProxyProtocol::Socks5, addr: addr.to_string(), credentials: None }
+    }
+
+    /// The function `http_connect` creates an HTTP proxy at `addr`, tunneling with `CONNECT`.
+    pub fn http_connect(addr: &str) -> Self {
+        TcpProxy { protocol: ProxyProtocol::HttpConnect, addr: addr.to_string(), credentials: None }
+    }
+
+    /// The function `with_credentials` authenticates with `username` and `password`, as the
+    /// username/password method of SOCKS5 (RFC 1929) or a `Proxy-Authorization: Basic` header.
+    pub fn with_credentials(mut self, username: &str, password: &str) -> Self {
+        self.credentials = Some((username.to_string(), password.to_string()));
+        self
+    }
+
+    /// The function `protocol` returns whether the proxy speaks SOCKS5 or HTTP `CONNECT`.
+    pub fn protocol(&self) -> ProxyProtocol {
+        self.protocol
+    }
+
+    /// The function `addr` returns the address of the proxy.
+    pub fn addr(&self) -> &str {
+        &self.addr
+    }
+
+    /// The function `open` connects to the proxy and has it open a tunnel to `target`, a `host:port`
+    /// address.
+    pub(crate) async fn open(&self, target: &str) -> Result<TcpStream, BoxError> {
+        let target = TargetAddr::from_address(target)?;
+        let mut socket = TcpStream::connect(&self.addr).await?;
+        let credentials = self.credentials.as_ref().map(|(user, password)| (user.as_str(), password.as_str()));
+        match self.protocol {
+            ProxyProtocol::Socks5 => socks::connect_through(&mut socket, &target, credentials).await?,
+            ProxyProtocol::HttpConnect => http_connect(&mut socket, &target, credentials).await?,
+        }
+        Ok(socket)
+    }
+}
+
+/// The function `http_connect` sends a `CONNECT` request for `target` to the HTTP proxy at the
+/// other end of `socket` and checks that it opened the tunnel. The response is read byte by byte so
+/// nothing the target sends after it is consumed.
+async fn http_connect(
+    socket: &mut TcpStream,
+    target: &TargetAddr,
+    credentials: Option<(&str, &str)>,
+) -> Result<(), BoxError> {
+    let authority = match target {
+        TargetAddr::Ip(addr) => addr.to_string(),
+        TargetAddr::Domain(host, port) => format!("{}:{}", host, port),
+    };
+    let mut request = format!("CONNECT {0} HTTP/1.1\r\nHost: {0}\r\n", authority);
+    if let Some((username, password)) = credentials {
+        let token = STANDARD.encode(format!("{}:{}", username, password));
+        request.push_str(&format!("Proxy-Authorization: Basic {}\r\n", token));
+    }
+    request.push_str("\r\n");
+    socket.write_all(request.as_bytes()).await?;
+
+    let mut response = Vec::new();
+    while !response.ends_with(b"\r\n\r\n") {
+        if response.len() >= MAX_CONNECT_RESPONSE {
+            return Err("the response of the proxy is too long".into());
+        }
+        response.push(socket.read_u8().await?);
+    }
+    let response = String::from_utf8_lossy(&response);
+    let status_line = response.lines().next().unwrap_or_default();
+    let mut parts = status_line.splitn(3, ' ');
+    let (version, status) = (parts.next().unwrap_or_default(), parts.next().unwrap_or_default());
+    if !version.starts_with("HTTP/1.") {
+        return Err(io::Error::new(io::ErrorKind::InvalidData, "the proxy did not answer with HTTP").into());
+    }
+    match status.parse::<u16>() {
+        Ok(200..=299) => Ok(()),
+        Ok(407) => Err(format!("the proxy requires authentication: {}", status_line).into()),
+        _ => Err(format!("the proxy refused the tunnel: {}", status_line).into()),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::TcpProxy;
+    use crate::{error::AarambhNetError, socks::Socks5Server, tcp::TcpClient, tcp::TcpServer};
+    use std::{io, sync::Arc};
+    use tokio::{
+        io::{AsyncReadExt, AsyncWriteExt},
+        net::TcpListener,
+    };
+
+    #[tokio::test]
+    async fn test_connect_via_proxy() {
+        let echo = Arc::new(TcpServer::bind("127.0.0.1:0").await.unwrap());
+        let echo_addr = echo.local_addr().unwrap();
+        let echo_task = tokio::spawn({
+            let echo = echo.clone();
+            async move { echo.run_scoped().await.map_err(|e| e.to_string()) }
+        });
+        let socks = Arc::new(Socks5Server::bind("127.0.0.1:0").await.unwrap().add_user("lab", "secret"));
+        let socks_addr = socks.local_addr().unwrap().to_string();
+        let socks_task = tokio::spawn({
+            let socks = socks.clone();
+            async move { socks.run().await.map_err(|e| e.to_string()) }
+        });
+
+        // The host name travels to the proxy, which resolves it.
+        let proxy = TcpProxy::socks5(&socks_addr).with_credentials("lab", "secret");
+        let target = format!("localhost:{}", echo_addr.port());
+        let mut client = TcpClient::connect_via_proxy(&proxy, &target).await.unwrap();
+        client.send_message("through socks").await.unwrap();
+        assert_eq!(client.receive_response().await.unwrap(), "through socks");
+        let wrong = TcpProxy::socks5(&socks_addr).with_credentials("lab", "wrong");
+        assert!(TcpClient::connect_via_proxy(&wrong, &target).await.is_err());
+        let closed = std::net::TcpListener::bind("127.0.0.1:0").unwrap().local_addr().unwrap().to_string();
+        let Err(err) = TcpClient::connect_via_proxy(&proxy, &closed).await else { panic!("reached a closed port") };
+        let kind = err.downcast_ref::<AarambhNetError>().unwrap().cause::<io::Error>().map(io::Error::kind);
+        assert_eq!(kind, Some(io::ErrorKind::ConnectionRefused));
+
+        // An HTTP proxy gets the credentials in a Basic header, then relays the tunnel.
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let http_addr = listener.local_addr().unwrap().to_string();
+        let http_proxy = tokio::spawn(async move {
+            let (mut socket, _) = listener.accept().await.unwrap();
+            let mut request = Vec::new();
+            while !request.ends_with(b"\r\n\r\n") {
+                request.push(socket.read_u8().await.unwrap());
+            }
+            let request = String::from_utf8(request).unwrap();
+            socket.write_all(b"HTTP/1.1 200 Connection established\r\n\r\nwelcome").await.unwrap();
+            let mut upstream = tokio::net::TcpStream::connect(echo_addr).await.unwrap();
+            let _ = tokio::io::copy_bidirectional(&mut socket, &mut upstream).await;
+            request
+        });
+        let proxy = TcpProxy::http_connect(&http_addr).with_credentials("lab", "secret");
+        let mut client = TcpClient::connect_via_proxy(&proxy, &echo_addr.to_string()).await.unwrap();
+        assert_eq!(client.receive_response().await.unwrap(), "welcome");
+        client.send_message("through http").await.unwrap();
+        assert_eq!(client.receive_response().await.unwrap(), "through http");
+        drop(client);
+        let request = http_proxy.await.unwrap();
+        assert!(request.starts_with(&format!("CONNECT {0} HTTP/1.1\r\nHost: {0}\r\n", echo_addr)));
+        assert!(request.contains("Proxy-Authorization: Basic bGFiOnNlY3JldA==\r\n"));
+
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let http_addr = listener.local_addr().unwrap().to_string();
+        tokio::spawn(async move {
+            let (mut socket, _) = listener.accept().await.unwrap();
+            let mut request = Vec::new();
+            while !request.ends_with(b"\r\n\r\n") {
+                request.push(socket.read_u8().await.unwrap());
+            }
+            socket.write_all(b"HTTP/1.1 407 Proxy Authentication Required\r\n\r\n").await.unwrap();
+        });
+        let refused = TcpClient::connect_via_proxy(&TcpProxy::http_connect(&http_addr), "example.com:443").await;
+        assert!(refused.is_err_and(|e| e.to_string().contains("407")));
+
+        socks.shutdown().await;
+        socks_task.await.unwrap().unwrap();
+        echo.shutdown().await;
+        echo_task.await.unwrap().unwrap();
+    }
+}
@@ -0,0 +1,327 @@
+use super::client::TcpClient;
+use crate::error::AarambhNetError;
+use std::{error::Error, future::Future, io, pin::Pin, time::Duration};
+use tokio::time::sleep;
+use tokio_util::codec::Decoder;
+
+type Handshake = Box<dyn Fn(TcpClient) -> Pin<Box<dyn Future<Output = Result<TcpClient, Box<dyn Error>>>>>>;
+type EventHook = Box<dyn Fn(&ReconnectEvent)>;
+
+/// The `ReconnectEvent` enum tells the `on_event` hook of a `ReconnectingTcpClient` how its
+/// connection is doing.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ReconnectEvent {
+    /// The connection was established, after `attempts` attempts, including the handshake.
+    Connected { attempts: u32 },
+    /// The connection was lost because of `error`.
+    Disconnected { error: String },
+    /// Attempt number `attempt` starts after `delay`, the previous one having failed with `error`.
+    Retrying { attempt: u32, delay: Duration, error: String },
+    /// The client stopped trying after `attempts` attempts, the last one failing with `error`.
+    GaveUp { attempts: u32, error: String },
+}
+
+/// The `ReconnectingTcpClient` struct is a `TcpClient` that connects again when the connection is
+/// lost, waiting `initial_backoff` before the second attempt and twice as long before each further
+/// one, up to `max_backoff`. A failed send is sent again once on the new connection. A failed read
+/// reconnects the client as well but returns its error, since the reply to a request sent on the
+/// lost connection will not arrive on the new one: the caller sends the request again.
+///
+/// # Properties:
+///
+/// * `addr`: The address of the server.
+/// * `client`: The connection, `None` until connected and once lost for good.
+/// * `initial_backoff`: How long to wait before the second attempt.
+/// * `max_backoff`: How long to wait between attempts at most.
+/// * `max_attempts`: How many attempts to make before giving up, if limited.
+/// * `handshake`: Runs on every new connection before it is used, if set with `on_connect`.
+/// * `on_event`: Tells the caller about connections, losses, retries and giving up, if set.
+/// * `reconnects`: How many times the connection was established again.
+pub struct ReconnectingTcpClient {
+    addr: String,
+    client: Option<TcpClient>,
+    initial_backoff: Duration,
+    max_backoff: Duration,
+    max_attempts: Option<u32>,
+    handshake: Option<Handshake>,
+    on_event: Option<EventHook>,
+    reconnects: u64,
+}
+
+impl ReconnectingTcpClient {
+    /// The function `new` creates a client for the server at `addr`, retrying forever with a backoff
+    /// from 100 milliseconds up to 30 seconds. Nothing is connected until `connect` or the first
+    /// send or read.
+    pub fn new(addr: &str) -> Self {
+        ReconnectingTcpClient {
+            addr: addr.to_string(),
+            client: None,
+            initial_backoff: Duration::from_millis(100),
+            max_backoff: Duration::from_secs(30),
+            max_attempts: None,
+            handshake: None,
+            on_event: None,
+            reconnects: 0,
+        }
+    }
+
+    /// The function `backoff` waits `initial` before the second attempt, doubling the wait for each
+    /// further attempt up to `max`.
+    pub fn backoff(mut self, initial: Duration, max: Duration) -> Self {
+        self.initial_backoff = initial;
+        self.max_backoff = max.max(initial);
+        self
+    }
+
+    /// The function `max_attempts` gives up connecting after `attempts` failed attempts in a row,
+    /// returning the error of the last one. The next send or read starts over.
+    pub fn max_attempts(mut self, attempts: u32) -> Self {
+        self.max_attempts = Some(attempts.max(1));
+        self
+    }
+
+    /// The function `on_connect` sets a handshake replayed on every new connection before it is
+    /// used, such as logging in or subscribing. The handshake receives the connection and returns it
+    /// once done; a failed handshake counts as a failed attempt.
+    pub fn on_connect<F, Fut>(mut self, handshake: F) -> Self
+    where
+        F: Fn(TcpClient) -> Fut + 'static,
+        Fut: Future<Output = Result<TcpClient, Box<dyn Error>>> + 'static,
+    {
+        self.handshake = Some(Box::new(move |client| Box::pin(handshake(client))));
+        self
+    }
+
+    /// The function `on_event` sets a hook called with every `ReconnectEvent`.
+    pub fn on_event<F: Fn(&ReconnectEvent) + 'static>(mut self, hook: F) -> Self {
+        self.on_event = Some(Box::new(hook));
+        self
+    }
+
+    /// The function `connect` establishes the connection at once, retrying like after a loss.
+    ///
+    /// # Returns:
+    ///
+    /// The `connect` function returns a `Result` containing the connected client, or the error of
+    /// the last attempt once `max_attempts` is reached.
+    pub async fn connect(mut self) -> Result<Self, Box<dyn Error>> {
+        self.connected().await?;
+        Ok(self)
+    }
+
+    /// The function `is_connected` returns whether the client holds a connection, which may still
+    /// turn out to be lost on the next send or read.
+    pub fn is_connected(&self) -> bool {
+        self.client.is_some()
+    }
+
+    /// The function `reconnects` returns how many times the connection was established again after
+    /// being lost.
+    pub fn reconnects(&self) -> u64 {
+        self.reconnects
+    }
+
+    /// The function `send_message` sends `message`, reconnecting and sending it again once if the
+    /// connection was lost.
+    ///
+    /// # Returns:
+    ///
+    /// The `send_message` function returns a `Result` that is an error if the client could not
+    /// reconnect, or if sending failed on the new connection too.
+    pub async fn send_message(&mut self, message: &str) -> Result<(), Box<dyn Error>> {
+        let e = match self.connected().await?.send_message(message).await {
+            Ok(()) => return Ok(()),
+            Err(e) => e,
+        };
+        self.reconnect(e.as_ref()).await?;
+        let sent = self.connected().await?.send_message(message).await;
+        if sent.is_err() {
+            self.client = None;
+        }
+        sent
+    }
+
+    /// The function `receive_response` reads data from the server like `TcpClient::receive_response`.
+    ///
+    /// # Returns:
+    ///
+    /// The `receive_response` function returns a `Result` containing the data, or the error that
+    /// lost the connection, an `UnexpectedEof` if the server closed it, once the client reconnected.
+    pub async fn receive_response(&mut self) -> Result<String, Box<dyn Error>> {
+        let e = match self.connected().await?.receive_response().await {
+            Ok(response) if !response.is_empty() => return Ok(response),
+            Ok(_) => self.closed(),
+            Err(e) => e,
+        };
+        self.reconnect(e.as_ref()).await?;
+        Err(e)
+    }
+
+    /// The function `receive_frame` reads a message decoded by `codec` like
+    /// `TcpClient::receive_frame`, reconnecting like `receive_response` when the connection is lost.
+    pub async fn receive_frame<C: Decoder>(&mut self, codec: &mut C) -> Result<C::Item, Box<dyn Error>>
+    where
+        C::Error: Into<Box<dyn Error + Send + Sync>>,
+    {
+        let e = match self.connected().await?.receive_frame(codec).await {
+            Ok(Some(frame)) => return Ok(frame),
+            Ok(None) => self.closed(),
+            Err(e) => e,
+        };
+        self.reconnect(e.as_ref()).await?;
+        Err(e)
+    }
+
+    /// The function `closed` returns the error of a connection the server closed.
+    fn closed(&self) -> Box<dyn Error> {
+        let e = io::Error::new(io::ErrorKind::UnexpectedEof, "the server closed the connection");
+        Box::new(AarambhNetError::new("receive", e).with_target(&self.addr))
+    }
+
+    /// The function `connected` returns the connection, establishing it first if there is none.
+    async fn connected(&mut self) -> Result<&mut TcpClient, Box<dyn Error>> {
+        let client = match self.client.take() {
+            Some(client) => client,
+            None => self.establish().await?,
+        };
+        Ok(self.client.insert(client))
+    }
+
+    /// The function `reconnect` replaces the connection lost because of `cause`.
+    async fn reconnect(&mut self, cause: &dyn Error) -> Result<(), Box<dyn Error>> {
+        self.client = None;
+        self.emit(ReconnectEvent::Disconnected { error: cause.to_string() });
+        let client = self.establish().await?;
+        self.reconnects += 1;
+        self.client = Some(client);
+        Ok(())
+    }
+
+    /// The function `establish` connects and runs the handshake until an attempt succeeds or
+    /// `max_attempts` is reached, backing off between attempts.
+    async fn establish(&self) -> Result<TcpClient, Box<dyn Error>> {
+        let (mut attempt, mut delay) = (0, self.initial_backoff);
+        loop {
+            attempt += 1;
+            let e = match self.open().await {
+                Ok(client) => {
+                    self.emit(ReconnectEvent::Connected { attempts: attempt });
+                    return Ok(client);
+                }
+                Err(e) => e,
+            };
+            if self.max_attempts.is_some_and(|max| attempt >= max) {
+                self.emit(ReconnectEvent::GaveUp { attempts: attempt, error: e.to_string() });
+                return Err(e);
+            }
+            self.emit(ReconnectEvent::Retrying { attempt: attempt + 1, delay, error: e.to_string() });
+            sleep(delay).await;
+            delay = (delay * 2).min(self.max_backoff);
+        }
+    }
+
+    /// The function `open` makes a single attempt at connecting and running the handshake.
+    async fn open(&self) -> Result<TcpClient, Box<dyn Error>> {
+        let client = TcpClient::connect(&self.addr).await?;
+        match &self.handshake {
+            Some(handshake) => handshake(client).await,
+            None => Ok(client),
+        }
+    }
+
+    /// The function `emit` passes `event` to the `on_event` hook, if set.
+    fn emit(&self, event: ReconnectEvent) {
+        if let Some(hook) = &self.on_event {
+            hook(&event);
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::tcp::TcpServer;
+    use std::{
+        sync::{Arc, Mutex},
+        time::Duration,
+    };
+
+    #[tokio::test]
+    async fn test_reconnecting_client() {
+        let server = Arc::new(TcpServer::bind("127.0.0.1:0").await.unwrap());
+        let addr = server.local_addr().unwrap().to_string();
+        let task = tokio::spawn({
+            let server = server.clone();
+            async move { server.run_scoped().await.map_err(|e| e.to_string()) }
+        });
+
+        let events = Arc::new(Mutex::new(Vec::new()));
+        let handshakes = Arc::new(Mutex::new(0));
+        let recorded = events.clone();
+        let counted = handshakes.clone();
+        let mut client = ReconnectingTcpClient::new(&addr)
+            .backoff(Duration::from_millis(10), Duration::from_millis(50))
+            .on_connect(move |mut client| {
+                *counted.lock().unwrap() += 1;
+                async move {
+                    client.send_message("login").await?;
+                    assert_eq!(client.receive_response().await?, "login");
+                    Ok(client)
+                }
+            })
+            .on_event(move |event| recorded.lock().unwrap().push(event.clone()))
+            .connect()
+            .await
+            .unwrap();
+        client.send_message("one").await.unwrap();
+        assert_eq!(client.receive_response().await.unwrap(), "one");
+
+        // The server restarts on the same address: the read that finds the connection closed
+        // reconnects, replaying the handshake, and the next request goes through.
+        server.shutdown_with_timeout(Duration::from_millis(100)).await;
+        task.await.unwrap().unwrap();
+        drop(server);
+        let server = Arc::new(TcpServer::bind(&addr).await.unwrap());
+        let task = tokio::spawn({
+            let server = server.clone();
+            async move { server.run_scoped().await.map_err(|e| e.to_string()) }
+        });
+        assert!(client.receive_response().await.is_err());
+        assert!(client.is_connected());
+        client.send_message("two").await.unwrap();
+        assert_eq!(client.receive_response().await.unwrap(), "two");
+        assert_eq!((client.reconnects(), *handshakes.lock().unwrap()), (1, 2));
+        let events = events.lock().unwrap().clone();
+        assert!(matches!(
+            events.as_slice(),
+            [
+                ReconnectEvent::Connected { attempts: 1 },
+                ReconnectEvent::Disconnected { .. },
+                ReconnectEvent::Connected { attempts: 1 }
+            ]
+        ));
+        drop(client);
+        server.shutdown().await;
+        task.await.unwrap().unwrap();
+
+        // Without a server, the client gives up after the configured attempts.
+        let closed = std::net::TcpListener::bind("127.0.0.1:0").unwrap().local_addr().unwrap().to_string();
+        let events = Arc::new(Mutex::new(Vec::new()));
+        let recorded = events.clone();
+        let client = ReconnectingTcpClient::new(&closed)
+            .backoff(Duration::from_millis(5), Duration::from_millis(8))
+            .max_attempts(3)
+            .on_event(move |event| recorded.lock().unwrap().push(event.clone()));
+        assert!(client.connect().await.is_err());
+        let events = events.lock().unwrap().clone();
+        let delays: Vec<_> = events
+            .iter()
+            .filter_map(|event| match event {
+                ReconnectEvent::Retrying { attempt, delay, .. } => Some((*attempt, delay.as_millis())),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(delays, [(2, 5), (3, 8)]);
+        assert!(matches!(events.last(), Some(ReconnectEvent::GaveUp { attempts: 3, .. })));
+    }
+}
@@ -0,0 +1,272 @@
+use super::{
+    certificate::PeerCertificate,
+    hooks::Hooks,
+    outbox::{Outbox, QueuePolicy, DEFAULT_QUEUE_CAPACITY},
+    rooms::RoomTable,
+    session::Session,
+    stats::{ConnectionInfo, TcpServerStats, Traffic},
+};
+use bytes::Bytes;
+use futures_util::future::join_all;
+use std::{
+    collections::{HashMap, HashSet},
+    net::SocketAddr,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc, Mutex,
+    },
+};
+
+/// The `Registry` struct keeps the connections open on a `TcpServer`, so messages can be pushed to
+/// them from outside their handlers.
+///
+/// # Properties:
+///
+/// * `next_id`: The identifier given to the next connection, which is also the number of
+///   connections accepted.
+/// * `connections`: The session and outbox of each open connection, by identifier.
+/// * `refused`: The number of connections refused.
+/// * `traffic`: The bytes and messages of every connection.
+/// * `queue_capacity`: How many messages the outbox of a connection holds.
+/// * `queue_policy`: What happens to messages pushed to a full outbox.
+/// * `rooms`: The rooms the connections joined.
+#[derive(Debug)]
+pub(crate) struct Registry {
+    next_id: AtomicU64,
+    connections: Mutex<HashMap<u64, (Session, Arc<Outbox>)>>,
+    refused: AtomicU64,
+    traffic: Arc<Traffic>,
+    queue_capacity: usize,
+    queue_policy: QueuePolicy,
+    pub(crate) rooms: RoomTable,
+}
+
+impl Registry {
+    /// The function `new` creates a registry whose connections queue up to `queue_capacity`
+    /// messages, handling more according to `queue_policy`.
+    pub(crate) fn new(queue_capacity: usize, queue_policy: QueuePolicy) -> Self {
+        Registry {
+            next_id: AtomicU64::new(0),
+            connections: Mutex::new(HashMap::new()),
+            refused: AtomicU64::new(0),
+            traffic: Arc::default(),
+            queue_capacity,
+            queue_policy,
+            rooms: RoomTable::default(),
+        }
+    }
+
+    /// The function `register` adds a connection from `peer`, removed again when the returned
+    /// `Registration` is dropped, and calls the `on_connect` hook of `hooks`.
+    pub(crate) fn register(
+        self: &Arc<Self>,
+        peer: SocketAddr,
+        certificates: Vec<PeerCertificate>,
+        hooks: Arc<Hooks>,
+    ) -> Registration {
+        let id = self.next_id.fetch_add(1, Ordering::Relaxed);
+        let session = Session::new(id, peer, certificates, Traffic::connection(&self.traffic));
+        let outbox = Arc::new(Outbox::new(self.queue_capacity, self.queue_policy));
+        self.connections.lock().unwrap().insert(session.id(), (session.clone(), outbox.clone()));
+        hooks.connected(&session);
+        Registration { session, outbox, hooks, registry: self.clone() }
+    }
+
+    /// The function `sessions` returns the sessions of the open connections, oldest first.
+    pub(crate) fn sessions(&self) -> Vec<Session> {
+        let connections = self.connections.lock().unwrap();
+        let mut sessions: Vec<_> = connections.values().map(|(session, _)| session.clone()).collect();
+        sessions.sort_by_key(Session::id);
+        sessions
+    }
+
+    /// The function `connections` returns a snapshot of the open connections, oldest first.
+    pub(crate) fn connections(&self) -> Vec<ConnectionInfo> {
+        let info = |session: Session| ConnectionInfo {
+            id: session.id(),
+            peer_addr: session.peer_addr(),
+            stats: session.stats(),
+        };
+        self.sessions().into_iter().map(info).collect()
+    }
+
+    /// The function `disconnect` closes the outbox of connection `id`, which makes its reads and
+    /// writes fail.
+    ///
+    /// # Returns:
+    ///
+    /// The `disconnect` function returns whether the connection was open.
+    pub(crate) fn disconnect(&self, id: u64) -> bool {
+        let Some((_, outbox)) = self.connections.lock().unwrap().get(&id).cloned() else { return false };
+        outbox.close("disconnected by the server");
+        true
+    }
+
+    /// The function `refuse` counts a connection refused before it was registered.
+    pub(crate) fn refuse(&self) {
+        self.refused.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// The function `stats` returns a snapshot of the connections and traffic of the server.
+    pub(crate) fn stats(&self) -> TcpServerStats {
+        let active = self.connections.lock().unwrap().len() as u64;
+        let accepted = self.next_id.load(Ordering::Relaxed);
+        self.traffic.server_stats(accepted, active, self.refused.load(Ordering::Relaxed))
+    }
+
+    /// The function `broadcast` queues `data` on every open connection.
+    ///
+    /// # Returns:
+    ///
+    /// The `broadcast` function returns the number of connections `data` was queued on.
+    pub(crate) fn broadcast(&self, data: Bytes) -> usize {
+        let connections = self.connections.lock().unwrap();
+        connections.values().filter(|(_, outbox)| outbox.push(data.clone())).count()
+    }
+
+    /// The function `send_to` queues `data` on the open connections among `ids`.
+    ///
+    /// # Returns:
+    ///
+    /// The `send_to` function returns the number of connections `data` was queued on.
+    pub(crate) fn send_to(&self, ids: &HashSet<u64>, data: Bytes) -> usize {
+        let connections = self.connections.lock().unwrap();
+        let outboxes = ids.iter().filter_map(|id| connections.get(id));
+        outboxes.filter(|(_, outbox)| outbox.push(data.clone())).count()
+    }
+
+    /// The function `join` adds connection `id` to `room`, if it is open and not a member yet.
+    pub(crate) fn join(&self, room: &str, id: u64) -> bool {
+        let mut rooms = self.rooms.members.lock().unwrap();
+        if !self.connections.lock().unwrap().contains_key(&id) {
+            return false;
+        }
+        rooms.entry(room.to_string()).or_default().insert(id)
+    }
+
+    /// The function `broadcast_with_backpressure` queues `data` on every open connection, waiting
+    /// for room in the full outboxes that block.
+    pub(crate) async fn broadcast_with_backpressure(&self, data: Bytes) -> usize {
+        let outboxes: Vec<_> = self.connections.lock().unwrap().values().map(|(_, outbox)| outbox.clone()).collect();
+        let pushes = outboxes.iter().map(|outbox| outbox.push_with_backpressure(data.clone()));
+        join_all(pushes).await.into_iter().filter(|&queued| queued).count()
+    }
+}
+
+impl Default for Registry {
+    fn default() -> Self {
+        Self::new(DEFAULT_QUEUE_CAPACITY, QueuePolicy::DropOldest)
+    }
+}
+
+/// The `Registration` struct is the entry of a connection in a `Registry`, removed when dropped,
+/// calling the `on_disconnect` hook.
+///
+/// # Properties:
+///
+/// * `session`: The state of the connection.
+/// * `outbox`: The data queued for the connection.
+/// * `hooks`: The lifecycle callbacks of the server.
+/// * `registry`: The registry holding the entry.
+#[derive(Debug)]
+pub(crate) struct Registration {
+    pub(crate) session: Session,
+    pub(crate) outbox: Arc<Outbox>,
+    pub(crate) hooks: Arc<Hooks>,
+    registry: Arc<Registry>,
+}
+
+impl Drop for Registration {
+    fn drop(&mut self) {
+        self.registry.connections.lock().unwrap().remove(&self.session.id());
+        self.outbox.close("connection closed");
+        self.registry.rooms.leave_all(self.session.id());
+        self.hooks.disconnected(&self.session);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{
+        server_handle::ServerHandle,
+        tcp::{LineCodec, TcpServer},
+    };
+    use futures_util::{SinkExt, StreamExt};
+    use std::sync::Arc;
+    use tokio::net::TcpStream;
+    use tokio_util::codec::Framed;
+
+    /// Serves clients echoing their lines, leaving the server shared with the test.
+    async fn echo() -> (Arc<TcpServer>, ServerHandle<()>) {
+        let server = Arc::new(TcpServer::bind("127.0.0.1:0").await.unwrap());
+        let handle = server.spawn_shared(|server| async move {
+            let served = server.run_framed(LineCodec::new(), |mut lines, _| async move {
+                while let Some(Ok(line)) = lines.next().await {
+                    let _ = lines.send(line).await;
+                }
+            });
+            served.await
+        });
+        (server, handle.unwrap())
+    }
+
+    #[tokio::test]
+    async fn test_broadcast() {
+        let (server, handle) = echo().await;
+        let addr = handle.addr();
+
+        let mut clients = Vec::new();
+        for name in ["first", "second"] {
+            let mut lines = Framed::new(TcpStream::connect(addr).await.unwrap(), LineCodec::new());
+            lines.send(name).await.unwrap();
+            assert_eq!(lines.next().await.unwrap().unwrap(), name);
+            clients.push(lines);
+        }
+        assert_eq!(server.broadcast_message("news\n"), 2);
+        assert_eq!(server.broadcast(b"more\n"), 2);
+        for lines in &mut clients {
+            assert_eq!(lines.next().await.unwrap().unwrap(), "news");
+            assert_eq!(lines.next().await.unwrap().unwrap(), "more");
+        }
+
+        // Closed connections leave the registry.
+        drop(clients.pop());
+        clients[0].send("ping").await.unwrap();
+        assert_eq!(clients[0].next().await.unwrap().unwrap(), "ping");
+        tokio::time::sleep(std::time::Duration::from_millis(50)).await;
+        assert_eq!(server.broadcast_message("later\n"), 1);
+
+        server.shutdown().await;
+        handle.await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_disconnect() {
+        let (server, handle) = echo().await;
+        let addr = handle.addr();
+
+        let mut clients = Vec::new();
+        for name in ["first", "second"] {
+            let mut lines = Framed::new(TcpStream::connect(addr).await.unwrap(), LineCodec::new());
+            lines.send(name).await.unwrap();
+            assert_eq!(lines.next().await.unwrap().unwrap(), name);
+            clients.push(lines);
+        }
+        let connections = server.connections();
+        assert_eq!(connections.len(), 2);
+        assert_eq!(connections[0].peer_addr, clients[0].get_ref().local_addr().unwrap());
+        assert_eq!(connections[0].stats.bytes_received, 6);
+
+        // The kicked client is closed while the other stays connected.
+        assert!(server.disconnect(connections[0].id));
+        assert!(matches!(clients[0].next().await, None | Some(Err(_))));
+        clients[1].send("ping").await.unwrap();
+        assert_eq!(clients[1].next().await.unwrap().unwrap(), "ping");
+        tokio::time::sleep(std::time::Duration::from_millis(50)).await;
+        assert_eq!(server.connections().len(), 1);
+        assert!(!server.disconnect(u64::MAX));
+
+        server.shutdown().await;
+        handle.await.unwrap();
+    }
+}
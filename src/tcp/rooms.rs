@@ -0,0 +1,177 @@
+use super::{registry::Registry, session::Session};
+use bytes::Bytes;
+use std::{
+    collections::{HashMap, HashSet},
+    sync::{Arc, Mutex},
+};
+
+/// The `Rooms` struct groups the clients of a `TcpServer` into named rooms, so a message published
+/// to a room reaches its members only: the building block of chat rooms and game lobbies. Handlers
+/// decide who joins and leaves which room, usually on a command of the client; clients leave every
+/// room when they disconnect. A room exists while it has members. Clones share the same rooms, so
+/// they can be moved into handlers.
+///
+/// # Properties:
+///
+/// * `registry`: The connections of the server, holding the members of each room.
+#[derive(Debug, Clone)]
+pub struct Rooms {
+    registry: Arc<Registry>,
+}
+
+impl Rooms {
+    pub(crate) fn new(registry: Arc<Registry>) -> Self {
+        Rooms { registry }
+    }
+
+    /// The function `join` adds the client of `session` to `room`, creating the room if needed.
+    ///
+    /// # Returns:
+    ///
+    /// The `join` function returns whether the client joined, which it does not if it already was a
+    /// member or has disconnected.
+    pub fn join(&self, room: &str, session: &Session) -> bool {
+        self.registry.join(room, session.id())
+    }
+
+    /// The function `leave` removes the client of `session` from `room`.
+    ///
+    /// # Returns:
+    ///
+    /// The `leave` function returns whether the client was a member of the room.
+    pub fn leave(&self, room: &str, session: &Session) -> bool {
+        self.registry.rooms.leave(room, session.id())
+    }
+
+    /// The function `publish` sends `data` to every member of `room` like `TcpServer::broadcast`:
+    /// as is, queued and written between the writes of their handlers.
+    ///
+    /// # Returns:
+    ///
+    /// The `publish` function returns the number of members the data was queued on.
+    pub fn publish(&self, room: &str, data: &[u8]) -> usize {
+        let members = self.registry.rooms.members(room);
+        self.registry.send_to(&members, Bytes::copy_from_slice(data))
+    }
+
+    /// The function `publish_message` sends `message` to every member of `room` like `publish`.
+    pub fn publish_message(&self, room: &str, message: &str) -> usize {
+        self.publish(room, message.as_bytes())
+    }
+
+    /// The function `members` returns the sessions of the members of `room`, oldest first.
+    pub fn members(&self, room: &str) -> Vec<Session> {
+        let members = self.registry.rooms.members(room);
+        self.registry.sessions().into_iter().filter(|session| members.contains(&session.id())).collect()
+    }
+
+    /// The function `names` returns the names of the rooms with members, in no particular order.
+    pub fn names(&self) -> Vec<String> {
+        self.registry.rooms.members.lock().unwrap().keys().cloned().collect()
+    }
+}
+
+/// The `RoomTable` struct holds the members of each room of a `Registry`.
+///
+/// # Properties:
+///
+/// * `members`: The identifiers of the connections in each room, by name.
+#[derive(Debug, Default)]
+pub(crate) struct RoomTable {
+    pub(crate) members: Mutex<HashMap<String, HashSet<u64>>>,
+}
+
+impl RoomTable {
+    /// The function `leave` removes connection `id` from `room`, dropping the room once empty.
+    pub(crate) fn leave(&self, room: &str, id: u64) -> bool {
+        let mut rooms = self.members.lock().unwrap();
+        let Some(members) = rooms.get_mut(room) else { return false };
+        let left = members.remove(&id);
+        if members.is_empty() {
+            rooms.remove(room);
+        }
+        left
+    }
+
+    /// The function `leave_all` removes connection `id` from every room.
+    pub(crate) fn leave_all(&self, id: u64) {
+        let mut rooms = self.members.lock().unwrap();
+        rooms.retain(|_, members| {
+            members.remove(&id);
+            !members.is_empty()
+        });
+    }
+
+    /// The function `members` returns the identifiers of the connections in `room`.
+    pub(crate) fn members(&self, room: &str) -> HashSet<u64> {
+        self.members.lock().unwrap().get(room).cloned().unwrap_or_default()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::tcp::{LineCodec, TcpServer};
+    use futures_util::{SinkExt, StreamExt};
+    use std::{sync::Arc, time::Duration};
+    use tokio::net::TcpStream;
+    use tokio_util::codec::Framed;
+
+    #[tokio::test]
+    async fn test_rooms() {
+        let server = Arc::new(TcpServer::bind("127.0.0.1:0").await.unwrap());
+        let addr = server.local_addr().unwrap();
+        let rooms = server.rooms();
+        let task = tokio::spawn({
+            let (server, rooms) = (server.clone(), rooms.clone());
+            async move {
+                // Clients send `join <room>`, `leave <room>` or `<room> <message>`.
+                let served = server.run_framed(LineCodec::new(), move |mut lines, _| {
+                    let rooms = rooms.clone();
+                    async move {
+                        let session = lines.get_ref().session().clone();
+                        while let Some(Ok(line)) = lines.next().await {
+                            let (command, argument) = line.split_once(' ').unwrap_or((&line, ""));
+                            let reply = match command {
+                                "join" => rooms.join(argument, &session).to_string(),
+                                "leave" => rooms.leave(argument, &session).to_string(),
+                                room => rooms.publish_message(room, &format!("{}\n", argument)).to_string(),
+                            };
+                            let _ = lines.send(reply).await;
+                        }
+                    }
+                });
+                served.await.map_err(|e| e.to_string())
+            }
+        });
+
+        let mut clients = Vec::new();
+        for room in ["lobby", "lobby", "game"] {
+            let mut lines = Framed::new(TcpStream::connect(addr).await.unwrap(), LineCodec::new());
+            lines.send(format!("join {}", room)).await.unwrap();
+            assert_eq!(lines.next().await.unwrap().unwrap(), "true");
+            clients.push(lines);
+        }
+        let mut names = rooms.names();
+        names.sort();
+        assert_eq!(names, ["game", "lobby"]);
+        assert_eq!(rooms.members("lobby").len(), 2);
+
+        // A message to the lobby reaches its two members only.
+        clients[2].send("lobby hello").await.unwrap();
+        assert_eq!(clients[2].next().await.unwrap().unwrap(), "2");
+        assert_eq!(clients[0].next().await.unwrap().unwrap(), "hello");
+        assert_eq!(clients[1].next().await.unwrap().unwrap(), "hello");
+
+        // Members leave on request and on disconnect, and empty rooms disappear.
+        clients[0].send("leave lobby").await.unwrap();
+        assert_eq!(clients[0].next().await.unwrap().unwrap(), "true");
+        drop(clients.remove(1));
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        assert!(rooms.members("lobby").is_empty());
+        assert_eq!(rooms.names(), ["game"]);
+        assert_eq!(rooms.publish_message("lobby", "anyone?\n"), 0);
+
+        server.shutdown().await;
+        task.await.unwrap().unwrap();
+    }
+}
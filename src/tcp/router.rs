@@ -0,0 +1,166 @@
+use super::{json::JsonConnection, session::Session, stream::ServerStream};
+use crate::error::AarambhNetError;
+use serde::{de::DeserializeOwned, Serialize};
+use serde_json::{json, Value};
+use std::{collections::HashMap, fmt, future::Future, io, pin::Pin, sync::Arc};
+use tokio_util::codec::{Decoder, Encoder};
+
+type RouteFuture = Pin<Box<dyn Future<Output = Result<Value, AarambhNetError>> + Send>>;
+type Route = Arc<dyn Fn(Value, Session) -> RouteFuture + Send + Sync>;
+
+/// The `MessageRouter` struct dispatches the JSON messages of a request/response protocol to a
+/// handler per message type, served by `TcpServer::run_router`. Each message is an object naming
+/// its type in a field, `"type"` by default, such as `{"type": "add", "a": 1, "b": 2}`; the handler
+/// of the type receives the whole object decoded into its own request type and the session of the
+/// client, and returns the reply sent back. Messages without a type, of an unknown type or not
+/// matching the request type are answered with `{"error": "..."}` and the connection goes on.
+///
+/// # Properties:
+///
+/// * `field`: The field naming the type of each message.
+/// * `routes`: The handler of each message type.
+#[derive(Clone)]
+pub struct MessageRouter {
+    field: String,
+    routes: HashMap<String, Route>,
+}
+
+impl MessageRouter {
+    /// The function `new` creates a router without routes, reading the message type from the
+    /// `"type"` field.
+    pub fn new() -> Self {
+        MessageRouter { field: "type".to_string(), routes: HashMap::new() }
+    }
+
+    /// The function `type_field` sets the field naming the type of each message.
+    pub fn type_field(mut self, field: &str) -> Self {
+        self.field = field.to_string();
+        self
+    }
+
+    /// The function `route` calls `handler` for the messages of type `message_type`, replacing the
+    /// handler the type had.
+    ///
+    /// # Arguments:
+    ///
+    /// * `message_type`: The value of the type field the route answers.
+    /// * `handler`: The function called with the message, decoded as a `T`, and the session of the
+    ///   client, returning the reply.
+    pub fn route<T, H, F, R>(mut self, message_type: &str, handler: H) -> Self
+    where
+        T: DeserializeOwned + 'static,
+        H: Fn(T, Session) -> F + Send + Sync + 'static,
+        F: Future<Output = R> + Send + 'static,
+        R: Serialize,
+    {
+        let handler = Arc::new(handler);
+        let route: Route = Arc::new(move |message, session| {
+            let handler = handler.clone();
+            Box::pin(async move {
+                let request = serde_json::from_value(message).map_err(|e| AarambhNetError::new("decode", e))?;
+                let reply = handler(request, session).await;
+                serde_json::to_value(reply).map_err(|e| AarambhNetError::new("encode", e))
+            })
+        });
+        self.routes.insert(message_type.to_string(), route);
+        self
+    }
+
+    /// The function `serve` answers the messages of `connection` until the client closes it or it
+    /// fails.
+    pub(crate) async fn serve<C>(&self, mut connection: JsonConnection<ServerStream, C>)
+    where
+        C: Decoder<Error = io::Error> + for<'a> Encoder<&'a [u8], Error = io::Error>,
+        C::Item: AsRef<[u8]>,
+    {
+        let session = connection.get_ref().session().clone();
+        loop {
+            let reply = match connection.recv::<Value>().await {
+                Ok(Some(message)) => self.dispatch(message, &session).await,
+                Ok(None) => return,
+                Err(e) if e.operation() == "decode" => Err(e),
+                Err(_) => return,
+            };
+            let reply = reply.unwrap_or_else(|e| {
+                tracing::debug!(peer = %session.peer_addr(), "failed to route a message: {}", e);
+                json!({ "error": e.to_string() })
+            });
+            if connection.send(&reply).await.is_err() {
+                return;
+            }
+        }
+    }
+
+    /// The function `dispatch` calls the handler of the type of `message`.
+    async fn dispatch(&self, message: Value, session: &Session) -> Result<Value, AarambhNetError> {
+        let Some(message_type) = message.get(&self.field).and_then(Value::as_str) else {
+            return Err(AarambhNetError::new("route", format!("message without a `{}` field", self.field)));
+        };
+        let Some(route) = self.routes.get(message_type) else {
+            return Err(AarambhNetError::new("route", format!("unknown message type `{}`", message_type)));
+        };
+        route(message, session.clone()).await
+    }
+}
+
+impl Default for MessageRouter {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl fmt::Debug for MessageRouter {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("MessageRouter").field("field", &self.field).field("routes", &self.routes.keys()).finish()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::tcp::{LineCodec, MessageRouter, TcpClient, TcpServer};
+    use serde::{Deserialize, Serialize};
+    use serde_json::{json, Value};
+    use std::sync::Arc;
+
+    #[derive(Deserialize)]
+    struct Add {
+        a: i64,
+        b: i64,
+    }
+
+    #[derive(Serialize)]
+    struct Sum {
+        sum: i64,
+    }
+
+    #[tokio::test]
+    async fn test_message_router() {
+        let router = MessageRouter::new()
+            .route("add", |Add { a, b }, _| async move { Sum { sum: a + b } })
+            .route("whoami", |_: Value, session| async move { json!({ "id": session.id() }) });
+        let server = Arc::new(TcpServer::bind("127.0.0.1:0").await.unwrap());
+        let addr = server.local_addr().unwrap().to_string();
+        let task = tokio::spawn({
+            let server = server.clone();
+            async move { server.run_router(LineCodec::new(), router).await.map_err(|e| e.to_string()) }
+        });
+
+        let mut connection = TcpClient::connect(&addr).await.unwrap().into_json(LineCodec::new());
+        connection.send(&json!({ "type": "add", "a": 2, "b": 40 })).await.unwrap();
+        assert_eq!(connection.recv::<Value>().await.unwrap(), Some(json!({ "sum": 42 })));
+        connection.send(&json!({ "type": "whoami" })).await.unwrap();
+        assert_eq!(connection.recv::<Value>().await.unwrap(), Some(json!({ "id": 0 })));
+
+        // Unroutable messages are answered with an error and the connection goes on.
+        for message in [json!({ "type": "divide" }), json!({ "a": 1 }), json!({ "type": "add", "a": "one" })] {
+            connection.send(&message).await.unwrap();
+            let reply: Value = connection.recv().await.unwrap().unwrap();
+            assert!(reply["error"].is_string(), "{}", reply);
+        }
+        connection.send(&json!({ "type": "add", "a": 1, "b": 1 })).await.unwrap();
+        assert_eq!(connection.recv::<Value>().await.unwrap(), Some(json!({ "sum": 2 })));
+
+        server.shutdown().await;
+        task.await.unwrap().unwrap();
+    }
+}
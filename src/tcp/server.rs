@@ -1,21 +1,121 @@
-use std::{error::Error, sync::Arc};
+#[cfg(any(target_os = "linux", target_os = "android"))]
+use super::sockopt::set_tcp_option;
+use super::{
+    access::AccessList,
+    bandwidth::BandwidthLimits,
+    builder::TcpServerBuilder,
+    codec::ServerCodec,
+    error::TcpServerError,
+    heartbeat::Heartbeat,
+    hooks::Hooks,
+    json::JsonConnection,
+    limits::PeerLimits,
+    listener::{Accepted, Listener},
+    options::SocketOptions,
+    outbox::QueuePolicy,
+    registry::Registry,
+    rooms::Rooms,
+    router::MessageRouter,
+    session::Session,
+    stats::{ConnectionInfo, TcpServerStats},
+    stream::ServerStream,
+};
+use bytes::Bytes;
+use futures_util::FutureExt;
+use crate::{
+    error::AarambhNetError,
+    server_handle::{ServerHandle, ShutdownReport},
+    tls::TlsConfig,
+};
+#[cfg(unix)]
+use std::path::Path;
+use std::{
+    error::Error,
+    fmt,
+    future::Future,
+    io,
+    net::SocketAddr,
+    panic::AssertUnwindSafe,
+    pin::Pin,
+    sync::{Arc, Mutex},
+    time::Duration,
+};
 use tokio::{
-    io::{AsyncReadExt, AsyncWriteExt},
-    net::TcpListener, sync::Notify,
+    io::{copy_bidirectional, AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt},
+    net::{TcpListener, TcpStream},
+    sync::{mpsc, watch},
+    task::JoinSet,
+    time::{interval_at, sleep, timeout, Instant, Interval},
+};
+use tokio_rustls::TlsAcceptor;
+use tokio_util::{
+    codec::{Decoder, Encoder, Framed},
+    sync::CancellationToken,
 };
 
-/// The `TcpServer` struct represents a TCP server with a listener and a notification mechanism.
+type ConnectionFuture = Pin<Box<dyn Future<Output = ()> + Send>>;
+pub(crate) type StreamHandler = Arc<dyn Fn(ServerStream, SocketAddr) -> ConnectionFuture + Send + Sync>;
+
+/// How many bytes the connections of a `TcpServer` read at once, unless set with `buffer_size`.
+const DEFAULT_BUFFER_SIZE: usize = 1024;
+
+/// How long connections get to finish on their own after `shutdown` before they are cancelled.
+const DRAIN_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// How long `forward_to` waits for the upstream server to accept a connection.
+const FORWARD_CONNECT_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// How many accepted connections wait for the accept loop before the acceptors stop accepting.
+const ACCEPT_QUEUE: usize = 128;
+
+/// How long an acceptor waits after the first failed accept, doubling with each further failure.
+const ACCEPT_BACKOFF_MIN: Duration = Duration::from_millis(10);
+
+/// The longest an acceptor waits between failed accepts.
+const ACCEPT_BACKOFF_MAX: Duration = Duration::from_secs(1);
+
+/// The `TcpServer` struct represents a TCP server with a listener and a shutdown signal.
 /// 
 /// # Properties:
 /// 
-/// * `listener`: The `listener` property in the `TcpServer` struct is of type `TcpListener`. It is used
-/// to listen for incoming TCP connections on a specific port.
-/// * `notify`: The `notify` property in the `TcpServer` struct is of type `Arc<Notify>`. `Arc` stands
-/// for "Atomically Reference Counted" and is a thread-safe reference-counting pointer. `Notify` is a
-/// synchronization primitive that allows threads to wait until a condition is satisfied
+/// * `listeners`: The `listeners` property in the `TcpServer` struct holds the TCP listeners, one per
+///   address of `bind_all` or acceptor of `bind_reuseport`, or the Unix domain socket of `bind_unix`.
+///   They are used to listen for incoming connections, each on its own task.
+/// * `shutdown`: Cancelled by `shutdown`, stopping every accept loop of the server and telling its
+///   connections to finish.
+/// * `tls`: The acceptor terminating TLS, if the server encrypts its connections.
+/// * `limits`: The connection and message limits of each peer address, if any.
+/// * `access`: The peer addresses accepted, if restricted.
+/// * `idle_timeout`: How long a connection may go without sending data before it is closed, if
+///   limited.
+/// * `heartbeat`: The heartbeats detecting dead peers, if enabled.
+/// * `bandwidth`: The bytes per second the connections may read and write, if limited.
+/// * `options`: The options set on every accepted socket, if any.
+/// * `connections`: The connections open on the server, receiving broadcasts.
+/// * `hooks`: The callbacks run as connections open, close and fail.
+/// * `buffer_size`: How many bytes a connection reads at once.
+/// * `stats_interval`: How often the statistics of the server are logged, if they are.
+/// * `max_connections`: How many connections may be open at once, if limited.
+/// * `handler`: The handler `run` and `run_scoped` call for each connection instead of echoing, if
+///   set.
+/// * `draining`: How the connections are drained on shutdown, and how many were.
 pub struct TcpServer {
-    listener: TcpListener,
-    notify: Arc<Notify>,
+    listeners: Vec<Arc<Listener>>,
+    shutdown: CancellationToken,
+    tls: Option<TlsAcceptor>,
+    limits: Option<Arc<PeerLimits>>,
+    access: Option<AccessList>,
+    idle_timeout: Option<Duration>,
+    heartbeat: Option<Heartbeat>,
+    bandwidth: Option<BandwidthLimits>,
+    options: Option<SocketOptions>,
+    connections: Arc<Registry>,
+    hooks: Hooks,
+    buffer_size: usize,
+    stats_interval: Option<Duration>,
+    max_connections: Option<usize>,
+    handler: Option<StreamHandler>,
+    draining: Draining,
 }
 
 impl TcpServer {
@@ -25,68 +125,1079 @@ impl TcpServer {
     /// # Arguments:
     /// 
     /// * `addr`: The `addr` parameter in the `bind` function is a reference to a string that represents
-    /// the address to which the TCP listener will bind. This address typically includes the IP address
-    /// and port number on which the server will listen for incoming connections.
+    ///   the address to which the TCP listener will bind. This address typically includes the IP address
+    ///   and port number on which the server will listen for incoming connections.
     /// 
     /// # Returns:
     /// 
     /// The `bind` function returns a `Result` containing an instance of `TcpServer` if the operation is
-    /// successful, or a boxed `dyn Error` trait object if an error occurs during the process.
-    pub async fn bind(addr: &str) -> Result<Self, Box<dyn Error>> {
-        let listener = TcpListener::bind(addr).await?;
-        let notify = Arc::new(Notify::new());
-        Ok(TcpServer { listener, notify })
+    /// successful, or a `TcpServerError::Bind` if the address cannot be bound.
+    pub async fn bind(addr: &str) -> Result<Self, TcpServerError> {
+        let listener = TcpListener::bind(addr).await.map_err(|source| bind_error(addr, source))?;
+        Ok(Self::new(vec![Listener::Tcp(listener)]))
+    }
+
+    /// The function `bind_all` creates a server listening on every address of `addrs`, for example
+    /// `["0.0.0.0:9000", "[::]:9000"]` to serve IPv4 and IPv6 clients on the same port. The
+    /// listeners share one `run` and one `shutdown`, and IPv6 listeners only accept IPv6 clients, so
+    /// they do not clash with IPv4 listeners on the same port. An address resolving to several IP
+    /// addresses, like `localhost:9000`, is bound on each of them.
+    ///
+    /// # Returns:
+    ///
+    /// The `bind_all` function returns a `Result` containing the `TcpServer`, or an error if `addrs`
+    /// is empty or one of the addresses cannot be bound.
+    pub async fn bind_all(addrs: &[&str]) -> Result<Self, TcpServerError> {
+        let mut listeners = Vec::new();
+        for addr in addrs {
+            let bind = async {
+                for resolved in tokio::net::lookup_host(addr).await? {
+                    listeners.push(Listener::bind_tcp(resolved)?);
+                }
+                io::Result::Ok(())
+            };
+            bind.await.map_err(|source| bind_error(addr, source))?;
+        }
+        if listeners.is_empty() {
+            return Err(bind_error("", io::Error::new(io::ErrorKind::InvalidInput, "no address to bind")));
+        }
+        Ok(Self::new(listeners))
+    }
+
+    /// The function `bind_reuseport` creates a server with `acceptors` listeners on `addr`, all bound
+    /// with `SO_REUSEPORT`, for workloads opening connections faster than one task can accept them.
+    /// The kernel spreads incoming connections between the listeners, each accepting on its own
+    /// task; one acceptor per core, as given by `std::thread::available_parallelism`, is a good
+    /// start. When `addr` has port 0, every listener shares the port assigned to the first.
+    ///
+    /// # Returns:
+    ///
+    /// The `bind_reuseport` function returns a `Result` containing the `TcpServer`, or an error if
+    /// `addr` cannot be resolved or bound.
+    #[cfg(any(target_os = "linux", target_os = "android"))]
+    pub async fn bind_reuseport(addr: &str, acceptors: usize) -> Result<Self, TcpServerError> {
+        let bind = async {
+            let mut resolved = tokio::net::lookup_host(addr).await?;
+            let Some(mut local) = resolved.next() else {
+                return Err(io::Error::new(io::ErrorKind::InvalidInput, "the address resolves to nothing"));
+            };
+            let mut listeners = Vec::new();
+            for _ in 0..acceptors.max(1) {
+                let listener = Listener::bind_reuse_port(local)?;
+                local = listener.tcp()?.local_addr()?;
+                listeners.push(listener);
+            }
+            Ok(listeners)
+        };
+        let listeners = bind.await.map_err(|source| bind_error(addr, source))?;
+        Ok(Self::new(listeners))
+    }
+
+    /// The function `bind_unix` creates a server listening on the Unix domain socket at `path`, for
+    /// local clients connecting with `TcpClient::connect_unix`. A socket file left at `path` by a
+    /// previous server is replaced, unless a server still listens on it. The server runs and shuts
+    /// down like one bound with `bind`, but its clients have no address: handlers and sessions see
+    /// the unspecified address `0.0.0.0:0`, and access lists, peer limits, TLS and socket options,
+    /// which apply to TCP, are skipped.
+    ///
+    /// # Returns:
+    ///
+    /// The `bind_unix` function returns a `Result` containing the `TcpServer`, or an error if the
+    /// socket cannot be created.
+    #[cfg(unix)]
+    pub fn bind_unix(path: impl AsRef<Path>) -> Result<Self, TcpServerError> {
+        let path = path.as_ref();
+        let listener = Listener::bind_unix(path).map_err(|source| bind_error(path.display(), source))?;
+        Ok(Self::new(vec![listener]))
+    }
+
+    /// The function `builder` returns a `TcpServerBuilder` gathering the addresses and settings of
+    /// a server before binding it.
+    pub fn builder() -> TcpServerBuilder {
+        TcpServerBuilder::default()
+    }
+
+    fn new(listeners: Vec<Listener>) -> Self {
+        TcpServer {
+            listeners: listeners.into_iter().map(Arc::new).collect(),
+            shutdown: CancellationToken::new(),
+            tls: None,
+            limits: None,
+            access: None,
+            idle_timeout: None,
+            heartbeat: None,
+            bandwidth: None,
+            options: None,
+            connections: Arc::default(),
+            hooks: Hooks::default(),
+            buffer_size: DEFAULT_BUFFER_SIZE,
+            stats_interval: None,
+            max_connections: None,
+            handler: None,
+            draining: Draining::new(),
+        }
+    }
+
+    /// The function `with_tls` makes the server encrypt its connections with the certificate of
+    /// `tls`, verifying client certificates if it was configured with `TlsConfig::with_client_auth`.
+    /// The TLS handshake runs on the task of each connection before its handler is called; clients
+    /// failing it are dropped. It applies to `run_scoped`, `run_framed` and `run_json`.
+    ///
+    /// # Returns:
+    ///
+    /// The `with_tls` function returns a `Result` containing the `TcpServer`, or an error if the
+    /// rustls configuration cannot be built.
+    pub fn with_tls(self, tls: &TlsConfig) -> Result<Self, Box<dyn Error>> {
+        let acceptor = tls.acceptor(Vec::new()).map_err(|e| AarambhNetError::new("configure TLS", e))?;
+        Ok(self.with_tls_acceptor(acceptor))
+    }
+
+    /// The function `with_tls_acceptor` makes the server encrypt its connections with `acceptor`.
+    pub(crate) fn with_tls_acceptor(mut self, acceptor: TlsAcceptor) -> Self {
+        self.tls = Some(acceptor);
+        self
+    }
+
+    /// The function `with_hooks` replaces the lifecycle callbacks of the server with `hooks`.
+    pub(crate) fn with_hooks(mut self, hooks: Hooks) -> Self {
+        self.hooks = hooks;
+        self
+    }
+
+    /// The function `peer_limits` limits the connections and messages of each peer address with
+    /// `limits`.
+    pub fn peer_limits(mut self, limits: PeerLimits) -> Self {
+        self.limits = Some(Arc::new(limits));
+        self
+    }
+
+    /// The function `access_list` restricts the peer addresses the server accepts to those `access`
+    /// allows. Rules changed through a clone of `access` apply to the connections accepted next.
+    pub fn access_list(mut self, access: AccessList) -> Self {
+        self.access = Some(access);
+        self
+    }
+
+    /// The function `idle_timeout` closes connections whose reads waited `timeout` for data,
+    /// logging each one, so clients that went away without closing do not pile up. Time the handler
+    /// spends between reads does not count. It applies to `run`, `run_scoped`, `run_framed` and
+    /// `run_json`.
+    pub fn idle_timeout(mut self, timeout: Duration) -> Self {
+        self.idle_timeout = Some(timeout);
+        self
+    }
+
+    /// The function `heartbeat` pings the clients of `run_scoped`, `run_framed`, `run_json` and
+    /// `run_router` once their connection has been waiting for data for a while, closing those
+    /// that stop answering and reporting them to the `on_error` hook with a `TimedOut` error. The
+    /// framed connections also answer the pings of clients and hide the heartbeat frames from their
+    /// handlers.
+    pub fn heartbeat(mut self, heartbeat: Heartbeat) -> Self {
+        self.heartbeat = Some(heartbeat);
+        self
+    }
+
+    /// The function `bandwidth` keeps the bytes read and written by the connections of `run`,
+    /// `run_scoped`, `run_framed`, `run_json` and `run_router` within `limits`.
+    pub fn bandwidth(mut self, limits: BandwidthLimits) -> Self {
+        self.bandwidth = Some(limits);
+        self
+    }
+
+    /// The function `socket_options` sets `options`, such as keepalive, `TCP_NODELAY` and
+    /// `SO_LINGER`, on every accepted socket before its handler runs.
+    pub fn socket_options(mut self, options: SocketOptions) -> Self {
+        self.options = Some(options);
+        self
+    }
+
+    /// The function `buffer_size` sets how many bytes a connection reads at once, 1024 by default:
+    /// the size of the chunks `run` and `run_scoped` echo, and the initial read buffer of the
+    /// connections of `run_framed` and `run_json`, which grows until a whole frame fits.
+    pub fn buffer_size(mut self, size: usize) -> Self {
+        self.buffer_size = size.max(1);
+        self
+    }
+
+    /// The function `write_queue` sets how many broadcast messages each connection queues while its
+    /// client is slow to read them, 1024 by default, and what happens to the messages pushed to a
+    /// full queue, `QueuePolicy::DropOldest` by default.
+    pub fn write_queue(mut self, capacity: usize, policy: QueuePolicy) -> Self {
+        self.connections = Arc::new(Registry::new(capacity, policy));
+        self
+    }
+
+    /// The function `log_stats` logs the statistics returned by `stats` every `interval` through
+    /// `tracing`, while the server runs.
+    pub fn log_stats(mut self, interval: Duration) -> Self {
+        self.stats_interval = Some(interval);
+        self
+    }
+
+    /// The function `max_connections` refuses new connections while `max` are open, dropping them
+    /// at accept time like a denied address.
+    pub fn max_connections(mut self, max: usize) -> Self {
+        self.max_connections = Some(max);
+        self
+    }
+
+    /// The function `handler` makes `run` and `run_scoped` call `handler` with each connection and
+    /// the address of its peer, instead of echoing data back.
+    pub fn handler<H, F>(mut self, handler: H) -> Self
+    where
+        H: Fn(ServerStream, SocketAddr) -> F + Send + Sync + 'static,
+        F: Future<Output = ()> + Send + 'static,
+    {
+        self.handler = Some(Arc::new(move |stream, peer| Box::pin(handler(stream, peer))));
+        self
+    }
+
+    /// The function `on_connect` calls `hook` with the session of every connection once it is
+    /// established, after the TLS handshake if any, before its handler runs.
+    pub fn on_connect<F: Fn(&Session) + Send + Sync + 'static>(mut self, hook: F) -> Self {
+        self.hooks.connect = Some(Arc::new(hook));
+        self
+    }
+
+    /// The function `on_disconnect` calls `hook` with the session of every connection once it is
+    /// closed, whether its handler returned, failed or was cancelled at shutdown.
+    pub fn on_disconnect<F: Fn(&Session) + Send + Sync + 'static>(mut self, hook: F) -> Self {
+        self.hooks.disconnect = Some(Arc::new(hook));
+        self
+    }
+
+    /// The function `on_error` calls `hook` with the error whenever a connection or an accept fails:
+    /// a failed TLS handshake, an idle timeout, an error reading or writing, a panicking handler or a
+    /// failed accept. The server keeps running after each of them.
+    pub fn on_error<F: Fn(&TcpServerError) + Send + Sync + 'static>(mut self, hook: F) -> Self {
+        self.hooks.error = Some(Arc::new(hook));
+        self
     }
 
     /// The function `run` is an asynchronous Rust function that continuously accepts incoming
-    /// connections, reads data from the socket, echoes it back, and can be shut down upon notification.
+    /// connections, reads data from the socket and echoes it back, until `shutdown` is called. A
+    /// server configured with `handler` passes the connections to it instead.
     /// 
     /// # Returns:
     /// 
-    /// The `run` function is returning a `Result` with an empty tuple `()` on success or a `Box`
-    /// containing any type that implements the `Error` trait on failure.
-    pub async fn run(&self) -> Result<(), Box<dyn Error>> {
+    /// The `run` function is returning a `Result` with an empty tuple `()` once the server has shut
+    /// down and its connections have been drained. The failures of single connections do not stop
+    /// the server; they are passed to the `on_error` hook instead.
+    pub async fn run(&self) -> Result<(), TcpServerError> {
+        self.run_scoped().await
+    }
+
+    /// The function `run_scoped` accepts connections and echoes data back, or calls the `handler`
+    /// of the server, like `run`. Every
+    /// connection task belongs to a `JoinSet` owned by the call instead of being detached, and
+    /// finished connections are reaped as the server runs.
+    ///
+    /// # Returns:
+    ///
+    /// The `run_scoped` function returns `Ok(())` after `shutdown` was called, once every connection
+    /// task has finished or been cancelled. Dropping the returned future cancels the connection tasks
+    /// as well, so none outlives the call.
+    pub async fn run_scoped(&self) -> Result<(), TcpServerError> {
+        match self.handler.clone() {
+            Some(handler) => self.serve_streams(move |stream, peer| handler(stream, peer)).await,
+            None => {
+                let buffer_size = self.buffer_size;
+                self.serve_streams(move |stream, _| echo(stream, buffer_size)).await
+            }
+        }
+    }
+
+    /// The function `run_framed` accepts connections and hands each one to `handler` framed by
+    /// `codec`, as a `Stream` of decoded messages and a `Sink` of messages to encode, on a task of a
+    /// `JoinSet` owned by the call. Every connection gets its own clone of `codec`. Shutting down
+    /// cancels the connections like `run_scoped`.
+    ///
+    /// # Arguments:
+    ///
+    /// * `codec`: The framing of the protocol, for example a `LineCodec`.
+    /// * `handler`: The function serving a connection, called with the framed socket and the
+    ///   address of the peer.
+    pub async fn run_framed<C, H, F>(&self, codec: C, handler: H) -> Result<(), TcpServerError>
+    where
+        C: Decoder + Clone + Send + Sync + 'static,
+        H: Fn(Framed<ServerStream, ServerCodec<C>>, SocketAddr) -> F + Send + Sync + 'static,
+        F: Future<Output = ()> + Send + 'static,
+    {
+        let (limits, buffer_size) = (self.limits.clone(), self.buffer_size);
+        self.serve_streams(move |stream, peer| {
+            let limits = limits.as_ref().filter(|_| stream.tcp_stream().is_some());
+            let limiter = limits.and_then(|limits| limits.message_limiter(peer, stream.read_pause()));
+            let codec = ServerCodec::new(codec.clone(), limiter, stream.session().traffic().clone())
+                .with_heartbeat(stream.heartbeat_frames());
+            handler(Framed::with_capacity(stream, codec, buffer_size), peer)
+        })
+        .await
+    }
+
+    /// The function `run_json` accepts connections and hands each one to `handler` as a
+    /// `JsonConnection` exchanging JSON messages framed by `framing`, a `LineCodec` or a
+    /// `LengthPrefixedCodec`. Connections run and are cancelled like with `run_framed`.
+    pub async fn run_json<F, H, Fut>(&self, framing: F, handler: H) -> Result<(), TcpServerError>
+    where
+        F: Decoder<Error = io::Error> + for<'a> Encoder<&'a [u8], Error = io::Error> + Clone + Send + Sync + 'static,
+        F::Item: AsRef<[u8]>,
+        H: Fn(JsonConnection<ServerStream, ServerCodec<F>>, SocketAddr) -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = ()> + Send + 'static,
+    {
+        let (limits, buffer_size) = (self.limits.clone(), self.buffer_size);
+        self.serve_streams(move |stream, peer| {
+            let limits = limits.as_ref().filter(|_| stream.tcp_stream().is_some());
+            let limiter = limits.and_then(|limits| limits.message_limiter(peer, stream.read_pause()));
+            let framing = ServerCodec::new(framing.clone(), limiter, stream.session().traffic().clone())
+                .with_heartbeat(stream.heartbeat_frames());
+            handler(JsonConnection::new(Framed::with_capacity(stream, framing, buffer_size), peer.to_string()), peer)
+        })
+        .await
+    }
+
+    /// The function `run_router` serves a request/response protocol of JSON messages framed by
+    /// `framing`, answering each message with the handler `router` has for its type. Connections run
+    /// and are cancelled like with `run_json`.
+    pub async fn run_router<F>(&self, framing: F, router: MessageRouter) -> Result<(), TcpServerError>
+    where
+        F: Decoder<Error = io::Error> + for<'a> Encoder<&'a [u8], Error = io::Error> + Clone + Send + Sync + 'static,
+        F::Item: AsRef<[u8]>,
+    {
+        let router = Arc::new(router);
+        self.run_json(framing, move |connection, _| {
+            let router = router.clone();
+            async move { router.serve(connection).await }
+        })
+        .await
+    }
+
+    /// The function `forward_to` relays every connection to the server at `upstream`, copying the
+    /// bytes both ways until both sides are done, which makes the server a TCP tunnel or port
+    /// forwarder. TLS, access lists and limits apply to the clients as with the other runs. A
+    /// client whose upstream connection is not established within 10 seconds is closed and
+    /// reported to the `on_error` hook. The bytes relayed are counted by `stats` and
+    /// `Session::stats`: those from the client as received, those from upstream as sent.
+    pub async fn forward_to(&self, upstream: &str) -> Result<(), TcpServerError> {
+        let (upstream, hooks) = (Arc::<str>::from(upstream), Arc::new(self.hooks.clone()));
+        self.serve_streams(move |mut stream, peer| {
+            let (upstream, hooks) = (upstream.clone(), hooks.clone());
+            async move {
+                let connected = match timeout(FORWARD_CONNECT_TIMEOUT, TcpStream::connect(&*upstream)).await {
+                    Ok(connected) => connected,
+                    Err(_) => Err(io::Error::new(io::ErrorKind::TimedOut, "connecting to upstream timed out")),
+                };
+                let mut relayed = match connected {
+                    Ok(relayed) => relayed,
+                    Err(source) => {
+                        tracing::debug!(%peer, %upstream, "failed to connect to upstream: {}", source);
+                        hooks.failed(&TcpServerError::Connection { peer, source });
+                        return;
+                    }
+                };
+                match copy_bidirectional(&mut stream, &mut relayed).await {
+                    Ok((sent, received)) => tracing::debug!(%peer, %upstream, sent, received, "relay closed"),
+                    Err(e) => tracing::debug!(%peer, %upstream, "relay failed: {}", e),
+                }
+            }
+        })
+        .await
+    }
+
+    /// The function `spawn` runs the server with `run` on a task of its own, echoing data back.
+    ///
+    /// # Returns:
+    ///
+    /// The `spawn` function returns a `ServerHandle` to shut the server down and await it, or an
+    /// error for a server bound with `bind_unix`, which has no address.
+    pub fn spawn(self) -> io::Result<ServerHandle<()>> {
+        self.spawn_with(|server| async move { server.run().await })
+    }
+
+    /// The function `spawn_with` runs the server on a task of its own with `run`, given the server
+    /// to call one of its run functions on, such as
+    /// `|server| async move { server.run_framed(codec, handler).await }`.
+    ///
+    /// # Returns:
+    ///
+    /// The `spawn_with` function returns a `ServerHandle` to shut the server down and await it, or an
+    /// error for a server bound with `bind_unix`, which has no address.
+    pub fn spawn_with<R, F>(self, run: R) -> io::Result<ServerHandle<()>>
+    where
+        R: FnOnce(Arc<TcpServer>) -> F,
+        F: Future<Output = Result<(), TcpServerError>> + Send + 'static,
+    {
+        Arc::new(self).spawn_shared(run)
+    }
+
+    /// The function `spawn_shared` runs the server like `spawn_with`, leaving it shared with the
+    /// caller, which can go on using it while it runs, for example to `broadcast`.
+    pub(crate) fn spawn_shared<R, F>(self: &Arc<Self>, run: R) -> io::Result<ServerHandle<()>>
+    where
+        R: FnOnce(Arc<TcpServer>) -> F,
+        F: Future<Output = Result<(), TcpServerError>> + Send + 'static,
+    {
+        let (addr, shutdown) = (self.local_addr()?, self.shutdown.clone());
+        Ok(ServerHandle::spawn(addr, move || shutdown.cancel(), run(self.clone())))
+    }
+
+    /// The function `serve_streams` serves connections like `serve`, handing `handler` a
+    /// `ServerStream` once the TLS handshake, if any, has completed. After `shutdown`, reads from the
+    /// streams return end of file and their handlers get `DRAIN_TIMEOUT` to return before they are
+    /// cancelled.
+    async fn serve_streams<H, F>(&self, handler: H) -> Result<(), TcpServerError>
+    where
+        H: Fn(ServerStream, SocketAddr) -> F + Send + Sync + 'static,
+        F: Future<Output = ()> + Send + 'static,
+    {
+        let (handler, tls, shutdown) = (Arc::new(handler), self.tls.clone(), self.shutdown.clone());
+        let (idle_timeout, heartbeat, bandwidth) = (self.idle_timeout, self.heartbeat.clone(), self.bandwidth.clone());
+        let (connections, hooks) = (self.connections.clone(), Arc::new(self.hooks.clone()));
+        self.accept_loop(DRAIN_TIMEOUT, move |socket, peer| {
+            let (handler, tls, shutdown) = (handler.clone(), tls.clone(), shutdown.clone());
+            let (heartbeat, bandwidth) = (heartbeat.clone(), bandwidth.clone());
+            let (connections, hooks) = (connections.clone(), hooks.clone());
+            async move {
+                match ServerStream::accept(socket, peer, tls.as_ref(), shutdown, &connections, &hooks).await {
+                    Ok(stream) => {
+                        let stream = stream.with_idle_timeout(idle_timeout).with_heartbeat(heartbeat);
+                        let stream = stream.with_bandwidth(bandwidth.as_ref());
+                        handler(stream, peer).await
+                    }
+                    Err(source) => {
+                        let error = TcpServerError::TlsHandshake { peer, source };
+                        tracing::debug!("{}", error);
+                        hooks.failed(&error);
+                    }
+                }
+            }
+        })
+        .await
+    }
+
+    /// The function `serve` accepts connections and runs `handler` for each one on a task of a
+    /// `JoinSet` owned by the call, handing it the raw socket of a TCP or Unix domain socket
+    /// listener. The raw sockets cannot observe `shutdown`, so it cancels the connections still open
+    /// at once.
+    pub(crate) async fn serve<H, F>(&self, handler: H) -> Result<(), TcpServerError>
+    where
+        H: Fn(Accepted, SocketAddr) -> F,
+        F: Future<Output = ()> + Send + 'static,
+    {
+        self.accept_loop(Duration::ZERO, handler).await
+    }
+
+    /// The function `accept_loop` accepts connections, on a task per listener, until `shutdown` is
+    /// called, then waits up to `drain` for the connections still open to finish and cancels the
+    /// rest.
+    async fn accept_loop<H, F>(&self, drain: Duration, handler: H) -> Result<(), TcpServerError>
+    where
+        H: Fn(Accepted, SocketAddr) -> F,
+        F: Future<Output = ()> + Send + 'static,
+    {
+        let mut running = self.draining.start();
+        let (queue, mut accepted) = mpsc::channel(ACCEPT_QUEUE);
+        let mut acceptors = JoinSet::new();
+        let hooks = Arc::new(self.hooks.clone());
+        for listener in &self.listeners {
+            let (listener, queue, hooks) = (listener.clone(), queue.clone(), hooks.clone());
+            acceptors.spawn(async move {
+                let mut backoff = AcceptBackoff::default();
+                loop {
+                    match listener.accept().await {
+                        Ok(connection) => {
+                            backoff.succeeded();
+                            if queue.send(connection).await.is_err() {
+                                return;
+                            }
+                        }
+                        Err(e) => {
+                            let error = TcpServerError::Accept(e);
+                            tracing::debug!("{}", error);
+                            hooks.failed(&error);
+                            // Errors such as running out of file descriptors persist for a while.
+                            sleep(backoff.failed()).await;
+                        }
+                    }
+                }
+            });
+        }
+        drop(queue);
+        let mut connections = JoinSet::new();
+        let mut stats_timer = self.stats_interval.map(|period| interval_at(Instant::now() + period, period));
         loop {
             tokio::select! {
-                Ok((mut socket, _)) = self.listener.accept() => {
-                    let notify = self.notify.clone();
-                    tokio::spawn(async move {
-                        let mut buffer = vec![0; 1024]; // Buffer to read data
-                        loop {
-                            tokio::select! {
-                                result = socket.read(&mut buffer) => {
-                                    match result {
-                                        Ok(0) => return, // Connection closed
-                                        Ok(n) => {
-                                            // Echo the message back
-                                            if let Err(e) = socket.write_all(&buffer[..n]).await {
-                                                eprintln!("Failed to write to socket: {}", e);
-                                                return;
-                                            }
-                                        }
-                                        Err(_) => {
-                                            eprintln!("Failed to read from socket");
-                                            return;
-                                        }
-                                    }
-                                },
-                                // Check for shutdown signal
-                                _ = notify.notified() => {
-                                    println!("Shutting down the server...");
-                                    return; // Exit the loop if notified
-                                }
-                            }
+                biased;
+                _ = self.shutdown.cancelled() => break,
+                Some((socket, peer)) = accepted.recv() => {
+                    if let Accepted::Tcp(socket) = &socket {
+                        if self.access.as_ref().is_some_and(|access| !access.is_allowed(peer.ip())) {
+                            tracing::info!(%peer, "refused connection from a denied address");
+                            self.connections.refuse();
+                            continue;
+                        }
+                        if self.limits.as_ref().is_some_and(|limits| !limits.allow_connection(peer.ip())) {
+                            tracing::debug!("refused connection from {}: connection rate exceeded", peer);
+                            self.connections.refuse();
+                            continue;
+                        }
+                        if let Some(Err(e)) = self.options.as_ref().map(|options| options.apply(socket)) {
+                            tracing::debug!("failed to set socket options for {}: {}", peer, e);
+                        }
+                    }
+                    while connections.try_join_next().is_some() {}
+                    if self.max_connections.is_some_and(|max| connections.len() >= max) {
+                        tracing::debug!("refused connection from {}: too many connections", peer);
+                        self.connections.refuse();
+                        continue;
+                    }
+                    let (connection, hooks) = (AssertUnwindSafe(handler(socket, peer)), hooks.clone());
+                    connections.spawn(async move {
+                        if let Err(panic) = connection.catch_unwind().await {
+                            let error = TcpServerError::handler_panic(peer, panic);
+                            tracing::error!("{}", error);
+                            hooks.failed(&error);
                         }
                     });
                 }
-                // You can include other handling or a timeout here if needed
+                Some(_) = connections.join_next(), if !connections.is_empty() => {}
+                _ = tick(&mut stats_timer) => tracing::info!(stats = ?self.stats(), "TCP server statistics"),
             }
         }
+        let (started, open) = (Instant::now(), connections.len());
+        acceptors.shutdown().await;
+        let drain = self.draining.timeout.lock().unwrap().unwrap_or(drain);
+        if timeout(drain, async { while connections.join_next().await.is_some() {} }).await.is_err() {
+            tracing::debug!(remaining = connections.len(), "cancelling connections still open after the drain timeout");
+        }
+        let aborted = connections.len();
+        connections.shutdown().await;
+        running.report = Some(ShutdownReport { drained: open - aborted, aborted, elapsed: started.elapsed() });
+        Ok(())
+    }
+
+    /// The function `broadcast` sends `data` to every client connected to `run`, `run_scoped`,
+    /// `run_framed` or `run_json`, as is: it must already be framed for the protocol, for example
+    /// end with a newline for a `LineCodec`. The data is queued and written by each connection
+    /// between the writes of its handler, whenever the handler waits for a message or flushes.
+    /// Connections whose write queue is full handle it according to the policy of `write_queue`.
+    ///
+    /// # Returns:
+    ///
+    /// The `broadcast` function returns the number of connections the data was queued on.
+    pub fn broadcast(&self, data: &[u8]) -> usize {
+        self.connections.broadcast(Bytes::copy_from_slice(data))
+    }
+
+    /// The function `broadcast_with_backpressure` sends `data` to every connected client like
+    /// `broadcast`, but waits for room in the full write queues of a server configured with
+    /// `QueuePolicy::Block`, so the sender slows down to the pace of the slowest client.
+    ///
+    /// # Returns:
+    ///
+    /// The `broadcast_with_backpressure` function returns the number of connections the data was
+    /// queued on, leaving out those closed while waiting.
+    pub async fn broadcast_with_backpressure(&self, data: &[u8]) -> usize {
+        self.connections.broadcast_with_backpressure(Bytes::copy_from_slice(data)).await
+    }
+
+    /// The function `broadcast_message` sends `message` to every connected client like `broadcast`.
+    pub fn broadcast_message(&self, message: &str) -> usize {
+        self.broadcast(message.as_bytes())
     }
 
-    /// The `shutdown` function in Rust asynchronously notifies one waiting task to shut down.
+    /// The function `rooms` returns the rooms of the server, which handlers make their clients join
+    /// and leave and which messages are published to.
+    pub fn rooms(&self) -> Rooms {
+        Rooms::new(self.connections.clone())
+    }
+
+    /// The function `stats` returns a snapshot of the connections accepted, open, closed and refused
+    /// by the server and of the bytes and messages exchanged with its clients. The statistics of a
+    /// single connection are returned by `Session::stats`.
+    pub fn stats(&self) -> TcpServerStats {
+        self.connections.stats()
+    }
+
+    /// The function `sessions` returns the sessions of the clients connected to `run`, `run_scoped`,
+    /// `run_framed` or `run_json`, oldest first. They share their state with the sessions the
+    /// handlers see through `ServerStream::session`.
+    pub fn sessions(&self) -> Vec<Session> {
+        self.connections.sessions()
+    }
+
+    /// The function `connections` returns the identifier, peer address, age and traffic of every
+    /// client connected to `run`, `run_scoped`, `run_framed` or `run_json`, oldest first, for
+    /// control planes deciding which clients to `disconnect`.
+    pub fn connections(&self) -> Vec<ConnectionInfo> {
+        self.connections.connections()
+    }
+
+    /// The function `disconnect` forcibly closes the connection `id`, as listed by `connections` or
+    /// `Session::id`: its queued broadcasts are discarded and the reads and writes of its handler
+    /// fail with `ConnectionAborted` from then on, so the handler returns and the socket is dropped.
+    /// A handler waiting on something other than the connection notices on its next read or write.
+    ///
+    /// # Returns:
+    ///
+    /// The `disconnect` function returns whether the connection was open.
+    pub fn disconnect(&self, id: u64) -> bool {
+        self.connections.disconnect(id)
+    }
+
+    /// The function `enable_fast_open` turns on TCP Fast Open for the listeners, so clients that hold a
+    /// cookie from an earlier connection can send their first request in the SYN and save a round
+    /// trip. Server support must also be enabled in the `net.ipv4.tcp_fastopen` sysctl.
+    ///
+    /// # Arguments:
+    ///
+    /// * `queue_len`: The maximum number of Fast Open connections waiting to complete the handshake.
+    #[cfg(any(target_os = "linux", target_os = "android"))]
+    pub fn enable_fast_open(&self, queue_len: u32) -> Result<(), Box<dyn Error>> {
+        let queue_len = queue_len.min(i32::MAX as u32) as libc::c_int;
+        for listener in &self.listeners {
+            set_tcp_option(listener.tcp()?, libc::TCP_FASTOPEN, queue_len)
+                .map_err(|e| AarambhNetError::new("enable fast open", e))?;
+        }
+        Ok(())
+    }
+
+    /// The function `defer_accept` sets `TCP_DEFER_ACCEPT` on the listeners: connections are only
+    /// handed to `accept` once the client has sent data, or dropped if it sends nothing within
+    /// `timeout`. It saves a wakeup per connection for request/response protocols where the client
+    /// speaks first.
+    #[cfg(any(target_os = "linux", target_os = "android"))]
+    pub fn defer_accept(&self, timeout: Duration) -> Result<(), Box<dyn Error>> {
+        let seconds = timeout.as_secs().clamp(1, i32::MAX as u64) as libc::c_int;
+        for listener in &self.listeners {
+            set_tcp_option(listener.tcp()?, libc::TCP_DEFER_ACCEPT, seconds)
+                .map_err(|e| AarambhNetError::new("defer accept", e))?;
+        }
+        Ok(())
+    }
+
+    /// The function `local_addr` returns the address the server listens on, with the port the system
+    /// assigned when bound to port 0, or the first of them for a server bound with `bind_all`. It
+    /// fails for a server bound with `bind_unix`.
+    pub fn local_addr(&self) -> io::Result<SocketAddr> {
+        self.listeners[0].tcp()?.local_addr()
+    }
+
+    /// The function `local_addrs` returns every address the server listens on, in the order they
+    /// were given to `bind_all`. It fails for a server bound with `bind_unix`.
+    pub fn local_addrs(&self) -> io::Result<Vec<SocketAddr>> {
+        self.listeners.iter().map(|listener| listener.tcp()?.local_addr()).collect()
+    }
+
+    /// The `shutdown` function stops every accept loop of the server and tells its connections to
+    /// finish: their reads return end of file, so handlers can complete the messages they hold
+    /// before returning. The running `run` calls return once the connections are drained. Calling it
+    /// before `run` makes `run` return at once.
     pub async fn shutdown(&self) {
-        self.notify.notify_one();
+        self.shutdown.cancel();
     }
 
+    /// The function `shutdown_with_timeout` shuts the server down like `shutdown`, but gives the
+    /// connections `timeout` to finish, instead of the 10 seconds of `run`, before aborting those
+    /// still open, and waits until every running `run` has returned.
+    ///
+    /// # Returns:
+    ///
+    /// The `shutdown_with_timeout` function returns a `ShutdownReport` counting the connections that
+    /// finished within `timeout` and those aborted, summed over every running `run`, which drain at
+    /// the same time: `elapsed` is that of the slowest. A server that is not running reports none.
+    pub async fn shutdown_with_timeout(&self, timeout: Duration) -> ShutdownReport {
+        *self.draining.timeout.lock().unwrap() = Some(timeout);
+        self.draining.state.send_modify(|(_, report)| *report = NO_SHUTDOWN);
+        self.shutdown.cancel();
+        let mut state = self.draining.state.subscribe();
+        let report = state.wait_for(|(running, _)| *running == 0).await.map(|state| state.1);
+        report.unwrap_or(NO_SHUTDOWN)
+    }
+}
+
+/// The report of a shutdown that had no connection to drain.
+const NO_SHUTDOWN: ShutdownReport = ShutdownReport { drained: 0, aborted: 0, elapsed: Duration::ZERO };
+
+/// The `Draining` struct tells how the accept loops of a `TcpServer` drain their connections on
+/// shutdown, and how it went.
+///
+/// # Properties:
+///
+/// * `timeout`: How long the connections get to finish, if set by `shutdown_with_timeout`.
+/// * `state`: How many accept loops are running, and the sum of the reports of those that returned
+///   since `shutdown_with_timeout` was last called.
+struct Draining {
+    timeout: Mutex<Option<Duration>>,
+    state: watch::Sender<(usize, ShutdownReport)>,
+}
+
+impl Draining {
+    /// The function `new` drains with the timeout of `run`, no accept loop running.
+    fn new() -> Self {
+        Draining { timeout: Mutex::new(None), state: watch::channel((0, NO_SHUTDOWN)).0 }
+    }
+
+    /// The function `start` counts an accept loop as running until the returned guard is dropped,
+    /// which adds the report the loop left in it to the sum.
+    fn start(&self) -> RunningLoop<'_> {
+        self.state.send_modify(|(running, _)| *running += 1);
+        RunningLoop { draining: self, report: None }
+    }
+}
+
+/// The `RunningLoop` struct counts an accept loop as running, even when its future is dropped.
+struct RunningLoop<'a> {
+    draining: &'a Draining,
+    report: Option<ShutdownReport>,
+}
+
+impl Drop for RunningLoop<'_> {
+    fn drop(&mut self) {
+        let report = self.report.take();
+        self.draining.state.send_modify(|(running, total)| {
+            *running -= 1;
+            if let Some(report) = report {
+                total.drained += report.drained;
+                total.aborted += report.aborted;
+                total.elapsed = total.elapsed.max(report.elapsed);
+            }
+        });
+    }
+}
+
+/// The `AcceptBackoff` struct spaces out the accepts of a listener that keeps failing, so an error
+/// such as running out of file descriptors neither spins a core nor floods `on_error`.
+///
+/// # Properties:
+///
+/// * `delay`: The wait after the last failure, `None` since the last successful accept.
+#[derive(Default)]
+struct AcceptBackoff {
+    delay: Option<Duration>,
+}
+
+impl AcceptBackoff {
+    /// The function `failed` records a failed accept, returning how long to wait before the next.
+    fn failed(&mut self) -> Duration {
+        let delay = self.delay.map_or(ACCEPT_BACKOFF_MIN, |delay| (delay * 2).min(ACCEPT_BACKOFF_MAX));
+        self.delay = Some(delay);
+        delay
+    }
+
+    /// The function `succeeded` records a successful accept, so the next failure waits the least.
+    fn succeeded(&mut self) {
+        self.delay = None;
+    }
+}
+
+/// The function `bind_error` returns the error of binding `address`.
+fn bind_error(address: impl fmt::Display, source: io::Error) -> TcpServerError {
+    TcpServerError::Bind { address: address.to_string(), source }
+}
+
+/// The function `tick` waits for the next tick of `timer`, or forever without one.
+async fn tick(timer: &mut Option<Interval>) {
+    match timer {
+        Some(timer) => {
+            timer.tick().await;
+        }
+        None => std::future::pending().await,
+    }
+}
+
+/// The function `echo` writes everything read from `socket` back until the peer closes it, reading
+/// up to `buffer_size` bytes at once.
+async fn echo(mut socket: impl AsyncRead + AsyncWrite + Unpin, buffer_size: usize) {
+    let mut buffer = vec![0; buffer_size];
+    loop {
+        match socket.read(&mut buffer).await {
+            Ok(0) | Err(_) => return,
+            Ok(n) => {
+                if socket.write_all(&buffer[..n]).await.is_err() {
+                    return;
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::tcp::{LineCodec, TcpClient};
+    use futures_util::{SinkExt, StreamExt};
+
+    #[tokio::test]
+    async fn test_run_scoped_cancels_connections() {
+        let server = Arc::new(TcpServer::bind("127.0.0.1:0").await.unwrap());
+        let addr = server.local_addr().unwrap().to_string();
+        let running = server.clone();
+        let task = tokio::spawn(async move { running.run_scoped().await.map_err(|e| e.to_string()) });
+
+        let mut client = TcpClient::connect(&addr).await.unwrap();
+        client.send_message("hello").await.unwrap();
+        assert_eq!(client.receive_response().await.unwrap(), "hello");
+
+        server.shutdown().await;
+        task.await.unwrap().unwrap();
+        // The connection task was cancelled, so the socket is closed without waiting for the peer.
+        assert_eq!(client.receive_response().await.unwrap(), "");
+    }
+
+    #[tokio::test]
+    async fn test_bind_all() {
+        assert!(TcpServer::bind_all(&[]).await.is_err());
+        let server = Arc::new(TcpServer::bind_all(&["127.0.0.1:0", "[::1]:0"]).await.unwrap());
+        let addrs = server.local_addrs().unwrap();
+        assert!(addrs[0].is_ipv4() && addrs[1].is_ipv6());
+        let running = server.clone();
+        let task = tokio::spawn(async move { running.run_scoped().await.map_err(|e| e.to_string()) });
+
+        for addr in addrs {
+            let mut client = TcpClient::connect(&addr.to_string()).await.unwrap();
+            client.send_message("hello").await.unwrap();
+            assert_eq!(client.receive_response().await.unwrap(), "hello");
+        }
+
+        server.shutdown().await;
+        task.await.unwrap().unwrap();
+    }
+
+    #[cfg(any(target_os = "linux", target_os = "android"))]
+    #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+    async fn test_bind_reuseport() {
+        let server = Arc::new(TcpServer::bind_reuseport("127.0.0.1:0", 4).await.unwrap());
+        let addrs = server.local_addrs().unwrap();
+        assert_eq!(addrs.len(), 4);
+        assert!(addrs.iter().all(|addr| *addr == addrs[0]));
+        let running = server.clone();
+        let task = tokio::spawn(async move { running.run_scoped().await.map_err(|e| e.to_string()) });
+
+        for _ in 0..16 {
+            let mut client = TcpClient::connect(&addrs[0].to_string()).await.unwrap();
+            client.send_message("hello").await.unwrap();
+            assert_eq!(client.receive_response().await.unwrap(), "hello");
+        }
+        assert_eq!(server.stats().connections_accepted, 16);
+
+        server.shutdown().await;
+        task.await.unwrap().unwrap();
+    }
+
+    /// Serves `forwarder` relaying to `upstream` on a task.
+    fn forward(forwarder: &Arc<TcpServer>, upstream: String) -> tokio::task::JoinHandle<Result<(), String>> {
+        let forwarder = forwarder.clone();
+        tokio::spawn(async move { forwarder.forward_to(&upstream).await.map_err(|e| e.to_string()) })
+    }
+
+    #[tokio::test]
+    async fn test_forward_to() {
+        let upstream = Arc::new(TcpServer::bind("127.0.0.1:0").await.unwrap());
+        let upstream_addr = upstream.local_addr().unwrap().to_string();
+        let upstream_task = tokio::spawn({
+            let upstream = upstream.clone();
+            async move { upstream.run_scoped().await.map_err(|e| e.to_string()) }
+        });
+        let forwarder = Arc::new(TcpServer::bind("127.0.0.1:0").await.unwrap());
+        let addr = forwarder.local_addr().unwrap().to_string();
+        let task = forward(&forwarder, upstream_addr);
+
+        let mut client = TcpClient::connect(&addr).await.unwrap();
+        client.send_message("through the tunnel").await.unwrap();
+        assert_eq!(client.receive_response().await.unwrap(), "through the tunnel");
+        let stats = forwarder.sessions()[0].stats();
+        assert_eq!((stats.bytes_received, stats.bytes_sent), (18, 18));
+        drop(client);
+        forwarder.shutdown().await;
+        upstream.shutdown().await;
+        task.await.unwrap().unwrap();
+        upstream_task.await.unwrap().unwrap();
+
+        // Without an upstream server, clients are closed and reported.
+        let closed = std::net::TcpListener::bind("127.0.0.1:0").unwrap().local_addr().unwrap().to_string();
+        let failures = Arc::new(std::sync::atomic::AtomicUsize::new(0));
+        let forwarder = TcpServer::bind("127.0.0.1:0").await.unwrap().on_error({
+            let failures = failures.clone();
+            move |e| {
+                assert!(matches!(e, TcpServerError::Connection { .. }), "{}", e);
+                failures.fetch_add(1, std::sync::atomic::Ordering::SeqCst);
+            }
+        });
+        let forwarder = Arc::new(forwarder);
+        let addr = forwarder.local_addr().unwrap().to_string();
+        let task = forward(&forwarder, closed);
+        let mut client = TcpClient::connect(&addr).await.unwrap();
+        assert_eq!(client.receive_response().await.unwrap_or_default(), "");
+        assert_eq!(failures.load(std::sync::atomic::Ordering::SeqCst), 1);
+        forwarder.shutdown().await;
+        task.await.unwrap().unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_server_errors() {
+        let errors = Arc::new(std::sync::Mutex::new(Vec::new()));
+        let server = TcpServer::bind("127.0.0.1:0").await.unwrap().on_error({
+            let errors = errors.clone();
+            move |e| errors.lock().unwrap().push((e.to_string(), e.is_fatal()))
+        });
+        let server = Arc::new(server);
+        let addr = server.local_addr().unwrap().to_string();
+        let Err(taken) = TcpServer::bind(&addr).await else { panic!("bound a taken address") };
+        assert!(matches!(&taken, TcpServerError::Bind { address, .. } if *address == addr));
+        assert!(taken.is_fatal() && taken.peer().is_none());
+
+        let task = tokio::spawn({
+            let server = server.clone();
+            async move {
+                let served = server.run_framed(LineCodec::new(), |mut lines, _| async move {
+                    while let Some(Ok(line)) = lines.next().await {
+                        assert_ne!(line, "panic", "asked to panic");
+                        let _ = lines.send(line).await;
+                    }
+                });
+                served.await.map_err(|e| e.to_string())
+            }
+        });
+
+        // A panicking handler is reported and the server goes on serving.
+        let mut client = TcpClient::connect(&addr).await.unwrap();
+        client.send_message("panic\n").await.unwrap();
+        assert_eq!(client.receive_response().await.unwrap_or_default(), "");
+        let mut client = TcpClient::connect(&addr).await.unwrap();
+        client.send_message("still up\n").await.unwrap();
+        assert_eq!(client.receive_response().await.unwrap(), "still up\n");
+        let errors = errors.lock().unwrap().clone();
+        assert_eq!(errors.len(), 1);
+        assert!(errors[0].0.starts_with("handler of 127.0.0.1:"), "{}", errors[0].0);
+        assert!(errors[0].0.contains("panicked: assertion `left != right` failed: asked to panic"));
+        assert!(!errors[0].1);
+
+        server.shutdown().await;
+        task.await.unwrap().unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_shutdown_with_timeout() {
+        let server = Arc::new(TcpServer::bind("127.0.0.1:0").await.unwrap());
+        let addr = server.local_addr().unwrap();
+        // Two loops accept from the same listener; the report sums both.
+        let tasks: Vec<_> = (0..2)
+            .map(|_| {
+                let server = server.clone();
+                tokio::spawn(async move {
+                    let served = server.run_framed(LineCodec::new(), |mut lines, _| async move {
+                        while let Some(Ok(line)) = lines.next().await {
+                            let stall = line == "stall";
+                            let _ = lines.send(line).await;
+                            if stall {
+                                std::future::pending::<()>().await;
+                            }
+                        }
+                    });
+                    served.await.map_err(|e| e.to_string())
+                })
+            })
+            .collect();
+
+        let mut clients = Vec::new();
+        for line in ["hello", "stall", "hello", "stall"] {
+            let mut lines = Framed::new(TcpStream::connect(addr).await.unwrap(), LineCodec::new());
+            lines.send(line).await.unwrap();
+            assert_eq!(lines.next().await.unwrap().unwrap(), line);
+            clients.push(lines);
+        }
+
+        let report = server.shutdown_with_timeout(Duration::from_millis(100)).await;
+        assert_eq!((report.drained, report.aborted), (2, 2));
+        assert!(report.elapsed >= Duration::from_millis(100));
+        for task in tasks {
+            tokio::time::timeout(Duration::from_secs(1), task).await.unwrap().unwrap().unwrap();
+        }
+        let idle = TcpServer::bind("127.0.0.1:0").await.unwrap();
+        assert_eq!(idle.shutdown_with_timeout(Duration::from_secs(1)).await.aborted, 0);
+    }
+
+    #[tokio::test]
+    async fn test_shutdown_drains_connections() {
+        let server = Arc::new(TcpServer::bind("127.0.0.1:0").await.unwrap());
+        let addr = server.local_addr().unwrap();
+        let echo = tokio::spawn({
+            let server = server.clone();
+            async move { server.run().await.map_err(|e| e.to_string()) }
+        });
+        let framed = tokio::spawn({
+            let server = server.clone();
+            async move {
+                let served = server.run_framed(LineCodec::new(), |mut lines, _| async move {
+                    while let Some(Ok(line)) = lines.next().await {
+                        tokio::time::sleep(Duration::from_millis(200)).await;
+                        let _ = lines.send(line).await;
+                    }
+                });
+                served.await.map_err(|e| e.to_string())
+            }
+        });
+
+        // The message received before the shutdown is still answered, then the connection closes.
+        let mut lines = Framed::new(TcpStream::connect(addr).await.unwrap(), LineCodec::new());
+        lines.send("in flight").await.unwrap();
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        server.shutdown().await;
+        assert_eq!(lines.next().await.unwrap().unwrap(), "in flight");
+        assert!(lines.next().await.is_none());
+        timeout(Duration::from_secs(1), echo).await.unwrap().unwrap().unwrap();
+        timeout(Duration::from_secs(1), framed).await.unwrap().unwrap().unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_idle_timeout() {
+        let server = TcpServer::bind("127.0.0.1:0").await.unwrap().idle_timeout(Duration::from_millis(200));
+        let server = Arc::new(server);
+        let addr = server.local_addr().unwrap().to_string();
+        let running = server.clone();
+        let task = tokio::spawn(async move { running.run_scoped().await.map_err(|e| e.to_string()) });
+
+        let mut client = TcpClient::connect(&addr).await.unwrap();
+        for message in ["first", "second"] {
+            tokio::time::sleep(Duration::from_millis(100)).await;
+            client.send_message(message).await.unwrap();
+            assert_eq!(client.receive_response().await.unwrap(), message);
+        }
+        // Silence past the timeout gets the connection closed by the server.
+        let closed = timeout(Duration::from_secs(1), client.receive_response()).await.unwrap();
+        assert_eq!(closed.unwrap_or_default(), "");
+
+        server.shutdown().await;
+        task.await.unwrap().unwrap();
+    }
+
+    #[test]
+    fn test_accept_backoff() {
+        let mut backoff = AcceptBackoff::default();
+        let delays: Vec<_> = (0..9).map(|_| backoff.failed().as_millis()).collect();
+        assert_eq!(delays, [10, 20, 40, 80, 160, 320, 640, 1000, 1000]);
+        backoff.succeeded();
+        assert_eq!(backoff.failed(), ACCEPT_BACKOFF_MIN);
+    }
+
+    #[cfg(any(target_os = "linux", target_os = "android"))]
+    #[tokio::test]
+    async fn test_fast_open_and_deferred_accept() {
+        let server = Arc::new(TcpServer::bind("127.0.0.1:0").await.unwrap());
+        server.enable_fast_open(16).unwrap();
+        server.defer_accept(Duration::from_secs(1)).unwrap();
+        let addr = server.local_addr().unwrap().to_string();
+        let running = server.clone();
+        let task = tokio::spawn(async move { running.run_scoped().await.map_err(|e| e.to_string()) });
+
+        // The second connection may carry its data in the SYN using the cookie of the first.
+        for message in ["first", "second"] {
+            let mut client = TcpClient::connect_fast_open(&addr).await.unwrap();
+            client.send_message(message).await.unwrap();
+            assert_eq!(client.receive_response().await.unwrap(), message);
+        }
+
+        server.shutdown().await;
+        task.await.unwrap().unwrap();
+    }
 }
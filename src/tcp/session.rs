@@ -0,0 +1,151 @@
+use super::{
+    certificate::PeerCertificate,
+    stats::{ConnectionStats, Traffic},
+};
+use http::Extensions;
+use std::{
+    fmt,
+    net::SocketAddr,
+    sync::{Arc, Mutex},
+};
+use tokio::time::Instant;
+
+/// The `Session` struct holds the state of a connection accepted by a `TcpServer`: its identifier,
+/// the address of its peer, and values of any type stored by the handler, one per type. Clones share
+/// the same state, so what the handler stores is seen by `TcpServer::sessions`.
+///
+/// # Properties:
+///
+/// * `id`: The identifier of the connection, unique within the server.
+/// * `peer`: The address of the peer.
+/// * `certificates`: The certificate chain the peer presented during the TLS handshake, if any.
+/// * `extensions`: The values stored for the connection, by type.
+/// * `traffic`: The bytes and messages of the connection.
+/// * `connected_at`: When the connection was established.
+#[derive(Clone)]
+pub struct Session {
+    id: u64,
+    peer: SocketAddr,
+    certificates: Arc<[PeerCertificate]>,
+    extensions: Arc<Mutex<Extensions>>,
+    traffic: Arc<Traffic>,
+    connected_at: Instant,
+}
+
+impl Session {
+    pub(crate) fn new(id: u64, peer: SocketAddr, certificates: Vec<PeerCertificate>, traffic: Traffic) -> Self {
+        Session {
+            id,
+            peer,
+            certificates: certificates.into(),
+            extensions: Arc::default(),
+            traffic: Arc::new(traffic),
+            connected_at: Instant::now(),
+        }
+    }
+
+    /// The function `traffic` returns the counters of the connection.
+    pub(crate) fn traffic(&self) -> &Arc<Traffic> {
+        &self.traffic
+    }
+
+    /// The function `stats` returns a snapshot of the bytes and messages exchanged over the
+    /// connection and how long it has been open.
+    pub fn stats(&self) -> ConnectionStats {
+        self.traffic.connection_stats(self.connected_at.elapsed())
+    }
+
+    /// The function `id` returns the identifier of the connection, unique within the server.
+    pub fn id(&self) -> u64 {
+        self.id
+    }
+
+    /// The function `peer_addr` returns the address of the peer.
+    pub fn peer_addr(&self) -> SocketAddr {
+        self.peer
+    }
+
+    /// The function `peer_certificates` returns the certificate chain the peer presented, its own
+    /// certificate first, once verified by a server configured with `TlsConfig::with_client_auth`
+    /// or `TlsConfig::with_optional_client_auth`. It is empty for peers without a certificate.
+    pub fn peer_certificates(&self) -> &[PeerCertificate] {
+        &self.certificates
+    }
+
+    /// The function `insert` stores `value` for the connection, returning the value of the same type
+    /// it replaces, if any.
+    pub fn insert<T: Clone + Send + Sync + 'static>(&self, value: T) -> Option<T> {
+        self.extensions.lock().unwrap().insert(value)
+    }
+
+    /// The function `get` returns a clone of the value of type `T` stored for the connection, if any.
+    pub fn get<T: Clone + Send + Sync + 'static>(&self) -> Option<T> {
+        self.extensions.lock().unwrap().get::<T>().cloned()
+    }
+
+    /// The function `remove` removes and returns the value of type `T` stored for the connection, if
+    /// any.
+    pub fn remove<T: Clone + Send + Sync + 'static>(&self) -> Option<T> {
+        self.extensions.lock().unwrap().remove::<T>()
+    }
+}
+
+impl fmt::Debug for Session {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Session").field("id", &self.id).field("peer", &self.peer).finish_non_exhaustive()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::tcp::{LineCodec, TcpServer};
+    use futures_util::{SinkExt, StreamExt};
+    use std::sync::Arc;
+    use tokio::net::TcpStream;
+    use tokio_util::codec::Framed;
+
+    #[derive(Debug, Clone, PartialEq)]
+    struct Nickname(String);
+
+    #[tokio::test]
+    async fn test_sessions() {
+        let server = Arc::new(TcpServer::bind("127.0.0.1:0").await.unwrap());
+        let addr = server.local_addr().unwrap();
+        let task = tokio::spawn({
+            let server = server.clone();
+            async move {
+                let served = server.run_framed(LineCodec::new(), |mut lines, peer| async move {
+                    let session = lines.get_ref().session().clone();
+                    assert_eq!(session.peer_addr(), peer);
+                    while let Some(Ok(line)) = lines.next().await {
+                        let reply = match session.insert(Nickname(line.clone())) {
+                            Some(Nickname(old)) => format!("{} is now {}", old, line),
+                            None => format!("welcome {}", line),
+                        };
+                        let _ = lines.send(reply).await;
+                    }
+                });
+                served.await.map_err(|e| e.to_string())
+            }
+        });
+
+        let mut first = Framed::new(TcpStream::connect(addr).await.unwrap(), LineCodec::new());
+        first.send("ada").await.unwrap();
+        assert_eq!(first.next().await.unwrap().unwrap(), "welcome ada");
+        first.send("countess").await.unwrap();
+        assert_eq!(first.next().await.unwrap().unwrap(), "ada is now countess");
+        let mut second = Framed::new(TcpStream::connect(addr).await.unwrap(), LineCodec::new());
+        second.send("alan").await.unwrap();
+        assert_eq!(second.next().await.unwrap().unwrap(), "welcome alan");
+
+        let sessions = server.sessions();
+        assert_eq!(sessions.len(), 2);
+        assert!(sessions[0].id() < sessions[1].id());
+        assert_eq!(sessions[0].peer_addr(), first.get_ref().local_addr().unwrap());
+        let names: Vec<_> = sessions.iter().map(|session| session.get::<Nickname>().unwrap().0).collect();
+        assert_eq!(names, ["countess", "alan"]);
+
+        server.shutdown().await;
+        task.await.unwrap().unwrap();
+    }
+}
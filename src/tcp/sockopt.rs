@@ -0,0 +1,11 @@
+use std::{io, os::fd::AsRawFd};
+
+/// The function `set_tcp_option` sets the `IPPROTO_TCP` level option `name` of `socket` to `value`.
+pub(crate) fn set_tcp_option(socket: &impl AsRawFd, name: libc::c_int, value: libc::c_int) -> io::Result<()> {
+    let len = std::mem::size_of_val(&value) as libc::socklen_t;
+    let value = (&value as *const libc::c_int).cast();
+    if unsafe { libc::setsockopt(socket.as_raw_fd(), libc::IPPROTO_TCP, name, value, len) } < 0 {
+        return Err(io::Error::last_os_error());
+    }
+    Ok(())
+}
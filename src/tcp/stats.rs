@@ -0,0 +1,193 @@
+use std::{
+    net::SocketAddr,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc,
+    },
+    time::Duration,
+};
+
+/// The `Traffic` struct counts the bytes and messages of a connection, or of every connection of a
+/// server. The counters of a connection add to the totals of its server as well.
+///
+/// # Properties:
+///
+/// * `bytes_received`: The bytes read from the peers.
+/// * `bytes_sent`: The bytes written to the peers.
+/// * `messages_received`: The messages decoded by `run_framed` and `run_json`.
+/// * `messages_sent`: The messages encoded by `run_framed` and `run_json`.
+/// * `total`: The counters of the server, for the counters of a connection.
+#[derive(Debug, Default)]
+pub(crate) struct Traffic {
+    bytes_received: AtomicU64,
+    bytes_sent: AtomicU64,
+    messages_received: AtomicU64,
+    messages_sent: AtomicU64,
+    total: Option<Arc<Traffic>>,
+}
+
+impl Traffic {
+    /// The function `connection` creates the counters of a connection, adding to `total`.
+    pub(crate) fn connection(total: &Arc<Traffic>) -> Self {
+        Traffic { total: Some(total.clone()), ..Traffic::default() }
+    }
+
+    pub(crate) fn received(&self, bytes: usize) {
+        self.add(|traffic| &traffic.bytes_received, bytes as u64);
+    }
+
+    pub(crate) fn sent(&self, bytes: usize) {
+        self.add(|traffic| &traffic.bytes_sent, bytes as u64);
+    }
+
+    pub(crate) fn message_received(&self) {
+        self.add(|traffic| &traffic.messages_received, 1);
+    }
+
+    pub(crate) fn message_sent(&self) {
+        self.add(|traffic| &traffic.messages_sent, 1);
+    }
+
+    fn add(&self, counter: impl Fn(&Traffic) -> &AtomicU64, value: u64) {
+        counter(self).fetch_add(value, Ordering::Relaxed);
+        if let Some(total) = &self.total {
+            counter(total).fetch_add(value, Ordering::Relaxed);
+        }
+    }
+
+    /// The function `load` returns the bytes received and sent, then the messages received and sent.
+    fn load(&self) -> [u64; 4] {
+        [&self.bytes_received, &self.bytes_sent, &self.messages_received, &self.messages_sent]
+            .map(|counter| counter.load(Ordering::Relaxed))
+    }
+
+    /// The function `connection_stats` returns a snapshot of the counters of a connection open for
+    /// `connected_for`.
+    pub(crate) fn connection_stats(&self, connected_for: Duration) -> ConnectionStats {
+        let [bytes_received, bytes_sent, messages_received, messages_sent] = self.load();
+        ConnectionStats { bytes_received, bytes_sent, messages_received, messages_sent, connected_for }
+    }
+
+    /// The function `server_stats` returns a snapshot of the counters of a server, with the counts
+    /// of its connections.
+    pub(crate) fn server_stats(&self, accepted: u64, active: u64, refused: u64) -> TcpServerStats {
+        let [bytes_received, bytes_sent, messages_received, messages_sent] = self.load();
+        TcpServerStats {
+            connections_accepted: accepted,
+            connections_active: active,
+            connections_closed: accepted - active,
+            connections_refused: refused,
+            bytes_received,
+            bytes_sent,
+            messages_received,
+            messages_sent,
+        }
+    }
+}
+
+/// The `TcpServerStats` struct is a snapshot of the activity of a `TcpServer`, returned by
+/// `TcpServer::stats`. It covers the connections of `run`, `run_scoped`, `run_framed` and `run_json`;
+/// messages are only counted by the last two, which frame them.
+///
+/// # Properties:
+///
+/// * `connections_accepted`: The connections accepted since the server was bound.
+/// * `connections_active`: The connections open.
+/// * `connections_closed`: The connections closed.
+/// * `connections_refused`: The connections refused by the access list or the peer limits.
+/// * `bytes_received`: The bytes read from the clients.
+/// * `bytes_sent`: The bytes written to the clients, broadcasts included.
+/// * `messages_received`: The messages decoded from the clients.
+/// * `messages_sent`: The messages encoded for the clients.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct TcpServerStats {
+    pub connections_accepted: u64,
+    pub connections_active: u64,
+    pub connections_closed: u64,
+    pub connections_refused: u64,
+    pub bytes_received: u64,
+    pub bytes_sent: u64,
+    pub messages_received: u64,
+    pub messages_sent: u64,
+}
+
+/// The `ConnectionStats` struct is a snapshot of the activity of one connection, returned by
+/// `Session::stats`.
+///
+/// # Properties:
+///
+/// * `bytes_received`: The bytes read from the peer.
+/// * `bytes_sent`: The bytes written to the peer.
+/// * `messages_received`: The messages decoded from the peer.
+/// * `messages_sent`: The messages encoded for the peer.
+/// * `connected_for`: How long the connection has been open.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ConnectionStats {
+    pub bytes_received: u64,
+    pub bytes_sent: u64,
+    pub messages_received: u64,
+    pub messages_sent: u64,
+    pub connected_for: Duration,
+}
+
+/// The `ConnectionInfo` struct describes a connection open on a `TcpServer`, as returned by
+/// `TcpServer::connections`.
+///
+/// # Properties:
+///
+/// * `id`: The identifier of the connection, accepted by `TcpServer::disconnect`.
+/// * `peer_addr`: The address of the peer.
+/// * `stats`: The activity of the connection, including how long it has been open.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ConnectionInfo {
+    pub id: u64,
+    pub peer_addr: SocketAddr,
+    pub stats: ConnectionStats,
+}
+
+#[cfg(test)]
+mod test {
+    use crate::tcp::{LineCodec, TcpServer};
+    use futures_util::{SinkExt, StreamExt};
+    use std::{sync::Arc, time::Duration};
+    use tokio::net::TcpStream;
+    use tokio_util::codec::Framed;
+
+    #[tokio::test]
+    async fn test_stats() {
+        let server = Arc::new(TcpServer::bind("127.0.0.1:0").await.unwrap());
+        let addr = server.local_addr().unwrap();
+        let task = tokio::spawn({
+            let server = server.clone();
+            async move {
+                let served = server.run_framed(LineCodec::new(), |mut lines, _| async move {
+                    while let Some(Ok(line)) = lines.next().await {
+                        let _ = lines.send(line).await;
+                    }
+                });
+                served.await.map_err(|e| e.to_string())
+            }
+        });
+
+        let mut first = Framed::new(TcpStream::connect(addr).await.unwrap(), LineCodec::new());
+        for line in ["one", "two"] {
+            first.send(line).await.unwrap();
+            assert_eq!(first.next().await.unwrap().unwrap(), line);
+        }
+        let mut second = Framed::new(TcpStream::connect(addr).await.unwrap(), LineCodec::new());
+        second.send("three").await.unwrap();
+        assert_eq!(second.next().await.unwrap().unwrap(), "three");
+
+        let session = server.sessions()[0].stats();
+        assert_eq!((session.bytes_received, session.bytes_sent, session.messages_received), (8, 8, 2));
+        drop(second);
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        let stats = server.stats();
+        assert_eq!((stats.connections_accepted, stats.connections_active, stats.connections_closed), (2, 1, 1));
+        assert_eq!((stats.bytes_received, stats.bytes_sent), (14, 14));
+        assert_eq!((stats.messages_received, stats.messages_sent), (3, 3));
+
+        server.shutdown().await;
+        task.await.unwrap().unwrap();
+    }
+}
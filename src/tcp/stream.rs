@@ -0,0 +1,499 @@
+use super::{
+    bandwidth::{BandwidthLimits, Throttle},
+    certificate::PeerCertificate,
+    heartbeat::{Heartbeat, HeartbeatFrames, HeartbeatTimer},
+    hooks::Hooks,
+    listener::Accepted,
+    registry::{Registration, Registry},
+    session::Session,
+};
+use bytes::{Buf, Bytes};
+use std::{
+    future::Future,
+    io::{self, IoSlice},
+    net::SocketAddr,
+    pin::Pin,
+    sync::{Arc, Mutex},
+    task::{ready, Context, Poll},
+    time::Duration,
+};
+use tokio::{
+    io::{AsyncRead, AsyncWrite, ReadBuf},
+    net::TcpStream,
+    time::{sleep, sleep_until, timeout, Instant, Sleep},
+};
+#[cfg(unix)]
+use tokio::net::UnixStream;
+use tokio_rustls::{server::TlsStream, TlsAcceptor};
+use tokio_util::sync::{CancellationToken, WaitForCancellationFutureOwned};
+
+/// How long a client may take to complete the TLS handshake.
+const TLS_HANDSHAKE_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// The `ServerStream` struct is a connection accepted by a `TcpServer`, in plain text or encrypted
+/// with TLS when the server was configured with `TcpServer::with_tls`. It reads and writes the
+/// same either way. Once the server shuts down, reads return end of file while writes still go
+/// through, so handlers can answer the messages they hold and return.
+///
+/// # Properties:
+///
+/// * `inner`: The socket, wrapped in TLS or not.
+/// * `pause`: Stops reading until a deadline, set by the limits of the server.
+/// * `paused`: The timer of the current pause.
+/// * `shutdown`: Completes when the server shuts down.
+/// * `idle`: Closes the connection once a read waited too long for data, if set.
+/// * `heartbeat`: Pings the peer while a read waits for data, closing the connection once the
+///   peer stops answering, if set.
+/// * `throttle`: Keeps the reads and writes within the bandwidth limits of the server, if any.
+/// * `registration`: The entry of the connection in the registry of the server, holding its session
+///   and receiving broadcasts.
+/// * `outgoing`: The rest of the broadcast being written.
+pub struct ServerStream {
+    inner: Inner,
+    pause: ReadPause,
+    paused: Option<Pin<Box<Sleep>>>,
+    shutdown: Pin<Box<WaitForCancellationFutureOwned>>,
+    idle: Option<IdleTimer>,
+    heartbeat: Option<HeartbeatTimer>,
+    throttle: Option<Throttle>,
+    registration: Registration,
+    outgoing: Option<Bytes>,
+}
+
+/// The `IdleTimer` struct tracks how long a read of a `ServerStream` has been waiting for data.
+///
+/// # Properties:
+///
+/// * `timeout`: How long a read may wait.
+/// * `timer`: Fires when the current read has waited `timeout`.
+/// * `waiting`: Whether a read is waiting, with `timer` running.
+struct IdleTimer {
+    timeout: Duration,
+    timer: Pin<Box<Sleep>>,
+    waiting: bool,
+}
+
+enum Inner {
+    Plain(TcpStream),
+    Tls(Box<TlsStream<TcpStream>>),
+    #[cfg(unix)]
+    Unix(UnixStream),
+}
+
+impl ServerStream {
+    /// The function `accept` wraps an accepted `socket` from `peer`, performing the TLS handshake
+    /// first if the server has an acceptor and `socket` is a TCP socket, and adds it to `registry`
+    /// with the lifecycle `hooks` of the server. Reads stop once `shutdown` is cancelled.
+    pub(crate) async fn accept(
+        socket: Accepted,
+        peer: SocketAddr,
+        tls: Option<&TlsAcceptor>,
+        shutdown: CancellationToken,
+        registry: &Arc<Registry>,
+        hooks: &Arc<Hooks>,
+    ) -> io::Result<Self> {
+        let inner = match (socket, tls) {
+            (Accepted::Tcp(socket), Some(tls)) => match timeout(TLS_HANDSHAKE_TIMEOUT, tls.accept(socket)).await {
+                Ok(stream) => Inner::Tls(Box::new(stream?)),
+                Err(_) => return Err(io::Error::new(io::ErrorKind::TimedOut, "TLS handshake timed out")),
+            },
+            (Accepted::Tcp(socket), None) => Inner::Plain(socket),
+            #[cfg(unix)]
+            (Accepted::Unix(socket), _) => Inner::Unix(socket),
+        };
+        let certificates = match &inner {
+            Inner::Tls(stream) => stream.get_ref().1.peer_certificates().unwrap_or_default(),
+            _ => &[],
+        };
+        let certificates = certificates.iter().map(|der| PeerCertificate::from_der(der)).collect();
+        let shutdown = Box::pin(shutdown.cancelled_owned());
+        Ok(ServerStream {
+            inner,
+            pause: ReadPause::default(),
+            paused: None,
+            shutdown,
+            idle: None,
+            heartbeat: None,
+            throttle: None,
+            registration: registry.register(peer, certificates, hooks.clone()),
+            outgoing: None,
+        })
+    }
+
+    /// The function `session` returns the state of the connection, where the handler can store
+    /// values for it.
+    pub fn session(&self) -> &Session {
+        &self.registration.session
+    }
+
+    /// The function `poll_outbox` writes the data broadcast to the connection, between the writes of
+    /// the handler so they never interleave.
+    fn poll_outbox(&mut self, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        let registration = &self.registration;
+        registration.outbox.waker.register(cx.waker());
+        registration.outbox.check_open()?;
+        let mut wrote = false;
+        loop {
+            if self.outgoing.is_none() {
+                self.outgoing = registration.outbox.pop();
+            }
+            let Some(data) = &mut self.outgoing else { break };
+            if let Some(throttle) = &mut self.throttle {
+                ready!(throttle.poll_write_ready(cx));
+            }
+            let written = match &mut self.inner {
+                Inner::Plain(stream) => ready!(Pin::new(stream).poll_write(cx, data)),
+                Inner::Tls(stream) => ready!(Pin::new(stream).poll_write(cx, data)),
+                #[cfg(unix)]
+                Inner::Unix(stream) => ready!(Pin::new(stream).poll_write(cx, data)),
+            }?;
+            if written == 0 {
+                return Poll::Ready(Err(io::ErrorKind::WriteZero.into()));
+            }
+            data.advance(written);
+            self.registration.session.traffic().sent(written);
+            if let Some(throttle) = &mut self.throttle {
+                throttle.wrote(written);
+            }
+            if data.is_empty() {
+                self.outgoing = None;
+            }
+            wrote = true;
+        }
+        if wrote {
+            if let Inner::Tls(stream) = &mut self.inner {
+                ready!(Pin::new(stream).poll_flush(cx))?;
+            }
+        }
+        Poll::Ready(Ok(()))
+    }
+
+    /// The function `poll_read_data` reads from the connection, writing the data broadcast to it
+    /// first, stopping at shutdown, during pauses and after the idle timeout, and pinging the peer
+    /// while waiting.
+    fn poll_read_data(&mut self, cx: &mut Context<'_>, buf: &mut ReadBuf<'_>) -> Poll<io::Result<()>> {
+        if let Poll::Ready(Err(e)) = self.poll_outbox(cx) {
+            return Poll::Ready(Err(e));
+        }
+        if self.shutdown.as_mut().poll(cx).is_ready() {
+            return Poll::Ready(Ok(()));
+        }
+        if let Some(until) = self.pause.until() {
+            let paused = self.paused.get_or_insert_with(|| Box::pin(sleep_until(until)));
+            if paused.deadline() != until {
+                paused.as_mut().reset(until);
+            }
+            ready!(paused.as_mut().poll(cx));
+        }
+        self.paused = None;
+        let read = match &mut self.inner {
+            Inner::Plain(stream) => Pin::new(stream).poll_read(cx, buf),
+            Inner::Tls(stream) => Pin::new(stream).poll_read(cx, buf),
+            #[cfg(unix)]
+            Inner::Unix(stream) => Pin::new(stream).poll_read(cx, buf),
+        };
+        if read.is_ready() {
+            if let Some(idle) = &mut self.idle {
+                idle.waiting = false;
+            }
+            if let Some(heartbeat) = &mut self.heartbeat {
+                heartbeat.received();
+            }
+            return read;
+        }
+        if let Some(heartbeat) = &mut self.heartbeat {
+            match heartbeat.poll_ping(cx) {
+                Poll::Ready(Ok(ping)) => {
+                    self.registration.outbox.push(ping);
+                }
+                Poll::Ready(Err(e)) => {
+                    tracing::info!(peer = %self.session().peer_addr(), "closing connection after missed heartbeats");
+                    return Poll::Ready(Err(e));
+                }
+                Poll::Pending => {}
+            }
+        }
+        let Some(idle) = &mut self.idle else { return Poll::Pending };
+        if !idle.waiting {
+            idle.waiting = true;
+            idle.timer.as_mut().reset(Instant::now() + idle.timeout);
+        }
+        ready!(idle.timer.as_mut().poll(cx));
+        let timeout = idle.timeout;
+        tracing::info!(peer = %self.session().peer_addr(), ?timeout, "closing idle connection");
+        Poll::Ready(Err(io::Error::new(io::ErrorKind::TimedOut, "connection idle for too long")))
+    }
+
+    /// The function `count_sent` counts the bytes a write of the handler sent.
+    fn count_sent(&mut self, poll: Poll<io::Result<usize>>) -> Poll<io::Result<usize>> {
+        if let Poll::Ready(Ok(written)) = poll {
+            self.session().traffic().sent(written);
+            if let Some(throttle) = &mut self.throttle {
+                throttle.wrote(written);
+            }
+        }
+        self.report(poll)
+    }
+
+    /// The function `report` passes the errors of the connection to the `on_error` hook of the
+    /// server.
+    fn report<T>(&self, poll: Poll<io::Result<T>>) -> Poll<io::Result<T>> {
+        if let Poll::Ready(Err(e)) = &poll {
+            self.registration.hooks.connection_failed(self.session().peer_addr(), e);
+        }
+        poll
+    }
+
+    /// The function `with_idle_timeout` makes reads fail with `TimedOut` once they waited `timeout`
+    /// for data, if set.
+    pub(crate) fn with_idle_timeout(mut self, timeout: Option<Duration>) -> Self {
+        self.idle = timeout.map(|timeout| IdleTimer { timeout, timer: Box::pin(sleep(timeout)), waiting: false });
+        self
+    }
+
+    /// The function `with_heartbeat` makes the connection ping its peer while reads wait for data,
+    /// if set.
+    pub(crate) fn with_heartbeat(mut self, heartbeat: Option<Heartbeat>) -> Self {
+        self.heartbeat = heartbeat.map(HeartbeatTimer::new);
+        self
+    }
+
+    /// The function `with_bandwidth` keeps the reads and writes of the connection within `limits`,
+    /// if set.
+    pub(crate) fn with_bandwidth(mut self, limits: Option<&BandwidthLimits>) -> Self {
+        self.throttle = limits.and_then(BandwidthLimits::throttle);
+        self
+    }
+
+    /// The function `heartbeat_frames` returns the recognizer of the heartbeat frames of the peer,
+    /// if the connection has heartbeats.
+    pub(crate) fn heartbeat_frames(&self) -> Option<HeartbeatFrames> {
+        let heartbeat = self.heartbeat.as_ref()?;
+        Some(heartbeat.frames(self.registration.outbox.clone()))
+    }
+
+    /// The function `read_pause` returns the handle pausing the reads of the connection.
+    pub(crate) fn read_pause(&self) -> ReadPause {
+        self.pause.clone()
+    }
+
+    /// The function `is_tls` returns whether the connection is encrypted with TLS.
+    pub fn is_tls(&self) -> bool {
+        matches!(self.inner, Inner::Tls(_))
+    }
+
+    /// The function `tcp_stream` returns the TCP socket under the connection, for socket options,
+    /// or `None` for a Unix domain socket. Reading or writing it directly would bypass TLS.
+    pub fn tcp_stream(&self) -> Option<&TcpStream> {
+        match &self.inner {
+            Inner::Plain(stream) => Some(stream),
+            Inner::Tls(stream) => Some(stream.get_ref().0),
+            #[cfg(unix)]
+            Inner::Unix(_) => None,
+        }
+    }
+}
+
+impl AsyncRead for ServerStream {
+    fn poll_read(self: Pin<&mut Self>, cx: &mut Context<'_>, buf: &mut ReadBuf<'_>) -> Poll<io::Result<()>> {
+        let this = self.get_mut();
+        let filled = buf.filled().len();
+        let read = this.poll_read_data(cx, buf);
+        let received = buf.filled().len() - filled;
+        this.session().traffic().received(received);
+        let wait = this.throttle.as_ref().map_or(Duration::ZERO, |throttle| throttle.read(received));
+        if !wait.is_zero() {
+            this.pause.pause_for(wait);
+        }
+        this.report(read)
+    }
+}
+
+
+/// The `ReadPause` struct stops the reads of a `ServerStream` until a deadline, leaving the peer
+/// to block on TCP flow control.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct ReadPause(Arc<Mutex<Option<Instant>>>);
+
+impl ReadPause {
+    /// The function `pause_for` stops reading for `duration`, unless a longer pause is running.
+    pub(crate) fn pause_for(&self, duration: Duration) {
+        let until = Instant::now() + duration.min(Duration::from_secs(60));
+        let mut paused = self.0.lock().unwrap();
+        if paused.is_none_or(|current| current < until) {
+            *paused = Some(until);
+        }
+    }
+
+    /// The function `until` returns the end of the pause, if one is running.
+    fn until(&self) -> Option<Instant> {
+        let mut paused = self.0.lock().unwrap();
+        match *paused {
+            Some(until) if until > Instant::now() => Some(until),
+            _ => {
+                *paused = None;
+                None
+            }
+        }
+    }
+}
+
+impl AsyncWrite for ServerStream {
+    fn poll_write(self: Pin<&mut Self>, cx: &mut Context<'_>, buf: &[u8]) -> Poll<io::Result<usize>> {
+        let this = self.get_mut();
+        if let Err(e) = this.registration.outbox.check_open() {
+            return this.report(Poll::Ready(Err(e)));
+        }
+        if let Some(throttle) = &mut this.throttle {
+            ready!(throttle.poll_write_ready(cx));
+        }
+        let written = match &mut this.inner {
+            Inner::Plain(stream) => Pin::new(stream).poll_write(cx, buf),
+            Inner::Tls(stream) => Pin::new(stream).poll_write(cx, buf),
+            #[cfg(unix)]
+            Inner::Unix(stream) => Pin::new(stream).poll_write(cx, buf),
+        };
+        this.count_sent(written)
+    }
+
+    fn poll_write_vectored(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        bufs: &[IoSlice<'_>],
+    ) -> Poll<io::Result<usize>> {
+        let this = self.get_mut();
+        if let Err(e) = this.registration.outbox.check_open() {
+            return this.report(Poll::Ready(Err(e)));
+        }
+        if let Some(throttle) = &mut this.throttle {
+            ready!(throttle.poll_write_ready(cx));
+        }
+        let written = match &mut this.inner {
+            Inner::Plain(stream) => Pin::new(stream).poll_write_vectored(cx, bufs),
+            Inner::Tls(stream) => Pin::new(stream).poll_write_vectored(cx, bufs),
+            #[cfg(unix)]
+            Inner::Unix(stream) => Pin::new(stream).poll_write_vectored(cx, bufs),
+        };
+        this.count_sent(written)
+    }
+
+    fn is_write_vectored(&self) -> bool {
+        match &self.inner {
+            Inner::Plain(stream) => stream.is_write_vectored(),
+            Inner::Tls(stream) => stream.is_write_vectored(),
+            #[cfg(unix)]
+            Inner::Unix(stream) => stream.is_write_vectored(),
+        }
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        let this = self.get_mut();
+        let flushed = match this.poll_outbox(cx) {
+            Poll::Ready(Ok(())) => match &mut this.inner {
+                Inner::Plain(stream) => Pin::new(stream).poll_flush(cx),
+                Inner::Tls(stream) => Pin::new(stream).poll_flush(cx),
+                #[cfg(unix)]
+                Inner::Unix(stream) => Pin::new(stream).poll_flush(cx),
+            },
+            pending_or_failed => pending_or_failed,
+        };
+        this.report(flushed)
+    }
+
+    fn poll_shutdown(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        let this = self.get_mut();
+        let closed = match &mut this.inner {
+            Inner::Plain(stream) => Pin::new(stream).poll_shutdown(cx),
+            Inner::Tls(stream) => Pin::new(stream).poll_shutdown(cx),
+            #[cfg(unix)]
+            Inner::Unix(stream) => Pin::new(stream).poll_shutdown(cx),
+        };
+        this.report(closed)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{
+        tcp::{LineCodec, TcpServer},
+        tls::TlsConfig,
+    };
+    use futures_util::{SinkExt, StreamExt};
+    use rcgen::{BasicConstraints, CertificateParams, DnType, IsCa, KeyPair};
+    use rustls::{
+        crypto::ring,
+        pki_types::{CertificateDer, PrivateKeyDer, PrivatePkcs8KeyDer, ServerName},
+        ClientConfig, RootCertStore,
+    };
+    use std::sync::Arc;
+    use tokio::net::TcpStream;
+    use tokio_rustls::TlsConnector;
+    use tokio_util::codec::Framed;
+
+    #[tokio::test]
+    async fn test_tls_with_client_auth() {
+        let ca_key = KeyPair::generate().unwrap();
+        let mut ca = CertificateParams::new(Vec::new()).unwrap();
+        ca.is_ca = IsCa::Ca(BasicConstraints::Unconstrained);
+        ca.distinguished_name.push(DnType::CommonName, "AarambhNet test CA");
+        let ca = ca.self_signed(&ca_key).unwrap();
+        let server_key = KeyPair::generate().unwrap();
+        let server_cert = CertificateParams::new(vec!["localhost".to_string()]).unwrap();
+        let server_cert = server_cert.signed_by(&server_key, &ca, &ca_key).unwrap();
+        let client_key = KeyPair::generate().unwrap();
+        let client_cert = CertificateParams::new(vec!["client".to_string()]).unwrap();
+        let client_cert = client_cert.signed_by(&client_key, &ca, &ca_key).unwrap();
+
+        let tls = TlsConfig::from_pem(server_cert.pem().as_bytes(), server_key.serialize_pem().as_bytes())
+            .unwrap()
+            .with_client_auth(ca.pem().as_bytes())
+            .unwrap();
+        let server = Arc::new(TcpServer::bind("127.0.0.1:0").await.unwrap().with_tls(&tls).unwrap());
+        let addr = server.local_addr().unwrap();
+        let task = tokio::spawn({
+            let server = server.clone();
+            async move {
+                let served = server.run_framed(LineCodec::new(), |mut lines, _| async move {
+                    assert!(lines.get_ref().is_tls());
+                    let certificates = lines.get_ref().session().peer_certificates().to_vec();
+                    let names = certificates.iter().flat_map(|cert| cert.subject_alt_names().to_vec());
+                    let client = names.collect::<Vec<_>>().join(",");
+                    while let Some(Ok(line)) = lines.next().await {
+                        let _ = lines.send(format!("secure {} from {}", line, client)).await;
+                    }
+                });
+                served.await.map_err(|e| e.to_string())
+            }
+        });
+
+        let mut roots = RootCertStore::empty();
+        roots.add(ca.der().clone()).unwrap();
+        let builder = ClientConfig::builder_with_provider(Arc::new(ring::default_provider()))
+            .with_safe_default_protocol_versions()
+            .unwrap()
+            .with_root_certificates(roots);
+        let key = PrivateKeyDer::Pkcs8(PrivatePkcs8KeyDer::from(client_key.serialize_der()));
+        let chain: Vec<CertificateDer<'static>> = vec![client_cert.der().clone()];
+        let with_cert = TlsConnector::from(Arc::new(builder.clone().with_client_auth_cert(chain, key).unwrap()));
+        let without_cert = TlsConnector::from(Arc::new(builder.with_no_client_auth()));
+        let domain = ServerName::try_from("localhost").unwrap();
+
+        let socket = TcpStream::connect(addr).await.unwrap();
+        let stream = with_cert.connect(domain.clone(), socket).await.unwrap();
+        let mut lines = Framed::new(stream, LineCodec::new());
+        lines.send("hello").await.unwrap();
+        assert_eq!(lines.next().await.unwrap().unwrap(), "secure hello from client");
+
+        // Under TLS 1.3 the server refuses the missing certificate after the client finished.
+        let socket = TcpStream::connect(addr).await.unwrap();
+        let refused = async {
+            let mut lines = Framed::new(without_cert.connect(domain, socket).await?, LineCodec::new());
+            lines.send("hello").await?;
+            lines.next().await.transpose()
+        };
+        assert!(!matches!(refused.await, Ok(Some(_))));
+
+        server.shutdown().await;
+        task.await.unwrap().unwrap();
+    }
+}
@@ -0,0 +1,95 @@
+use std::{error::Error, fmt, future::Future, io, time::Duration};
+
+/// The `TimeoutOperation` enum tells which operation of a `TcpClient` ran out of time.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TimeoutOperation {
+    Connect,
+    Read,
+    Write,
+}
+
+/// The `TimeoutError` is the cause of the `AarambhNetError` returned when a `TcpClient` takes longer
+/// than `TcpClient::connect_timeout`, `set_read_timeout` or `set_write_timeout` allow. Find it with
+/// `AarambhNetError::cause` to tell an unresponsive host from a refused or reset connection.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TimeoutError {
+    pub operation: TimeoutOperation,
+    pub timeout: Duration,
+}
+
+impl fmt::Display for TimeoutError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let operation = match self.operation {
+            TimeoutOperation::Connect => "connecting",
+            TimeoutOperation::Read => "reading",
+            TimeoutOperation::Write => "writing",
+        };
+        write!(f, "{} timed out after {:?}", operation, self.timeout)
+    }
+}
+
+impl Error for TimeoutError {}
+
+/// The function `within` runs `io`, failing with a `TimeoutError` for `operation` if it takes longer
+/// than `limit`, if set.
+pub(crate) async fn within<T>(
+    limit: Option<Duration>,
+    operation: TimeoutOperation,
+    io: impl Future<Output = io::Result<T>>,
+) -> Result<T, Box<dyn Error + Send + Sync>> {
+    let Some(limit) = limit else { return Ok(io.await?) };
+    match tokio::time::timeout(limit, io).await {
+        Ok(result) => Ok(result?),
+        Err(_) => Err(Box::new(TimeoutError { operation, timeout: limit })),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{TimeoutError, TimeoutOperation};
+    use crate::{error::AarambhNetError, tcp::TcpClient};
+    use std::time::Duration;
+    use tokio::{io::AsyncReadExt, net::TcpListener};
+
+    #[tokio::test]
+    async fn test_client_timeouts() {
+        // The listener accepts but never answers, then stops reading.
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap().to_string();
+        let server = tokio::spawn(async move {
+            let (mut socket, _) = listener.accept().await.unwrap();
+            let mut buf = [0; 5];
+            socket.read_exact(&mut buf).await.unwrap();
+            tokio::time::sleep(Duration::from_secs(5)).await;
+        });
+
+        let mut client = TcpClient::connect_timeout(&addr, Duration::from_secs(1)).await.unwrap();
+        client.set_read_timeout(Some(Duration::from_millis(50)));
+        client.set_write_timeout(Some(Duration::from_millis(50)));
+        client.send_message("hello").await.unwrap();
+        let err = client.receive_response().await.unwrap_err();
+        let err = err.downcast_ref::<AarambhNetError>().unwrap();
+        let read = TimeoutError { operation: TimeoutOperation::Read, timeout: Duration::from_millis(50) };
+        assert_eq!(err.cause::<TimeoutError>(), Some(&read));
+        assert_eq!(err.operation(), "receive");
+
+        // Writes stall once the socket buffers are full.
+        let chunk = "x".repeat(1 << 20);
+        let err = loop {
+            if let Err(err) = client.send_message(&chunk).await {
+                break err;
+            }
+        };
+        let timeout = err.downcast_ref::<AarambhNetError>().unwrap().cause::<TimeoutError>().copied();
+        assert_eq!(timeout.map(|e| e.operation), Some(TimeoutOperation::Write));
+        server.abort();
+
+        // A non-routable address leaves the connect hanging until the timeout.
+        if let Err(err) = TcpClient::connect_timeout("10.255.255.1:80", Duration::from_millis(50)).await {
+            let err = err.downcast_ref::<AarambhNetError>().unwrap();
+            if let Some(timeout) = err.cause::<TimeoutError>() {
+                assert_eq!(timeout.operation, TimeoutOperation::Connect);
+            }
+        }
+    }
+}
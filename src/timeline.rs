@@ -0,0 +1,143 @@
+use crate::rt::Instant;
+use std::{
+    error::Error,
+    fmt,
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+tokio::task_local! {
+    /// The timeline of the request being sent on the current task, used by hooks (such as the DNS
+    /// resolver) that cannot be handed the timeline directly.
+    pub(crate) static CURRENT: Timeline;
+}
+
+/// The `TimelineEventKind` enum names the stages of a connection recorded in a `Timeline`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TimelineEventKind {
+    Resolve,
+    Connect,
+    Tls,
+    Write,
+    FirstByte,
+    Close,
+}
+
+impl TimelineEventKind {
+    fn as_str(&self) -> &'static str {
+        match self {
+            TimelineEventKind::Resolve => "resolve",
+            TimelineEventKind::Connect => "connect",
+            TimelineEventKind::Tls => "tls",
+            TimelineEventKind::Write => "write",
+            TimelineEventKind::FirstByte => "first-byte",
+            TimelineEventKind::Close => "close",
+        }
+    }
+}
+
+/// The `TimelineEvent` struct is one entry of a `Timeline`.
+///
+/// # Properties:
+///
+/// * `kind`: The stage that was reached.
+/// * `elapsed`: The time since the timeline was started.
+/// * `detail`: A human readable description, such as the resolved addresses or the response status.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TimelineEvent {
+    pub kind: TimelineEventKind,
+    pub elapsed: Duration,
+    pub detail: String,
+}
+
+/// The `Timeline` struct is the structured equivalent of `curl -v`: a list of timestamped events
+/// recorded while a request or connection is in progress. Clones share the same events, so a clone
+/// kept by the caller also sees events recorded later, such as `Close`.
+///
+/// # Properties:
+///
+/// * `start`: The instant the timeline was started; event times are relative to it.
+/// * `events`: The events recorded so far, in order.
+#[derive(Debug, Clone)]
+pub struct Timeline {
+    start: Instant,
+    events: Arc<Mutex<Vec<TimelineEvent>>>,
+}
+
+impl Timeline {
+    pub(crate) fn new() -> Self {
+        Timeline { start: Instant::now(), events: Arc::new(Mutex::new(Vec::new())) }
+    }
+
+    /// The function `record` appends an event stamped with the time since the timeline started.
+    pub(crate) fn record(&self, kind: TimelineEventKind, detail: impl Into<String>) {
+        let event = TimelineEvent { kind, elapsed: self.start.elapsed(), detail: detail.into() };
+        self.events.lock().unwrap().push(event);
+    }
+
+    /// The function `elapsed` returns the time since the timeline was started.
+    pub(crate) fn elapsed(&self) -> Duration {
+        self.start.elapsed()
+    }
+
+    /// The function `record_at` inserts an event that happened at `elapsed`, before the events
+    /// recorded after it.
+    pub(crate) fn record_at(&self, kind: TimelineEventKind, elapsed: Duration, detail: impl Into<String>) {
+        let mut events = self.events.lock().unwrap();
+        let index = events.partition_point(|e| e.elapsed <= elapsed);
+        events.insert(index, TimelineEvent { kind, elapsed, detail: detail.into() });
+    }
+
+    /// The function `events` returns a copy of the events recorded so far.
+    pub fn events(&self) -> Vec<TimelineEvent> {
+        self.events.lock().unwrap().clone()
+    }
+
+    /// The function `elapsed_at` returns the time of the first event of the given kind, if it was
+    /// recorded.
+    pub fn elapsed_at(&self, kind: TimelineEventKind) -> Option<Duration> {
+        self.events.lock().unwrap().iter().find(|e| e.kind == kind).map(|e| e.elapsed)
+    }
+}
+
+impl fmt::Display for Timeline {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for event in self.events.lock().unwrap().iter() {
+            let millis = event.elapsed.as_secs_f64() * 1000.0;
+            writeln!(f, "[{:>10.3} ms] {:<10} {}", millis, event.kind.as_str(), event.detail)?;
+        }
+        Ok(())
+    }
+}
+
+/// The `TimelineError` is the cause of the `AarambhNetError` returned by clients in verbose mode when
+/// an operation fails. It carries the timeline recorded up to the failure; the original error is
+/// available through `source` and `inner`.
+#[derive(Debug)]
+pub struct TimelineError {
+    pub timeline: Timeline,
+    source: Box<dyn Error + Send + Sync>,
+}
+
+impl TimelineError {
+    pub(crate) fn new(timeline: Timeline, source: Box<dyn Error + Send + Sync>) -> Self {
+        TimelineError { timeline, source }
+    }
+
+    /// The function `inner` returns the error that made the operation fail.
+    pub fn inner(&self) -> &(dyn Error + Send + Sync + 'static) {
+        self.source.as_ref()
+    }
+}
+
+impl fmt::Display for TimelineError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}\n{}", self.source, self.timeline)
+    }
+}
+
+impl Error for TimelineError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        Some(self.source.as_ref())
+    }
+}
@@ -0,0 +1,242 @@
+use rustls::{
+    client::WantsClientCert,
+    crypto::ring,
+    pki_types::{CertificateDer, PrivateKeyDer},
+    server::{danger::ClientCertVerifier, WebPkiClientVerifier},
+    ClientConfig, ConfigBuilder, RootCertStore, ServerConfig,
+};
+use std::{error::Error, fs, path::Path, sync::Arc};
+use tokio_rustls::{TlsAcceptor, TlsConnector};
+
+/// The `TlsConfig` struct holds the certificate and private key a server presents to its clients.
+///
+/// # Properties:
+///
+/// * `cert_chain`: The server certificate followed by the intermediate certificates.
+/// * `key`: The private key of the server certificate.
+/// * `client_verifier`: The verifier of client certificates, if clients authenticate with one.
+pub struct TlsConfig {
+    cert_chain: Vec<CertificateDer<'static>>,
+    key: PrivateKeyDer<'static>,
+    client_verifier: Option<Arc<dyn ClientCertVerifier>>,
+}
+
+impl TlsConfig {
+    /// The function `from_pem` reads the certificate chain and private key from PEM data.
+    ///
+    /// # Arguments:
+    ///
+    /// * `cert_chain`: The PEM certificates, server certificate first, as in a `fullchain.pem`.
+    /// * `private_key`: The PEM private key, in PKCS#8, PKCS#1 or SEC1 format.
+    ///
+    /// # Returns:
+    ///
+    /// The `from_pem` function returns a `Result` containing the `TlsConfig`, or an error if the data
+    /// holds no certificate or no private key, or if the key does not belong to the certificate.
+    pub fn from_pem(cert_chain: &[u8], private_key: &[u8]) -> Result<Self, Box<dyn Error>> {
+        let (cert_chain, key) = read_identity(cert_chain, private_key)?;
+        let config = TlsConfig { cert_chain, key, client_verifier: None };
+        // Building a configuration checks that rustls supports the key.
+        config.server_config(Vec::new())?;
+        Ok(config)
+    }
+
+    /// The function `from_pem_files` reads the certificate chain and private key from PEM files, such
+    /// as the `fullchain.pem` and `privkey.pem` written by Let's Encrypt clients.
+    ///
+    /// # Returns:
+    ///
+    /// The `from_pem_files` function returns a `Result` containing the `TlsConfig`, or an error if a
+    /// file cannot be read or does not hold what `from_pem` expects.
+    pub fn from_pem_files(cert_path: impl AsRef<Path>, key_path: impl AsRef<Path>) -> Result<Self, Box<dyn Error>> {
+        let cert_chain = fs::read(cert_path.as_ref())
+            .map_err(|e| format!("failed to read {}: {}", cert_path.as_ref().display(), e))?;
+        let private_key = fs::read(key_path.as_ref())
+            .map_err(|e| format!("failed to read {}: {}", key_path.as_ref().display(), e))?;
+        Self::from_pem(&cert_chain, &private_key)
+    }
+
+    /// The function `with_client_auth` makes the server ask clients for a certificate issued by one
+    /// of the PEM certificate authorities in `ca_pem`, refusing the handshake of clients without
+    /// one (mutual TLS).
+    ///
+    /// # Returns:
+    ///
+    /// The `with_client_auth` function returns a `Result` containing the `TlsConfig`, or an error if
+    /// `ca_pem` holds no usable certificate.
+    pub fn with_client_auth(self, ca_pem: &[u8]) -> Result<Self, Box<dyn Error>> {
+        self.client_auth(ca_pem, true)
+    }
+
+    /// The function `with_optional_client_auth` asks clients for a certificate like
+    /// `with_client_auth`, but also accepts clients presenting none. Certificates that are
+    /// presented must still be valid.
+    pub fn with_optional_client_auth(self, ca_pem: &[u8]) -> Result<Self, Box<dyn Error>> {
+        self.client_auth(ca_pem, false)
+    }
+
+    fn client_auth(mut self, ca_pem: &[u8], required: bool) -> Result<Self, Box<dyn Error>> {
+        let roots = read_roots(ca_pem)?;
+        let builder = WebPkiClientVerifier::builder_with_provider(Arc::new(roots), Arc::new(ring::default_provider()));
+        let builder = if required { builder } else { builder.allow_unauthenticated() };
+        self.client_verifier = Some(builder.build()?);
+        Ok(self)
+    }
+
+    /// The function `server_config` builds the rustls configuration, advertising `alpn_protocols`
+    /// in order of preference.
+    pub(crate) fn server_config(&self, alpn_protocols: Vec<Vec<u8>>) -> Result<ServerConfig, rustls::Error> {
+        let builder = ServerConfig::builder_with_provider(Arc::new(ring::default_provider()))
+            .with_safe_default_protocol_versions()?;
+        let builder = match &self.client_verifier {
+            Some(verifier) => builder.with_client_cert_verifier(verifier.clone()),
+            None => builder.with_no_client_auth(),
+        };
+        let mut config = builder.with_single_cert(self.cert_chain.clone(), self.key.clone_key())?;
+        config.alpn_protocols = alpn_protocols;
+        Ok(config)
+    }
+
+    /// The function `acceptor` creates the acceptor performing the server side of TLS handshakes.
+    pub(crate) fn acceptor(&self, alpn_protocols: Vec<Vec<u8>>) -> Result<TlsAcceptor, rustls::Error> {
+        Ok(TlsAcceptor::from(Arc::new(self.server_config(alpn_protocols)?)))
+    }
+}
+
+/// The `TlsClientConfig` struct holds what a `TcpClient` needs to connect to a TLS server: the
+/// certificate authorities it trusts and, for servers requiring mutual TLS, the certificate it
+/// presents. No system roots are loaded, so the authorities of the server must be given.
+///
+/// # Properties:
+///
+/// * `roots`: The certificate authorities the certificate of the server must be issued by.
+/// * `config`: The rustls configuration, shared by the connections so they can resume sessions.
+#[derive(Clone)]
+pub struct TlsClientConfig {
+    roots: Arc<RootCertStore>,
+    config: Arc<ClientConfig>,
+}
+
+impl TlsClientConfig {
+    /// The function `from_roots_pem` trusts the PEM certificate authorities in `ca_pem`, such as the
+    /// private CA of a database or an MQTT broker.
+    ///
+    /// # Returns:
+    ///
+    /// The `from_roots_pem` function returns a `Result` containing the `TlsClientConfig`, or an
+    /// error if `ca_pem` holds no usable certificate.
+    pub fn from_roots_pem(ca_pem: &[u8]) -> Result<Self, Box<dyn Error>> {
+        let roots = Arc::new(read_roots(ca_pem)?);
+        let config = client_config_builder(&roots)?.with_no_client_auth();
+        Ok(TlsClientConfig { roots, config: Arc::new(config) })
+    }
+
+    /// The function `with_client_cert` makes the client present the PEM certificate chain and
+    /// private key, read like `TlsConfig::from_pem`, to servers asking for one.
+    ///
+    /// # Returns:
+    ///
+    /// The `with_client_cert` function returns a `Result` containing the `TlsClientConfig`, or an
+    /// error if the data holds no certificate or no private key, or if rustls does not support the
+    /// key.
+    pub fn with_client_cert(self, cert_chain: &[u8], private_key: &[u8]) -> Result<Self, Box<dyn Error>> {
+        let (cert_chain, key) = read_identity(cert_chain, private_key)?;
+        let config = client_config_builder(&self.roots)?.with_client_auth_cert(cert_chain, key)?;
+        Ok(TlsClientConfig { config: Arc::new(config), ..self })
+    }
+
+    /// The function `connector` creates the connector performing the client side of TLS handshakes.
+    pub(crate) fn connector(&self) -> TlsConnector {
+        TlsConnector::from(self.config.clone())
+    }
+}
+
+/// The function `client_config_builder` starts a rustls client configuration trusting `roots`.
+fn client_config_builder(
+    roots: &Arc<RootCertStore>,
+) -> Result<ConfigBuilder<ClientConfig, WantsClientCert>, rustls::Error> {
+    Ok(ClientConfig::builder_with_provider(Arc::new(ring::default_provider()))
+        .with_safe_default_protocol_versions()?
+        .with_root_certificates(roots.clone()))
+}
+
+/// The function `read_identity` reads a PEM certificate chain and its PEM private key.
+fn read_identity(
+    cert_chain: &[u8],
+    private_key: &[u8],
+) -> Result<(Vec<CertificateDer<'static>>, PrivateKeyDer<'static>), Box<dyn Error>> {
+    let cert_chain = rustls_pemfile::certs(&mut &cert_chain[..]).collect::<Result<Vec<_>, _>>()?;
+    if cert_chain.is_empty() {
+        return Err("no certificate found in the PEM data".into());
+    }
+    let key = rustls_pemfile::private_key(&mut &private_key[..])?.ok_or("no private key found in the PEM data")?;
+    Ok((cert_chain, key))
+}
+
+/// The function `read_roots` reads the PEM certificate authorities in `ca_pem`.
+fn read_roots(ca_pem: &[u8]) -> Result<RootCertStore, Box<dyn Error>> {
+    let mut roots = RootCertStore::empty();
+    for cert in rustls_pemfile::certs(&mut &ca_pem[..]) {
+        roots.add(cert?)?;
+    }
+    if roots.is_empty() {
+        return Err("no certificate found in the PEM data".into());
+    }
+    Ok(roots)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::http::{HttpServer, Router, ServerResponse};
+    use rcgen::{BasicConstraints, CertificateParams, DnType, IsCa, KeyPair};
+    use reqwest::{Certificate, Client, Version};
+
+    #[tokio::test]
+    async fn test_https_server_and_redirect() {
+        let ca_key = KeyPair::generate().unwrap();
+        let mut ca = CertificateParams::new(Vec::new()).unwrap();
+        ca.is_ca = IsCa::Ca(BasicConstraints::Unconstrained);
+        ca.distinguished_name.push(DnType::CommonName, "AarambhNet test CA");
+        let ca = ca.self_signed(&ca_key).unwrap();
+        let key = KeyPair::generate().unwrap();
+        let cert = CertificateParams::new(vec!["localhost".to_string()]).unwrap();
+        let cert = cert.signed_by(&key, &ca, &ca_key).unwrap();
+        let tls = TlsConfig::from_pem(cert.pem().as_bytes(), key.serialize_pem().as_bytes()).unwrap();
+        assert!(TlsConfig::from_pem(cert.pem().as_bytes(), b"").is_err());
+
+        let https = HttpServer::bind("127.0.0.1:0")
+            .await
+            .unwrap()
+            .get("/hello", |request| async move { ServerResponse::text(request.uri().to_string()) })
+            .with_tls(&tls)
+            .unwrap();
+        let https = Arc::new(https);
+        let port = https.local_addr().unwrap().port();
+        let http = HttpServer::bind("127.0.0.1:0").await.unwrap().with_router(Router::redirect_to_https(port));
+        let http = Arc::new(http);
+        let plain_port = http.local_addr().unwrap().port();
+        let run = |server: Arc<HttpServer>| tokio::spawn(async move { server.run().await.unwrap() });
+        let tasks = [run(https.clone()), run(http.clone())];
+
+        // Clients offering h2 through ALPN get HTTP/2, the others HTTP/1.1.
+        let ca = Certificate::from_pem(ca.pem().as_bytes()).unwrap();
+        let client = Client::builder().add_root_certificate(ca.clone()).build().unwrap();
+        let response = client.get(format!("https://localhost:{}/hello", port)).send().await.unwrap();
+        assert_eq!(response.version(), Version::HTTP_2);
+        let http1 = Client::builder().add_root_certificate(ca).http1_only().build().unwrap();
+        let response = http1.get(format!("https://localhost:{}/hello", port)).send().await.unwrap();
+        assert_eq!(response.version(), Version::HTTP_11);
+        assert_eq!(response.text().await.unwrap(), "/hello");
+
+        let response = http1.get(format!("http://localhost:{}/hello?page=2", plain_port)).send().await.unwrap();
+        assert_eq!(response.url().as_str(), format!("https://localhost:{}/hello?page=2", port));
+        assert_eq!(response.text().await.unwrap(), "/hello?page=2");
+
+        https.shutdown().await;
+        http.shutdown().await;
+        for task in tasks {
+            task.await.unwrap();
+        }
+    }
+}
@@ -0,0 +1,310 @@
+use socket2::SockAddr;
+use std::{
+    io, mem,
+    net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr, SocketAddrV6},
+    os::fd::{AsRawFd, RawFd},
+    ptr,
+};
+use tokio::{io::Interest, net::UdpSocket};
+
+/// The size of the control buffer, enough for every message enabled by `enable`.
+const CONTROL_LEN: usize = 256;
+/// The bits of the IPv6 flow information holding the flow label.
+const FLOW_LABEL_MASK: u32 = 0x000f_ffff;
+/// `IPV6_FL_A_GET`, `IPV6_FL_S_EXCL` and `IPV6_FL_F_CREATE` from `linux/in6.h`.
+const FLOW_LABEL_GET: u8 = 0;
+const FLOW_LABEL_EXCLUSIVE: u8 = 1;
+const FLOW_LABEL_CREATE: u16 = 1;
+
+/// The `UdpRecvInfo` struct describes how a datagram arrived, as reported by the kernel alongside
+/// it. Fields the kernel did not report are `None`.
+///
+/// # Properties:
+///
+/// * `peer`: The address the datagram came from.
+/// * `destination`: The local address the datagram was sent to. On a socket bound to a wildcard
+///   address this tells which address of a multi-homed host the peer used.
+/// * `interface`: The index of the interface the datagram arrived on.
+/// * `hop_limit`: The remaining IPv4 TTL or IPv6 hop limit.
+/// * `flow_label`: The IPv6 flow label.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct UdpRecvInfo {
+    pub peer: SocketAddr,
+    pub destination: Option<IpAddr>,
+    pub interface: Option<u32>,
+    pub hop_limit: Option<u8>,
+    pub flow_label: Option<u32>,
+}
+
+/// The `UdpSendInfo` struct sets the ancillary data of an outgoing datagram. Fields left `None` use
+/// the defaults of the socket and the routing table.
+///
+/// # Properties:
+///
+/// * `source`: The local address to send from; it must be assigned to the host.
+/// * `interface`: The index of the interface to send through.
+/// * `hop_limit`: The IPv4 TTL or IPv6 hop limit of the datagram.
+/// * `flow_label`: The IPv6 flow label, leased for the socket on first use.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct UdpSendInfo {
+    pub source: Option<IpAddr>,
+    pub interface: Option<u32>,
+    pub hop_limit: Option<u8>,
+    pub flow_label: Option<u32>,
+}
+
+impl UdpSendInfo {
+    /// The function `reply_to` returns the settings answering a datagram from the address and
+    /// interface it was received on, so the peer sees the reply come from the address it wrote to.
+    /// Datagrams received on broadcast or multicast addresses should be answered without a source.
+    pub fn reply_to(info: &UdpRecvInfo) -> Self {
+        UdpSendInfo { source: info.destination, interface: info.interface, ..Self::default() }
+    }
+}
+
+/// `struct in6_flowlabel_req` from `linux/in6.h`, which the libc crate does not provide.
+#[repr(C)]
+struct FlowLabelRequest {
+    dst: libc::in6_addr,
+    label: u32,
+    action: u8,
+    share: u8,
+    flags: u16,
+    expires: u16,
+    linger: u16,
+    pad: u32,
+}
+
+fn set_option<T>(fd: RawFd, level: libc::c_int, name: libc::c_int, value: &T) -> io::Result<()> {
+    let len = mem::size_of::<T>() as libc::socklen_t;
+    if unsafe { libc::setsockopt(fd, level, name, (value as *const T).cast(), len) } < 0 {
+        return Err(io::Error::last_os_error());
+    }
+    Ok(())
+}
+
+/// The function `enable` asks the kernel to report the destination address, interface, hop limit
+/// and, on IPv6, the flow label of every datagram received on `socket`.
+pub(crate) fn enable(socket: &UdpSocket) -> io::Result<()> {
+    let fd = socket.as_raw_fd();
+    let on: libc::c_int = 1;
+    if socket.local_addr()?.is_ipv4() {
+        set_option(fd, libc::IPPROTO_IP, libc::IP_PKTINFO, &on)?;
+        set_option(fd, libc::IPPROTO_IP, libc::IP_RECVTTL, &on)?;
+    } else {
+        set_option(fd, libc::IPPROTO_IPV6, libc::IPV6_RECVPKTINFO, &on)?;
+        set_option(fd, libc::IPPROTO_IPV6, libc::IPV6_RECVHOPLIMIT, &on)?;
+        set_option(fd, libc::IPPROTO_IPV6, libc::IPV6_FLOWINFO, &on)?;
+        set_option(fd, libc::IPPROTO_IPV6, libc::IPV6_FLOWINFO_SEND, &on)?;
+        // IPv4 peers of a dual-stack socket are reported through the IPv4 options.
+        let _ = set_option(fd, libc::IPPROTO_IP, libc::IP_PKTINFO, &on);
+        let _ = set_option(fd, libc::IPPROTO_IP, libc::IP_RECVTTL, &on);
+    }
+    Ok(())
+}
+
+/// The function `recv` receives one datagram into `buf` together with its ancillary data.
+pub(crate) async fn recv(socket: &UdpSocket, buf: &mut [u8]) -> io::Result<(usize, UdpRecvInfo)> {
+    socket.async_io(Interest::READABLE, || recv_now(socket.as_raw_fd(), buf)).await
+}
+
+/// The function `send` sends `data` to `target` with the ancillary data described by `info`.
+pub(crate) async fn send(socket: &UdpSocket, data: &[u8], target: SocketAddr, info: &UdpSendInfo) -> io::Result<usize> {
+    let fd = socket.as_raw_fd();
+    let target = match (target, info.flow_label) {
+        (SocketAddr::V6(addr), Some(label)) => {
+            lease_flow_label(fd, *addr.ip(), label & FLOW_LABEL_MASK)?;
+            let flowinfo = (label & FLOW_LABEL_MASK).to_be();
+            SocketAddr::V6(SocketAddrV6::new(*addr.ip(), addr.port(), flowinfo, addr.scope_id()))
+        }
+        _ => target,
+    };
+    socket.async_io(Interest::WRITABLE, || send_now(fd, data, target, info)).await
+}
+
+/// The function `lease_flow_label` registers `label` towards `destination` for the socket, which
+/// Linux requires before a flow label may be sent. Leasing a label the socket already holds is a
+/// no-op.
+fn lease_flow_label(fd: RawFd, destination: Ipv6Addr, label: u32) -> io::Result<()> {
+    let request = FlowLabelRequest {
+        dst: libc::in6_addr { s6_addr: destination.octets() },
+        label: label.to_be(),
+        action: FLOW_LABEL_GET,
+        share: FLOW_LABEL_EXCLUSIVE,
+        flags: FLOW_LABEL_CREATE,
+        expires: 0,
+        linger: 0,
+        pad: 0,
+    };
+    set_option(fd, libc::IPPROTO_IPV6, libc::IPV6_FLOWLABEL_MGR, &request)
+}
+
+/// Whether datagrams to `target` travel over IPv4, including IPv4-mapped peers of a dual-stack
+/// socket.
+fn is_ipv4(target: &SocketAddr) -> bool {
+    match target {
+        SocketAddr::V4(_) => true,
+        SocketAddr::V6(addr) => addr.ip().to_ipv4_mapped().is_some(),
+    }
+}
+
+fn recv_now(fd: RawFd, buf: &mut [u8]) -> io::Result<(usize, UdpRecvInfo)> {
+    let mut name: libc::sockaddr_storage = unsafe { mem::zeroed() };
+    let mut control = [0u64; CONTROL_LEN / 8];
+    let mut iov = libc::iovec { iov_base: buf.as_mut_ptr().cast(), iov_len: buf.len() };
+    let mut msg: libc::msghdr = unsafe { mem::zeroed() };
+    msg.msg_name = ptr::addr_of_mut!(name).cast();
+    msg.msg_namelen = mem::size_of_val(&name) as libc::socklen_t;
+    msg.msg_iov = &mut iov;
+    msg.msg_iovlen = 1;
+    msg.msg_control = control.as_mut_ptr().cast();
+    msg.msg_controllen = mem::size_of_val(&control) as _;
+
+    let len = unsafe { libc::recvmsg(fd, &mut msg, 0) };
+    if len < 0 {
+        return Err(io::Error::last_os_error());
+    }
+    let peer = unsafe { SockAddr::new(name, msg.msg_namelen) }
+        .as_socket()
+        .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidData, "datagram from a non-IP address"))?;
+
+    let mut info = UdpRecvInfo { peer, destination: None, interface: None, hop_limit: None, flow_label: None };
+    unsafe {
+        let mut cmsg = libc::CMSG_FIRSTHDR(&msg);
+        while !cmsg.is_null() {
+            let data = libc::CMSG_DATA(cmsg);
+            match ((*cmsg).cmsg_level, (*cmsg).cmsg_type) {
+                (libc::IPPROTO_IP, libc::IP_PKTINFO) => {
+                    let pktinfo = ptr::read_unaligned(data.cast::<libc::in_pktinfo>());
+                    info.destination = Some(IpAddr::V4(Ipv4Addr::from(u32::from_be(pktinfo.ipi_addr.s_addr))));
+                    info.interface = Some(pktinfo.ipi_ifindex as u32);
+                }
+                (libc::IPPROTO_IP, libc::IP_TTL) => {
+                    info.hop_limit = Some(ptr::read_unaligned(data.cast::<libc::c_int>()) as u8);
+                }
+                (libc::IPPROTO_IPV6, libc::IPV6_PKTINFO) => {
+                    let pktinfo = ptr::read_unaligned(data.cast::<libc::in6_pktinfo>());
+                    info.destination = Some(IpAddr::V6(Ipv6Addr::from(pktinfo.ipi6_addr.s6_addr)));
+                    info.interface = Some(pktinfo.ipi6_ifindex);
+                }
+                (libc::IPPROTO_IPV6, libc::IPV6_HOPLIMIT) => {
+                    info.hop_limit = Some(ptr::read_unaligned(data.cast::<libc::c_int>()) as u8);
+                }
+                (libc::IPPROTO_IPV6, libc::IPV6_FLOWINFO) => {
+                    info.flow_label = Some(u32::from_be(ptr::read_unaligned(data.cast::<u32>())) & FLOW_LABEL_MASK);
+                }
+                _ => {}
+            }
+            cmsg = libc::CMSG_NXTHDR(&msg, cmsg);
+        }
+    }
+    Ok((len as usize, info))
+}
+
+/// The function `push_control` appends one control message to `control` at `*len`.
+///
+/// # Safety
+///
+/// `control` must have room for `CMSG_SPACE(size_of::<T>())` more bytes.
+unsafe fn push_control<T>(control: &mut [u64], len: &mut usize, level: libc::c_int, kind: libc::c_int, value: T) {
+    let cmsg = control.as_mut_ptr().cast::<u8>().add(*len).cast::<libc::cmsghdr>();
+    (*cmsg).cmsg_level = level;
+    (*cmsg).cmsg_type = kind;
+    (*cmsg).cmsg_len = libc::CMSG_LEN(mem::size_of::<T>() as u32) as _;
+    ptr::write_unaligned(libc::CMSG_DATA(cmsg).cast::<T>(), value);
+    *len += libc::CMSG_SPACE(mem::size_of::<T>() as u32) as usize;
+}
+
+fn send_now(fd: RawFd, data: &[u8], target: SocketAddr, info: &UdpSendInfo) -> io::Result<usize> {
+    let addr = SockAddr::from(target);
+    let mut control = [0u64; CONTROL_LEN / 8];
+    let mut len = 0;
+    unsafe {
+        if info.source.is_some() || info.interface.is_some() {
+            match info.source {
+                Some(IpAddr::V4(source)) => {
+                    let pktinfo = libc::in_pktinfo {
+                        ipi_ifindex: info.interface.unwrap_or(0) as libc::c_int,
+                        ipi_spec_dst: libc::in_addr { s_addr: u32::from(source).to_be() },
+                        ipi_addr: libc::in_addr { s_addr: 0 },
+                    };
+                    push_control(&mut control, &mut len, libc::IPPROTO_IP, libc::IP_PKTINFO, pktinfo);
+                }
+                None if is_ipv4(&target) => {
+                    let pktinfo = libc::in_pktinfo {
+                        ipi_ifindex: info.interface.unwrap_or(0) as libc::c_int,
+                        ipi_spec_dst: libc::in_addr { s_addr: 0 },
+                        ipi_addr: libc::in_addr { s_addr: 0 },
+                    };
+                    push_control(&mut control, &mut len, libc::IPPROTO_IP, libc::IP_PKTINFO, pktinfo);
+                }
+                source => {
+                    let source = match source {
+                        Some(IpAddr::V6(source)) => source,
+                        _ => Ipv6Addr::UNSPECIFIED,
+                    };
+                    let pktinfo = libc::in6_pktinfo {
+                        ipi6_addr: libc::in6_addr { s6_addr: source.octets() },
+                        ipi6_ifindex: info.interface.unwrap_or(0),
+                    };
+                    push_control(&mut control, &mut len, libc::IPPROTO_IPV6, libc::IPV6_PKTINFO, pktinfo);
+                }
+            }
+        }
+        if let Some(hop_limit) = info.hop_limit {
+            let (level, kind) = if is_ipv4(&target) {
+                (libc::IPPROTO_IP, libc::IP_TTL)
+            } else {
+                (libc::IPPROTO_IPV6, libc::IPV6_HOPLIMIT)
+            };
+            push_control(&mut control, &mut len, level, kind, hop_limit as libc::c_int);
+        }
+    }
+
+    let mut iov = libc::iovec { iov_base: data.as_ptr() as *mut libc::c_void, iov_len: data.len() };
+    let mut msg: libc::msghdr = unsafe { mem::zeroed() };
+    msg.msg_name = addr.as_ptr() as *mut libc::c_void;
+    msg.msg_namelen = addr.len();
+    msg.msg_iov = &mut iov;
+    msg.msg_iovlen = 1;
+    if len > 0 {
+        msg.msg_control = control.as_mut_ptr().cast();
+        msg.msg_controllen = len as _;
+    }
+
+    let sent = unsafe { libc::sendmsg(fd, &msg, 0) };
+    if sent < 0 {
+        return Err(io::Error::last_os_error());
+    }
+    Ok(sent as usize)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_reply_from_destination_address() {
+        let server = UdpSocket::bind("0.0.0.0:0").await.unwrap();
+        let client = UdpSocket::bind("127.0.0.1:0").await.unwrap();
+        enable(&server).unwrap();
+        enable(&client).unwrap();
+        let server_addr: SocketAddr = format!("127.0.0.1:{}", server.local_addr().unwrap().port()).parse().unwrap();
+
+        let ttl = UdpSendInfo { hop_limit: Some(7), ..UdpSendInfo::default() };
+        send(&client, b"ping", server_addr, &ttl).await.unwrap();
+        let mut buf = [0; 16];
+        let (len, info) = recv(&server, &mut buf).await.unwrap();
+        assert_eq!(&buf[..len], b"ping");
+        assert_eq!(info.peer, client.local_addr().unwrap());
+        assert_eq!(info.destination, Some(IpAddr::V4(Ipv4Addr::LOCALHOST)));
+        assert_eq!(info.hop_limit, Some(7));
+
+        let reply = UdpSendInfo { hop_limit: Some(9), ..UdpSendInfo::reply_to(&info) };
+        send(&server, b"pong", info.peer, &reply).await.unwrap();
+        let (len, info) = recv(&client, &mut buf).await.unwrap();
+        assert_eq!(&buf[..len], b"pong");
+        assert_eq!(info.peer, server_addr);
+        assert_eq!(info.hop_limit, Some(9));
+    }
+}
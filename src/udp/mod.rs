@@ -0,0 +1,9 @@
+#[cfg(any(target_os = "linux", target_os = "android"))]
+mod ancillary;
+mod scheduler;
+mod server;
+
+#[cfg(any(target_os = "linux", target_os = "android"))]
+pub use ancillary::{UdpRecvInfo, UdpSendInfo};
+pub use scheduler::{DropPolicy, SchedulerConfig, SchedulerStats};
+pub use server::UdpServer;
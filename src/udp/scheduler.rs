@@ -0,0 +1,249 @@
+use std::{
+    collections::{HashMap, VecDeque},
+    net::SocketAddr,
+    sync::{
+        atomic::{AtomicBool, AtomicU64, Ordering},
+        Mutex,
+    },
+};
+use tokio::sync::Notify;
+
+/// The `DropPolicy` enum decides which datagram is discarded when a queue is full.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DropPolicy {
+    /// Discard the datagram that just arrived.
+    DropNewest,
+    /// Discard the oldest datagram of the source with the longest queue, so a chatty peer pays for
+    /// its own backlog.
+    DropOldest,
+}
+
+/// The `SchedulerConfig` struct configures the worker pool `UdpServer::run_with_handler` runs the
+/// handler on.
+///
+/// # Properties:
+///
+/// * `workers`: The number of handler invocations running at once.
+/// * `per_source_capacity`: The number of datagrams queued per peer address.
+/// * `total_capacity`: The number of datagrams queued across all peers.
+/// * `drop_policy`: What to discard when a queue is full.
+#[derive(Debug, Clone)]
+pub struct SchedulerConfig {
+    pub workers: usize,
+    pub per_source_capacity: usize,
+    pub total_capacity: usize,
+    pub drop_policy: DropPolicy,
+}
+
+impl Default for SchedulerConfig {
+    fn default() -> Self {
+        SchedulerConfig { workers: 4, per_source_capacity: 64, total_capacity: 1024, drop_policy: DropPolicy::DropNewest }
+    }
+}
+
+/// The `SchedulerStats` struct is a snapshot of the handler queues of a `UdpServer`.
+///
+/// # Properties:
+///
+/// * `queued`: The number of datagrams waiting for a worker.
+/// * `sources`: The number of peers with queued datagrams.
+/// * `max_source_depth`: The length of the longest per-peer queue.
+/// * `processed`: The number of datagrams handed to the handler.
+/// * `dropped`: The number of datagrams discarded because a queue was full.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SchedulerStats {
+    pub queued: usize,
+    pub sources: usize,
+    pub max_source_depth: usize,
+    pub processed: u64,
+    pub dropped: u64,
+}
+
+#[derive(Default)]
+struct Queues {
+    by_source: HashMap<SocketAddr, VecDeque<Vec<u8>>>,
+    rotation: VecDeque<SocketAddr>,
+    queued: usize,
+}
+
+/// The `FairScheduler` struct queues datagrams per peer and hands them to workers round-robin, one
+/// datagram per peer per turn, so a peer flooding the server cannot delay the others.
+pub(crate) struct FairScheduler {
+    config: SchedulerConfig,
+    queues: Mutex<Queues>,
+    ready: Notify,
+    closed: AtomicBool,
+    processed: AtomicU64,
+    dropped: AtomicU64,
+}
+
+impl FairScheduler {
+    pub(crate) fn new(config: SchedulerConfig) -> Self {
+        FairScheduler {
+            config,
+            queues: Mutex::new(Queues::default()),
+            ready: Notify::new(),
+            closed: AtomicBool::new(false),
+            processed: AtomicU64::new(0),
+            dropped: AtomicU64::new(0),
+        }
+    }
+
+    pub(crate) fn config(&self) -> &SchedulerConfig {
+        &self.config
+    }
+
+    /// The function `push` queues a datagram from `source`, applying the drop policy when the peer's
+    /// queue or the scheduler as a whole is full.
+    pub(crate) fn push(&self, source: SocketAddr, data: Vec<u8>) {
+        let mut queues = self.queues.lock().unwrap();
+        let depth = queues.by_source.get(&source).map_or(0, VecDeque::len);
+        let full = depth >= self.config.per_source_capacity || queues.queued >= self.config.total_capacity;
+        if full {
+            self.dropped.fetch_add(1, Ordering::Relaxed);
+            let source_full = depth >= self.config.per_source_capacity;
+            if self.config.drop_policy == DropPolicy::DropNewest || !Self::evict_oldest(&mut queues, source, source_full) {
+                return;
+            }
+        }
+
+        let queue = queues.by_source.entry(source).or_default();
+        let was_empty = queue.is_empty();
+        queue.push_back(data);
+        if was_empty {
+            queues.rotation.push_back(source);
+        }
+        queues.queued += 1;
+        drop(queues);
+        self.ready.notify_one();
+    }
+
+    /// Removes the oldest datagram of `source` when its own queue is full, otherwise of the longest
+    /// queue. Returns `false` when there was nothing to evict, such as for a peer without a queue
+    /// when `per_source_capacity` is zero.
+    fn evict_oldest(queues: &mut Queues, source: SocketAddr, source_full: bool) -> bool {
+        let victim = if source_full {
+            source
+        } else {
+            match queues.by_source.iter().max_by_key(|(_, q)| q.len()) {
+                Some((addr, _)) => *addr,
+                None => return false,
+            }
+        };
+        let Some(queue) = queues.by_source.get_mut(&victim) else { return false };
+        queue.pop_front();
+        if queue.is_empty() {
+            queues.by_source.remove(&victim);
+            queues.rotation.retain(|addr| *addr != victim);
+        }
+        queues.queued -= 1;
+        true
+    }
+
+    /// The function `pop` waits for the next datagram in round-robin order, or returns `None` once
+    /// the scheduler is closed.
+    pub(crate) async fn pop(&self) -> Option<(SocketAddr, Vec<u8>)> {
+        loop {
+            if self.closed.load(Ordering::Acquire) {
+                return None;
+            }
+            let notified = self.ready.notified();
+            if let Some(item) = self.try_pop() {
+                return Some(item);
+            }
+            notified.await;
+        }
+    }
+
+    fn try_pop(&self) -> Option<(SocketAddr, Vec<u8>)> {
+        let mut queues = self.queues.lock().unwrap();
+        let source = queues.rotation.pop_front()?;
+        let queue = queues.by_source.get_mut(&source).unwrap();
+        let data = queue.pop_front().unwrap();
+        if queue.is_empty() {
+            queues.by_source.remove(&source);
+        } else {
+            queues.rotation.push_back(source);
+        }
+        queues.queued -= 1;
+        let more = queues.queued > 0;
+        drop(queues);
+
+        self.processed.fetch_add(1, Ordering::Relaxed);
+        if more {
+            self.ready.notify_one();
+        }
+        Some((source, data))
+    }
+
+    /// The function `close` wakes every worker and makes `pop` return `None`.
+    pub(crate) fn close(&self) {
+        self.closed.store(true, Ordering::Release);
+        self.ready.notify_waiters();
+    }
+
+    pub(crate) fn stats(&self) -> SchedulerStats {
+        let queues = self.queues.lock().unwrap();
+        SchedulerStats {
+            queued: queues.queued,
+            sources: queues.by_source.len(),
+            max_source_depth: queues.by_source.values().map(VecDeque::len).max().unwrap_or(0),
+            processed: self.processed.load(Ordering::Relaxed),
+            dropped: self.dropped.load(Ordering::Relaxed),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_round_robin_and_drop_policy() {
+        let config = SchedulerConfig { per_source_capacity: 3, total_capacity: 10, ..Default::default() };
+        let scheduler = FairScheduler::new(config);
+        let (chatty, quiet): (SocketAddr, SocketAddr) = ("10.0.0.1:1".parse().unwrap(), "10.0.0.2:1".parse().unwrap());
+        for i in 0..5u8 {
+            scheduler.push(chatty, vec![i]);
+        }
+        scheduler.push(quiet, vec![9]);
+
+        let stats = scheduler.stats();
+        assert_eq!((stats.queued, stats.sources, stats.max_source_depth, stats.dropped), (4, 2, 3, 2));
+
+        let order: Vec<_> = [(); 4].iter().map(|_| scheduler.try_pop().unwrap()).collect();
+        assert_eq!(order[0], (chatty, vec![0]));
+        assert_eq!(order[1], (quiet, vec![9]));
+        assert_eq!(order[2], (chatty, vec![1]));
+
+        let scheduler = FairScheduler::new(SchedulerConfig {
+            per_source_capacity: 2,
+            drop_policy: DropPolicy::DropOldest,
+            ..Default::default()
+        });
+        for i in 0..3u8 {
+            scheduler.push(chatty, vec![i]);
+        }
+        assert_eq!(scheduler.try_pop(), Some((chatty, vec![1])));
+        scheduler.close();
+        assert_eq!(scheduler.pop().await, None);
+    }
+
+    #[test]
+    fn test_zero_capacity_drops_everything() {
+        let peer: SocketAddr = "10.0.0.1:1".parse().unwrap();
+        for (per_source_capacity, total_capacity) in [(0, 10), (3, 0)] {
+            let scheduler = FairScheduler::new(SchedulerConfig {
+                per_source_capacity,
+                total_capacity,
+                drop_policy: DropPolicy::DropOldest,
+                ..Default::default()
+            });
+            scheduler.push(peer, vec![1]);
+            scheduler.push(peer, vec![2]);
+            let stats = scheduler.stats();
+            assert_eq!((stats.queued, stats.sources, stats.dropped), (0, 0, 2));
+            assert_eq!(scheduler.try_pop(), None);
+        }
+    }
+}
@@ -0,0 +1,303 @@
+use super::scheduler::{FairScheduler, SchedulerConfig, SchedulerStats};
+#[cfg(any(target_os = "linux", target_os = "android"))]
+use super::ancillary::{self, UdpRecvInfo, UdpSendInfo};
+use crate::{error::AarambhNetError, server_handle::ServerHandle};
+use std::{error::Error, future::Future, io, net::SocketAddr, sync::Arc};
+use tokio::{net::UdpSocket, sync::Notify, task::JoinSet};
+
+/// The `UdpServer` struct in Rust contains a UDP socket and an Arc-wrapped notification mechanism.
+/// 
+/// # Properties:
+/// 
+/// * `socket`: The `socket` property in the `UdpServer` struct represents a UDP socket that the server
+///   uses to send and receive data over the network.
+/// * `notify`: The `notify` property in the `UdpServer` struct is of type `Arc<Notify>`. `Arc` stands
+///   for "atomic reference counting" and is a thread-safe reference-counting pointer. `Notify` is a
+///   synchronization primitive that allows threads to wait until a condition is satisfied.
+/// * `scheduler`: The per-peer queues feeding the workers of `run_with_handler`.
+/// * `buffer_size`: The largest datagram received whole; longer ones are truncated.
+pub struct UdpServer {
+    socket: UdpSocket,
+    notify: Arc<Notify>,
+    scheduler: FairScheduler,
+    buffer_size: usize,
+}
+
+/// The largest UDP payload, received whole by default.
+const MAX_DATAGRAM_SIZE: usize = 65535;
+
+impl UdpServer {
+    /// The function `bind` creates a UDP server bound to a specified address and returns a result with
+    /// the server instance or an error.
+    /// 
+    /// # Arguments:
+    /// 
+    /// * `addr`: The `addr` parameter in the `bind` function is a reference to a string that represents
+    ///   the address to bind the UDP socket to. This address typically includes the IP address and port
+    ///   number on which the socket will listen for incoming connections.
+    /// 
+    /// # Returns:
+    /// 
+    /// The `bind` function is returning a `Result` containing an instance of `UdpServer` if the binding
+    /// operation is successful. The `UdpServer` struct contains a `UdpSocket` and an `Arc<Notify>`
+    /// instance.
+    pub async fn bind(addr: &str) -> Result<Self, Box<dyn Error>> {
+        let socket = UdpSocket::bind(addr).await.map_err(|e| AarambhNetError::new("bind", e).with_target(addr))?;
+        let notify = Arc::new(Notify::new());
+        let scheduler = FairScheduler::new(SchedulerConfig::default());
+        Ok(UdpServer { socket, notify, scheduler, buffer_size: MAX_DATAGRAM_SIZE })
+    }
+
+    /// The function `with_scheduler` configures the worker pool used by `run_with_handler`: how many
+    /// datagrams are handled at once, how much is queued per peer and overall, and what is dropped
+    /// when the queues are full.
+    pub fn with_scheduler(mut self, config: SchedulerConfig) -> Self {
+        self.scheduler = FairScheduler::new(config);
+        self
+    }
+
+    /// The function `buffer_size` sets the largest datagram `run` and `run_with_handler` receive
+    /// whole, 65535 bytes by default so that no datagram is truncated. A smaller buffer saves memory
+    /// when the protocol bounds its datagrams.
+    pub fn buffer_size(mut self, size: usize) -> Self {
+        self.buffer_size = size.clamp(1, MAX_DATAGRAM_SIZE);
+        self
+    }
+
+    /// The function `local_addr` returns the address the server listens on, with the port the system
+    /// assigned when bound to port 0.
+    pub fn local_addr(&self) -> io::Result<SocketAddr> {
+        self.socket.local_addr()
+    }
+
+    /// The function `handler_stats` returns the queue depths and counters of the handler worker pool.
+    pub fn handler_stats(&self) -> SchedulerStats {
+        self.scheduler.stats()
+    }
+
+    /// The function `run` is an asynchronous method in Rust that continuously listens for incoming data
+    /// on a UDP socket, processes the data, and echoes it back to the sender while also checking for a
+    /// shutdown signal.
+    /// 
+    /// # Arguments:
+    /// 
+    /// * ``: The code you provided is a Rust asynchronous function that runs a UDP server using Tokio.
+    ///   Here's a breakdown of the key components:
+    /// 
+    /// # Returns:
+    /// 
+    /// The `run` function returns a `Result` with an `Ok(())` value if the UDP server is shut down
+    /// successfully.
+    pub async fn run(self: Arc<Self>) -> Result<(), Box<dyn Error>> {
+        let mut buf = vec![0; self.buffer_size]; // Buffer to store incoming data
+
+        loop {
+            tokio::select! {
+                // Wait for incoming data
+                Ok((len, addr)) = self.socket.recv_from(&mut buf) => {
+                    // Process the incoming data
+                    let received_message = String::from_utf8_lossy(&buf[..len]);
+                    println!("Received from {}: {}", addr, received_message);
+
+                    // Echo the message back to the sender
+                    if let Err(e) = self.socket.send_to(&buf[..len], addr).await {
+                        eprintln!("Failed to send data: {}", e);
+                    }
+                },
+                // Check for shutdown signal
+                _ = self.notify.notified() => {
+                    println!("Shutting down the UDP server...");
+                    return Ok(()); // Exit the loop if notified
+                }
+            }
+        }
+    }
+
+    /// The function `run_with_handler` receives datagrams and passes each one to `handler` on a
+    /// bounded pool of workers. Datagrams are queued per peer and served round-robin, so a peer
+    /// flooding the server cannot delay the others; see `with_scheduler` for the limits.
+    ///
+    /// # Arguments:
+    ///
+    /// * `handler`: The async function called with the peer address and the datagram. The bytes it
+    ///   returns, if any, are sent back to the peer.
+    ///
+    /// # Returns:
+    ///
+    /// The `run_with_handler` function returns `Ok(())` after `shutdown` was called and the workers
+    /// finished the datagram they were handling. Queued datagrams are discarded, and the server
+    /// cannot run a handler again afterwards.
+    pub async fn run_with_handler<H, F>(self: Arc<Self>, handler: H) -> Result<(), Box<dyn Error>>
+    where
+        H: Fn(SocketAddr, Vec<u8>) -> F + Send + Sync + 'static,
+        F: Future<Output = Option<Vec<u8>>> + Send + 'static,
+    {
+        let handler = Arc::new(handler);
+        let mut workers = JoinSet::new();
+        for _ in 0..self.scheduler.config().workers.max(1) {
+            let (server, handler) = (self.clone(), handler.clone());
+            workers.spawn(async move {
+                while let Some((peer, data)) = server.scheduler.pop().await {
+                    if let Some(reply) = handler(peer, data).await {
+                        if let Err(e) = server.socket.send_to(&reply, peer).await {
+                            tracing::warn!(%peer, error = %e, "failed to send the reply");
+                        }
+                    }
+                }
+            });
+        }
+
+        let mut buf = vec![0; self.buffer_size];
+        loop {
+            tokio::select! {
+                Ok((len, addr)) = self.socket.recv_from(&mut buf) => {
+                    self.scheduler.push(addr, buf[..len].to_vec());
+                },
+                _ = self.notify.notified() => break,
+            }
+        }
+
+        self.scheduler.close();
+        while workers.join_next().await.is_some() {}
+        Ok(())
+    }
+
+    /// The function `spawn` runs the server with `run` on a task of its own, echoing datagrams back.
+    ///
+    /// # Returns:
+    ///
+    /// The `spawn` function returns a `ServerHandle` to shut the server down and await it, or an
+    /// error if the address of the socket cannot be read.
+    pub fn spawn(self) -> io::Result<ServerHandle<()>> {
+        let (addr, notify) = (self.local_addr()?, self.notify.clone());
+        let server = Arc::new(self);
+        let run = async move { server.run().await.map_err(|e| e.to_string()) };
+        Ok(ServerHandle::spawn(addr, move || notify.notify_one(), run))
+    }
+
+    /// The function `spawn_with_handler` runs the server with `run_with_handler` on a task of its
+    /// own, passing each datagram to `handler`.
+    ///
+    /// # Returns:
+    ///
+    /// The `spawn_with_handler` function returns a `ServerHandle` to shut the server down and await
+    /// it, or an error if the address of the socket cannot be read.
+    pub fn spawn_with_handler<H, F>(self, handler: H) -> io::Result<ServerHandle<()>>
+    where
+        H: Fn(SocketAddr, Vec<u8>) -> F + Send + Sync + 'static,
+        F: Future<Output = Option<Vec<u8>>> + Send + 'static,
+    {
+        let (addr, notify) = (self.local_addr()?, self.notify.clone());
+        let server = Arc::new(self);
+        let run = async move { server.run_with_handler(handler).await.map_err(|e| e.to_string()) };
+        Ok(ServerHandle::spawn(addr, move || notify.notify_one(), run))
+    }
+
+    /// The function `enable_ancillary_data` asks the kernel to report, with every datagram read by
+    /// `recv_with_info`, the local address it was sent to, the interface it arrived on, its TTL or hop
+    /// limit and its IPv6 flow label. It also allows `send_with_info` to set an IPv6 flow label.
+    #[cfg(any(target_os = "linux", target_os = "android"))]
+    pub fn enable_ancillary_data(&self) -> Result<(), Box<dyn Error>> {
+        ancillary::enable(&self.socket).map_err(|e| AarambhNetError::new("enable ancillary data", e))?;
+        Ok(())
+    }
+
+    /// The function `recv_with_info` receives one datagram into `buf` together with the ancillary
+    /// data enabled by `enable_ancillary_data`. It must not be mixed with `run` or `run_with_handler`,
+    /// which read from the same socket.
+    ///
+    /// # Returns:
+    ///
+    /// The `recv_with_info` function returns a `Result` containing the length of the datagram and a
+    /// `UdpRecvInfo` describing how it arrived.
+    #[cfg(any(target_os = "linux", target_os = "android"))]
+    pub async fn recv_with_info(&self, buf: &mut [u8]) -> Result<(usize, UdpRecvInfo), Box<dyn Error>> {
+        let received = ancillary::recv(&self.socket, buf).await.map_err(|e| AarambhNetError::new("receive", e))?;
+        Ok(received)
+    }
+
+    /// The function `send_with_info` sends `data` to `target` with the source address, interface, hop
+    /// limit and flow label set in `info`. A server bound to a wildcard address answers from the
+    /// address the peer wrote to with `UdpSendInfo::reply_to`.
+    ///
+    /// # Returns:
+    ///
+    /// The `send_with_info` function returns a `Result` containing the number of bytes sent.
+    #[cfg(any(target_os = "linux", target_os = "android"))]
+    pub async fn send_with_info(&self, data: &[u8], target: SocketAddr, info: &UdpSendInfo) -> Result<usize, Box<dyn Error>> {
+        let sent = ancillary::send(&self.socket, data, target, info)
+            .await
+            .map_err(|e| AarambhNetError::new("send", e).with_target(target))?;
+        Ok(sent)
+    }
+
+    /// The `shutdown` function in Rust asynchronously notifies the server to shut down.
+    pub async fn shutdown(&self) {
+        self.notify.notify_one(); // Notify the server to shut down
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::error::Error;
+
+    #[tokio::test]
+    async fn test_udp_server() -> Result<(), Box<dyn Error>> {
+        let server = Arc::new(UdpServer::bind("127.0.0.1:0").await?);
+        let server_addr = server.local_addr()?;
+        let server_task = {
+            let server_clone = Arc::clone(&server);
+            tokio::spawn(async move {
+                server_clone.run().await.unwrap();
+            })
+        };
+
+        let client_socket = UdpSocket::bind("127.0.0.1:0").await?;
+        let message = b"Hello, UDP server!";
+
+        client_socket.send_to(message, server_addr).await?;
+
+        let mut buf = [0; 1024];
+        match client_socket.recv_from(&mut buf).await {
+            Ok((len, addr)) => {
+                let response = String::from_utf8_lossy(&buf[..len]);
+                assert_eq!(response, "Hello, UDP server!"); // Assert that the response matches the sent message
+                println!("Received from {}: {}", addr, response);
+            }
+            Err(e) => {
+                eprintln!("Failed to receive response: {}", e);
+            }
+        }
+
+        // Shutdown the server
+        server.shutdown().await;
+
+        // Wait for the server task to complete
+        let _ = server_task.await;
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_run_with_handler() -> Result<(), Box<dyn Error>> {
+        let server = Arc::new(UdpServer::bind("127.0.0.1:0").await?);
+        let server_addr = server.local_addr()?;
+        let handler = |_, data: Vec<u8>| async move { Some(data.to_ascii_uppercase()) };
+        let server_task = tokio::spawn({
+            let server = server.clone();
+            async move { server.run_with_handler(handler).await.unwrap() }
+        });
+
+        let client_socket = UdpSocket::bind("127.0.0.1:0").await?;
+        client_socket.send_to(b"ping", server_addr).await?;
+        let mut buf = [0; 16];
+        let (len, _) = client_socket.recv_from(&mut buf).await?;
+        assert_eq!(&buf[..len], b"PING");
+        assert_eq!(server.handler_stats().processed, 1);
+
+        server.shutdown().await;
+        server_task.await?;
+        Ok(())
+    }
+}